@@ -0,0 +1,2 @@
+-- migrations/20240707181522_add_collect_feedback_to_newsletter_issues.sql
+ALTER TABLE newsletter_issues ADD COLUMN collect_feedback BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,10 @@
+-- migrations/20240707182047_create_issue_feedback_table.sql
+CREATE TABLE issue_feedback (
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (newsletter_issue_id),
+    subscriber_id uuid NOT NULL,
+    useful BOOLEAN NOT NULL,
+    comment TEXT,
+    submitted_at timestamptz NOT NULL,
+    PRIMARY KEY(newsletter_issue_id, subscriber_id)
+);
@@ -0,0 +1,9 @@
+-- migrations/20240714093512_create_newsletter_issue_attachments_table.sql
+CREATE TABLE newsletter_issue_attachments (
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (newsletter_issue_id),
+    file_name TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    content BYTEA NOT NULL,
+    PRIMARY KEY(newsletter_issue_id, file_name)
+);
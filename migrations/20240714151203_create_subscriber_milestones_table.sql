@@ -0,0 +1,6 @@
+-- migrations/20240714151203_create_subscriber_milestones_table.sql
+CREATE TABLE subscriber_milestones (
+    milestone INT NOT NULL,
+    reached_at timestamptz NOT NULL,
+    PRIMARY KEY(milestone)
+);
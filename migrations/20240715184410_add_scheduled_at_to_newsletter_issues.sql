@@ -0,0 +1,2 @@
+-- migrations/20240715184410_add_scheduled_at_to_newsletter_issues.sql
+ALTER TABLE newsletter_issues ADD COLUMN scheduled_at timestamptz;
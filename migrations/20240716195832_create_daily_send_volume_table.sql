@@ -0,0 +1,5 @@
+-- migrations/20240716195832_create_daily_send_volume_table.sql
+CREATE TABLE daily_send_volume(
+    send_date DATE NOT NULL PRIMARY KEY,
+    num_sent INTEGER NOT NULL
+);
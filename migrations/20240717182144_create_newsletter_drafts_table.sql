@@ -0,0 +1,10 @@
+-- migrations/20240717182144_create_newsletter_drafts_table.sql
+CREATE TABLE newsletter_drafts(
+    draft_id uuid NOT NULL PRIMARY KEY,
+    title TEXT NOT NULL,
+    text_content TEXT NOT NULL,
+    html_content TEXT NOT NULL,
+    collect_feedback BOOLEAN NOT NULL,
+    scheduled_at timestamptz,
+    updated_at timestamptz NOT NULL
+);
@@ -0,0 +1,2 @@
+-- migrations/20240718201417_add_delivery_weight_to_newsletter_issues.sql
+ALTER TABLE newsletter_issues ADD COLUMN delivery_weight INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,2 @@
+-- migrations/20240719173052_add_markdown_content_to_newsletter_drafts.sql
+ALTER TABLE newsletter_drafts ADD COLUMN markdown_content TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,3 @@
+-- migrations/20240721190406_add_status_to_issue_delivery_queue.sql
+CREATE TYPE delivery_task_status AS ENUM ('pending', 'paused', 'cancelled');
+ALTER TABLE issue_delivery_queue ADD COLUMN status delivery_task_status NOT NULL DEFAULT 'pending';
@@ -0,0 +1,18 @@
+-- migrations/20240722174528_create_subscriber_events_table.sql
+-- no foreign keys: timeline is kept after subscribers unsubscribed and the delivery
+-- worker records events without locking newsletter_issues rows it updates in parallel
+CREATE TYPE subscriber_event_kind AS ENUM (
+    'subscribed',
+    'confirmed',
+    'received_issue',
+    'delivery_failed',
+    'unsubscribed'
+);
+CREATE TABLE subscriber_events(
+    event_id BIGSERIAL PRIMARY KEY,
+    subscriber_id uuid NOT NULL,
+    kind subscriber_event_kind NOT NULL,
+    newsletter_issue_id uuid,
+    occurred_at timestamptz NOT NULL
+);
+CREATE INDEX subscriber_events_subscriber_id_idx ON subscriber_events (subscriber_id);
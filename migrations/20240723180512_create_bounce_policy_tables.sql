@@ -0,0 +1,13 @@
+-- migrations/20240723180512_create_bounce_policy_tables.sql
+-- addresses, which must not be emailed anymore, e.g. after a hard bounce
+CREATE TABLE suppressions(
+    email TEXT PRIMARY KEY,
+    reason TEXT NOT NULL,
+    suppressed_at timestamptz NOT NULL
+);
+-- recipient domains, which blocked our emails; delivery to them waits until paused_until
+CREATE TABLE paused_domains(
+    domain TEXT PRIMARY KEY,
+    reason TEXT NOT NULL,
+    paused_until timestamptz NOT NULL
+);
@@ -0,0 +1,7 @@
+-- migrations/20240724172236_add_scan_result_to_newsletter_issue_attachments.sql
+CREATE TYPE attachment_scan_status AS ENUM ('not_scanned', 'clean', 'infected');
+ALTER TABLE newsletter_issue_attachments
+    ADD COLUMN scan_status attachment_scan_status NOT NULL DEFAULT 'not_scanned';
+-- name of detected malware
+ALTER TABLE newsletter_issue_attachments ADD COLUMN scan_signature TEXT;
+ALTER TABLE newsletter_issue_attachments ADD COLUMN scanned_at timestamptz;
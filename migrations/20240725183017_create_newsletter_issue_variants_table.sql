@@ -0,0 +1,15 @@
+-- migrations/20240725183017_create_newsletter_issue_variants_table.sql
+-- preferred language of subscriber; NULL receives the default content of issues
+ALTER TABLE subscriptions ADD COLUMN locale TEXT;
+-- content of an issue in another language; the issue itself is the default variant
+CREATE TABLE newsletter_issue_variants (
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (newsletter_issue_id),
+    locale TEXT NOT NULL,
+    title TEXT NOT NULL,
+    text_content TEXT NOT NULL,
+    html_content TEXT NOT NULL,
+    num_delivered_newsletters INTEGER NOT NULL DEFAULT 0,
+    num_failed_deliveries INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY(newsletter_issue_id, locale)
+);
@@ -0,0 +1,3 @@
+-- migrations/20240726190244_add_template_version_to_newsletter_issues.sql
+-- version of email templates used for the first sent email of an issue; NULL until delivery starts
+ALTER TABLE newsletter_issues ADD COLUMN template_version TEXT;
@@ -0,0 +1,6 @@
+-- migrations/20240728190000_add_exported_at_to_subscriber_events.sql
+-- events are marked after their export to Kafka or NATS is acknowledged; a column instead of
+-- a cursor on event_id, because events of concurrent transactions may commit out of id order
+ALTER TABLE subscriber_events ADD COLUMN exported_at timestamptz;
+CREATE INDEX subscriber_events_not_exported_idx ON subscriber_events (event_id)
+    WHERE exported_at IS NULL;
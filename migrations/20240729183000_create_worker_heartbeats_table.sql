@@ -0,0 +1,11 @@
+-- migrations/20240729183000_create_worker_heartbeats_table.sql
+-- each background worker loop writes a heartbeat; a worker is considered dead, if it
+-- has not been seen for stale_after_seconds
+CREATE TABLE worker_heartbeats(
+    worker_id TEXT NOT NULL PRIMARY KEY,
+    kind TEXT NOT NULL,
+    started_at timestamptz NOT NULL,
+    last_seen timestamptz NOT NULL,
+    tasks_processed BIGINT NOT NULL,
+    stale_after_seconds INTEGER NOT NULL
+);
@@ -0,0 +1,5 @@
+-- migrations/20240730174512_add_send_time_optimization.sql
+-- opens are tracked with an image in newsletter emails
+ALTER TYPE subscriber_event_kind ADD VALUE 'opened';
+ALTER TABLE newsletter_issues
+    ADD COLUMN optimize_send_time BOOLEAN NOT NULL DEFAULT false;
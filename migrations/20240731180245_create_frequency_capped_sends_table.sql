@@ -0,0 +1,9 @@
+-- migrations/20240731180245_create_frequency_capped_sends_table.sql
+-- sends, which were not enqueued, because the subscriber reached the frequency cap
+CREATE TABLE frequency_capped_sends (
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (newsletter_issue_id),
+    subscriber_id uuid NOT NULL,
+    skipped_at timestamptz NOT NULL,
+    PRIMARY KEY(newsletter_issue_id, subscriber_id)
+);
@@ -0,0 +1,18 @@
+-- migrations/20240801183512_create_lists_table.sql
+-- subscriptions and issues belong to a mailing list; existing ones to the default list
+CREATE TABLE lists (
+    list_id uuid PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    created_at timestamptz NOT NULL
+);
+INSERT INTO lists (list_id, name, created_at)
+VALUES ('00000000-0000-0000-0000-000000000000', 'Newsletter', now());
+ALTER TABLE subscriptions
+    ADD COLUMN list_id uuid NOT NULL DEFAULT '00000000-0000-0000-0000-000000000000'
+        REFERENCES lists (list_id);
+-- the same email may subscribe to several lists
+ALTER TABLE subscriptions DROP CONSTRAINT subscriptions_email_key;
+ALTER TABLE subscriptions ADD CONSTRAINT subscriptions_list_id_email_key UNIQUE (list_id, email);
+ALTER TABLE newsletter_issues
+    ADD COLUMN list_id uuid NOT NULL DEFAULT '00000000-0000-0000-0000-000000000000'
+        REFERENCES lists (list_id);
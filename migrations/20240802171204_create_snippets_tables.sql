@@ -0,0 +1,25 @@
+-- migrations/20240802171204_create_snippets_tables.sql
+-- reusable content blocks, which issues reference with `{{snippet:name}}`
+CREATE TABLE snippets (
+    name TEXT NOT NULL PRIMARY KEY,
+    created_at timestamptz NOT NULL
+);
+-- each change of a snippet is stored as new version
+CREATE TABLE snippet_versions (
+    name TEXT NOT NULL REFERENCES snippets (name),
+    version INTEGER NOT NULL,
+    text_content TEXT NOT NULL,
+    html_content TEXT NOT NULL,
+    created_at timestamptz NOT NULL,
+    PRIMARY KEY(name, version)
+);
+-- version of snippets used by an issue, pinned when the issue is rendered first
+CREATE TABLE newsletter_issue_snippets (
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (newsletter_issue_id),
+    name TEXT NOT NULL,
+    version INTEGER NOT NULL,
+    pinned_at timestamptz NOT NULL,
+    PRIMARY KEY(newsletter_issue_id, name),
+    FOREIGN KEY (name, version) REFERENCES snippet_versions (name, version)
+);
@@ -0,0 +1,6 @@
+-- migrations/20240803174830_create_newsletter_test_sends_table.sql
+-- content of sent test emails, which satisfies the `preview_sent` item of the checklist
+CREATE TABLE newsletter_test_sends (
+    content_hash TEXT NOT NULL PRIMARY KEY,
+    sent_at timestamptz NOT NULL
+);
@@ -0,0 +1,3 @@
+-- migrations/20240804091522_add_cancellable_until_to_newsletter_issues.sql
+-- end of the undo window, in which a published issue can be cancelled before delivery starts
+ALTER TABLE newsletter_issues ADD COLUMN cancellable_until timestamptz;
@@ -0,0 +1,4 @@
+-- migrations/20240805183204_add_expires_at_to_subscription_tokens.sql
+-- confirmation links of pending subscribers expire; existing tokens get a fresh lifetime
+ALTER TABLE subscription_tokens
+    ADD COLUMN expires_at timestamptz NOT NULL DEFAULT now() + interval '48 hours';
@@ -0,0 +1,3 @@
+-- migrations/20240806175310_add_max_emails_per_week_to_subscriptions.sql
+-- maximum number of newsletter emails per week, which a subscriber chose in the preference center
+ALTER TABLE subscriptions ADD COLUMN max_emails_per_week integer;
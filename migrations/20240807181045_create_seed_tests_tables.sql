@@ -0,0 +1,19 @@
+-- migrations/20240807181045_create_seed_tests_tables.sql
+-- sends of issue content to the configured seed mailboxes before the real send; an
+-- acknowledged seed test satisfies the `seed_test_acknowledged` item of the checklist
+CREATE TABLE seed_tests (
+    seed_test_id uuid PRIMARY KEY,
+    content_hash TEXT NOT NULL,
+    title TEXT NOT NULL,
+    sent_at timestamptz NOT NULL,
+    acknowledged_at timestamptz
+);
+CREATE INDEX seed_tests_content_hash_idx ON seed_tests (content_hash);
+CREATE TABLE seed_test_results (
+    seed_test_id uuid NOT NULL
+        REFERENCES seed_tests (seed_test_id) ON DELETE CASCADE,
+    email TEXT NOT NULL,
+    -- error of sending to seed mailbox; NULL if the email server accepted the email
+    error TEXT,
+    PRIMARY KEY(seed_test_id, email)
+);
@@ -0,0 +1,7 @@
+-- migrations/20240808190512_create_password_reset_tokens_table.sql
+-- single-use tokens of password reset links, which are emailed to admin users
+CREATE TABLE password_reset_tokens (
+    token TEXT PRIMARY KEY,
+    user_id uuid NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+    expires_at timestamptz NOT NULL
+);
@@ -0,0 +1,3 @@
+-- migrations/20240809172233_add_deactivated_at_to_users.sql
+-- deactivated users can neither log in nor reset their password; NULL if active
+ALTER TABLE users ADD COLUMN deactivated_at timestamptz;
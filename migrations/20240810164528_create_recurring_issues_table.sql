@@ -0,0 +1,16 @@
+-- migrations/20240810164528_create_recurring_issues_table.sql
+-- templates of recurring issues, e.g. a weekly roundup; a draft of the next issue is
+-- created one period before it is scheduled, unless the recurrence is paused
+CREATE TABLE recurring_issues (
+    recurring_issue_id uuid PRIMARY KEY,
+    name TEXT NOT NULL,
+    title TEXT NOT NULL,
+    text_content TEXT NOT NULL,
+    html_content TEXT NOT NULL,
+    markdown_content TEXT NOT NULL,
+    collect_feedback BOOLEAN NOT NULL,
+    period_days INT NOT NULL CHECK (period_days > 0),
+    -- scheduled time of the next issue, whose draft has not been created yet
+    next_scheduled_at timestamptz NOT NULL,
+    paused BOOLEAN NOT NULL DEFAULT false
+);
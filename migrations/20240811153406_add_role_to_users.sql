@@ -0,0 +1,4 @@
+-- migrations/20240811153406_add_role_to_users.sql
+-- admins may do everything, editors manage content but neither users nor subscriber data
+CREATE TYPE user_role AS ENUM ('admin', 'editor');
+ALTER TABLE users ADD COLUMN role user_role NOT NULL DEFAULT 'admin';
@@ -0,0 +1,9 @@
+-- migrations/20240812174915_create_failed_logins_table.sql
+-- consecutive failed logins per subject, i.e. "username:<username>" or "ip:<address>";
+-- logins of a subject are rejected until locked_until
+CREATE TABLE failed_logins (
+    subject TEXT PRIMARY KEY,
+    failures INT NOT NULL,
+    last_failed_at timestamptz NOT NULL,
+    locked_until timestamptz
+);
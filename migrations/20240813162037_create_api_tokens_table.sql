@@ -0,0 +1,11 @@
+-- migrations/20240813162037_create_api_tokens_table.sql
+-- tokens of CI jobs and external tools, which call the APIs as bearer token; only the
+-- SHA-256 hash of a token is stored, the token itself is shown once when it is minted
+CREATE TABLE api_tokens (
+    token_id uuid PRIMARY KEY,
+    name TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    created_at timestamptz NOT NULL,
+    last_used_at timestamptz,
+    revoked_at timestamptz
+);
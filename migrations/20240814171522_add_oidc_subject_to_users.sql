@@ -0,0 +1,4 @@
+-- migrations/20240814171522_add_oidc_subject_to_users.sql
+-- subject of the external identity of users logging in via OpenID Connect; linked on
+-- the first login via the verified email address of the identity
+ALTER TABLE users ADD COLUMN oidc_subject TEXT UNIQUE;
@@ -0,0 +1,13 @@
+-- migrations/20240815183044_create_passkeys_table.sql
+-- WebAuthn passkeys of admin users as alternative to passwords; the public key is
+-- SEC1 encoded and the signature counter detects cloned authenticators
+CREATE TABLE passkeys (
+    passkey_id uuid PRIMARY KEY,
+    user_id uuid NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    credential_id BYTEA NOT NULL UNIQUE,
+    public_key BYTEA NOT NULL,
+    sign_count BIGINT NOT NULL,
+    created_at timestamptz NOT NULL,
+    last_used_at timestamptz
+);
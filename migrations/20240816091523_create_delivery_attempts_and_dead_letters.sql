@@ -0,0 +1,21 @@
+-- migrations/20240816091523_create_delivery_attempts_and_dead_letters.sql
+-- delivery tasks in flight at a worker loop; an attempt, which is still in flight when
+-- its task is dequeued again, crashed its worker and counts as crash of the task
+CREATE TABLE delivery_attempts (
+    newsletter_issue_id uuid NOT NULL,
+    user_id uuid NOT NULL,
+    worker_id uuid NOT NULL,
+    in_flight BOOLEAN NOT NULL,
+    n_crashes SMALLINT NOT NULL,
+    panic_message TEXT,
+    PRIMARY KEY (newsletter_issue_id, user_id)
+);
+-- tasks, which crashed the worker repeatedly, are quarantined instead of being retried
+CREATE TABLE delivery_dead_letters (
+    newsletter_issue_id uuid NOT NULL REFERENCES newsletter_issues (newsletter_issue_id),
+    user_id uuid NOT NULL,
+    n_crashes SMALLINT NOT NULL,
+    panic_message TEXT,
+    quarantined_at timestamptz NOT NULL,
+    PRIMARY KEY (newsletter_issue_id, user_id)
+);
@@ -0,0 +1,12 @@
+-- migrations/20240817170512_create_admin_sessions_table.sql
+-- Sessions of logged in admin users with metadata of their login; a session, whose
+-- row is deleted, is logged out with its next request
+CREATE TABLE admin_sessions (
+    session_id uuid PRIMARY KEY,
+    user_id uuid NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+    user_agent TEXT,
+    ip TEXT,
+    created_at timestamptz NOT NULL,
+    last_seen_at timestamptz NOT NULL
+);
+CREATE INDEX admin_sessions_user_id_idx ON admin_sessions (user_id);
@@ -0,0 +1,10 @@
+-- migrations/20240818164207_create_user_preferences_table.sql
+-- notification settings of admin users: the channel and the events, which are sent;
+-- users without preferences are not notified
+CREATE TYPE notification_channel AS ENUM ('email', 'slack', 'none');
+CREATE TABLE user_preferences (
+    user_id uuid PRIMARY KEY REFERENCES users (user_id) ON DELETE CASCADE,
+    notification_channel notification_channel NOT NULL DEFAULT 'none',
+    slack_webhook_url TEXT,
+    notification_events TEXT[] NOT NULL DEFAULT '{}'
+);
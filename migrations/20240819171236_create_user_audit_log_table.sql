@@ -0,0 +1,13 @@
+-- migrations/20240819171236_create_user_audit_log_table.sql
+-- changes of admin users by the identity provider via SCIM provisioning; no foreign key,
+-- so that the log is kept, if users are removed
+CREATE TYPE user_audit_action AS ENUM ('provisioned', 'updated', 'deactivated', 'reactivated');
+CREATE TABLE user_audit_log (
+    audit_id BIGSERIAL PRIMARY KEY,
+    user_id uuid NOT NULL,
+    action user_audit_action NOT NULL,
+    actor TEXT NOT NULL,
+    details TEXT,
+    occurred_at timestamptz NOT NULL
+);
+CREATE INDEX user_audit_log_user_id_idx ON user_audit_log (user_id);
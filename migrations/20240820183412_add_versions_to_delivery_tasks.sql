@@ -0,0 +1,6 @@
+-- migrations/20240820183412_add_versions_to_delivery_tasks.sql
+-- format version of a task; workers only claim tasks up to the task version of their
+-- binary, so that old and new workers drain the queue concurrently during deploys
+ALTER TABLE issue_delivery_queue ADD COLUMN task_version SMALLINT NOT NULL DEFAULT 1;
+-- binary version of the worker, whose attempt claimed the task
+ALTER TABLE delivery_attempts ADD COLUMN worker_version TEXT;
@@ -0,0 +1,23 @@
+-- migrations/20240821190215_create_instance_settings_table.sql
+-- settings chosen in the initial setup, which override the configuration; the table
+-- holds at most one row
+CREATE TABLE instance_settings (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    base_url TEXT NOT NULL,
+    sender_email TEXT NOT NULL,
+    completed_at timestamptz NOT NULL
+);
+-- the seeded admin with its well known password is replaced by the initial setup,
+-- unless its password has been changed
+DELETE FROM idempotency
+WHERE user_id IN (
+    SELECT user_id
+    FROM users
+    WHERE
+        user_id = '018b7120-4509-4348-9850-a9cc62ba3ce2' AND
+        password_hash = '$argon2id$v=19$m=15000,t=2,p=1$5mCHVA68B8AaqP6rc/IGDA$3D4izGvFN/ZXBc8uAMqYFiE9TxcZ4AaSdoio4zXuKZQ'
+);
+DELETE FROM users
+WHERE
+    user_id = '018b7120-4509-4348-9850-a9cc62ba3ce2' AND
+    password_hash = '$argon2id$v=19$m=15000,t=2,p=1$5mCHVA68B8AaqP6rc/IGDA$3D4izGvFN/ZXBc8uAMqYFiE9TxcZ4AaSdoio4zXuKZQ';
@@ -0,0 +1,4 @@
+-- migrations/20240822174530_allow_anonymous_idempotency_keys.sql
+-- requests without logged-in user like subscriptions store their idempotency keys
+-- with the nil uuid as user_id, which does not reference a user
+ALTER TABLE idempotency DROP CONSTRAINT idempotency_user_id_fkey;
@@ -0,0 +1,4 @@
+-- migrations/20240823081245_add_request_hash_to_idempotency.sql
+-- hash of the request payload to detect reuse of keys with other payloads; keys
+-- stored before have none
+ALTER TABLE idempotency ADD COLUMN request_hash TEXT;
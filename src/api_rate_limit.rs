@@ -0,0 +1,194 @@
+//! src/api_rate_limit.rs
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue, RETRY_AFTER};
+use tokio::sync::watch;
+
+use crate::config_reload::TunableSettings;
+use crate::configuration::ApiRateLimitSettings;
+use crate::token_bucket::{BucketStatus, TokenBucket};
+
+/// Id of the API key of the application settings.
+pub const DEFAULT_API_KEY_ID: &str = "default";
+
+pub const X_RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("x-ratelimit-limit");
+pub const X_RATELIMIT_REMAINING: HeaderName = HeaderName::from_static("x-ratelimit-remaining");
+pub const X_RATELIMIT_RESET: HeaderName = HeaderName::from_static("x-ratelimit-reset");
+
+/// Rate limits of API calls with one token bucket per API key, so that a runaway
+/// integration can not degrade the web UI. Without settings no limit applies.
+/// Reloaded settings replace all buckets, i.e. each API key starts with a full quota.
+pub struct ApiRateLimiter {
+    state: Mutex<LimiterState>,
+}
+
+struct LimiterState {
+    tunables: watch::Receiver<TunableSettings>,
+    settings: Option<ApiRateLimitSettings>,
+    buckets: HashMap<String, Arc<TokenBucket>>,
+}
+
+impl ApiRateLimiter {
+    pub fn new(tunables: watch::Receiver<TunableSettings>) -> Self {
+        let settings = tunables.borrow().api_rate_limit.clone();
+        Self {
+            state: Mutex::new(LimiterState {
+                tunables,
+                settings,
+                buckets: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Count a request of the API key; returns `None`, if no limit is configured.
+    pub fn check(&self, key_id: &str) -> Option<RateLimitStatus> {
+        let mut state = self.state.lock().unwrap();
+        let LimiterState {
+            tunables,
+            settings,
+            buckets,
+        } = &mut *state;
+        // a closed channel keeps the current settings
+        if tunables.has_changed().unwrap_or(false) {
+            *settings = tunables.borrow_and_update().api_rate_limit.clone();
+            buckets.clear();
+        }
+        let settings = settings.as_ref()?;
+        let bucket = buckets
+            .entry(key_id.to_string())
+            .or_insert_with(|| {
+                let (max_requests, interval_seconds) = match settings.keys.get(key_id) {
+                    Some(key) => (key.max_requests, key.interval_seconds),
+                    None => (settings.max_requests, settings.interval_seconds),
+                };
+                Arc::new(TokenBucket::new(
+                    max_requests,
+                    Duration::from_secs(interval_seconds),
+                ))
+            })
+            .clone();
+        drop(state);
+        Some(RateLimitStatus(bucket.take_one()))
+    }
+}
+
+/// Quota of an API key after a request.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitStatus(BucketStatus);
+
+impl RateLimitStatus {
+    pub fn is_exceeded(&self) -> bool {
+        !self.0.taken
+    }
+
+    /// Add `X-RateLimit-*` headers and, if the limit is exceeded, `Retry-After`.
+    /// Reset is the number of seconds until the full quota is available again.
+    pub fn insert_headers(&self, headers: &mut HeaderMap) {
+        headers.insert(X_RATELIMIT_LIMIT, HeaderValue::from(self.0.capacity));
+        headers.insert(X_RATELIMIT_REMAINING, HeaderValue::from(self.0.remaining));
+        headers.insert(
+            X_RATELIMIT_RESET,
+            HeaderValue::from(ceil_secs(self.0.until_full)),
+        );
+        if self.is_exceeded() {
+            headers.insert(
+                RETRY_AFTER,
+                HeaderValue::from(ceil_secs(self.0.until_next_token)),
+            );
+        }
+    }
+}
+
+fn ceil_secs(duration: Duration) -> u64 {
+    duration.as_secs_f64().ceil() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::ApiKeyRateLimitSettings;
+
+    fn tunables(api_rate_limit: Option<ApiRateLimitSettings>) -> TunableSettings {
+        TunableSettings {
+            log_level: "info".to_string(),
+            api_rate_limit,
+            email_rate_limit: None,
+            n_retries: 3,
+            execute_retry_after_milliseconds: 1000,
+        }
+    }
+
+    fn limiter(api_rate_limit: Option<ApiRateLimitSettings>) -> ApiRateLimiter {
+        ApiRateLimiter::new(watch::channel(tunables(api_rate_limit)).1)
+    }
+
+    fn settings() -> ApiRateLimitSettings {
+        ApiRateLimitSettings {
+            max_requests: 2,
+            interval_seconds: 60,
+            keys: HashMap::from([(
+                "generous".to_string(),
+                ApiKeyRateLimitSettings {
+                    max_requests: 5,
+                    interval_seconds: 60,
+                },
+            )]),
+        }
+    }
+
+    #[test]
+    fn no_settings_apply_no_limit() {
+        let limiter = limiter(None);
+        assert!(limiter.check(DEFAULT_API_KEY_ID).is_none());
+    }
+
+    #[test]
+    fn each_key_has_own_configurable_quota() {
+        let limiter = limiter(Some(settings()));
+        for _ in 0..2 {
+            assert!(!limiter.check(DEFAULT_API_KEY_ID).unwrap().is_exceeded());
+        }
+        assert!(limiter.check(DEFAULT_API_KEY_ID).unwrap().is_exceeded());
+        for _ in 0..5 {
+            assert!(!limiter.check("generous").unwrap().is_exceeded());
+        }
+        assert!(limiter.check("generous").unwrap().is_exceeded());
+    }
+
+    #[test]
+    fn exceeded_limit_adds_retry_after_header() {
+        let limiter = limiter(Some(settings()));
+        let mut headers = HeaderMap::new();
+        limiter
+            .check(DEFAULT_API_KEY_ID)
+            .unwrap()
+            .insert_headers(&mut headers);
+        assert_eq!(headers.get(X_RATELIMIT_LIMIT).unwrap(), "2");
+        assert_eq!(headers.get(X_RATELIMIT_REMAINING).unwrap(), "1");
+        assert!(headers.get(RETRY_AFTER).is_none());
+        limiter.check(DEFAULT_API_KEY_ID);
+        limiter
+            .check(DEFAULT_API_KEY_ID)
+            .unwrap()
+            .insert_headers(&mut headers);
+        assert_eq!(headers.get(X_RATELIMIT_REMAINING).unwrap(), "0");
+        assert!(headers.get(RETRY_AFTER).is_some());
+    }
+
+    #[test]
+    fn reloaded_settings_replace_quotas() {
+        let (sender, receiver) = watch::channel(tunables(None));
+        let limiter = ApiRateLimiter::new(receiver);
+        assert!(limiter.check(DEFAULT_API_KEY_ID).is_none());
+        sender.send_replace(tunables(Some(settings())));
+        for _ in 0..2 {
+            assert!(!limiter.check(DEFAULT_API_KEY_ID).unwrap().is_exceeded());
+        }
+        assert!(limiter.check(DEFAULT_API_KEY_ID).unwrap().is_exceeded());
+        sender.send_replace(tunables(None));
+        assert!(limiter.check(DEFAULT_API_KEY_ID).is_none());
+    }
+}
@@ -0,0 +1,208 @@
+//! src/attachment_scan.rs
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use secrecy::ExposeSecret;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::configuration::{AttachmentScanSettings, AttachmentScannerSettings, DetectionAction};
+
+/// Size of chunks streamed to clamd.
+const CLAMAV_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Result of scanning an attachment as recorded with the attachment.
+#[derive(Debug, Clone, Copy, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "attachment_scan_status", rename_all = "snake_case")]
+pub enum AttachmentScanStatus {
+    NotScanned,
+    Clean,
+    Infected,
+}
+
+#[derive(Debug)]
+pub struct AttachmentScan {
+    pub status: AttachmentScanStatus,
+    /// Name of detected malware
+    pub signature: Option<String>,
+    pub scanned_at: Option<DateTime<Utc>>,
+}
+
+impl AttachmentScan {
+    fn not_scanned() -> Self {
+        Self {
+            status: AttachmentScanStatus::NotScanned,
+            signature: None,
+            scanned_at: None,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct ApiScanResponse {
+    infected: bool,
+    #[serde(default)]
+    signature: Option<String>,
+}
+
+/// Hook to scan attachments before they are stored or sent. Without settings
+/// attachments are not scanned.
+pub struct AttachmentScanner {
+    settings: Option<AttachmentScanSettings>,
+    http_client: Client,
+}
+
+impl AttachmentScanner {
+    pub fn new(settings: Option<AttachmentScanSettings>) -> Self {
+        let timeout = settings
+            .as_ref()
+            .map(|s| Duration::from_millis(s.timeout_milliseconds))
+            .unwrap_or(Duration::from_secs(10));
+        Self {
+            settings,
+            http_client: Client::builder().timeout(timeout).build().unwrap(),
+        }
+    }
+
+    /// Action on detected malware; None, if scanning is disabled.
+    pub fn on_detection(&self) -> Option<DetectionAction> {
+        self.settings.as_ref().map(|s| s.on_detection)
+    }
+
+    #[tracing::instrument(name = "Scan attachment", skip_all, fields(file_name = %file_name))]
+    pub async fn scan(
+        &self,
+        file_name: &str,
+        content: &[u8],
+    ) -> Result<AttachmentScan, anyhow::Error> {
+        let Some(ref settings) = self.settings else {
+            return Ok(AttachmentScan::not_scanned());
+        };
+        let signature = match settings.scanner {
+            AttachmentScannerSettings::ClamAv { ref address } => tokio::time::timeout(
+                Duration::from_millis(settings.timeout_milliseconds),
+                scan_with_clamav(address, content),
+            )
+            .await
+            .context("Scan of attachment by clamd timed out.")??,
+            AttachmentScannerSettings::Api { ref url, ref token } => {
+                let mut request = self
+                    .http_client
+                    .post(url)
+                    .header("Content-Type", "application/octet-stream")
+                    .body(content.to_vec());
+                if let Some(token) = token {
+                    request = request.bearer_auth(token.expose_secret());
+                }
+                let response: ApiScanResponse = request
+                    .send()
+                    .await
+                    .context("Failed to send attachment to scan API.")?
+                    .error_for_status()
+                    .context("Scan API returned an error.")?
+                    .json()
+                    .await
+                    .context("Failed to parse response of scan API.")?;
+                response
+                    .infected
+                    .then(|| response.signature.unwrap_or_else(|| "unknown".to_string()))
+            }
+        };
+        let status = match signature {
+            Some(ref signature) => {
+                tracing::warn!(signature, "Malware detected in attachment.");
+                AttachmentScanStatus::Infected
+            }
+            None => AttachmentScanStatus::Clean,
+        };
+        Ok(AttachmentScan {
+            status,
+            signature,
+            scanned_at: Some(Utc::now()),
+        })
+    }
+}
+
+/// Stream content to clamd with its INSTREAM command. Returns the signature of
+/// detected malware or None, if content is clean.
+async fn scan_with_clamav(address: &str, content: &[u8]) -> Result<Option<String>, anyhow::Error> {
+    let mut stream = TcpStream::connect(address)
+        .await
+        .with_context(|| format!("Failed to connect to clamd at {}.", address))?;
+    stream.write_all(b"zINSTREAM\0").await?;
+    for chunk in content.chunks(CLAMAV_CHUNK_SIZE) {
+        stream
+            .write_all(&(chunk.len() as u32).to_be_bytes())
+            .await?;
+        stream.write_all(chunk).await?;
+    }
+    stream.write_all(&0_u32.to_be_bytes()).await?;
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+    // response is "stream: OK" or "stream: <signature> FOUND"
+    let response = String::from_utf8_lossy(&response);
+    let response = response.trim_end_matches('\0').trim();
+    let result = response.strip_prefix("stream:").unwrap_or(response).trim();
+    if result == "OK" {
+        return Ok(None);
+    }
+    match result.strip_suffix(" FOUND") {
+        Some(signature) => Ok(Some(signature.to_string())),
+        None => Err(anyhow::anyhow!(
+            "Unexpected response of clamd: {}",
+            response
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::scan_with_clamav;
+    use claims::{assert_err, assert_none};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Fake clamd, which reads a stream and answers with `response`.
+    async fn fake_clamd(response: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut command = [0_u8; 10];
+            socket.read_exact(&mut command).await.unwrap();
+            assert_eq!(&command, b"zINSTREAM\0");
+            loop {
+                let length = socket.read_u32().await.unwrap() as usize;
+                if length == 0 {
+                    break;
+                }
+                let mut chunk = vec![0_u8; length];
+                socket.read_exact(&mut chunk).await.unwrap();
+            }
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+        address
+    }
+
+    #[tokio::test]
+    async fn clean_content_has_no_signature() {
+        let address = fake_clamd("stream: OK\0").await;
+        let signature = scan_with_clamav(&address, b"hello").await.unwrap();
+        assert_none!(signature);
+    }
+
+    #[tokio::test]
+    async fn infected_content_returns_signature() {
+        let address = fake_clamd("stream: Eicar-Test-Signature FOUND\0").await;
+        let signature = scan_with_clamav(&address, b"X5O!P%@AP").await.unwrap();
+        assert_eq!(signature.as_deref(), Some("Eicar-Test-Signature"));
+    }
+
+    #[tokio::test]
+    async fn unexpected_response_is_an_error() {
+        let address = fake_clamd("INSTREAM size limit exceeded. ERROR\0").await;
+        assert_err!(scan_with_clamav(&address, b"hello").await);
+    }
+}
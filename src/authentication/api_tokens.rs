@@ -0,0 +1,110 @@
+//! src/authentication/api_tokens.rs
+
+use chrono::{DateTime, Utc};
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Prefix of minted tokens, which makes them recognizable e.g. by secret scanners.
+const API_TOKEN_PREFIX: &str = "z2p_";
+
+/// API token of a CI job or an external tool; the token itself is not stored.
+pub struct ApiToken {
+    pub token_id: Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+fn hash_api_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Generate and store a new API token; returns its id and the token, which can not be
+/// retrieved again.
+#[tracing::instrument(name = "Mint API token", skip(pool))]
+pub async fn mint_api_token(
+    pool: &PgPool,
+    name: &str,
+) -> Result<(Uuid, Secret<String>), sqlx::Error> {
+    let mut rng = thread_rng();
+    let token: String = std::iter::repeat_with(|| rng.sample(Alphanumeric))
+        .map(char::from)
+        .take(40)
+        .collect();
+    let token = format!("{}{}", API_TOKEN_PREFIX, token);
+    let token_id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO api_tokens (token_id, name, token_hash, created_at)
+        VALUES ($1, $2, $3, now())
+        "#,
+        token_id,
+        name,
+        hash_api_token(&token),
+    )
+    .execute(pool)
+    .await?;
+    Ok((token_id, Secret::new(token)))
+}
+
+/// Id of an API token, which is not revoked. Marks the token as used.
+#[tracing::instrument(name = "Authenticate API token", skip_all)]
+pub async fn get_api_token_id(
+    pool: &PgPool,
+    token: &Secret<String>,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    if !token.expose_secret().starts_with(API_TOKEN_PREFIX) {
+        return Ok(None);
+    }
+    let row = sqlx::query!(
+        r#"
+        UPDATE api_tokens
+        SET last_used_at = now()
+        WHERE token_hash = $1 AND revoked_at IS NULL
+        RETURNING token_id
+        "#,
+        hash_api_token(token.expose_secret()),
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|r| r.token_id))
+}
+
+/// Revoke an API token; returns its name or `None`, if the token does not exist.
+#[tracing::instrument(name = "Revoke API token", skip(pool))]
+pub async fn revoke_api_token(
+    pool: &PgPool,
+    token_id: Uuid,
+) -> Result<Option<String>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        UPDATE api_tokens
+        SET revoked_at = COALESCE(revoked_at, now())
+        WHERE token_id = $1
+        RETURNING name
+        "#,
+        token_id,
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|r| r.name))
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn get_api_tokens(pool: &PgPool) -> Result<Vec<ApiToken>, sqlx::Error> {
+    sqlx::query_as!(
+        ApiToken,
+        r#"
+        SELECT token_id, name, created_at, last_used_at, revoked_at
+        FROM api_tokens
+        ORDER BY revoked_at IS NOT NULL, created_at DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,236 @@
+//! src/authentication/login_throttle.rs
+
+use actix_web::HttpRequest;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use std::net::{IpAddr, SocketAddr};
+
+/// Temporary lockout of usernames and IP addresses after consecutive failed logins.
+/// Each further failure doubles the lockout up to its maximum.
+#[derive(serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LoginThrottleSettings {
+    /// Failed logins of a username, after which it is locked out.
+    #[serde(default = "default_max_failures_per_username")]
+    pub max_failures_per_username: u32,
+    /// Failed logins from an IP address, after which it is locked out. Larger than per
+    /// username, because users behind one NAT share their address.
+    #[serde(default = "default_max_failures_per_ip")]
+    pub max_failures_per_ip: u32,
+    /// First lockout after reaching the maximum failures.
+    #[serde(default = "default_lockout_seconds")]
+    pub lockout_seconds: u32,
+    #[serde(default = "default_max_lockout_seconds")]
+    pub max_lockout_seconds: u32,
+    /// Failures are forgotten, if no further failure occurs within this time.
+    #[serde(default = "default_reset_after_seconds")]
+    pub reset_after_seconds: u32,
+    /// Source of the client address, whose failed logins are counted.
+    #[serde(default)]
+    pub client_ip: ClientIpSource,
+}
+
+/// Source of the IP address of the client of a login.
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClientIpSource {
+    /// Failed logins are only counted per username. Behind a load balancer all clients
+    /// share its address, so failures of anyone would lock out everybody.
+    #[default]
+    Disabled,
+    /// Address of the connection, if clients connect directly to the app.
+    PeerAddress,
+    /// Client address of the `Forwarded` or `X-Forwarded-For` header of a trusted reverse
+    /// proxy. Clients can set these headers themselves, so the app must only be
+    /// reachable via the proxy.
+    ForwardedHeader,
+}
+
+impl ClientIpSource {
+    pub fn client_ip(&self, request: &HttpRequest) -> Option<String> {
+        let ip = match self {
+            ClientIpSource::Disabled => return None,
+            ClientIpSource::PeerAddress => request.peer_addr()?.ip(),
+            ClientIpSource::ForwardedHeader => {
+                let connection_info = request.connection_info();
+                let address = connection_info.realip_remote_addr()?;
+                // without forwarded header, this is the peer address including its port
+                match address.parse::<SocketAddr>() {
+                    Ok(address) => address.ip(),
+                    Err(_) => address.parse::<IpAddr>().ok()?,
+                }
+            }
+        };
+        Some(ip.to_string())
+    }
+}
+
+impl Default for LoginThrottleSettings {
+    fn default() -> Self {
+        Self {
+            max_failures_per_username: default_max_failures_per_username(),
+            max_failures_per_ip: default_max_failures_per_ip(),
+            lockout_seconds: default_lockout_seconds(),
+            max_lockout_seconds: default_max_lockout_seconds(),
+            reset_after_seconds: default_reset_after_seconds(),
+            client_ip: ClientIpSource::default(),
+        }
+    }
+}
+
+fn default_max_failures_per_username() -> u32 {
+    5
+}
+
+fn default_max_failures_per_ip() -> u32 {
+    20
+}
+
+fn default_lockout_seconds() -> u32 {
+    30
+}
+
+fn default_max_lockout_seconds() -> u32 {
+    3600
+}
+
+fn default_reset_after_seconds() -> u32 {
+    86400
+}
+
+impl LoginThrottleSettings {
+    /// Lockout after the given number of consecutive failures; `None` below the maximum.
+    fn lockout(&self, failures: u32, max_failures: u32) -> Option<Duration> {
+        if failures < max_failures.max(1) {
+            return None;
+        }
+        let doublings = (failures - max_failures.max(1)).min(31);
+        let seconds = u64::from(self.lockout_seconds)
+            .saturating_mul(1 << doublings)
+            .min(u64::from(self.max_lockout_seconds));
+        Some(Duration::seconds(seconds as i64))
+    }
+}
+
+/// Subjects of a login attempt, whose failures are counted.
+pub struct LoginSubjects {
+    username: String,
+    ip: Option<String>,
+}
+
+impl LoginSubjects {
+    pub fn new(username: &str, ip: Option<&str>) -> Self {
+        Self {
+            username: format!("username:{}", username),
+            ip: ip.map(|ip| format!("ip:{}", ip)),
+        }
+    }
+
+    fn all(&self) -> Vec<String> {
+        std::iter::once(self.username.clone())
+            .chain(self.ip.clone())
+            .collect()
+    }
+}
+
+/// End of the lockout of the subjects, if any of them is locked out.
+#[tracing::instrument(name = "Check login lockout", skip_all)]
+pub async fn get_login_lockout(
+    pool: &PgPool,
+    subjects: &LoginSubjects,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT max(locked_until) AS locked_until
+        FROM failed_logins
+        WHERE subject = ANY($1) AND locked_until > now()
+        "#,
+        &subjects.all(),
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(row.locked_until)
+}
+
+/// Count a failed login of the subjects and lock them out after too many failures.
+#[tracing::instrument(name = "Record failed login", skip_all)]
+pub async fn record_failed_login(
+    pool: &PgPool,
+    settings: &LoginThrottleSettings,
+    subjects: &LoginSubjects,
+) -> Result<(), sqlx::Error> {
+    let mut limited_subjects = vec![(&subjects.username, settings.max_failures_per_username)];
+    if let Some(ip) = &subjects.ip {
+        limited_subjects.push((ip, settings.max_failures_per_ip));
+    }
+    for (subject, max_failures) in limited_subjects {
+        let failures = sqlx::query!(
+            r#"
+            INSERT INTO failed_logins (subject, failures, last_failed_at)
+            VALUES ($1, 1, now())
+            ON CONFLICT (subject) DO UPDATE
+            SET
+                failures = CASE
+                    WHEN failed_logins.last_failed_at < now() - make_interval(secs => $2)
+                    THEN 1
+                    ELSE failed_logins.failures + 1
+                END,
+                last_failed_at = now()
+            RETURNING failures
+            "#,
+            subject,
+            f64::from(settings.reset_after_seconds),
+        )
+        .fetch_one(pool)
+        .await?
+        .failures;
+        if let Some(lockout) = settings.lockout(failures.max(0) as u32, max_failures) {
+            tracing::warn!(subject, failures, "Locked out login after failed attempts.");
+            sqlx::query!(
+                "UPDATE failed_logins SET locked_until = $2 WHERE subject = $1",
+                subject,
+                Utc::now() + lockout,
+            )
+            .execute(pool)
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Forget failed logins of the username after a successful login. Failures of the IP
+/// address are kept, since one valid account must not unlock guessing others.
+#[tracing::instrument(name = "Reset failed logins", skip_all)]
+pub async fn reset_failed_logins(
+    pool: &PgPool,
+    subjects: &LoginSubjects,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "DELETE FROM failed_logins WHERE subject = $1",
+        subjects.username
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lockout_doubles_with_each_failure_up_to_maximum() {
+        let settings = LoginThrottleSettings {
+            max_failures_per_username: 3,
+            max_failures_per_ip: 10,
+            lockout_seconds: 30,
+            max_lockout_seconds: 100,
+            reset_after_seconds: 3600,
+            client_ip: ClientIpSource::Disabled,
+        };
+        assert_eq!(settings.lockout(2, 3), None);
+        assert_eq!(settings.lockout(3, 3), Some(Duration::seconds(30)));
+        assert_eq!(settings.lockout(4, 3), Some(Duration::seconds(60)));
+        assert_eq!(settings.lockout(5, 3), Some(Duration::seconds(100)));
+        assert_eq!(settings.lockout(500, 3), Some(Duration::seconds(100)));
+    }
+}
@@ -1,35 +1,309 @@
 //! src/authentication/middleware.rs
 
+use super::api_tokens::get_api_token_id;
+use super::sessions::touch_admin_session;
+use crate::api_rate_limit::{ApiRateLimiter, DEFAULT_API_KEY_ID};
 use crate::error::{Error, Z2PResult};
+use crate::policy::{is_allowed, Role, UserRole};
 use crate::session_state::{SessionError, TypedSession};
+use crate::startup::{ApiKey, ScimToken, WebhookSecret};
 use actix_web::{
     body::MessageBody,
     dev::{ServiceRequest, ServiceResponse},
-    FromRequest, HttpMessage,
+    http::header::{HeaderMap, AUTHORIZATION},
+    web, FromRequest, HttpMessage,
 };
 use actix_web_lab::middleware::Next;
 use anyhow::Context;
+use base64::Engine;
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
 use sqlx::PgPool;
 use std::ops::Deref;
+use subtle::ConstantTimeEq;
 use uuid::Uuid;
 
-pub async fn reject_anonymous_users(
+/// Admin pages require a logged in user, whose role allows the request.
+pub async fn reject_unauthorized_users(
     mut req: ServiceRequest,
     next: Next<impl MessageBody>,
 ) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let (user_id, role) = logged_in_user(&mut req).await?;
+    check_policy(&req, role.into())?;
+    req.extensions_mut().insert(user_id);
+    req.extensions_mut().insert(role);
+    next.call(req).await
+}
+
+/// The admin API accepts the session of a logged in user like admin pages or an API
+/// key or token as bearer token like the integration API. Only API key calls are rate limited.
+pub async fn reject_unauthorized_admin_api_calls(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    if req.headers().contains_key(AUTHORIZATION) {
+        let key_id = check_api_key(&req).await?;
+        check_policy(&req, Role::ApiClient)?;
+        req.extensions_mut().insert(ApiClientId(key_id.clone()));
+        // the GraphQL API checks its fields against the policy
+        req.extensions_mut().insert(Role::ApiClient);
+        call_with_rate_limit(req, next, &key_id).await
+    } else {
+        let (user_id, role) = logged_in_user(&mut req).await?;
+        check_policy(&req, role.into())?;
+        req.extensions_mut().insert(user_id);
+        req.extensions_mut().insert(role);
+        req.extensions_mut().insert(Role::from(role));
+        next.call(req).await
+    }
+}
+
+/// Rules are checked against the percent-decoded path, which the router matches routes
+/// with; `/admin/%75sers` must not pass a rule of `/admin/users`.
+fn check_policy(req: &ServiceRequest, role: Role) -> Result<(), Error> {
+    let path = req.match_info().as_str();
+    if is_allowed(role, req.method(), path) {
+        Ok(())
+    } else {
+        tracing::warn!(?role, path, "Rejected request by policy.");
+        Err(Error::Forbidden)
+    }
+}
+
+async fn logged_in_user(req: &mut ServiceRequest) -> Result<(UserId, UserRole), actix_web::Error> {
     let session = {
         let (http_request, payload) = req.parts_mut();
         TypedSession::from_request(http_request, payload).await
     }?;
 
-    match session.get_user_id()? {
-        Some(user_id) => {
-            req.extensions_mut().insert(UserId(user_id));
-            next.call(req).await
+    let user_id = match session.get_user_id()? {
+        Some(user_id) => user_id,
+        None => {
+            return Err(actix_web::Error::from(Error::from(
+                SessionError::UserNotLoggedIn,
+            )))
         }
-        None => Err(actix_web::Error::from(Error::from(
+    };
+    // sessions of deactivated users end with their next request
+    let pool = req
+        .app_data::<web::Data<PgPool>>()
+        .context("Database pool is not available as app data.")
+        .map_err(Error::from)?;
+    let Some(role) = get_role_of_active_user(pool, user_id)
+        .await
+        .context("Failed to read status of user.")
+        .map_err(Error::from)?
+    else {
+        session.log_out();
+        return Err(actix_web::Error::from(Error::from(
+            SessionError::UserNotLoggedIn,
+        )));
+    };
+    // revoked sessions end with their next request, too
+    let is_active = match session.get_session_id()? {
+        Some(session_id) => touch_admin_session(pool, user_id, session_id)
+            .await
+            .context("Failed to check admin session.")
+            .map_err(Error::from)?,
+        None => false,
+    };
+    if !is_active {
+        session.log_out();
+        return Err(actix_web::Error::from(Error::from(
             SessionError::UserNotLoggedIn,
-        ))),
+        )));
+    }
+    Ok((UserId(user_id), role))
+}
+
+/// Role of the user; `None`, if the user is deactivated.
+#[tracing::instrument(name = "Get role of active user", skip(pool))]
+async fn get_role_of_active_user(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<Option<UserRole>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT role AS "role: UserRole"
+        FROM users
+        WHERE user_id = $1 AND deactivated_at IS NULL
+        "#,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|r| r.role))
+}
+
+/// Webhooks of the email provider authenticate with basic auth.
+/// The username is not checked, the password must match the webhook secret.
+pub async fn reject_unauthorized_webhooks(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let webhook_secret = req
+        .app_data::<web::Data<WebhookSecret>>()
+        .context("Webhook secret is not available as app data.")
+        .map_err(Error::from)?;
+    match basic_authentication_password(req.headers()) {
+        Ok(password) if secrets_match(&password, &webhook_secret.0) => next.call(req).await,
+        Ok(_) => Err(Error::WebhookAuthError.into()),
+        Err(e) => {
+            tracing::warn!(error.message = %e, "Rejected webhook call.");
+            Err(Error::WebhookAuthError.into())
+        }
+    }
+}
+
+/// API clients authenticate with an API key or token as bearer token.
+pub async fn reject_invalid_api_keys(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let key_id = check_api_key(&req).await?;
+    check_policy(&req, Role::ApiClient)?;
+    req.extensions_mut().insert(ApiClientId(key_id.clone()));
+    call_with_rate_limit(req, next, &key_id).await
+}
+
+/// Id of the identity provider in the audit log of users changed via SCIM.
+const SCIM_CLIENT_ID: &str = "identity_provider";
+
+/// The identity provider authenticates SCIM calls with the configured SCIM token as
+/// bearer token. API keys and tokens for publishing must not provision admin users.
+pub async fn reject_invalid_scim_tokens(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let scim_token = req
+        .app_data::<web::Data<ScimToken>>()
+        .context("SCIM token is not available as app data.")
+        .map_err(Error::from)?;
+    let Some(scim_token) = &scim_token.0 else {
+        tracing::warn!("Rejected SCIM call, because no SCIM token is configured.");
+        return Err(Error::ApiAuthError.into());
+    };
+    match bearer_token(req.headers()) {
+        Ok(token) if secrets_match(&token, scim_token) => {
+            check_policy(&req, Role::ScimClient)?;
+            req.extensions_mut()
+                .insert(ApiClientId(SCIM_CLIENT_ID.to_string()));
+            next.call(req).await
+        }
+        Ok(_) => Err(Error::ApiAuthError.into()),
+        Err(e) => {
+            tracing::warn!(error.message = %e, "Rejected SCIM call.");
+            Err(Error::ApiAuthError.into())
+        }
+    }
+}
+
+/// Reject calls of API keys, which exceeded their rate limit, with 429. Responses
+/// report the quota of the API key in `X-RateLimit-*` headers.
+async fn call_with_rate_limit<B: MessageBody>(
+    req: ServiceRequest,
+    next: Next<B>,
+    key_id: &str,
+) -> Result<ServiceResponse<B>, actix_web::Error> {
+    let rate_limiter = req
+        .app_data::<web::Data<ApiRateLimiter>>()
+        .context("API rate limiter is not available as app data.")
+        .map_err(Error::from)?;
+    let Some(status) = rate_limiter.check(key_id) else {
+        return next.call(req).await;
+    };
+    if status.is_exceeded() {
+        tracing::warn!(key_id, "Rejected API call above rate limit.");
+        return Err(Error::ApiRateLimitExceeded(status).into());
+    }
+    let mut response = next.call(req).await?;
+    status.insert_headers(response.headers_mut());
+    Ok(response)
+}
+
+/// API clients authenticate with the configured API key or with a minted API token.
+/// Returns the id of the key or token, which is used for its rate limit.
+async fn check_api_key(req: &ServiceRequest) -> Result<String, Error> {
+    let api_key = req
+        .app_data::<web::Data<ApiKey>>()
+        .context("API key is not available as app data.")?;
+    let token = match bearer_token(req.headers()) {
+        Ok(token) => token,
+        Err(e) => {
+            tracing::warn!(error.message = %e, "Rejected API call.");
+            return Err(Error::ApiAuthError);
+        }
+    };
+    if secrets_match(&token, &api_key.0) {
+        return Ok(DEFAULT_API_KEY_ID.to_string());
+    }
+    let pool = req
+        .app_data::<web::Data<PgPool>>()
+        .context("Database pool is not available as app data.")?;
+    let token_id = get_api_token_id(pool, &token)
+        .await
+        .context("Failed to authenticate API token.")?
+        .ok_or(Error::ApiAuthError)?;
+    Ok(token_id.to_string())
+}
+
+/// Compare secrets in constant time, which does not reveal the length of a matching prefix.
+pub(crate) fn secrets_match(candidate: &Secret<String>, expected: &Secret<String>) -> bool {
+    candidate
+        .expose_secret()
+        .as_bytes()
+        .ct_eq(expected.expose_secret().as_bytes())
+        .into()
+}
+
+fn bearer_token(headers: &HeaderMap) -> Result<Secret<String>, anyhow::Error> {
+    let header_value = headers
+        .get(AUTHORIZATION)
+        .context("The 'Authorization' header was missing")?
+        .to_str()
+        .context("The 'Authorization' header was not a valid UTF8 string.")?;
+    let token = header_value
+        .strip_prefix("Bearer ")
+        .context("The authorization scheme was not 'Bearer'.")?;
+    Ok(Secret::new(token.to_string()))
+}
+
+fn basic_authentication_password(headers: &HeaderMap) -> Result<Secret<String>, anyhow::Error> {
+    // The header value, if present, must be a valid UTF8 string
+    let header_value = headers
+        .get(AUTHORIZATION)
+        .context("The 'Authorization' header was missing")?
+        .to_str()
+        .context("The 'Authorization' header was not a valid UTF8 string.")?;
+    let base64encoded_segment = header_value
+        .strip_prefix("Basic ")
+        .context("The authorization scheme was not 'Basic'.")?;
+    let decoded_bytes = base64::engine::general_purpose::STANDARD
+        .decode(base64encoded_segment)
+        .context("Failed to base64-decode 'Basic' credentials.")?;
+    let decoded_credentials = String::from_utf8(decoded_bytes)
+        .context("The decoded credential string is not valid UTF8.")?;
+    // Split into two segments, using ':' as delimiter
+    let (_username, password) = decoded_credentials
+        .split_once(':')
+        .context("A password must be provided in 'Basic' auth.")?;
+    Ok(Secret::new(password.to_string()))
+}
+
+/// Id of the API key or token of an API call, e.g. to log changes of the caller.
+#[derive(Debug, Clone)]
+pub struct ApiClientId(pub String);
+
+impl ApiClientId {
+    /// Owner of the idempotency keys of the caller. Tokens are identified by their id,
+    /// the configured API key by an id derived from its name.
+    pub fn idempotency_owner(&self) -> Uuid {
+        Uuid::parse_str(&self.0).unwrap_or_else(|_| {
+            let hash = Sha256::digest(self.0.as_bytes());
+            let mut bytes = [0; 16];
+            bytes.copy_from_slice(&hash[..16]);
+            Uuid::from_bytes(bytes)
+        })
     }
 }
 
@@ -67,4 +341,21 @@ impl UserId {
         let username = row.map(|r| r.username).ok_or(SessionError::UserNotFound)?;
         Ok(username)
     }
+
+    #[tracing::instrument(name = "Get email address from UserID", skip(pool))]
+    pub async fn get_email(&self, pool: &PgPool) -> Z2PResult<Option<String>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT email
+            FROM users
+            WHERE user_id = $1
+            "#,
+            self.0,
+        )
+        .fetch_optional(pool)
+        .await
+        .context("Failed to perform query to retrieve an email address.")?;
+        let email = row.map(|r| r.email).ok_or(SessionError::UserNotFound)?;
+        Ok(email)
+    }
 }
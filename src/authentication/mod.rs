@@ -1,9 +1,40 @@
 //! src/authentication/mod.rs
 
+mod api_tokens;
+mod login_throttle;
 mod middleware;
+mod oidc;
+mod passkeys;
 mod password;
+mod password_reset;
+mod sessions;
 
-pub use middleware::{reject_anonymous_users, UserId};
+pub use api_tokens::{get_api_tokens, mint_api_token, revoke_api_token, ApiToken};
+pub use login_throttle::{
+    get_login_lockout, record_failed_login, reset_failed_logins, ClientIpSource, LoginSubjects,
+    LoginThrottleSettings,
+};
+pub(crate) use middleware::secrets_match;
+pub use middleware::{
+    reject_invalid_api_keys, reject_invalid_scim_tokens, reject_unauthorized_admin_api_calls,
+    reject_unauthorized_users, reject_unauthorized_webhooks, ApiClientId, UserId,
+};
+pub use oidc::{get_user_of_oidc_identity, OidcClient, OidcIdentity, OidcLoginState};
+pub use passkeys::{
+    delete_passkey, get_passkey_credential_ids, get_passkey_of_credential, get_passkeys,
+    record_passkey_use, store_passkey, AuthenticationCredential, NewPasskey, Passkey,
+    PasskeyChallenge, PasskeyRelyingParty, RegistrationCredential, StoredPasskey,
+};
 pub use password::{
-    change_password_in_db, check_new_password, validate_credentials, Credentials, CredentialsError,
+    change_password_in_db, check_new_password, check_new_password_properties,
+    create_initial_admin_in_db, create_user_in_db, validate_credentials, Credentials,
+    CredentialsError,
+};
+pub use password_reset::{
+    delete_password_reset_tokens, get_user_id_of_reset_token, store_password_reset_token,
+    use_password_reset_token, PASSWORD_RESET_TOKEN_LIFETIME_MINUTES,
+};
+pub use sessions::{
+    get_admin_sessions, revoke_admin_session, revoke_all_admin_sessions, start_admin_session,
+    touch_admin_session, AdminSession,
 };
@@ -0,0 +1,369 @@
+//! src/authentication/oidc.rs
+
+use anyhow::{bail, Context};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+use reqwest::{Client, Url};
+use secrecy::ExposeSecret;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::configuration::OidcSettings;
+
+/// State of a login at the provider, which is kept in the session until the callback.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct OidcLoginState {
+    state: String,
+    nonce: String,
+    pkce_verifier: String,
+}
+
+impl OidcLoginState {
+    fn generate() -> Self {
+        Self {
+            state: random_string(32),
+            nonce: random_string(32),
+            pkce_verifier: random_string(64),
+        }
+    }
+
+    pub fn matches(&self, state: &str) -> bool {
+        self.state == state
+    }
+
+    fn pkce_challenge(&self) -> String {
+        URL_SAFE_NO_PAD.encode(Sha256::digest(self.pkce_verifier.as_bytes()))
+    }
+}
+
+fn random_string(len: usize) -> String {
+    let mut rng = thread_rng();
+    std::iter::repeat_with(|| rng.sample(Alphanumeric))
+        .map(char::from)
+        .take(len)
+        .collect()
+}
+
+/// External identity of a user, who logged in at the provider.
+#[derive(Debug)]
+pub struct OidcIdentity {
+    pub subject: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+}
+
+#[derive(serde::Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+}
+
+#[derive(serde::Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(serde::Deserialize)]
+struct IdTokenClaims {
+    iss: String,
+    sub: String,
+    aud: Audience,
+    #[serde(default)]
+    azp: Option<String>,
+    exp: i64,
+    #[serde(default)]
+    nonce: Option<String>,
+    #[serde(default)]
+    email: Option<String>,
+    #[serde(default)]
+    email_verified: bool,
+}
+
+/// Client of the authorization code flow with PKCE at an OpenID Connect provider.
+/// Without settings the OIDC login is disabled.
+pub struct OidcClient {
+    settings: Option<OidcSettings>,
+    redirect_url: String,
+    http_client: Client,
+}
+
+impl OidcClient {
+    pub fn new(settings: Option<OidcSettings>, base_url: &str) -> Self {
+        let timeout = settings
+            .as_ref()
+            .map(|s| Duration::from_millis(s.timeout_milliseconds))
+            .unwrap_or(Duration::from_secs(10));
+        Self {
+            settings,
+            redirect_url: format!("{}/login/oidc/callback", base_url),
+            http_client: Client::builder().timeout(timeout).build().unwrap(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.settings.is_some()
+    }
+
+    fn settings(&self) -> Result<&OidcSettings, anyhow::Error> {
+        self.settings.as_ref().context("OIDC login is disabled.")
+    }
+
+    async fn provider_metadata(&self) -> Result<ProviderMetadata, anyhow::Error> {
+        let settings = self.settings()?;
+        let issuer_url = settings.issuer_url.trim_end_matches('/');
+        let metadata: ProviderMetadata = self
+            .http_client
+            .get(format!("{}/.well-known/openid-configuration", issuer_url))
+            .send()
+            .await
+            .context("Failed to request OIDC provider metadata.")?
+            .error_for_status()
+            .context("OIDC provider returned an error for its metadata.")?
+            .json()
+            .await
+            .context("Failed to parse OIDC provider metadata.")?;
+        if metadata.issuer.trim_end_matches('/') != issuer_url {
+            bail!("Issuer of OIDC provider metadata does not match configured issuer.");
+        }
+        Ok(metadata)
+    }
+
+    /// URL of the authorization endpoint of the provider and the state of the login,
+    /// which must be kept until the callback.
+    #[tracing::instrument(name = "Start OIDC login", skip(self))]
+    pub async fn authorization_request(&self) -> Result<(Url, OidcLoginState), anyhow::Error> {
+        let settings = self.settings()?;
+        let metadata = self.provider_metadata().await?;
+        let login = OidcLoginState::generate();
+        let url = Url::parse_with_params(
+            &metadata.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", settings.client_id.as_str()),
+                ("redirect_uri", self.redirect_url.as_str()),
+                ("scope", "openid email"),
+                ("state", login.state.as_str()),
+                ("nonce", login.nonce.as_str()),
+                ("code_challenge", login.pkce_challenge().as_str()),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .context("Invalid authorization endpoint of OIDC provider.")?;
+        Ok((url, login))
+    }
+
+    /// Exchange the authorization code of the callback for the identity of the user.
+    #[tracing::instrument(name = "Exchange OIDC authorization code", skip_all)]
+    pub async fn exchange_code(
+        &self,
+        code: &str,
+        login: &OidcLoginState,
+    ) -> Result<OidcIdentity, anyhow::Error> {
+        let settings = self.settings()?;
+        let metadata = self.provider_metadata().await?;
+        let response: TokenResponse = self
+            .http_client
+            .post(&metadata.token_endpoint)
+            .basic_auth(
+                &settings.client_id,
+                Some(settings.client_secret.expose_secret()),
+            )
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", self.redirect_url.as_str()),
+                ("code_verifier", login.pkce_verifier.as_str()),
+            ])
+            .send()
+            .await
+            .context("Failed to request OIDC token.")?
+            .error_for_status()
+            .context("OIDC provider rejected authorization code.")?
+            .json()
+            .await
+            .context("Failed to parse OIDC token response.")?;
+        let claims = decode_id_token(&response.id_token)?;
+        verify_id_token_claims(&claims, &metadata.issuer, &settings.client_id, &login.nonce)?;
+        Ok(OidcIdentity {
+            subject: claims.sub,
+            email: claims.email,
+            email_verified: claims.email_verified,
+        })
+    }
+}
+
+/// Claims of the ID token. Its signature is not verified, because the token is received
+/// directly from the token endpoint via TLS (OpenID Connect Core 1.0, 3.1.3.7).
+fn decode_id_token(id_token: &str) -> Result<IdTokenClaims, anyhow::Error> {
+    let payload = id_token
+        .split('.')
+        .nth(1)
+        .context("ID token is not a JWT.")?;
+    let payload = URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .context("Failed to decode ID token.")?;
+    serde_json::from_slice(&payload).context("Failed to parse claims of ID token.")
+}
+
+fn verify_id_token_claims(
+    claims: &IdTokenClaims,
+    issuer: &str,
+    client_id: &str,
+    nonce: &str,
+) -> Result<(), anyhow::Error> {
+    if claims.iss != issuer {
+        bail!("ID token was issued by another issuer.");
+    }
+    let audience_matches = match claims.aud {
+        Audience::One(ref aud) => aud == client_id,
+        Audience::Many(ref aud) => {
+            aud.iter().any(|a| a == client_id)
+                && (aud.len() == 1 || claims.azp.as_deref() == Some(client_id))
+        }
+    };
+    if !audience_matches {
+        bail!("ID token was issued for another client.");
+    }
+    if claims.exp <= Utc::now().timestamp() {
+        bail!("ID token has expired.");
+    }
+    if claims.nonce.as_deref() != Some(nonce) {
+        bail!("Nonce of ID token does not match login.");
+    }
+    Ok(())
+}
+
+/// Active user of the identity. The first login links the identity to the only active
+/// user with its verified email address, which has no identity yet.
+#[tracing::instrument(name = "Get user of OIDC identity", skip(pool))]
+pub async fn get_user_of_oidc_identity(
+    pool: &PgPool,
+    identity: &OidcIdentity,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let user = sqlx::query!(
+        r#"
+        SELECT user_id
+        FROM users
+        WHERE oidc_subject = $1 AND deactivated_at IS NULL
+        "#,
+        identity.subject,
+    )
+    .fetch_optional(pool)
+    .await?;
+    if let Some(user) = user {
+        return Ok(Some(user.user_id));
+    }
+    let Some(email) = identity.email.as_ref().filter(|_| identity.email_verified) else {
+        return Ok(None);
+    };
+    let candidates = sqlx::query!(
+        r#"
+        SELECT user_id
+        FROM users
+        WHERE lower(email) = lower($1) AND oidc_subject IS NULL AND deactivated_at IS NULL
+        "#,
+        email,
+    )
+    .fetch_all(pool)
+    .await?;
+    let [candidate] = candidates.as_slice() else {
+        return Ok(None);
+    };
+    sqlx::query!(
+        "UPDATE users SET oidc_subject = $2 WHERE user_id = $1",
+        candidate.user_id,
+        identity.subject,
+    )
+    .execute(pool)
+    .await?;
+    tracing::info!(user_id = %candidate.user_id, "Linked OIDC identity to user.");
+    Ok(Some(candidate.user_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims(aud: Audience, exp: i64, nonce: &str) -> IdTokenClaims {
+        IdTokenClaims {
+            iss: "https://sso.example.com".to_string(),
+            sub: "subject".to_string(),
+            aud,
+            azp: None,
+            exp,
+            nonce: Some(nonce.to_string()),
+            email: None,
+            email_verified: false,
+        }
+    }
+
+    #[test]
+    fn id_token_claims_must_match_issuer_client_nonce_and_be_valid() {
+        let valid_until = Utc::now().timestamp() + 60;
+        let verify =
+            |c: &IdTokenClaims| verify_id_token_claims(c, "https://sso.example.com", "z2p", "n");
+        assert!(verify(&claims(Audience::One("z2p".into()), valid_until, "n")).is_ok());
+        assert!(verify(&claims(
+            Audience::Many(vec!["z2p".into()]),
+            valid_until,
+            "n"
+        ))
+        .is_ok());
+        assert!(verify(&claims(Audience::One("other".into()), valid_until, "n")).is_err());
+        assert!(verify(&claims(
+            Audience::Many(vec!["z2p".into(), "other".into()]),
+            valid_until,
+            "n"
+        ))
+        .is_err());
+        assert!(verify(&claims(
+            Audience::One("z2p".into()),
+            valid_until,
+            "replayed"
+        ))
+        .is_err());
+        assert!(verify(&claims(
+            Audience::One("z2p".into()),
+            Utc::now().timestamp() - 1,
+            "n"
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn id_token_payload_is_decoded() {
+        let payload = URL_SAFE_NO_PAD.encode(
+            r#"{"iss":"https://sso.example.com","sub":"s","aud":"z2p","exp":1,"email_verified":true}"#,
+        );
+        let claims = decode_id_token(&format!("header.{}.signature", payload)).unwrap();
+        assert_eq!(claims.sub, "s");
+        assert!(claims.email_verified);
+        assert!(decode_id_token("not a jwt").is_err());
+    }
+
+    #[test]
+    fn pkce_challenge_is_s256_of_verifier() {
+        // example of RFC 7636, appendix B
+        let login = OidcLoginState {
+            state: String::new(),
+            nonce: String::new(),
+            pkce_verifier: "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk".to_string(),
+        };
+        assert_eq!(
+            login.pkce_challenge(),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+}
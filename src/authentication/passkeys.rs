@@ -0,0 +1,510 @@
+//! src/authentication/passkeys.rs
+
+use anyhow::{bail, Context};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use ciborium::Value;
+use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use rand::{thread_rng, RngCore};
+use reqwest::Url;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Passkeys are created and used within this time.
+const CEREMONY_TIMEOUT_MILLISECONDS: u32 = 120_000;
+/// COSE algorithm identifier of ES256, the only supported signature algorithm.
+const COSE_ALG_ES256: i64 = -7;
+/// Flags of authenticator data: user present and attested credential data included.
+const FLAG_UP: u8 = 0x01;
+const FLAG_AT: u8 = 0x40;
+
+/// Type of the client data of a WebAuthn ceremony.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasskeyCeremony {
+    #[serde(rename = "webauthn.create")]
+    Registration,
+    #[serde(rename = "webauthn.get")]
+    Authentication,
+}
+
+/// Challenge of a pending ceremony, which is kept in the session until the response.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct PasskeyChallenge {
+    ceremony: PasskeyCeremony,
+    challenge: String,
+}
+
+impl PasskeyChallenge {
+    fn generate(ceremony: PasskeyCeremony) -> Self {
+        let mut challenge = [0u8; 32];
+        thread_rng().fill_bytes(&mut challenge);
+        Self {
+            ceremony,
+            challenge: URL_SAFE_NO_PAD.encode(challenge),
+        }
+    }
+}
+
+/// Response of `navigator.credentials.create()`; binary fields are base64url encoded.
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+pub struct RegistrationCredential {
+    pub client_data_json: String,
+    pub attestation_object: String,
+}
+
+/// Response of `navigator.credentials.get()`; binary fields are base64url encoded.
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+pub struct AuthenticationCredential {
+    pub credential_id: String,
+    pub client_data_json: String,
+    pub authenticator_data: String,
+    pub signature: String,
+}
+
+impl AuthenticationCredential {
+    pub fn credential_id(&self) -> Result<Vec<u8>, anyhow::Error> {
+        decode(&self.credential_id, "credential id")
+    }
+}
+
+/// Public key credential of an authenticator, which has been registered.
+#[derive(Debug)]
+pub struct NewPasskey {
+    pub credential_id: Vec<u8>,
+    /// SEC1 encoded P-256 public key
+    pub public_key: Vec<u8>,
+    pub sign_count: u32,
+}
+
+#[derive(serde::Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    ceremony: PasskeyCeremony,
+    challenge: String,
+    origin: String,
+}
+
+struct AuthenticatorData<'a> {
+    flags: u8,
+    sign_count: u32,
+    /// Attested credential data; only present in registrations.
+    attested: &'a [u8],
+}
+
+/// Relying party of WebAuthn ceremonies with passkeys. Its id and origin are derived from
+/// the base url of the application. Only ES256 credentials without attestation are
+/// supported, which all common platform authenticators and security keys provide.
+pub struct PasskeyRelyingParty {
+    rp_id: String,
+    origin: String,
+}
+
+impl PasskeyRelyingParty {
+    pub fn new(base_url: &str) -> Self {
+        let url = Url::parse(base_url).expect("Invalid base url of application.");
+        Self {
+            rp_id: url.host_str().unwrap_or_default().to_string(),
+            origin: url.origin().ascii_serialization(),
+        }
+    }
+
+    /// Options of `navigator.credentials.create()` to register a discoverable passkey of
+    /// the user; registered credentials are excluded.
+    pub fn registration_options(
+        &self,
+        user_id: Uuid,
+        username: &str,
+        registered: &[Vec<u8>],
+    ) -> (serde_json::Value, PasskeyChallenge) {
+        let challenge = PasskeyChallenge::generate(PasskeyCeremony::Registration);
+        let exclude_credentials: Vec<_> = registered
+            .iter()
+            .map(|id| serde_json::json!({"type": "public-key", "id": URL_SAFE_NO_PAD.encode(id)}))
+            .collect();
+        let options = serde_json::json!({
+            "challenge": challenge.challenge,
+            "rp": {"id": self.rp_id, "name": "zero2prod newsletter"},
+            "user": {
+                "id": URL_SAFE_NO_PAD.encode(user_id.as_bytes()),
+                "name": username,
+                "displayName": username,
+            },
+            "pubKeyCredParams": [{"type": "public-key", "alg": COSE_ALG_ES256}],
+            "timeout": CEREMONY_TIMEOUT_MILLISECONDS,
+            "attestation": "none",
+            "authenticatorSelection": {
+                "residentKey": "required",
+                "userVerification": "preferred",
+            },
+            "excludeCredentials": exclude_credentials,
+        });
+        (options, challenge)
+    }
+
+    /// Options of `navigator.credentials.get()` to log in with any discoverable passkey.
+    pub fn authentication_options(&self) -> (serde_json::Value, PasskeyChallenge) {
+        let challenge = PasskeyChallenge::generate(PasskeyCeremony::Authentication);
+        let options = serde_json::json!({
+            "challenge": challenge.challenge,
+            "rpId": self.rp_id,
+            "timeout": CEREMONY_TIMEOUT_MILLISECONDS,
+            "userVerification": "preferred",
+            "allowCredentials": [],
+        });
+        (options, challenge)
+    }
+
+    /// Verify the response of a registration and return the new credential.
+    pub fn finish_registration(
+        &self,
+        challenge: &PasskeyChallenge,
+        credential: &RegistrationCredential,
+    ) -> Result<NewPasskey, anyhow::Error> {
+        let client_data_json = decode(&credential.client_data_json, "client data")?;
+        self.verify_client_data(&client_data_json, challenge, PasskeyCeremony::Registration)?;
+        let attestation_object = decode(&credential.attestation_object, "attestation object")?;
+        let attestation: Value = ciborium::from_reader(attestation_object.as_slice())
+            .context("Failed to parse attestation object.")?;
+        // the attestation statement is not verified, because `none` is requested
+        let auth_data = cbor_map_get(&attestation, "authData")
+            .and_then(Value::as_bytes)
+            .context("Attestation object has no authenticator data.")?;
+        let auth_data = self.parse_authenticator_data(auth_data)?;
+        if auth_data.flags & FLAG_AT == 0 {
+            bail!("Authenticator data has no attested credential.");
+        }
+        let (credential_id, public_key) = parse_attested_credential(auth_data.attested)?;
+        Ok(NewPasskey {
+            credential_id,
+            public_key,
+            sign_count: auth_data.sign_count,
+        })
+    }
+
+    /// Verify the response of a login with the stored public key and signature counter
+    /// of the passkey and return the new signature counter.
+    pub fn finish_authentication(
+        &self,
+        challenge: &PasskeyChallenge,
+        credential: &AuthenticationCredential,
+        public_key: &[u8],
+        stored_sign_count: u32,
+    ) -> Result<u32, anyhow::Error> {
+        let client_data_json = decode(&credential.client_data_json, "client data")?;
+        self.verify_client_data(
+            &client_data_json,
+            challenge,
+            PasskeyCeremony::Authentication,
+        )?;
+        let authenticator_data = decode(&credential.authenticator_data, "authenticator data")?;
+        let auth_data = self.parse_authenticator_data(&authenticator_data)?;
+        let verifying_key =
+            VerifyingKey::from_sec1_bytes(public_key).context("Invalid stored public key.")?;
+        let signature = Signature::from_der(&decode(&credential.signature, "signature")?)
+            .context("Invalid signature encoding.")?;
+        let mut signed = authenticator_data.clone();
+        signed.extend_from_slice(&Sha256::digest(&client_data_json));
+        verifying_key
+            .verify(&signed, &signature)
+            .context("Invalid signature of passkey.")?;
+        verify_sign_count(stored_sign_count, auth_data.sign_count)?;
+        Ok(auth_data.sign_count)
+    }
+
+    fn verify_client_data(
+        &self,
+        client_data_json: &[u8],
+        challenge: &PasskeyChallenge,
+        ceremony: PasskeyCeremony,
+    ) -> Result<(), anyhow::Error> {
+        let client_data: ClientData =
+            serde_json::from_slice(client_data_json).context("Failed to parse client data.")?;
+        if challenge.ceremony != ceremony || client_data.ceremony != ceremony {
+            bail!("Client data belongs to another ceremony.");
+        }
+        if client_data.challenge != challenge.challenge {
+            bail!("Challenge of client data does not match.");
+        }
+        if client_data.origin != self.origin {
+            bail!("Origin of client data does not match.");
+        }
+        Ok(())
+    }
+
+    fn parse_authenticator_data<'a>(
+        &self,
+        data: &'a [u8],
+    ) -> Result<AuthenticatorData<'a>, anyhow::Error> {
+        if data.len() < 37 {
+            bail!("Authenticator data is too short.");
+        }
+        if data[..32] != *Sha256::digest(self.rp_id.as_bytes()) {
+            bail!("Authenticator data belongs to another relying party.");
+        }
+        let flags = data[32];
+        if flags & FLAG_UP == 0 {
+            bail!("User was not present at authenticator.");
+        }
+        Ok(AuthenticatorData {
+            flags,
+            sign_count: u32::from_be_bytes(data[33..37].try_into().unwrap()),
+            attested: &data[37..],
+        })
+    }
+}
+
+fn decode(value: &str, what: &str) -> Result<Vec<u8>, anyhow::Error> {
+    URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .with_context(|| format!("Failed to decode {}.", what))
+}
+
+fn cbor_map_get(map: &Value, key: impl Into<Value>) -> Option<&Value> {
+    let key = key.into();
+    map.as_map()?
+        .iter()
+        .find_map(|(k, v)| (*k == key).then_some(v))
+}
+
+/// Credential id and SEC1 encoded public key of attested credential data, which is the
+/// AAGUID, the length and the id of the credential and its public key as COSE key.
+fn parse_attested_credential(data: &[u8]) -> Result<(Vec<u8>, Vec<u8>), anyhow::Error> {
+    if data.len() < 18 {
+        bail!("Attested credential data is too short.");
+    }
+    let id_len = u16::from_be_bytes([data[16], data[17]]) as usize;
+    let credential_id = data
+        .get(18..18 + id_len)
+        .context("Attested credential data is too short.")?
+        .to_vec();
+    // extensions may follow the COSE key, they are ignored
+    let cose_key: Value = ciborium::from_reader(&data[18 + id_len..])
+        .context("Failed to parse public key of credential.")?;
+    let int = |label: i64| cbor_map_get(&cose_key, label).and_then(Value::as_integer);
+    let bytes = |label: i64| cbor_map_get(&cose_key, label).and_then(Value::as_bytes);
+    // kty EC2, alg ES256 and crv P-256
+    if int(1) != Some(2.into())
+        || int(3) != Some(COSE_ALG_ES256.into())
+        || int(-1) != Some(1.into())
+    {
+        bail!("Only ES256 passkeys are supported.");
+    }
+    let (Some(x), Some(y)) = (bytes(-2), bytes(-3)) else {
+        bail!("Public key of credential has no coordinates.");
+    };
+    let public_key = [&[0x04], x.as_slice(), y.as_slice()].concat();
+    VerifyingKey::from_sec1_bytes(&public_key).context("Invalid public key of credential.")?;
+    Ok((credential_id, public_key))
+}
+
+/// Authenticators with signature counter must increase it on each use; otherwise the
+/// passkey may have been cloned. Counters of 0 are not supported by the authenticator.
+fn verify_sign_count(stored: u32, received: u32) -> Result<(), anyhow::Error> {
+    if (stored != 0 || received != 0) && received <= stored {
+        bail!("Signature counter of passkey did not increase.");
+    }
+    Ok(())
+}
+
+/// Passkey of a user as shown on the passkeys page.
+pub struct Passkey {
+    pub passkey_id: Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// Passkey with its credential to verify a login.
+pub struct StoredPasskey {
+    pub passkey_id: Uuid,
+    pub user_id: Uuid,
+    pub public_key: Vec<u8>,
+    pub sign_count: u32,
+}
+
+#[tracing::instrument(name = "Store passkey", skip(pool, passkey))]
+pub async fn store_passkey(
+    pool: &PgPool,
+    user_id: Uuid,
+    name: &str,
+    passkey: &NewPasskey,
+) -> Result<Uuid, sqlx::Error> {
+    let passkey_id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO passkeys
+            (passkey_id, user_id, name, credential_id, public_key, sign_count, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, now())
+        "#,
+        passkey_id,
+        user_id,
+        name,
+        passkey.credential_id,
+        passkey.public_key,
+        passkey.sign_count as i64,
+    )
+    .execute(pool)
+    .await?;
+    Ok(passkey_id)
+}
+
+#[tracing::instrument(name = "Get passkeys of user", skip(pool))]
+pub async fn get_passkeys(pool: &PgPool, user_id: Uuid) -> Result<Vec<Passkey>, sqlx::Error> {
+    sqlx::query_as!(
+        Passkey,
+        r#"
+        SELECT passkey_id, name, created_at, last_used_at
+        FROM passkeys
+        WHERE user_id = $1
+        ORDER BY created_at
+        "#,
+        user_id,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+#[tracing::instrument(name = "Get credential ids of passkeys of user", skip(pool))]
+pub async fn get_passkey_credential_ids(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<Vec<Vec<u8>>, sqlx::Error> {
+    let rows = sqlx::query!(
+        "SELECT credential_id FROM passkeys WHERE user_id = $1",
+        user_id,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|r| r.credential_id).collect())
+}
+
+/// Passkey of the credential, if it belongs to an active user.
+#[tracing::instrument(name = "Get passkey of credential", skip_all)]
+pub async fn get_passkey_of_credential(
+    pool: &PgPool,
+    credential_id: &[u8],
+) -> Result<Option<StoredPasskey>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT p.passkey_id, p.user_id, p.public_key, p.sign_count
+        FROM passkeys p
+        JOIN users u ON u.user_id = p.user_id
+        WHERE p.credential_id = $1 AND u.deactivated_at IS NULL
+        "#,
+        credential_id,
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|r| StoredPasskey {
+        passkey_id: r.passkey_id,
+        user_id: r.user_id,
+        public_key: r.public_key,
+        sign_count: r.sign_count as u32,
+    }))
+}
+
+#[tracing::instrument(name = "Record use of passkey", skip(pool))]
+pub async fn record_passkey_use(
+    pool: &PgPool,
+    passkey_id: Uuid,
+    sign_count: u32,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE passkeys SET sign_count = $2, last_used_at = now() WHERE passkey_id = $1",
+        passkey_id,
+        sign_count as i64,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Delete a passkey of the user; returns its name, if it existed.
+#[tracing::instrument(name = "Delete passkey", skip(pool))]
+pub async fn delete_passkey(
+    pool: &PgPool,
+    user_id: Uuid,
+    passkey_id: Uuid,
+) -> Result<Option<String>, sqlx::Error> {
+    let row = sqlx::query!(
+        "DELETE FROM passkeys WHERE passkey_id = $1 AND user_id = $2 RETURNING name",
+        passkey_id,
+        user_id,
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|r| r.name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn authenticator_data(rp_id: &str, flags: u8, sign_count: u32) -> Vec<u8> {
+        let mut data = Sha256::digest(rp_id.as_bytes()).to_vec();
+        data.push(flags);
+        data.extend_from_slice(&sign_count.to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn authenticator_data_must_match_relying_party_and_user_presence() {
+        let rp = PasskeyRelyingParty::new("https://newsletter.example.com:443");
+        assert_eq!(rp.origin, "https://newsletter.example.com");
+        let data = authenticator_data("newsletter.example.com", FLAG_UP, 7);
+        assert_eq!(rp.parse_authenticator_data(&data).unwrap().sign_count, 7);
+        let data = authenticator_data("evil.example.com", FLAG_UP, 7);
+        assert!(rp.parse_authenticator_data(&data).is_err());
+        let data = authenticator_data("newsletter.example.com", 0, 7);
+        assert!(rp.parse_authenticator_data(&data).is_err());
+        assert!(rp.parse_authenticator_data(&data[..36]).is_err());
+    }
+
+    #[test]
+    fn sign_count_must_increase_unless_unsupported() {
+        assert!(verify_sign_count(0, 0).is_ok());
+        assert!(verify_sign_count(0, 1).is_ok());
+        assert!(verify_sign_count(5, 6).is_ok());
+        assert!(verify_sign_count(5, 5).is_err());
+        assert!(verify_sign_count(5, 0).is_err());
+    }
+
+    #[test]
+    fn client_data_must_match_challenge_ceremony_and_origin() {
+        let rp = PasskeyRelyingParty::new("http://127.0.0.1:8000");
+        let challenge = PasskeyChallenge::generate(PasskeyCeremony::Authentication);
+        let client_data = |ceremony: &str, challenge: &str, origin: &str| {
+            serde_json::json!({"type": ceremony, "challenge": challenge, "origin": origin})
+                .to_string()
+        };
+        let verify = |json: String| {
+            rp.verify_client_data(json.as_bytes(), &challenge, PasskeyCeremony::Authentication)
+        };
+        let expected = challenge.challenge.as_str();
+        assert!(verify(client_data(
+            "webauthn.get",
+            expected,
+            "http://127.0.0.1:8000"
+        ))
+        .is_ok());
+        assert!(verify(client_data(
+            "webauthn.create",
+            expected,
+            "http://127.0.0.1:8000"
+        ))
+        .is_err());
+        assert!(verify(client_data(
+            "webauthn.get",
+            "other",
+            "http://127.0.0.1:8000"
+        ))
+        .is_err());
+        assert!(verify(client_data(
+            "webauthn.get",
+            expected,
+            "http://evil.example.com"
+        ))
+        .is_err());
+    }
+}
@@ -1,6 +1,7 @@
 //! src/authentication/password.rs
 
 use crate::error::error_chain_fmt;
+use crate::policy::UserRole;
 use crate::routes::PasswordFormData;
 use crate::telemetry::spawn_blocking_with_tracing;
 use anyhow::Context;
@@ -9,7 +10,7 @@ use argon2::{
     PasswordVerifier, Version,
 };
 use secrecy::{ExposeSecret, Secret};
-use sqlx::PgPool;
+use sqlx::{PgPool, Postgres, Transaction};
 
 type CredsResult<T> = Result<T, CredentialsError>;
 
@@ -92,7 +93,7 @@ async fn get_stored_credentials(
         r#"
         SELECT user_id, password_hash
         FROM users
-        WHERE username = $1
+        WHERE username = $1 AND deactivated_at IS NULL
         "#,
         username,
     )
@@ -127,6 +128,74 @@ pub async fn change_password_in_db(
     Ok(())
 }
 
+/// Create a user with a random password, which is never revealed. The user sets a
+/// password with a password reset link. Returns `None`, if the username exists already.
+#[tracing::instrument(name = "Create user", skip(pool))]
+pub async fn create_user_in_db(
+    username: &str,
+    email: &str,
+    role: UserRole,
+    pool: &PgPool,
+) -> CredsResult<Option<uuid::Uuid>> {
+    let random_password = Secret::new(uuid::Uuid::new_v4().to_string());
+    let password_hash = spawn_blocking_with_tracing(move || compute_password_hash(random_password))
+        .await
+        .context("Failed to spawn computation of password hash")??;
+    let row = sqlx::query!(
+        r#"
+        INSERT INTO users (user_id, username, password_hash, email, role)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (username) DO NOTHING
+        RETURNING user_id
+        "#,
+        uuid::Uuid::new_v4(),
+        username,
+        password_hash.expose_secret(),
+        email,
+        role as UserRole,
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to create user in the database.")?;
+    Ok(row.map(|r| r.user_id))
+}
+
+/// Create the first admin user with the chosen password. Returns `None`, if any user
+/// exists already; the users table is locked, so that concurrent setups create at
+/// most one initial admin.
+#[tracing::instrument(name = "Create initial admin", skip(password, transaction))]
+pub async fn create_initial_admin_in_db(
+    username: &str,
+    email: &str,
+    password: Secret<String>,
+    transaction: &mut Transaction<'_, Postgres>,
+) -> CredsResult<Option<uuid::Uuid>> {
+    let password_hash = spawn_blocking_with_tracing(move || compute_password_hash(password))
+        .await
+        .context("Failed to spawn computation of password hash")??;
+    sqlx::query!("LOCK TABLE users IN SHARE ROW EXCLUSIVE MODE")
+        .execute(&mut **transaction)
+        .await
+        .context("Failed to lock users.")?;
+    let row = sqlx::query!(
+        r#"
+        INSERT INTO users (user_id, username, password_hash, email, role)
+        SELECT $1, $2, $3, $4, $5
+        WHERE NOT EXISTS (SELECT 1 FROM users)
+        RETURNING user_id
+        "#,
+        uuid::Uuid::new_v4(),
+        username,
+        password_hash.expose_secret(),
+        email,
+        UserRole::Admin as UserRole,
+    )
+    .fetch_optional(&mut **transaction)
+    .await
+    .context("Failed to create initial admin in the database.")?;
+    Ok(row.map(|r| r.user_id))
+}
+
 fn compute_password_hash(password: Secret<String>) -> CredsResult<Secret<String>> {
     let salt = SaltString::generate(&mut rand::thread_rng());
     let password_hash = Argon2::new(
@@ -155,11 +224,21 @@ pub async fn check_new_password(
     };
     // validate current password
     validate_credentials(credentials, pool).await?;
-    // check new password properties
-    if form.new_password.expose_secret().chars().count() < 13
-        || form.new_password.expose_secret().chars().count() > 128
-        || form
-            .new_password
+    check_new_password_properties(&form.new_password, &form.new_password_check)
+}
+
+/// Checks of a new password, which do not require the current password, e.g. when
+/// a forgotten password is reset.
+pub fn check_new_password_properties(
+    new_password: &Secret<String>,
+    new_password_check: &Secret<String>,
+) -> CredsResult<()> {
+    if new_password.expose_secret() != new_password_check.expose_secret() {
+        return Err(CredentialsError::DifferentNewPasswords);
+    }
+    if new_password.expose_secret().chars().count() < 13
+        || new_password.expose_secret().chars().count() > 128
+        || new_password
             .expose_secret()
             .chars()
             .any(|c| c.is_ascii_whitespace())
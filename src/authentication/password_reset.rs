@@ -0,0 +1,87 @@
+//! src/authentication/password_reset.rs
+
+use chrono::{Duration, Utc};
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Lifetime of a password reset link.
+pub const PASSWORD_RESET_TOKEN_LIFETIME_MINUTES: i64 = 60;
+
+/// Generate and store a password reset token of the user, which is valid for `lifetime`.
+#[tracing::instrument(name = "Store password reset token", skip(pool))]
+pub async fn store_password_reset_token(
+    pool: &PgPool,
+    user_id: Uuid,
+    lifetime: Duration,
+) -> Result<String, sqlx::Error> {
+    let mut rng = thread_rng();
+    let token: String = std::iter::repeat_with(|| rng.sample(Alphanumeric))
+        .map(char::from)
+        .take(32)
+        .collect();
+    sqlx::query!(
+        r#"
+        INSERT INTO password_reset_tokens (token, user_id, expires_at)
+        VALUES ($1, $2, $3)
+        "#,
+        token,
+        user_id,
+        Utc::now() + lifetime,
+    )
+    .execute(pool)
+    .await?;
+    Ok(token)
+}
+
+/// User of a password reset token, which is neither used nor expired.
+#[tracing::instrument(name = "Get user of password reset token", skip_all)]
+pub async fn get_user_id_of_reset_token(
+    pool: &PgPool,
+    token: &str,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT user_id
+        FROM password_reset_tokens
+        WHERE token = $1 AND expires_at > now()
+        "#,
+        token,
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|r| r.user_id))
+}
+
+/// Use a password reset token up; returns its user, if the token was neither used nor
+/// expired. Concurrent resets with the same token succeed only once.
+#[tracing::instrument(name = "Use password reset token", skip_all)]
+pub async fn use_password_reset_token(
+    pool: &PgPool,
+    token: &str,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        DELETE FROM password_reset_tokens
+        WHERE token = $1 AND expires_at > now()
+        RETURNING user_id
+        "#,
+        token,
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|r| r.user_id))
+}
+
+/// Delete all password reset tokens of the user, e.g. after the password was reset.
+#[tracing::instrument(name = "Delete password reset tokens", skip(pool))]
+pub async fn delete_password_reset_tokens(pool: &PgPool, user_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "DELETE FROM password_reset_tokens WHERE user_id = $1",
+        user_id
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,146 @@
+//! src/authentication/sessions.rs
+
+use crate::error::Z2PResult;
+use crate::session_state::TypedSession;
+use actix_web::{http::header::USER_AGENT, HttpRequest};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Redis keeps the state of a session for one day after its last request (default of
+/// actix-session); older sessions have expired.
+const ADMIN_SESSION_TTL_HOURS: i32 = 24;
+
+/// Session of a logged in admin user with metadata of its login.
+pub struct AdminSession {
+    pub session_id: Uuid,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+}
+
+/// Log in the user with a renewed session, which is stored to be listed and revoked.
+pub async fn start_admin_session(
+    pool: &PgPool,
+    session: &TypedSession,
+    user_id: Uuid,
+    request: &HttpRequest,
+) -> Z2PResult<()> {
+    let session_id = create_admin_session(pool, user_id, request)
+        .await
+        .context("Failed to create admin session.")?;
+    session.renew();
+    session.insert_user_id(user_id)?;
+    session.insert_session_id(session_id)?;
+    Ok(())
+}
+
+/// Store a new session of the user with user agent and IP address of the login request;
+/// expired sessions of the user are removed.
+#[tracing::instrument(name = "Create admin session", skip(pool, request))]
+async fn create_admin_session(
+    pool: &PgPool,
+    user_id: Uuid,
+    request: &HttpRequest,
+) -> Result<Uuid, sqlx::Error> {
+    let user_agent = request
+        .headers()
+        .get(USER_AGENT)
+        .and_then(|value| value.to_str().ok());
+    let ip = request.peer_addr().map(|addr| addr.ip().to_string());
+    sqlx::query!(
+        r#"
+        DELETE FROM admin_sessions
+        WHERE user_id = $1 AND last_seen_at < now() - make_interval(hours => $2)
+        "#,
+        user_id,
+        ADMIN_SESSION_TTL_HOURS,
+    )
+    .execute(pool)
+    .await?;
+    let session_id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO admin_sessions (session_id, user_id, user_agent, ip, created_at, last_seen_at)
+        VALUES ($1, $2, $3, $4, now(), now())
+        "#,
+        session_id,
+        user_id,
+        user_agent,
+        ip,
+    )
+    .execute(pool)
+    .await?;
+    Ok(session_id)
+}
+
+/// Check, that the session of the user has not been revoked, and mark it as seen.
+#[tracing::instrument(name = "Check admin session", skip(pool))]
+pub async fn touch_admin_session(
+    pool: &PgPool,
+    user_id: Uuid,
+    session_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        UPDATE admin_sessions
+        SET last_seen_at = now()
+        WHERE session_id = $1 AND user_id = $2
+        RETURNING session_id
+        "#,
+        session_id,
+        user_id,
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.is_some())
+}
+
+/// Revoke a session of the user; returns `false`, if the session does not exist.
+#[tracing::instrument(name = "Revoke admin session", skip(pool))]
+pub async fn revoke_admin_session(
+    pool: &PgPool,
+    user_id: Uuid,
+    session_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        "DELETE FROM admin_sessions WHERE session_id = $1 AND user_id = $2",
+        session_id,
+        user_id,
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Revoke all sessions of the user, i.e. log out everywhere.
+#[tracing::instrument(name = "Revoke all admin sessions", skip(pool))]
+pub async fn revoke_all_admin_sessions(pool: &PgPool, user_id: Uuid) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!("DELETE FROM admin_sessions WHERE user_id = $1", user_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Active sessions of the user, most recently seen first.
+#[tracing::instrument(skip(pool))]
+pub async fn get_admin_sessions(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<Vec<AdminSession>, sqlx::Error> {
+    sqlx::query_as!(
+        AdminSession,
+        r#"
+        SELECT session_id, user_agent, ip, created_at, last_seen_at
+        FROM admin_sessions
+        WHERE user_id = $1 AND last_seen_at >= now() - make_interval(hours => $2)
+        ORDER BY last_seen_at DESC
+        "#,
+        user_id,
+        ADMIN_SESSION_TTL_HOURS,
+    )
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,19 @@
+//! src/bin/smoketest.rs
+
+use zero2prod::smoketest::{get_smoke_test_settings, run_smoke_test};
+use zero2prod::telemetry::{get_subscriber, init_subscriber};
+
+/// End-to-end smoke test of a deployed instance, e.g. after a deployment to staging.
+/// Exits with 1, if any step fails.
+#[tokio::main]
+async fn main() {
+    let subscriber = get_subscriber("smoketest".into(), "info".into(), std::io::stdout, None);
+    init_subscriber(subscriber);
+
+    let settings = get_smoke_test_settings().expect("Failed to read SMOKETEST_* settings.");
+    if let Err(e) = run_smoke_test(&settings).await {
+        tracing::error!(error.cause_chain = ?e, error.message = %e, "Smoke test failed.");
+        std::process::exit(1);
+    }
+    tracing::info!("Smoke test passed.");
+}
@@ -0,0 +1,235 @@
+//! src/bounces.rs
+
+use chrono::{DateTime, TimeDelta, Utc};
+use sqlx::{PgExecutor, PgPool};
+use std::collections::{HashMap, HashSet};
+
+/// Delivery to a domain, which blocked our emails, is paused for this time.
+pub const DOMAIN_BLOCK_PAUSE: TimeDelta = TimeDelta::hours(1);
+
+/// Classification of bounces, which selects the policy applied to the recipient:
+/// hard bounces suppress the address, soft bounces are retried with extended backoff,
+/// blocks pause delivery to the recipient's domain and auto-replies are ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BounceKind {
+    Hard,
+    Soft,
+    Block,
+    AutoReply,
+}
+
+impl BounceKind {
+    /// Classify the bounce type of Postmark's bounce webhook and bounce API.
+    pub fn from_postmark_type(bounce_type: &str) -> Option<Self> {
+        match bounce_type {
+            "HardBounce" | "BadEmailAddress" | "ManuallyDeactivated" => Some(Self::Hard),
+            "SoftBounce" | "Transient" | "DnsError" => Some(Self::Soft),
+            "Blocked" | "ContentRelated" | "DMARCPolicy" => Some(Self::Block),
+            "AutoResponder" | "ChallengeVerification" => Some(Self::AutoReply),
+            _ => None,
+        }
+    }
+
+    /// Classify the error code of an email, which Postmark rejected while sending.
+    pub fn from_postmark_error_code(error_code: i64) -> Option<Self> {
+        match error_code {
+            // invalid recipient or inactive recipient, i.e. it bounced hard or complained before
+            300 | 406 => Some(Self::Hard),
+            // rate limit exceeded
+            429 => Some(Self::Soft),
+            _ => None,
+        }
+    }
+}
+
+/// Apply policy of a hard bounce or block to recipient. Soft bounces and auto-replies
+/// are handled by the retry of the delivery worker.
+#[tracing::instrument(name = "Apply bounce policy", skip(executor))]
+pub async fn apply_bounce_policy<'e>(
+    executor: impl PgExecutor<'e>,
+    email: &str,
+    kind: BounceKind,
+) -> Result<(), sqlx::Error> {
+    match kind {
+        BounceKind::Hard => {
+            sqlx::query!(
+                r#"
+                INSERT INTO suppressions (email, reason, suppressed_at)
+                VALUES ($1, 'hard_bounce', now())
+                ON CONFLICT (email) DO NOTHING
+                "#,
+                email,
+            )
+            .execute(executor)
+            .await?;
+        }
+        BounceKind::Block => {
+            sqlx::query!(
+                r#"
+                INSERT INTO paused_domains (domain, reason, paused_until)
+                VALUES ($1, 'block', $2)
+                ON CONFLICT (domain) DO UPDATE
+                SET paused_until = GREATEST(paused_domains.paused_until, EXCLUDED.paused_until)
+                "#,
+                email_domain(email),
+                Utc::now() + DOMAIN_BLOCK_PAUSE,
+            )
+            .execute(executor)
+            .await?;
+        }
+        BounceKind::Soft | BounceKind::AutoReply => {}
+    }
+    Ok(())
+}
+
+/// Lower case domain of email address.
+pub fn email_domain(email: &str) -> String {
+    email
+        .rsplit_once('@')
+        .map(|(_, domain)| domain)
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+/// Suppressed addresses of given emails.
+#[tracing::instrument(skip_all)]
+pub async fn get_suppressed_emails(
+    pool: &PgPool,
+    emails: &[String],
+) -> Result<HashSet<String>, sqlx::Error> {
+    let suppressed = sqlx::query!(
+        r#"
+        SELECT email
+        FROM suppressions
+        WHERE email = ANY($1)
+        "#,
+        emails,
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|r| r.email)
+    .collect();
+    Ok(suppressed)
+}
+
+/// Entry of the suppression list; no email is ever sent to a suppressed address.
+pub struct Suppression {
+    pub email: String,
+    /// `hard_bounce`, `spam_complaint` or `manual`
+    pub reason: String,
+    pub suppressed_at: DateTime<Utc>,
+}
+
+/// Check, if email is on the suppression list.
+#[tracing::instrument(skip(executor))]
+pub async fn is_suppressed<'e>(
+    executor: impl PgExecutor<'e>,
+    email: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!("SELECT email FROM suppressions WHERE email = $1", email)
+        .fetch_optional(executor)
+        .await?;
+    Ok(result.is_some())
+}
+
+/// Add email to suppression list. Returns false, if the email was suppressed already.
+#[tracing::instrument(skip(executor))]
+pub async fn suppress_email<'e>(
+    executor: impl PgExecutor<'e>,
+    email: &str,
+    reason: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO suppressions (email, reason, suppressed_at)
+        VALUES ($1, $2, now())
+        ON CONFLICT (email) DO NOTHING
+        "#,
+        email,
+        reason,
+    )
+    .execute(executor)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Remove email from suppression list. Returns false, if the email was not suppressed.
+#[tracing::instrument(skip(executor))]
+pub async fn remove_suppression<'e>(
+    executor: impl PgExecutor<'e>,
+    email: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!("DELETE FROM suppressions WHERE email = $1", email)
+        .execute(executor)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// All entries of the suppression list, latest first.
+#[tracing::instrument(skip_all)]
+pub async fn get_suppressions(pool: &PgPool) -> Result<Vec<Suppression>, sqlx::Error> {
+    sqlx::query_as!(
+        Suppression,
+        r#"
+        SELECT email, reason, suppressed_at
+        FROM suppressions
+        ORDER BY suppressed_at DESC, email
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Currently paused domains of given domains with end of their pause.
+#[tracing::instrument(skip_all)]
+pub async fn get_paused_domains(
+    pool: &PgPool,
+    domains: &[String],
+) -> Result<HashMap<String, DateTime<Utc>>, sqlx::Error> {
+    let paused = sqlx::query!(
+        r#"
+        SELECT domain, paused_until
+        FROM paused_domains
+        WHERE domain = ANY($1) AND paused_until > now()
+        "#,
+        domains,
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|r| (r.domain, r.paused_until))
+    .collect();
+    Ok(paused)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn postmark_bounce_types_are_classified() {
+        assert_eq!(
+            BounceKind::from_postmark_type("HardBounce"),
+            Some(BounceKind::Hard)
+        );
+        assert_eq!(
+            BounceKind::from_postmark_type("SoftBounce"),
+            Some(BounceKind::Soft)
+        );
+        assert_eq!(
+            BounceKind::from_postmark_type("Blocked"),
+            Some(BounceKind::Block)
+        );
+        assert_eq!(
+            BounceKind::from_postmark_type("AutoResponder"),
+            Some(BounceKind::AutoReply)
+        );
+        assert_eq!(BounceKind::from_postmark_type("SpamComplaint"), None);
+    }
+
+    #[test]
+    fn email_domain_is_lower_case() {
+        assert_eq!(email_domain("ursula@Example.COM"), "example.com");
+    }
+}
@@ -0,0 +1,106 @@
+//! src/config_reload.rs
+
+use std::time::Duration;
+use tokio::sync::watch;
+
+use crate::configuration::{get_configuration, ApiRateLimitSettings, RateLimitSettings, Settings};
+use crate::error::Z2PResult;
+use crate::telemetry::set_log_level;
+
+/// Settings, which are safe to change while the application runs. The API and the
+/// delivery worker receive changes via a `watch` channel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TunableSettings {
+    pub log_level: String,
+    pub api_rate_limit: Option<ApiRateLimitSettings>,
+    /// Send rate limit of the delivery worker.
+    pub email_rate_limit: Option<RateLimitSettings>,
+    pub n_retries: u8,
+    pub execute_retry_after_milliseconds: u64,
+}
+
+impl From<&Settings> for TunableSettings {
+    fn from(configuration: &Settings) -> Self {
+        Self {
+            log_level: configuration.application.log_level.clone(),
+            api_rate_limit: configuration.application.api_rate_limit.clone(),
+            email_rate_limit: configuration.emailclient.rate_limit.clone(),
+            n_retries: configuration.emailclient.n_retries,
+            execute_retry_after_milliseconds: configuration
+                .emailclient
+                .execute_retry_after_milliseconds,
+        }
+    }
+}
+
+impl TunableSettings {
+    pub fn retry_time_delta(&self) -> chrono::TimeDelta {
+        chrono::TimeDelta::milliseconds(self.execute_retry_after_milliseconds as i64)
+    }
+}
+
+/// Re-read the configuration every `config_reload_seconds` and publish changes of
+/// tunable settings. Changes of other settings are ignored until restart.
+pub async fn run_config_watcher_until_stopped(
+    configuration: Settings,
+    tunables: watch::Sender<TunableSettings>,
+) -> Z2PResult<()> {
+    let Some(reload_seconds) = configuration.application.config_reload_seconds else {
+        // reload is disabled; never finish, since main stops at the first finished task
+        return std::future::pending().await;
+    };
+    let mut interval = tokio::time::interval(Duration::from_secs(reload_seconds.max(1)));
+    // first tick completes immediately
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        match tokio::task::spawn_blocking(get_configuration).await {
+            Ok(Ok(reloaded)) => {
+                publish_tunable_settings(&tunables, TunableSettings::from(&reloaded));
+            }
+            Ok(Err(e)) => {
+                tracing::warn!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Failed to reload configuration; keeping current settings."
+                );
+            }
+            Err(e) => {
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Reload of configuration failed to complete."
+                );
+            }
+        }
+    }
+}
+
+/// Publish reloaded settings, if they differ from the current ones; returns if they changed.
+/// A new log level is applied right away, unless `RUST_LOG` sets the filter of log output.
+pub fn publish_tunable_settings(
+    tunables: &watch::Sender<TunableSettings>,
+    reloaded: TunableSettings,
+) -> bool {
+    tunables.send_if_modified(|current| {
+        if *current == reloaded {
+            return false;
+        }
+        if current.log_level != reloaded.log_level && std::env::var("RUST_LOG").is_err() {
+            if let Err(e) = set_log_level(&reloaded.log_level) {
+                tracing::warn!(
+                    error.cause_chain = ?e,
+                    log_level = reloaded.log_level,
+                    "Failed to apply reloaded log level."
+                );
+            }
+        }
+        tracing::info!(
+            previous = ?current,
+            reloaded = ?reloaded,
+            "Applied reloaded tunable settings."
+        );
+        *current = reloaded;
+        true
+    })
+}
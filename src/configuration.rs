@@ -1,12 +1,21 @@
 //! src/configuration.rs
 
-use crate::email_client::EmailClient;
+use crate::authentication::LoginThrottleSettings;
+use crate::email_client::{EmailClient, EmailClientMode, EmailProvider, HttpClientSettings};
+use crate::locale::LocaleFallbacks;
+use crate::routes::{ChecklistItem, EmailSizeBudget};
+use crate::secrets::resolve_secrets;
+use crate::subscriber_events::SubscriberEventKind;
+use crate::welcome_issue::WelcomeIssue;
+use actix_cors::Cors;
+use chrono::NaiveDate;
 use secrecy::{ExposeSecret, Secret};
 use serde_aux::field_attributes::deserialize_number_from_string;
 use sqlx::{
     postgres::{PgConnectOptions, PgSslMode},
     ConnectOptions,
 };
+use std::collections::HashMap;
 
 use crate::domain::{SubscriberEmail, ValidationError};
 
@@ -16,6 +25,27 @@ pub struct Settings {
     pub application: ApplicationSettings,
     pub emailclient: EmailClientSettings,
     pub redis_uri: Secret<String>,
+    /// Optional scanning of newsletter attachments for malware.
+    #[serde(default)]
+    pub attachment_scan: Option<AttachmentScanSettings>,
+    /// Backend of the delivery queue; Postgres if not configured.
+    #[serde(default)]
+    pub delivery_queue: DeliveryQueueSettings,
+    /// Store of idempotency keys; Postgres if not configured.
+    #[serde(default)]
+    pub idempotency_store: IdempotencyStoreSettings,
+    /// Optional export of subscriber events to Kafka or NATS.
+    #[serde(default)]
+    pub event_export: Option<EventExportSettings>,
+    /// Optional admin login via an OpenID Connect provider.
+    #[serde(default)]
+    pub oidc: Option<OidcSettings>,
+    /// Optional export of traces to an OpenTelemetry collector.
+    #[serde(default)]
+    pub otlp: Option<OtlpSettings>,
+    /// Optional backend of secret references `secret://<name>#<key>` in other values.
+    #[serde(default)]
+    pub secrets: Option<SecretsSettings>,
 }
 
 #[derive(serde::Deserialize, Clone)]
@@ -59,17 +89,440 @@ pub struct ApplicationSettings {
     pub host: String,
     pub base_url: String,
     pub hmac_secret: Secret<String>,
+    pub webhook_secret: Secret<String>,
+    pub api_key: Secret<String>,
+    /// Optional token of the identity provider, which provisions admin users via SCIM;
+    /// SCIM is disabled without it.
+    #[serde(default)]
+    pub scim_token: Option<Secret<String>>,
+    /// Optional token, which the initial setup requires; without it a random token is
+    /// generated and logged at startup.
+    #[serde(default)]
+    pub setup_token: Option<Secret<String>>,
     pub idempotency_lifetime_minutes: u32,
+    /// Interval of the cleanup worker, which deletes outlived idempotency keys.
+    #[serde(default = "default_idempotency_cleanup_interval_seconds")]
+    pub idempotency_cleanup_interval_seconds: u64,
+    /// Maximum number of idempotency keys the cleanup worker deletes per statement.
+    #[serde(default = "default_idempotency_cleanup_batch_size")]
+    pub idempotency_cleanup_batch_size: u32,
+    /// Target latency of sending a confirmation email to a new subscriber.
+    pub confirmation_latency_slo_milliseconds: u64,
+    /// Optional maximum number of newsletter emails a subscriber receives within 7 days.
+    #[serde(default)]
+    pub max_emails_per_subscriber_per_week: Option<u32>,
+    /// Items, which must be checked or satisfied automatically before an issue is published.
+    #[serde(default)]
+    pub publish_checklist: Vec<ChecklistItem>,
+    /// Addresses of seed mailboxes at different email providers, which receive seed tests.
+    #[serde(default)]
+    pub seed_addresses: Vec<String>,
+    /// Minutes after publishing, in which an issue can be cancelled before delivery starts.
+    #[serde(default)]
+    pub undo_window_minutes: u32,
+    /// Issue from the archive, which newly confirmed subscribers receive.
+    #[serde(default)]
+    pub welcome_issue: WelcomeIssue,
+    /// Size budget of rendered html emails, which is checked at publish time.
+    #[serde(default)]
+    pub email_size_budget: EmailSizeBudget,
+    /// Optional rate limit of API calls per API key; sessions of the web UI are not limited.
+    #[serde(default)]
+    pub api_rate_limit: Option<ApiRateLimitSettings>,
+    /// Temporary lockout of usernames and IP addresses after failed logins.
+    #[serde(default)]
+    pub login_throttle: LoginThrottleSettings,
+    /// Fallbacks of locales for selecting language variants of issues.
+    #[serde(default)]
+    pub locale_fallbacks: LocaleFallbacks,
+    /// Time in-flight requests get to finish after a shutdown signal.
+    #[serde(default = "default_shutdown_timeout_seconds")]
+    pub shutdown_timeout_seconds: u64,
+    /// Filter of log output, e.g. "info" or "zero2prod=debug,info", if `RUST_LOG` is not set.
+    #[serde(default = "default_log_level")]
+    pub log_level: String,
+    /// Optional interval to re-read the configuration and apply changes of tunable
+    /// settings without restart.
+    #[serde(default)]
+    pub config_reload_seconds: Option<u64>,
+    /// Optional cross-origin access of public routes, e.g. by sites embedding the subscribe form.
+    #[serde(default)]
+    pub cors: Option<CorsSettings>,
+}
+
+fn default_idempotency_cleanup_interval_seconds() -> u64 {
+    600
+}
+
+fn default_idempotency_cleanup_batch_size() -> u32 {
+    1000
+}
+
+fn default_shutdown_timeout_seconds() -> u64 {
+    30
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
 }
 
 #[derive(serde::Deserialize, Clone)]
 pub struct EmailClientSettings {
+    #[serde(default)]
+    pub mode: EmailClientMode,
+    pub provider: EmailProvider,
     pub base_url: String,
     pub sender_email: String,
     pub token: Secret<String>,
     pub timeout_milliseconds: u64,
+    pub connect_timeout_milliseconds: u64,
+    pub pool_idle_timeout_seconds: u64,
+    pub pool_max_idle_per_host: usize,
+    pub tcp_keepalive_seconds: u64,
     pub n_retries: u8,
     pub execute_retry_after_milliseconds: u64,
+    /// Crashes of the worker by a task, after which the task is quarantined.
+    #[serde(default = "default_max_task_crashes")]
+    pub max_task_crashes: u8,
+    /// Number of queued emails the delivery worker sends per request to the email server.
+    pub batch_size: u16,
+    /// Number of concurrent task loops of the delivery worker.
+    #[serde(default = "default_worker_concurrency")]
+    pub worker_concurrency: u16,
+    /// Consecutive failures of active provider before failing over to next provider.
+    pub failover_threshold: u32,
+    pub failover_reset_milliseconds: u64,
+    #[serde(default)]
+    pub fallbacks: Vec<FallbackEmailProviderSettings>,
+    /// Optional ramp-up of daily send volume, e.g. after moving to a new sending domain or IP.
+    #[serde(default)]
+    pub warm_up: Option<WarmUpSettings>,
+    /// Optional limit of send rate to stay below throttling limits of provider.
+    #[serde(default)]
+    pub rate_limit: Option<RateLimitSettings>,
+    /// Optional plan of provider, against which the monthly send volume is tracked.
+    #[serde(default)]
+    pub plan: Option<ProviderPlanSettings>,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct ProviderPlanSettings {
+    /// Number of emails per calendar month included in the plan.
+    pub monthly_email_limit: u32,
+    /// Price of 1000 emails to estimate the cost of issues.
+    pub cost_per_thousand_emails: f64,
+    #[serde(default = "default_currency")]
+    pub currency: String,
+}
+
+fn default_currency() -> String {
+    "USD".to_string()
+}
+
+#[derive(serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct RateLimitSettings {
+    /// Maximum number of emails per interval; also the maximum burst.
+    pub max_emails: u32,
+    pub interval_seconds: u64,
+}
+
+#[derive(serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct ApiRateLimitSettings {
+    /// Maximum number of requests per interval of each API key; also the maximum burst.
+    pub max_requests: u32,
+    pub interval_seconds: u64,
+    /// Limits of single API keys by key id, which override the limit above.
+    #[serde(default)]
+    pub keys: HashMap<String, ApiKeyRateLimitSettings>,
+}
+
+#[derive(serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct ApiKeyRateLimitSettings {
+    pub max_requests: u32,
+    pub interval_seconds: u64,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct CorsSettings {
+    /// Origins like "https://example.com", which may call the routes; "*" allows any origin.
+    pub allowed_origins: Vec<String>,
+    #[serde(default = "default_cors_allowed_methods")]
+    pub allowed_methods: Vec<String>,
+    /// Request headers, which cross-origin requests may set.
+    #[serde(default = "default_cors_allowed_headers")]
+    pub allowed_headers: Vec<String>,
+    /// Time browsers cache the response of a preflight request.
+    #[serde(default = "default_cors_max_age_seconds")]
+    pub max_age_seconds: usize,
+}
+
+fn default_cors_allowed_methods() -> Vec<String> {
+    vec!["GET".to_string(), "POST".to_string()]
+}
+
+fn default_cors_allowed_headers() -> Vec<String> {
+    vec!["Content-Type".to_string()]
+}
+
+fn default_cors_max_age_seconds() -> usize {
+    3600
+}
+
+fn default_worker_concurrency() -> u16 {
+    1
+}
+
+fn default_max_task_crashes() -> u8 {
+    3
+}
+
+#[derive(serde::Deserialize, Clone, Debug, Default)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum DeliveryQueueSettings {
+    /// Table `issue_delivery_queue` of the application database
+    #[default]
+    Postgres,
+    /// Sorted set and hash in Redis at `redis_uri`, whose keys start with `key_prefix`
+    Redis {
+        #[serde(default = "default_queue_key_prefix")]
+        key_prefix: String,
+        /// Claimed tasks of a crashed worker become due again after the lease.
+        #[serde(default = "default_queue_lease_seconds")]
+        lease_seconds: u64,
+    },
+}
+
+fn default_queue_key_prefix() -> String {
+    "delivery_queue".to_string()
+}
+
+fn default_queue_lease_seconds() -> u64 {
+    300
+}
+
+#[derive(serde::Deserialize, Clone, Debug, Default)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum IdempotencyStoreSettings {
+    /// Table `idempotency` of the application database
+    #[default]
+    Postgres,
+    /// Keys in Redis at `redis_uri`, which start with `key_prefix` and expire after
+    /// `idempotency_lifetime_minutes`. Keys of requests in process are reserved for
+    /// `lease_seconds`, but at least for `shutdown_timeout_seconds`.
+    Redis {
+        #[serde(default = "default_idempotency_key_prefix")]
+        key_prefix: String,
+        #[serde(default = "default_idempotency_lease_seconds")]
+        lease_seconds: u64,
+    },
+}
+
+fn default_idempotency_key_prefix() -> String {
+    "idempotency".to_string()
+}
+
+fn default_idempotency_lease_seconds() -> u64 {
+    60
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct EventExportSettings {
+    pub sink: EventSinkSettings,
+    /// Topic (Kafka) or subject (NATS) per event kind
+    #[serde(default)]
+    pub topics: HashMap<SubscriberEventKind, String>,
+    /// Topic of event kinds without entry in `topics`; they are not exported if `None`.
+    #[serde(default)]
+    pub default_topic: Option<String>,
+    #[serde(default = "default_export_batch_size")]
+    pub batch_size: u16,
+    #[serde(default = "default_export_poll_interval_seconds")]
+    pub poll_interval_seconds: u64,
+}
+
+impl EventExportSettings {
+    pub fn topic(&self, kind: SubscriberEventKind) -> Option<&str> {
+        self.topics
+            .get(&kind)
+            .or(self.default_topic.as_ref())
+            .map(String::as_str)
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum EventSinkSettings {
+    /// Requires cargo feature `kafka`; `brokers` is a comma separated list of `host:port`.
+    Kafka { brokers: String },
+    /// Requires cargo feature `nats`; events are published to JetStream.
+    Nats { url: String },
+}
+
+fn default_export_batch_size() -> u16 {
+    100
+}
+
+fn default_export_poll_interval_seconds() -> u64 {
+    5
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct AttachmentScanSettings {
+    pub scanner: AttachmentScannerSettings,
+    /// Reject attachments with detected malware or store them flagged as infected.
+    #[serde(default)]
+    pub on_detection: DetectionAction,
+    pub timeout_milliseconds: u64,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum AttachmentScannerSettings {
+    /// clamd listening on a TCP socket, e.g. "127.0.0.1:3310"
+    ClamAv { address: String },
+    /// External API, which receives the attachment as request body and answers
+    /// with `{"infected": bool, "signature": string or null}`
+    Api {
+        url: String,
+        #[serde(default)]
+        token: Option<Secret<String>>,
+    },
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct OidcSettings {
+    /// Issuer URL of the provider, which serves `/.well-known/openid-configuration`.
+    pub issuer_url: String,
+    pub client_id: String,
+    pub client_secret: Secret<String>,
+    pub timeout_milliseconds: u64,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct SecretsSettings {
+    pub backend: SecretsBackendSettings,
+    /// Time, for which fetched secrets are reused by configurations read again.
+    #[serde(default = "default_secrets_refresh_seconds")]
+    pub refresh_seconds: u64,
+    #[serde(default = "default_secrets_timeout_milliseconds")]
+    pub timeout_milliseconds: u64,
+}
+
+fn default_secrets_refresh_seconds() -> u64 {
+    300
+}
+
+fn default_secrets_timeout_milliseconds() -> u64 {
+    10000
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SecretsBackendSettings {
+    /// Key-value secrets engine (version 2) of HashiCorp Vault at `mount`.
+    Vault {
+        address: String,
+        token: Secret<String>,
+        #[serde(default = "default_vault_mount")]
+        mount: String,
+    },
+    /// Secrets of AWS Secrets Manager, whose secret string is a JSON object.
+    AwsSecretsManager {
+        region: String,
+        access_key_id: String,
+        secret_access_key: Secret<String>,
+        #[serde(default)]
+        session_token: Option<Secret<String>>,
+        /// Endpoint other than the one of the region, e.g. of a VPC endpoint.
+        #[serde(default)]
+        endpoint: Option<String>,
+    },
+}
+
+fn default_vault_mount() -> String {
+    "secret".to_string()
+}
+
+impl SecretsBackendSettings {
+    /// Identity of the backend, which tells apart cached secrets of different backends.
+    pub fn id(&self) -> String {
+        match self {
+            Self::Vault { address, mount, .. } => format!("vault:{}/{}", address, mount),
+            Self::AwsSecretsManager {
+                region, endpoint, ..
+            } => format!("aws:{}", endpoint.as_deref().unwrap_or(region.as_str())),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct OtlpSettings {
+    /// OTLP/HTTP endpoint of the collector, e.g. Jaeger or Tempo.
+    pub endpoint: String,
+    #[serde(default = "default_otlp_service_name")]
+    pub service_name: String,
+    /// Share of traces to export between 0.0 and 1.0; child spans follow their parent.
+    #[serde(default = "default_otlp_sampling_ratio")]
+    pub sampling_ratio: f64,
+}
+
+fn default_otlp_service_name() -> String {
+    "zero2prod".to_string()
+}
+
+fn default_otlp_sampling_ratio() -> f64 {
+    1.0
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DetectionAction {
+    #[default]
+    Block,
+    Flag,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct WarmUpSettings {
+    /// First day of warm-up (UTC).
+    pub start_date: NaiveDate,
+    /// Maximum number of emails per day, starting with `start_date`.
+    pub daily_limits: Vec<u32>,
+}
+
+impl WarmUpSettings {
+    /// Send limit of given day; `None` once the warm-up schedule is completed.
+    pub fn daily_limit(&self, date: NaiveDate) -> Option<u32> {
+        let day = (date - self.start_date).num_days().max(0) as usize;
+        self.daily_limits.get(day).copied()
+    }
+
+    /// Number of days to send `num_emails`, starting with `date` on which `num_sent` emails
+    /// have been sent already. Emails left at the end of the schedule are sent on the next day.
+    pub fn days_to_send(&self, date: NaiveDate, num_sent: u32, num_emails: u64) -> u64 {
+        let mut remaining = num_emails;
+        let mut num_sent = num_sent as u64;
+        let mut date = date;
+        let mut days = 1;
+        while let Some(daily_limit) = self.daily_limit(date) {
+            let capacity = (daily_limit as u64).saturating_sub(num_sent);
+            if remaining <= capacity {
+                return days;
+            }
+            remaining -= capacity;
+            num_sent = 0;
+            date += chrono::TimeDelta::days(1);
+            days += 1;
+        }
+        days
+    }
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct FallbackEmailProviderSettings {
+    pub provider: EmailProvider,
+    pub base_url: String,
+    pub token: Secret<String>,
 }
 
 impl EmailClientSettings {
@@ -79,10 +532,48 @@ impl EmailClientSettings {
     pub fn timeout(&self) -> std::time::Duration {
         std::time::Duration::from_millis(self.timeout_milliseconds)
     }
+    pub fn http_settings(&self) -> HttpClientSettings {
+        HttpClientSettings {
+            timeout: self.timeout(),
+            connect_timeout: std::time::Duration::from_millis(self.connect_timeout_milliseconds),
+            pool_idle_timeout: std::time::Duration::from_secs(self.pool_idle_timeout_seconds),
+            pool_max_idle_per_host: self.pool_max_idle_per_host,
+            tcp_keepalive: std::time::Duration::from_secs(self.tcp_keepalive_seconds),
+        }
+    }
     pub fn client(self) -> EmailClient {
         let sender_email = self.sender().expect("Invalid sender email address.");
-        let timeout = self.timeout();
-        EmailClient::new(self.base_url, sender_email, self.token, timeout)
+        let http_settings = self.http_settings();
+        let failover_reset = std::time::Duration::from_millis(self.failover_reset_milliseconds);
+        self.fallbacks.into_iter().fold(
+            EmailClient::new(
+                self.provider,
+                self.base_url,
+                sender_email,
+                self.token,
+                &http_settings,
+            )
+            .with_failover_policy(self.failover_threshold, failover_reset)
+            .with_mode(self.mode),
+            |client, fallback| {
+                client.with_fallback(fallback.provider, fallback.base_url, fallback.token)
+            },
+        )
+    }
+}
+
+impl CorsSettings {
+    pub fn cors(&self) -> Cors {
+        let cors = if self.allowed_origins.iter().any(|origin| origin == "*") {
+            Cors::default().allow_any_origin()
+        } else {
+            self.allowed_origins
+                .iter()
+                .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
+        };
+        cors.allowed_methods(self.allowed_methods.iter().map(String::as_str))
+            .allowed_headers(self.allowed_headers.iter().map(String::as_str))
+            .max_age(self.max_age_seconds)
     }
 }
 
@@ -148,6 +639,8 @@ pub fn get_configuration() -> Result<Settings, config::ConfigError> {
                 .separator("__"),
         )
         .build()?;
+    // Replace references to secrets by their values of the secrets backend
+    let settings = resolve_secrets(settings)?;
     // Try to convert the configuration values it read into our Settings type
     settings.try_deserialize::<Settings>()
 }
@@ -0,0 +1,282 @@
+//! src/delivery_attempts.rs
+
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, PgPool};
+use std::any::Any;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::delivery_queue::Task;
+
+/// Version of the binary, which is recorded at the attempts of its workers.
+const WORKER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Crashes of a task, i.e. attempts, which never finished, because their worker loop
+/// panicked or the process died.
+#[derive(Debug, Default, Clone)]
+pub struct TaskCrashes {
+    pub n_crashes: i16,
+    pub panic_message: Option<String>,
+}
+
+/// Tracks the attempts of a worker loop in table `delivery_attempts` outside of the
+/// claim of its tasks, so that attempts of crashed loops survive the crash. Tasks with
+/// crashes are executed one by one to find the poison task, which is quarantined into
+/// table `delivery_dead_letters` after `max_task_crashes` crashes.
+#[derive(Debug, Clone, Copy)]
+pub struct DeliveryAttempts {
+    worker_id: Uuid,
+    max_task_crashes: i16,
+}
+
+impl DeliveryAttempts {
+    /// Attempts of a new worker loop.
+    pub fn new(max_task_crashes: u8) -> Self {
+        Self {
+            worker_id: Uuid::new_v4(),
+            max_task_crashes: max_task_crashes.max(1).into(),
+        }
+    }
+
+    /// Start attempts of dequeued tasks; returns crashes of tasks, which crashed before.
+    /// Attempts, which are still in flight, have crashed.
+    #[tracing::instrument(name = "Begin delivery attempts", skip_all)]
+    pub async fn begin(
+        &self,
+        pool: &PgPool,
+        tasks: &[Task],
+    ) -> Result<HashMap<(Uuid, Uuid), TaskCrashes>, sqlx::Error> {
+        let (issue_ids, user_ids) = task_keys(tasks);
+        let rows = sqlx::query!(
+            r#"
+            INSERT INTO delivery_attempts AS a
+                (newsletter_issue_id, user_id, worker_id, in_flight, n_crashes, worker_version)
+            SELECT issue_id, user_id, $3, TRUE, 0, $4
+            FROM UNNEST($1::uuid[], $2::uuid[]) AS t(issue_id, user_id)
+            ON CONFLICT (newsletter_issue_id, user_id) DO UPDATE
+            SET
+                worker_id = EXCLUDED.worker_id,
+                worker_version = EXCLUDED.worker_version,
+                in_flight = TRUE,
+                n_crashes = a.n_crashes + a.in_flight::INTEGER
+            RETURNING newsletter_issue_id, user_id, n_crashes, panic_message
+            "#,
+            &issue_ids,
+            &user_ids,
+            self.worker_id,
+            WORKER_VERSION,
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .filter(|r| r.n_crashes > 0)
+            .map(|r| {
+                (
+                    (r.newsletter_issue_id, r.user_id),
+                    TaskCrashes {
+                        n_crashes: r.n_crashes,
+                        panic_message: r.panic_message,
+                    },
+                )
+            })
+            .collect())
+    }
+
+    /// Task has crashed too often and must be quarantined.
+    pub fn is_poison(&self, crashes: &TaskCrashes) -> bool {
+        crashes.n_crashes >= self.max_task_crashes
+    }
+
+    /// Finish attempts of executed tasks, which did not crash this time.
+    #[tracing::instrument(name = "Finish delivery attempts", skip_all)]
+    pub async fn finish(&self, pool: &PgPool, tasks: &[Task]) -> Result<(), sqlx::Error> {
+        let (issue_ids, user_ids) = task_keys(tasks);
+        sqlx::query!(
+            r#"
+            DELETE FROM delivery_attempts
+            WHERE (newsletter_issue_id, user_id) IN (
+                SELECT * FROM UNNEST($1::uuid[], $2::uuid[])
+            )
+            "#,
+            &issue_ids,
+            &user_ids,
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Stop attempts of tasks, which were released without execution, e.g. to execute a
+    /// task with crashes alone. Their crashes are kept.
+    #[tracing::instrument(name = "Set aside delivery attempts", skip_all)]
+    pub async fn set_aside(&self, pool: &PgPool, tasks: &[Task]) -> Result<(), sqlx::Error> {
+        let (issue_ids, user_ids) = task_keys(tasks);
+        sqlx::query!(
+            r#"
+            UPDATE delivery_attempts
+            SET in_flight = FALSE
+            WHERE (newsletter_issue_id, user_id) IN (
+                SELECT * FROM UNNEST($1::uuid[], $2::uuid[])
+            )
+            "#,
+            &issue_ids,
+            &user_ids,
+        )
+        .execute(pool)
+        .await?;
+        // attempts without crashes are no longer needed
+        sqlx::query!(
+            "DELETE FROM delivery_attempts WHERE NOT in_flight AND n_crashes = 0 AND worker_id = $1",
+            self.worker_id,
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Record the panic message at the attempts in flight of the loop, which panicked.
+    /// The attempts count as crash, when their tasks are dequeued again.
+    #[tracing::instrument(name = "Record panic of delivery attempts", skip(self, pool))]
+    pub async fn record_panic(
+        &self,
+        pool: &PgPool,
+        panic_message: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE delivery_attempts
+            SET panic_message = $2
+            WHERE worker_id = $1 AND in_flight
+            "#,
+            self.worker_id,
+            panic_message,
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+}
+
+fn task_keys(tasks: &[Task]) -> (Vec<Uuid>, Vec<Uuid>) {
+    tasks
+        .iter()
+        .map(|task| (task.issue_id, task.user_id))
+        .unzip()
+}
+
+/// Message of a panic payload, which is a string for `panic!` with message.
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "panic without message".to_string())
+}
+
+/// Move the task with its crashes into the dead letters; its attempt is removed.
+#[tracing::instrument(skip(executor, crashes))]
+pub async fn insert_dead_letter<'e>(
+    executor: impl PgExecutor<'e>,
+    task: &Task,
+    crashes: &TaskCrashes,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        WITH attempt AS (
+            DELETE FROM delivery_attempts
+            WHERE newsletter_issue_id = $1 AND user_id = $2
+        )
+        INSERT INTO delivery_dead_letters
+            (newsletter_issue_id, user_id, n_crashes, panic_message, quarantined_at)
+        VALUES ($1, $2, $3, $4, now())
+        ON CONFLICT (newsletter_issue_id, user_id) DO UPDATE
+        SET
+            n_crashes = EXCLUDED.n_crashes,
+            panic_message = EXCLUDED.panic_message,
+            quarantined_at = EXCLUDED.quarantined_at
+        "#,
+        task.issue_id,
+        task.user_id,
+        crashes.n_crashes,
+        crashes.panic_message,
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// Task, which has been quarantined, as shown on the workers page.
+pub struct DeadLetter {
+    pub newsletter_issue_id: Uuid,
+    pub user_id: Uuid,
+    pub n_crashes: i16,
+    pub panic_message: Option<String>,
+    pub quarantined_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn get_dead_letters(pool: &PgPool) -> Result<Vec<DeadLetter>, sqlx::Error> {
+    sqlx::query_as!(
+        DeadLetter,
+        r#"
+        SELECT newsletter_issue_id, user_id, n_crashes, panic_message, quarantined_at
+        FROM delivery_dead_letters
+        ORDER BY quarantined_at DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Attempts in flight of the workers of one binary version, e.g. old and new workers,
+/// which drain the queue together during a deploy.
+pub struct VersionAttempts {
+    pub worker_version: String,
+    pub n_in_flight: i64,
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn get_attempts_per_version(pool: &PgPool) -> Result<Vec<VersionAttempts>, sqlx::Error> {
+    sqlx::query_as!(
+        VersionAttempts,
+        r#"
+        SELECT
+            COALESCE(worker_version, 'unknown') AS "worker_version!",
+            COUNT(*) AS "n_in_flight!"
+        FROM delivery_attempts
+        WHERE in_flight
+        GROUP BY 1
+        ORDER BY 1
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panic_message_is_read_from_payload() {
+        let payload = std::panic::catch_unwind(|| panic!("static message")).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "static message");
+        let payload = std::panic::catch_unwind(|| panic!("formatted {}", 42)).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "formatted 42");
+        let payload = std::panic::catch_unwind(|| std::panic::panic_any(42)).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "panic without message");
+    }
+
+    #[test]
+    fn tasks_are_poison_after_max_crashes() {
+        let attempts = DeliveryAttempts::new(3);
+        let crashes = |n_crashes| TaskCrashes {
+            n_crashes,
+            panic_message: None,
+        };
+        assert!(!attempts.is_poison(&crashes(2)));
+        assert!(attempts.is_poison(&crashes(3)));
+        assert!(DeliveryAttempts::new(0).is_poison(&crashes(1)));
+    }
+}
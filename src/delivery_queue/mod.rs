@@ -0,0 +1,96 @@
+//! src/delivery_queue/mod.rs
+
+mod postgres;
+mod redis;
+
+pub use self::redis::RedisDeliveryQueue;
+pub use postgres::PgDeliveryQueue;
+
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::future::Future;
+use uuid::Uuid;
+
+/// Format version of tasks, which this binary enqueues and is able to execute.
+/// Increase it with changes of tasks, which older binaries would misinterpret. During
+/// rolling deploys workers of older binaries leave newer tasks to the new workers,
+/// while both drain the tasks they understand.
+pub const TASK_VERSION: i16 = 1;
+
+/// Delivery of an issue to a subscriber.
+#[derive(Debug, Clone)]
+pub struct Task {
+    pub issue_id: Uuid,
+    pub user_id: Uuid,
+    pub n_retries: u8,
+    pub execute_after: DateTime<Utc>,
+    /// Format version, with which the task has been enqueued.
+    pub version: i16,
+}
+
+/// Queue of delivery tasks. Dequeued tasks are claimed by the worker until their
+/// claim is released; other workers dequeue disjoint tasks. Changes of claimed tasks
+/// by `complete` and `postpone` take effect with `release`. If a claim is dropped
+/// without release, its tasks become due again.
+/// Futures are `Send`, so that worker loops generic over the queue can be spawned.
+pub trait DeliveryQueue: Send + Sync {
+    type Claim: Send;
+
+    /// Enqueue delivery of issue to subscribers; returns number of enqueued tasks.
+    fn enqueue(
+        &self,
+        issue_id: Uuid,
+        subscriber_ids: &[Uuid],
+        execute_after: DateTime<Utc>,
+    ) -> impl Future<Output = Result<u64, anyhow::Error>> + Send;
+
+    /// Claim up to `batch_size` due tasks, whose version is not newer than `TASK_VERSION`.
+    fn dequeue(
+        &self,
+        batch_size: u16,
+    ) -> impl Future<Output = Result<(Self::Claim, Vec<Task>), anyhow::Error>> + Send;
+
+    /// Remove delivered or failed task from queue.
+    fn complete(
+        &self,
+        claim: &mut Self::Claim,
+        task: &Task,
+    ) -> impl Future<Output = Result<(), anyhow::Error>> + Send;
+
+    /// Delay task until `execute_after`. A retry increments the retry counter of task.
+    fn postpone(
+        &self,
+        claim: &mut Self::Claim,
+        task: &Task,
+        execute_after: DateTime<Utc>,
+        is_retry: bool,
+    ) -> impl Future<Output = Result<(), anyhow::Error>> + Send;
+
+    /// Apply changes of claimed tasks and release them.
+    fn release(&self, claim: Self::Claim)
+        -> impl Future<Output = Result<(), anyhow::Error>> + Send;
+
+    /// No pending tasks are left, neither due nor postponed.
+    fn is_empty(&self) -> impl Future<Output = Result<bool, anyhow::Error>> + Send;
+
+    /// Number of pending tasks of issue, including claimed and postponed tasks.
+    fn pending_count(
+        &self,
+        issue_id: Uuid,
+    ) -> impl Future<Output = Result<u64, anyhow::Error>> + Send;
+
+    /// Number of tasks of other issues than `except_issue_id` per subscriber, which
+    /// are pending or paused and therefore still may be sent. Subscribers without such
+    /// tasks are missing.
+    fn pending_for(
+        &self,
+        subscriber_ids: &[Uuid],
+        except_issue_id: Uuid,
+    ) -> impl Future<Output = Result<HashMap<Uuid, u64>, anyhow::Error>> + Send;
+
+    /// Time, since which the longest waiting due task is due; `None` if no task is due.
+    /// Due tasks of a queue, which is drained by workers, do not wait long.
+    fn oldest_due_task(
+        &self,
+    ) -> impl Future<Output = Result<Option<DateTime<Utc>>, anyhow::Error>> + Send;
+}
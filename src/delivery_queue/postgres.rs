@@ -0,0 +1,290 @@
+//! src/delivery_queue/postgres.rs
+
+use chrono::{DateTime, Utc};
+use rand::distributions::{Distribution, WeightedIndex};
+use sqlx::{Executor, PgPool, Postgres, Row};
+use std::collections::HashMap;
+use tracing::Span;
+use uuid::Uuid;
+
+use super::{DeliveryQueue, Task, TASK_VERSION};
+use crate::issue_delivery_worker::PgTransaction;
+
+/// Queue in table `issue_delivery_queue`. Dequeued tasks are locked by the
+/// transaction of their claim.
+#[derive(Clone)]
+pub struct PgDeliveryQueue {
+    pool: PgPool,
+}
+
+impl PgDeliveryQueue {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Number of pending tasks, which only workers of newer binaries are able to execute.
+    #[tracing::instrument(skip_all)]
+    pub async fn num_newer_tasks(&self) -> Result<i64, sqlx::Error> {
+        let count = sqlx::query!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM issue_delivery_queue
+            WHERE status = 'pending' AND task_version > $1
+            "#,
+            TASK_VERSION,
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .count;
+        Ok(count)
+    }
+
+    /// `pending_for` with `executor`, e.g. a transaction, which sees its own tasks.
+    pub async fn pending_for_with<'c>(
+        executor: impl Executor<'c, Database = Postgres>,
+        subscriber_ids: &[Uuid],
+        except_issue_id: Uuid,
+    ) -> Result<HashMap<Uuid, u64>, sqlx::Error> {
+        let counts = sqlx::query!(
+            r#"
+            SELECT user_id, COUNT(*) AS "count!"
+            FROM issue_delivery_queue
+            WHERE
+                user_id = ANY($1) AND
+                newsletter_issue_id <> $2 AND
+                status <> 'cancelled'
+            GROUP BY user_id
+            "#,
+            subscriber_ids,
+            except_issue_id,
+        )
+        .fetch_all(executor)
+        .await?
+        .into_iter()
+        .map(|r| (r.user_id, r.count as u64))
+        .collect();
+        Ok(counts)
+    }
+}
+
+impl DeliveryQueue for PgDeliveryQueue {
+    type Claim = PgTransaction;
+
+    #[tracing::instrument(skip_all, fields(newsletter_issue_id = %issue_id))]
+    async fn enqueue(
+        &self,
+        issue_id: Uuid,
+        subscriber_ids: &[Uuid],
+        execute_after: DateTime<Utc>,
+    ) -> Result<u64, anyhow::Error> {
+        let num_tasks = sqlx::query!(
+            r#"
+            INSERT INTO issue_delivery_queue (
+                newsletter_issue_id,
+                user_id,
+                n_retries,
+                execute_after,
+                task_version
+            )
+            SELECT $1, subscriber_id, 0, $3, $4
+            FROM UNNEST($2::uuid[]) AS subscriber_id
+            "#,
+            issue_id,
+            subscriber_ids,
+            execute_after,
+            TASK_VERSION,
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        Ok(num_tasks)
+    }
+
+    /// Dequeue tasks of one issue, which is chosen randomly according to the delivery
+    /// weights of all issues with due tasks. Therefore an issue with weight 4 gets about
+    /// 80% of worker batches next to an issue with weight 1, independent of their backlogs.
+    #[tracing::instrument(skip_all, fields(newsletter_issue_id = tracing::field::Empty))]
+    async fn dequeue(&self, batch_size: u16) -> Result<(PgTransaction, Vec<Task>), anyhow::Error> {
+        let mut transaction: PgTransaction = self.pool.begin().await?;
+        let weighted_issues = sqlx::query!(
+            r#"
+            SELECT DISTINCT q.newsletter_issue_id, i.delivery_weight
+            FROM issue_delivery_queue q
+            JOIN newsletter_issues i ON i.newsletter_issue_id = q.newsletter_issue_id
+            WHERE
+                NOW() > q.execute_after AND q.status = 'pending' AND q.task_version <= $1
+            "#,
+            TASK_VERSION,
+        )
+        .fetch_all(&mut *transaction)
+        .await?;
+        let chosen_issue = WeightedIndex::new(
+            weighted_issues
+                .iter()
+                .map(|issue| issue.delivery_weight.max(1)),
+        )
+        .ok()
+        .map(|index| weighted_issues[index.sample(&mut rand::thread_rng())].newsletter_issue_id);
+        let Some(issue_id) = chosen_issue else {
+            return Ok((transaction, Vec::new()));
+        };
+        Span::current().record("newsletter_issue_id", issue_id.to_string());
+        let query = sqlx::query!(
+            r#"
+            SELECT newsletter_issue_id, user_id, n_retries, execute_after, task_version
+            FROM issue_delivery_queue
+            WHERE
+                NOW() > execute_after AND status = 'pending' AND newsletter_issue_id = $2 AND
+                task_version <= $3
+            FOR UPDATE
+            SKIP LOCKED
+            LIMIT $1
+            "#,
+            batch_size.max(1) as i64,
+            issue_id,
+            TASK_VERSION,
+        );
+        let mut rows = transaction.fetch_all(query).await?;
+        if rows.is_empty() {
+            // due tasks of chosen issue are locked by other workers, take any due task
+            let query = sqlx::query!(
+                r#"
+                SELECT newsletter_issue_id, user_id, n_retries, execute_after, task_version
+                FROM issue_delivery_queue
+                WHERE NOW() > execute_after AND status = 'pending' AND task_version <= $2
+                FOR UPDATE
+                SKIP LOCKED
+                LIMIT $1
+                "#,
+                batch_size.max(1) as i64,
+                TASK_VERSION,
+            );
+            rows = transaction.fetch_all(query).await?;
+        }
+        let mut tasks = Vec::with_capacity(rows.len());
+        for r in rows {
+            let n_retries: i16 = r.try_get("n_retries")?;
+            if n_retries < 0 {
+                Err(anyhow::anyhow!("value n_retries < 0"))?;
+            }
+            tasks.push(Task {
+                issue_id: r.try_get("newsletter_issue_id")?,
+                user_id: r.try_get("user_id")?,
+                n_retries: n_retries as u8,
+                execute_after: r.try_get("execute_after")?,
+                version: r.try_get("task_version")?,
+            });
+        }
+        Ok((transaction, tasks))
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn complete(
+        &self,
+        transaction: &mut PgTransaction,
+        task: &Task,
+    ) -> Result<(), anyhow::Error> {
+        let query = sqlx::query!(
+            r#"
+            DELETE FROM issue_delivery_queue
+            WHERE
+                newsletter_issue_id = $1 AND
+                user_id = $2
+            "#,
+            task.issue_id,
+            task.user_id
+        );
+        transaction.execute(query).await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn postpone(
+        &self,
+        transaction: &mut PgTransaction,
+        task: &Task,
+        execute_after: DateTime<Utc>,
+        is_retry: bool,
+    ) -> Result<(), anyhow::Error> {
+        let n_retries = if is_retry {
+            task.n_retries + 1
+        } else {
+            task.n_retries
+        };
+        let query = sqlx::query!(
+            r#"
+            UPDATE issue_delivery_queue
+            SET
+                n_retries = $3,
+                execute_after = $4
+            WHERE
+                newsletter_issue_id = $1 AND
+                user_id = $2
+            "#,
+            task.issue_id,
+            task.user_id,
+            n_retries as i16,
+            execute_after
+        );
+        transaction.execute(query).await?;
+        Ok(())
+    }
+
+    async fn release(&self, transaction: PgTransaction) -> Result<(), anyhow::Error> {
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn is_empty(&self) -> Result<bool, anyhow::Error> {
+        let count = sqlx::query!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM issue_delivery_queue
+            WHERE status = 'pending'
+            "#
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .count;
+        Ok(count == 0)
+    }
+
+    async fn pending_count(&self, issue_id: Uuid) -> Result<u64, anyhow::Error> {
+        let count = sqlx::query!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM issue_delivery_queue
+            WHERE newsletter_issue_id = $1 AND status = 'pending'
+            "#,
+            issue_id,
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .count;
+        Ok(count as u64)
+    }
+
+    async fn pending_for(
+        &self,
+        subscriber_ids: &[Uuid],
+        except_issue_id: Uuid,
+    ) -> Result<HashMap<Uuid, u64>, anyhow::Error> {
+        Ok(Self::pending_for_with(&self.pool, subscriber_ids, except_issue_id).await?)
+    }
+
+    async fn oldest_due_task(&self) -> Result<Option<DateTime<Utc>>, anyhow::Error> {
+        let oldest = sqlx::query!(
+            r#"
+            SELECT MIN(execute_after) AS oldest
+            FROM issue_delivery_queue
+            WHERE NOW() > execute_after AND status = 'pending' AND task_version <= $1
+            "#,
+            TASK_VERSION,
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .oldest;
+        Ok(oldest)
+    }
+}
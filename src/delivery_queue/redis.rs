@@ -0,0 +1,788 @@
+//! src/delivery_queue/redis.rs
+
+use ::redis::aio::ConnectionManager;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use rand::distributions::{Distribution, WeightedIndex};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::Span;
+use uuid::Uuid;
+
+use super::{DeliveryQueue, Task, TASK_VERSION};
+use crate::issue_delivery_worker::DeliveryTaskStatus;
+
+/// Claims due tasks by moving their score behind the end of the lease and returns
+/// members and data of claimed tasks.
+/// KEYS[1]: sorted set of tasks of issue, KEYS[2]: hash of task data
+/// ARGV[1]: now, ARGV[2]: end of lease, ARGV[3]: batch size
+const DEQUEUE_SCRIPT: &str = r#"
+local members = redis.call('ZRANGEBYSCORE', KEYS[1], '-inf', ARGV[1], 'LIMIT', 0, ARGV[3])
+local result = {}
+for _, member in ipairs(members) do
+    redis.call('ZADD', KEYS[1], ARGV[2], member)
+    table.insert(result, member)
+    table.insert(result, redis.call('HGET', KEYS[2], member) or '')
+end
+return result
+"#;
+
+/// Moves tasks, whose member matches the pattern, to another sorted set and returns
+/// number of moved tasks.
+/// KEYS[1]: source sorted set, KEYS[2]: destination sorted set
+/// ARGV[1]: pattern of members
+const MOVE_SCRIPT: &str = r#"
+local cursor = '0'
+local moved = 0
+repeat
+    local result = redis.call('ZSCAN', KEYS[1], cursor, 'MATCH', ARGV[1], 'COUNT', 1000)
+    cursor = result[1]
+    for i = 1, #result[2], 2 do
+        if redis.call('ZREM', KEYS[1], result[2][i]) == 1 then
+            redis.call('ZADD', KEYS[2], result[2][i + 1], result[2][i])
+            moved = moved + 1
+        end
+    end
+until cursor == '0'
+return moved
+"#;
+
+/// Removes tasks, whose member matches the pattern, from all sorted sets and their
+/// data and returns number of removed tasks.
+/// KEYS[1]: hash of task data, KEYS[2..]: sorted sets of tasks
+/// ARGV[1]: pattern of members
+const REMOVE_SCRIPT: &str = r#"
+local removed = 0
+for k = 2, #KEYS do
+    local cursor = '0'
+    repeat
+        local result = redis.call('ZSCAN', KEYS[k], cursor, 'MATCH', ARGV[1], 'COUNT', 1000)
+        cursor = result[1]
+        for i = 1, #result[2], 2 do
+            if redis.call('ZREM', KEYS[k], result[2][i]) == 1 then
+                redis.call('HDEL', KEYS[1], result[2][i])
+                removed = removed + 1
+            end
+        end
+    until cursor == '0'
+end
+return removed
+"#;
+
+/// Counts tasks, whose member matches the pattern.
+/// KEYS[1]: sorted set of tasks
+/// ARGV[1]: pattern of members
+const COUNT_SCRIPT: &str = r#"
+local cursor = '0'
+local counted = 0
+repeat
+    local result = redis.call('ZSCAN', KEYS[1], cursor, 'MATCH', ARGV[1], 'COUNT', 1000)
+    cursor = result[1]
+    counted = counted + #result[2] / 2
+until cursor == '0'
+return counted
+"#;
+
+/// Counts tasks of other issues per user and returns flat pairs of user and count.
+/// Every task of the sorted sets is read.
+/// KEYS[1..]: sorted sets of tasks
+/// ARGV[1]: excepted issue id, ARGV[2..]: user ids
+const PENDING_FOR_SCRIPT: &str = r#"
+local users = {}
+for i = 2, #ARGV do
+    users[ARGV[i]] = true
+end
+local open_tasks = {}
+for _, key in ipairs(KEYS) do
+    for _, member in ipairs(redis.call('ZRANGE', key, 0, -1)) do
+        local issue_id, user_id = string.match(member, '^([^:]+):(.+)$')
+        if issue_id and issue_id ~= ARGV[1] and users[user_id] then
+            open_tasks[user_id] = (open_tasks[user_id] or 0) + 1
+        end
+    end
+end
+local result = {}
+for user_id, count in pairs(open_tasks) do
+    table.insert(result, user_id)
+    table.insert(result, count)
+end
+return result
+"#;
+
+/// Removes issue from the set of issues with pending tasks, if it has none.
+/// KEYS[1]: set of issues, KEYS[2]: sorted set of tasks of issue
+/// ARGV[1]: issue id
+const FORGET_ISSUE_SCRIPT: &str = r#"
+if redis.call('ZCARD', KEYS[2]) == 0 then
+    return redis.call('SREM', KEYS[1], ARGV[1])
+end
+return 0
+"#;
+
+/// Enqueues tasks, which are in none of the sorted sets, and returns number of
+/// enqueued tasks.
+/// KEYS[1]: hash of task data, KEYS[2]: sorted set of pending tasks,
+/// KEYS[3..]: other sorted sets of tasks
+/// ARGV[1]: due time, ARGV[2]: task data, ARGV[3..]: members
+const ENQUEUE_MISSING_SCRIPT: &str = r#"
+local enqueued = 0
+for i = 3, #ARGV do
+    local exists = false
+    for k = 2, #KEYS do
+        if redis.call('ZSCORE', KEYS[k], ARGV[i]) then
+            exists = true
+        end
+    end
+    if not exists then
+        redis.call('ZADD', KEYS[2], ARGV[1], ARGV[i])
+        redis.call('HSET', KEYS[1], ARGV[i], ARGV[2])
+        enqueued = enqueued + 1
+    end
+end
+return enqueued
+"#;
+
+/// Queue in Redis, which keeps delivery churn out of the database. Pending tasks are
+/// members `<issue_id>:<user_id>` of a sorted set `<key_prefix>:tasks:<issue_id>` per
+/// issue, scored by the time in milliseconds at which they become due. The set
+/// `<key_prefix>:issues` holds the issues with pending tasks. Retry counter,
+/// `execute_after` and format version of tasks are stored in a hash.
+/// Like `PgDeliveryQueue` a dequeue claims tasks of one issue, which is chosen randomly
+/// according to the delivery weights of all issues with due tasks; weights are read
+/// from Postgres. Dequeued tasks are claimed by scoring them at the end of their lease.
+/// Paused and cancelled tasks are moved to sorted sets of their own, which workers do
+/// not dequeue. Tasks, which cannot be parsed, are moved with their data to the hash
+/// `<key_prefix>:invalid` for inspection.
+#[derive(Clone)]
+pub struct RedisDeliveryQueue {
+    /// reconnects after connection errors
+    connection: ConnectionManager,
+    pool: PgPool,
+    issues_key: String,
+    tasks_key_prefix: String,
+    paused_key: String,
+    cancelled_key: String,
+    data_key: String,
+    invalid_key: String,
+    lease: Duration,
+}
+
+/// Change of a claimed task, which is applied at release.
+enum TaskChange {
+    Unchanged,
+    Completed,
+    Postponed {
+        n_retries: u8,
+        execute_after: DateTime<Utc>,
+        version: i16,
+    },
+}
+
+pub struct RedisClaim {
+    tasks: Vec<(String, DateTime<Utc>, TaskChange)>,
+}
+
+impl RedisClaim {
+    fn change(&mut self, task: &Task, change: TaskChange) -> Result<(), anyhow::Error> {
+        let member = task_member(task.issue_id, task.user_id);
+        let entry = self
+            .tasks
+            .iter_mut()
+            .find(|(m, ..)| *m == member)
+            .ok_or_else(|| anyhow::anyhow!("Task `{}` is not claimed.", member))?;
+        entry.2 = change;
+        Ok(())
+    }
+}
+
+fn task_member(issue_id: Uuid, user_id: Uuid) -> String {
+    format!("{}:{}", issue_id, user_id)
+}
+
+/// Issue part of member of a task.
+fn member_issue(member: &str) -> &str {
+    member
+        .split_once(':')
+        .map_or(member, |(issue_id, _)| issue_id)
+}
+
+/// Pattern of members of all tasks of issue.
+fn issue_pattern(issue_id: Uuid) -> String {
+    format!("{}:*", issue_id)
+}
+
+/// Data of task; the version is omitted for version 1, which binaries without versioned
+/// tasks are able to parse.
+fn task_data(n_retries: u8, execute_after: DateTime<Utc>, version: i16) -> String {
+    match version {
+        1 => format!("{}:{}", n_retries, execute_after.timestamp_millis()),
+        version => format!(
+            "{}:{}:{}",
+            n_retries,
+            execute_after.timestamp_millis(),
+            version
+        ),
+    }
+}
+
+fn parse_task(member: &str, data: &str) -> Result<Task, anyhow::Error> {
+    let (issue_id, user_id) = member
+        .split_once(':')
+        .ok_or_else(|| anyhow::anyhow!("Invalid task `{}` in queue.", member))?;
+    let (n_retries, execute_after) = data
+        .split_once(':')
+        .ok_or_else(|| anyhow::anyhow!("Invalid data `{}` of task `{}`.", data, member))?;
+    let (execute_after, version) = execute_after
+        .split_once(':')
+        .unwrap_or((execute_after, "1"));
+    Ok(Task {
+        issue_id: Uuid::parse_str(issue_id).context("Invalid issue id of task.")?,
+        user_id: Uuid::parse_str(user_id).context("Invalid user id of task.")?,
+        n_retries: n_retries
+            .parse()
+            .context("Invalid retry counter of task.")?,
+        execute_after: DateTime::from_timestamp_millis(
+            execute_after
+                .parse()
+                .context("Invalid execute_after of task.")?,
+        )
+        .ok_or_else(|| anyhow::anyhow!("execute_after of task is out of range."))?,
+        version: version.parse().context("Invalid version of task.")?,
+    })
+}
+
+impl RedisDeliveryQueue {
+    /// Connect to Redis; delivery weights of issues are read with `pool`.
+    pub async fn connect(
+        redis_uri: &str,
+        key_prefix: &str,
+        lease: Duration,
+        pool: PgPool,
+    ) -> Result<Self, anyhow::Error> {
+        let client = ::redis::Client::open(redis_uri).context("Invalid redis uri.")?;
+        let connection = ConnectionManager::new(client)
+            .await
+            .context("Failed to connect to redis.")?;
+        Ok(Self {
+            connection,
+            pool,
+            issues_key: format!("{}:issues", key_prefix),
+            tasks_key_prefix: format!("{}:tasks", key_prefix),
+            paused_key: format!("{}:paused", key_prefix),
+            cancelled_key: format!("{}:cancelled", key_prefix),
+            data_key: format!("{}:data", key_prefix),
+            invalid_key: format!("{}:invalid", key_prefix),
+            lease,
+        })
+    }
+
+    /// Key of pending tasks of issue; `issue_id` is the issue part of task members.
+    fn tasks_key(&self, issue_id: impl std::fmt::Display) -> String {
+        format!("{}:{}", self.tasks_key_prefix, issue_id)
+    }
+
+    fn status_key(&self, issue_id: Uuid, status: &DeliveryTaskStatus) -> String {
+        match status {
+            DeliveryTaskStatus::Pending => self.tasks_key(issue_id),
+            DeliveryTaskStatus::Paused => self.paused_key.clone(),
+            DeliveryTaskStatus::Cancelled => self.cancelled_key.clone(),
+        }
+    }
+
+    /// Register issue as issue with pending tasks; call it after adding its tasks, so
+    /// that it is not forgotten before they are added.
+    async fn register_issue(&self, issue_id: Uuid) -> Result<(), anyhow::Error> {
+        ::redis::cmd("SADD")
+            .arg(&self.issues_key)
+            .arg(issue_id.to_string())
+            .query_async::<_, ()>(&mut self.connection.clone())
+            .await
+            .context("Failed to register issue in redis.")
+    }
+
+    /// Remove issue from the issues with pending tasks, if it has none left.
+    async fn forget_issue(&self, issue_id: &str) -> Result<(), anyhow::Error> {
+        ::redis::cmd("EVAL")
+            .arg(FORGET_ISSUE_SCRIPT)
+            .arg(2)
+            .arg(&self.issues_key)
+            .arg(self.tasks_key(issue_id))
+            .arg(issue_id)
+            .query_async::<_, ()>(&mut self.connection.clone())
+            .await
+            .context("Failed to forget issue in redis.")
+    }
+
+    /// Issues with pending tasks.
+    async fn issues(&self) -> Result<Vec<String>, anyhow::Error> {
+        ::redis::cmd("SMEMBERS")
+            .arg(&self.issues_key)
+            .query_async(&mut self.connection.clone())
+            .await
+            .context("Failed to read issues from redis.")
+    }
+
+    /// Due time of the longest waiting due task of each issue, if it has one.
+    async fn oldest_due_tasks(
+        &self,
+        issues: &[String],
+        now: DateTime<Utc>,
+    ) -> Result<Vec<Option<i64>>, anyhow::Error> {
+        // claimed tasks are scored at the end of their lease and therefore not due
+        let mut pipe = ::redis::pipe();
+        for issue_id in issues {
+            pipe.cmd("ZRANGEBYSCORE")
+                .arg(self.tasks_key(issue_id))
+                .arg("-inf")
+                .arg(now.timestamp_millis())
+                .arg("WITHSCORES")
+                .arg("LIMIT")
+                .arg(0)
+                .arg(1);
+        }
+        let oldest: Vec<Vec<(String, f64)>> = pipe
+            .query_async(&mut self.connection.clone())
+            .await
+            .context("Failed to read due tasks from redis.")?;
+        Ok(oldest
+            .into_iter()
+            .map(|task| task.first().map(|(_, due_at)| *due_at as i64))
+            .collect())
+    }
+
+    /// Choose one of the issues with due tasks randomly according to their delivery
+    /// weights, which are read from Postgres; issues missing there have weight 1.
+    async fn choose_issue(&self, due_issues: Vec<Uuid>) -> Result<Option<Uuid>, anyhow::Error> {
+        let weights: HashMap<Uuid, i32> = sqlx::query!(
+            r#"
+            SELECT newsletter_issue_id, delivery_weight
+            FROM newsletter_issues
+            WHERE newsletter_issue_id = ANY($1)
+            "#,
+            &due_issues,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to read delivery weights of issues.")?
+        .into_iter()
+        .map(|r| (r.newsletter_issue_id, r.delivery_weight))
+        .collect();
+        Ok(WeightedIndex::new(
+            due_issues
+                .iter()
+                .map(|issue_id| weights.get(issue_id).copied().unwrap_or(1).max(1)),
+        )
+        .ok()
+        .map(|index| due_issues[index.sample(&mut rand::thread_rng())]))
+    }
+
+    /// Change status of tasks of issue; returns number of changed tasks.
+    #[tracing::instrument(skip(self))]
+    pub async fn update_status(
+        &self,
+        issue_id: Uuid,
+        from: &[DeliveryTaskStatus],
+        to: DeliveryTaskStatus,
+    ) -> Result<u64, anyhow::Error> {
+        let mut num_tasks = 0;
+        for status in from.iter().filter(|status| **status != to) {
+            num_tasks += ::redis::cmd("EVAL")
+                .arg(MOVE_SCRIPT)
+                .arg(2)
+                .arg(self.status_key(issue_id, status))
+                .arg(self.status_key(issue_id, &to))
+                .arg(issue_pattern(issue_id))
+                .query_async::<_, u64>(&mut self.connection.clone())
+                .await
+                .context("Failed to change status of tasks in redis.")?;
+        }
+        match to {
+            DeliveryTaskStatus::Pending if num_tasks > 0 => self.register_issue(issue_id).await?,
+            DeliveryTaskStatus::Pending => {}
+            _ => self.forget_issue(&issue_id.to_string()).await?,
+        }
+        Ok(num_tasks)
+    }
+
+    /// Number of tasks of issue with status.
+    #[tracing::instrument(skip(self))]
+    pub async fn count_tasks(
+        &self,
+        issue_id: Uuid,
+        status: DeliveryTaskStatus,
+    ) -> Result<u64, anyhow::Error> {
+        ::redis::cmd("EVAL")
+            .arg(COUNT_SCRIPT)
+            .arg(1)
+            .arg(self.status_key(issue_id, &status))
+            .arg(issue_pattern(issue_id))
+            .query_async(&mut self.connection.clone())
+            .await
+            .context("Failed to count tasks in redis.")
+    }
+
+    /// Enqueue delivery of issue to subscribers, which have no task of issue in any
+    /// status; returns number of enqueued tasks.
+    #[tracing::instrument(skip_all, fields(newsletter_issue_id = %issue_id))]
+    pub async fn enqueue_missing(
+        &self,
+        issue_id: Uuid,
+        subscriber_ids: &[Uuid],
+        execute_after: DateTime<Utc>,
+    ) -> Result<u64, anyhow::Error> {
+        if subscriber_ids.is_empty() {
+            return Ok(0);
+        }
+        let mut cmd = ::redis::cmd("EVAL");
+        cmd.arg(ENQUEUE_MISSING_SCRIPT)
+            .arg(4)
+            .arg(&self.data_key)
+            .arg(self.tasks_key(issue_id))
+            .arg(&self.paused_key)
+            .arg(&self.cancelled_key)
+            .arg(execute_after.timestamp_millis())
+            .arg(task_data(0, execute_after, TASK_VERSION));
+        for user_id in subscriber_ids {
+            cmd.arg(task_member(issue_id, *user_id));
+        }
+        let num_tasks = cmd
+            .query_async(&mut self.connection.clone())
+            .await
+            .context("Failed to enqueue tasks in redis.")?;
+        if num_tasks > 0 {
+            self.register_issue(issue_id).await?;
+        }
+        Ok(num_tasks)
+    }
+
+    /// Remove all tasks of issue regardless of their status; returns number of removed tasks.
+    #[tracing::instrument(skip(self))]
+    pub async fn remove_issue(&self, issue_id: Uuid) -> Result<u64, anyhow::Error> {
+        let num_tasks = ::redis::cmd("EVAL")
+            .arg(REMOVE_SCRIPT)
+            .arg(4)
+            .arg(&self.data_key)
+            .arg(self.tasks_key(issue_id))
+            .arg(&self.paused_key)
+            .arg(&self.cancelled_key)
+            .arg(issue_pattern(issue_id))
+            .query_async(&mut self.connection.clone())
+            .await
+            .context("Failed to remove tasks from redis.")?;
+        self.forget_issue(&issue_id.to_string()).await?;
+        Ok(num_tasks)
+    }
+}
+
+impl DeliveryQueue for RedisDeliveryQueue {
+    type Claim = RedisClaim;
+
+    #[tracing::instrument(skip_all, fields(newsletter_issue_id = %issue_id))]
+    async fn enqueue(
+        &self,
+        issue_id: Uuid,
+        subscriber_ids: &[Uuid],
+        execute_after: DateTime<Utc>,
+    ) -> Result<u64, anyhow::Error> {
+        if subscriber_ids.is_empty() {
+            return Ok(0);
+        }
+        let tasks_key = self.tasks_key(issue_id);
+        let mut pipe = ::redis::pipe();
+        pipe.atomic();
+        for user_id in subscriber_ids {
+            let member = task_member(issue_id, *user_id);
+            pipe.zadd(&tasks_key, &member, execute_after.timestamp_millis())
+                .ignore()
+                .hset(
+                    &self.data_key,
+                    &member,
+                    task_data(0, execute_after, TASK_VERSION),
+                )
+                .ignore();
+        }
+        pipe.sadd(&self.issues_key, issue_id.to_string()).ignore();
+        pipe.query_async::<_, ()>(&mut self.connection.clone())
+            .await
+            .context("Failed to enqueue tasks in redis.")?;
+        Ok(subscriber_ids.len() as u64)
+    }
+
+    /// Dequeue tasks of one issue, which is chosen randomly according to the delivery
+    /// weights of all issues with due tasks like in `PgDeliveryQueue`.
+    #[tracing::instrument(skip_all, fields(newsletter_issue_id = tracing::field::Empty))]
+    async fn dequeue(&self, batch_size: u16) -> Result<(RedisClaim, Vec<Task>), anyhow::Error> {
+        let now = Utc::now();
+        let lease = chrono::Duration::from_std(self.lease).context("Lease is too long.")?;
+        let mut claim = RedisClaim { tasks: Vec::new() };
+        let issues = self.issues().await?;
+        let oldest_due_tasks = self.oldest_due_tasks(&issues, now).await?;
+        let mut due_issues = Vec::new();
+        for (issue_id, oldest_due_task) in issues.iter().zip(oldest_due_tasks) {
+            if oldest_due_task.is_none() {
+                // issues without due tasks may have no pending tasks left at all
+                self.forget_issue(issue_id).await?;
+            } else if let Ok(issue_id) = Uuid::parse_str(issue_id) {
+                due_issues.push(issue_id);
+            }
+        }
+        let Some(issue_id) = self.choose_issue(due_issues).await? else {
+            return Ok((claim, Vec::new()));
+        };
+        Span::current().record("newsletter_issue_id", issue_id.to_string());
+        let tasks_key = self.tasks_key(issue_id);
+        let claimed: Vec<String> = ::redis::cmd("EVAL")
+            .arg(DEQUEUE_SCRIPT)
+            .arg(2)
+            .arg(&tasks_key)
+            .arg(&self.data_key)
+            .arg(now.timestamp_millis())
+            .arg((now + lease).timestamp_millis())
+            .arg(batch_size.max(1))
+            .query_async(&mut self.connection.clone())
+            .await
+            .context("Failed to dequeue tasks from redis.")?;
+        let mut tasks = Vec::with_capacity(claimed.len() / 2);
+        let mut invalid = Vec::new();
+        for pair in claimed.chunks_exact(2) {
+            let task = match parse_task(&pair[0], &pair[1]) {
+                Ok(task) => task,
+                Err(e) => {
+                    tracing::error!(
+                        error.cause_chain = ?e,
+                        task = %pair[0],
+                        "Moving invalid task out of queue."
+                    );
+                    invalid.push((&pair[0], &pair[1]));
+                    continue;
+                }
+            };
+            claim
+                .tasks
+                .push((pair[0].clone(), task.execute_after, TaskChange::Unchanged));
+            // newer tasks are released unchanged for workers of newer binaries
+            if task.version <= TASK_VERSION {
+                tasks.push(task);
+            }
+        }
+        // one invalid task must not stop delivery of the other tasks
+        if !invalid.is_empty() {
+            let mut pipe = ::redis::pipe();
+            pipe.atomic();
+            for (member, data) in invalid {
+                pipe.hset(&self.invalid_key, member, data)
+                    .ignore()
+                    .zrem(&tasks_key, member)
+                    .ignore()
+                    .hdel(&self.data_key, member)
+                    .ignore();
+            }
+            if let Err(e) = pipe
+                .query_async::<_, ()>(&mut self.connection.clone())
+                .await
+            {
+                // they are dequeued and moved again after their lease
+                tracing::warn!(error.cause_chain = ?e, "Failed to move invalid tasks out of queue.");
+            }
+        }
+        Ok((claim, tasks))
+    }
+
+    async fn complete(&self, claim: &mut RedisClaim, task: &Task) -> Result<(), anyhow::Error> {
+        claim.change(task, TaskChange::Completed)
+    }
+
+    async fn postpone(
+        &self,
+        claim: &mut RedisClaim,
+        task: &Task,
+        execute_after: DateTime<Utc>,
+        is_retry: bool,
+    ) -> Result<(), anyhow::Error> {
+        let n_retries = if is_retry {
+            task.n_retries + 1
+        } else {
+            task.n_retries
+        };
+        claim.change(
+            task,
+            TaskChange::Postponed {
+                n_retries,
+                execute_after,
+                version: task.version,
+            },
+        )
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn release(&self, claim: RedisClaim) -> Result<(), anyhow::Error> {
+        if claim.tasks.is_empty() {
+            return Ok(());
+        }
+        let mut pipe = ::redis::pipe();
+        pipe.atomic();
+        // tasks, which have been paused, cancelled or removed while they were claimed,
+        // are not added back to the pending tasks
+        for (member, execute_after, change) in claim.tasks {
+            let tasks_key = self.tasks_key(member_issue(&member));
+            match change {
+                TaskChange::Unchanged => {
+                    for key in [&tasks_key, &self.paused_key] {
+                        pipe.cmd("ZADD")
+                            .arg(key)
+                            .arg("XX")
+                            .arg(execute_after.timestamp_millis())
+                            .arg(&member)
+                            .ignore();
+                    }
+                }
+                TaskChange::Completed => {
+                    pipe.zrem(&tasks_key, &member)
+                        .ignore()
+                        .zrem(&self.paused_key, &member)
+                        .ignore()
+                        .zrem(&self.cancelled_key, &member)
+                        .ignore()
+                        .hdel(&self.data_key, &member)
+                        .ignore();
+                }
+                TaskChange::Postponed {
+                    n_retries,
+                    execute_after,
+                    version,
+                } => {
+                    for key in [&tasks_key, &self.paused_key] {
+                        pipe.cmd("ZADD")
+                            .arg(key)
+                            .arg("XX")
+                            .arg(execute_after.timestamp_millis())
+                            .arg(&member)
+                            .ignore();
+                    }
+                    pipe.hset(
+                        &self.data_key,
+                        &member,
+                        task_data(n_retries, execute_after, version),
+                    )
+                    .ignore();
+                }
+            }
+        }
+        pipe.query_async::<_, ()>(&mut self.connection.clone())
+            .await
+            .context("Failed to release tasks in redis.")?;
+        Ok(())
+    }
+
+    async fn pending_count(&self, issue_id: Uuid) -> Result<u64, anyhow::Error> {
+        ::redis::cmd("ZCARD")
+            .arg(self.tasks_key(issue_id))
+            .query_async(&mut self.connection.clone())
+            .await
+            .context("Failed to count tasks in redis.")
+    }
+
+    /// Reads all pending and paused tasks in Redis.
+    async fn pending_for(
+        &self,
+        subscriber_ids: &[Uuid],
+        except_issue_id: Uuid,
+    ) -> Result<HashMap<Uuid, u64>, anyhow::Error> {
+        let except_issue_id = except_issue_id.to_string();
+        let mut keys: Vec<String> = self
+            .issues()
+            .await?
+            .into_iter()
+            .filter(|issue_id| *issue_id != except_issue_id)
+            .map(|issue_id| self.tasks_key(issue_id))
+            .collect();
+        keys.push(self.paused_key.clone());
+        let mut cmd = ::redis::cmd("EVAL");
+        cmd.arg(PENDING_FOR_SCRIPT)
+            .arg(keys.len())
+            .arg(keys)
+            .arg(except_issue_id);
+        for user_id in subscriber_ids {
+            cmd.arg(user_id.to_string());
+        }
+        let counts: Vec<(String, u64)> = cmd
+            .query_async(&mut self.connection.clone())
+            .await
+            .context("Failed to count tasks of subscribers in redis.")?;
+        counts
+            .into_iter()
+            .map(|(user_id, count)| {
+                Uuid::parse_str(&user_id)
+                    .map(|user_id| (user_id, count))
+                    .context("Invalid user id of task in redis.")
+            })
+            .collect()
+    }
+
+    async fn is_empty(&self) -> Result<bool, anyhow::Error> {
+        let mut pipe = ::redis::pipe();
+        for issue_id in self.issues().await? {
+            pipe.zcard(self.tasks_key(issue_id));
+        }
+        let num_tasks: Vec<u64> = pipe
+            .query_async(&mut self.connection.clone())
+            .await
+            .context("Failed to count tasks in redis.")?;
+        Ok(num_tasks.iter().all(|num_tasks| *num_tasks == 0))
+    }
+
+    async fn oldest_due_task(&self) -> Result<Option<DateTime<Utc>>, anyhow::Error> {
+        let issues = self.issues().await?;
+        Ok(self
+            .oldest_due_tasks(&issues, Utc::now())
+            .await?
+            .into_iter()
+            .flatten()
+            .min()
+            .and_then(DateTime::from_timestamp_millis))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_task, task_data, task_member};
+    use chrono::{TimeZone, Utc};
+    use claims::assert_err;
+    use uuid::Uuid;
+
+    #[test]
+    fn task_is_parsed_from_member_and_data() {
+        let issue_id = Uuid::new_v4();
+        let user_id = Uuid::new_v4();
+        let execute_after = Utc.timestamp_millis_opt(1_722_000_000_123).unwrap();
+        let task = parse_task(
+            &task_member(issue_id, user_id),
+            &task_data(3, execute_after, 1),
+        )
+        .unwrap();
+        assert_eq!(task.issue_id, issue_id);
+        assert_eq!(task.user_id, user_id);
+        assert_eq!(task.n_retries, 3);
+        assert_eq!(task.execute_after, execute_after);
+        assert_eq!(task.version, 1);
+    }
+
+    #[test]
+    fn version_of_task_is_kept_in_data() {
+        let member = task_member(Uuid::new_v4(), Uuid::new_v4());
+        let execute_after = Utc.timestamp_millis_opt(1_722_000_000_123).unwrap();
+        // data of version 1 is compatible with binaries without versioned tasks
+        assert_eq!(task_data(0, execute_after, 1), "0:1722000000123");
+        let task = parse_task(&member, &task_data(2, execute_after, 7)).unwrap();
+        assert_eq!(task.n_retries, 2);
+        assert_eq!(task.execute_after, execute_after);
+        assert_eq!(task.version, 7);
+    }
+
+    #[test]
+    fn task_without_data_is_rejected() {
+        let member = task_member(Uuid::new_v4(), Uuid::new_v4());
+        assert_err!(parse_task(&member, ""));
+    }
+}
@@ -0,0 +1,209 @@
+//! src/delivery_stats.rs
+
+use sqlx::PgPool;
+use std::collections::HashSet;
+use std::fmt;
+use uuid::Uuid;
+
+/// Delivery counters of an issue, which drifted from its delivery log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatsCorrection {
+    pub newsletter_issue_id: Uuid,
+    pub title: String,
+    pub recorded_delivered: i32,
+    pub recorded_failed: i32,
+    pub num_delivered: i32,
+    pub num_failed: i32,
+}
+
+/// Delivery counters of published issues reconciled with their delivery log.
+#[derive(Debug)]
+pub struct BackfillReport {
+    /// Corrections are reported, but not written.
+    pub dry_run: bool,
+    pub num_checked: usize,
+    pub corrections: Vec<StatsCorrection>,
+    /// Issues with attempts in flight or crashed, whose counters may be updated right now.
+    pub skipped_in_flight: Vec<Uuid>,
+    /// Issues without any delivery in the log, e.g. delivered before deliveries were logged.
+    pub num_skipped_without_log: usize,
+}
+
+impl fmt::Display for BackfillReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.corrections.iter() {
+            writeln!(
+                f,
+                "{} {}: delivered {} -> {}, failed {} -> {}",
+                c.newsletter_issue_id,
+                c.title,
+                c.recorded_delivered,
+                c.num_delivered,
+                c.recorded_failed,
+                c.num_failed
+            )?;
+        }
+        for issue_id in self.skipped_in_flight.iter() {
+            writeln!(f, "{} skipped: deliveries in flight", issue_id)?;
+        }
+        write!(
+            f,
+            "{} issue(s) checked, {} {}corrected, {} without delivery log.",
+            self.num_checked,
+            self.corrections.len(),
+            if self.dry_run { "to be " } else { "" },
+            self.num_skipped_without_log
+        )
+    }
+}
+
+/// Recompute `num_delivered_newsletters` and `num_failed_deliveries` of published issues
+/// from the last delivery event of each recipient and the dead letters of the issue.
+/// The worker updates these counters outside of the transaction, which records the
+/// delivery event and removes the task, so a crash in between counts a delivery twice.
+/// Recipients, whose failed delivery is retried, are counted once their new task is done.
+#[tracing::instrument(skip(pool))]
+pub async fn backfill_delivery_stats(
+    pool: &PgPool,
+    newsletter_issue_id: Option<Uuid>,
+    dry_run: bool,
+) -> Result<BackfillReport, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+    // workers wait for these locks to count further deliveries of the issues
+    let issues = sqlx::query!(
+        r#"
+        SELECT newsletter_issue_id, title, num_delivered_newsletters, num_failed_deliveries
+        FROM newsletter_issues
+        WHERE
+            num_current_subscribers IS NOT NULL AND
+            ($1::uuid IS NULL OR newsletter_issue_id = $1)
+        ORDER BY newsletter_issue_id
+        FOR UPDATE
+        "#,
+        newsletter_issue_id,
+    )
+    .fetch_all(&mut *transaction)
+    .await?;
+    let issue_ids: Vec<Uuid> = issues.iter().map(|i| i.newsletter_issue_id).collect();
+    // counters of attempts in flight are updated before their events are committed
+    let in_flight: HashSet<Uuid> = sqlx::query!(
+        r#"
+        SELECT DISTINCT newsletter_issue_id
+        FROM delivery_attempts
+        WHERE
+            in_flight AND
+            newsletter_issue_id = ANY($1)
+        "#,
+        &issue_ids,
+    )
+    .fetch_all(&mut *transaction)
+    .await?
+    .into_iter()
+    .map(|r| r.newsletter_issue_id)
+    .collect();
+    let logged = sqlx::query!(
+        r#"
+        WITH last_delivery AS (
+            SELECT DISTINCT ON (newsletter_issue_id, subscriber_id)
+                newsletter_issue_id, subscriber_id, kind
+            FROM subscriber_events
+            WHERE
+                newsletter_issue_id = ANY($1) AND
+                kind IN ('received_issue', 'delivery_failed')
+            ORDER BY newsletter_issue_id, subscriber_id, occurred_at DESC, event_id DESC
+        ),
+        outcome AS (
+            SELECT newsletter_issue_id, subscriber_id, kind = 'received_issue' AS delivered
+            FROM last_delivery
+            UNION ALL
+            SELECT d.newsletter_issue_id, d.user_id, FALSE
+            FROM delivery_dead_letters d
+            WHERE
+                d.newsletter_issue_id = ANY($1) AND
+                NOT EXISTS (
+                    SELECT 1
+                    FROM last_delivery l
+                    WHERE
+                        l.newsletter_issue_id = d.newsletter_issue_id AND
+                        l.subscriber_id = d.user_id
+                )
+        )
+        SELECT
+            o.newsletter_issue_id AS "newsletter_issue_id!",
+            COUNT(*) FILTER (WHERE o.delivered AND q.user_id IS NULL) AS "num_delivered!",
+            COUNT(*) FILTER (WHERE NOT o.delivered AND q.user_id IS NULL) AS "num_failed!"
+        FROM outcome o
+        LEFT JOIN issue_delivery_queue q ON
+            q.newsletter_issue_id = o.newsletter_issue_id AND
+            q.user_id = o.subscriber_id
+        GROUP BY o.newsletter_issue_id
+        "#,
+        &issue_ids,
+    )
+    .fetch_all(&mut *transaction)
+    .await?;
+
+    let mut report = BackfillReport {
+        dry_run,
+        num_checked: issues.len(),
+        corrections: Vec::new(),
+        skipped_in_flight: Vec::new(),
+        num_skipped_without_log: 0,
+    };
+    for issue in issues {
+        if in_flight.contains(&issue.newsletter_issue_id) {
+            report.skipped_in_flight.push(issue.newsletter_issue_id);
+            continue;
+        }
+        let Some(log) = logged
+            .iter()
+            .find(|l| l.newsletter_issue_id == issue.newsletter_issue_id)
+        else {
+            report.num_skipped_without_log += 1;
+            continue;
+        };
+        let correction = StatsCorrection {
+            newsletter_issue_id: issue.newsletter_issue_id,
+            title: issue.title,
+            recorded_delivered: issue.num_delivered_newsletters.unwrap_or(0),
+            recorded_failed: issue.num_failed_deliveries.unwrap_or(0),
+            num_delivered: log.num_delivered as i32,
+            num_failed: log.num_failed as i32,
+        };
+        if correction.recorded_delivered != correction.num_delivered
+            || correction.recorded_failed != correction.num_failed
+        {
+            report.corrections.push(correction);
+        }
+    }
+    if dry_run {
+        transaction.rollback().await?;
+        return Ok(report);
+    }
+    for c in report.corrections.iter() {
+        sqlx::query!(
+            r#"
+            UPDATE newsletter_issues
+            SET
+                num_delivered_newsletters = $2,
+                num_failed_deliveries = $3
+            WHERE newsletter_issue_id = $1
+            "#,
+            c.newsletter_issue_id,
+            c.num_delivered,
+            c.num_failed,
+        )
+        .execute(&mut *transaction)
+        .await?;
+        tracing::warn!(
+            newsletter_issue_id = %c.newsletter_issue_id,
+            recorded_delivered = c.recorded_delivered,
+            recorded_failed = c.recorded_failed,
+            num_delivered = c.num_delivered,
+            num_failed = c.num_failed,
+            "Corrected drifted delivery counters of issue.",
+        );
+    }
+    transaction.commit().await?;
+    Ok(report)
+}
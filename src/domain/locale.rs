@@ -0,0 +1,93 @@
+//! src/domain/locale.rs
+
+use crate::domain::ValidationError;
+use std::borrow::Borrow;
+
+/// Language tag like `de` or `pt-br`, stored in lower case.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Deserialize)]
+#[serde(try_from = "String")]
+pub struct Locale(String);
+
+impl AsRef<str> for Locale {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// allows lookups of maps with locale keys by `&str`
+impl Borrow<str> for Locale {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for Locale {
+    type Error = ValidationError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Locale::parse(s)
+    }
+}
+
+impl Locale {
+    /// Accepts a primary language of 2 or 3 letters followed by optional
+    /// subtags of 1 to 8 letters or digits, separated by `-` or `_`.
+    pub fn parse(s: String) -> Result<Locale, ValidationError> {
+        let locale = s.trim().to_lowercase().replace('_', "-");
+        let mut subtags = locale.split('-');
+        let is_valid_language = subtags.next().is_some_and(|l| {
+            (2..=3).contains(&l.len()) && l.chars().all(|c| c.is_ascii_lowercase())
+        });
+        let are_valid_subtags = subtags
+            .all(|t| (1..=8).contains(&t.len()) && t.chars().all(|c| c.is_ascii_alphanumeric()));
+        if is_valid_language && are_valid_subtags {
+            Ok(Self(locale))
+        } else {
+            Err(ValidationError::InvalidLocale(s))
+        }
+    }
+
+    /// Locales to look up in order of preference, e.g. `de-at` and `de` for `de-at`.
+    pub fn fallbacks(&self) -> impl Iterator<Item = &str> {
+        let mut end = Some(self.0.len());
+        std::iter::from_fn(move || {
+            let current = &self.0[..end?];
+            end = current.rfind('-');
+            Some(current)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Locale;
+    use claims::{assert_err, assert_ok};
+
+    #[test]
+    fn valid_locales_are_parsed_to_lower_case() {
+        for (input, expected) in [
+            ("de", "de"),
+            ("pt-BR", "pt-br"),
+            ("zh_Hant_TW", "zh-hant-tw"),
+        ] {
+            let locale = assert_ok!(Locale::parse(input.to_string()));
+            assert_eq!(locale.as_ref(), expected);
+        }
+    }
+
+    #[test]
+    fn invalid_locales_are_rejected() {
+        for input in ["", "d", "german", "de-", "de-toolongsubtag", "1e", "de at"] {
+            assert_err!(Locale::parse(input.to_string()));
+        }
+    }
+
+    #[test]
+    fn fallbacks_go_from_specific_to_language() {
+        let locale = Locale::parse("zh-hant-tw".to_string()).unwrap();
+        assert_eq!(
+            locale.fallbacks().collect::<Vec<_>>(),
+            vec!["zh-hant-tw", "zh-hant", "zh"]
+        );
+    }
+}
@@ -1,10 +1,12 @@
 //! src/domain/mod.rs
 
+mod locale;
 mod new_subscriber;
 mod subscriber_email;
 mod subscriber_name;
 mod subscriber_token;
 
+pub use locale::Locale;
 pub use new_subscriber::NewSubscriber;
 pub use subscriber_email::SubscriberEmail;
 pub use subscriber_name::SubscriberName;
@@ -19,4 +21,12 @@ pub enum ValidationError {
     InvalidName(String),
     #[error("`{0}` is not a valid subscriber token.")]
     InvalidToken(String),
+    #[error("The confirmation link with token `{0}` has expired. Please request a new confirmation email.")]
+    ExpiredToken(String),
+    #[error("`{0}` is not a valid locale.")]
+    InvalidLocale(String),
+    #[error("`{0}` is not a known mailing list.")]
+    InvalidList(String),
+    #[error("`{0}` does not receive emails anymore, since emails to it bounced or were reported as spam.")]
+    SuppressedEmail(String),
 }
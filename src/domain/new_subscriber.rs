@@ -1,5 +1,6 @@
 //! src/domain/new_subscriber.rs
 
+use crate::domain::Locale;
 use crate::domain::SubscriberEmail;
 use crate::domain::SubscriberName;
 
@@ -7,4 +8,6 @@ use crate::domain::SubscriberName;
 pub struct NewSubscriber {
     pub email: SubscriberEmail,
     pub name: SubscriberName,
+    /// Preferred language of newsletter issues
+    pub locale: Option<Locale>,
 }
@@ -3,7 +3,7 @@
 use crate::domain::ValidationError;
 use unicode_segmentation::UnicodeSegmentation;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SubscriberName(String);
 
 impl AsRef<str> for SubscriberName {
@@ -4,7 +4,8 @@ use crate::domain::ValidationError;
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
 
-#[derive(serde::Deserialize, Debug, Clone)]
+#[derive(serde::Deserialize, Debug, Clone, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct SubscriberToken {
     subscription_token: String,
 }
@@ -0,0 +1,45 @@
+//! src/email_client/mailgun.rs
+
+use super::EmailMessage;
+use anyhow::Context;
+use reqwest::{
+    multipart::{Form, Part},
+    Client, RequestBuilder,
+};
+use secrecy::{ExposeSecret, Secret};
+
+/// Build request for Mailgun's messages API.
+///
+/// Mailgun expects a multipart form and the sending domain as part of the url.
+/// The sending domain is taken from the sender address.
+pub(super) fn send_email_request(
+    http_client: &Client,
+    base_url: &str,
+    authorization_token: &Secret<String>,
+    message: &EmailMessage<'_>,
+) -> Result<RequestBuilder, anyhow::Error> {
+    let (_, domain) = message
+        .from
+        .rsplit_once('@')
+        .context("Sender address does not contain a domain.")?;
+    let mut form = Form::new()
+        .text("from", message.from.to_owned())
+        .text("to", message.to.to_owned())
+        .text("subject", message.subject.to_owned())
+        .text("text", message.text_content.to_owned())
+        .text("html", message.html_content.to_owned());
+    if let Some(tag) = message.tag {
+        form = form.text("o:tag", tag.to_owned());
+    }
+    for attachment in message.attachments {
+        let part = Part::bytes(attachment.content.clone())
+            .file_name(attachment.file_name.clone())
+            .mime_str(&attachment.content_type)
+            .context("Invalid content type of attachment.")?;
+        form = form.part("attachment", part);
+    }
+    Ok(http_client
+        .post(format!("{}/v3/{}/messages", base_url, domain))
+        .basic_auth("api", Some(authorization_token.expose_secret()))
+        .multipart(form))
+}
@@ -0,0 +1,1019 @@
+//! src/email_client/mod.rs
+
+mod mailgun;
+mod postmark;
+mod sendgrid;
+
+use crate::bounces::BounceKind;
+use crate::domain::SubscriberEmail;
+use crate::error::Z2PResult;
+use anyhow::Context;
+use base64::Engine;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use secrecy::Secret;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Email delivery providers supported by `EmailClient`.
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailProvider {
+    Postmark,
+    SendGrid,
+    Mailgun,
+}
+
+/// `Sandbox` logs emails instead of sending them, e.g. for staging environments.
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailClientMode {
+    #[default]
+    Live,
+    Sandbox,
+}
+
+/// File attached to an email.
+#[derive(Clone, Debug)]
+pub struct Attachment {
+    pub file_name: String,
+    pub content_type: String,
+    pub content: Vec<u8>,
+}
+
+impl Attachment {
+    /// Content as base64, as required by JSON email APIs.
+    fn base64_content(&self) -> String {
+        base64::engine::general_purpose::STANDARD.encode(&self.content)
+    }
+}
+
+/// All data of a single email, independent of the provider's API format.
+struct EmailMessage<'a> {
+    from: &'a str,
+    to: &'a str,
+    subject: &'a str,
+    html_content: &'a str,
+    text_content: &'a str,
+    /// Tag to correlate the message with provider side data (e.g. the newsletter issue).
+    tag: Option<&'a str>,
+    attachments: &'a [Attachment],
+}
+
+/// Connection data of a single email provider.
+struct ProviderEndpoint {
+    provider: EmailProvider,
+    base_url: String,
+    authorization_token: Secret<String>,
+}
+
+/// Circuit breaker state, which selects the provider used for sending.
+struct CircuitBreaker {
+    /// Index of provider currently used for sending.
+    active: usize,
+    /// Number of consecutive failures of active provider.
+    consecutive_failures: u32,
+    /// Time of last failover, used to return to the primary provider.
+    failed_over_at: Option<Instant>,
+}
+
+/// Failure of a single request to an email provider.
+enum SendError {
+    /// Provider did not answer or returned 5xx. Counts for failover.
+    ProviderUnavailable(anyhow::Error),
+    /// Provider rejected request, e.g. because of invalid recipient.
+    Rejected(anyhow::Error),
+}
+
+/// Email rejected by the provider with an error code, which may classify a bounce.
+#[derive(thiserror::Error, Debug)]
+#[error("Email server rejected email with error code {error_code}: {message}")]
+pub struct RejectedEmail {
+    pub error_code: i64,
+    pub message: String,
+    pub bounce: Option<BounceKind>,
+}
+
+impl RejectedEmail {
+    fn from_postmark(error_code: i64, message: String) -> Self {
+        Self {
+            error_code,
+            message,
+            bounce: BounceKind::from_postmark_error_code(error_code),
+        }
+    }
+}
+
+/// Timeouts and connection pool parameters of the HTTP client,
+/// which sends requests to the email server.
+#[derive(Clone, Debug)]
+pub struct HttpClientSettings {
+    /// Timeout of a complete request including the response.
+    pub timeout: Duration,
+    /// Timeout to establish a connection, which fails fast if the server is unreachable.
+    pub connect_timeout: Duration,
+    /// Idle connections in the pool are closed after this time.
+    pub pool_idle_timeout: Duration,
+    pub pool_max_idle_per_host: usize,
+    /// Interval of TCP keep-alive probes on open connections.
+    pub tcp_keepalive: Duration,
+}
+
+impl HttpClientSettings {
+    fn build_client(&self) -> Client {
+        Client::builder()
+            .timeout(self.timeout)
+            .connect_timeout(self.connect_timeout)
+            .pool_idle_timeout(self.pool_idle_timeout)
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .tcp_keepalive(self.tcp_keepalive)
+            .build()
+            .unwrap()
+    }
+}
+
+/// Single email of a batch, see `EmailClient::send_email_batch()`.
+pub struct BatchEmail<'a> {
+    pub recipient: &'a SubscriberEmail,
+    pub subject: &'a str,
+    pub html_content: &'a str,
+    pub text_content: &'a str,
+    pub tag: Option<&'a str>,
+    pub attachments: &'a [Attachment],
+}
+
+pub struct EmailClient {
+    mode: EmailClientMode,
+    sender: SubscriberEmail,
+    http_client: Client,
+    /// Primary provider followed by fallback providers.
+    endpoints: Vec<ProviderEndpoint>,
+    failover_threshold: u32,
+    failover_reset: Duration,
+    circuit_breaker: Mutex<CircuitBreaker>,
+}
+
+impl EmailClient {
+    pub fn new(
+        provider: EmailProvider,
+        base_url: String,
+        sender: SubscriberEmail,
+        authorization_token: Secret<String>,
+        http_settings: &HttpClientSettings,
+    ) -> Self {
+        let http_client = http_settings.build_client();
+        Self {
+            mode: EmailClientMode::Live,
+            sender,
+            http_client,
+            endpoints: vec![ProviderEndpoint {
+                provider,
+                base_url,
+                authorization_token,
+            }],
+            failover_threshold: 3,
+            failover_reset: Duration::from_secs(300),
+            circuit_breaker: Mutex::new(CircuitBreaker {
+                active: 0,
+                consecutive_failures: 0,
+                failed_over_at: None,
+            }),
+        }
+    }
+
+    pub fn with_mode(mut self, mode: EmailClientMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Add a fallback provider, which is used if all providers before it are unavailable.
+    pub fn with_fallback(
+        mut self,
+        provider: EmailProvider,
+        base_url: String,
+        authorization_token: Secret<String>,
+    ) -> Self {
+        self.endpoints.push(ProviderEndpoint {
+            provider,
+            base_url,
+            authorization_token,
+        });
+        self
+    }
+
+    /// Fail over to the next provider after `threshold` consecutive failures of the
+    /// active provider. After `reset` the primary provider is tried again.
+    pub fn with_failover_policy(mut self, threshold: u32, reset: Duration) -> Self {
+        self.failover_threshold = threshold.max(1);
+        self.failover_reset = reset;
+        self
+    }
+
+    /// Provider currently used for sending emails.
+    pub fn active_provider(&self) -> EmailProvider {
+        self.endpoints[self.active_endpoint()].provider
+    }
+
+    /// Check, that the active provider is reachable, without sending an email. Any
+    /// HTTP response counts as reachable, since providers answer unauthenticated
+    /// requests of their base url differently. Sandbox mode does not contact providers.
+    #[tracing::instrument(name = "Probe email provider", skip(self))]
+    pub async fn probe(&self) -> Result<(), anyhow::Error> {
+        if self.mode == EmailClientMode::Sandbox {
+            return Ok(());
+        }
+        let endpoint = &self.endpoints[self.active_endpoint()];
+        self.http_client
+            .get(&endpoint.base_url)
+            .send()
+            .await
+            .with_context(|| format!("Email provider {:?} is unreachable.", endpoint.provider))?;
+        Ok(())
+    }
+
+    pub async fn send_email(
+        &self,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+    ) -> Z2PResult<()> {
+        let message = EmailMessage {
+            from: self.sender.as_ref(),
+            to: recipient.as_ref(),
+            subject,
+            html_content,
+            text_content,
+            tag: None,
+            attachments: &[],
+        };
+        self.send(&message).await
+    }
+
+    /// Send an email from another sender than the configured one, e.g. to verify a
+    /// sender before it replaces the configured one.
+    pub async fn send_email_from(
+        &self,
+        sender: &SubscriberEmail,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+    ) -> Z2PResult<()> {
+        let message = EmailMessage {
+            from: sender.as_ref(),
+            to: recipient.as_ref(),
+            subject,
+            html_content,
+            text_content,
+            tag: None,
+            attachments: &[],
+        };
+        self.send(&message).await
+    }
+
+    /// Send an email tagged with `tag`, which allows to correlate the
+    /// message with the provider's delivery data.
+    pub async fn send_tagged_email(
+        &self,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        tag: &str,
+    ) -> Z2PResult<()> {
+        let message = EmailMessage {
+            from: self.sender.as_ref(),
+            to: recipient.as_ref(),
+            subject,
+            html_content,
+            text_content,
+            tag: Some(tag),
+            attachments: &[],
+        };
+        self.send(&message).await
+    }
+
+    /// Send a batch of emails with as few requests as possible. Returns one result per
+    /// email in order of `emails`. Providers without batch API get one request per email.
+    pub async fn send_email_batch(&self, emails: &[BatchEmail<'_>]) -> Vec<Z2PResult<()>> {
+        let messages: Vec<EmailMessage> = emails
+            .iter()
+            .map(|email| EmailMessage {
+                from: self.sender.as_ref(),
+                to: email.recipient.as_ref(),
+                subject: email.subject,
+                html_content: email.html_content,
+                text_content: email.text_content,
+                tag: email.tag,
+                attachments: email.attachments,
+            })
+            .collect();
+        let mut results = Vec::with_capacity(messages.len());
+        for chunk in postmark::batch_chunks(&messages) {
+            results.extend(self.send_batch(chunk).await);
+        }
+        results
+    }
+
+    #[tracing::instrument(
+        name = "Send email via provider",
+        skip_all,
+        fields(email_provider = tracing::field::Empty)
+    )]
+    async fn send(&self, message: &EmailMessage<'_>) -> Z2PResult<()> {
+        if self.mode == EmailClientMode::Sandbox {
+            tracing::info!(
+                email.from = message.from,
+                email.to = message.to,
+                email.subject = message.subject,
+                email.tag = message.tag,
+                email.html_content = message.html_content,
+                email.text_content = message.text_content,
+                "Sandbox mode: email is logged instead of sent."
+            );
+            return Ok(());
+        }
+        // every provider is tried at most once per message
+        let mut n_attempts = 0;
+        loop {
+            let index = self.active_endpoint();
+            let endpoint = &self.endpoints[index];
+            tracing::Span::current()
+                .record("email_provider", tracing::field::debug(endpoint.provider));
+            n_attempts += 1;
+            match self.send_request(endpoint, message).await {
+                Ok(()) => {
+                    self.record_success(index);
+                    return Ok(());
+                }
+                Err(SendError::ProviderUnavailable(e)) => {
+                    if self.record_failure(index) && n_attempts < self.endpoints.len() {
+                        continue;
+                    }
+                    return Err(e.into());
+                }
+                Err(SendError::Rejected(e)) => return Err(e.into()),
+            }
+        }
+    }
+
+    #[tracing::instrument(
+        name = "Send email batch via provider",
+        skip_all,
+        fields(email_provider = tracing::field::Empty, n_emails = messages.len())
+    )]
+    async fn send_batch(&self, messages: &[EmailMessage<'_>]) -> Vec<Z2PResult<()>> {
+        // every provider is tried at most once per batch
+        let mut n_attempts = 0;
+        loop {
+            let index = self.active_endpoint();
+            let endpoint = &self.endpoints[index];
+            if messages.len() == 1
+                || endpoint.provider != EmailProvider::Postmark
+                || self.mode == EmailClientMode::Sandbox
+            {
+                // no batch API required or available: send (or log) emails one by one
+                let mut results = Vec::with_capacity(messages.len());
+                for message in messages {
+                    results.push(self.send(message).await);
+                }
+                return results;
+            }
+            tracing::Span::current()
+                .record("email_provider", tracing::field::debug(endpoint.provider));
+            n_attempts += 1;
+            match self.send_batch_request(endpoint, messages).await {
+                Ok(results) => {
+                    self.record_success(index);
+                    return results.into_iter().map(|r| r.map_err(Into::into)).collect();
+                }
+                Err(SendError::ProviderUnavailable(e)) => {
+                    if self.record_failure(index) && n_attempts < self.endpoints.len() {
+                        continue;
+                    }
+                    return batch_failure(messages.len(), &e);
+                }
+                Err(SendError::Rejected(e)) => return batch_failure(messages.len(), &e),
+            }
+        }
+    }
+
+    async fn send_request(
+        &self,
+        endpoint: &ProviderEndpoint,
+        message: &EmailMessage<'_>,
+    ) -> Result<(), SendError> {
+        let request = match endpoint.provider {
+            EmailProvider::Postmark => postmark::send_email_request(
+                &self.http_client,
+                &endpoint.base_url,
+                &endpoint.authorization_token,
+                message,
+            ),
+            EmailProvider::SendGrid => sendgrid::send_email_request(
+                &self.http_client,
+                &endpoint.base_url,
+                &endpoint.authorization_token,
+                message,
+            ),
+            EmailProvider::Mailgun => mailgun::send_email_request(
+                &self.http_client,
+                &endpoint.base_url,
+                &endpoint.authorization_token,
+                message,
+            )
+            .map_err(SendError::Rejected)?,
+        };
+        execute_request(request, &format!("`{}`", message.to)).await?;
+        Ok(())
+    }
+
+    async fn send_batch_request(
+        &self,
+        endpoint: &ProviderEndpoint,
+        messages: &[EmailMessage<'_>],
+    ) -> Result<Vec<Result<(), anyhow::Error>>, SendError> {
+        let request = postmark::send_email_batch_request(
+            &self.http_client,
+            &endpoint.base_url,
+            &endpoint.authorization_token,
+            messages,
+        );
+        let recipients = format!("batch of {} emails", messages.len());
+        let response: Vec<postmark::SendEmailBatchResponse> = execute_request(request, &recipients)
+            .await?
+            .json()
+            .await
+            .with_context(|| format!("Failed to parse response for {}.", recipients))
+            .map_err(SendError::Rejected)?;
+        if response.len() != messages.len() {
+            return Err(SendError::Rejected(anyhow::anyhow!(
+                "Response for {} contains {} results.",
+                recipients,
+                response.len()
+            )));
+        }
+        Ok(messages
+            .iter()
+            .zip(response)
+            .map(|(message, result)| {
+                if result.error_code == 0 {
+                    Ok(())
+                } else {
+                    Err(anyhow::Error::new(RejectedEmail::from_postmark(
+                        result.error_code,
+                        result.message,
+                    ))
+                    .context(format!("Failed to send email for `{}`.", message.to)))
+                }
+            })
+            .collect())
+    }
+
+    /// Index of endpoint to use for next request.
+    fn active_endpoint(&self) -> usize {
+        let mut circuit_breaker = self.circuit_breaker.lock().unwrap();
+        if let Some(failed_over_at) = circuit_breaker.failed_over_at {
+            if failed_over_at.elapsed() >= self.failover_reset {
+                // try primary again; a single failure fails over immediately
+                tracing::info!("Trying primary email provider again.");
+                circuit_breaker.active = 0;
+                circuit_breaker.consecutive_failures = self.failover_threshold - 1;
+                circuit_breaker.failed_over_at = None;
+            }
+        }
+        circuit_breaker.active
+    }
+
+    fn record_success(&self, index: usize) {
+        let mut circuit_breaker = self.circuit_breaker.lock().unwrap();
+        if circuit_breaker.active == index {
+            circuit_breaker.consecutive_failures = 0;
+        }
+    }
+
+    /// Count failure of endpoint and fail over, if threshold is reached.
+    /// Returns true, if another endpoint is active now.
+    fn record_failure(&self, index: usize) -> bool {
+        let mut circuit_breaker = self.circuit_breaker.lock().unwrap();
+        if circuit_breaker.active != index {
+            // concurrent request already failed over
+            return true;
+        }
+        circuit_breaker.consecutive_failures += 1;
+        if circuit_breaker.consecutive_failures < self.failover_threshold
+            || self.endpoints.len() < 2
+        {
+            return false;
+        }
+        circuit_breaker.active = (index + 1) % self.endpoints.len();
+        circuit_breaker.consecutive_failures = 0;
+        circuit_breaker.failed_over_at = Some(Instant::now());
+        tracing::warn!(
+            from_provider = ?self.endpoints[index].provider,
+            to_provider = ?self.endpoints[circuit_breaker.active].provider,
+            "Email provider failover."
+        );
+        true
+    }
+}
+
+/// Send request to email server and check status of response.
+async fn execute_request(request: RequestBuilder, recipients: &str) -> Result<Response, SendError> {
+    let response = request
+        .send()
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to send email request for {} to email server.",
+                recipients
+            )
+        })
+        .map_err(SendError::ProviderUnavailable)?;
+    if response.status() == StatusCode::UNPROCESSABLE_ENTITY {
+        // Postmark explains rejected emails with an error code
+        let rejection = match response.json::<postmark::SendEmailBatchResponse>().await {
+            Ok(r) => anyhow::Error::new(RejectedEmail::from_postmark(r.error_code, r.message)),
+            Err(e) => anyhow::Error::new(e),
+        };
+        return Err(SendError::Rejected(rejection.context(format!(
+            "Email server rejected email request for {}.",
+            recipients
+        ))));
+    }
+    let is_server_error = response.status().is_server_error();
+    response
+        .error_for_status()
+        .with_context(|| {
+            format!(
+                "Response of email request for {} to email server returned an error.",
+                recipients
+            )
+        })
+        .map_err(|e| {
+            if is_server_error {
+                SendError::ProviderUnavailable(e)
+            } else {
+                SendError::Rejected(e)
+            }
+        })
+}
+
+/// Failure of a whole batch request, reported for every email of the batch.
+fn batch_failure(n_emails: usize, e: &anyhow::Error) -> Vec<Z2PResult<()>> {
+    (0..n_emails)
+        .map(|_| Err(anyhow::anyhow!("Email batch request failed: {:#}", e).into()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SubscriberEmail;
+    use super::{BatchEmail, EmailClient, EmailClientMode, EmailProvider, HttpClientSettings};
+    use claims::{assert_err, assert_ok};
+    use fake::faker::internet::en::SafeEmail;
+    use fake::faker::lorem::en::{Paragraph, Sentence};
+    use fake::{Fake, Faker};
+    use secrecy::Secret;
+    use std::time::Duration;
+    use wiremock::matchers::{any, header, header_exists, method, path, path_regex};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// Generate a random email subject
+    fn subject() -> String {
+        Sentence(1..2).fake()
+    }
+
+    /// Generate a random email content
+    fn content() -> String {
+        Paragraph(1..2).fake()
+    }
+
+    /// Generate a random subscriber email
+    fn email() -> SubscriberEmail {
+        SubscriberEmail::parse(SafeEmail().fake()).unwrap()
+    }
+
+    /// Get a test instance of EmailClient
+    fn email_client(base_url: String) -> EmailClient {
+        email_client_of_provider(EmailProvider::Postmark, base_url)
+    }
+
+    /// Get a test instance of EmailClient using the given provider
+    fn email_client_of_provider(provider: EmailProvider, base_url: String) -> EmailClient {
+        EmailClient::new(
+            provider,
+            base_url,
+            email(),
+            Secret::new(Faker.fake()),
+            &HttpClientSettings {
+                timeout: Duration::from_millis(200),
+                connect_timeout: Duration::from_millis(100),
+                pool_idle_timeout: Duration::from_secs(90),
+                pool_max_idle_per_host: 10,
+                tcp_keepalive: Duration::from_secs(60),
+            },
+        )
+    }
+
+    struct SendEmailBodyMatcher;
+
+    impl wiremock::Match for SendEmailBodyMatcher {
+        fn matches(&self, request: &wiremock::Request) -> bool {
+            // Try to parse the body as a JSON value
+            let result: Result<serde_json::Value, _> = serde_json::from_slice(&request.body);
+            if let Ok(body) = result {
+                // Check all mandatory fields without checking thier values
+                body.get("From").is_some()
+                    && body.get("To").is_some()
+                    && body.get("Subject").is_some()
+                    && body.get("HtmlBody").is_some()
+                    && body.get("TextBody").is_some()
+            } else {
+                // parsing failed
+                false
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn send_email_sends_the_expected_request() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(header_exists("X-Postmark-Server-Token"))
+            .and(header("Content-Type", "application/json"))
+            .and(path("/email"))
+            .and(method("POST"))
+            // use custom matcher
+            .and(SendEmailBodyMatcher)
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let _ = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        // see above Mock....expect(1) for what we are testing
+    }
+
+    struct SendGridBodyMatcher;
+
+    impl wiremock::Match for SendGridBodyMatcher {
+        fn matches(&self, request: &wiremock::Request) -> bool {
+            let result: Result<serde_json::Value, _> = serde_json::from_slice(&request.body);
+            if let Ok(body) = result {
+                // SendGrid demands plain text content before html content
+                body["personalizations"][0]["to"][0].get("email").is_some()
+                    && body["from"].get("email").is_some()
+                    && body.get("subject").is_some()
+                    && body["content"][0]["type"] == "text/plain"
+                    && body["content"][1]["type"] == "text/html"
+            } else {
+                false
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn send_email_with_sendgrid_sends_the_expected_request() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client_of_provider(EmailProvider::SendGrid, mock_server.uri());
+
+        Mock::given(header_exists("Authorization"))
+            .and(header("Content-Type", "application/json"))
+            .and(path("/v3/mail/send"))
+            .and(method("POST"))
+            .and(SendGridBodyMatcher)
+            .respond_with(ResponseTemplate::new(202))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert_ok!(outcome);
+    }
+
+    struct MailgunTagMatcher(String);
+
+    impl wiremock::Match for MailgunTagMatcher {
+        fn matches(&self, request: &wiremock::Request) -> bool {
+            // multipart body contains the tag as field `o:tag`
+            let body = String::from_utf8_lossy(&request.body);
+            body.contains("name=\"o:tag\"")
+                && body.contains(&self.0)
+                && body.contains("name=\"html\"")
+                && body.contains("name=\"text\"")
+        }
+    }
+
+    #[tokio::test]
+    async fn send_tagged_email_with_mailgun_sends_the_expected_request() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client_of_provider(EmailProvider::Mailgun, mock_server.uri());
+        let tag = uuid::Uuid::new_v4().to_string();
+
+        Mock::given(header_exists("Authorization"))
+            .and(path_regex(r"^/v3/[^/]+/messages$"))
+            .and(method("POST"))
+            .and(MailgunTagMatcher(tag.clone()))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_tagged_email(&email(), &subject(), &content(), &content(), &tag)
+            .await;
+
+        // Assert
+        assert_ok!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_succeeds_if_server_returns_200() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert_ok!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_server_returns_500() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert_err!(outcome);
+    }
+
+    /// Get a test instance of EmailClient with a SendGrid fallback for a Postmark primary
+    fn email_client_with_fallback(
+        primary_url: String,
+        fallback_url: String,
+        failover_reset: std::time::Duration,
+    ) -> EmailClient {
+        email_client(primary_url)
+            .with_fallback(
+                EmailProvider::SendGrid,
+                fallback_url,
+                Secret::new(Faker.fake()),
+            )
+            .with_failover_policy(2, failover_reset)
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_over_after_repeated_server_errors() {
+        // Arrange
+        let primary_server = MockServer::start().await;
+        let fallback_server = MockServer::start().await;
+        let email_client = email_client_with_fallback(
+            primary_server.uri(),
+            fallback_server.uri(),
+            std::time::Duration::from_secs(300),
+        );
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(503))
+            .expect(2)
+            .mount(&primary_server)
+            .await;
+        Mock::given(path("/v3/mail/send"))
+            .respond_with(ResponseTemplate::new(202))
+            .expect(2)
+            .mount(&fallback_server)
+            .await;
+
+        // Act
+        let first = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+        // second failure of primary opens the circuit and is sent with fallback
+        let second = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+        let third = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert_err!(first);
+        assert_ok!(second);
+        assert_ok!(third);
+        assert_eq!(email_client.active_provider(), EmailProvider::SendGrid);
+    }
+
+    #[tokio::test]
+    async fn send_email_does_not_fail_over_if_request_is_rejected() {
+        // Arrange
+        let primary_server = MockServer::start().await;
+        let fallback_server = MockServer::start().await;
+        let email_client = email_client_with_fallback(
+            primary_server.uri(),
+            fallback_server.uri(),
+            std::time::Duration::from_secs(300),
+        );
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(422))
+            .expect(3)
+            .mount(&primary_server)
+            .await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(202))
+            .expect(0)
+            .mount(&fallback_server)
+            .await;
+
+        // Act & Assert
+        for _ in 0..3 {
+            let outcome = email_client
+                .send_email(&email(), &subject(), &content(), &content())
+                .await;
+            assert_err!(outcome);
+        }
+        assert_eq!(email_client.active_provider(), EmailProvider::Postmark);
+    }
+
+    #[tokio::test]
+    async fn primary_provider_is_used_again_after_failover_reset() {
+        // Arrange
+        let primary_server = MockServer::start().await;
+        let fallback_server = MockServer::start().await;
+        let email_client = email_client_with_fallback(
+            primary_server.uri(),
+            fallback_server.uri(),
+            std::time::Duration::from_millis(100),
+        );
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&primary_server)
+            .await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&primary_server)
+            .await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(202))
+            .expect(1)
+            .mount(&fallback_server)
+            .await;
+        for _ in 0..2 {
+            let _ = email_client
+                .send_email(&email(), &subject(), &content(), &content())
+                .await;
+        }
+        assert_eq!(email_client.active_provider(), EmailProvider::SendGrid);
+
+        // Act
+        tokio::time::sleep(std::time::Duration::from_millis(150)).await;
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert_ok!(outcome);
+        assert_eq!(email_client.active_provider(), EmailProvider::Postmark);
+    }
+
+    #[tokio::test]
+    async fn sandbox_mode_does_not_send_emails() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri()).with_mode(EmailClientMode::Sandbox);
+        let recipients = [email(), email()];
+        let (subject, content) = (subject(), content());
+        let batch: Vec<BatchEmail> = recipients
+            .iter()
+            .map(|recipient| BatchEmail {
+                recipient,
+                subject: &subject,
+                html_content: &content,
+                text_content: &content,
+                tag: None,
+                attachments: &[],
+            })
+            .collect();
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(&email(), &subject, &content, &content)
+            .await;
+        let batch_outcome = email_client.send_email_batch(&batch).await;
+
+        // Assert
+        assert_ok!(outcome);
+        assert_eq!(batch_outcome.len(), 2);
+        assert!(batch_outcome.iter().all(|r| r.is_ok()));
+    }
+
+    #[tokio::test]
+    async fn probe_counts_any_response_as_reachable() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client.probe().await;
+
+        // Assert
+        assert_ok!(outcome);
+    }
+
+    #[tokio::test]
+    async fn probe_fails_if_provider_is_unreachable() {
+        // Arrange
+        // nothing listens on a released port
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let email_client = email_client(format!("http://127.0.0.1:{}", port));
+
+        // Act
+        let outcome = email_client.probe().await;
+
+        // Assert
+        assert_err!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_times_out_if_the_server_takes_too_long() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        let response = ResponseTemplate::new(200)
+            // 3 Minutes!
+            .set_delay(std::time::Duration::from_secs(180));
+
+        Mock::given(any())
+            .respond_with(response)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert_err!(outcome);
+    }
+}
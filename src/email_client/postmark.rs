@@ -0,0 +1,131 @@
+//! src/email_client/postmark.rs
+
+use super::EmailMessage;
+use reqwest::{Client, RequestBuilder};
+use secrecy::{ExposeSecret, Secret};
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct SendEmailRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    subject: &'a str,
+    html_body: &'a str,
+    text_body: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tag: Option<&'a str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<PostmarkAttachment<'a>>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct PostmarkAttachment<'a> {
+    name: &'a str,
+    content: String,
+    content_type: &'a str,
+}
+
+impl<'a> From<&'a EmailMessage<'a>> for SendEmailRequest<'a> {
+    fn from(message: &'a EmailMessage<'a>) -> Self {
+        Self {
+            from: message.from,
+            to: message.to,
+            subject: message.subject,
+            html_body: message.html_content,
+            text_body: message.text_content,
+            tag: message.tag,
+            attachments: message
+                .attachments
+                .iter()
+                .map(|attachment| PostmarkAttachment {
+                    name: &attachment.file_name,
+                    content: attachment.base64_content(),
+                    content_type: &attachment.content_type,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Build request for Postmark's single email API.
+pub(super) fn send_email_request(
+    http_client: &Client,
+    base_url: &str,
+    authorization_token: &Secret<String>,
+    message: &EmailMessage<'_>,
+) -> RequestBuilder {
+    let request_body = SendEmailRequest::from(message);
+    http_client
+        .post(format!("{}/email", base_url))
+        .header(
+            "X-Postmark-Server-Token",
+            authorization_token.expose_secret(),
+        )
+        .header("Accept", "application/json")
+        .json(&request_body)
+}
+
+/// Maximum number of messages Postmark accepts in a single batch request.
+const MAX_BATCH_SIZE: usize = 500;
+/// Maximum payload size of a batch request, including base64 encoded attachments.
+const MAX_BATCH_BYTES: usize = 50 * 1024 * 1024;
+
+/// Split messages into chunks, which are within Postmark's limits of a batch request.
+pub(super) fn batch_chunks<'m, 'a>(
+    messages: &'m [EmailMessage<'a>],
+) -> Vec<&'m [EmailMessage<'a>]> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut chunk_bytes = 0;
+    for (index, message) in messages.iter().enumerate() {
+        // base64 encoding increases size of attachments by 4/3
+        let message_bytes = message.html_content.len()
+            + message.text_content.len()
+            + message
+                .attachments
+                .iter()
+                .map(|a| a.content.len() * 4 / 3)
+                .sum::<usize>();
+        if index > start
+            && (index - start == MAX_BATCH_SIZE || chunk_bytes + message_bytes > MAX_BATCH_BYTES)
+        {
+            chunks.push(&messages[start..index]);
+            start = index;
+            chunk_bytes = 0;
+        }
+        chunk_bytes += message_bytes;
+    }
+    if start < messages.len() {
+        chunks.push(&messages[start..]);
+    }
+    chunks
+}
+
+/// Build request for Postmark's batch email API.
+pub(super) fn send_email_batch_request(
+    http_client: &Client,
+    base_url: &str,
+    authorization_token: &Secret<String>,
+    messages: &[EmailMessage<'_>],
+) -> RequestBuilder {
+    let request_body: Vec<SendEmailRequest> = messages.iter().map(SendEmailRequest::from).collect();
+    http_client
+        .post(format!("{}/email/batch", base_url))
+        .header(
+            "X-Postmark-Server-Token",
+            authorization_token.expose_secret(),
+        )
+        .header("Accept", "application/json")
+        .json(&request_body)
+}
+
+/// Result of a single message of a batch request. Postmark answers a batch
+/// request with one entry per message in order of the request.
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub(super) struct SendEmailBatchResponse {
+    pub(super) error_code: i64,
+    #[serde(default)]
+    pub(super) message: String,
+}
@@ -0,0 +1,85 @@
+//! src/email_client/sendgrid.rs
+
+use super::EmailMessage;
+use reqwest::{Client, RequestBuilder};
+use secrecy::{ExposeSecret, Secret};
+
+#[derive(serde::Serialize)]
+struct SendEmailRequest<'a> {
+    personalizations: [Personalization<'a>; 1],
+    from: Address<'a>,
+    subject: &'a str,
+    content: [Content<'a>; 2],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    categories: Option<[&'a str; 1]>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<SendGridAttachment<'a>>,
+}
+
+#[derive(serde::Serialize)]
+struct SendGridAttachment<'a> {
+    content: String,
+    filename: &'a str,
+    #[serde(rename = "type")]
+    mime_type: &'a str,
+}
+
+#[derive(serde::Serialize)]
+struct Personalization<'a> {
+    to: [Address<'a>; 1],
+}
+
+#[derive(serde::Serialize)]
+struct Address<'a> {
+    email: &'a str,
+}
+
+#[derive(serde::Serialize)]
+struct Content<'a> {
+    #[serde(rename = "type")]
+    mime_type: &'a str,
+    value: &'a str,
+}
+
+/// Build request for SendGrid's v3 mail send API.
+pub(super) fn send_email_request(
+    http_client: &Client,
+    base_url: &str,
+    authorization_token: &Secret<String>,
+    message: &EmailMessage<'_>,
+) -> RequestBuilder {
+    let request_body = SendEmailRequest {
+        personalizations: [Personalization {
+            to: [Address { email: message.to }],
+        }],
+        from: Address {
+            email: message.from,
+        },
+        subject: message.subject,
+        // SendGrid requires text/plain to be listed before text/html
+        content: [
+            Content {
+                mime_type: "text/plain",
+                value: message.text_content,
+            },
+            Content {
+                mime_type: "text/html",
+                value: message.html_content,
+            },
+        ],
+        categories: message.tag.map(|t| [t]),
+        attachments: message
+            .attachments
+            .iter()
+            .map(|attachment| SendGridAttachment {
+                content: attachment.base64_content(),
+                filename: &attachment.file_name,
+                mime_type: &attachment.content_type,
+            })
+            .collect(),
+    };
+    http_client
+        .post(format!("{}/v3/mail/send", base_url))
+        .bearer_auth(authorization_token.expose_secret())
+        .json(&request_body)
+}
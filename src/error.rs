@@ -1,10 +1,12 @@
 //! src/app_error.rs
 
+use crate::api_rate_limit::RateLimitStatus;
 use crate::authentication::CredentialsError;
 use crate::domain::ValidationError;
 use crate::routes::NewsletterError;
 use crate::session_state::SessionError;
 use crate::utils::see_other;
+use actix_web::{http::header::WWW_AUTHENTICATE, HttpResponse};
 use actix_web_flash_messages::FlashMessage;
 
 pub type Z2PResult<T> = Result<T, Error>;
@@ -28,6 +30,8 @@ pub enum Error {
     SubscriptionError(#[from] ValidationError),
     #[error("Failed Login Authentication")]
     LoginError,
+    #[error("Too many failed login attempts. Please try again in {0} seconds.")]
+    LoginLockedOut(i64),
     #[error("Failure changing password")]
     PasswordChangingError(#[from] CredentialsError),
     #[error("Invalid input for Newsletter")]
@@ -36,6 +40,18 @@ pub enum Error {
     SessionStateError(#[from] SessionError),
     #[error("Wrong format of idempotency key")]
     IdempotencyKeyError,
+    #[error("The idempotency key has already been used for a request with other content")]
+    IdempotencyKeyReused,
+    #[error("The requested resource could not be found")]
+    NotFound,
+    #[error("You are not allowed to access the requested resource")]
+    Forbidden,
+    #[error("Invalid webhook credentials")]
+    WebhookAuthError,
+    #[error("Invalid API key")]
+    ApiAuthError,
+    #[error("API rate limit exceeded")]
+    ApiRateLimitExceeded(RateLimitStatus),
     #[error(transparent)]
     UnexpectedError(#[from] anyhow::Error),
 }
@@ -52,15 +68,39 @@ impl From<Error> for actix_web::Error {
             Error::SubscriptionError(ref valerr) => {
                 FlashMessage::error(valerr.to_string()).send();
                 let response = match valerr {
-                    ValidationError::InvalidEmail(_) | ValidationError::InvalidName(_) => {
-                        see_other("/subscriptions")
+                    ValidationError::InvalidEmail(_)
+                    | ValidationError::InvalidName(_)
+                    | ValidationError::InvalidLocale(_)
+                    | ValidationError::InvalidList(_)
+                    | ValidationError::SuppressedEmail(_) => see_other("/subscriptions"),
+                    ValidationError::InvalidToken(_) | ValidationError::ExpiredToken(_) => {
+                        see_other("/subscriptions/token")
                     }
-                    ValidationError::InvalidToken(_) => see_other("/subscriptions/token"),
                 };
                 actix_web::error::InternalError::from_response(err, response).into()
             }
             Error::IdempotencyKeyError => actix_web::error::ErrorBadRequest(err),
-            Error::LoginError | Error::SessionStateError(_) => {
+            Error::IdempotencyKeyReused => actix_web::error::ErrorUnprocessableEntity(err),
+            Error::NotFound => actix_web::error::ErrorNotFound(err),
+            Error::Forbidden => actix_web::error::ErrorForbidden(err),
+            Error::WebhookAuthError => {
+                let response = HttpResponse::Unauthorized()
+                    .insert_header((WWW_AUTHENTICATE, r#"Basic realm="webhooks""#))
+                    .finish();
+                actix_web::error::InternalError::from_response(err, response).into()
+            }
+            Error::ApiAuthError => {
+                let response = HttpResponse::Unauthorized()
+                    .insert_header((WWW_AUTHENTICATE, r#"Bearer realm="api""#))
+                    .finish();
+                actix_web::error::InternalError::from_response(err, response).into()
+            }
+            Error::ApiRateLimitExceeded(status) => {
+                let mut response = HttpResponse::TooManyRequests().finish();
+                status.insert_headers(response.headers_mut());
+                actix_web::error::InternalError::from_response(err, response).into()
+            }
+            Error::LoginError | Error::LoginLockedOut(_) | Error::SessionStateError(_) => {
                 FlashMessage::error(err.to_string()).send();
                 let response = see_other("/login");
                 actix_web::error::InternalError::from_response(err, response).into()
@@ -0,0 +1,42 @@
+//! src/event_export/kafka.rs
+
+use super::EventSink;
+use anyhow::Context;
+use rdkafka::{
+    producer::{FutureProducer, FutureRecord},
+    ClientConfig,
+};
+use std::time::Duration;
+
+/// Publishes events to Kafka. Events are acknowledged after all in-sync replicas
+/// received them.
+pub struct KafkaEventSink {
+    producer: FutureProducer,
+}
+
+impl KafkaEventSink {
+    pub fn new(brokers: &str) -> Result<Self, anyhow::Error> {
+        let producer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("acks", "all")
+            .set("enable.idempotence", "true")
+            .set("message.timeout.ms", "30000")
+            .create()
+            .context("Failed to create Kafka producer.")?;
+        Ok(Self { producer })
+    }
+}
+
+impl EventSink for KafkaEventSink {
+    async fn publish(&self, topic: &str, key: &str, payload: &[u8]) -> Result<(), anyhow::Error> {
+        self.producer
+            .send(
+                FutureRecord::to(topic).key(key).payload(payload),
+                Duration::from_secs(5),
+            )
+            .await
+            .map_err(|(e, _)| e)
+            .context("Failed to publish event to Kafka.")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,183 @@
+//! src/event_export/mod.rs
+
+#[cfg(feature = "kafka")]
+mod kafka;
+#[cfg(feature = "nats")]
+mod nats;
+
+#[cfg(feature = "kafka")]
+pub use kafka::KafkaEventSink;
+#[cfg(feature = "nats")]
+pub use nats::NatsEventSink;
+
+use crate::{
+    configuration::{EventExportSettings, EventSinkSettings, Settings},
+    error::Z2PResult,
+    subscriber_events::SubscriberEventKind,
+    worker_heartbeat::WorkerHeartbeat,
+};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::{future::Future, time::Duration};
+use uuid::Uuid;
+
+/// Broker, which receives exported subscriber events.
+pub trait EventSink: Send + Sync {
+    /// Publish `payload` to `topic` and return after the broker acknowledged it.
+    /// `key` keeps events of one subscriber in order on partitioned topics.
+    fn publish(
+        &self,
+        topic: &str,
+        key: &str,
+        payload: &[u8],
+    ) -> impl Future<Output = Result<(), anyhow::Error>> + Send;
+}
+
+/// Payload of an exported event, serialized as JSON.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct ExportedEvent {
+    pub event_id: i64,
+    pub kind: SubscriberEventKind,
+    pub subscriber_id: Uuid,
+    pub newsletter_issue_id: Option<Uuid>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+pub async fn run_event_export_worker_until_stopped(configuration: Settings) -> Z2PResult<()> {
+    let Some(settings) = configuration.event_export else {
+        // export is disabled; never finish, since main stops at the first finished task
+        return std::future::pending().await;
+    };
+    match settings.sink {
+        #[cfg(feature = "kafka")]
+        EventSinkSettings::Kafka { ref brokers } => {
+            let sink = KafkaEventSink::new(brokers)?;
+            worker_loop(
+                crate::startup::get_connection_pool(&configuration.database),
+                sink,
+                settings,
+            )
+            .await
+        }
+        #[cfg(not(feature = "kafka"))]
+        EventSinkSettings::Kafka { .. } => Err(missing_feature("kafka"))?,
+        #[cfg(feature = "nats")]
+        EventSinkSettings::Nats { ref url } => {
+            let sink = NatsEventSink::connect(url).await?;
+            worker_loop(
+                crate::startup::get_connection_pool(&configuration.database),
+                sink,
+                settings,
+            )
+            .await
+        }
+        #[cfg(not(feature = "nats"))]
+        EventSinkSettings::Nats { .. } => Err(missing_feature("nats"))?,
+    }
+}
+
+#[cfg_attr(all(feature = "kafka", feature = "nats"), allow(dead_code))]
+fn missing_feature(feature: &str) -> anyhow::Error {
+    anyhow::anyhow!(
+        "Event export to {} requires a build with cargo feature `{}`.",
+        feature,
+        feature
+    )
+}
+
+#[cfg_attr(not(any(feature = "kafka", feature = "nats")), allow(dead_code))]
+async fn worker_loop(
+    pool: PgPool,
+    sink: impl EventSink,
+    settings: EventExportSettings,
+) -> Z2PResult<()> {
+    let poll_interval = Duration::from_secs(settings.poll_interval_seconds);
+    // beats at least every poll interval; stale after one minute or three missed polls
+    let mut heartbeat = WorkerHeartbeat::new(
+        "event_export",
+        3 * poll_interval.max(Duration::from_secs(20)),
+    );
+    loop {
+        let outcome = export_pending_events(&pool, &sink, &settings).await;
+        heartbeat.beat(&pool, *outcome.as_ref().unwrap_or(&0)).await;
+        match outcome {
+            // a full batch indicates more pending events
+            Ok(n) if n >= settings.batch_size as u64 => {}
+            Ok(_) => tokio::time::sleep(poll_interval).await,
+            Err(_) => {
+                // sleep and try to recover from transient errors of database or broker
+                tokio::time::sleep(poll_interval).await;
+            }
+        }
+    }
+}
+
+/// Export a batch of not yet exported events in order of their id and return the
+/// number of processed events. Events are marked as exported after the sink
+/// acknowledged them, therefore an event may be published again after a crash
+/// (at-least-once delivery). Events without topic are marked without publishing.
+#[tracing::instrument(name = "Export subscriber events", skip_all, err)]
+pub async fn export_pending_events(
+    pool: &PgPool,
+    sink: &impl EventSink,
+    settings: &EventExportSettings,
+) -> Result<u64, anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    // skip locked events of a concurrent exporter, e.g. during a rolling deployment
+    let events = sqlx::query_as!(
+        ExportedEvent,
+        r#"
+        SELECT event_id, kind AS "kind: SubscriberEventKind", subscriber_id,
+            newsletter_issue_id, occurred_at
+        FROM subscriber_events
+        WHERE exported_at IS NULL
+        ORDER BY event_id
+        LIMIT $1
+        FOR UPDATE
+        SKIP LOCKED
+        "#,
+        settings.batch_size.max(1) as i64,
+    )
+    .fetch_all(&mut *transaction)
+    .await
+    .context("Failed to fetch subscriber events to export.")?;
+
+    let mut processed = Vec::with_capacity(events.len());
+    let mut publish_error = None;
+    for event in events.iter() {
+        if let Some(topic) = settings.topic(event.kind) {
+            let payload =
+                serde_json::to_vec(event).context("Failed to serialize subscriber event.")?;
+            if let Err(e) = sink
+                .publish(topic, &event.subscriber_id.to_string(), &payload)
+                .await
+            {
+                // keep order: later events are exported after this one in next batch
+                publish_error = Some(e);
+                break;
+            }
+        }
+        processed.push(event.event_id);
+    }
+
+    sqlx::query!(
+        "UPDATE subscriber_events SET exported_at = now() WHERE event_id = ANY($1)",
+        &processed,
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to mark subscriber events as exported.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to mark exported subscriber events.")?;
+
+    match publish_error {
+        Some(e) => Err(e.context("Failed to publish subscriber event.")),
+        None => Ok(processed.len() as u64),
+    }
+}
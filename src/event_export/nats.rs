@@ -0,0 +1,34 @@
+//! src/event_export/nats.rs
+
+use super::EventSink;
+use anyhow::Context;
+use async_nats::jetstream;
+
+/// Publishes events to NATS JetStream. A stream must capture the configured subjects,
+/// otherwise publishing is not acknowledged and fails.
+pub struct NatsEventSink {
+    jetstream: jetstream::Context,
+}
+
+impl NatsEventSink {
+    pub async fn connect(url: &str) -> Result<Self, anyhow::Error> {
+        let client = async_nats::connect(url)
+            .await
+            .context("Failed to connect to NATS.")?;
+        Ok(Self {
+            jetstream: jetstream::new(client),
+        })
+    }
+}
+
+impl EventSink for NatsEventSink {
+    async fn publish(&self, topic: &str, _key: &str, payload: &[u8]) -> Result<(), anyhow::Error> {
+        self.jetstream
+            .publish(topic.to_string(), payload.to_vec().into())
+            .await
+            .context("Failed to publish event to NATS.")?
+            .await
+            .context("NATS did not acknowledge event.")?;
+        Ok(())
+    }
+}
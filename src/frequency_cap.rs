@@ -0,0 +1,149 @@
+//! src/frequency_cap.rs
+
+use crate::delivery_queue::{DeliveryQueue, PgDeliveryQueue};
+use crate::startup::ExternalDeliveryQueue;
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::collections::{HashMap, HashSet};
+use tracing::field::Empty;
+use tracing::Span;
+use uuid::Uuid;
+
+/// Sends within this number of days count against the cap.
+const CAP_PERIOD_DAYS: i32 = 7;
+
+/// Remove subscribers, who reached the weekly cap of emails, from the recipients of an
+/// issue and record their skipped sends. The cap of a subscriber is the lower of the
+/// configured cap and the maximum, which the subscriber chose in the preference center.
+/// Every subsystem, which enqueues newsletter emails, must pass its recipients through
+/// this function. Counted are emails received within the last seven days and tasks of
+/// other issues in the configured delivery queue, which still may be sent.
+#[tracing::instrument(skip(transaction, external_queue, subscriber_ids), fields(n_skipped = Empty))]
+pub async fn apply_frequency_cap(
+    transaction: &mut Transaction<'_, Postgres>,
+    external_queue: &ExternalDeliveryQueue,
+    max_emails_per_week: Option<u32>,
+    newsletter_issue_id: Uuid,
+    subscriber_ids: Vec<Uuid>,
+) -> Result<Vec<Uuid>, anyhow::Error> {
+    // the transaction sees tasks of issues, which are published with it
+    let pending_tasks: HashMap<Uuid, u64> = match external_queue.0 {
+        Some(ref queue) => {
+            queue
+                .pending_for(&subscriber_ids, newsletter_issue_id)
+                .await?
+        }
+        None => {
+            PgDeliveryQueue::pending_for_with(
+                &mut **transaction,
+                &subscriber_ids,
+                newsletter_issue_id,
+            )
+            .await?
+        }
+    };
+    let (pending_subscriber_ids, num_pending_tasks): (Vec<Uuid>, Vec<i64>) = pending_tasks
+        .into_iter()
+        .map(|(subscriber_id, num_tasks)| (subscriber_id, num_tasks as i64))
+        .unzip();
+    let capped: HashSet<Uuid> = sqlx::query!(
+        r#"
+        SELECT s.id
+        FROM subscriptions s
+        LEFT JOIN (
+            SELECT subscriber_id, COUNT(*) AS num_received
+            FROM subscriber_events
+            WHERE
+                kind = 'received_issue' AND
+                subscriber_id = ANY($1) AND
+                occurred_at > now() - make_interval(days => $2)
+            GROUP BY subscriber_id
+        ) received ON received.subscriber_id = s.id
+        LEFT JOIN UNNEST($3::uuid[], $4::bigint[]) AS pending(subscriber_id, num_tasks)
+            ON pending.subscriber_id = s.id
+        WHERE
+            s.id = ANY($1) AND
+            COALESCE(received.num_received, 0) + COALESCE(pending.num_tasks, 0)
+                >= LEAST($5::bigint, s.max_emails_per_week)
+        "#,
+        &subscriber_ids,
+        CAP_PERIOD_DAYS,
+        &pending_subscriber_ids,
+        &num_pending_tasks,
+        max_emails_per_week.map(i64::from),
+    )
+    .fetch_all(&mut **transaction)
+    .await
+    .context("Failed to read sends of subscribers.")?
+    .into_iter()
+    .map(|r| r.id)
+    .collect();
+    Span::current().record("n_skipped", capped.len());
+    if capped.is_empty() {
+        return Ok(subscriber_ids);
+    }
+    let capped_ids: Vec<Uuid> = capped.iter().copied().collect();
+    sqlx::query!(
+        r#"
+        INSERT INTO frequency_capped_sends (newsletter_issue_id, subscriber_id, skipped_at)
+        SELECT $1, subscriber_id, now()
+        FROM UNNEST($2::uuid[]) AS subscriber_id
+        ON CONFLICT DO NOTHING
+        "#,
+        newsletter_issue_id,
+        &capped_ids,
+    )
+    .execute(&mut **transaction)
+    .await
+    .context("Failed to record skipped sends.")?;
+    tracing::info!(
+        %newsletter_issue_id,
+        n_skipped = capped_ids.len(),
+        "Skipped sends to subscribers, who reached their frequency cap."
+    );
+    Ok(subscriber_ids
+        .into_iter()
+        .filter(|subscriber_id| !capped.contains(subscriber_id))
+        .collect())
+}
+
+/// Some confirmed subscribers of the list chose a maximum number of emails per week.
+#[tracing::instrument(skip(transaction))]
+pub async fn has_frequency_preferences(
+    transaction: &mut Transaction<'_, Postgres>,
+    list_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1
+            FROM subscriptions
+            WHERE list_id = $1 AND status = 'confirmed' AND max_emails_per_week IS NOT NULL
+        ) AS "exists!"
+        "#,
+        list_id
+    )
+    .fetch_one(&mut **transaction)
+    .await?;
+    Ok(result.exists)
+}
+
+/// Number of subscribers, who did not get the issue because of the frequency cap.
+#[tracing::instrument(skip(pool))]
+pub async fn count_capped_sends(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+) -> Result<i64, sqlx::Error> {
+    let count = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM frequency_capped_sends
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_one(pool)
+    .await?
+    .count;
+    Ok(count)
+}
@@ -1,9 +1,14 @@
 //! src/idempotency/key.rs
 
 use crate::error::{Error, Z2PResult};
+use actix_web::{dev::Payload, FromRequest, HttpRequest};
+use std::future::{ready, Ready};
 use std::str::FromStr;
 use uuid::Uuid;
 
+/// Name of the request header, which carries the idempotency key.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
 #[derive(Debug)]
 pub struct IdempotencyKey(String);
 
@@ -29,3 +34,55 @@ impl AsRef<str> for IdempotencyKey {
         &self.0
     }
 }
+
+/// Idempotency key of the `Idempotency-Key` header, if the request has one.
+/// A header with an invalid key is rejected with 400. Handlers, which also accept
+/// the key as form field, prefer the header and fall back to the field.
+#[derive(Debug)]
+pub struct IdempotencyKeyHeader(Option<IdempotencyKey>);
+
+impl IdempotencyKeyHeader {
+    /// Key of the header, or the key of `form_field`, if the header is missing.
+    pub fn or_form_field(self, form_field: String) -> Z2PResult<IdempotencyKey> {
+        match self.0 {
+            Some(key) => Ok(key),
+            None => form_field.try_into(),
+        }
+    }
+
+    /// Like `or_form_field`, but without key, if both header and form field are empty.
+    pub fn or_optional_form_field(self, form_field: String) -> Z2PResult<Option<IdempotencyKey>> {
+        match self.0 {
+            Some(key) => Ok(Some(key)),
+            None if form_field.trim().is_empty() => Ok(None),
+            None => form_field.try_into().map(Some),
+        }
+    }
+
+    /// Key of the header, if any; requests without key are processed without idempotency.
+    pub fn optional(self) -> Option<IdempotencyKey> {
+        self.0
+    }
+
+    /// Key of the header; it is required by endpoints without form field.
+    pub fn required(self) -> Z2PResult<IdempotencyKey> {
+        self.0.ok_or(Error::IdempotencyKeyError)
+    }
+}
+
+impl FromRequest for IdempotencyKeyHeader {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<IdempotencyKeyHeader, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let key = match req.headers().get(IDEMPOTENCY_KEY_HEADER) {
+            Some(value) => value
+                .to_str()
+                .map_err(|_| Error::IdempotencyKeyError)
+                .and_then(|key| IdempotencyKey::try_from(key.trim().to_string()))
+                .map(Some),
+            None => Ok(None),
+        };
+        ready(key.map(IdempotencyKeyHeader).map_err(Into::into))
+    }
+}
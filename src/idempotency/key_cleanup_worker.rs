@@ -1,39 +1,77 @@
 //! src/idempotency/key_cleanup_worker.rs
 
-use crate::{configuration::Settings, error::Z2PResult, startup::get_connection_pool};
+use crate::{
+    configuration::Settings, error::Z2PResult, startup::get_connection_pool,
+    worker_heartbeat::WorkerHeartbeat,
+};
 use anyhow::Context;
 use sqlx::PgPool;
 use std::time::Duration;
 
+/// Kind of heartbeats of the cleanup worker, which count deleted keys as processed tasks.
+pub const CLEANUP_WORKER_KIND: &str = "idempotency_cleanup";
+
 pub async fn run_cleanup_worker_until_stopped(configuration: Settings) -> Z2PResult<()> {
     let connection_pool = get_connection_pool(&configuration.database);
 
     worker_loop(
         connection_pool,
         configuration.application.idempotency_lifetime_minutes,
+        Duration::from_secs(
+            configuration
+                .application
+                .idempotency_cleanup_interval_seconds,
+        ),
+        configuration.application.idempotency_cleanup_batch_size,
     )
     .await
 }
 
-async fn worker_loop(pool: PgPool, lifetime_minutes: u32) -> Z2PResult<()> {
+async fn worker_loop(
+    pool: PgPool,
+    lifetime_minutes: u32,
+    interval: Duration,
+    batch_size: u32,
+) -> Z2PResult<()> {
+    // missing two beats marks the worker as stalled
+    let mut heartbeat = WorkerHeartbeat::new(CLEANUP_WORKER_KIND, interval * 2);
     loop {
-        delete_outlived_idempotency_key(&pool, lifetime_minutes).await?;
-        tokio::time::sleep(Duration::from_secs(600)).await;
+        let num_deleted =
+            delete_outlived_idempotency_key(&pool, lifetime_minutes, batch_size).await?;
+        heartbeat.beat(&pool, num_deleted).await;
+        tokio::time::sleep(interval).await;
     }
 }
 
+/// Delete outlived idempotency keys in batches of at most `batch_size` rows, each of
+/// them in its own statement; returns the number of deleted keys.
 pub async fn delete_outlived_idempotency_key(
     pool: &PgPool,
     lifetime_minutes: u32,
+    batch_size: u32,
 ) -> Z2PResult<u64> {
-    let query = format!(
-        "DELETE FROM idempotency WHERE created_at < NOW() - INTERVAL '{} minutes'",
-        lifetime_minutes
-    );
-    let delete_result = sqlx::query(&query)
+    let batch_size = batch_size.max(1);
+    let mut num_deleted = 0;
+    loop {
+        let delete_result = sqlx::query!(
+            r#"
+            DELETE FROM idempotency
+            WHERE ctid IN (
+                SELECT ctid
+                FROM idempotency
+                WHERE created_at < NOW() - make_interval(mins => $1)
+                LIMIT $2
+            )
+            "#,
+            lifetime_minutes as i32,
+            i64::from(batch_size)
+        )
         .execute(pool)
         .await
         .context("Could not execute query to delete idempotency keys.")?;
-
-    Ok(delete_result.rows_affected())
+        num_deleted += delete_result.rows_affected();
+        if delete_result.rows_affected() < u64::from(batch_size) {
+            return Ok(num_deleted);
+        }
+    }
 }
@@ -3,7 +3,14 @@
 mod key;
 mod key_cleanup_worker;
 mod persistence;
+mod redis;
 
-pub use key::IdempotencyKey;
-pub use key_cleanup_worker::{delete_outlived_idempotency_key, run_cleanup_worker_until_stopped};
-pub use persistence::{get_saved_response, save_response, try_processing, NextAction};
+pub use self::redis::RedisIdempotencyStore;
+pub use key::{IdempotencyKey, IdempotencyKeyHeader, IDEMPOTENCY_KEY_HEADER};
+pub use key_cleanup_worker::{
+    delete_outlived_idempotency_key, run_cleanup_worker_until_stopped, CLEANUP_WORKER_KIND,
+};
+pub use persistence::{
+    get_saved_response, get_stored_keys, request_hash, save_response, try_processing, NextAction,
+    ProcessingTransaction, StoredKeys, ANONYMOUS_USER_ID,
+};
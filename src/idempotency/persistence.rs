@@ -1,8 +1,13 @@
 //! src/idempotency/persistence.rs
 
-use super::IdempotencyKey;
+use super::redis::Reservation;
+use super::{IdempotencyKey, RedisIdempotencyStore};
 use actix_web::{body::to_bytes, http::StatusCode, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
 use sqlx::{postgres::PgHasArrayType, Executor, PgPool, Postgres, Transaction};
+use std::ops::{Deref, DerefMut};
 use uuid::Uuid;
 
 #[derive(Debug, sqlx::Type)]
@@ -18,10 +23,64 @@ impl PgHasArrayType for HeaderPairRecord {
     }
 }
 
+/// `user_id` of idempotency keys of requests without logged-in user, e.g. subscriptions.
+pub const ANONYMOUS_USER_ID: Uuid = Uuid::nil();
+
 #[allow(clippy::large_enum_variant)]
 pub enum NextAction {
-    StartProcessing(Transaction<'static, Postgres>),
+    StartProcessing(ProcessingTransaction),
     ReturnSavedResponse(HttpResponse),
+    /// The key has been used for a request with another payload.
+    RejectReusedKey,
+}
+
+/// Transaction of a request, which processes its idempotency key. With a Redis store
+/// it carries the token of the reservation of the key.
+pub struct ProcessingTransaction {
+    transaction: Transaction<'static, Postgres>,
+    reservation: Option<String>,
+}
+
+impl Deref for ProcessingTransaction {
+    type Target = Transaction<'static, Postgres>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.transaction
+    }
+}
+
+impl DerefMut for ProcessingTransaction {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.transaction
+    }
+}
+
+/// Idempotency keys stored in Postgres.
+#[derive(Debug)]
+pub struct StoredKeys {
+    pub num_keys: i64,
+    pub oldest_created_at: Option<DateTime<Utc>>,
+}
+
+pub async fn get_stored_keys(pool: &PgPool) -> Result<StoredKeys, sqlx::Error> {
+    sqlx::query_as!(
+        StoredKeys,
+        r#"
+        SELECT
+            COUNT(*) AS "num_keys!",
+            MIN(created_at) AS oldest_created_at
+        FROM idempotency
+        "#
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Hash of the request payload without its idempotency key, which is stored with the
+/// key to detect reuse of the key with another payload.
+pub fn request_hash(payload: &impl serde::Serialize) -> Result<String, anyhow::Error> {
+    let payload = serde_json::to_vec(payload).context("Failed to serialize request payload.")?;
+    Ok(hex::encode(Sha256::digest(payload)))
 }
 
 pub async fn get_saved_response(
@@ -57,15 +116,18 @@ pub async fn get_saved_response(
     }
 }
 
+/// Save response of request with idempotency key and commit the transaction of
+/// request. With a Redis store the response is saved in Redis after the commit; the
+/// transaction is only committed, if the key is still reserved by the request.
 pub async fn save_response(
-    mut transaction: Transaction<'static, Postgres>,
+    processing: ProcessingTransaction,
+    redis_store: Option<&RedisIdempotencyStore>,
     idempotency_key: &IdempotencyKey,
     user_id: Uuid,
     http_response: HttpResponse,
 ) -> Result<HttpResponse, anyhow::Error> {
     let (response_head, body) = http_response.into_parts();
     let body = to_bytes(body).await.map_err(|e| anyhow::anyhow!("{}", e))?;
-    let status_code = response_head.status().as_u16() as i16;
     let headers = {
         let mut h = Vec::with_capacity(response_head.headers().len());
         for (name, value) in response_head.headers().iter() {
@@ -76,6 +138,31 @@ pub async fn save_response(
         h
     };
 
+    let ProcessingTransaction {
+        mut transaction,
+        reservation,
+    } = processing;
+    if let Some(store) = redis_store {
+        let token = reservation.context("Idempotency key is not reserved in redis.")?;
+        store
+            .hold_reservation(idempotency_key, user_id, &token)
+            .await?;
+        transaction.commit().await?;
+        let headers = headers.into_iter().map(|h| (h.name, h.value)).collect();
+        store
+            .save_response(
+                idempotency_key,
+                user_id,
+                response_head.status().as_u16(),
+                headers,
+                body.as_ref(),
+            )
+            .await?;
+        let http_response = response_head.set_body(body).map_into_boxed_body();
+        return Ok(http_response);
+    }
+
+    let status_code = response_head.status().as_u16() as i16;
     transaction
         .execute(sqlx::query_unchecked!(
             r#"
@@ -103,29 +190,74 @@ pub async fn save_response(
     Ok(http_response)
 }
 
+/// Start processing of request with idempotency key in a new transaction or return
+/// the saved response of key, if `request_hash` matches the hash of the first request
+/// with key. Without Redis store the key is stored in Postgres.
 pub async fn try_processing(
     pool: &PgPool,
+    redis_store: Option<&RedisIdempotencyStore>,
     idempotency_key: &IdempotencyKey,
     user_id: Uuid,
+    request_hash: &str,
 ) -> Result<NextAction, anyhow::Error> {
+    if let Some(store) = redis_store {
+        return match store
+            .reserve_or_get_saved_response(idempotency_key, user_id, request_hash)
+            .await?
+        {
+            Reservation::Reserved(token) => {
+                Ok(NextAction::StartProcessing(ProcessingTransaction {
+                    transaction: pool.begin().await?,
+                    reservation: Some(token),
+                }))
+            }
+            Reservation::SavedResponse(saved_response) => {
+                Ok(NextAction::ReturnSavedResponse(saved_response))
+            }
+            Reservation::OtherRequest => Ok(NextAction::RejectReusedKey),
+        };
+    }
     let mut transaction = pool.begin().await?;
     let query = sqlx::query!(
         r#"
         INSERT INTO idempotency (
             user_id,
             idempotency_key,
+            request_hash,
             created_at
         )
-        VALUES ($1, $2, now())
+        VALUES ($1, $2, $3, now())
         ON CONFLICT DO NOTHING
         "#,
         user_id,
         idempotency_key.as_ref(),
+        request_hash,
     );
     let n_inserted_rows = transaction.execute(query).await?.rows_affected();
     if n_inserted_rows > 0 {
-        Ok(NextAction::StartProcessing(transaction))
+        Ok(NextAction::StartProcessing(ProcessingTransaction {
+            transaction,
+            reservation: None,
+        }))
     } else {
+        // keys stored before hashes were introduced match any request
+        let saved_hash = sqlx::query!(
+            r#"
+            SELECT request_hash
+            FROM idempotency
+            WHERE
+                user_id = $1 AND
+                idempotency_key = $2
+            "#,
+            user_id,
+            idempotency_key.as_ref()
+        )
+        .fetch_optional(pool)
+        .await?
+        .and_then(|r| r.request_hash);
+        if saved_hash.is_some_and(|saved_hash| saved_hash != request_hash) {
+            return Ok(NextAction::RejectReusedKey);
+        }
         let saved_response = get_saved_response(pool, idempotency_key, user_id)
             .await?
             .ok_or_else(|| anyhow::anyhow!("We expected a saved response, we didn't find it."))?;
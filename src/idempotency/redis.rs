@@ -0,0 +1,211 @@
+//! src/idempotency/redis.rs
+
+use ::redis::aio::ConnectionManager;
+use actix_web::{http::StatusCode, HttpResponse};
+use anyhow::Context;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use super::IdempotencyKey;
+
+/// Concurrent requests with the same key wait at most this time for the saved response.
+const MAX_WAIT: Duration = Duration::from_secs(10);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Outcome of reserving a key for processing.
+pub(super) enum Reservation {
+    /// The key is reserved with this token.
+    Reserved(String),
+    SavedResponse(HttpResponse),
+    /// The key is reserved by a request with another payload.
+    OtherRequest,
+}
+
+/// Value of keys in Redis; the response is `None`, while the request is processed.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct IdempotencyRecord {
+    request_hash: String,
+    /// token of the request, which reserved the key
+    #[serde(default)]
+    reservation: Option<String>,
+    response: Option<SavedResponse>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SavedResponse {
+    status_code: u16,
+    headers: Vec<(String, Vec<u8>)>,
+    body: Vec<u8>,
+}
+
+impl SavedResponse {
+    fn into_http_response(self) -> Result<HttpResponse, anyhow::Error> {
+        let mut response = HttpResponse::build(StatusCode::from_u16(self.status_code)?);
+        for (name, value) in self.headers {
+            response.append_header((name, value));
+        }
+        Ok(response.body(self.body))
+    }
+}
+
+/// Idempotency records in Redis, which keeps their churn out of the database. Keys
+/// `<key_prefix>:<user_id>:<idempotency_key>` hold the request hash and, once their
+/// request is processed, the saved response as JSON. They expire after their lifetime
+/// instead of being deleted by the cleanup worker.
+/// Reservations of requests, which failed without saving a response, expire after
+/// their lease; until then retries with the same key wait for the saved response.
+#[derive(Clone)]
+pub struct RedisIdempotencyStore {
+    /// reconnects after connection errors
+    connection: ConnectionManager,
+    key_prefix: String,
+    lifetime: Duration,
+    lease: Duration,
+}
+
+impl RedisIdempotencyStore {
+    pub async fn connect(
+        redis_uri: &str,
+        key_prefix: &str,
+        lifetime: Duration,
+        lease: Duration,
+    ) -> Result<Self, anyhow::Error> {
+        let client = ::redis::Client::open(redis_uri).context("Invalid redis uri.")?;
+        let connection = ConnectionManager::new(client)
+            .await
+            .context("Failed to connect to redis.")?;
+        Ok(Self {
+            connection,
+            key_prefix: key_prefix.to_string(),
+            lifetime,
+            lease,
+        })
+    }
+
+    fn key(&self, idempotency_key: &IdempotencyKey, user_id: Uuid) -> String {
+        format!(
+            "{}:{}:{}",
+            self.key_prefix,
+            user_id,
+            idempotency_key.as_ref()
+        )
+    }
+
+    async fn get_record(&self, key: &str) -> Result<Option<IdempotencyRecord>, anyhow::Error> {
+        let record: Option<String> = ::redis::cmd("GET")
+            .arg(key)
+            .query_async(&mut self.connection.clone())
+            .await
+            .context("Failed to read idempotency key from redis.")?;
+        record
+            .map(|record| serde_json::from_str(&record).context("Invalid idempotency record."))
+            .transpose()
+    }
+
+    /// Reserve key for processing, or return the saved response of key. A request with
+    /// the same key, which is still processed, is waited for.
+    pub(super) async fn reserve_or_get_saved_response(
+        &self,
+        idempotency_key: &IdempotencyKey,
+        user_id: Uuid,
+        request_hash: &str,
+    ) -> Result<Reservation, anyhow::Error> {
+        let key = self.key(idempotency_key, user_id);
+        let token = Uuid::new_v4().to_string();
+        let reservation = serde_json::to_string(&IdempotencyRecord {
+            request_hash: request_hash.to_string(),
+            reservation: Some(token.clone()),
+            response: None,
+        })
+        .context("Failed to serialize idempotency record.")?;
+        let started_at = Instant::now();
+        loop {
+            let reserved: Option<String> = ::redis::cmd("SET")
+                .arg(&key)
+                .arg(&reservation)
+                .arg("NX")
+                .arg("EX")
+                .arg(self.lease.as_secs().max(1))
+                .query_async(&mut self.connection.clone())
+                .await
+                .context("Failed to reserve idempotency key in redis.")?;
+            if reserved.is_some() {
+                return Ok(Reservation::Reserved(token));
+            }
+            // the reservation may expire between both commands, then we try again
+            match self.get_record(&key).await? {
+                Some(record) if record.request_hash != request_hash => {
+                    return Ok(Reservation::OtherRequest)
+                }
+                Some(IdempotencyRecord {
+                    response: Some(saved),
+                    ..
+                }) => return saved.into_http_response().map(Reservation::SavedResponse),
+                Some(_) if started_at.elapsed() > MAX_WAIT => {
+                    anyhow::bail!("Request with idempotency key is still processed.")
+                }
+                Some(_) => tokio::time::sleep(POLL_INTERVAL).await,
+                None => (),
+            }
+        }
+    }
+
+    /// Check that the key is still reserved with `token` and renew its lease, so that
+    /// the reservation holds until the response is saved. A request, whose reservation
+    /// has expired, may be processed by a retry and must not commit its changes.
+    pub(super) async fn hold_reservation(
+        &self,
+        idempotency_key: &IdempotencyKey,
+        user_id: Uuid,
+        token: &str,
+    ) -> Result<(), anyhow::Error> {
+        let key = self.key(idempotency_key, user_id);
+        let is_reserved = self.get_record(&key).await?.is_some_and(|record| {
+            record.response.is_none() && record.reservation.as_deref() == Some(token)
+        });
+        if !is_reserved {
+            anyhow::bail!("Reservation of idempotency key has been lost.");
+        }
+        let renewed: bool = ::redis::cmd("EXPIRE")
+            .arg(&key)
+            .arg(self.lease.as_secs().max(1))
+            .query_async(&mut self.connection.clone())
+            .await
+            .context("Failed to renew reservation of idempotency key in redis.")?;
+        if !renewed {
+            anyhow::bail!("Reservation of idempotency key has been lost.");
+        }
+        Ok(())
+    }
+
+    /// Save response of reserved key for the lifetime of idempotency keys.
+    pub(super) async fn save_response(
+        &self,
+        idempotency_key: &IdempotencyKey,
+        user_id: Uuid,
+        status_code: u16,
+        headers: Vec<(String, Vec<u8>)>,
+        body: &[u8],
+    ) -> Result<(), anyhow::Error> {
+        let key = self.key(idempotency_key, user_id);
+        let mut record = self
+            .get_record(&key)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Reservation of idempotency key has expired."))?;
+        record.response = Some(SavedResponse {
+            status_code,
+            headers,
+            body: body.to_vec(),
+        });
+        let record = serde_json::to_string(&record).context("Failed to serialize response.")?;
+        ::redis::cmd("SET")
+            .arg(&key)
+            .arg(record)
+            .arg("EX")
+            .arg(self.lifetime.as_secs().max(1))
+            .query_async::<_, ()>(&mut self.connection.clone())
+            .await
+            .context("Failed to save response of idempotency key in redis.")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+//! src/instance_setup.rs
+
+use anyhow::Context;
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+use secrecy::Secret;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::authentication::{create_initial_admin_in_db, CredentialsError};
+use crate::configuration::Settings;
+
+/// Settings chosen in the initial setup, which override the configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstanceSettings {
+    pub base_url: String,
+    pub sender_email: String,
+}
+
+impl InstanceSettings {
+    pub fn apply(self, configuration: &mut Settings) {
+        configuration.application.base_url = self.base_url;
+        configuration.emailclient.sender_email = self.sender_email;
+    }
+}
+
+/// First admin user created in the initial setup.
+pub struct InitialAdmin {
+    pub username: String,
+    pub email: String,
+    pub password: Secret<String>,
+}
+
+/// Token, which the initial setup requires, so that the setup of a new instance can not
+/// be claimed by the first visitor; the configured token or a random one.
+pub fn setup_token(configured: Option<Secret<String>>) -> Secret<String> {
+    configured.unwrap_or_else(|| {
+        let mut rng = thread_rng();
+        let token: String = std::iter::repeat_with(|| rng.sample(Alphanumeric))
+            .map(char::from)
+            .take(32)
+            .collect();
+        Secret::new(token)
+    })
+}
+
+/// The initial setup is served, as long as no user exists.
+#[tracing::instrument(skip_all)]
+pub async fn needs_setup(pool: &PgPool) -> Result<bool, sqlx::Error> {
+    let needs_setup = sqlx::query!(r#"SELECT NOT EXISTS (SELECT 1 FROM users) AS "needs_setup!""#)
+        .fetch_one(pool)
+        .await?
+        .needs_setup;
+    Ok(needs_setup)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn get_instance_settings(pool: &PgPool) -> Result<Option<InstanceSettings>, sqlx::Error> {
+    let settings = sqlx::query_as!(
+        InstanceSettings,
+        "SELECT base_url, sender_email FROM instance_settings"
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(settings)
+}
+
+/// Override the configuration with the settings of the initial setup, if any.
+pub async fn apply_instance_settings(
+    configuration: &mut Settings,
+    pool: &PgPool,
+) -> Result<(), anyhow::Error> {
+    if let Some(settings) = get_instance_settings(pool)
+        .await
+        .context("Failed to read instance settings.")?
+    {
+        settings.apply(configuration);
+    }
+    Ok(())
+}
+
+/// Create the initial admin and store the instance settings in one transaction.
+/// Returns `None`, if the setup has been completed already, e.g. by a concurrent request.
+#[tracing::instrument(skip(pool, admin), fields(username = %admin.username))]
+pub async fn complete_setup(
+    pool: &PgPool,
+    admin: InitialAdmin,
+    settings: &InstanceSettings,
+) -> Result<Option<Uuid>, CredentialsError> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    let Some(user_id) = create_initial_admin_in_db(
+        &admin.username,
+        &admin.email,
+        admin.password,
+        &mut transaction,
+    )
+    .await?
+    else {
+        return Ok(None);
+    };
+    sqlx::query!(
+        r#"
+        INSERT INTO instance_settings (base_url, sender_email, completed_at)
+        VALUES ($1, $2, now())
+        ON CONFLICT (id) DO UPDATE
+        SET
+            base_url = EXCLUDED.base_url,
+            sender_email = EXCLUDED.sender_email,
+            completed_at = EXCLUDED.completed_at
+        "#,
+        settings.base_url,
+        settings.sender_email,
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to store instance settings.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit initial setup.")?;
+    Ok(Some(user_id))
+}
@@ -1,50 +1,239 @@
 //! src/issue_delivery_worker.rs
 
 use crate::{
-    configuration::Settings,
-    email_client::EmailClient,
+    bounces::{
+        apply_bounce_policy, email_domain, get_paused_domains, get_suppressed_emails, BounceKind,
+        DOMAIN_BLOCK_PAUSE,
+    },
+    config_reload::TunableSettings,
+    configuration::{DeliveryQueueSettings, Settings, WarmUpSettings},
+    delivery_attempts::{insert_dead_letter, panic_message, DeliveryAttempts, TaskCrashes},
+    delivery_queue::{DeliveryQueue, PgDeliveryQueue, RedisDeliveryQueue, Task},
+    domain::{Locale, SubscriberEmail},
+    email_client::{Attachment, BatchEmail, EmailClient, RejectedEmail},
     error::{Error, Z2PResult},
-    routes::get_subscriber_from_subscriber_id,
-    startup::get_connection_pool,
+    locale::LocaleFallbacks,
+    notifications::{notify_admins, AdminNotification},
+    send_rate_limit::SendRateLimiter,
+    snippets::{pin_issue_snippets, referenced_snippets},
+    subscriber_events::{record_subscriber_event, SubscriberEventKind},
+    subscriber_repository::{SubscriberRecord, SubscriberRepository},
+    token_bucket::TokenBucket,
+    worker_heartbeat::WorkerHeartbeat,
 };
 use anyhow::Context;
 use askama::Template;
-use chrono::{DateTime, Utc};
-use sqlx::{Executor, PgPool, Postgres, Row, Transaction};
+use chrono::{NaiveDate, Utc};
+use secrecy::ExposeSecret;
+use sqlx::{
+    postgres::{PgListener, PgPoolOptions},
+    Executor, PgExecutor, PgPool, Postgres, Row, Transaction,
+};
+use std::collections::{hash_map::Entry, HashMap};
+use std::sync::Arc;
 use std::time::Duration;
-use tracing::{field::display, Span};
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+use tracing::Span;
 use uuid::Uuid;
 
-pub async fn run_delivery_worker_until_stopped(configuration: Settings) -> Z2PResult<()> {
-    let connection_pool = get_connection_pool(&configuration.database);
-    let max_retries = configuration.emailclient.n_retries;
-    let time_delta = chrono::TimeDelta::milliseconds(
-        configuration.emailclient.execute_retry_after_milliseconds as i64,
-    );
+/// Run `worker_concurrency` task loops, which dequeue disjoint batches of tasks
+/// from the configured queue. If one loop stops with an error, all loops are stopped.
+/// Retry settings and the send rate limit are read from `tunables` for each batch.
+pub async fn run_delivery_worker_until_stopped(
+    configuration: Settings,
+    tunables: watch::Receiver<TunableSettings>,
+) -> Z2PResult<()> {
+    let worker_concurrency = configuration.emailclient.worker_concurrency.max(1);
+    // each loop holds the claim of its batch and a transaction of its bookkeeping
+    // and runs queries with the pool; one more connection listens for new tasks
+    let connection_pool = PgPoolOptions::new()
+        .max_connections((3 * worker_concurrency as u32 + 2).max(10))
+        .connect_lazy_with(configuration.database.with_db());
+    match configuration.delivery_queue {
+        DeliveryQueueSettings::Postgres => {
+            let queue = PgDeliveryQueue::new(connection_pool.clone());
+            run_worker_loops(connection_pool, queue, configuration, tunables).await
+        }
+        DeliveryQueueSettings::Redis {
+            ref key_prefix,
+            lease_seconds,
+        } => {
+            let queue = RedisDeliveryQueue::connect(
+                configuration.redis_uri.expose_secret(),
+                key_prefix,
+                Duration::from_secs(lease_seconds),
+                connection_pool.clone(),
+            )
+            .await?;
+            run_worker_loops(connection_pool, queue, configuration, tunables).await
+        }
+    }
+}
+
+async fn run_worker_loops<Q: DeliveryQueue + Clone + 'static>(
+    connection_pool: PgPool,
+    queue: Q,
+    configuration: Settings,
+    tunables: watch::Receiver<TunableSettings>,
+) -> Z2PResult<()> {
+    let worker_concurrency = configuration.emailclient.worker_concurrency.max(1);
+    let batch_size = configuration.emailclient.batch_size;
+    let max_task_crashes = configuration.emailclient.max_task_crashes;
     let base_url = configuration.application.base_url;
-    let email_client = configuration.emailclient.client();
-    worker_loop(
-        connection_pool,
-        email_client,
-        max_retries,
-        time_delta,
-        &base_url,
-    )
-    .await
+    let warm_up = configuration.emailclient.warm_up.clone();
+    // loops share failover state of email client and send rate limit
+    let rate_limit = Arc::new(SendRateLimiter::new(tunables.clone()));
+    let locale_fallbacks = Arc::new(configuration.application.locale_fallbacks);
+    let email_client = Arc::new(configuration.emailclient.client());
+    let mut listener = PgListener::connect_with(&connection_pool)
+        .await
+        .context("Failed to connect listener of delivery worker.")?;
+    listener
+        .listen(DELIVERY_CHANNEL)
+        .await
+        .context("Failed to listen for new delivery tasks.")?;
+    let (wake_sender, wake_receiver) = watch::channel(());
+    let mut workers = JoinSet::new();
+    workers.spawn(forward_notifications(listener, wake_sender));
+    for _ in 0..worker_concurrency {
+        let wake = wake_receiver.clone();
+        let pool = connection_pool.clone();
+        let queue = queue.clone();
+        let email_client = email_client.clone();
+        let base_url = base_url.clone();
+        let warm_up = warm_up.clone();
+        let rate_limit = rate_limit.clone();
+        let locale_fallbacks = locale_fallbacks.clone();
+        let tunables = tunables.clone();
+        let attempts = DeliveryAttempts::new(max_task_crashes);
+        workers.spawn(async move {
+            let loop_pool = pool.clone();
+            let worker = tokio::spawn(async move {
+                worker_loop(
+                    loop_pool,
+                    &queue,
+                    wake,
+                    &email_client,
+                    &tunables,
+                    batch_size,
+                    &base_url,
+                    warm_up.as_ref(),
+                    &rate_limit,
+                    &locale_fallbacks,
+                    &attempts,
+                )
+                .await
+            });
+            match worker.await {
+                Ok(result) => result,
+                Err(e) if e.is_panic() => {
+                    // tasks in flight count as crashed, when they are dequeued again
+                    let message = panic_message(e.into_panic().as_ref());
+                    if let Err(e) = attempts.record_panic(&pool, &message).await {
+                        tracing::error!(
+                            error.cause_chain = ?e,
+                            "Failed to record panic of delivery worker loop."
+                        );
+                    }
+                    Err(anyhow::anyhow!("Delivery worker loop panicked: {}", message).into())
+                }
+                Err(e) => Err(anyhow::Error::from(e)
+                    .context("Delivery worker loop failed to complete.")
+                    .into()),
+            }
+        });
+    }
+    // dropping the join set aborts remaining loops
+    match workers.join_next().await {
+        Some(Ok(result)) => result,
+        Some(Err(e)) => Err(anyhow::Error::from(e)
+            .context("Delivery worker loop failed to complete.")
+            .into()),
+        None => Ok(()),
+    }
+}
+
+/// Channel of notifications about newly enqueued delivery tasks.
+pub const DELIVERY_CHANNEL: &str = "issue_delivery_queue";
+
+/// Wake idle worker loops after enqueueing tasks. Inside a transaction the
+/// notification is sent at commit.
+pub async fn notify_delivery_worker<'e>(executor: impl PgExecutor<'e>) -> Result<(), sqlx::Error> {
+    sqlx::query("NOTIFY issue_delivery_queue")
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Wake all worker loops on each notification. Loops, which are busy, see the
+/// change after finishing their batch.
+async fn forward_notifications(mut listener: PgListener, wake: watch::Sender<()>) -> Z2PResult<()> {
+    loop {
+        if let Err(e) = listener.recv().await {
+            tracing::warn!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Listener of delivery worker lost connection."
+            );
+            // notifications may have been lost; next recv reconnects
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+        wake.send_replace(());
+    }
 }
 
-async fn worker_loop(
+#[allow(clippy::too_many_arguments)]
+async fn worker_loop<Q: DeliveryQueue>(
     pool: PgPool,
-    email_client: EmailClient,
-    max_retries: u8,
-    time_delta: chrono::TimeDelta,
+    queue: &Q,
+    mut wake: watch::Receiver<()>,
+    email_client: &EmailClient,
+    tunables: &watch::Receiver<TunableSettings>,
+    batch_size: u16,
     base_url: &str,
+    warm_up: Option<&WarmUpSettings>,
+    rate_limit: &SendRateLimiter,
+    locale_fallbacks: &LocaleFallbacks,
+    attempts: &DeliveryAttempts,
 ) -> Z2PResult<()> {
     let mut wait_postponed_tasks: u64 = 10;
+    // idle loops sleep up to 10 seconds between two beats
+    let mut heartbeat = WorkerHeartbeat::new("delivery", Duration::from_secs(60));
     loop {
-        match try_execute_task(&pool, &email_client, max_retries, time_delta, base_url).await {
+        // reloaded retry settings and send rate limit apply to the next batch
+        let (max_retries, time_delta) = {
+            let tunables = tunables.borrow();
+            (tunables.n_retries, tunables.retry_time_delta())
+        };
+        let bucket = rate_limit.bucket();
+        let outcome = try_execute_queued_task(
+            &pool,
+            queue,
+            email_client,
+            max_retries,
+            time_delta,
+            batch_size,
+            base_url,
+            warm_up,
+            bucket.as_deref(),
+            locale_fallbacks,
+            attempts,
+        )
+        .await;
+        // delivery worker counts executed batches
+        let batches_executed = matches!(outcome, Ok(ExecutionOutcome::TaskCompleted)) as u64;
+        heartbeat.beat(&pool, batches_executed).await;
+        match outcome {
             Ok(ExecutionOutcome::EmptyQueue) => {
-                tokio::time::sleep(Duration::from_secs(10)).await;
+                // wait for new tasks; poll anyway for scheduled tasks, which become
+                // due without notification
+                if let Ok(Err(_)) =
+                    tokio::time::timeout(Duration::from_secs(10), wake.changed()).await
+                {
+                    // listener has stopped
+                    tokio::time::sleep(Duration::from_secs(10)).await;
+                }
                 wait_postponed_tasks = 10;
             }
             Ok(ExecutionOutcome::PostponedTasks) => {
@@ -68,6 +257,16 @@ async fn worker_loop(
     }
 }
 
+/// Status of a queued delivery task. Only pending tasks are executed by the worker.
+/// Cancelled tasks are kept as record of undelivered recipients.
+#[derive(Debug, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "delivery_task_status", rename_all = "snake_case")]
+pub enum DeliveryTaskStatus {
+    Pending,
+    Paused,
+    Cancelled,
+}
+
 pub enum ExecutionOutcome {
     TaskCompleted,
     EmptyQueue,
@@ -76,236 +275,797 @@ pub enum ExecutionOutcome {
 
 #[derive(Template)]
 #[template(path = "email_newsletter.html", escape = "none")]
-struct EmailHtmlTemplate<'a> {
-    title: &'a str,
-    name: &'a str,
-    content: &'a str,
-    unsubscribe_link: &'a str,
+pub(crate) struct EmailHtmlTemplate<'a> {
+    pub(crate) title: &'a str,
+    pub(crate) name: &'a str,
+    pub(crate) content: &'a str,
+    pub(crate) unsubscribe_link: &'a str,
+    pub(crate) preferences_link: &'a str,
+    pub(crate) feedback_link: Option<&'a str>,
+    /// Image, which records opens of the email; only set for deliveries to subscribers
+    pub(crate) open_tracking_link: Option<&'a str>,
 }
 
 #[derive(Template)]
 #[template(path = "email_newsletter.txt")]
-struct EmailTextTemplate<'a> {
-    title: &'a str,
-    name: &'a str,
-    content: &'a str,
-    unsubscribe_link: &'a str,
-}
-
-#[tracing::instrument(
-    skip_all,
-    fields(
-        newsletter_issue_id=tracing::field::Empty,
-        subscriber_email=tracing::field::Empty
+pub(crate) struct EmailTextTemplate<'a> {
+    pub(crate) title: &'a str,
+    pub(crate) name: &'a str,
+    pub(crate) content: &'a str,
+    pub(crate) unsubscribe_link: &'a str,
+    pub(crate) preferences_link: &'a str,
+    pub(crate) feedback_link: Option<&'a str>,
+}
+
+/// Hash of the email templates, which are compiled into the binary.
+const EMAIL_TEMPLATE_HASH: u64 = fnv1a_hash(
+    concat!(
+        include_str!("../templates/email_newsletter.html"),
+        include_str!("../templates/email_newsletter.txt")
     )
-)]
+    .as_bytes(),
+);
+
+/// Version of the newsletter email templates; changes with every edit of the templates.
+pub fn email_template_version() -> String {
+    format!("{:016x}", EMAIL_TEMPLATE_HASH)
+}
+
+/// 64 bit FNV-1a hash, which is stable across builds and platforms.
+pub(crate) const fn fnv1a_hash(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+        i += 1;
+    }
+    hash
+}
+
+/// Rendered newsletter email of a task, ready to be sent.
+struct Delivery {
+    task: Task,
+    email: SubscriberEmail,
+    html_body: String,
+    plain_body: String,
+    subject: String,
+    tag: String,
+    /// Locale of language variant; None for default content of issue
+    variant: Option<String>,
+}
+
+/// Execute tasks of the Postgres delivery queue, see `try_execute_queued_task`.
+#[allow(clippy::too_many_arguments)]
 pub async fn try_execute_task(
     pool: &PgPool,
     email_client: &EmailClient,
     max_retries: u8,
     time_delta: chrono::TimeDelta,
+    batch_size: u16,
+    base_url: &str,
+    warm_up: Option<&WarmUpSettings>,
+    rate_limit: Option<&TokenBucket>,
+    locale_fallbacks: &LocaleFallbacks,
+    attempts: &DeliveryAttempts,
+) -> Z2PResult<ExecutionOutcome> {
+    try_execute_queued_task(
+        pool,
+        &PgDeliveryQueue::new(pool.clone()),
+        email_client,
+        max_retries,
+        time_delta,
+        batch_size,
+        base_url,
+        warm_up,
+        rate_limit,
+        locale_fallbacks,
+        attempts,
+    )
+    .await
+}
+
+/// Dequeue up to `batch_size` tasks and send their emails in one batch.
+/// During warm-up the batch is limited by the send volume, which is reserved of the
+/// remaining volume of the day.
+/// With a rate limit the batch is limited by the available tokens of the limit.
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(skip_all, fields(n_tasks = tracing::field::Empty))]
+pub async fn try_execute_queued_task<Q: DeliveryQueue>(
+    pool: &PgPool,
+    queue: &Q,
+    email_client: &EmailClient,
+    max_retries: u8,
+    time_delta: chrono::TimeDelta,
+    batch_size: u16,
     base_url: &str,
+    warm_up: Option<&WarmUpSettings>,
+    rate_limit: Option<&TokenBucket>,
+    locale_fallbacks: &LocaleFallbacks,
+    attempts: &DeliveryAttempts,
 ) -> Z2PResult<ExecutionOutcome> {
-    let task = dequeue_task(pool).await?;
-    if task.is_none() {
-        if is_task_queue_empty(pool).await? {
+    let today = Utc::now().date_naive();
+    let Some(daily_limit) = warm_up.and_then(|w| w.daily_limit(today)) else {
+        return execute_batch(
+            pool,
+            queue,
+            email_client,
+            max_retries,
+            time_delta,
+            batch_size,
+            base_url,
+            rate_limit,
+            locale_fallbacks,
+            attempts,
+            today,
+        )
+        .await;
+    };
+    // concurrent workers reserve their share of the remaining volume of the day
+    let reserved = reserve_daily_send_volume(pool, today, batch_size.into(), daily_limit).await?;
+    if reserved == 0 {
+        // daily limit of warm-up is reached, tasks wait for next day
+        return Ok(ExecutionOutcome::PostponedTasks);
+    }
+    let outcome = execute_batch(
+        pool,
+        queue,
+        email_client,
+        max_retries,
+        time_delta,
+        reserved as u16,
+        base_url,
+        rate_limit,
+        locale_fallbacks,
+        attempts,
+        today,
+    )
+    .await;
+    // sent emails have been added to the send volume by their execution
+    if let Err(e) = release_daily_send_volume(pool, today, reserved).await {
+        tracing::warn!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            "Failed to release reserved send volume."
+        );
+    }
+    outcome
+}
+
+/// Dequeue up to `batch_size` tasks, limited by the rate limit, and execute them.
+#[allow(clippy::too_many_arguments)]
+async fn execute_batch<Q: DeliveryQueue>(
+    pool: &PgPool,
+    queue: &Q,
+    email_client: &EmailClient,
+    max_retries: u8,
+    time_delta: chrono::TimeDelta,
+    mut batch_size: u16,
+    base_url: &str,
+    rate_limit: Option<&TokenBucket>,
+    locale_fallbacks: &LocaleFallbacks,
+    attempts: &DeliveryAttempts,
+    today: NaiveDate,
+) -> Z2PResult<ExecutionOutcome> {
+    let mut num_tokens = 0;
+    if let Some(rate_limit) = rate_limit {
+        num_tokens = rate_limit.take(batch_size.max(1).into());
+        if num_tokens == 0 {
+            // send rate limit is reached, tasks wait for refill of tokens
+            return Ok(ExecutionOutcome::PostponedTasks);
+        }
+        batch_size = batch_size.min(num_tokens as u16);
+    }
+    let (mut claim, tasks) = queue.dequeue(batch_size).await?;
+    if let Some(rate_limit) = rate_limit {
+        rate_limit.give_back(num_tokens.saturating_sub(tasks.len() as u32));
+    }
+    if tasks.is_empty() {
+        if queue.is_empty().await? {
             return Ok(ExecutionOutcome::EmptyQueue);
         } else {
             return Ok(ExecutionOutcome::PostponedTasks);
         }
     }
-    let (transaction, issue_id, user_id, n_retries, execute_after) = task.unwrap();
-    Span::current().record("newsletter_issue_id", &display(issue_id));
-    match get_subscriber_from_subscriber_id(pool, user_id).await {
-        Ok((parsed_name, parsed_email, parsed_token, _)) => {
-            Span::current()
-                .record("subscriber_name", &display(parsed_name.as_ref()))
-                .record("subscriber_email", &display(parsed_email.as_ref()));
-            let issue = get_issue(pool, issue_id).await?;
-            // We create a unsubscribe link
-            let unsubscribe_link = format!(
-                "{}/subscriptions/unsubscribe?subscription_token={}",
-                base_url,
-                parsed_token.as_ref()
-            );
+    let crashes = attempts
+        .begin(pool, &tasks)
+        .await
+        .context("Failed to begin delivery attempts.")?;
+    let (poison, tasks): (Vec<Task>, Vec<Task>) = tasks.into_iter().partition(|task| {
+        crashes
+            .get(&(task.issue_id, task.user_id))
+            .is_some_and(|c| attempts.is_poison(c))
+    });
+    if !poison.is_empty() {
+        quarantine_tasks(pool, queue, &mut claim, &poison, &crashes).await?;
+        for task in poison.iter() {
+            let notification = AdminNotification::DeliveryQuarantined {
+                newsletter_issue_id: task.issue_id,
+                subscriber_id: task.user_id,
+                n_crashes: crashes[&(task.issue_id, task.user_id)].n_crashes,
+            };
+            notify_admins(pool, email_client, &notification).await;
+        }
+    }
+    // tasks with crashes are executed alone, so that only a poison task crashes again
+    let (tasks, set_aside) = isolate_crashed_task(tasks, &crashes);
+    if !set_aside.is_empty() {
+        attempts
+            .set_aside(pool, &set_aside)
+            .await
+            .context("Failed to set aside delivery attempts.")?;
+    }
+    if let Some(rate_limit) = rate_limit {
+        rate_limit.give_back((poison.len() + set_aside.len()) as u32);
+    }
+    if tasks.is_empty() {
+        queue
+            .release(claim)
+            .await
+            .context("Failed to release quarantined tasks.")?;
+        return Ok(ExecutionOutcome::TaskCompleted);
+    }
+    Span::current().record("n_tasks", tasks.len());
+    let executed = tasks.clone();
+    let outcome = execute_tasks(
+        pool,
+        queue,
+        claim,
+        tasks,
+        email_client,
+        max_retries,
+        time_delta,
+        base_url,
+        locale_fallbacks,
+        today,
+    )
+    .await;
+    // tasks, which failed without crashing the worker, did not crash
+    let finished = match outcome {
+        Ok(_) => attempts.finish(pool, &executed).await,
+        Err(_) => attempts.set_aside(pool, &executed).await,
+    };
+    if let Err(e) = finished {
+        tracing::warn!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            "Failed to finish delivery attempts."
+        );
+    }
+    outcome
+}
 
-            let plain_body = EmailTextTemplate {
-                title: &issue.title,
-                name: parsed_name.as_ref(),
-                content: &issue.text_content,
-                unsubscribe_link: unsubscribe_link.as_ref(),
-            }
-            .render()
-            .context("Failed to render html body.")?;
-            let html_body = EmailHtmlTemplate {
-                title: &issue.title,
-                name: parsed_name.as_ref(),
-                content: &issue.html_content,
-                unsubscribe_link: unsubscribe_link.as_ref(),
-            }
-            .render()
-            .context("Failed to render html body.")?;
-            if let Err(e) = email_client
-                .send_email(&parsed_email, &issue.title, &html_body, &plain_body)
-                .await
-            {
-                if n_retries >= max_retries {
-                    tracing::error!(
-                        error.cause_chain = ?e,
-                        error.message = %e,
-                        "Failed to deliver issue to a confirmed subscriber. Skipping.",
-                    );
-                    update_issue_delivery_failure(pool, issue_id).await?;
-                    delete_task(transaction, issue_id, user_id).await?;
-                } else {
-                    let update_execute_after_timestamp = execute_after
-                        .checked_add_signed(time_delta)
-                        .ok_or(anyhow::anyhow!("failed to add time_delta"))?;
-                    update_execute_after_of_task(
-                        transaction,
-                        issue_id,
-                        user_id,
-                        n_retries,
-                        update_execute_after_timestamp,
+/// Task with crashes, which is executed alone, and the other tasks, which are set aside;
+/// all tasks, if none has crashed.
+fn isolate_crashed_task(
+    mut tasks: Vec<Task>,
+    crashes: &HashMap<(Uuid, Uuid), TaskCrashes>,
+) -> (Vec<Task>, Vec<Task>) {
+    match tasks
+        .iter()
+        .position(|task| crashes.contains_key(&(task.issue_id, task.user_id)))
+    {
+        Some(index) => {
+            let crashed = tasks.swap_remove(index);
+            (vec![crashed], tasks)
+        }
+        None => (tasks, Vec::new()),
+    }
+}
+
+/// Move tasks, which crashed the worker too often, into the dead letters and count their
+/// deliveries as failed, so that they cannot wedge the queue.
+async fn quarantine_tasks<Q: DeliveryQueue>(
+    pool: &PgPool,
+    queue: &Q,
+    claim: &mut Q::Claim,
+    tasks: &[Task],
+    crashes: &HashMap<(Uuid, Uuid), TaskCrashes>,
+) -> Result<(), anyhow::Error> {
+    let mut transaction: PgTransaction = pool
+        .begin()
+        .await
+        .context("Failed to create transaction.")?;
+    for task in tasks {
+        let crashes = &crashes[&(task.issue_id, task.user_id)];
+        tracing::error!(
+            newsletter_issue_id = %task.issue_id,
+            subscriber_id = %task.user_id,
+            n_crashes = crashes.n_crashes,
+            panic_message = ?crashes.panic_message,
+            "Quarantining a delivery task, which crashed the worker repeatedly.",
+        );
+        fail_task(pool, queue, claim, &mut transaction, task, None).await?;
+    }
+    // dead letters reference their issue, which is locked by counting failed deliveries
+    for task in tasks {
+        insert_dead_letter(
+            &mut *transaction,
+            task,
+            &crashes[&(task.issue_id, task.user_id)],
+        )
+        .await
+        .context("Failed to insert dead letter.")?;
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction of quarantined tasks.")?;
+    Ok(())
+}
+
+/// Render and send the emails of claimed tasks and release them.
+#[allow(clippy::too_many_arguments)]
+async fn execute_tasks<Q: DeliveryQueue>(
+    pool: &PgPool,
+    queue: &Q,
+    mut claim: Q::Claim,
+    tasks: Vec<Task>,
+    email_client: &EmailClient,
+    max_retries: u8,
+    time_delta: chrono::TimeDelta,
+    base_url: &str,
+    locale_fallbacks: &LocaleFallbacks,
+    today: NaiveDate,
+) -> Z2PResult<ExecutionOutcome> {
+    // bookkeeping of deliveries is committed before claimed tasks are released
+    let mut transaction: PgTransaction = pool
+        .begin()
+        .await
+        .context("Failed to create transaction.")?;
+    let mut issues: HashMap<Uuid, NewsletterIssue> = HashMap::new();
+    let mut deliveries = Vec::with_capacity(tasks.len());
+    let locales = get_subscriber_locales(pool, &tasks).await?;
+    for task in tasks {
+        match pool.subscriber_from_subscriber_id(task.user_id).await {
+            Ok(record) => {
+                if let Entry::Vacant(entry) = issues.entry(task.issue_id) {
+                    entry.insert(get_issue(pool, task.issue_id).await?);
+                }
+                let issue = &issues[&task.issue_id];
+                // a panic while rendering fails only the task, which caused it
+                match catch_task_panic(|| {
+                    render_delivery(
+                        &task,
+                        issue,
+                        record,
+                        locales.get(&task.user_id),
+                        locale_fallbacks,
+                        base_url,
                     )
-                    .await?;
+                }) {
+                    Ok(delivery) => deliveries.push(delivery?),
+                    Err(panic_message) => {
+                        tracing::error!(
+                            newsletter_issue_id = %task.issue_id,
+                            subscriber_id = %task.user_id,
+                            panic_message = %panic_message,
+                            "Rendering the issue for a confirmed subscriber panicked. Skipping.",
+                        );
+                        fail_task(pool, queue, &mut claim, &mut transaction, &task, None).await?;
+                    }
                 }
-            } else {
-                update_issue_delivery_success(pool, issue_id).await?;
-                delete_task(transaction, issue_id, user_id).await?;
+            }
+            Err(Error::SubscriptionError(e)) => {
+                // ValidationError is fatal and cannot be recoverd.
+                // Task is completed.
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    newsletter_issue_id = %task.issue_id,
+                    "Skipping a confirmed subscriber. \
+                    Thier stored contact details are invalid.",
+                );
+                fail_task(pool, queue, &mut claim, &mut transaction, &task, None).await?;
+            }
+            Err(e) => {
+                // unexpected transient err
+                Err(e)?;
             }
         }
-        Err(Error::SubscriptionError(e)) => {
-            // ValidationError is fatal and cannot be recoverd.
-            // Task is completed.
-            tracing::error!(
-                error.cause_chain = ?e,
-                error.message = %e,
-                "Skipping a confirmed subscriber. \
-                Thier stored contact details are invalid.",
-            );
-            update_issue_delivery_failure(pool, issue_id).await?;
-            delete_task(transaction, issue_id, user_id).await?;
-        }
+    }
 
-        Err(e) => {
-            // unexpected transient err
-            Err(e)?;
+    let deliveries =
+        skip_blocked_deliveries(pool, queue, &mut claim, &mut transaction, deliveries).await?;
+    if deliveries.is_empty() {
+        transaction
+            .commit()
+            .await
+            .context("Failed to commit SQL transaction of skipped tasks.")?;
+        queue
+            .release(claim)
+            .await
+            .context("Failed to release skipped tasks.")?;
+        return Ok(ExecutionOutcome::TaskCompleted);
+    }
+    let emails: Vec<BatchEmail> = deliveries
+        .iter()
+        .map(|delivery| BatchEmail {
+            recipient: &delivery.email,
+            subject: &delivery.subject,
+            html_content: &delivery.html_body,
+            text_content: &delivery.plain_body,
+            tag: Some(&delivery.tag),
+            attachments: &issues[&delivery.task.issue_id].attachments,
+        })
+        .collect();
+    let results = email_client.send_email_batch(&emails).await;
+    // the send volume limits the warm-up and is tracked against the plan of the provider
+    add_daily_send_volume(&mut transaction, today, emails.len() as i32).await?;
+    for (delivery, result) in deliveries.iter().zip(results) {
+        let task = &delivery.task;
+        if let Err(e) = result {
+            let bounce = bounce_kind(&e);
+            if let Some(kind) = bounce {
+                apply_bounce_policy(&mut *transaction, delivery.email.as_ref(), kind)
+                    .await
+                    .context("Failed to apply bounce policy.")?;
+            }
+            if task.n_retries >= max_retries || bounce == Some(BounceKind::Hard) {
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    newsletter_issue_id = %task.issue_id,
+                    subscriber_email = %delivery.email.as_ref(),
+                    bounce = ?bounce,
+                    "Failed to deliver issue to a confirmed subscriber. Skipping.",
+                );
+                fail_task(
+                    pool,
+                    queue,
+                    &mut claim,
+                    &mut transaction,
+                    task,
+                    delivery.variant.as_deref(),
+                )
+                .await?;
+            } else {
+                let update_execute_after_timestamp = match bounce {
+                    // wait for end of domain pause
+                    Some(BounceKind::Block) => Some(Utc::now() + DOMAIN_BLOCK_PAUSE),
+                    // extended backoff, which doubles with each retry
+                    Some(BounceKind::Soft) => 2_i32
+                        .checked_pow(task.n_retries.into())
+                        .and_then(|factor| time_delta.checked_mul(factor))
+                        .and_then(|backoff| Utc::now().checked_add_signed(backoff)),
+                    _ => task.execute_after.checked_add_signed(time_delta),
+                }
+                .ok_or(anyhow::anyhow!("failed to add time_delta"))?;
+                queue
+                    .postpone(&mut claim, task, update_execute_after_timestamp, true)
+                    .await?;
+            }
+        } else {
+            update_issue_delivery_success(pool, task.issue_id).await?;
+            if let Some(ref variant) = delivery.variant {
+                update_variant_delivery_count(pool, task.issue_id, variant, true).await?;
+            }
+            record_delivery_event(&mut transaction, task, SubscriberEventKind::ReceivedIssue)
+                .await?;
+            queue.complete(&mut claim, task).await?;
         }
     }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction of delivered tasks.")?;
+    queue
+        .release(claim)
+        .await
+        .context("Failed to release delivered tasks.")?;
     Ok(ExecutionOutcome::TaskCompleted)
 }
 
+/// Render the email of a task for its subscriber.
+fn render_delivery(
+    task: &Task,
+    issue: &NewsletterIssue,
+    subscriber: SubscriberRecord,
+    locale: Option<&Locale>,
+    locale_fallbacks: &LocaleFallbacks,
+    base_url: &str,
+) -> Result<Delivery, anyhow::Error> {
+    let (variant, content) = issue.content_for(locale, locale_fallbacks);
+    let token = subscriber.token.as_ref();
+    // We create a unsubscribe link
+    let unsubscribe_link = format!(
+        "{}/subscriptions/unsubscribe?subscription_token={}",
+        base_url, token
+    );
+    let preferences_link = format!("{}/preferences?t={}", base_url, token);
+    // We create a feedback link, if the issue asks readers for feedback
+    let feedback_link = issue
+        .collect_feedback
+        .then(|| format!("{}/feedback/{}?t={}", base_url, task.issue_id, token));
+    let open_tracking_link = format!("{}/open/{}?t={}", base_url, task.issue_id, token);
+
+    let plain_body = EmailTextTemplate {
+        title: &content.title,
+        name: subscriber.name.as_ref(),
+        content: &content.text_content,
+        unsubscribe_link: unsubscribe_link.as_ref(),
+        preferences_link: preferences_link.as_ref(),
+        feedback_link: feedback_link.as_deref(),
+    }
+    .render()
+    .context("Failed to render html body.")?;
+    let html_body = EmailHtmlTemplate {
+        title: &content.title,
+        name: subscriber.name.as_ref(),
+        content: &content.html_content,
+        unsubscribe_link: unsubscribe_link.as_ref(),
+        preferences_link: preferences_link.as_ref(),
+        feedback_link: feedback_link.as_deref(),
+        open_tracking_link: Some(&open_tracking_link),
+    }
+    .render()
+    .context("Failed to render html body.")?;
+    Ok(Delivery {
+        tag: task.issue_id.to_string(),
+        subject: content.title.clone(),
+        variant: variant.map(|v| v.to_string()),
+        task: task.clone(),
+        email: subscriber.email,
+        html_body,
+        plain_body,
+    })
+}
+
+/// Run work of a single task, which may panic, e.g. rendering of its email. Returns
+/// the message of the panic, so that only the task fails instead of the worker loop.
+fn catch_task_panic<T>(work: impl FnOnce() -> T) -> Result<T, String> {
+    // work is dropped on panic; no broken state is observed afterwards
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(work))
+        .map_err(|payload| panic_message(payload.as_ref()))
+}
+
 pub type PgTransaction = Transaction<'static, Postgres>;
-type TaskData = (PgTransaction, Uuid, Uuid, u8, DateTime<Utc>);
 
-#[tracing::instrument(skip_all)]
-async fn dequeue_task(pool: &PgPool) -> Result<Option<TaskData>, anyhow::Error> {
-    let mut transaction: PgTransaction = pool.begin().await?;
-    let query = sqlx::query!(
-        r#"
-        SELECT newsletter_issue_id, user_id, n_retries, execute_after
-        FROM issue_delivery_queue
-        WHERE NOW() > execute_after
-        FOR UPDATE
-        SKIP LOCKED
-        LIMIT 1
-        "#,
-    );
-    let r = transaction.fetch_optional(query).await?;
-    if let Some(r) = r {
-        let n_retries: i16 = r.try_get("n_retries")?;
-        if n_retries < 0 {
-            Err(anyhow::anyhow!("value n_retries < 0"))?;
+/// Skip deliveries to suppressed addresses and postpone deliveries to paused domains.
+async fn skip_blocked_deliveries<Q: DeliveryQueue>(
+    pool: &PgPool,
+    queue: &Q,
+    claim: &mut Q::Claim,
+    transaction: &mut PgTransaction,
+    deliveries: Vec<Delivery>,
+) -> Result<Vec<Delivery>, anyhow::Error> {
+    let emails: Vec<String> = deliveries
+        .iter()
+        .map(|delivery| delivery.email.as_ref().to_string())
+        .collect();
+    let domains: Vec<String> = emails.iter().map(|email| email_domain(email)).collect();
+    let suppressed_emails = get_suppressed_emails(pool, &emails)
+        .await
+        .context("Failed to read suppressed emails.")?;
+    let paused_domains = get_paused_domains(pool, &domains)
+        .await
+        .context("Failed to read paused domains.")?;
+    let mut remaining = Vec::with_capacity(deliveries.len());
+    for (delivery, domain) in deliveries.into_iter().zip(domains) {
+        let task = &delivery.task;
+        if suppressed_emails.contains(delivery.email.as_ref()) {
+            tracing::warn!(
+                newsletter_issue_id = %task.issue_id,
+                subscriber_email = %delivery.email.as_ref(),
+                "Skipping a suppressed subscriber.",
+            );
+            fail_task(
+                pool,
+                queue,
+                claim,
+                transaction,
+                task,
+                delivery.variant.as_deref(),
+            )
+            .await?;
+        } else if let Some(paused_until) = paused_domains.get(&domain) {
+            queue.postpone(claim, task, *paused_until, false).await?;
+        } else {
+            remaining.push(delivery);
         }
-        Ok(Some((
-            transaction,
-            r.try_get("newsletter_issue_id")?,
-            r.try_get("user_id")?,
-            n_retries as u8,
-            r.try_get("execute_after")?,
-        )))
-    } else {
-        Ok(None)
     }
+    Ok(remaining)
 }
 
-#[tracing::instrument(skip_all)]
-async fn is_task_queue_empty(pool: &PgPool) -> Result<bool, anyhow::Error> {
-    // Prepare the query to count rows in the specified table
-    let query = "SELECT COUNT(*) as count FROM issue_delivery_queue".to_string();
-
-    // Execute the query
-    let row = sqlx::query(&query).fetch_one(pool).await?;
+/// Bounce classification of a failed delivery, if the provider rejected the email.
+fn bounce_kind(error: &Error) -> Option<BounceKind> {
+    match error {
+        Error::UnexpectedError(e) => e.downcast_ref::<RejectedEmail>().and_then(|r| r.bounce),
+        _ => None,
+    }
+}
 
-    // Extract the count from the row
-    let count: i64 = row.try_get("count")?;
+/// Count delivery of task as failed and remove it from queue.
+async fn fail_task<Q: DeliveryQueue>(
+    pool: &PgPool,
+    queue: &Q,
+    claim: &mut Q::Claim,
+    transaction: &mut PgTransaction,
+    task: &Task,
+    variant: Option<&str>,
+) -> Result<(), anyhow::Error> {
+    update_issue_delivery_failure(pool, task.issue_id).await?;
+    if let Some(variant) = variant {
+        update_variant_delivery_count(pool, task.issue_id, variant, false).await?;
+    }
+    record_delivery_event(transaction, task, SubscriberEventKind::DeliveryFailed).await?;
+    queue.complete(claim, task).await?;
+    Ok(())
+}
 
-    // Check if the count is 0
-    Ok(count == 0)
+async fn record_delivery_event(
+    transaction: &mut PgTransaction,
+    task: &Task,
+    kind: SubscriberEventKind,
+) -> Result<(), anyhow::Error> {
+    record_subscriber_event(&mut **transaction, task.user_id, kind, Some(task.issue_id))
+        .await
+        .context("Failed to record delivery event of subscriber.")?;
+    Ok(())
 }
 
 #[tracing::instrument(skip_all)]
-async fn delete_task(
-    mut transaction: PgTransaction,
-    issue_id: Uuid,
-    user_id: Uuid,
+pub(crate) async fn get_daily_send_volume(
+    pool: &PgPool,
+    date: NaiveDate,
+) -> Result<u32, anyhow::Error> {
+    let num_sent = sqlx::query!(
+        r#"
+        SELECT num_sent
+        FROM daily_send_volume
+        WHERE send_date = $1
+        "#,
+        date
+    )
+    .fetch_optional(pool)
+    .await?
+    .map(|r| r.num_sent)
+    .unwrap_or(0);
+    Ok(num_sent.max(0) as u32)
+}
+
+/// Reserve up to `num_emails` of the remaining send volume of the day below `daily_limit`;
+/// returns the number of reserved emails. The reservation counts as sent, until it is
+/// released, so that concurrent workers can not exceed the limit together.
+#[tracing::instrument(skip(pool))]
+async fn reserve_daily_send_volume(
+    pool: &PgPool,
+    date: NaiveDate,
+    num_emails: u32,
+    daily_limit: u32,
+) -> Result<u32, anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO daily_send_volume (send_date, num_sent)
+        VALUES ($1, 0)
+        ON CONFLICT (send_date) DO NOTHING
+        "#,
+        date
+    )
+    .execute(pool)
+    .await?;
+    // the locked row is re-read after updates of concurrent reservations
+    let reserved = sqlx::query!(
+        r#"
+        WITH current AS (
+            SELECT num_sent
+            FROM daily_send_volume
+            WHERE send_date = $1
+            FOR UPDATE
+        )
+        UPDATE daily_send_volume
+        SET num_sent = daily_send_volume.num_sent + LEAST($2, $3 - current.num_sent)
+        FROM current
+        WHERE send_date = $1 AND current.num_sent < $3
+        RETURNING LEAST($2, $3 - current.num_sent) AS "reserved!"
+        "#,
+        date,
+        i32::try_from(num_emails).unwrap_or(i32::MAX),
+        i32::try_from(daily_limit).unwrap_or(i32::MAX),
+    )
+    .fetch_optional(pool)
+    .await?
+    .map_or(0, |r| r.reserved);
+    Ok(reserved.max(0) as u32)
+}
+
+#[tracing::instrument(skip(pool))]
+async fn release_daily_send_volume(
+    pool: &PgPool,
+    date: NaiveDate,
+    num_emails: u32,
 ) -> Result<(), anyhow::Error> {
-    let query = sqlx::query!(
+    sqlx::query!(
         r#"
-        DELETE FROM issue_delivery_queue
-        WHERE
-            newsletter_issue_id = $1 AND
-            user_id = $2
+        UPDATE daily_send_volume
+        SET num_sent = GREATEST(num_sent - $2, 0)
+        WHERE send_date = $1
         "#,
-        issue_id,
-        user_id
-    );
-    transaction.execute(query).await?;
-    transaction.commit().await?;
+        date,
+        i32::try_from(num_emails).unwrap_or(i32::MAX),
+    )
+    .execute(pool)
+    .await?;
     Ok(())
 }
 
 #[tracing::instrument(skip_all)]
-async fn update_execute_after_of_task(
-    mut transaction: PgTransaction,
-    issue_id: Uuid,
-    user_id: Uuid,
-    n_retries: u8,
-    update_execute_after_timestamp: DateTime<Utc>,
+async fn add_daily_send_volume(
+    transaction: &mut PgTransaction,
+    date: NaiveDate,
+    num_sent: i32,
 ) -> Result<(), anyhow::Error> {
     let query = sqlx::query!(
         r#"
-        UPDATE issue_delivery_queue
-        SET
-            n_retries = $3,
-            execute_after = $4
-        WHERE
-            newsletter_issue_id = $1 AND
-            user_id = $2
+        INSERT INTO daily_send_volume (send_date, num_sent)
+        VALUES ($1, $2)
+        ON CONFLICT (send_date) DO UPDATE
+        SET num_sent = daily_send_volume.num_sent + EXCLUDED.num_sent
         "#,
-        issue_id,
-        user_id,
-        (n_retries + 1) as i16,
-        update_execute_after_timestamp
+        date,
+        num_sent
     );
     transaction.execute(query).await?;
-    transaction.commit().await?;
     Ok(())
 }
 
 struct NewsletterIssue {
+    content: IssueContent,
+    collect_feedback: bool,
+    attachments: Vec<Attachment>,
+    /// Language variants by locale
+    variants: HashMap<String, IssueContent>,
+}
+
+struct IssueContent {
     title: String,
     text_content: String,
     html_content: String,
 }
 
+impl NewsletterIssue {
+    /// Content of the variant, which matches the fallback chain of the locale best,
+    /// with its locale. Falls back to the default content of the issue.
+    fn content_for(
+        &self,
+        locale: Option<&Locale>,
+        locale_fallbacks: &LocaleFallbacks,
+    ) -> (Option<&str>, &IssueContent) {
+        locale
+            .and_then(|l| locale_fallbacks.resolve(std::slice::from_ref(l), &self.variants))
+            .map(|(l, content)| (Some(l), content))
+            .unwrap_or((None, &self.content))
+    }
+}
+
+/// Preferred locales of subscribers of tasks; subscribers without valid locale are missing.
+#[tracing::instrument(skip_all)]
+async fn get_subscriber_locales(
+    pool: &PgPool,
+    tasks: &[Task],
+) -> Result<HashMap<Uuid, Locale>, anyhow::Error> {
+    let user_ids: Vec<Uuid> = tasks.iter().map(|task| task.user_id).collect();
+    let rows = sqlx::query!(
+        r#"
+        SELECT id, locale AS "locale!"
+        FROM subscriptions
+        WHERE id = ANY($1) AND locale IS NOT NULL
+        "#,
+        &user_ids
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|r| Locale::parse(r.locale).ok().map(|l| (r.id, l)))
+        .collect())
+}
+
 #[tracing::instrument(skip_all)]
 async fn get_issue(pool: &PgPool, issue_id: Uuid) -> Result<NewsletterIssue, anyhow::Error> {
-    let issue = sqlx::query_as!(
-        NewsletterIssue,
+    let issue = sqlx::query!(
         r#"
-        SELECT title, text_content, html_content
+        SELECT title, text_content, html_content, collect_feedback, template_version
         FROM newsletter_issues
         WHERE
             newsletter_issue_id = $1
@@ -314,9 +1074,145 @@ async fn get_issue(pool: &PgPool, issue_id: Uuid) -> Result<NewsletterIssue, any
     )
     .fetch_one(pool)
     .await?;
+    pin_template_version(pool, issue_id, issue.template_version.as_deref()).await?;
+    let attachments = sqlx::query_as!(
+        Attachment,
+        r#"
+        SELECT file_name, content_type, content
+        FROM newsletter_issue_attachments
+        WHERE
+            newsletter_issue_id = $1
+        ORDER BY file_name
+        "#,
+        issue_id
+    )
+    .fetch_all(pool)
+    .await?;
+    let variants = sqlx::query!(
+        r#"
+        SELECT locale, title, text_content, html_content
+        FROM newsletter_issue_variants
+        WHERE
+            newsletter_issue_id = $1
+        "#,
+        issue_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|v| {
+        (
+            v.locale,
+            IssueContent {
+                title: v.title,
+                text_content: v.text_content,
+                html_content: v.html_content,
+            },
+        )
+    })
+    .collect();
+    let mut issue = NewsletterIssue {
+        content: IssueContent {
+            title: issue.title,
+            text_content: issue.text_content,
+            html_content: issue.html_content,
+        },
+        collect_feedback: issue.collect_feedback,
+        attachments,
+        variants,
+    };
+    resolve_issue_snippets(pool, issue_id, &mut issue).await?;
     Ok(issue)
 }
 
+/// Replace snippets of issue content and variants with the versions pinned to the issue.
+#[tracing::instrument(skip(pool, issue))]
+async fn resolve_issue_snippets(
+    pool: &PgPool,
+    issue_id: Uuid,
+    issue: &mut NewsletterIssue,
+) -> Result<(), anyhow::Error> {
+    let contents = std::iter::once(&issue.content).chain(issue.variants.values());
+    let contents: Vec<&str> = contents
+        .flat_map(|c| [c.text_content.as_str(), c.html_content.as_str()])
+        .collect();
+    let names = referenced_snippets(&contents);
+    if names.is_empty() {
+        return Ok(());
+    }
+    let snippets = pin_issue_snippets(pool, issue_id, &names).await?;
+    for content in std::iter::once(&mut issue.content).chain(issue.variants.values_mut()) {
+        content.text_content = snippets.resolve_text(&content.text_content);
+        content.html_content = snippets.resolve_html(&content.html_content);
+    }
+    Ok(())
+}
+
+/// Pin issue to the current version of email templates, when its first email is rendered.
+/// Emails of issues pinned to an older version look different from earlier emails.
+#[tracing::instrument(skip(pool))]
+async fn pin_template_version(
+    pool: &PgPool,
+    issue_id: Uuid,
+    pinned_version: Option<&str>,
+) -> Result<(), anyhow::Error> {
+    let current_version = email_template_version();
+    match pinned_version {
+        None => {
+            sqlx::query!(
+                r#"
+                UPDATE newsletter_issues
+                SET template_version = $2
+                WHERE
+                    newsletter_issue_id = $1 AND
+                    template_version IS NULL
+                "#,
+                issue_id,
+                current_version
+            )
+            .execute(pool)
+            .await?;
+        }
+        Some(pinned_version) if pinned_version != current_version => {
+            tracing::warn!(
+                newsletter_issue_id = %issue_id,
+                pinned_version,
+                current_version,
+                "Email templates changed since first email of issue was sent.",
+            );
+        }
+        Some(_) => {}
+    }
+    Ok(())
+}
+
+/// Count a delivered or failed email of a language variant.
+#[tracing::instrument(skip(pool))]
+async fn update_variant_delivery_count(
+    pool: &PgPool,
+    issue_id: Uuid,
+    locale: &str,
+    delivered: bool,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE newsletter_issue_variants
+        SET
+            num_delivered_newsletters = num_delivered_newsletters + $3::INTEGER,
+            num_failed_deliveries = num_failed_deliveries + 1 - $3::INTEGER
+        WHERE
+            newsletter_issue_id = $1 AND
+            locale = $2
+        "#,
+        issue_id,
+        locale,
+        delivered as i32
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
 #[tracing::instrument(skip_all)]
 async fn update_issue_delivery_success(pool: &PgPool, issue_id: Uuid) -> Result<(), anyhow::Error> {
     let mut transaction: Transaction<'_, Postgres> = pool.begin().await?;
@@ -386,3 +1282,22 @@ async fn update_issue_delivery_failure(pool: &PgPool, issue_id: Uuid) -> Result<
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{catch_task_panic, fnv1a_hash};
+
+    #[test]
+    fn fnv1a_hash_matches_reference_values() {
+        assert_eq!(fnv1a_hash(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a_hash(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a_hash(b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn panic_of_task_is_caught_with_message() {
+        assert_eq!(catch_task_panic(|| 42), Ok(42));
+        let result: Result<(), String> = catch_task_panic(|| panic!("broken template"));
+        assert_eq!(result, Err("broken template".to_string()));
+    }
+}
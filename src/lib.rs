@@ -1,13 +1,46 @@
 //! src/lib.rs
+pub mod api_rate_limit;
+pub mod attachment_scan;
 pub mod authentication;
+pub mod bounces;
+pub mod config_reload;
 pub mod configuration;
+pub mod delivery_attempts;
+pub mod delivery_queue;
+pub mod delivery_stats;
 pub mod domain;
 pub mod email_client;
 pub mod error;
+pub mod event_export;
+pub mod frequency_cap;
 pub mod idempotency;
+pub mod instance_setup;
 pub mod issue_delivery_worker;
+pub mod loadgen;
+pub mod locale;
+pub mod mailing_lists;
+pub mod markdown;
+pub mod metrics;
+pub mod migration_check;
+pub mod notifications;
+pub mod policy;
+pub mod provider_usage;
+pub mod recurring_issues;
 pub mod routes;
+pub mod secrets;
+pub mod send_rate_limit;
+pub mod send_time;
 pub mod session_state;
+#[cfg(feature = "smoketest")]
+pub mod smoketest;
+pub mod snippets;
 pub mod startup;
+pub mod subscriber_events;
+pub mod subscriber_milestones;
+pub mod subscriber_repository;
 pub mod telemetry;
+pub mod token_bucket;
+pub mod user_audit_log;
 pub mod utils;
+pub mod welcome_issue;
+pub mod worker_heartbeat;
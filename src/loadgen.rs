@@ -0,0 +1,308 @@
+//! src/loadgen.rs
+
+use crate::{
+    config_reload::TunableSettings,
+    configuration::Settings,
+    domain::SubscriberToken,
+    email_client::EmailClientMode,
+    error::Z2PResult,
+    issue_delivery_worker::run_delivery_worker_until_stopped,
+    mailing_lists::create_mailing_list,
+    migration_check::verify_schema,
+    routes::{publish_issue_via_api, ApiPublishData, PublishIssueInput},
+    startup::{
+        get_connection_pool, get_external_delivery_queue, ApplicationBaseUrl, FrequencyCap,
+        PublishChecklist, UndoWindow,
+    },
+};
+use actix_web::web::Data;
+use anyhow::{bail, Context};
+use sqlx::PgPool;
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use uuid::Uuid;
+
+/// Interval of polling the delivery progress; resolution of measured latencies.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// The run is aborted, if no email is processed within this time.
+const STALL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Throughput and latencies of delivering one synthetic issue.
+pub struct LoadReport {
+    pub list_id: Uuid,
+    pub issue_id: Uuid,
+    pub num_emails: usize,
+    pub num_failed: usize,
+    pub worker_concurrency: u16,
+    pub batch_size: u16,
+    pub duration: Duration,
+    /// Time from publishing until each email was processed, ascending.
+    latencies: Vec<Duration>,
+}
+
+impl LoadReport {
+    /// Processed emails per second.
+    pub fn throughput(&self) -> f64 {
+        self.num_emails as f64 / self.duration.as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// Latency, within which `percentile` percent of emails were processed.
+    pub fn latency_percentile(&self, percentile: f64) -> Duration {
+        if self.latencies.is_empty() {
+            return Duration::ZERO;
+        }
+        let rank = (percentile / 100.0 * self.latencies.len() as f64).ceil() as usize;
+        self.latencies[rank.clamp(1, self.latencies.len()) - 1]
+    }
+}
+
+impl Display for LoadReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Delivered synthetic issue {} to {} subscribers of list {} ({} failed).",
+            self.issue_id, self.num_emails, self.list_id, self.num_failed
+        )?;
+        writeln!(
+            f,
+            "worker concurrency: {}, batch size: {}",
+            self.worker_concurrency, self.batch_size
+        )?;
+        writeln!(
+            f,
+            "duration: {:.1}s, throughput: {:.1} emails/s",
+            self.duration.as_secs_f64(),
+            self.throughput()
+        )?;
+        write!(f, "latency since publishing:")?;
+        for percentile in [50.0, 90.0, 99.0, 100.0] {
+            write!(
+                f,
+                " p{}={:.1}s",
+                percentile,
+                self.latency_percentile(percentile).as_secs_f64()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Insert `num_subscribers` synthetic subscribers into a new list, publish a synthetic
+/// issue to them and run the delivery worker until all emails are processed.
+/// Emails are only logged by the sandbox mode of the email client, so that operators
+/// can size worker concurrency and database pools without real sends.
+pub async fn run_load_test(
+    mut configuration: Settings,
+    num_subscribers: u32,
+) -> Z2PResult<LoadReport> {
+    configuration.emailclient.mode = EmailClientMode::Sandbox;
+    // synthetic sends must not wait for the daily limit of a warm-up
+    configuration.emailclient.warm_up = None;
+    let pool = get_connection_pool(&configuration.database);
+    verify_schema(&pool).await?;
+
+    let run_id = Uuid::new_v4();
+    let list_id = create_mailing_list(&pool, &format!("loadgen {}", run_id))
+        .await
+        .context("Failed to create list of synthetic subscribers.")?;
+    insert_synthetic_subscribers(&pool, list_id, run_id, num_subscribers).await?;
+    tracing::info!(%list_id, num_subscribers, "Inserted synthetic subscribers.");
+
+    let db_pool = Data::new(pool.clone());
+    let external_queue = Data::new(get_external_delivery_queue(&configuration, &pool).await?);
+    let frequency_cap = Data::new(FrequencyCap(None));
+    let publish_checklist = Data::new(PublishChecklist(vec![]));
+    let undo_window = Data::new(UndoWindow(0));
+    let email_size_budget = Data::new(configuration.application.email_size_budget.clone());
+    let base_url = Data::new(ApplicationBaseUrl(
+        configuration.application.base_url.clone(),
+    ));
+    let data = ApiPublishData {
+        pool: &db_pool,
+        external_queue: &external_queue,
+        frequency_cap: &frequency_cap,
+        publish_checklist: &publish_checklist,
+        undo_window: &undo_window,
+        email_size_budget: &email_size_budget,
+        base_url: &base_url,
+    };
+    let input = PublishIssueInput {
+        title: format!("Load test {}", run_id),
+        text_content: "Synthetic issue of a load test.".to_string(),
+        html_content: "<p>Synthetic issue of a load test.</p>".to_string(),
+        markdown_content: None,
+        scheduled_at: None,
+        collect_feedback: false,
+        delivery_weight: 1,
+        optimize_send_time: false,
+        list_id: Some(list_id),
+        checked_items: vec![],
+    };
+    let started = Instant::now();
+    let issue_id = publish_issue_via_api(&data, input).await?;
+    tracing::info!(%issue_id, "Published synthetic issue.");
+
+    // settings are not reloaded during a load test
+    let (_, tunables) = watch::channel(TunableSettings::from(&configuration));
+    let worker = tokio::spawn(run_delivery_worker_until_stopped(
+        configuration.clone(),
+        tunables,
+    ));
+    let result =
+        measure_deliveries(&pool, issue_id, num_subscribers as usize, started, &worker).await;
+    // dropping the handle would detach the worker instead of stopping it
+    worker.abort();
+    let (latencies, num_failed) = result?;
+    Ok(LoadReport {
+        list_id,
+        issue_id,
+        num_emails: latencies.len(),
+        num_failed,
+        worker_concurrency: configuration.emailclient.worker_concurrency.max(1),
+        batch_size: configuration.emailclient.batch_size,
+        duration: latencies.last().copied().unwrap_or_default(),
+        latencies,
+    })
+}
+
+#[tracing::instrument(name = "Insert synthetic subscribers", skip(pool))]
+async fn insert_synthetic_subscribers(
+    pool: &PgPool,
+    list_id: Uuid,
+    run_id: Uuid,
+    num_subscribers: u32,
+) -> Result<(), anyhow::Error> {
+    let ids: Vec<Uuid> = (0..num_subscribers).map(|_| Uuid::new_v4()).collect();
+    let emails: Vec<String> = (0..num_subscribers)
+        .map(|i| format!("loadgen-{}-{}@example.com", run_id.simple(), i))
+        .collect();
+    let tokens: Vec<String> = (0..num_subscribers)
+        .map(|_| {
+            SubscriberToken::generate_subscription_token()
+                .as_ref()
+                .to_string()
+        })
+        .collect();
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (id, email, name, subscribed_at, status, list_id)
+        SELECT id, email, 'Load Test', now(), 'confirmed', $3
+        FROM UNNEST($1::uuid[], $2::text[]) AS s(id, email)
+        "#,
+        &ids,
+        &emails,
+        list_id,
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to insert synthetic subscribers.")?;
+    sqlx::query!(
+        r#"
+        INSERT INTO subscription_tokens (subscription_token, subscriber_id)
+        SELECT * FROM UNNEST($1::text[], $2::uuid[])
+        "#,
+        &tokens,
+        &ids,
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to insert tokens of synthetic subscribers.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit synthetic subscribers.")?;
+    Ok(())
+}
+
+/// Poll the delivery progress of the issue and record the time since publishing of
+/// each processed email. Returns the latencies and the number of failed deliveries.
+async fn measure_deliveries(
+    pool: &PgPool,
+    issue_id: Uuid,
+    num_emails: usize,
+    started: Instant,
+    worker: &tokio::task::JoinHandle<Z2PResult<()>>,
+) -> Result<(Vec<Duration>, usize), anyhow::Error> {
+    let mut latencies = Vec::with_capacity(num_emails);
+    let mut last_progress = Instant::now();
+    let mut num_failed = 0;
+    while latencies.len() < num_emails {
+        if worker.is_finished() {
+            bail!("Delivery worker stopped before all emails were processed.");
+        }
+        if last_progress.elapsed() > STALL_TIMEOUT {
+            bail!(
+                "No email was processed within {}s; {} of {} emails are processed.",
+                STALL_TIMEOUT.as_secs(),
+                latencies.len(),
+                num_emails
+            );
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                COALESCE(num_delivered_newsletters, 0) AS "num_delivered!",
+                COALESCE(num_failed_deliveries, 0) AS "num_failed!"
+            FROM newsletter_issues
+            WHERE newsletter_issue_id = $1
+            "#,
+            issue_id
+        )
+        .fetch_one(pool)
+        .await
+        .context("Failed to read delivery progress of synthetic issue.")?;
+        num_failed = row.num_failed as usize;
+        let num_processed = (row.num_delivered as usize + num_failed).min(num_emails);
+        if num_processed > latencies.len() {
+            let latency = started.elapsed();
+            latencies.resize(num_processed, latency);
+            last_progress = Instant::now();
+        }
+    }
+    Ok((latencies, num_failed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(latencies_ms: &[u64]) -> LoadReport {
+        let latencies: Vec<Duration> = latencies_ms
+            .iter()
+            .map(|ms| Duration::from_millis(*ms))
+            .collect();
+        LoadReport {
+            list_id: Uuid::nil(),
+            issue_id: Uuid::nil(),
+            num_emails: latencies.len(),
+            num_failed: 0,
+            worker_concurrency: 1,
+            batch_size: 1,
+            duration: latencies.last().copied().unwrap_or_default(),
+            latencies,
+        }
+    }
+
+    #[test]
+    fn latency_percentiles_use_nearest_rank() {
+        let report = report(&[100, 200, 300, 400, 500, 600, 700, 800, 900, 1000]);
+        assert_eq!(report.latency_percentile(50.0), Duration::from_millis(500));
+        assert_eq!(report.latency_percentile(90.0), Duration::from_millis(900));
+        assert_eq!(report.latency_percentile(99.0), Duration::from_millis(1000));
+        assert_eq!(report.latency_percentile(0.0), Duration::from_millis(100));
+        assert_eq!(report.throughput(), 10.0);
+    }
+
+    #[test]
+    fn empty_report_has_zero_latencies() {
+        let report = report(&[]);
+        assert_eq!(report.latency_percentile(99.0), Duration::ZERO);
+        assert_eq!(report.throughput(), 0.0);
+    }
+}
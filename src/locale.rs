@@ -0,0 +1,111 @@
+//! src/locale.rs
+
+use crate::domain::Locale;
+use std::collections::HashMap;
+
+/// Configured fallbacks of locales, e.g. `de: [en]` for the chain `de-at`, `de`, `en`.
+/// Each locale falls back to its less specific tags first, e.g. `de-at` to `de`. Used to
+/// select language variants of issues in the delivery worker and on public pages.
+#[derive(serde::Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct LocaleFallbacks(HashMap<Locale, Vec<Locale>>);
+
+impl LocaleFallbacks {
+    /// Locales to look up in order of preference without duplicates.
+    pub fn chain<'a>(&'a self, locale: &'a Locale) -> Vec<&'a str> {
+        let mut chain = Vec::new();
+        self.extend_chain(locale, &mut chain);
+        chain
+    }
+
+    fn extend_chain<'a>(&'a self, locale: &'a Locale, chain: &mut Vec<&'a str>) {
+        for tag in locale.fallbacks() {
+            if !chain.contains(&tag) {
+                chain.push(tag);
+            }
+            // configured fallbacks of a tag precede its less specific tags
+            for fallback in self.0.get(tag).into_iter().flatten() {
+                if !chain.contains(&fallback.as_ref()) {
+                    self.extend_chain(fallback, chain);
+                }
+            }
+        }
+    }
+
+    /// Entry of `available`, which matches the preferred locales best, with its locale.
+    /// The chains of the preferred locales are tried in order.
+    pub fn resolve<'a, V>(
+        &self,
+        preferred: &[Locale],
+        available: &'a HashMap<String, V>,
+    ) -> Option<(&'a str, &'a V)> {
+        preferred
+            .iter()
+            .flat_map(|locale| self.chain(locale))
+            .find_map(|tag| available.get_key_value(tag))
+            .map(|(tag, value)| (tag.as_str(), value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locale(s: &str) -> Locale {
+        Locale::parse(s.to_string()).unwrap()
+    }
+
+    fn fallbacks(entries: &[(&str, &[&str])]) -> LocaleFallbacks {
+        LocaleFallbacks(
+            entries
+                .iter()
+                .map(|(l, f)| (locale(l), f.iter().map(|f| locale(f)).collect()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn chain_without_configuration_falls_back_to_language() {
+        let fallbacks = LocaleFallbacks::default();
+        assert_eq!(fallbacks.chain(&locale("de-at")), vec!["de-at", "de"]);
+    }
+
+    #[test]
+    fn configured_fallbacks_extend_chain() {
+        let fallbacks = fallbacks(&[("de", &["en"]), ("de-at", &["de-de"])]);
+        assert_eq!(
+            fallbacks.chain(&locale("de-at")),
+            vec!["de-at", "de-de", "de", "en"]
+        );
+        assert_eq!(fallbacks.chain(&locale("de-ch")), vec!["de-ch", "de", "en"]);
+    }
+
+    #[test]
+    fn cyclic_fallbacks_terminate() {
+        let fallbacks = fallbacks(&[("de", &["en"]), ("en", &["de"])]);
+        assert_eq!(fallbacks.chain(&locale("en-gb")), vec!["en-gb", "en", "de"]);
+    }
+
+    #[test]
+    fn resolve_tries_preferred_locales_in_order() {
+        let fallbacks = fallbacks(&[("de", &["en"])]);
+        let available = HashMap::from([("en".to_string(), 1), ("fr".to_string(), 2)]);
+        assert_eq!(
+            fallbacks.resolve(&[locale("de-at"), locale("fr")], &available),
+            Some(("en", &1))
+        );
+        assert_eq!(
+            fallbacks.resolve(&[locale("fr-ca"), locale("de")], &available),
+            Some(("fr", &2))
+        );
+        assert_eq!(fallbacks.resolve(&[locale("it")], &available), None);
+    }
+
+    #[test]
+    fn fallbacks_are_deserialized_from_configuration() {
+        let fallbacks: LocaleFallbacks =
+            serde_json::from_str(r#"{"de-AT": ["de", "en"]}"#).unwrap();
+        assert_eq!(fallbacks.chain(&locale("de-at")), vec!["de-at", "de", "en"]);
+        assert!(serde_json::from_str::<LocaleFallbacks>(r#"{"german": ["en"]}"#).is_err());
+    }
+}
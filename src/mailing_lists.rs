@@ -0,0 +1,65 @@
+//! src/mailing_lists.rs
+
+use chrono::Utc;
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+/// List of subscriptions and issues, which did not select a list. It is created by
+/// migration, which also moved all earlier subscriptions and issues to it.
+pub const DEFAULT_LIST_ID: Uuid = Uuid::nil();
+
+#[derive(Debug, Clone)]
+pub struct MailingList {
+    pub list_id: Uuid,
+    pub name: String,
+}
+
+/// Mailing lists with the default list first and other lists ordered by name.
+#[tracing::instrument(name = "Get mailing lists", skip(pool))]
+pub async fn get_mailing_lists(pool: &PgPool) -> Result<Vec<MailingList>, sqlx::Error> {
+    sqlx::query_as!(
+        MailingList,
+        r#"
+        SELECT list_id, name
+        FROM lists
+        ORDER BY list_id <> $1, name
+        "#,
+        DEFAULT_LIST_ID,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Parse list id of a form field; an empty field selects the default list.
+/// Returns `None`, if the id is invalid or the list does not exist.
+#[tracing::instrument(name = "Parse list id", skip(executor))]
+pub async fn parse_list_id<'e>(
+    executor: impl PgExecutor<'e>,
+    list_id: &str,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let list_id = list_id.trim();
+    if list_id.is_empty() {
+        return Ok(Some(DEFAULT_LIST_ID));
+    }
+    let Ok(list_id) = Uuid::parse_str(list_id) else {
+        return Ok(None);
+    };
+    let list = sqlx::query!("SELECT list_id FROM lists WHERE list_id = $1", list_id)
+        .fetch_optional(executor)
+        .await?;
+    Ok(list.map(|r| r.list_id))
+}
+
+#[tracing::instrument(name = "Create mailing list", skip(pool))]
+pub async fn create_mailing_list(pool: &PgPool, name: &str) -> Result<Uuid, sqlx::Error> {
+    let list_id = Uuid::new_v4();
+    sqlx::query!(
+        "INSERT INTO lists (list_id, name, created_at) VALUES ($1, $2, $3)",
+        list_id,
+        name,
+        Utc::now(),
+    )
+    .execute(pool)
+    .await?;
+    Ok(list_id)
+}
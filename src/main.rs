@@ -1,32 +1,106 @@
 //! main.rs
 
+use anyhow::Context;
 use std::fmt::{Debug, Display};
 use tokio::task::JoinError;
+use zero2prod::config_reload::run_config_watcher_until_stopped;
 use zero2prod::configuration::get_configuration;
+use zero2prod::delivery_stats::backfill_delivery_stats;
 use zero2prod::error::Z2PResult;
+use zero2prod::event_export::run_event_export_worker_until_stopped;
 use zero2prod::idempotency::run_cleanup_worker_until_stopped;
+use zero2prod::instance_setup::apply_instance_settings;
 use zero2prod::issue_delivery_worker::run_delivery_worker_until_stopped;
-use zero2prod::startup::Application;
-use zero2prod::telemetry::{get_subscriber, init_subscriber};
+use zero2prod::loadgen::run_load_test;
+use zero2prod::migration_check::check_migrations;
+use zero2prod::recurring_issues::run_recurring_issues_worker_until_stopped;
+use zero2prod::startup::get_connection_pool;
+use zero2prod::startup::{shutdown_signal, Application};
+use zero2prod::telemetry::{get_subscriber, init_subscriber, shutdown_tracing};
 
 #[tokio::main]
 async fn main() -> Z2PResult<()> {
-    let subscriber = get_subscriber("zero2prod".into(), "info".into(), std::io::stdout);
-    init_subscriber(subscriber);
-
+    let args: Vec<String> = std::env::args().collect();
+    // load test: `zero2prod loadgen [number of subscribers]`
+    let loadgen = args.get(1).is_some_and(|arg| arg == "loadgen");
     // Panic if we can't read configuration
-    let configuration = get_configuration().expect("Failed to read configuration.");
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    // the sandbox mode of load tests would log each email at info level
+    let env_filter = if loadgen {
+        "warn".to_string()
+    } else {
+        configuration.application.log_level.clone()
+    };
+    let subscriber = get_subscriber(
+        "zero2prod".into(),
+        env_filter,
+        std::io::stdout,
+        configuration.otlp.as_ref(),
+    );
+    init_subscriber(subscriber);
+    if loadgen {
+        let num_subscribers = match args.get(2) {
+            Some(n) => n.parse().expect("Failed to parse number of subscribers."),
+            None => 1000,
+        };
+        let report = run_load_test(configuration, num_subscribers).await?;
+        println!("{}", report);
+        return Ok(());
+    }
+    // dry run: report migrations and exit without migrating the database
+    if args.iter().any(|arg| arg == "--check-migrations") {
+        let report = check_migrations(&get_connection_pool(&configuration.database)).await?;
+        println!("{}", report);
+        std::process::exit(if report.compatible { 0 } else { 1 });
+    }
+    // backfill: `zero2prod backfill-stats [--dry-run]` reconciles delivery counters of issues
+    if args.get(1).is_some_and(|arg| arg == "backfill-stats") {
+        let dry_run = args.iter().any(|arg| arg == "--dry-run");
+        let pool = get_connection_pool(&configuration.database);
+        let report = backfill_delivery_stats(&pool, None, dry_run)
+            .await
+            .context("Failed to backfill delivery statistics.")?;
+        println!("{}", report);
+        return Ok(());
+    }
     let application = Application::build(configuration.clone()).await?;
+    // workers use base url and sender of the initial setup as well; the database has
+    // been migrated by building the application
+    let pool = get_connection_pool(&configuration.database);
+    apply_instance_settings(&mut configuration, &pool).await?;
+    let shutdown_handle = application.shutdown_handle();
+    let tunables = application.tunable_settings();
     let application_task = tokio::spawn(application.run_until_stopped());
-    let delivery_worker_task =
-        tokio::spawn(run_delivery_worker_until_stopped(configuration.clone()));
-    let cleanup_idempotency_keys = tokio::spawn(run_cleanup_worker_until_stopped(configuration));
+    // the server stops accepting connections and drains in-flight requests, e.g. with
+    // their idempotency transactions, before the API task exits and stops all workers
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        tracing::info!("Shutdown signal received, draining in-flight requests.");
+        shutdown_handle.stop(true).await;
+    });
+    let delivery_worker_task = tokio::spawn(run_delivery_worker_until_stopped(
+        configuration.clone(),
+        tunables.subscribe(),
+    ));
+    let cleanup_idempotency_keys =
+        tokio::spawn(run_cleanup_worker_until_stopped(configuration.clone()));
+    let event_export_task =
+        tokio::spawn(run_event_export_worker_until_stopped(configuration.clone()));
+    let recurring_issues_task = tokio::spawn(run_recurring_issues_worker_until_stopped(
+        configuration.clone(),
+    ));
+    let config_watcher_task =
+        tokio::spawn(run_config_watcher_until_stopped(configuration, tunables));
 
     tokio::select! {
         o = application_task => report_exit("API", o),
         o = delivery_worker_task => report_exit("Background delivery worker", o),
         o = cleanup_idempotency_keys => report_exit("Background cleanup of idempotency keys", o),
+        o = event_export_task => report_exit("Background export of subscriber events", o),
+        o = recurring_issues_task => report_exit("Background drafts of recurring issues", o),
+        o = config_watcher_task => report_exit("Background reload of configuration", o),
     };
+    shutdown_tracing();
 
     Ok(())
 }
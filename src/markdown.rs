@@ -0,0 +1,153 @@
+//! src/markdown.rs
+
+use pulldown_cmark::{html, Event, Options, Parser, Tag, TagEnd};
+
+fn parser(markdown: &str) -> Parser<'_> {
+    Parser::new_ext(
+        markdown,
+        Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TABLES,
+    )
+}
+
+/// Render markdown as html content of a newsletter.
+pub fn render_html(markdown: &str) -> String {
+    let mut html_content = String::new();
+    html::push_html(&mut html_content, parser(markdown));
+    html_content
+}
+
+/// Derive plain text content of a newsletter from markdown.
+/// Formatting is dropped and list items are prefixed with "-" or their number.
+/// Links are replaced by numbered footnotes, which list their urls at the bottom.
+/// Raw html is skipped.
+pub fn render_text(markdown: &str) -> String {
+    let mut text = String::new();
+    // for each nested list the number of the next item, None for unordered lists
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    // urls of open links and images
+    let mut link_urls: Vec<String> = Vec::new();
+    // footnotes in order of first appearance; same urls share their number
+    let mut footnotes: Vec<String> = Vec::new();
+    for event in parser(markdown) {
+        match event {
+            Event::Start(Tag::List(first_number)) => {
+                if !lists.is_empty() && !text.ends_with('\n') {
+                    text.push('\n');
+                }
+                lists.push(first_number);
+            }
+            Event::End(TagEnd::List(_)) => {
+                lists.pop();
+                if lists.is_empty() {
+                    text.push('\n');
+                }
+            }
+            Event::Start(Tag::Item) => {
+                text.push_str(&"  ".repeat(lists.len().saturating_sub(1)));
+                match lists.last_mut() {
+                    Some(Some(number)) => {
+                        text.push_str(&format!("{}. ", number));
+                        *number += 1;
+                    }
+                    _ => text.push_str("- "),
+                }
+            }
+            Event::End(TagEnd::Item) if !text.ends_with('\n') => text.push('\n'),
+            Event::Start(Tag::Link { dest_url, .. })
+            | Event::Start(Tag::Image { dest_url, .. }) => link_urls.push(dest_url.to_string()),
+            Event::End(TagEnd::Link) | Event::End(TagEnd::Image) => {
+                if let Some(url) = link_urls.pop() {
+                    let number = match footnotes.iter().position(|f| *f == url) {
+                        Some(index) => index + 1,
+                        None => {
+                            footnotes.push(url);
+                            footnotes.len()
+                        }
+                    };
+                    text.push_str(&format!(" [{}]", number));
+                }
+            }
+            Event::End(TagEnd::Paragraph) => {
+                // paragraphs of loose list items are not separated by blank lines
+                text.push_str(if lists.is_empty() { "\n\n" } else { "\n" });
+            }
+            Event::End(TagEnd::Heading(_))
+            | Event::End(TagEnd::BlockQuote)
+            | Event::End(TagEnd::CodeBlock)
+            | Event::End(TagEnd::Table) => end_block(&mut text),
+            Event::End(TagEnd::TableRow) | Event::End(TagEnd::TableHead) => text.push('\n'),
+            Event::End(TagEnd::TableCell) => text.push('\t'),
+            Event::Text(content) | Event::Code(content) => text.push_str(&content),
+            Event::SoftBreak | Event::HardBreak => text.push('\n'),
+            Event::Rule => text.push_str("----\n\n"),
+            _ => {}
+        }
+    }
+    let mut text = text.trim_end().to_string();
+    if !footnotes.is_empty() {
+        text.push_str("\n\nLinks:");
+        for (index, url) in footnotes.iter().enumerate() {
+            text.push_str(&format!("\n[{}] {}", index + 1, url));
+        }
+    }
+    text
+}
+
+/// Separate block from following content by a blank line.
+fn end_block(text: &mut String) {
+    while !text.ends_with("\n\n") {
+        text.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markdown_is_rendered_as_html() {
+        let html_content = render_html("# Title\n\nSome **bold** text.");
+        assert_eq!(
+            html_content,
+            "<h1>Title</h1>\n<p>Some <strong>bold</strong> text.</p>\n"
+        );
+    }
+
+    #[test]
+    fn plain_text_drops_formatting() {
+        let text =
+            render_text("# Title\n\nSome **bold** and _emphasized_ text.\n\nLast paragraph.");
+        assert_eq!(
+            text,
+            "Title\n\nSome bold and emphasized text.\n\nLast paragraph."
+        );
+    }
+
+    #[test]
+    fn plain_text_links_are_numbered_footnotes() {
+        let text = render_text(
+            "Read [this](https://example.com/a) and [that](https://example.com/b).\n\n\
+            Again [this](https://example.com/a).",
+        );
+        assert_eq!(
+            text,
+            "Read this [1] and that [2].\n\nAgain this [1].\n\n\
+            Links:\n[1] https://example.com/a\n[2] https://example.com/b"
+        );
+    }
+
+    #[test]
+    fn plain_text_prefixes_list_items() {
+        let text = render_text("- first\n- second\n  1. nested\n  2. list\n\nAfter list");
+        assert_eq!(
+            text,
+            "- first\n- second\n  1. nested\n  2. list\n\nAfter list"
+        );
+    }
+
+    #[test]
+    fn plain_text_skips_raw_html() {
+        let text = render_text("Hello <span>reader</span>!");
+        assert_eq!(text, "Hello reader!");
+    }
+}
@@ -0,0 +1,116 @@
+//! src/metrics.rs
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::idempotency::NextAction;
+
+/// Delivery metrics of confirmation emails since start of the application.
+/// Confirmation emails are sent right away while subscribing, outside the
+/// newsletter delivery queue, since their latency affects signup conversion.
+pub struct ConfirmationEmailMetrics {
+    latency_slo: Duration,
+    num_sent: AtomicU64,
+    num_failed: AtomicU64,
+    num_slo_violations: AtomicU64,
+    total_latency_ms: AtomicU64,
+    max_latency_ms: AtomicU64,
+}
+
+/// Snapshot of confirmation email metrics.
+#[derive(Debug)]
+pub struct ConfirmationEmailStats {
+    pub latency_slo_ms: u64,
+    pub num_sent: u64,
+    pub num_failed: u64,
+    pub num_slo_violations: u64,
+    pub avg_latency_ms: u64,
+    pub max_latency_ms: u64,
+}
+
+impl ConfirmationEmailMetrics {
+    pub fn new(latency_slo: Duration) -> Self {
+        Self {
+            latency_slo,
+            num_sent: AtomicU64::new(0),
+            num_failed: AtomicU64::new(0),
+            num_slo_violations: AtomicU64::new(0),
+            total_latency_ms: AtomicU64::new(0),
+            max_latency_ms: AtomicU64::new(0),
+        }
+    }
+
+    /// Record latency of sending a confirmation email. Failed attempts count as SLO violation.
+    pub fn record(&self, latency: Duration, success: bool) {
+        let latency_ms = latency.as_millis() as u64;
+        if success {
+            self.num_sent.fetch_add(1, Ordering::Relaxed);
+            self.total_latency_ms
+                .fetch_add(latency_ms, Ordering::Relaxed);
+            self.max_latency_ms.fetch_max(latency_ms, Ordering::Relaxed);
+        } else {
+            self.num_failed.fetch_add(1, Ordering::Relaxed);
+        }
+        if !success || latency > self.latency_slo {
+            self.num_slo_violations.fetch_add(1, Ordering::Relaxed);
+            tracing::warn!(
+                latency_ms,
+                latency_slo_ms = self.latency_slo.as_millis() as u64,
+                success,
+                "Confirmation email missed its latency SLO."
+            );
+        }
+    }
+
+    pub fn stats(&self) -> ConfirmationEmailStats {
+        let num_sent = self.num_sent.load(Ordering::Relaxed);
+        let total_latency_ms = self.total_latency_ms.load(Ordering::Relaxed);
+        ConfirmationEmailStats {
+            latency_slo_ms: self.latency_slo.as_millis() as u64,
+            num_sent,
+            num_failed: self.num_failed.load(Ordering::Relaxed),
+            num_slo_violations: self.num_slo_violations.load(Ordering::Relaxed),
+            avg_latency_ms: total_latency_ms.checked_div(num_sent).unwrap_or(0),
+            max_latency_ms: self.max_latency_ms.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Outcomes of requests with idempotency key since start of the application. Many
+/// misses and few hits of replayed keys allow a shorter `idempotency_lifetime_minutes`.
+#[derive(Default)]
+pub struct IdempotencyMetrics {
+    /// replayed keys, which returned the saved response
+    num_hits: AtomicU64,
+    /// new keys, whose request was processed
+    num_misses: AtomicU64,
+    /// replayed keys with another payload, which were rejected
+    num_conflicts: AtomicU64,
+}
+
+/// Snapshot of idempotency metrics.
+#[derive(Debug)]
+pub struct IdempotencyStats {
+    pub num_hits: u64,
+    pub num_misses: u64,
+    pub num_conflicts: u64,
+}
+
+impl IdempotencyMetrics {
+    pub fn record(&self, next_action: &NextAction) {
+        let counter = match next_action {
+            NextAction::StartProcessing(_) => &self.num_misses,
+            NextAction::ReturnSavedResponse(_) => &self.num_hits,
+            NextAction::RejectReusedKey => &self.num_conflicts,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> IdempotencyStats {
+        IdempotencyStats {
+            num_hits: self.num_hits.load(Ordering::Relaxed),
+            num_misses: self.num_misses.load(Ordering::Relaxed),
+            num_conflicts: self.num_conflicts.load(Ordering::Relaxed),
+        }
+    }
+}
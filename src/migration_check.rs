@@ -0,0 +1,237 @@
+//! src/migration_check.rs
+
+use anyhow::Context;
+use sqlx::migrate::Migrator;
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Migrations embedded into the binary.
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    /// Applied migration differs from migration of binary.
+    ChecksumMismatch,
+    /// Migration was started, but did not complete.
+    Failed,
+}
+
+#[derive(Debug, serde::Serialize, utoipa::ToSchema)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    /// Hex encoded SHA-384 checksum of migration of binary
+    pub checksum: String,
+    pub state: MigrationState,
+}
+
+/// Embedded migrations compared with migrations applied to the database.
+#[derive(Debug, serde::Serialize, utoipa::ToSchema)]
+pub struct MigrationReport {
+    /// Binary can run with database schema: all migrations of binary are applied
+    /// unchanged and the database contains no migrations unknown to the binary.
+    pub compatible: bool,
+    pub migrations: Vec<MigrationStatus>,
+    /// Versions applied to database, which are missing in binary, e.g. applied by a newer release.
+    pub unknown_versions: Vec<i64>,
+}
+
+impl MigrationReport {
+    pub fn num_pending(&self) -> usize {
+        self.migrations
+            .iter()
+            .filter(|m| m.state == MigrationState::Pending)
+            .count()
+    }
+}
+
+impl fmt::Display for MigrationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for m in self.migrations.iter() {
+            writeln!(
+                f,
+                "{:<16} {} {} {}",
+                format!("{:?}", m.state),
+                m.version,
+                m.description,
+                m.checksum
+            )?;
+        }
+        for version in self.unknown_versions.iter() {
+            writeln!(f, "{:<16} {}", "Unknown", version)?;
+        }
+        write!(
+            f,
+            "{} pending migration(s), binary is {}compatible with database schema.",
+            self.num_pending(),
+            if self.compatible { "" } else { "not " }
+        )
+    }
+}
+
+/// Compare embedded migrations with `_sqlx_migrations` without applying any migration.
+#[tracing::instrument(skip_all)]
+pub async fn check_migrations(pool: &PgPool) -> Result<MigrationReport, anyhow::Error> {
+    let has_migrations_table: bool =
+        sqlx::query("SELECT to_regclass('_sqlx_migrations') IS NOT NULL AS exists")
+            .fetch_one(pool)
+            .await
+            .context("Failed to look up migrations table.")?
+            .try_get("exists")?;
+    let mut applied: HashMap<i64, (Vec<u8>, bool)> = HashMap::new();
+    if has_migrations_table {
+        let rows = sqlx::query("SELECT version, checksum, success FROM _sqlx_migrations")
+            .fetch_all(pool)
+            .await
+            .context("Failed to read applied migrations.")?;
+        for row in rows {
+            applied.insert(
+                row.try_get("version")?,
+                (row.try_get("checksum")?, row.try_get("success")?),
+            );
+        }
+    }
+    let migrations: Vec<MigrationStatus> = MIGRATOR
+        .iter()
+        .filter(|m| m.migration_type.is_up_migration())
+        .map(|m| {
+            let state = match applied.remove(&m.version) {
+                None => MigrationState::Pending,
+                Some((_, false)) => MigrationState::Failed,
+                Some((checksum, true)) if checksum != *m.checksum => {
+                    MigrationState::ChecksumMismatch
+                }
+                Some(_) => MigrationState::Applied,
+            };
+            MigrationStatus {
+                version: m.version,
+                description: m.description.to_string(),
+                checksum: m.checksum.iter().map(|b| format!("{:02x}", b)).collect(),
+                state,
+            }
+        })
+        .collect();
+    let mut unknown_versions: Vec<i64> = applied.into_keys().collect();
+    unknown_versions.sort_unstable();
+    let compatible = unknown_versions.is_empty()
+        && migrations
+            .iter()
+            .all(|m| m.state == MigrationState::Applied);
+    Ok(MigrationReport {
+        compatible,
+        migrations,
+        unknown_versions,
+    })
+}
+
+/// Tables and columns, which are required by subscription, login and delivery of issues.
+const CRITICAL_SCHEMA: &[(&str, &[&str])] = &[
+    (
+        "subscriptions",
+        &[
+            "id",
+            "email",
+            "name",
+            "subscribed_at",
+            "status",
+            "locale",
+            "list_id",
+            "max_emails_per_week",
+        ],
+    ),
+    ("lists", &["list_id", "name"]),
+    (
+        "subscription_tokens",
+        &["subscription_token", "subscriber_id", "expires_at"],
+    ),
+    ("users", &["user_id", "username", "password_hash", "email"]),
+    (
+        "idempotency",
+        &[
+            "user_id",
+            "idempotency_key",
+            "response_status_code",
+            "response_headers",
+            "response_body",
+            "created_at",
+            "request_hash",
+        ],
+    ),
+    (
+        "newsletter_issues",
+        &[
+            "newsletter_issue_id",
+            "title",
+            "text_content",
+            "html_content",
+            "published_at",
+            "num_current_subscribers",
+            "num_delivered_newsletters",
+            "num_failed_deliveries",
+            "collect_feedback",
+            "scheduled_at",
+            "delivery_weight",
+            "optimize_send_time",
+            "list_id",
+        ],
+    ),
+    (
+        "issue_delivery_queue",
+        &[
+            "newsletter_issue_id",
+            "user_id",
+            "n_retries",
+            "execute_after",
+            "status",
+            "task_version",
+        ],
+    ),
+];
+
+/// Database objects the binary depends on, which are missing in the schema.
+#[derive(thiserror::Error, Debug)]
+#[error("Database schema is missing {}.", .0.join(", "))]
+pub struct SchemaDriftError(pub Vec<String>);
+
+/// Verify that all critical tables and columns exist, e.g. at startup after migrating.
+#[tracing::instrument(skip_all)]
+pub async fn verify_schema(pool: &PgPool) -> Result<(), anyhow::Error> {
+    let rows = sqlx::query(
+        r#"
+        SELECT table_name::TEXT, column_name::TEXT
+        FROM information_schema.columns
+        WHERE table_schema = current_schema()
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to read database schema.")?;
+    let mut columns: HashMap<String, Vec<String>> = HashMap::new();
+    for row in rows {
+        columns
+            .entry(row.try_get("table_name")?)
+            .or_default()
+            .push(row.try_get("column_name")?);
+    }
+    let mut missing = Vec::new();
+    for (table, required_columns) in CRITICAL_SCHEMA {
+        let Some(existing_columns) = columns.get(*table) else {
+            missing.push(format!("table `{}`", table));
+            continue;
+        };
+        for column in required_columns.iter() {
+            if !existing_columns.iter().any(|c| c == column) {
+                missing.push(format!("column `{}.{}`", table, column));
+            }
+        }
+    }
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(SchemaDriftError(missing).into())
+    }
+}
@@ -0,0 +1,284 @@
+//! src/notifications.rs
+
+use crate::domain::SubscriberEmail;
+use crate::email_client::EmailClient;
+use anyhow::Context;
+use sqlx::PgPool;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Channel, on which an admin user is notified about events.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, sqlx::Type, serde::Serialize, serde::Deserialize,
+)]
+#[sqlx(type_name = "notification_channel", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationChannel {
+    Email,
+    Slack,
+    #[default]
+    None,
+}
+
+impl NotificationChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationChannel::Email => "email",
+            NotificationChannel::Slack => "slack",
+            NotificationChannel::None => "none",
+        }
+    }
+}
+
+/// Events, which admin users may be notified about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationEvent {
+    SubscriberMilestone,
+    DeliveryQuarantined,
+}
+
+impl NotificationEvent {
+    pub const ALL: [NotificationEvent; 2] = [
+        NotificationEvent::SubscriberMilestone,
+        NotificationEvent::DeliveryQuarantined,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationEvent::SubscriberMilestone => "subscriber_milestone",
+            NotificationEvent::DeliveryQuarantined => "delivery_quarantined",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            NotificationEvent::SubscriberMilestone => {
+                "The newsletter reached a milestone of confirmed subscribers"
+            }
+            NotificationEvent::DeliveryQuarantined => {
+                "A delivery task crashed the delivery worker repeatedly and has been quarantined"
+            }
+        }
+    }
+
+    fn parse(event: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|e| e.as_str() == event)
+    }
+}
+
+/// Notification preferences of an admin user; users without stored preferences
+/// are not notified.
+#[derive(Debug, Default)]
+pub struct NotificationPreferences {
+    pub channel: NotificationChannel,
+    pub slack_webhook_url: Option<String>,
+    pub events: Vec<NotificationEvent>,
+}
+
+impl NotificationPreferences {
+    pub fn is_subscribed(&self, event: &NotificationEvent) -> bool {
+        self.events.contains(event)
+    }
+}
+
+/// Notification of admin users about an event.
+pub enum AdminNotification {
+    SubscriberMilestone {
+        milestone: i32,
+    },
+    DeliveryQuarantined {
+        newsletter_issue_id: Uuid,
+        subscriber_id: Uuid,
+        n_crashes: i16,
+    },
+}
+
+impl AdminNotification {
+    pub fn event(&self) -> NotificationEvent {
+        match self {
+            AdminNotification::SubscriberMilestone { .. } => NotificationEvent::SubscriberMilestone,
+            AdminNotification::DeliveryQuarantined { .. } => NotificationEvent::DeliveryQuarantined,
+        }
+    }
+
+    fn subject(&self) -> String {
+        match self {
+            AdminNotification::SubscriberMilestone { milestone } => {
+                format!("Your newsletter reached {} subscribers", milestone)
+            }
+            AdminNotification::DeliveryQuarantined { .. } => {
+                "A delivery task has been quarantined".to_string()
+            }
+        }
+    }
+
+    fn text(&self) -> String {
+        match self {
+            AdminNotification::SubscriberMilestone { milestone } => format!(
+                "Congratulations: your newsletter reached {} confirmed subscribers!",
+                milestone
+            ),
+            AdminNotification::DeliveryQuarantined {
+                newsletter_issue_id,
+                subscriber_id,
+                n_crashes,
+            } => format!(
+                "The delivery of issue {} to subscriber {} crashed the delivery worker {} times. \
+                The task has been quarantined and its delivery counts as failed.",
+                newsletter_issue_id, subscriber_id, n_crashes
+            ),
+        }
+    }
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn get_notification_preferences(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<NotificationPreferences, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            notification_channel AS "channel: NotificationChannel",
+            slack_webhook_url,
+            notification_events
+        FROM user_preferences
+        WHERE user_id = $1
+        "#,
+        user_id,
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row
+        .map(|r| NotificationPreferences {
+            channel: r.channel,
+            slack_webhook_url: r.slack_webhook_url,
+            events: r
+                .notification_events
+                .iter()
+                .filter_map(|e| NotificationEvent::parse(e))
+                .collect(),
+        })
+        .unwrap_or_default())
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn save_notification_preferences(
+    pool: &PgPool,
+    user_id: Uuid,
+    preferences: &NotificationPreferences,
+) -> Result<(), sqlx::Error> {
+    let events: Vec<String> = preferences
+        .events
+        .iter()
+        .map(|e| e.as_str().to_string())
+        .collect();
+    sqlx::query!(
+        r#"
+        INSERT INTO user_preferences
+            (user_id, notification_channel, slack_webhook_url, notification_events)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (user_id) DO UPDATE
+        SET
+            notification_channel = EXCLUDED.notification_channel,
+            slack_webhook_url = EXCLUDED.slack_webhook_url,
+            notification_events = EXCLUDED.notification_events
+        "#,
+        user_id,
+        preferences.channel as NotificationChannel,
+        preferences.slack_webhook_url,
+        &events,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Notify all active admin users, who subscribed to the event of the notification,
+/// on their channel. Notifications are not essential, therefore failures are only logged.
+#[tracing::instrument(skip_all, fields(event = notification.event().as_str()))]
+pub async fn notify_admins(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    notification: &AdminNotification,
+) {
+    let recipients = match sqlx::query!(
+        r#"
+        SELECT
+            u.user_id,
+            u.email,
+            p.notification_channel AS "channel: NotificationChannel",
+            p.slack_webhook_url
+        FROM user_preferences p
+        JOIN users u ON u.user_id = p.user_id
+        WHERE
+            u.deactivated_at IS NULL
+            AND p.notification_channel <> 'none'
+            AND $1 = ANY(p.notification_events)
+        "#,
+        notification.event().as_str(),
+    )
+    .fetch_all(pool)
+    .await
+    {
+        Ok(recipients) => recipients,
+        Err(e) => {
+            tracing::warn!(error.cause_chain = ?e, "Failed to read notification preferences.");
+            return;
+        }
+    };
+    for recipient in recipients {
+        let result = match recipient.channel {
+            NotificationChannel::Email => {
+                send_email_notification(email_client, recipient.email, notification).await
+            }
+            NotificationChannel::Slack => {
+                send_slack_notification(recipient.slack_webhook_url, notification).await
+            }
+            NotificationChannel::None => Ok(()),
+        };
+        if let Err(e) = result {
+            tracing::warn!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                user_id = %recipient.user_id,
+                channel = recipient.channel.as_str(),
+                "Failed to notify admin user."
+            );
+        }
+    }
+}
+
+async fn send_email_notification(
+    email_client: &EmailClient,
+    email: Option<String>,
+    notification: &AdminNotification,
+) -> Result<(), anyhow::Error> {
+    let email = email.context("The user has no email address.")?;
+    let email =
+        SubscriberEmail::parse(email).context("The email address of the user is invalid.")?;
+    let text = notification.text();
+    // texts of notifications contain no user input
+    let html = format!("<p>{}</p>", text);
+    email_client
+        .send_email(&email, &notification.subject(), &html, &text)
+        .await
+        .context("Failed to send notification email.")?;
+    Ok(())
+}
+
+async fn send_slack_notification(
+    webhook_url: Option<String>,
+    notification: &AdminNotification,
+) -> Result<(), anyhow::Error> {
+    let webhook_url = webhook_url.context("The user has no Slack webhook URL.")?;
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()?
+        .post(webhook_url)
+        .json(&serde_json::json!({ "text": notification.text() }))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
@@ -0,0 +1,292 @@
+//! src/policy.rs
+
+use actix_web::http::Method;
+
+/// Role of an admin user, which is stored with the user.
+#[derive(
+    Debug, Clone, Copy, Default, sqlx::Type, PartialEq, Eq, serde::Serialize, serde::Deserialize,
+)]
+#[sqlx(type_name = "user_role", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum UserRole {
+    #[default]
+    Admin,
+    Editor,
+}
+
+impl UserRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UserRole::Admin => "admin",
+            UserRole::Editor => "editor",
+        }
+    }
+}
+
+/// Role of the caller of a request, whose permissions are checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Admin,
+    Editor,
+    /// Integrations calling the APIs with an API key.
+    ApiClient,
+    /// Identity provider provisioning admin users with the SCIM token.
+    ScimClient,
+}
+
+impl From<UserRole> for Role {
+    fn from(role: UserRole) -> Self {
+        match role {
+            UserRole::Admin => Role::Admin,
+            UserRole::Editor => Role::Editor,
+        }
+    }
+}
+
+/// Reads do not change state, all other methods write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Read,
+    Write,
+}
+
+impl From<&Method> for Action {
+    fn from(method: &Method) -> Self {
+        if method == Method::GET || method == Method::HEAD {
+            Action::Read
+        } else {
+            Action::Write
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Allow,
+    Deny,
+}
+
+struct Rule {
+    role: Role,
+    /// Path and all paths below it, e.g. `/admin/users` matches `/admin/users/{id}/deactivate`.
+    path: &'static str,
+    /// Any action, if none.
+    action: Option<Action>,
+    decision: Decision,
+}
+
+impl Rule {
+    const fn new(role: Role, path: &'static str, decision: Decision) -> Self {
+        Self {
+            role,
+            path,
+            action: None,
+            decision,
+        }
+    }
+
+    const fn on(mut self, action: Action) -> Self {
+        self.action = Some(action);
+        self
+    }
+
+    fn matches(&self, role: Role, action: Action, path: &str) -> bool {
+        self.role == role
+            && self.action.is_none_or(|a| a == action)
+            && path
+                .strip_prefix(self.path)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+}
+
+/// The first matching rule decides; requests without matching rule are denied. Fields
+/// of the admin GraphQL API are checked as paths below its route.
+const RULES: &[Rule] = &[
+    Rule::new(Role::Admin, "/admin", Decision::Allow),
+    Rule::new(Role::Editor, "/admin/users", Decision::Deny),
+    Rule::new(Role::Editor, "/admin/api_tokens", Decision::Deny),
+    Rule::new(Role::Editor, "/admin/subscribers/import", Decision::Deny),
+    Rule::new(
+        Role::Editor,
+        "/admin/subscribers/export.csv",
+        Decision::Deny,
+    ),
+    Rule::new(Role::Editor, "/admin/suppressions", Decision::Deny).on(Action::Write),
+    Rule::new(
+        Role::Editor,
+        "/admin/api/graphql/subscribers",
+        Decision::Deny,
+    ),
+    Rule::new(
+        Role::Editor,
+        "/admin/api/graphql/subscriber",
+        Decision::Deny,
+    ),
+    Rule::new(
+        Role::Editor,
+        "/admin/api/graphql/unsubscribe",
+        Decision::Deny,
+    ),
+    Rule::new(Role::Editor, "/admin", Decision::Allow),
+    Rule::new(
+        Role::ApiClient,
+        "/admin/api/graphql/subscribers",
+        Decision::Deny,
+    ),
+    Rule::new(
+        Role::ApiClient,
+        "/admin/api/graphql/subscriber",
+        Decision::Deny,
+    ),
+    Rule::new(
+        Role::ApiClient,
+        "/admin/api/graphql/unsubscribe",
+        Decision::Deny,
+    ),
+    Rule::new(Role::ApiClient, "/admin/api/graphql", Decision::Allow),
+    Rule::new(Role::ApiClient, "/api/v1", Decision::Allow),
+    Rule::new(Role::ScimClient, "/scim/v2", Decision::Allow),
+];
+
+/// Decide, whether the role may execute the action on the route of the path.
+pub fn decide(role: Role, action: Action, path: &str) -> Decision {
+    RULES
+        .iter()
+        .find(|rule| rule.matches(role, action, path))
+        .map_or(Decision::Deny, |rule| rule.decision)
+}
+
+pub fn is_allowed(role: Role, method: &Method, path: &str) -> bool {
+    decide(role, method.into(), path) == Decision::Allow
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn admins_may_access_all_admin_routes() {
+        assert!(is_allowed(Role::Admin, &Method::GET, "/admin/dashboard"));
+        assert!(is_allowed(Role::Admin, &Method::POST, "/admin/users"));
+        assert!(is_allowed(Role::Admin, &Method::POST, "/admin/api/graphql"));
+        assert!(!is_allowed(Role::Admin, &Method::GET, "/api/v1/migrations"));
+    }
+
+    #[test]
+    fn editors_may_neither_manage_users_nor_subscriber_data() {
+        assert!(is_allowed(
+            Role::Editor,
+            &Method::POST,
+            "/admin/newsletters"
+        ));
+        assert!(is_allowed(Role::Editor, &Method::GET, "/admin/subscribers"));
+        assert!(is_allowed(
+            Role::Editor,
+            &Method::GET,
+            "/admin/suppressions"
+        ));
+        assert!(!is_allowed(
+            Role::Editor,
+            &Method::POST,
+            "/admin/suppressions"
+        ));
+        assert!(!is_allowed(Role::Editor, &Method::GET, "/admin/users"));
+        assert!(!is_allowed(
+            Role::Editor,
+            &Method::POST,
+            "/admin/api_tokens"
+        ));
+        assert!(!is_allowed(
+            Role::Editor,
+            &Method::POST,
+            &format!("/admin/users/{}/deactivate", uuid::Uuid::new_v4())
+        ));
+        assert!(!is_allowed(
+            Role::Editor,
+            &Method::GET,
+            "/admin/subscribers/export.csv"
+        ));
+        assert!(!is_allowed(
+            Role::Editor,
+            &Method::POST,
+            "/admin/subscribers/import"
+        ));
+    }
+
+    #[test]
+    fn api_clients_may_only_access_apis() {
+        assert!(is_allowed(
+            Role::ApiClient,
+            &Method::GET,
+            "/api/v1/issues/1"
+        ));
+        assert!(is_allowed(
+            Role::ApiClient,
+            &Method::POST,
+            "/admin/api/graphql"
+        ));
+        assert!(!is_allowed(
+            Role::ApiClient,
+            &Method::PATCH,
+            "/scim/v2/Users"
+        ));
+        assert!(!is_allowed(
+            Role::ApiClient,
+            &Method::GET,
+            "/admin/dashboard"
+        ));
+    }
+
+    #[test]
+    fn only_admins_may_access_subscribers_via_graphql() {
+        for field in ["subscribers", "subscriber"] {
+            let path = format!("/admin/api/graphql/{}", field);
+            assert_eq!(decide(Role::Admin, Action::Read, &path), Decision::Allow);
+            assert_eq!(decide(Role::Editor, Action::Read, &path), Decision::Deny);
+            assert_eq!(decide(Role::ApiClient, Action::Read, &path), Decision::Deny);
+        }
+        let path = "/admin/api/graphql/unsubscribe";
+        assert_eq!(decide(Role::Admin, Action::Write, path), Decision::Allow);
+        assert_eq!(decide(Role::Editor, Action::Write, path), Decision::Deny);
+        assert_eq!(decide(Role::ApiClient, Action::Write, path), Decision::Deny);
+        assert_eq!(
+            decide(
+                Role::Editor,
+                Action::Write,
+                "/admin/api/graphql/publishIssue"
+            ),
+            Decision::Allow
+        );
+        assert_eq!(
+            decide(Role::ApiClient, Action::Read, "/admin/api/graphql/issues"),
+            Decision::Allow
+        );
+    }
+
+    #[test]
+    fn scim_clients_may_only_provision_users() {
+        assert!(is_allowed(
+            Role::ScimClient,
+            &Method::PATCH,
+            "/scim/v2/Users"
+        ));
+        assert!(!is_allowed(
+            Role::ScimClient,
+            &Method::POST,
+            "/api/v1/issues"
+        ));
+        assert!(!is_allowed(Role::ScimClient, &Method::GET, "/admin/users"));
+    }
+
+    #[test]
+    fn rule_paths_match_whole_segments() {
+        assert_eq!(
+            decide(Role::Editor, Action::Read, "/admin/users_overview"),
+            Decision::Allow
+        );
+        assert_eq!(
+            decide(Role::ApiClient, Action::Read, "/api/v10"),
+            Decision::Deny
+        );
+    }
+}
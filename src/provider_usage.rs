@@ -0,0 +1,170 @@
+//! src/provider_usage.rs
+
+use crate::configuration::ProviderPlanSettings;
+use chrono::NaiveDate;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Share of the monthly limit, above which the dashboard warns about the quota.
+const QUOTA_WARNING_PERCENT: u64 = 90;
+
+/// Newsletter emails sent via the email provider in a calendar month compared with
+/// the limit of its plan.
+#[derive(Debug, Clone, Copy)]
+pub struct ProviderUsage {
+    pub num_sent: u32,
+    pub monthly_limit: u32,
+}
+
+impl ProviderUsage {
+    pub fn remaining(&self) -> u32 {
+        self.monthly_limit.saturating_sub(self.num_sent)
+    }
+
+    pub fn percent_used(&self) -> u64 {
+        match self.monthly_limit {
+            0 => 100,
+            limit => self.num_sent as u64 * 100 / limit as u64,
+        }
+    }
+
+    pub fn is_nearly_exhausted(&self) -> bool {
+        self.percent_used() >= QUOTA_WARNING_PERCENT
+    }
+}
+
+/// Estimated cost of the emails of an issue and the usage of the month, in which they
+/// are sent.
+#[derive(Debug)]
+pub struct CostEstimate {
+    pub num_emails: u64,
+    pub cost: f64,
+    pub currency: String,
+    pub usage: ProviderUsage,
+}
+
+impl CostEstimate {
+    pub fn new(plan: &ProviderPlanSettings, usage: ProviderUsage, num_emails: u64) -> Self {
+        Self {
+            num_emails,
+            cost: num_emails as f64 * plan.cost_per_thousand_emails / 1000.0,
+            currency: plan.currency.clone(),
+            usage,
+        }
+    }
+
+    pub fn cost_label(&self) -> String {
+        format!("{:.2} {}", self.cost, self.currency)
+    }
+
+    /// Warning, if the emails exceed the remaining quota of the month.
+    pub fn quota_warning(&self) -> Option<String> {
+        (self.num_emails > self.usage.remaining() as u64).then(|| {
+            format!(
+                "Sending {} emails exceeds the remaining quota of {} emails of your provider \
+                plan this month ({} of {} sent).",
+                self.num_emails,
+                self.usage.remaining(),
+                self.usage.num_sent,
+                self.usage.monthly_limit
+            )
+        })
+    }
+}
+
+/// Usage of the plan in the calendar month of `date`.
+#[tracing::instrument(skip(pool, plan))]
+pub async fn get_provider_usage(
+    pool: &PgPool,
+    plan: &ProviderPlanSettings,
+    date: NaiveDate,
+) -> Result<ProviderUsage, sqlx::Error> {
+    let num_sent = sqlx::query!(
+        r#"
+        SELECT COALESCE(SUM(num_sent), 0) AS "num_sent!"
+        FROM daily_send_volume
+        WHERE date_trunc('month', send_date) = date_trunc('month', $1::DATE)
+        "#,
+        date
+    )
+    .fetch_one(pool)
+    .await?
+    .num_sent;
+    Ok(ProviderUsage {
+        num_sent: num_sent.clamp(0, u32::MAX as i64) as u32,
+        monthly_limit: plan.monthly_email_limit,
+    })
+}
+
+/// Estimate the cost of a published issue from the number of its recipients.
+#[tracing::instrument(skip(pool, plan))]
+pub async fn estimate_issue_cost(
+    pool: &PgPool,
+    plan: &ProviderPlanSettings,
+    issue_id: Uuid,
+) -> Result<CostEstimate, sqlx::Error> {
+    let issue = sqlx::query!(
+        r#"
+        SELECT
+            num_current_subscribers,
+            COALESCE(scheduled_at, published_at)::DATE AS "send_date!"
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        "#,
+        issue_id
+    )
+    .fetch_one(pool)
+    .await?;
+    let usage = get_provider_usage(pool, plan, issue.send_date).await?;
+    let num_emails = issue.num_current_subscribers.unwrap_or(0).max(0) as u64;
+    Ok(CostEstimate::new(plan, usage, num_emails))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan() -> ProviderPlanSettings {
+        ProviderPlanSettings {
+            monthly_email_limit: 1000,
+            cost_per_thousand_emails: 1.5,
+            currency: "EUR".to_string(),
+        }
+    }
+
+    #[test]
+    fn cost_is_estimated_per_thousand_emails() {
+        let usage = ProviderUsage {
+            num_sent: 0,
+            monthly_limit: 1000,
+        };
+        let estimate = CostEstimate::new(&plan(), usage, 250);
+        assert_eq!(estimate.cost_label(), "0.38 EUR");
+    }
+
+    #[test]
+    fn emails_above_remaining_quota_are_warned_about() {
+        let usage = ProviderUsage {
+            num_sent: 900,
+            monthly_limit: 1000,
+        };
+        assert!(CostEstimate::new(&plan(), usage, 100)
+            .quota_warning()
+            .is_none());
+        let warning = CostEstimate::new(&plan(), usage, 101)
+            .quota_warning()
+            .unwrap();
+        assert!(warning.contains("remaining quota of 100 emails"));
+    }
+
+    #[test]
+    fn usage_above_limit_has_no_remaining_quota() {
+        let usage = ProviderUsage {
+            num_sent: 1200,
+            monthly_limit: 1000,
+        };
+        assert_eq!(usage.remaining(), 0);
+        assert_eq!(usage.percent_used(), 120);
+        assert!(usage.is_nearly_exhausted());
+    }
+}
@@ -0,0 +1,203 @@
+//! src/recurring_issues.rs
+
+use crate::{
+    configuration::Settings, error::Z2PResult, startup::get_connection_pool,
+    worker_heartbeat::WorkerHeartbeat,
+};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{Executor, PgPool};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Placeholder in the title of a recurring issue, which is replaced by the scheduled
+/// date of each issue, e.g. `Monday roundup {date}`.
+pub const DATE_PLACEHOLDER: &str = "{date}";
+
+/// Template of a recurring issue.
+#[derive(Debug)]
+pub struct RecurringIssue {
+    pub recurring_issue_id: Uuid,
+    pub name: String,
+    pub title: String,
+    pub text_content: String,
+    pub html_content: String,
+    pub markdown_content: String,
+    pub collect_feedback: bool,
+    pub period_days: i32,
+    pub next_scheduled_at: DateTime<Utc>,
+    pub paused: bool,
+}
+
+impl RecurringIssue {
+    fn period(&self) -> chrono::Duration {
+        chrono::Duration::days(self.period_days.into())
+    }
+
+    /// Next scheduled time, which is in the future. Occurrences missed while the
+    /// recurrence was paused or the worker was down are skipped.
+    fn next_future_scheduled_at(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let mut scheduled_at = self.next_scheduled_at;
+        while scheduled_at <= now {
+            scheduled_at += self.period();
+        }
+        scheduled_at
+    }
+
+    fn title_of_issue(&self, scheduled_at: DateTime<Utc>) -> String {
+        self.title.replace(
+            DATE_PLACEHOLDER,
+            &scheduled_at.format("%Y-%m-%d").to_string(),
+        )
+    }
+}
+
+pub async fn run_recurring_issues_worker_until_stopped(configuration: Settings) -> Z2PResult<()> {
+    let connection_pool = get_connection_pool(&configuration.database);
+    worker_loop(connection_pool).await
+}
+
+async fn worker_loop(pool: PgPool) -> Z2PResult<()> {
+    let mut heartbeat = WorkerHeartbeat::new("recurring_issues", Duration::from_secs(900));
+    loop {
+        match create_due_drafts(&pool).await {
+            Ok(num_created) => heartbeat.beat(&pool, num_created).await,
+            Err(e) => {
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Failed to create drafts of recurring issues."
+                );
+                heartbeat.beat(&pool, 0).await;
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(300)).await;
+    }
+}
+
+/// Create drafts of all recurring issues, which are scheduled within one period, and
+/// advance the recurrences to their next issue. Returns the number of created drafts.
+#[tracing::instrument(skip_all)]
+pub async fn create_due_drafts(pool: &PgPool) -> Z2PResult<u64> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to create transaction.")?;
+    let due_issues = sqlx::query_as!(
+        RecurringIssue,
+        r#"
+        SELECT *
+        FROM recurring_issues
+        WHERE NOT paused AND next_scheduled_at <= now() + make_interval(days => period_days)
+        FOR UPDATE
+        SKIP LOCKED
+        "#
+    )
+    .fetch_all(&mut *transaction)
+    .await
+    .context("Failed to read due recurring issues.")?;
+    let now = Utc::now();
+    let mut num_created = 0;
+    for issue in due_issues {
+        // the selected issues are scheduled within one period from now
+        let scheduled_at = issue.next_future_scheduled_at(now);
+        let query = sqlx::query!(
+            r#"
+            INSERT INTO newsletter_drafts (
+                draft_id,
+                title,
+                text_content,
+                html_content,
+                markdown_content,
+                collect_feedback,
+                scheduled_at,
+                updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, now())
+            "#,
+            Uuid::new_v4(),
+            issue.title_of_issue(scheduled_at),
+            issue.text_content,
+            issue.html_content,
+            issue.markdown_content,
+            issue.collect_feedback,
+            scheduled_at,
+        );
+        transaction
+            .execute(query)
+            .await
+            .context("Failed to create draft of recurring issue.")?;
+        num_created += 1;
+        let query = sqlx::query!(
+            r#"
+            UPDATE recurring_issues
+            SET next_scheduled_at = $2
+            WHERE recurring_issue_id = $1
+            "#,
+            issue.recurring_issue_id,
+            scheduled_at + issue.period(),
+        );
+        transaction
+            .execute(query)
+            .await
+            .context("Failed to advance recurring issue.")?;
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit drafts of recurring issues.")?;
+    Ok(num_created)
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn get_recurring_issues(pool: &PgPool) -> Result<Vec<RecurringIssue>, sqlx::Error> {
+    sqlx::query_as!(
+        RecurringIssue,
+        "SELECT * FROM recurring_issues ORDER BY paused, next_scheduled_at"
+    )
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn weekly_issue(next_scheduled_at: DateTime<Utc>) -> RecurringIssue {
+        RecurringIssue {
+            recurring_issue_id: Uuid::new_v4(),
+            name: "Monday roundup".to_string(),
+            title: "Roundup {date}".to_string(),
+            text_content: String::new(),
+            html_content: String::new(),
+            markdown_content: String::new(),
+            collect_feedback: false,
+            period_days: 7,
+            next_scheduled_at,
+            paused: false,
+        }
+    }
+
+    #[test]
+    fn missed_occurrences_are_skipped() {
+        let monday = Utc.with_ymd_and_hms(2024, 8, 5, 8, 0, 0).unwrap();
+        let issue = weekly_issue(monday);
+        let now = monday + chrono::Duration::days(10);
+        assert_eq!(
+            issue.next_future_scheduled_at(now),
+            monday + chrono::Duration::days(14)
+        );
+        assert_eq!(
+            issue.next_future_scheduled_at(monday - chrono::Duration::days(1)),
+            monday
+        );
+    }
+
+    #[test]
+    fn date_placeholder_is_replaced_in_title() {
+        let monday = Utc.with_ymd_and_hms(2024, 8, 5, 8, 0, 0).unwrap();
+        let issue = weekly_issue(monday);
+        assert_eq!(issue.title_of_issue(monday), "Roundup 2024-08-05");
+    }
+}
@@ -0,0 +1,102 @@
+//! src/routes/admin/api_tokens.rs
+
+use actix_web::{web, HttpResponse, Responder};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use askama_actix::Template;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::authentication::{get_api_tokens, mint_api_token, revoke_api_token, ApiToken};
+use crate::error::{Error, Z2PResult};
+use crate::utils::see_other;
+
+#[derive(Template)]
+#[template(path = "api_tokens.html")]
+struct ApiTokensTemplate {
+    flash_messages: Vec<String>,
+    tokens: Vec<ApiToken>,
+    minted_token: Option<MintedToken>,
+}
+
+/// Newly minted token, which is shown once.
+struct MintedToken {
+    name: String,
+    token: Secret<String>,
+}
+
+impl MintedToken {
+    fn token(&self) -> &str {
+        self.token.expose_secret()
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct ApiTokenFormData {
+    pub name: String,
+}
+
+pub async fn api_tokens(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<impl Responder> {
+    let flash_messages: Vec<String> = flash_messages
+        .iter()
+        .map(|m| m.content().to_string())
+        .collect();
+    let tokens = get_api_tokens(&pool)
+        .await
+        .context("Failed to read API tokens.")?;
+    Ok(ApiTokensTemplate {
+        flash_messages,
+        tokens,
+        minted_token: None,
+    })
+}
+
+/// Mint an API token for a CI job or an external tool. The token is shown in the
+/// response only, it is neither stored nor sent via flash message cookie.
+#[tracing::instrument(name = "Mint API token", skip_all, fields(name=%form.name))]
+pub async fn create_api_token(
+    form: web::Form<ApiTokenFormData>,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<HttpResponse> {
+    let name = form.name.trim();
+    if name.is_empty() {
+        FlashMessage::error("You must set a name for the API token.").send();
+        return Ok(see_other("/admin/api_tokens"));
+    }
+    let (_, token) = mint_api_token(&pool, name)
+        .await
+        .context("Failed to store API token.")?;
+    let tokens = get_api_tokens(&pool)
+        .await
+        .context("Failed to read API tokens.")?;
+    let body = ApiTokensTemplate {
+        flash_messages: vec![],
+        tokens,
+        minted_token: Some(MintedToken {
+            name: name.to_string(),
+            token,
+        }),
+    }
+    .render()
+    .context("Failed to render API tokens page.")?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(body))
+}
+
+#[tracing::instrument(name = "Revoke API token", skip(pool))]
+pub async fn revoke_token(
+    path: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<HttpResponse> {
+    let name = revoke_api_token(&pool, path.into_inner())
+        .await
+        .context("Failed to revoke API token.")?
+        .ok_or(Error::NotFound)?;
+    FlashMessage::info(format!("The API token `{}` has been revoked.", name)).send();
+    Ok(see_other("/admin/api_tokens"))
+}
@@ -0,0 +1,254 @@
+//! src/routes/admin/calendar.rs
+
+use actix_web::{web, Responder};
+use anyhow::Context;
+use askama_actix::Template;
+use chrono::{DateTime, Datelike, Months, NaiveDate, Utc};
+use sqlx::PgPool;
+
+use crate::error::{Error, Z2PResult};
+
+#[derive(serde::Deserialize, Debug)]
+pub struct CalendarQuery {
+    /// Month to display as `YYYY-MM`; current month if not set.
+    month: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CalendarStatus {
+    Draft,
+    Scheduled,
+    Sending,
+    Sent,
+}
+
+impl std::fmt::Display for CalendarStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            CalendarStatus::Draft => "draft",
+            CalendarStatus::Scheduled => "scheduled",
+            CalendarStatus::Sending => "sending",
+            CalendarStatus::Sent => "sent",
+        };
+        f.write_str(status)
+    }
+}
+
+/// Issue or draft at the time of its planned or actual delivery.
+#[derive(Debug)]
+struct CalendarEntry {
+    title: String,
+    status: CalendarStatus,
+    link: String,
+    planned_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+struct CalendarDay {
+    day: u32,
+    is_today: bool,
+    entries: Vec<CalendarEntry>,
+}
+
+#[derive(Template)]
+#[template(path = "calendar.html")]
+struct CalendarTemplate {
+    month_label: String,
+    previous_month: String,
+    next_month: String,
+    /// weeks from Monday to Sunday; days outside of month are `None`
+    weeks: Vec<Vec<Option<CalendarDay>>>,
+}
+
+/// Month grid of published and scheduled issues and of drafts with a scheduled time.
+/// Issues are placed at their scheduled time or, if not scheduled, at publishing.
+#[tracing::instrument(name = "Show calendar of issues", skip(pool))]
+pub async fn issue_calendar(
+    query: web::Query<CalendarQuery>,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<impl Responder> {
+    let today = Utc::now().date_naive();
+    let first_day = match query.month {
+        Some(ref month) => NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d")
+            .map_err(|_| Error::NotFound)?,
+        None => today.with_day(1).expect("First day of month is valid."),
+    };
+    let (previous_month, next_month) = first_day
+        .checked_sub_months(Months::new(1))
+        .zip(first_day.checked_add_months(Months::new(1)))
+        .ok_or(Error::NotFound)?;
+    let start = first_day.and_hms_opt(0, 0, 0).unwrap().and_utc();
+    let end = next_month.and_hms_opt(0, 0, 0).unwrap().and_utc();
+    let mut entries = get_issue_entries(&pool, start, end)
+        .await
+        .context("Failed to read issues of calendar month.")?;
+    entries.extend(
+        get_draft_entries(&pool, start, end)
+            .await
+            .context("Failed to read drafts of calendar month.")?,
+    );
+    Ok(CalendarTemplate {
+        month_label: first_day.format("%B %Y").to_string(),
+        previous_month: previous_month.format("%Y-%m").to_string(),
+        next_month: next_month.format("%Y-%m").to_string(),
+        weeks: month_grid(first_day, entries, today),
+    })
+}
+
+fn month_grid(
+    first_day: NaiveDate,
+    mut entries: Vec<CalendarEntry>,
+    today: NaiveDate,
+) -> Vec<Vec<Option<CalendarDay>>> {
+    entries.sort_by_key(|entry| entry.planned_at);
+    let leading_days = first_day.weekday().num_days_from_monday() as usize;
+    let mut cells: Vec<Option<CalendarDay>> = (0..leading_days).map(|_| None).collect();
+    let mut date = first_day;
+    while date.month() == first_day.month() {
+        let (entries_of_day, remaining) = entries
+            .into_iter()
+            .partition(|entry| entry.planned_at.date_naive() == date);
+        entries = remaining;
+        cells.push(Some(CalendarDay {
+            day: date.day(),
+            is_today: date == today,
+            entries: entries_of_day,
+        }));
+        date = date.succ_opt().expect("Date is in range.");
+    }
+    while !cells.len().is_multiple_of(7) {
+        cells.push(None);
+    }
+    let mut weeks = Vec::with_capacity(cells.len() / 7);
+    let mut cells = cells.into_iter();
+    loop {
+        let week: Vec<_> = cells.by_ref().take(7).collect();
+        if week.is_empty() {
+            break;
+        }
+        weeks.push(week);
+    }
+    weeks
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_issue_entries(
+    pool: &PgPool,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<CalendarEntry>, sqlx::Error> {
+    let now = Utc::now();
+    let issues = sqlx::query!(
+        r#"
+        SELECT
+            newsletter_issue_id,
+            title,
+            COALESCE(scheduled_at, published_at) AS "planned_at!",
+            num_current_subscribers,
+            num_delivered_newsletters,
+            num_failed_deliveries
+        FROM newsletter_issues
+        WHERE COALESCE(scheduled_at, published_at) >= $1
+            AND COALESCE(scheduled_at, published_at) < $2
+        "#,
+        start,
+        end,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(issues
+        .into_iter()
+        .map(|issue| {
+            let num_finished = issue.num_delivered_newsletters.unwrap_or_default()
+                + issue.num_failed_deliveries.unwrap_or_default();
+            let status = if issue.planned_at > now {
+                CalendarStatus::Scheduled
+            } else if num_finished < issue.num_current_subscribers.unwrap_or_default() {
+                CalendarStatus::Sending
+            } else {
+                CalendarStatus::Sent
+            };
+            CalendarEntry {
+                title: issue.title,
+                status,
+                link: format!(
+                    "/admin/delivery_overview?newsletter_issue_id={}",
+                    issue.newsletter_issue_id
+                ),
+                planned_at: issue.planned_at,
+            }
+        })
+        .collect())
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_draft_entries(
+    pool: &PgPool,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<CalendarEntry>, sqlx::Error> {
+    let drafts = sqlx::query!(
+        r#"
+        SELECT draft_id, title, scheduled_at AS "scheduled_at!"
+        FROM newsletter_drafts
+        WHERE scheduled_at >= $1 AND scheduled_at < $2
+        "#,
+        start,
+        end,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(drafts
+        .into_iter()
+        .map(|draft| CalendarEntry {
+            title: draft.title,
+            status: CalendarStatus::Draft,
+            link: format!("/admin/newsletters?draft_id={}", draft.draft_id),
+            planned_at: draft.scheduled_at,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn entry(planned_at: DateTime<Utc>) -> CalendarEntry {
+        CalendarEntry {
+            title: "Issue".to_string(),
+            status: CalendarStatus::Sent,
+            link: String::new(),
+            planned_at,
+        }
+    }
+
+    #[test]
+    fn month_grid_starts_on_monday_and_fills_full_weeks() {
+        // July 2024 starts on a Monday and ends on a Wednesday
+        let first_day = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+        let weeks = month_grid(first_day, vec![], first_day);
+        assert_eq!(weeks.len(), 5);
+        assert!(weeks.iter().all(|week| week.len() == 7));
+        assert_eq!(weeks[0][0].as_ref().unwrap().day, 1);
+        assert!(weeks[0][0].as_ref().unwrap().is_today);
+        assert_eq!(weeks[4][2].as_ref().unwrap().day, 31);
+        assert!(weeks[4][3].is_none());
+    }
+
+    #[test]
+    fn entries_are_placed_on_their_day() {
+        // August 2024 starts on a Thursday
+        let first_day = NaiveDate::from_ymd_opt(2024, 8, 1).unwrap();
+        let entries = vec![
+            entry(Utc.with_ymd_and_hms(2024, 8, 2, 18, 0, 0).unwrap()),
+            entry(Utc.with_ymd_and_hms(2024, 8, 2, 8, 0, 0).unwrap()),
+        ];
+        let weeks = month_grid(first_day, entries, first_day);
+        assert!(weeks[0][2].is_none());
+        let day = weeks[0][4].as_ref().unwrap();
+        assert_eq!(day.day, 2);
+        assert_eq!(day.entries.len(), 2);
+        assert!(day.entries[0].planned_at < day.entries[1].planned_at);
+    }
+}
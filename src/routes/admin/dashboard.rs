@@ -1,22 +1,56 @@
 //! src/routes/admin/dashboard.rs
 
+use actix_web::http::Method;
 use actix_web::{web, Responder};
+use anyhow::Context;
 use askama_actix::Template;
+use chrono::Utc;
 use sqlx::PgPool;
 
 use crate::authentication::UserId;
 use crate::error::Z2PResult;
+use crate::metrics::{ConfirmationEmailMetrics, ConfirmationEmailStats};
+use crate::policy::{is_allowed, UserRole};
+use crate::provider_usage::{get_provider_usage, ProviderUsage};
+use crate::startup::ProviderPlan;
+use crate::subscriber_milestones::{get_recent_milestone, ReachedMilestone};
 
 #[derive(Template)]
 #[template(path = "dashboard.html")]
 struct DashboardTemplate {
     username: String,
+    milestone: Option<ReachedMilestone>,
+    confirmation_emails: ConfirmationEmailStats,
+    /// Usage of the plan of the email provider this month, if configured
+    provider_usage: Option<ProviderUsage>,
+    can_manage_users: bool,
+    can_import_subscribers: bool,
 }
 
 pub async fn admin_dashboard(
     pool: web::Data<PgPool>,
     user_id: web::ReqData<UserId>,
+    role: web::ReqData<UserRole>,
+    confirmation_metrics: web::Data<ConfirmationEmailMetrics>,
+    provider_plan: web::Data<ProviderPlan>,
 ) -> Z2PResult<impl Responder> {
     let username = user_id.get_username(&pool).await?;
-    Ok(DashboardTemplate { username })
+    let role = role.into_inner().into();
+    let milestone = get_recent_milestone(&pool).await?;
+    let provider_usage = match provider_plan.0 {
+        Some(ref plan) => Some(
+            get_provider_usage(&pool, plan, Utc::now().date_naive())
+                .await
+                .context("Failed to read usage of provider plan.")?,
+        ),
+        None => None,
+    };
+    Ok(DashboardTemplate {
+        username,
+        milestone,
+        confirmation_emails: confirmation_metrics.stats(),
+        provider_usage,
+        can_manage_users: is_allowed(role, &Method::GET, "/admin/users"),
+        can_import_subscribers: is_allowed(role, &Method::GET, "/admin/subscribers/import"),
+    })
 }
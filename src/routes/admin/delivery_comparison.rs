@@ -0,0 +1,163 @@
+//! src/routes/admin/delivery_comparison.rs
+
+use actix_web::{web, HttpRequest, Responder};
+use anyhow::Context;
+use askama_actix::Template;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::error::Z2PResult;
+
+/// Engagement of a newsletter issue, counted from the events of its recipients.
+#[derive(Debug)]
+struct IssueEngagement {
+    newsletter_issue_id: Uuid,
+    title: String,
+    published_at: DateTime<Utc>,
+    num_recipients: i64,
+    num_delivered: i64,
+    num_opened: i64,
+    num_unsubscribed: i64,
+}
+
+impl IssueEngagement {
+    /// Share of recipients, to whom the issue has been delivered.
+    fn delivery_rate(&self) -> f64 {
+        percentage(self.num_delivered, self.num_recipients)
+    }
+
+    /// Share of delivered emails, which have been opened.
+    fn open_rate(&self) -> f64 {
+        percentage(self.num_opened, self.num_delivered)
+    }
+
+    /// Share of delivered emails, which were the last issue before an unsubscribe.
+    fn unsubscribe_rate(&self) -> f64 {
+        percentage(self.num_unsubscribed, self.num_delivered)
+    }
+}
+
+fn percentage(part: i64, total: i64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / total as f64
+    }
+}
+
+/// Bars of one rate, one bar per compared issue.
+struct RateChart {
+    name: &'static str,
+    bars: Vec<(String, f64)>,
+}
+
+#[derive(Template)]
+#[template(path = "delivery_comparison.html")]
+struct DeliveryComparisonTemplate {
+    /// all issues to select from, newest first
+    issues: Vec<(Uuid, String)>,
+    selected: Vec<Uuid>,
+    compared: Vec<IssueEngagement>,
+    charts: Vec<RateChart>,
+}
+
+/// Compare rates of two or more issues side by side. Issues are selected by
+/// repeated `issue_id` query parameters.
+#[tracing::instrument(name = "Compare delivery of issues", skip(request, pool))]
+pub async fn delivery_comparison(
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<impl Responder> {
+    let mut selected: Vec<Uuid> = request
+        .full_url()
+        .query_pairs()
+        .filter(|(key, _)| key == "issue_id")
+        .filter_map(|(_, value)| Uuid::parse_str(&value).ok())
+        .collect();
+    selected.sort();
+    selected.dedup();
+    let issues = sqlx::query!(
+        r#"
+        SELECT newsletter_issue_id, title
+        FROM newsletter_issues
+        ORDER BY published_at DESC
+        "#
+    )
+    .fetch_all(pool.as_ref())
+    .await
+    .context("Failed to read newsletter issues")?
+    .into_iter()
+    .map(|r| (r.newsletter_issue_id, r.title))
+    .collect();
+    let compared = if selected.len() >= 2 {
+        get_issue_engagement(&pool, &selected)
+            .await
+            .context("Failed to read engagement of newsletter issues")?
+    } else {
+        Vec::new()
+    };
+    let chart = |name, rate: fn(&IssueEngagement) -> f64| RateChart {
+        name,
+        bars: compared
+            .iter()
+            .map(|i| (i.title.clone(), rate(i)))
+            .collect(),
+    };
+    let charts = vec![
+        chart("Delivery rate", IssueEngagement::delivery_rate),
+        chart("Open rate", IssueEngagement::open_rate),
+        chart("Unsubscribe rate", IssueEngagement::unsubscribe_rate),
+    ];
+    Ok(DeliveryComparisonTemplate {
+        issues,
+        selected,
+        compared,
+        charts,
+    })
+}
+
+/// Count recipients, deliveries, opens and unsubscribes of issues in order of publishing.
+/// An unsubscribe is attributed to the last issue the subscriber received before.
+#[tracing::instrument(skip(pool))]
+async fn get_issue_engagement(
+    pool: &PgPool,
+    issue_ids: &[Uuid],
+) -> Result<Vec<IssueEngagement>, sqlx::Error> {
+    sqlx::query_as!(
+        IssueEngagement,
+        r#"
+        SELECT
+            i.newsletter_issue_id,
+            i.title,
+            i.published_at,
+            COALESCE(i.num_current_subscribers, 0)::bigint AS "num_recipients!",
+            COALESCE(i.num_delivered_newsletters, 0)::bigint AS "num_delivered!",
+            (
+                SELECT COUNT(DISTINCT o.subscriber_id)
+                FROM subscriber_events o
+                WHERE o.newsletter_issue_id = i.newsletter_issue_id AND o.kind = 'opened'
+            ) AS "num_opened!",
+            (
+                SELECT COUNT(*)
+                FROM subscriber_events u
+                WHERE u.kind = 'unsubscribed' AND (
+                    SELECT r.newsletter_issue_id
+                    FROM subscriber_events r
+                    WHERE
+                        r.subscriber_id = u.subscriber_id AND
+                        r.kind = 'received_issue' AND
+                        r.occurred_at <= u.occurred_at
+                    ORDER BY r.occurred_at DESC, r.event_id DESC
+                    LIMIT 1
+                ) = i.newsletter_issue_id
+            ) AS "num_unsubscribed!"
+        FROM newsletter_issues i
+        WHERE i.newsletter_issue_id = ANY($1)
+        ORDER BY i.published_at
+        "#,
+        issue_ids
+    )
+    .fetch_all(pool)
+    .await
+}
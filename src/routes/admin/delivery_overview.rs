@@ -1,18 +1,32 @@
 //! src/routes/admin/delivery_overview.rs
 
-use actix_web::{web, Responder};
+use actix_web::{web, HttpResponse, Responder};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
 use anyhow::Context;
 use askama_actix::Template;
 use chrono::{DateTime, Utc};
-use sqlx::PgPool;
+use sqlx::{PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
+use crate::delivery_queue::TASK_VERSION;
+use crate::delivery_stats::backfill_delivery_stats;
 use crate::error::Z2PResult;
+use crate::frequency_cap::count_capped_sends;
+use crate::issue_delivery_worker::{
+    email_template_version, notify_delivery_worker, DeliveryTaskStatus,
+};
+use crate::startup::ExternalDeliveryQueue;
+use crate::utils::see_other;
 
 #[derive(Template)]
 #[template(path = "delivery_overview.html")]
 struct DeliveryOverview {
+    flash_messages: Vec<String>,
     issue_to_display: Option<NewsletterIssue>,
+    queue: Option<QueueSummary>,
+    /// recipients skipped because of the frequency cap
+    num_capped_sends: i64,
+    feedback: Option<FeedbackSummary>,
     newsletters: Vec<NewsletterIssue>,
 }
 
@@ -26,6 +40,47 @@ struct NewsletterIssue {
     num_current_subscribers: Option<i32>,
     num_delivered_newsletters: Option<i32>,
     num_failed_deliveries: Option<i32>,
+    collect_feedback: bool,
+    scheduled_at: Option<DateTime<Utc>>,
+    delivery_weight: i32,
+    template_version: Option<String>,
+    cancellable_until: Option<DateTime<Utc>>,
+}
+
+impl NewsletterIssue {
+    /// Email templates changed since first email of issue was sent.
+    fn is_template_outdated(&self) -> bool {
+        self.template_version
+            .as_ref()
+            .is_some_and(|version| *version != email_template_version())
+    }
+
+    /// Issue can be cancelled, since its undo window has not passed yet.
+    fn is_cancellable(&self) -> bool {
+        self.cancellable_until
+            .is_some_and(|cancellable_until| cancellable_until > Utc::now())
+    }
+
+    /// Delivery of issue has not started yet, since it is scheduled for a future time.
+    fn is_scheduled(&self) -> bool {
+        self.scheduled_at
+            .is_some_and(|scheduled_at| scheduled_at > Utc::now())
+    }
+}
+
+/// Remaining delivery tasks of an issue by status.
+#[derive(Debug)]
+struct QueueSummary {
+    num_pending: i64,
+    num_paused: i64,
+    num_cancelled: i64,
+}
+
+#[derive(Debug)]
+struct FeedbackSummary {
+    num_useful: i64,
+    num_not_useful: i64,
+    comments: Vec<String>,
 }
 
 #[derive(serde::Deserialize, Debug)]
@@ -33,10 +88,32 @@ pub struct QueryData {
     newsletter_issue_id: Uuid,
 }
 
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryAction {
+    Pause,
+    Resume,
+    Cancel,
+    RetryFailed,
+    ReconcileStats,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+pub struct DeliveryActionFormData {
+    pub newsletter_issue_id: Uuid,
+    pub action: DeliveryAction,
+}
+
 pub async fn delivery_overview(
+    flash_messages: IncomingFlashMessages,
     query: Option<web::Query<QueryData>>,
     pool: web::Data<PgPool>,
+    external_queue: web::Data<ExternalDeliveryQueue>,
 ) -> Z2PResult<impl Responder> {
+    let flash_messages: Vec<String> = flash_messages
+        .iter()
+        .map(|m| m.content().to_string())
+        .collect();
     let newsletters = get_newsletters_info(&pool)
         .await
         .context("Failed to read infos of all newsletters")?;
@@ -48,18 +125,266 @@ pub async fn delivery_overview(
     } else {
         None
     };
+    let feedback = match issue_to_display {
+        Some(ref issue) if issue.collect_feedback => Some(
+            get_feedback_summary(&pool, issue.newsletter_issue_id)
+                .await
+                .context("Failed to read feedback of newsletter")?,
+        ),
+        _ => None,
+    };
+    let queue = match issue_to_display {
+        Some(ref issue) => Some(
+            get_queue_summary(&pool, &external_queue, issue.newsletter_issue_id)
+                .await
+                .context("Failed to read delivery tasks of newsletter")?,
+        ),
+        None => None,
+    };
+    let num_capped_sends = match issue_to_display {
+        Some(ref issue) => count_capped_sends(&pool, issue.newsletter_issue_id)
+            .await
+            .context("Failed to count frequency capped sends of newsletter")?,
+        None => 0,
+    };
     Ok(DeliveryOverview {
+        flash_messages,
         issue_to_display,
+        queue,
+        num_capped_sends,
+        feedback,
         newsletters,
     })
 }
 
+/// Pause, resume or cancel the remaining delivery tasks of an issue, re-enqueue
+/// delivery to recipients whose delivery failed or reconcile its delivery counters.
+#[tracing::instrument(
+    name = "Change delivery of newsletter issue",
+    skip(pool, external_queue)
+)]
+pub async fn change_delivery(
+    form: web::Form<DeliveryActionFormData>,
+    pool: web::Data<PgPool>,
+    external_queue: web::Data<ExternalDeliveryQueue>,
+) -> Z2PResult<HttpResponse> {
+    let DeliveryActionFormData {
+        newsletter_issue_id,
+        action,
+    } = form.into_inner();
+    let redirect = see_other(&format!(
+        "/admin/delivery_overview?newsletter_issue_id={}",
+        newsletter_issue_id
+    ));
+    let (from, to, message): (&[DeliveryTaskStatus], _, _) = match action {
+        DeliveryAction::Pause => (
+            &[DeliveryTaskStatus::Pending],
+            DeliveryTaskStatus::Paused,
+            "Delivery has been paused",
+        ),
+        DeliveryAction::Resume => (
+            &[DeliveryTaskStatus::Paused],
+            DeliveryTaskStatus::Pending,
+            "Delivery has been resumed",
+        ),
+        DeliveryAction::Cancel => (
+            &[DeliveryTaskStatus::Pending, DeliveryTaskStatus::Paused],
+            DeliveryTaskStatus::Cancelled,
+            "Delivery has been cancelled",
+        ),
+        DeliveryAction::RetryFailed => {
+            let num_tasks = retry_failed_deliveries(&pool, &external_queue, newsletter_issue_id)
+                .await
+                .context("Failed to re-enqueue failed deliveries")?;
+            FlashMessage::info(format!(
+                "Delivery has been retried for {} recipients.",
+                num_tasks
+            ))
+            .send();
+            return Ok(redirect);
+        }
+        DeliveryAction::ReconcileStats => {
+            let report = backfill_delivery_stats(&pool, Some(newsletter_issue_id), false)
+                .await
+                .context("Failed to reconcile delivery statistics")?;
+            let message = if !report.skipped_in_flight.is_empty() {
+                "Delivery statistics can not be reconciled while deliveries are in flight."
+            } else if !report.corrections.is_empty() {
+                "Delivery statistics have been corrected from the delivery log."
+            } else {
+                "Delivery statistics match the delivery log."
+            };
+            FlashMessage::info(message).send();
+            return Ok(redirect);
+        }
+    };
+    let num_tasks = match external_queue.0 {
+        Some(ref queue) => queue.update_status(newsletter_issue_id, from, to).await,
+        None => update_delivery_task_status(&pool, newsletter_issue_id, from, to)
+            .await
+            .map_err(Into::into),
+    }
+    .context("Failed to change status of delivery tasks")?;
+    FlashMessage::info(format!("{} for {} recipients.", message, num_tasks)).send();
+    Ok(redirect)
+}
+
+#[tracing::instrument(skip(pool))]
+async fn update_delivery_task_status(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+    from: &[DeliveryTaskStatus],
+    to: DeliveryTaskStatus,
+) -> Result<u64, sqlx::Error> {
+    let mut num_tasks = 0;
+    for status in from {
+        num_tasks += sqlx::query!(
+            r#"
+            UPDATE issue_delivery_queue
+            SET status = $3
+            WHERE
+                newsletter_issue_id = $1 AND
+                status = $2
+            "#,
+            newsletter_issue_id,
+            status as &DeliveryTaskStatus,
+            &to as &DeliveryTaskStatus,
+        )
+        .execute(pool)
+        .await?
+        .rows_affected();
+    }
+    Ok(num_tasks)
+}
+
+/// Enqueue new delivery tasks with reset retry counter for confirmed subscribers, whose
+/// last delivery of the issue failed, and remove them from the failed deliveries.
+#[tracing::instrument(skip(pool, external_queue))]
+async fn retry_failed_deliveries(
+    pool: &PgPool,
+    external_queue: &ExternalDeliveryQueue,
+    newsletter_issue_id: Uuid,
+) -> Result<u64, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let subscriber_ids = get_failed_recipients(&mut transaction, newsletter_issue_id).await?;
+    let num_tasks = match external_queue.0 {
+        Some(ref queue) => {
+            queue
+                .enqueue_missing(newsletter_issue_id, &subscriber_ids, Utc::now())
+                .await?
+        }
+        None => sqlx::query!(
+            r#"
+            INSERT INTO issue_delivery_queue (
+                newsletter_issue_id,
+                user_id,
+                n_retries,
+                execute_after,
+                task_version
+            )
+            SELECT $1, failed.user_id, 0, now(), $2
+            FROM UNNEST($3::uuid[]) AS failed(user_id)
+            WHERE NOT EXISTS (
+                SELECT 1
+                FROM issue_delivery_queue q
+                WHERE q.newsletter_issue_id = $1 AND q.user_id = failed.user_id
+            )
+            "#,
+            newsletter_issue_id,
+            TASK_VERSION,
+            &subscriber_ids,
+        )
+        .execute(&mut *transaction)
+        .await?
+        .rows_affected(),
+    };
+    sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET num_failed_deliveries = GREATEST(num_failed_deliveries - $2, 0)
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id,
+        num_tasks as i32,
+    )
+    .execute(&mut *transaction)
+    .await?;
+    notify_delivery_worker(&mut *transaction).await?;
+    transaction.commit().await?;
+    Ok(num_tasks)
+}
+
+/// Confirmed subscribers, whose last delivery of the issue failed.
+#[tracing::instrument(skip(transaction))]
+async fn get_failed_recipients(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+) -> Result<Vec<Uuid>, sqlx::Error> {
+    let subscriber_ids = sqlx::query_scalar!(
+        r#"
+        SELECT last_delivery.subscriber_id
+        FROM (
+            SELECT DISTINCT ON (subscriber_id) subscriber_id, kind
+            FROM subscriber_events
+            WHERE
+                newsletter_issue_id = $1 AND
+                kind IN ('received_issue', 'delivery_failed')
+            ORDER BY subscriber_id, occurred_at DESC, event_id DESC
+        ) last_delivery
+        JOIN subscriptions s ON s.id = last_delivery.subscriber_id
+        WHERE
+            last_delivery.kind = 'delivery_failed' AND
+            s.status = 'confirmed'
+        "#,
+        newsletter_issue_id,
+    )
+    .fetch_all(&mut **transaction)
+    .await?;
+    Ok(subscriber_ids)
+}
+
+#[tracing::instrument(skip(pool, external_queue))]
+async fn get_queue_summary(
+    pool: &PgPool,
+    external_queue: &ExternalDeliveryQueue,
+    newsletter_issue_id: Uuid,
+) -> Result<QueueSummary, anyhow::Error> {
+    if let Some(ref queue) = external_queue.0 {
+        return Ok(QueueSummary {
+            num_pending: queue
+                .count_tasks(newsletter_issue_id, DeliveryTaskStatus::Pending)
+                .await? as i64,
+            num_paused: queue
+                .count_tasks(newsletter_issue_id, DeliveryTaskStatus::Paused)
+                .await? as i64,
+            num_cancelled: queue
+                .count_tasks(newsletter_issue_id, DeliveryTaskStatus::Cancelled)
+                .await? as i64,
+        });
+    }
+    let summary = sqlx::query_as!(
+        QueueSummary,
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE status = 'pending') AS "num_pending!",
+            COUNT(*) FILTER (WHERE status = 'paused') AS "num_paused!",
+            COUNT(*) FILTER (WHERE status = 'cancelled') AS "num_cancelled!"
+        FROM issue_delivery_queue
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(summary)
+}
+
 #[tracing::instrument(skip_all)]
 async fn get_newsletters_info(pool: &PgPool) -> Result<Vec<NewsletterIssue>, sqlx::Error> {
     let newsletters_info = sqlx::query_as!(
         NewsletterIssue,
         r#"
-        SELECT newsletter_issue_id, title, text_content, html_content, published_at, num_current_subscribers, num_delivered_newsletters, num_failed_deliveries
+        SELECT newsletter_issue_id, title, text_content, html_content, published_at, num_current_subscribers, num_delivered_newsletters, num_failed_deliveries, collect_feedback, scheduled_at, delivery_weight, template_version, cancellable_until
         FROM newsletter_issues
         "#
     )
@@ -67,3 +392,43 @@ async fn get_newsletters_info(pool: &PgPool) -> Result<Vec<NewsletterIssue>, sql
     .await?;
     Ok(newsletters_info)
 }
+
+#[tracing::instrument(skip(pool))]
+async fn get_feedback_summary(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+) -> Result<FeedbackSummary, sqlx::Error> {
+    let counts = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE useful) AS "num_useful!",
+            COUNT(*) FILTER (WHERE NOT useful) AS "num_not_useful!"
+        FROM issue_feedback
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_one(pool)
+    .await?;
+    let comments = sqlx::query!(
+        r#"
+        SELECT comment AS "comment!"
+        FROM issue_feedback
+        WHERE
+            newsletter_issue_id = $1 AND
+            comment IS NOT NULL
+        ORDER BY submitted_at
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|r| r.comment)
+    .collect();
+    Ok(FeedbackSummary {
+        num_useful: counts.num_useful,
+        num_not_useful: counts.num_not_useful,
+        comments,
+    })
+}
@@ -0,0 +1,81 @@
+//! src/routes/admin/email.rs
+
+use actix_web::{web, HttpResponse, Responder};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use askama_actix::Template;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::authentication::UserId;
+use crate::domain::SubscriberEmail;
+use crate::error::Z2PResult;
+use crate::utils::see_other;
+
+#[derive(Template)]
+#[template(path = "email.html")]
+struct EmailTemplate {
+    flash_messages: Vec<String>,
+    email: String,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct EmailFormData {
+    pub email: String,
+}
+
+pub async fn change_email_form(
+    flash_messages: IncomingFlashMessages,
+    user_id: web::ReqData<UserId>,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<impl Responder> {
+    let flash_messages: Vec<String> = flash_messages
+        .iter()
+        .map(|m| m.content().to_string())
+        .collect();
+    let email = user_id.get_email(&pool).await?.unwrap_or_default();
+    Ok(EmailTemplate {
+        flash_messages,
+        email,
+    })
+}
+
+/// Change email address of admin, which receives test emails of newsletters.
+pub async fn change_email(
+    form: web::Form<EmailFormData>,
+    user_id: web::ReqData<UserId>,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<HttpResponse> {
+    let email = match SubscriberEmail::parse(form.0.email) {
+        Ok(email) => email,
+        Err(e) => {
+            FlashMessage::error(e.to_string()).send();
+            return Ok(see_other("/admin/email"));
+        }
+    };
+    change_email_in_db(*user_id.into_inner(), &email, &pool)
+        .await
+        .context("Failed to change email address of user.")?;
+    FlashMessage::info("Your email address has been changed.").send();
+    Ok(see_other("/admin/email"))
+}
+
+#[tracing::instrument(skip_all)]
+async fn change_email_in_db(
+    user_id: Uuid,
+    email: &SubscriberEmail,
+    pool: &PgPool,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET email = $1
+        WHERE user_id = $2
+        "#,
+        email.as_ref(),
+        user_id
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
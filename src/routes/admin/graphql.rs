@@ -0,0 +1,374 @@
+//! src/routes/admin/graphql.rs
+
+use std::sync::Arc;
+
+use actix_web::web::ReqData;
+use actix_web::{web, HttpResponse};
+use anyhow::Context as _;
+use async_graphql::{
+    ComplexObject, Context, EmptySubscription, Object, Schema, SimpleObject, Value,
+};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use super::newsletters::{
+    api_error_message, commit_published_issues, publish_issue_in_transaction,
+    publish_issue_via_api, remove_external_tasks, ApiPublishData, EmailSizeBudget,
+    PublishIssueInput,
+};
+use crate::authentication::{ApiClientId, UserId};
+use crate::delivery_queue::{DeliveryQueue, PgDeliveryQueue};
+use crate::error::{Error, Z2PResult};
+use crate::idempotency::{
+    request_hash, save_response, try_processing, IdempotencyKeyHeader, NextAction,
+    ProcessingTransaction,
+};
+use crate::metrics::IdempotencyMetrics;
+use crate::policy::{decide, Action, Decision, Role};
+use crate::routes::{remove_subscriber_from_database, SubscriptionsStatus};
+use crate::startup::{
+    ApplicationBaseUrl, ExternalDeliveryQueue, ExternalIdempotencyStore, FrequencyCap,
+    PublishChecklist, UndoWindow,
+};
+
+pub type AdminSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
+
+/// Maximum nesting of queries; the schema has no recursive types.
+const MAX_QUERY_DEPTH: usize = 8;
+
+pub fn build_admin_schema() -> AdminSchema {
+    Schema::build(QueryRoot, MutationRoot, EmptySubscription)
+        .limit_depth(MAX_QUERY_DEPTH)
+        .finish()
+}
+
+/// Fields of the admin API are checked by the policy like routes below the API route,
+/// e.g. editors may not read subscribers at `/admin/api/graphql/subscribers`.
+fn check_field_policy(ctx: &Context<'_>, action: Action) -> async_graphql::Result<()> {
+    let role = ctx.data::<Role>()?;
+    let path = format!("/admin/api/graphql/{}", ctx.field().name());
+    if decide(*role, action, &path) == Decision::Allow {
+        Ok(())
+    } else {
+        tracing::warn!(?role, path, "Rejected GraphQL field by policy.");
+        Err(async_graphql::Error::new(Error::Forbidden.to_string()))
+    }
+}
+
+/// Issues of a request with idempotency key are published in the transaction, in which
+/// the response of the request is saved.
+struct IdempotentRequest {
+    transaction: Mutex<ProcessingTransaction>,
+    published_issues: Mutex<Vec<Uuid>>,
+}
+
+/// Execute GraphQL request of admin frontends. Requests are authenticated by
+/// middleware like admin pages or with the API key.
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(name = "Execute admin GraphQL request", skip_all)]
+pub async fn admin_graphql(
+    schema: web::Data<AdminSchema>,
+    pool: web::Data<PgPool>,
+    external_queue: web::Data<ExternalDeliveryQueue>,
+    idempotency_store: web::Data<ExternalIdempotencyStore>,
+    idempotency_metrics: web::Data<IdempotencyMetrics>,
+    frequency_cap: web::Data<FrequencyCap>,
+    publish_checklist: web::Data<PublishChecklist>,
+    undo_window: web::Data<UndoWindow>,
+    email_size_budget: web::Data<EmailSizeBudget>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    idempotency_key_header: IdempotencyKeyHeader,
+    user_id: Option<ReqData<UserId>>,
+    api_client_id: Option<ReqData<ApiClientId>>,
+    role: ReqData<Role>,
+    request: web::Json<async_graphql::Request>,
+) -> Z2PResult<HttpResponse> {
+    let request = request.into_inner().data(role.into_inner());
+    let Some(idempotency_key) = idempotency_key_header.optional() else {
+        let request = request
+            .data(pool)
+            .data(external_queue)
+            .data(frequency_cap)
+            .data(publish_checklist)
+            .data(undo_window)
+            .data(email_size_budget)
+            .data(base_url);
+        return Ok(HttpResponse::Ok().json(schema.execute(request).await));
+    };
+    // the middleware provides either the logged in user or the API client
+    let user_id = match (user_id, api_client_id) {
+        (Some(user_id), _) => **user_id,
+        (None, Some(api_client_id)) => api_client_id.idempotency_owner(),
+        (None, None) => {
+            return Err(Error::UnexpectedError(anyhow::anyhow!(
+                "Caller is unknown."
+            )))
+        }
+    };
+    let hash = request_hash(&(&request.query, &request.operation_name, &request.variables))?;
+    let next_action = try_processing(
+        &pool,
+        idempotency_store.0.as_ref(),
+        &idempotency_key,
+        user_id,
+        &hash,
+    )
+    .await?;
+    idempotency_metrics.record(&next_action);
+    let transaction = match next_action {
+        NextAction::StartProcessing(t) => t,
+        NextAction::ReturnSavedResponse(saved_response) => return Ok(saved_response),
+        NextAction::RejectReusedKey => return Err(Error::IdempotencyKeyReused),
+    };
+    let idempotent_request = Arc::new(IdempotentRequest {
+        transaction: Mutex::new(transaction),
+        published_issues: Mutex::new(Vec::new()),
+    });
+    let request = request
+        .data(pool.clone())
+        .data(external_queue.clone())
+        .data(frequency_cap)
+        .data(publish_checklist)
+        .data(undo_window)
+        .data(email_size_budget)
+        .data(base_url)
+        .data(idempotent_request.clone());
+    let mut response = schema.execute(request).await;
+    let IdempotentRequest {
+        transaction,
+        published_issues,
+    } = Arc::into_inner(idempotent_request)
+        .context("Transaction of idempotent request is still in use.")?;
+    let published_issues = published_issues.into_inner();
+    if response.is_err() {
+        // the transaction is rolled back, therefore no issue of the request is published
+        for issue_id in published_issues {
+            remove_external_tasks(&external_queue, issue_id).await;
+        }
+        response.data = Value::Null;
+        return Ok(HttpResponse::Ok().json(response));
+    }
+    let response = HttpResponse::Ok().json(response);
+    commit_published_issues(
+        &pool,
+        &external_queue,
+        &published_issues,
+        save_response(
+            transaction.into_inner(),
+            idempotency_store.0.as_ref(),
+            &idempotency_key,
+            user_id,
+            response,
+        ),
+    )
+    .await
+}
+
+#[derive(SimpleObject)]
+struct Subscriber {
+    id: Uuid,
+    email: String,
+    name: String,
+    subscribed_at: DateTime<Utc>,
+    status: Option<SubscriptionsStatus>,
+}
+
+#[derive(SimpleObject)]
+#[graphql(complex)]
+struct Issue {
+    id: Uuid,
+    title: String,
+    text_content: String,
+    html_content: String,
+    published_at: DateTime<Utc>,
+    scheduled_at: Option<DateTime<Utc>>,
+    collect_feedback: bool,
+    delivery_weight: i32,
+    #[graphql(skip)]
+    num_current_subscribers: Option<i32>,
+    #[graphql(skip)]
+    num_delivered_newsletters: Option<i32>,
+    #[graphql(skip)]
+    num_failed_deliveries: Option<i32>,
+}
+
+/// Delivery progress of an issue. Pending tasks are counted in the configured queue.
+#[derive(SimpleObject)]
+struct DeliveryStats {
+    num_current_subscribers: i32,
+    num_delivered: i32,
+    num_failed: i32,
+    num_pending: u64,
+}
+
+#[ComplexObject]
+impl Issue {
+    async fn delivery_stats(&self, ctx: &Context<'_>) -> async_graphql::Result<DeliveryStats> {
+        let pool = ctx.data::<web::Data<PgPool>>()?;
+        let external_queue = ctx.data::<web::Data<ExternalDeliveryQueue>>()?;
+        let num_pending = match external_queue.0 {
+            Some(ref queue) => queue.pending_count(self.id).await,
+            None => {
+                PgDeliveryQueue::new(pool.get_ref().clone())
+                    .pending_count(self.id)
+                    .await
+            }
+        }
+        .context("Failed to count pending delivery tasks.")?;
+        Ok(DeliveryStats {
+            num_current_subscribers: self.num_current_subscribers.unwrap_or_default(),
+            num_delivered: self.num_delivered_newsletters.unwrap_or_default(),
+            num_failed: self.num_failed_deliveries.unwrap_or_default(),
+            num_pending,
+        })
+    }
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Subscribers ordered by time of subscription, newest first.
+    async fn subscribers(
+        &self,
+        ctx: &Context<'_>,
+        status: Option<SubscriptionsStatus>,
+    ) -> async_graphql::Result<Vec<Subscriber>> {
+        check_field_policy(ctx, Action::Read)?;
+        let pool = ctx.data::<web::Data<PgPool>>()?;
+        let subscribers = sqlx::query_as!(
+            Subscriber,
+            r#"
+            SELECT id, email, name, subscribed_at, status AS "status: SubscriptionsStatus"
+            FROM subscriptions
+            WHERE $1::subscriptions_status IS NULL OR status = $1
+            ORDER BY subscribed_at DESC
+            "#,
+            status as Option<SubscriptionsStatus>,
+        )
+        .fetch_all(pool.as_ref())
+        .await
+        .context("Failed to read subscribers.")?;
+        Ok(subscribers)
+    }
+
+    async fn subscriber(
+        &self,
+        ctx: &Context<'_>,
+        id: Uuid,
+    ) -> async_graphql::Result<Option<Subscriber>> {
+        check_field_policy(ctx, Action::Read)?;
+        let pool = ctx.data::<web::Data<PgPool>>()?;
+        Ok(get_subscriber(pool, id).await?)
+    }
+
+    /// Newsletter issues ordered by time of publishing, newest first.
+    async fn issues(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Issue>> {
+        let pool = ctx.data::<web::Data<PgPool>>()?;
+        let issues = sqlx::query_as!(
+            Issue,
+            r#"
+            SELECT newsletter_issue_id AS id, title, text_content, html_content, published_at,
+                scheduled_at, collect_feedback, delivery_weight, num_current_subscribers,
+                num_delivered_newsletters, num_failed_deliveries
+            FROM newsletter_issues
+            ORDER BY published_at DESC
+            "#
+        )
+        .fetch_all(pool.as_ref())
+        .await
+        .context("Failed to read newsletter issues.")?;
+        Ok(issues)
+    }
+
+    async fn issue(&self, ctx: &Context<'_>, id: Uuid) -> async_graphql::Result<Option<Issue>> {
+        let pool = ctx.data::<web::Data<PgPool>>()?;
+        let issue = sqlx::query_as!(
+            Issue,
+            r#"
+            SELECT newsletter_issue_id AS id, title, text_content, html_content, published_at,
+                scheduled_at, collect_feedback, delivery_weight, num_current_subscribers,
+                num_delivered_newsletters, num_failed_deliveries
+            FROM newsletter_issues
+            WHERE newsletter_issue_id = $1
+            "#,
+            id
+        )
+        .fetch_optional(pool.as_ref())
+        .await
+        .context("Failed to read newsletter issue.")?;
+        Ok(issue)
+    }
+}
+
+pub struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+    /// Publish issue to all confirmed subscribers and return its id.
+    async fn publish_issue(
+        &self,
+        ctx: &Context<'_>,
+        input: PublishIssueInput,
+    ) -> async_graphql::Result<Uuid> {
+        let data = ApiPublishData {
+            pool: ctx.data::<web::Data<PgPool>>()?,
+            external_queue: ctx.data::<web::Data<ExternalDeliveryQueue>>()?,
+            frequency_cap: ctx.data::<web::Data<FrequencyCap>>()?,
+            publish_checklist: ctx.data::<web::Data<PublishChecklist>>()?,
+            undo_window: ctx.data::<web::Data<UndoWindow>>()?,
+            email_size_budget: ctx.data::<web::Data<EmailSizeBudget>>()?,
+            base_url: ctx.data::<web::Data<ApplicationBaseUrl>>()?,
+        };
+        let published = match ctx.data_opt::<Arc<IdempotentRequest>>() {
+            Some(idempotent_request) => {
+                let mut transaction = idempotent_request.transaction.lock().await;
+                let published = publish_issue_in_transaction(&data, input, &mut transaction).await;
+                if let Ok(issue_id) = published {
+                    idempotent_request
+                        .published_issues
+                        .lock()
+                        .await
+                        .push(issue_id);
+                }
+                published
+            }
+            None => publish_issue_via_api(&data, input).await,
+        };
+        published.map_err(|e| async_graphql::Error::new(api_error_message(&e)))
+    }
+
+    /// Remove subscriber like their unsubscribe link does. Returns false for
+    /// unknown subscribers.
+    async fn unsubscribe(
+        &self,
+        ctx: &Context<'_>,
+        subscriber_id: Uuid,
+    ) -> async_graphql::Result<bool> {
+        check_field_policy(ctx, Action::Write)?;
+        let pool = ctx.data::<web::Data<PgPool>>()?;
+        if get_subscriber(pool, subscriber_id).await?.is_none() {
+            return Ok(false);
+        }
+        remove_subscriber_from_database(pool, subscriber_id).await?;
+        Ok(true)
+    }
+}
+
+async fn get_subscriber(pool: &PgPool, id: Uuid) -> Result<Option<Subscriber>, anyhow::Error> {
+    let subscriber = sqlx::query_as!(
+        Subscriber,
+        r#"
+        SELECT id, email, name, subscribed_at, status AS "status: SubscriptionsStatus"
+        FROM subscriptions
+        WHERE id = $1
+        "#,
+        id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to read subscriber.")?;
+    Ok(subscriber)
+}
@@ -0,0 +1,149 @@
+//! src/routes/admin/issue_trace.rs
+
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::error::{Error, Z2PResult};
+
+/// Everything recorded about the send of an issue, bundled for offline debugging.
+/// Recipients are identified by subscriber id only, so the bundle contains no emails.
+#[derive(serde::Serialize)]
+struct IssueTrace {
+    generated_at: DateTime<Utc>,
+    issue: TracedIssue,
+    /// Timeline of the issue's events, e.g. deliveries, failed deliveries and opens
+    events: Vec<TracedEvent>,
+    /// Deliveries, which are still in the queue
+    queued_deliveries: Vec<TracedDelivery>,
+    /// Recipients skipped because of the frequency cap
+    capped_sends: Vec<TracedCappedSend>,
+}
+
+#[derive(serde::Serialize)]
+struct TracedIssue {
+    newsletter_issue_id: Uuid,
+    title: String,
+    list_id: Uuid,
+    published_at: DateTime<Utc>,
+    scheduled_at: Option<DateTime<Utc>>,
+    cancellable_until: Option<DateTime<Utc>>,
+    template_version: Option<String>,
+    delivery_weight: i32,
+    num_current_subscribers: Option<i32>,
+    num_delivered_newsletters: Option<i32>,
+    num_failed_deliveries: Option<i32>,
+}
+
+#[derive(serde::Serialize)]
+struct TracedEvent {
+    event_id: i64,
+    subscriber_id: Uuid,
+    kind: String,
+    occurred_at: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize)]
+struct TracedDelivery {
+    subscriber_id: Uuid,
+    status: String,
+    n_retries: i16,
+    execute_after: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize)]
+struct TracedCappedSend {
+    subscriber_id: Uuid,
+    skipped_at: DateTime<Utc>,
+}
+
+/// Download trace of an issue as JSON file.
+#[tracing::instrument(name = "Export trace of newsletter issue", skip(pool))]
+pub async fn issue_trace(
+    newsletter_issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<HttpResponse> {
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let trace = get_issue_trace(&pool, newsletter_issue_id)
+        .await
+        .context("Failed to read trace of newsletter issue.")?
+        .ok_or(Error::NotFound)?;
+    let file_name = format!("issue-{}-trace.json", newsletter_issue_id);
+    Ok(HttpResponse::Ok()
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(file_name)],
+        })
+        .insert_header(("Cache-Control", "no-store"))
+        .json(trace))
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_issue_trace(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+) -> Result<Option<IssueTrace>, sqlx::Error> {
+    let Some(issue) = sqlx::query_as!(
+        TracedIssue,
+        r#"
+        SELECT
+            newsletter_issue_id, title, list_id, published_at, scheduled_at,
+            cancellable_until, template_version, delivery_weight,
+            num_current_subscribers, num_delivered_newsletters, num_failed_deliveries
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(None);
+    };
+    let events = sqlx::query_as!(
+        TracedEvent,
+        r#"
+        SELECT event_id, subscriber_id, kind::text AS "kind!", occurred_at
+        FROM subscriber_events
+        WHERE newsletter_issue_id = $1
+        ORDER BY occurred_at, event_id
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_all(pool)
+    .await?;
+    let queued_deliveries = sqlx::query_as!(
+        TracedDelivery,
+        r#"
+        SELECT user_id AS subscriber_id, status::text AS "status!", n_retries, execute_after
+        FROM issue_delivery_queue
+        WHERE newsletter_issue_id = $1
+        ORDER BY execute_after, user_id
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_all(pool)
+    .await?;
+    let capped_sends = sqlx::query_as!(
+        TracedCappedSend,
+        r#"
+        SELECT subscriber_id, skipped_at
+        FROM frequency_capped_sends
+        WHERE newsletter_issue_id = $1
+        ORDER BY skipped_at, subscriber_id
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(Some(IssueTrace {
+        generated_at: Utc::now(),
+        issue,
+        events,
+        queued_deliveries,
+        capped_sends,
+    }))
+}
@@ -0,0 +1,89 @@
+//! src/routes/admin/lists.rs
+
+use actix_web::{web, HttpResponse, Responder};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use askama_actix::Template;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::error::Z2PResult;
+use crate::mailing_lists::{create_mailing_list, DEFAULT_LIST_ID};
+use crate::utils::see_other;
+
+#[derive(Template)]
+#[template(path = "lists.html")]
+struct ListsTemplate {
+    flash_messages: Vec<String>,
+    lists: Vec<ListOverview>,
+}
+
+struct ListOverview {
+    list_id: Uuid,
+    name: String,
+    num_confirmed: i64,
+    num_pending_confirmation: i64,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct ListFormData {
+    pub name: String,
+}
+
+pub async fn mailing_lists(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<impl Responder> {
+    let flash_messages: Vec<String> = flash_messages
+        .iter()
+        .map(|m| m.content().to_string())
+        .collect();
+    let lists = get_list_overviews(&pool)
+        .await
+        .context("Failed to read mailing lists.")?;
+    Ok(ListsTemplate {
+        flash_messages,
+        lists,
+    })
+}
+
+pub async fn create_list(
+    form: web::Form<ListFormData>,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<HttpResponse> {
+    let name = form.0.name.trim();
+    if name.is_empty() {
+        FlashMessage::error("You must set a name for the mailing list.").send();
+        return Ok(see_other("/admin/lists"));
+    }
+    match create_mailing_list(&pool, name).await {
+        Ok(_) => FlashMessage::info(format!("Mailing list `{}` has been created.", name)).send(),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            FlashMessage::error(format!("A mailing list named `{}` exists already.", name)).send()
+        }
+        Err(e) => Err(e).context("Failed to create mailing list.")?,
+    }
+    Ok(see_other("/admin/lists"))
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_list_overviews(pool: &PgPool) -> Result<Vec<ListOverview>, sqlx::Error> {
+    sqlx::query_as!(
+        ListOverview,
+        r#"
+        SELECT
+            l.list_id,
+            l.name,
+            COUNT(s.id) FILTER (WHERE s.status = 'confirmed') AS "num_confirmed!",
+            COUNT(s.id) FILTER (WHERE s.status = 'pending_confirmation')
+                AS "num_pending_confirmation!"
+        FROM lists l
+        LEFT JOIN subscriptions s ON s.list_id = l.list_id
+        GROUP BY l.list_id
+        ORDER BY l.list_id <> $1, l.name
+        "#,
+        DEFAULT_LIST_ID,
+    )
+    .fetch_all(pool)
+    .await
+}
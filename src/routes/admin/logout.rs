@@ -1,12 +1,24 @@
 //! src/routes/admin/logout.rs
 
+use crate::authentication::{revoke_admin_session, UserId};
 use crate::error::Z2PResult;
 use crate::session_state::TypedSession;
 use crate::utils::see_other;
-use actix_web::HttpResponse;
+use actix_web::{web, HttpResponse};
 use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
 
-pub async fn log_out(session: TypedSession) -> Z2PResult<HttpResponse> {
+pub async fn log_out(
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Z2PResult<HttpResponse> {
+    if let Some(session_id) = session.get_session_id()? {
+        revoke_admin_session(&pool, **user_id, session_id)
+            .await
+            .context("Failed to remove admin session.")?;
+    }
     session.log_out();
     FlashMessage::info("You have successfully logged out.").send();
     Ok(see_other("/login"))
@@ -1,13 +1,53 @@
 //! src/routes/admin/mod.rs
 
+mod api_tokens;
+mod calendar;
 mod dashboard;
+mod delivery_comparison;
 mod delivery_overview;
+mod email;
+mod graphql;
+mod issue_trace;
+mod lists;
 mod logout;
 mod newsletters;
+mod notifications;
+mod passkeys;
 mod password;
+mod sessions;
+mod snippets;
+mod subscriber_export;
+mod subscriber_import;
+mod subscribers;
+mod suppressions;
+mod system;
+mod users;
+mod workers;
 
+pub use api_tokens::{api_tokens, create_api_token, revoke_token, ApiTokenFormData};
+pub use calendar::issue_calendar;
 pub use dashboard::admin_dashboard;
+pub use delivery_comparison::delivery_comparison;
 pub use delivery_overview::*;
+pub use email::{change_email, change_email_form, EmailFormData};
+pub use graphql::{admin_graphql, build_admin_schema, AdminSchema};
+pub use issue_trace::issue_trace;
+pub use lists::{create_list, mailing_lists, ListFormData};
 pub use logout::log_out;
 pub use newsletters::*;
+pub use notifications::{notification_preferences, save_notifications, NotificationsFormData};
+pub use passkeys::{
+    passkey_registration_options, passkeys, register_passkey, remove_passkey, PasskeyRegistration,
+};
 pub use password::*;
+pub use sessions::{admin_sessions, revoke_all_sessions, revoke_session};
+pub use snippets::{content_snippets, save_content_snippet, SnippetFormData};
+pub use subscriber_export::export_subscribers;
+pub use subscriber_import::{
+    import_subscribers, subscriber_import_form, ImportForm, MAX_IMPORT_FILE_BYTES,
+};
+pub use subscribers::{subscriber_details, subscribers};
+pub use suppressions::{add_suppression, delete_suppression, suppressions, SuppressionFormData};
+pub use system::system_status;
+pub use users::{admin_users, deactivate_user, invite_user, reactivate_user, InviteUserFormData};
+pub use workers::workers;
@@ -0,0 +1,193 @@
+//! src/routes/admin/newsletters/api_publish.rs
+
+use actix_web::web;
+use anyhow::Context;
+use async_graphql::InputObject;
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use std::future::Future;
+use uuid::Uuid;
+
+use super::{
+    check_content, check_email_size, check_snippets, end_of_undo_window, enqueue_external_tasks,
+    remove_external_tasks, store_issue_for_delivery, verify_publish_checklist, ChecklistIssue,
+    ChecklistItem, EmailSizeBudget, NewIssue, NewsletterError, MAX_DELIVERY_WEIGHT,
+};
+use crate::error::{Error, Z2PResult};
+use crate::issue_delivery_worker::notify_delivery_worker;
+use crate::mailing_lists::{parse_list_id, DEFAULT_LIST_ID};
+use crate::markdown::{render_html, render_text};
+use crate::startup::{
+    ApplicationBaseUrl, ExternalDeliveryQueue, FrequencyCap, PublishChecklist, UndoWindow,
+};
+
+/// Newsletter issue to publish via the admin GraphQL API or the JSON API. If markdown
+/// content is given, html and text content are rendered from it.
+#[derive(InputObject, serde::Deserialize, serde::Serialize, utoipa::ToSchema)]
+pub struct PublishIssueInput {
+    pub title: String,
+    #[graphql(default)]
+    #[serde(default)]
+    pub text_content: String,
+    #[graphql(default)]
+    #[serde(default)]
+    pub html_content: String,
+    pub markdown_content: Option<String>,
+    /// Time of delivery; delivered immediately if not set.
+    pub scheduled_at: Option<DateTime<Utc>>,
+    #[graphql(default)]
+    #[serde(default)]
+    pub collect_feedback: bool,
+    #[graphql(default = 1)]
+    #[serde(default = "default_delivery_weight")]
+    pub delivery_weight: i32,
+    /// Deliver to each subscriber in the hour they usually open newsletters.
+    #[graphql(default)]
+    #[serde(default)]
+    pub optimize_send_time: bool,
+    /// Mailing list of the issue; the default list if not set.
+    pub list_id: Option<Uuid>,
+    /// Items of the pre-publish checklist, which have been checked manually.
+    #[graphql(default)]
+    #[serde(default)]
+    pub checked_items: Vec<ChecklistItem>,
+}
+
+fn default_delivery_weight() -> i32 {
+    1
+}
+
+/// App data, which is required to publish an issue via API.
+pub(crate) struct ApiPublishData<'a> {
+    pub pool: &'a web::Data<PgPool>,
+    pub external_queue: &'a web::Data<ExternalDeliveryQueue>,
+    pub frequency_cap: &'a web::Data<FrequencyCap>,
+    pub publish_checklist: &'a web::Data<PublishChecklist>,
+    pub undo_window: &'a web::Data<UndoWindow>,
+    pub email_size_budget: &'a web::Data<EmailSizeBudget>,
+    pub base_url: &'a web::Data<ApplicationBaseUrl>,
+}
+
+/// Publish issue to all confirmed subscribers of its list and return its id.
+pub(crate) async fn publish_issue_via_api(
+    data: &ApiPublishData<'_>,
+    input: PublishIssueInput,
+) -> Z2PResult<Uuid> {
+    let mut transaction = data
+        .pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    let issue_id = publish_issue_in_transaction(data, input, &mut transaction).await?;
+    commit_published_issues(data.pool, data.external_queue, &[issue_id], async {
+        transaction
+            .commit()
+            .await
+            .context("Failed to commit SQL transaction to store a new newsletter issue.")
+    })
+    .await?;
+    Ok(issue_id)
+}
+
+/// Store issue in transaction of request and return its id, e.g. to commit it with
+/// the saved response of an idempotency key. Tasks of an external queue are enqueued
+/// before commit; commit with `commit_published_issues`.
+pub(crate) async fn publish_issue_in_transaction(
+    data: &ApiPublishData<'_>,
+    input: PublishIssueInput,
+    transaction: &mut Transaction<'static, Postgres>,
+) -> Z2PResult<Uuid> {
+    let pool = data.pool;
+    let (html_content, text_content) = match input.markdown_content {
+        Some(markdown) if !markdown.trim().is_empty() => {
+            (render_html(&markdown), render_text(&markdown))
+        }
+        _ => (input.html_content, input.text_content),
+    };
+    check_content(&input.title, &text_content, &html_content)?;
+    check_snippets(pool, &[&text_content, &html_content]).await?;
+    let checklist_issue = ChecklistIssue {
+        title: &input.title,
+        text_content: &text_content,
+        html_content: &html_content,
+        list_chosen: input.list_id.is_some(),
+        checked: &input.checked_items,
+    };
+    verify_publish_checklist(pool, &data.publish_checklist.0, &checklist_issue).await?;
+    // the API has no flash messages, therefore warnings are only logged
+    if let Some(warning) = check_email_size(
+        pool,
+        &data.base_url.0,
+        data.email_size_budget,
+        &input.title,
+        &html_content,
+        input.collect_feedback,
+    )
+    .await?
+    {
+        tracing::warn!(warning, "Published issue exceeds email size budget.");
+    }
+    if !(1..=MAX_DELIVERY_WEIGHT).contains(&input.delivery_weight) {
+        return Err(NewsletterError::InvalidDeliveryWeight.into());
+    }
+    let list_id = input.list_id.unwrap_or(DEFAULT_LIST_ID);
+    if parse_list_id(pool.as_ref(), &list_id.to_string())
+        .await
+        .context("Failed to read mailing list.")?
+        .is_none()
+    {
+        return Err(NewsletterError::InvalidList.into());
+    }
+    let issue = NewIssue {
+        title: &input.title,
+        text_content: &text_content,
+        html_content: &html_content,
+        collect_feedback: input.collect_feedback,
+        scheduled_at: input.scheduled_at,
+        delivery_weight: input.delivery_weight,
+        optimize_send_time: input.optimize_send_time,
+        list_id,
+        cancellable_until: end_of_undo_window(data.undo_window),
+    };
+    let (issue_id, external_deliveries) =
+        store_issue_for_delivery(transaction, &issue, data.external_queue, data.frequency_cap)
+            .await?;
+    if let Some(ref deliveries) = external_deliveries {
+        enqueue_external_tasks(data.external_queue, issue_id, deliveries).await?;
+    }
+    Ok(issue_id)
+}
+
+/// Commit published issues with `commit`, e.g. by saving the response of an
+/// idempotency key. If it fails, their tasks are removed from the external queue;
+/// afterwards the delivery worker is notified of them.
+pub(crate) async fn commit_published_issues<T>(
+    pool: &PgPool,
+    external_queue: &ExternalDeliveryQueue,
+    issue_ids: &[Uuid],
+    commit: impl Future<Output = Result<T, anyhow::Error>>,
+) -> Z2PResult<T> {
+    let committed = match commit.await {
+        Ok(committed) => committed,
+        Err(e) => {
+            for issue_id in issue_ids {
+                remove_external_tasks(external_queue, *issue_id).await;
+            }
+            return Err(e.into());
+        }
+    };
+    if external_queue.0.is_some() && !issue_ids.is_empty() {
+        notify_delivery_worker(pool)
+            .await
+            .context("Failed to notify delivery worker")?;
+    }
+    Ok(committed)
+}
+
+/// Message of an error of publishing via API; invalid input is reported by its reason.
+pub(crate) fn api_error_message(e: &Error) -> String {
+    match e {
+        Error::NewsletterError(reason) => reason.to_string(),
+        _ => e.to_string(),
+    }
+}
@@ -0,0 +1,198 @@
+//! src/routes/admin/newsletters/checklist.rs
+
+use anyhow::Context;
+use reqwest::Url;
+use scraper::{Html, Selector};
+use sqlx::PgPool;
+
+use super::seed_test::is_seed_test_acknowledged;
+use super::NewsletterError;
+use crate::error::Error;
+use crate::issue_delivery_worker::fnv1a_hash;
+
+/// Item of the pre-publish checklist. An item is satisfied, if the admin checked it
+/// in the publish form or if it is satisfied automatically.
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    async_graphql::Enum,
+    utoipa::ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ChecklistItem {
+    /// Satisfied automatically by a title, which is not blank.
+    SubjectSet,
+    /// Satisfied automatically, if a test email of the same content has been sent.
+    PreviewSent,
+    /// Satisfied automatically, if all links of the html content are absolute
+    /// `http`, `https` or `mailto` URLs.
+    LinksValidated,
+    /// Satisfied automatically, if a mailing list is selected explicitly.
+    SegmentChosen,
+    /// Satisfied automatically, if a seed test of the same content has been acknowledged.
+    SeedTestAcknowledged,
+}
+
+impl ChecklistItem {
+    pub fn description(&self) -> &'static str {
+        match self {
+            ChecklistItem::SubjectSet => "subject set",
+            ChecklistItem::PreviewSent => "preview sent",
+            ChecklistItem::LinksValidated => "links validated",
+            ChecklistItem::SegmentChosen => "segment chosen",
+            ChecklistItem::SeedTestAcknowledged => "seed test acknowledged",
+        }
+    }
+
+    /// Name of the checkbox of the item in the publish form.
+    pub fn field_name(&self) -> &'static str {
+        match self {
+            ChecklistItem::SubjectSet => "checked_subject_set",
+            ChecklistItem::PreviewSent => "checked_preview_sent",
+            ChecklistItem::LinksValidated => "checked_links_validated",
+            ChecklistItem::SegmentChosen => "checked_segment_chosen",
+            ChecklistItem::SeedTestAcknowledged => "checked_seed_test_acknowledged",
+        }
+    }
+}
+
+/// Issue to publish as seen by the checklist.
+pub(crate) struct ChecklistIssue<'a> {
+    pub title: &'a str,
+    pub text_content: &'a str,
+    pub html_content: &'a str,
+    pub list_chosen: bool,
+    /// Items checked by the admin
+    pub checked: &'a [ChecklistItem],
+}
+
+/// Verify that all items of the configured checklist are checked or satisfied
+/// automatically. Unsatisfied items are reported in order of the checklist.
+pub(crate) async fn verify_publish_checklist(
+    pool: &PgPool,
+    checklist: &[ChecklistItem],
+    issue: &ChecklistIssue<'_>,
+) -> Result<(), Error> {
+    let mut unsatisfied = Vec::new();
+    for item in checklist.iter() {
+        if issue.checked.contains(item) {
+            continue;
+        }
+        let satisfied = match item {
+            ChecklistItem::SubjectSet => !issue.title.trim().is_empty(),
+            ChecklistItem::PreviewSent => is_test_email_sent(pool, issue)
+                .await
+                .context("Failed to read sent test emails.")?,
+            ChecklistItem::LinksValidated => match invalid_link(issue.html_content) {
+                Some(link) => {
+                    unsatisfied.push(format!(
+                        "{} (`{}` is not an absolute URL)",
+                        item.description(),
+                        link
+                    ));
+                    continue;
+                }
+                None => true,
+            },
+            ChecklistItem::SegmentChosen => issue.list_chosen,
+            ChecklistItem::SeedTestAcknowledged => is_seed_test_acknowledged(
+                pool,
+                &content_hash(issue.title, issue.text_content, issue.html_content),
+            )
+            .await
+            .context("Failed to read seed tests.")?,
+        };
+        if !satisfied {
+            unsatisfied.push(item.description().to_string());
+        }
+    }
+    if unsatisfied.is_empty() {
+        Ok(())
+    } else {
+        Err(NewsletterError::ChecklistIncomplete(unsatisfied.join(", ")).into())
+    }
+}
+
+/// Remember that a test email of the content has been sent, which satisfies the
+/// `preview_sent` item of the checklist, when the same content is published.
+pub(crate) async fn record_test_email(
+    pool: &PgPool,
+    content_hash: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO newsletter_test_sends (content_hash, sent_at)
+        VALUES ($1, now())
+        ON CONFLICT (content_hash) DO UPDATE SET sent_at = EXCLUDED.sent_at
+        "#,
+        content_hash,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+async fn is_test_email_sent(
+    pool: &PgPool,
+    issue: &ChecklistIssue<'_>,
+) -> Result<bool, sqlx::Error> {
+    let test_send = sqlx::query!(
+        "SELECT sent_at FROM newsletter_test_sends WHERE content_hash = $1",
+        content_hash(issue.title, issue.text_content, issue.html_content),
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(test_send.is_some())
+}
+
+/// Hash of issue content before snippets are resolved.
+pub(crate) fn content_hash(title: &str, text_content: &str, html_content: &str) -> String {
+    let content = [title, text_content, html_content].join("\0");
+    format!("{:016x}", fnv1a_hash(content.as_bytes()))
+}
+
+/// First link of html content, which is not an absolute `http`, `https` or `mailto` URL.
+fn invalid_link(html_content: &str) -> Option<String> {
+    let document = Html::parse_fragment(html_content);
+    let selector = Selector::parse("a[href]").expect("Selector is valid.");
+    document
+        .select(&selector)
+        .filter_map(|link| link.value().attr("href"))
+        .find(|href| {
+            !Url::parse(href).is_ok_and(|url| ["http", "https", "mailto"].contains(&url.scheme()))
+        })
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::invalid_link;
+
+    #[test]
+    fn absolute_links_are_valid() {
+        let html = r#"<p><a href="https://example.com/a?b=c">a</a>
+            <a href="mailto:jane@example.com">mail</a></p>"#;
+        assert_eq!(invalid_link(html), None);
+        // anchors without href are no links
+        assert_eq!(invalid_link(r#"<a name="top">top</a>"#), None);
+    }
+
+    #[test]
+    fn relative_and_empty_links_are_invalid() {
+        for (html, link) in [
+            (r#"<a href="/archive">archive</a>"#, "/archive"),
+            (r#"<a href="">empty</a>"#, ""),
+            (
+                r#"<a href="javascript:alert(1)">js</a>"#,
+                "javascript:alert(1)",
+            ),
+        ] {
+            assert_eq!(invalid_link(html).as_deref(), Some(link), "{}", html);
+        }
+    }
+}
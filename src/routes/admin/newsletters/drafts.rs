@@ -0,0 +1,191 @@
+//! src/routes/admin/newsletters/drafts.rs
+
+use actix_web::{web, HttpResponse, Responder};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use askama_actix::Template;
+use chrono::{DateTime, Utc};
+use sqlx::{Executor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use super::post::parse_scheduled_at;
+use super::NewsletterFormData;
+use crate::error::Z2PResult;
+use crate::utils::see_other;
+
+/// Saved content of the publish form. Attachments are not part of drafts.
+#[derive(Debug, Default)]
+pub struct NewsletterDraft {
+    pub draft_id: Option<Uuid>,
+    pub title: String,
+    pub text_content: String,
+    pub html_content: String,
+    pub markdown_content: String,
+    pub collect_feedback: bool,
+    pub scheduled_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl NewsletterDraft {
+    /// Scheduled time formatted as value of a `datetime-local` input.
+    pub fn scheduled_at_input(&self) -> String {
+        self.scheduled_at
+            .map(|s| s.format("%Y-%m-%dT%H:%M").to_string())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Template)]
+#[template(path = "newsletter_drafts.html")]
+struct NewsletterDraftsTemplate {
+    drafts: Vec<NewsletterDraft>,
+}
+
+#[tracing::instrument(name = "Save a newsletter draft", skip_all)]
+pub async fn save_newsletter_draft(
+    form: web::Form<NewsletterFormData>,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<HttpResponse> {
+    let scheduled_at = parse_scheduled_at(&form.0.scheduled_at)?;
+    let draft_id = Uuid::parse_str(&form.0.draft_id).unwrap_or_else(|_| Uuid::new_v4());
+    let NewsletterFormData {
+        title,
+        html_content,
+        text_content,
+        markdown_content,
+        collect_feedback,
+        ..
+    } = form.0;
+    let draft = NewsletterDraft {
+        draft_id: Some(draft_id),
+        title,
+        text_content,
+        html_content,
+        markdown_content,
+        collect_feedback,
+        scheduled_at,
+        updated_at: None,
+    };
+    upsert_newsletter_draft(&pool, draft_id, &draft)
+        .await
+        .context("Failed to store newsletter draft")?;
+    FlashMessage::info("The draft has been saved.").send();
+    Ok(see_other(&format!(
+        "/admin/newsletters?draft_id={}",
+        draft_id
+    )))
+}
+
+pub async fn newsletter_drafts(pool: web::Data<PgPool>) -> Z2PResult<impl Responder> {
+    let drafts = get_newsletter_drafts(&pool)
+        .await
+        .context("Failed to read newsletter drafts")?;
+    Ok(NewsletterDraftsTemplate { drafts })
+}
+
+#[tracing::instrument(skip_all)]
+async fn upsert_newsletter_draft(
+    pool: &PgPool,
+    draft_id: Uuid,
+    draft: &NewsletterDraft,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO newsletter_drafts (
+            draft_id,
+            title,
+            text_content,
+            html_content,
+            markdown_content,
+            collect_feedback,
+            scheduled_at,
+            updated_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, now())
+        ON CONFLICT (draft_id) DO UPDATE
+        SET
+            title = EXCLUDED.title,
+            text_content = EXCLUDED.text_content,
+            html_content = EXCLUDED.html_content,
+            markdown_content = EXCLUDED.markdown_content,
+            collect_feedback = EXCLUDED.collect_feedback,
+            scheduled_at = EXCLUDED.scheduled_at,
+            updated_at = EXCLUDED.updated_at
+        "#,
+        draft_id,
+        draft.title,
+        draft.text_content,
+        draft.html_content,
+        draft.markdown_content,
+        draft.collect_feedback,
+        draft.scheduled_at,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_newsletter_drafts(pool: &PgPool) -> Result<Vec<NewsletterDraft>, sqlx::Error> {
+    sqlx::query_as!(
+        NewsletterDraft,
+        r#"
+        SELECT
+            draft_id AS "draft_id?",
+            title,
+            text_content,
+            html_content,
+            markdown_content,
+            collect_feedback,
+            scheduled_at,
+            updated_at AS "updated_at?"
+        FROM newsletter_drafts
+        ORDER BY updated_at DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn get_newsletter_draft(
+    pool: &PgPool,
+    draft_id: Uuid,
+) -> Result<Option<NewsletterDraft>, sqlx::Error> {
+    sqlx::query_as!(
+        NewsletterDraft,
+        r#"
+        SELECT
+            draft_id AS "draft_id?",
+            title,
+            text_content,
+            html_content,
+            markdown_content,
+            collect_feedback,
+            scheduled_at,
+            updated_at AS "updated_at?"
+        FROM newsletter_drafts
+        WHERE draft_id = $1
+        "#,
+        draft_id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Remove draft once it has been published.
+#[tracing::instrument(skip_all)]
+pub async fn delete_newsletter_draft(
+    transaction: &mut Transaction<'_, Postgres>,
+    draft_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    let query = sqlx::query!(
+        r#"
+        DELETE FROM newsletter_drafts
+        WHERE draft_id = $1
+        "#,
+        draft_id
+    );
+    transaction.execute(query).await?;
+    Ok(())
+}
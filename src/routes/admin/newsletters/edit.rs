@@ -0,0 +1,347 @@
+//! src/routes/admin/newsletters/edit.rs
+
+use actix_web::{web, HttpResponse, Responder};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use askama_actix::Template;
+use sqlx::{Executor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use super::post::{check_content, check_snippets};
+use crate::error::{Error, Z2PResult};
+use crate::issue_delivery_worker::DeliveryTaskStatus;
+use crate::startup::ExternalDeliveryQueue;
+use crate::utils::see_other;
+
+#[derive(Template)]
+#[template(path = "newsletter_edit.html")]
+struct EditNewsletterTemplate {
+    flash_messages: Vec<String>,
+    newsletter_issue_id: Uuid,
+    issue: EditNewsletterFormData,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+pub struct EditNewsletterFormData {
+    pub title: String,
+    pub text_content: String,
+    pub html_content: String,
+}
+
+pub async fn edit_newsletter_form(
+    flash_messages: IncomingFlashMessages,
+    newsletter_issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<impl Responder> {
+    let flash_messages: Vec<String> = flash_messages
+        .iter()
+        .map(|m| m.content().to_string())
+        .collect();
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let issue = sqlx::query_as!(
+        EditNewsletterFormData,
+        r#"
+        SELECT title, text_content, html_content
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_optional(pool.as_ref())
+    .await
+    .context("Failed to read newsletter issue")?
+    .ok_or(Error::NotFound)?;
+    Ok(EditNewsletterTemplate {
+        flash_messages,
+        newsletter_issue_id,
+        issue,
+    })
+}
+
+/// Change title and content of an issue, which is not being delivered right now.
+#[tracing::instrument(name = "Edit a newsletter issue", skip(form, pool, external_queue))]
+pub async fn edit_newsletter(
+    newsletter_issue_id: web::Path<Uuid>,
+    form: web::Form<EditNewsletterFormData>,
+    pool: web::Data<PgPool>,
+    external_queue: web::Data<ExternalDeliveryQueue>,
+) -> Z2PResult<HttpResponse> {
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let edit_url = format!("/admin/newsletters/{}/edit", newsletter_issue_id);
+    if let Err(e) = check_content(&form.title, &form.text_content, &form.html_content) {
+        FlashMessage::error(e.to_string()).send();
+        return Ok(see_other(&edit_url));
+    }
+    match check_snippets(&pool, &[&form.text_content, &form.html_content]).await {
+        Err(Error::NewsletterError(e)) => {
+            FlashMessage::error(e.to_string()).send();
+            return Ok(see_other(&edit_url));
+        }
+        result => result?,
+    }
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    if let Some(response) = guard_delivery_in_progress(
+        &mut transaction,
+        &external_queue,
+        newsletter_issue_id,
+        &edit_url,
+    )
+    .await?
+    {
+        return Ok(response);
+    }
+    let query = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET
+            title = $2,
+            text_content = $3,
+            html_content = $4
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id,
+        form.title,
+        form.text_content,
+        form.html_content,
+    );
+    transaction
+        .execute(query)
+        .await
+        .context("Failed to update newsletter issue")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to update newsletter issue")?;
+    FlashMessage::info("The newsletter issue has been updated.").send();
+    Ok(see_other(&format!(
+        "/admin/delivery_overview?newsletter_issue_id={}",
+        newsletter_issue_id
+    )))
+}
+
+/// Delete an issue with its remaining delivery tasks, attachments, language variants and feedback.
+#[tracing::instrument(name = "Delete a newsletter issue", skip(pool, external_queue))]
+pub async fn delete_newsletter(
+    newsletter_issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    external_queue: web::Data<ExternalDeliveryQueue>,
+) -> Z2PResult<HttpResponse> {
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    let edit_url = format!("/admin/newsletters/{}/edit", newsletter_issue_id);
+    if let Some(response) = guard_delivery_in_progress(
+        &mut transaction,
+        &external_queue,
+        newsletter_issue_id,
+        &edit_url,
+    )
+    .await?
+    {
+        return Ok(response);
+    }
+    delete_newsletter_issue(&mut transaction, &external_queue, newsletter_issue_id)
+        .await
+        .context("Failed to delete newsletter issue")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to delete newsletter issue")?;
+    FlashMessage::info("The newsletter issue has been deleted.").send();
+    Ok(see_other("/admin/delivery_overview"))
+}
+
+/// Cancel an issue within its undo window. The issue is deleted, since none of its
+/// emails have been sent yet.
+#[tracing::instrument(name = "Cancel a newsletter issue", skip(pool, external_queue))]
+pub async fn cancel_newsletter(
+    newsletter_issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    external_queue: web::Data<ExternalDeliveryQueue>,
+) -> Z2PResult<HttpResponse> {
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    let is_cancellable = sqlx::query!(
+        r#"
+        SELECT cancellable_until > now() AS "is_cancellable"
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        FOR UPDATE
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .context("Failed to read undo window of newsletter issue")?
+    .ok_or(Error::NotFound)?
+    .is_cancellable;
+    if is_cancellable != Some(true) {
+        FlashMessage::error(
+            "The undo window of the newsletter issue has passed. \
+            Cancel its delivery instead.",
+        )
+        .send();
+        return Ok(see_other(&format!(
+            "/admin/delivery_overview?newsletter_issue_id={}",
+            newsletter_issue_id
+        )));
+    }
+    delete_newsletter_issue(&mut transaction, &external_queue, newsletter_issue_id)
+        .await
+        .context("Failed to delete newsletter issue")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to cancel newsletter issue")?;
+    FlashMessage::info("The newsletter issue has been cancelled. No emails have been sent.").send();
+    Ok(see_other("/admin/newsletters"))
+}
+
+/// Lock the issue and redirect with an error message, if it is missing or its delivery is in progress.
+async fn guard_delivery_in_progress(
+    transaction: &mut Transaction<'_, Postgres>,
+    external_queue: &ExternalDeliveryQueue,
+    newsletter_issue_id: Uuid,
+    redirect_url: &str,
+) -> Z2PResult<Option<HttpResponse>> {
+    let in_progress = is_delivery_in_progress(transaction, external_queue, newsletter_issue_id)
+        .await
+        .context("Failed to read delivery status of newsletter issue")?
+        .ok_or(Error::NotFound)?;
+    if in_progress {
+        FlashMessage::error(
+            "The newsletter issue cannot be changed while its delivery is in progress. \
+            Wait until delivery has finished or cancel it.",
+        )
+        .send();
+        return Ok(Some(see_other(redirect_url)));
+    }
+    Ok(None)
+}
+
+/// Delivery is in progress, if pending or paused tasks are left and the issue
+/// is neither scheduled for a future time nor within its undo window. None, if
+/// the issue does not exist.
+#[tracing::instrument(skip(transaction, external_queue))]
+async fn is_delivery_in_progress(
+    transaction: &mut Transaction<'_, Postgres>,
+    external_queue: &ExternalDeliveryQueue,
+    newsletter_issue_id: Uuid,
+) -> Result<Option<bool>, anyhow::Error> {
+    let issue = sqlx::query!(
+        r#"
+        SELECT GREATEST(scheduled_at, cancellable_until) > now() AS "is_scheduled"
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        FOR UPDATE
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_optional(&mut **transaction)
+    .await?;
+    let Some(issue) = issue else {
+        return Ok(None);
+    };
+    if issue.is_scheduled == Some(true) {
+        return Ok(Some(false));
+    }
+    if let Some(ref queue) = external_queue.0 {
+        let num_open_tasks = queue
+            .count_tasks(newsletter_issue_id, DeliveryTaskStatus::Pending)
+            .await?
+            + queue
+                .count_tasks(newsletter_issue_id, DeliveryTaskStatus::Paused)
+                .await?;
+        return Ok(Some(num_open_tasks > 0));
+    }
+    let num_open_tasks = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS "num_open_tasks!"
+        FROM issue_delivery_queue
+        WHERE
+            newsletter_issue_id = $1 AND
+            status IN ('pending', 'paused')
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_one(&mut **transaction)
+    .await?
+    .num_open_tasks;
+    Ok(Some(num_open_tasks > 0))
+}
+
+/// Tasks in the external queue are removed before the deletion is committed, so that
+/// a failure of Redis leaves the issue in place.
+#[tracing::instrument(skip(transaction, external_queue))]
+async fn delete_newsletter_issue(
+    transaction: &mut Transaction<'_, Postgres>,
+    external_queue: &ExternalDeliveryQueue,
+    newsletter_issue_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    if let Some(ref queue) = external_queue.0 {
+        queue.remove_issue(newsletter_issue_id).await?;
+    }
+    transaction
+        .execute(sqlx::query!(
+            "DELETE FROM issue_delivery_queue WHERE newsletter_issue_id = $1",
+            newsletter_issue_id
+        ))
+        .await?;
+    transaction
+        .execute(sqlx::query!(
+            "DELETE FROM newsletter_issue_attachments WHERE newsletter_issue_id = $1",
+            newsletter_issue_id
+        ))
+        .await?;
+    transaction
+        .execute(sqlx::query!(
+            "DELETE FROM newsletter_issue_variants WHERE newsletter_issue_id = $1",
+            newsletter_issue_id
+        ))
+        .await?;
+    transaction
+        .execute(sqlx::query!(
+            "DELETE FROM issue_feedback WHERE newsletter_issue_id = $1",
+            newsletter_issue_id
+        ))
+        .await?;
+    transaction
+        .execute(sqlx::query!(
+            "DELETE FROM newsletter_issue_snippets WHERE newsletter_issue_id = $1",
+            newsletter_issue_id
+        ))
+        .await?;
+    transaction
+        .execute(sqlx::query!(
+            "DELETE FROM frequency_capped_sends WHERE newsletter_issue_id = $1",
+            newsletter_issue_id
+        ))
+        .await?;
+    transaction
+        .execute(sqlx::query!(
+            "DELETE FROM delivery_attempts WHERE newsletter_issue_id = $1",
+            newsletter_issue_id
+        ))
+        .await?;
+    transaction
+        .execute(sqlx::query!(
+            "DELETE FROM delivery_dead_letters WHERE newsletter_issue_id = $1",
+            newsletter_issue_id
+        ))
+        .await?;
+    transaction
+        .execute(sqlx::query!(
+            "DELETE FROM newsletter_issues WHERE newsletter_issue_id = $1",
+            newsletter_issue_id
+        ))
+        .await?;
+    Ok(())
+}
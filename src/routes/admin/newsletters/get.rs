@@ -1,25 +1,73 @@
 //! src/routes/admin/newsletters/get.rs
 
-use actix_web::Responder;
+use actix_web::{web, Responder};
 use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
 use askama_actix::Template;
+use sqlx::PgPool;
 use uuid::Uuid;
 
+use super::drafts::{get_newsletter_draft, NewsletterDraft};
+use super::seed_test::{get_recent_seed_tests, SeedTest};
+use super::ChecklistItem;
+use crate::error::Z2PResult;
+use crate::mailing_lists::{get_mailing_lists, MailingList};
+use crate::startup::{PublishChecklist, SeedAddresses};
+
 #[derive(Template)]
 #[template(path = "newsletters.html")]
 struct NewslettersTemplate {
     flash_messages: Vec<String>,
     idempotency_key: Uuid,
+    draft: NewsletterDraft,
+    lists: Vec<MailingList>,
+    checklist: Vec<ChecklistItem>,
+    /// The form must not preselect a mailing list, if it must be chosen explicitly.
+    list_choice_required: bool,
+    seed_tests_enabled: bool,
+    /// Most recent seed tests with results per seed mailbox
+    seed_tests: Vec<SeedTest>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct DraftQuery {
+    draft_id: Uuid,
 }
 
-pub async fn publish_newsletter_form(flash_messages: IncomingFlashMessages) -> impl Responder {
+pub async fn publish_newsletter_form(
+    flash_messages: IncomingFlashMessages,
+    query: Option<web::Query<DraftQuery>>,
+    pool: web::Data<PgPool>,
+    publish_checklist: web::Data<PublishChecklist>,
+    seed_addresses: web::Data<SeedAddresses>,
+) -> Z2PResult<impl Responder> {
     let flash_messages: Vec<String> = flash_messages
         .iter()
         .map(|m| m.content().to_string())
         .collect();
     let idempotency_key = Uuid::new_v4();
-    NewslettersTemplate {
+    // load a saved draft back into the form
+    let draft = match query {
+        Some(query) => get_newsletter_draft(&pool, query.draft_id)
+            .await
+            .context("Failed to read newsletter draft")?
+            .unwrap_or_default(),
+        None => NewsletterDraft::default(),
+    };
+    let lists = get_mailing_lists(&pool)
+        .await
+        .context("Failed to read mailing lists")?;
+    let seed_tests = get_recent_seed_tests(&pool)
+        .await
+        .context("Failed to read seed tests")?;
+    Ok(NewslettersTemplate {
         flash_messages,
         idempotency_key,
-    }
+        draft,
+        lists,
+        checklist: publish_checklist.0.clone(),
+        list_choice_required: publish_checklist.0.contains(&ChecklistItem::SegmentChosen),
+        seed_tests_enabled: !seed_addresses.0.is_empty(),
+        seed_tests,
+    })
 }
@@ -1,7 +1,44 @@
 //! src/routes/admin/newsletters/mod.rs
 
+mod api_publish;
+mod checklist;
+mod drafts;
+mod edit;
 mod get;
 mod post;
+mod preview;
+mod recurring;
+mod seed_test;
+mod simulate;
+mod size_budget;
+mod test_send;
+mod variants;
 
+pub use api_publish::PublishIssueInput;
+pub(crate) use api_publish::{
+    api_error_message, commit_published_issues, publish_issue_in_transaction,
+    publish_issue_via_api, ApiPublishData,
+};
+pub use checklist::ChecklistItem;
+pub(crate) use checklist::{verify_publish_checklist, ChecklistIssue};
+pub use drafts::{newsletter_drafts, save_newsletter_draft};
+pub use edit::{
+    cancel_newsletter, delete_newsletter, edit_newsletter, edit_newsletter_form,
+    EditNewsletterFormData,
+};
 pub use get::publish_newsletter_form;
-pub use post::{publish_newsletter, NewsletterError, NewsletterFormData};
+pub use post::*;
+pub use preview::preview_newsletter;
+pub use recurring::{
+    create_recurring_issue, pause_recurring_issue, recurring_issues, resume_recurring_issue,
+    skip_recurring_issue, RecurringIssueFormData, MAX_PERIOD_DAYS,
+};
+pub use seed_test::{acknowledge_seed_test, send_seed_test};
+pub use simulate::simulate_newsletter;
+pub(crate) use size_budget::check_email_size;
+pub use size_budget::EmailSizeBudget;
+pub use test_send::send_test_newsletter;
+pub use variants::{
+    delete_newsletter_variant, newsletter_variants, save_newsletter_variant,
+    NewsletterVariantFormData,
+};
@@ -4,23 +4,115 @@ use actix_web::web::ReqData;
 use actix_web::{web, HttpResponse};
 use actix_web_flash_messages::FlashMessage;
 use anyhow::Context;
+use base64::Engine;
+use chrono::{DateTime, NaiveDateTime, Utc};
 use sqlx::{Executor, PgPool, Postgres, Transaction};
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
+use super::checklist::{verify_publish_checklist, ChecklistIssue, ChecklistItem};
+use super::drafts::delete_newsletter_draft;
+use super::size_budget::{check_email_size, EmailSizeBudget};
+use crate::attachment_scan::{AttachmentScan, AttachmentScanStatus, AttachmentScanner};
 use crate::authentication::UserId;
-use crate::error::{error_chain_fmt, Z2PResult};
-use crate::idempotency::{save_response, try_processing, IdempotencyKey, NextAction};
+use crate::configuration::DetectionAction;
+use crate::delivery_queue::{DeliveryQueue, TASK_VERSION};
+use crate::email_client::Attachment;
+use crate::error::{error_chain_fmt, Error, Z2PResult};
+use crate::frequency_cap::{apply_frequency_cap, has_frequency_preferences};
+use crate::idempotency::{
+    request_hash, save_response, try_processing, IdempotencyKeyHeader, NextAction,
+};
+use crate::issue_delivery_worker::notify_delivery_worker;
+use crate::mailing_lists::parse_list_id;
+use crate::markdown::{render_html, render_text};
+use crate::metrics::IdempotencyMetrics;
+use crate::provider_usage::estimate_issue_cost;
 use crate::routes::SubscriptionsStatus;
+use crate::send_time::{get_best_send_hours, optimized_send_time};
+use crate::snippets::{get_current_snippets, referenced_snippets, unknown_snippets};
+use crate::startup::{
+    ApplicationBaseUrl, ExternalDeliveryQueue, ExternalIdempotencyStore, FrequencyCap,
+    ProviderPlan, PublishChecklist, UndoWindow,
+};
 use crate::utils::see_other;
 
-#[derive(serde::Deserialize, serde::Serialize)]
+#[derive(serde::Deserialize, serde::Serialize, utoipa::ToSchema)]
 pub struct NewsletterFormData {
     pub title: String,
     pub html_content: String,
     pub text_content: String,
+    /// Optional markdown body; if set, html and text content are rendered from it.
+    #[serde(default)]
+    pub markdown_content: String,
+    /// Idempotency key; the `Idempotency-Key` header takes precedence, if present.
+    #[serde(default)]
     pub idempotency_key: String,
+    #[serde(default)]
+    pub collect_feedback: bool,
+    /// Optional UTC time of delivery; empty to deliver immediately.
+    #[serde(default)]
+    pub scheduled_at: String,
+    /// Share of delivery worker batches relative to other issues in queue; empty for default of 1.
+    #[serde(default)]
+    pub delivery_weight: String,
+    /// Deliver to each subscriber in the hour they usually open newsletters, within
+    /// 24 hours after the scheduled time.
+    #[serde(default)]
+    pub optimize_send_time: bool,
+    /// Mailing list, whose confirmed subscribers receive the issue; empty for the default list.
+    #[serde(default)]
+    pub list_id: String,
+    /// Optional attachment; the publish form fills these fields from a file input.
+    #[serde(default)]
+    pub attachment_name: String,
+    #[serde(default)]
+    pub attachment_content_type: String,
+    /// Base64 encoded content of attachment
+    #[serde(default)]
+    pub attachment_content: String,
+    /// Draft loaded into the form, if any; it is removed once the issue is published.
+    #[serde(default)]
+    pub draft_id: String,
+    /// Items of the pre-publish checklist, which the admin checked.
+    #[serde(default)]
+    pub checked_subject_set: bool,
+    #[serde(default)]
+    pub checked_preview_sent: bool,
+    #[serde(default)]
+    pub checked_links_validated: bool,
+    #[serde(default)]
+    pub checked_segment_chosen: bool,
+    #[serde(default)]
+    pub checked_seed_test_acknowledged: bool,
 }
 
+impl NewsletterFormData {
+    /// Items of the pre-publish checklist, which the admin checked in the form.
+    pub(super) fn checked_items(&self) -> Vec<ChecklistItem> {
+        [
+            (self.checked_subject_set, ChecklistItem::SubjectSet),
+            (self.checked_preview_sent, ChecklistItem::PreviewSent),
+            (self.checked_links_validated, ChecklistItem::LinksValidated),
+            (self.checked_segment_chosen, ChecklistItem::SegmentChosen),
+            (
+                self.checked_seed_test_acknowledged,
+                ChecklistItem::SeedTestAcknowledged,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(checked, item)| checked.then_some(item))
+        .collect()
+    }
+}
+
+/// Maximum size of a newsletter attachment in bytes.
+pub const MAX_ATTACHMENT_BYTES: usize = 5 * 1024 * 1024;
+/// Limit of newsletter form payload, which includes the base64 encoded attachment.
+pub const MAX_NEWSLETTER_FORM_BYTES: usize = 8 * 1024 * 1024;
+/// Maximum delivery weight of a newsletter issue.
+pub const MAX_DELIVERY_WEIGHT: i32 = 100;
+
 #[derive(thiserror::Error)]
 pub enum NewsletterError {
     #[error("You must set a title for your newsletter.")]
@@ -29,6 +121,30 @@ pub enum NewsletterError {
     NoTextContent,
     #[error("You must set html content for your newsletter.")]
     NoHtmlContent,
+    #[error("The attachment must have a file name and base64 encoded content.")]
+    InvalidAttachment,
+    #[error("The attachment exceeds the maximum size of 5 MB.")]
+    AttachmentTooLarge,
+    #[error("The scheduled time must be a valid date and time.")]
+    InvalidScheduledAt,
+    #[error("The delivery weight must be a number between 1 and 100.")]
+    InvalidDeliveryWeight,
+    #[error("The selected mailing list does not exist.")]
+    InvalidList,
+    #[error("The snippet `{0}` does not exist.")]
+    UnknownSnippet(String),
+    #[error("The pre-publish checklist is incomplete: {0}.")]
+    ChecklistIncomplete(String),
+    #[error("Set your email address to receive test emails.")]
+    NoAdminEmail,
+    #[error("Configure seed addresses to send seed tests.")]
+    NoSeedAddresses,
+    #[error("The attachment could not be scanned for malware. Please try again later.")]
+    AttachmentScanFailed(#[source] anyhow::Error),
+    #[error("The attachment has been rejected, because malware was detected: {0}")]
+    InfectedAttachment(String),
+    #[error("The issue exceeds the maximum email size of {0} KB: {1}.")]
+    EmailTooLarge(u32, String),
 }
 
 impl std::fmt::Debug for NewsletterError {
@@ -37,6 +153,22 @@ impl std::fmt::Debug for NewsletterError {
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/admin/newsletters",
+    tag = "newsletters",
+    request_body(content = NewsletterFormData, content_type = "application/x-www-form-urlencoded"),
+    params(
+        ("Idempotency-Key" = Option<String>, Header, description = "UUID, which replaces the idempotency key of the form."),
+    ),
+    responses(
+        (status = 303, description = "Issue queued for delivery or missing input, redirect to /admin/newsletters; not logged in, redirect to /login."),
+        (status = 400, description = "Missing or invalid idempotency key."),
+        (status = 422, description = "Idempotency key has been used for other content."),
+    ),
+    security(("session_cookie" = []))
+)]
+#[allow(clippy::too_many_arguments)]
 #[tracing::instrument(
     name = "Publish a newsletter issue",
     skip_all,
@@ -44,61 +176,499 @@ impl std::fmt::Debug for NewsletterError {
 )]
 pub async fn publish_newsletter(
     form: web::Form<NewsletterFormData>,
+    idempotency_key_header: IdempotencyKeyHeader,
     pool: web::Data<PgPool>,
+    attachment_scanner: web::Data<AttachmentScanner>,
+    external_queue: web::Data<ExternalDeliveryQueue>,
+    idempotency_store: web::Data<ExternalIdempotencyStore>,
+    idempotency_metrics: web::Data<IdempotencyMetrics>,
+    frequency_cap: web::Data<FrequencyCap>,
+    publish_checklist: web::Data<PublishChecklist>,
+    undo_window: web::Data<UndoWindow>,
+    email_size_budget: web::Data<EmailSizeBudget>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    provider_plan: web::Data<ProviderPlan>,
     user_id: ReqData<UserId>,
 ) -> Z2PResult<HttpResponse> {
-    if form.0.title.is_empty() {
-        Err(NewsletterError::NoTitle)?;
-    }
-    if form.0.text_content.is_empty() {
-        Err(NewsletterError::NoTextContent)?;
-    }
-    if form.0.html_content.is_empty() {
-        Err(NewsletterError::NoHtmlContent)?;
-    }
+    let mut form = form.into_inner();
+    let idempotency_key =
+        idempotency_key_header.or_form_field(std::mem::take(&mut form.idempotency_key))?;
+    let request_hash = request_hash(&form)?;
+    prepare_content(&mut form)?;
+    check_snippets(&pool, &[&form.text_content, &form.html_content]).await?;
+    let checked_items = form.checked_items();
+    let checklist_issue = ChecklistIssue {
+        title: &form.title,
+        text_content: &form.text_content,
+        html_content: &form.html_content,
+        list_chosen: !form.list_id.trim().is_empty(),
+        checked: &checked_items,
+    };
+    verify_publish_checklist(&pool, &publish_checklist.0, &checklist_issue).await?;
+    let size_warning = check_email_size(
+        &pool,
+        &base_url.0,
+        &email_size_budget,
+        &form.title,
+        &form.html_content,
+        form.collect_feedback,
+    )
+    .await?;
+    let attachment = parse_attachment(&form)?;
+    let attachment = match attachment {
+        Some(attachment) => {
+            let scan = scan_attachment(&attachment_scanner, &attachment).await?;
+            Some((attachment, scan))
+        }
+        None => None,
+    };
+    let scheduled_at = parse_scheduled_at(&form.scheduled_at)?;
+    let delivery_weight = parse_delivery_weight(&form.delivery_weight)?;
+    let list_id = parse_issue_list_id(&pool, &form.list_id).await?;
     let user_id = user_id.into_inner();
     // We must destructure the form to avoid upsetting the borrow-checker
     let NewsletterFormData {
         title,
         html_content,
         text_content,
-        idempotency_key,
-    } = form.0;
+        collect_feedback,
+        optimize_send_time,
+        draft_id,
+        ..
+    } = form;
 
-    let idempotency_key: IdempotencyKey = idempotency_key.try_into()?;
-    let mut transaction = match try_processing(&pool, &idempotency_key, *user_id).await? {
+    let next_action = try_processing(
+        &pool,
+        idempotency_store.0.as_ref(),
+        &idempotency_key,
+        *user_id,
+        &request_hash,
+    )
+    .await?;
+    idempotency_metrics.record(&next_action);
+    let mut transaction = match next_action {
         NextAction::StartProcessing(t) => t,
         NextAction::ReturnSavedResponse(saved_response) => {
-            success_message().send();
+            success_message(&undo_window).send();
             return Ok(saved_response);
         }
+        NextAction::RejectReusedKey => return Err(Error::IdempotencyKeyReused),
+    };
+    let issue = NewIssue {
+        title: &title,
+        text_content: &text_content,
+        html_content: &html_content,
+        collect_feedback,
+        scheduled_at,
+        delivery_weight,
+        optimize_send_time,
+        list_id,
+        cancellable_until: end_of_undo_window(&undo_window),
+    };
+    let (issue_id, external_deliveries) =
+        store_issue_for_delivery(&mut transaction, &issue, &external_queue, &frequency_cap).await?;
+    if let Some((attachment, scan)) = attachment {
+        insert_newsletter_issue_attachment(&mut transaction, issue_id, &attachment, &scan)
+            .await
+            .context("Failed to store newsletter issue attachment")?;
+    }
+    if let Ok(draft_id) = Uuid::parse_str(&draft_id) {
+        delete_newsletter_draft(&mut transaction, draft_id)
+            .await
+            .context("Failed to delete published newsletter draft")?;
+    }
+
+    // the delivery overview offers to cancel the issue within the undo window
+    let response = match issue.cancellable_until {
+        Some(_) => see_other(&format!(
+            "/admin/delivery_overview?newsletter_issue_id={}",
+            issue_id
+        )),
+        None => see_other("/admin/newsletters"),
+    };
+    // a saved response must not be replayed for an issue, whose tasks are missing
+    if let Some(ref deliveries) = external_deliveries {
+        enqueue_external_tasks(&external_queue, issue_id, deliveries).await?;
+    }
+    let response = match save_response(
+        transaction,
+        idempotency_store.0.as_ref(),
+        &idempotency_key,
+        *user_id,
+        response,
+    )
+    .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            remove_external_tasks(&external_queue, issue_id).await;
+            return Err(e.into());
+        }
     };
-    let issue_id = insert_newsletter_issue(&mut transaction, &title, &text_content, &html_content)
+    if external_deliveries.is_some() {
+        notify_delivery_worker(pool.as_ref())
+            .await
+            .context("Failed to notify delivery worker")?;
+    }
+    success_message(&undo_window).send();
+    if let Some(warning) = size_warning {
+        FlashMessage::warning(warning).send();
+    }
+    // the issue is published anyway, emails above the quota may be charged extra
+    if let Some(ref plan) = provider_plan.0 {
+        match estimate_issue_cost(&pool, plan, issue_id).await {
+            Ok(estimate) => {
+                if let Some(warning) = estimate.quota_warning() {
+                    FlashMessage::warning(warning).send();
+                }
+            }
+            Err(e) => {
+                tracing::warn!(error.cause_chain = ?e, "Failed to estimate cost of issue.")
+            }
+        }
+    }
+    Ok(response)
+}
+
+/// Scan attachment for malware. Infected attachments are rejected, unless scanning
+/// is configured to flag them. If the scanner fails, the attachment is rejected, too.
+pub(super) async fn scan_attachment(
+    scanner: &AttachmentScanner,
+    attachment: &Attachment,
+) -> Result<AttachmentScan, NewsletterError> {
+    let scan = scanner
+        .scan(&attachment.file_name, &attachment.content)
         .await
-        .context("Failed to store newsletter issue details")?;
-    let num_current_subscribers = enqueue_delivery_tasks(&mut transaction, issue_id)
+        .map_err(NewsletterError::AttachmentScanFailed)?;
+    if scan.status == AttachmentScanStatus::Infected
+        && scanner.on_detection() == Some(DetectionAction::Block)
+    {
+        return Err(NewsletterError::InfectedAttachment(
+            scan.signature.unwrap_or_default(),
+        ));
+    }
+    Ok(scan)
+}
+
+/// Render content from markdown, if given, and check that all content is set.
+pub(super) fn prepare_content(form: &mut NewsletterFormData) -> Result<(), NewsletterError> {
+    if !form.markdown_content.trim().is_empty() {
+        form.html_content = render_html(&form.markdown_content);
+        form.text_content = render_text(&form.markdown_content);
+    }
+    check_content(&form.title, &form.text_content, &form.html_content)
+}
+
+/// Check that title, text and html content are set.
+pub(crate) fn check_content(
+    title: &str,
+    text_content: &str,
+    html_content: &str,
+) -> Result<(), NewsletterError> {
+    if title.is_empty() {
+        return Err(NewsletterError::NoTitle);
+    }
+    if text_content.is_empty() {
+        return Err(NewsletterError::NoTextContent);
+    }
+    if html_content.is_empty() {
+        return Err(NewsletterError::NoHtmlContent);
+    }
+    Ok(())
+}
+
+/// Check that all snippets referenced by content exist. Snippets are resolved, when
+/// the delivery worker renders the emails of an issue.
+pub(crate) async fn check_snippets(pool: &PgPool, contents: &[&str]) -> Result<(), Error> {
+    let names = referenced_snippets(contents);
+    if names.is_empty() {
+        return Ok(());
+    }
+    let unknown = unknown_snippets(pool, &names)
+        .await
+        .context("Failed to read snippets.")?;
+    match unknown.into_iter().next() {
+        Some(name) => Err(NewsletterError::UnknownSnippet(name).into()),
+        None => Ok(()),
+    }
+}
+
+/// Replace snippets of form content with their latest version for previews and test emails.
+pub(super) async fn resolve_form_snippets(
+    pool: &PgPool,
+    form: &mut NewsletterFormData,
+) -> Z2PResult<()> {
+    let contents = [form.text_content.as_str(), form.html_content.as_str()];
+    check_snippets(pool, &contents).await?;
+    let names = referenced_snippets(&contents);
+    if names.is_empty() {
+        return Ok(());
+    }
+    let snippets = get_current_snippets(pool, &names)
+        .await
+        .context("Failed to read snippets.")?;
+    form.text_content = snippets.resolve_text(&form.text_content);
+    form.html_content = snippets.resolve_html(&form.html_content);
+    Ok(())
+}
+
+/// Decode and validate the optional attachment of the newsletter form.
+pub(super) fn parse_attachment(
+    form: &NewsletterFormData,
+) -> Result<Option<Attachment>, NewsletterError> {
+    if form.attachment_name.is_empty() && form.attachment_content.is_empty() {
+        return Ok(None);
+    }
+    if form.attachment_name.trim().is_empty() {
+        return Err(NewsletterError::InvalidAttachment);
+    }
+    // check encoded size first to avoid decoding oversized attachments
+    if form.attachment_content.len() > MAX_ATTACHMENT_BYTES.div_ceil(3) * 4 {
+        return Err(NewsletterError::AttachmentTooLarge);
+    }
+    let content = base64::engine::general_purpose::STANDARD
+        .decode(&form.attachment_content)
+        .map_err(|_| NewsletterError::InvalidAttachment)?;
+    if content.is_empty() {
+        return Err(NewsletterError::InvalidAttachment);
+    }
+    if content.len() > MAX_ATTACHMENT_BYTES {
+        return Err(NewsletterError::AttachmentTooLarge);
+    }
+    let content_type = if form.attachment_content_type.is_empty() {
+        "application/octet-stream".to_string()
+    } else {
+        form.attachment_content_type.clone()
+    };
+    Ok(Some(Attachment {
+        file_name: form.attachment_name.trim().to_string(),
+        content_type,
+        content,
+    }))
+}
+
+/// Parse scheduled time of delivery as RFC 3339 or as UTC time of a `datetime-local` input.
+pub(super) fn parse_scheduled_at(
+    scheduled_at: &str,
+) -> Result<Option<DateTime<Utc>>, NewsletterError> {
+    let scheduled_at = scheduled_at.trim();
+    if scheduled_at.is_empty() {
+        return Ok(None);
+    }
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(scheduled_at) {
+        return Ok(Some(date_time.with_timezone(&Utc)));
+    }
+    ["%Y-%m-%dT%H:%M", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(scheduled_at, format).ok())
+        .map(|date_time| Some(date_time.and_utc()))
+        .ok_or(NewsletterError::InvalidScheduledAt)
+}
+
+pub(super) fn parse_delivery_weight(delivery_weight: &str) -> Result<i32, NewsletterError> {
+    let delivery_weight = delivery_weight.trim();
+    if delivery_weight.is_empty() {
+        return Ok(1);
+    }
+    delivery_weight
+        .parse::<i32>()
+        .ok()
+        .filter(|weight| (1..=MAX_DELIVERY_WEIGHT).contains(weight))
+        .ok_or(NewsletterError::InvalidDeliveryWeight)
+}
+
+/// Mailing list of an issue; the default list, if none is selected.
+pub(super) async fn parse_issue_list_id(pool: &PgPool, list_id: &str) -> Z2PResult<Uuid> {
+    let list_id = parse_list_id(pool, list_id)
+        .await
+        .context("Failed to read mailing list")?;
+    Ok(list_id.ok_or(NewsletterError::InvalidList)?)
+}
+
+/// Content and delivery options of a newsletter issue to publish.
+pub(crate) struct NewIssue<'a> {
+    pub title: &'a str,
+    pub text_content: &'a str,
+    pub html_content: &'a str,
+    pub collect_feedback: bool,
+    pub scheduled_at: Option<DateTime<Utc>>,
+    pub delivery_weight: i32,
+    pub optimize_send_time: bool,
+    pub list_id: Uuid,
+    /// End of the undo window, in which the issue can be cancelled
+    pub cancellable_until: Option<DateTime<Utc>>,
+}
+
+impl NewIssue<'_> {
+    /// Delivery starts at the scheduled time, but not before the undo window has passed.
+    fn delivery_start(&self) -> Option<DateTime<Utc>> {
+        self.scheduled_at.max(self.cancellable_until)
+    }
+}
+
+/// End of the undo window of an issue published now; `None`, if it is disabled.
+pub(crate) fn end_of_undo_window(undo_window: &UndoWindow) -> Option<DateTime<Utc>> {
+    (undo_window.0 > 0).then(|| Utc::now() + chrono::Duration::minutes(undo_window.0.into()))
+}
+
+/// Confirmed subscribers grouped by the time their delivery task becomes due.
+pub(crate) type PlannedDeliveries = Vec<(DateTime<Utc>, Vec<Uuid>)>;
+
+/// Store issue and its delivery data in transaction and enqueue delivery tasks in the
+/// Postgres queue. With an external queue the planned deliveries are returned
+/// instead, which must be enqueued with `enqueue_external_tasks` before commit.
+/// Subscribers, who reached the frequency cap, are skipped.
+pub(crate) async fn store_issue_for_delivery(
+    transaction: &mut Transaction<'_, Postgres>,
+    issue: &NewIssue<'_>,
+    external_queue: &ExternalDeliveryQueue,
+    frequency_cap: &FrequencyCap,
+) -> Result<(Uuid, Option<PlannedDeliveries>), anyhow::Error> {
+    let issue_id = insert_newsletter_issue(transaction, issue)
         .await
-        .context("Failed to enqueue delivera tasks")?;
-    initialize_newsletter_delivery_data(&mut transaction, issue_id, num_current_subscribers)
+        .context("Failed to store newsletter issue details")?;
+    let is_capped = frequency_cap.0.is_some()
+        || has_frequency_preferences(transaction, issue.list_id)
+            .await
+            .context("Failed to read frequency preferences of subscribers")?;
+    let (num_current_subscribers, external_deliveries) = match external_queue.0 {
+        None if !issue.optimize_send_time && !is_capped => {
+            let num_current_subscribers = enqueue_delivery_tasks(
+                transaction,
+                issue_id,
+                issue.list_id,
+                issue.delivery_start(),
+            )
+            .await
+            .context("Failed to enqueue delivery tasks")?;
+            notify_delivery_worker(&mut **transaction)
+                .await
+                .context("Failed to notify delivery worker")?;
+            (num_current_subscribers, None)
+        }
+        None => {
+            let deliveries =
+                plan_deliveries(transaction, issue_id, issue, external_queue, frequency_cap)
+                    .await
+                    .context("Failed to plan deliveries")?;
+            let mut num_current_subscribers = 0;
+            for (execute_after, subscriber_ids) in deliveries.iter() {
+                num_current_subscribers +=
+                    enqueue_planned_tasks(transaction, issue_id, subscriber_ids, *execute_after)
+                        .await
+                        .context("Failed to enqueue delivery tasks")?;
+            }
+            notify_delivery_worker(&mut **transaction)
+                .await
+                .context("Failed to notify delivery worker")?;
+            (num_current_subscribers, None)
+        }
+        Some(_) => {
+            let deliveries =
+                plan_deliveries(transaction, issue_id, issue, external_queue, frequency_cap)
+                    .await
+                    .context("Failed to plan deliveries")?;
+            let num_current_subscribers = deliveries.iter().map(|(_, ids)| ids.len() as i32).sum();
+            (num_current_subscribers, Some(deliveries))
+        }
+    };
+    initialize_newsletter_delivery_data(transaction, issue_id, num_current_subscribers)
         .await
         .context("Failed to initialize newsletter delivery overview")?;
+    Ok((issue_id, external_deliveries))
+}
 
-    let response = see_other("/admin/newsletters");
-    let response = save_response(transaction, &idempotency_key, *user_id, response).await?;
-    success_message().send();
-    Ok(response)
+/// Enqueue delivery tasks of a committed issue in the external queue, if configured.
+pub(crate) async fn enqueue_external_tasks(
+    external_queue: &ExternalDeliveryQueue,
+    issue_id: Uuid,
+    deliveries: &PlannedDeliveries,
+) -> Result<(), anyhow::Error> {
+    let Some(ref queue) = external_queue.0 else {
+        return Ok(());
+    };
+    for (execute_after, subscriber_ids) in deliveries.iter() {
+        if let Err(e) = queue
+            .enqueue(issue_id, subscriber_ids, *execute_after)
+            .await
+            .context("Failed to enqueue delivery tasks")
+        {
+            remove_external_tasks(external_queue, issue_id).await;
+            return Err(e);
+        }
+    }
+    Ok(())
 }
 
-fn success_message() -> FlashMessage {
-    FlashMessage::info("The newsletter issue has been accepted - emails will go out shortly.")
+/// Remove tasks of an issue, which has not been committed, from the external queue.
+pub(crate) async fn remove_external_tasks(external_queue: &ExternalDeliveryQueue, issue_id: Uuid) {
+    if let Some(ref queue) = external_queue.0 {
+        if let Err(e) = queue.remove_issue(issue_id).await {
+            tracing::error!(
+                error.cause_chain = ?e,
+                newsletter_issue_id = %issue_id,
+                "Failed to remove delivery tasks of uncommitted issue."
+            );
+        }
+    }
+}
+
+/// Plan delivery of confirmed subscribers below the frequency cap at the start of
+/// delivery or now. With send time optimization subscribers with tracked opens are moved to
+/// their best hour.
+async fn plan_deliveries(
+    transaction: &mut Transaction<'_, Postgres>,
+    issue_id: Uuid,
+    issue: &NewIssue<'_>,
+    external_queue: &ExternalDeliveryQueue,
+    frequency_cap: &FrequencyCap,
+) -> Result<PlannedDeliveries, anyhow::Error> {
+    let earliest = issue.delivery_start().unwrap_or_else(Utc::now);
+    let subscriber_ids = get_confirmed_subscriber_ids(transaction, issue.list_id).await?;
+    let subscriber_ids = apply_frequency_cap(
+        transaction,
+        external_queue,
+        frequency_cap.0,
+        issue_id,
+        subscriber_ids,
+    )
+    .await?;
+    if !issue.optimize_send_time {
+        return Ok(vec![(earliest, subscriber_ids)]);
+    }
+    let best_hours = get_best_send_hours(transaction, &subscriber_ids).await?;
+    let mut deliveries: BTreeMap<DateTime<Utc>, Vec<Uuid>> = BTreeMap::new();
+    for subscriber_id in subscriber_ids {
+        let execute_after = best_hours
+            .get(&subscriber_id)
+            .map_or(earliest, |best_hour| {
+                optimized_send_time(earliest, *best_hour)
+            });
+        deliveries
+            .entry(execute_after)
+            .or_default()
+            .push(subscriber_id);
+    }
+    Ok(deliveries.into_iter().collect())
+}
+
+fn success_message(undo_window: &UndoWindow) -> FlashMessage {
+    match undo_window.0 {
+        0 => FlashMessage::info(
+            "The newsletter issue has been accepted - emails will go out shortly.",
+        ),
+        minutes => FlashMessage::info(format!(
+            "The newsletter issue has been accepted - emails will go out in {} minutes, \
+            unless you cancel it.",
+            minutes
+        )),
+    }
 }
 
 #[tracing::instrument(skip_all)]
 async fn insert_newsletter_issue(
     transaction: &mut Transaction<'_, Postgres>,
-    title: &str,
-    text_content: &str,
-    html_content: &str,
+    issue: &NewIssue<'_>,
 ) -> Result<Uuid, sqlx::Error> {
     let newsletter_issue_id = Uuid::new_v4();
     let query = sqlx::query!(
@@ -108,23 +678,69 @@ async fn insert_newsletter_issue(
             title,
             text_content,
             html_content,
-            published_at
+            published_at,
+            collect_feedback,
+            scheduled_at,
+            delivery_weight,
+            optimize_send_time,
+            list_id,
+            cancellable_until
         )
-        VALUES ($1, $2, $3, $4, now())
+        VALUES ($1, $2, $3, $4, now(), $5, $6, $7, $8, $9, $10)
         "#,
         newsletter_issue_id,
-        title,
-        text_content,
-        html_content
+        issue.title,
+        issue.text_content,
+        issue.html_content,
+        issue.collect_feedback,
+        issue.scheduled_at,
+        issue.delivery_weight,
+        issue.optimize_send_time,
+        issue.list_id,
+        issue.cancellable_until,
     );
     transaction.execute(query).await?;
     Ok(newsletter_issue_id)
 }
 
+#[tracing::instrument(skip_all)]
+async fn insert_newsletter_issue_attachment(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+    attachment: &Attachment,
+    scan: &AttachmentScan,
+) -> Result<(), sqlx::Error> {
+    let query = sqlx::query!(
+        r#"
+        INSERT INTO newsletter_issue_attachments (
+            newsletter_issue_id,
+            file_name,
+            content_type,
+            content,
+            scan_status,
+            scan_signature,
+            scanned_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+        newsletter_issue_id,
+        attachment.file_name,
+        attachment.content_type,
+        attachment.content,
+        scan.status as AttachmentScanStatus,
+        scan.signature,
+        scan.scanned_at,
+    );
+    transaction.execute(query).await?;
+    Ok(())
+}
+
 #[tracing::instrument(skip_all)]
 async fn enqueue_delivery_tasks(
     transaction: &mut Transaction<'_, Postgres>,
     newsletter_issue_id: Uuid,
+    list_id: Uuid,
+    scheduled_at: Option<DateTime<Utc>>,
 ) -> Result<i32, sqlx::Error> {
     let query = sqlx::query!(
         r#"
@@ -132,19 +748,77 @@ async fn enqueue_delivery_tasks(
             newsletter_issue_id,
             user_id,
             n_retries,
-            execute_after
+            execute_after,
+            task_version
         )
-        SELECT $1, id, 0, NOW()
+        SELECT $1, id, 0, COALESCE($3, NOW()), $5
         FROM subscriptions
-        WHERE status = $2
+        WHERE
+            status = $2 AND list_id = $4 AND
+            NOT EXISTS (SELECT 1 FROM suppressions WHERE email = subscriptions.email)
         "#,
         newsletter_issue_id,
         SubscriptionsStatus::Confirmed as SubscriptionsStatus,
+        scheduled_at,
+        list_id,
+        TASK_VERSION,
     );
     let num_current_subscribers = transaction.execute(query).await?.rows_affected() as i32;
     Ok(num_current_subscribers)
 }
 
+#[tracing::instrument(skip_all)]
+async fn enqueue_planned_tasks(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+    subscriber_ids: &[Uuid],
+    execute_after: DateTime<Utc>,
+) -> Result<i32, sqlx::Error> {
+    let query = sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_queue (
+            newsletter_issue_id,
+            user_id,
+            n_retries,
+            execute_after,
+            task_version
+        )
+        SELECT $1, user_id, 0, $3, $4
+        FROM UNNEST($2::uuid[]) AS user_id
+        "#,
+        newsletter_issue_id,
+        subscriber_ids,
+        execute_after,
+        TASK_VERSION,
+    );
+    let num_tasks = transaction.execute(query).await?.rows_affected() as i32;
+    Ok(num_tasks)
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_confirmed_subscriber_ids(
+    transaction: &mut Transaction<'_, Postgres>,
+    list_id: Uuid,
+) -> Result<Vec<Uuid>, sqlx::Error> {
+    let subscriber_ids = sqlx::query!(
+        r#"
+        SELECT id
+        FROM subscriptions
+        WHERE
+            status = $1 AND list_id = $2 AND
+            NOT EXISTS (SELECT 1 FROM suppressions WHERE email = subscriptions.email)
+        "#,
+        SubscriptionsStatus::Confirmed as SubscriptionsStatus,
+        list_id,
+    )
+    .fetch_all(&mut **transaction)
+    .await?
+    .into_iter()
+    .map(|r| r.id)
+    .collect();
+    Ok(subscriber_ids)
+}
+
 #[tracing::instrument(skip_all)]
 async fn initialize_newsletter_delivery_data(
     transaction: &mut Transaction<'_, Postgres>,
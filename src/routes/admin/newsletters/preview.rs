@@ -0,0 +1,108 @@
+//! src/routes/admin/newsletters/preview.rs
+
+use actix_web::{web, Responder};
+use anyhow::Context;
+use askama::Template;
+use sqlx::PgPool;
+
+use super::post::{prepare_content, resolve_form_snippets};
+use super::NewsletterFormData;
+use crate::error::Z2PResult;
+use crate::issue_delivery_worker::{EmailHtmlTemplate, EmailTextTemplate};
+use crate::startup::ApplicationBaseUrl;
+
+/// Name of the placeholder subscriber of previews.
+const PREVIEW_SUBSCRIBER_NAME: &str = "Jane Doe";
+
+#[derive(askama_actix::Template)]
+#[template(path = "newsletter_preview.html")]
+struct NewsletterPreviewTemplate {
+    title: String,
+    html_body: String,
+    plain_body: String,
+}
+
+/// Render the newsletter form with the email templates of the delivery worker for a
+/// placeholder subscriber. The html body is shown in a sandboxed iframe.
+#[tracing::instrument(name = "Preview a newsletter", skip_all)]
+pub async fn preview_newsletter(
+    form: web::Form<NewsletterFormData>,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Z2PResult<impl Responder> {
+    let mut form = form.into_inner();
+    prepare_content(&mut form)?;
+    resolve_form_snippets(&pool, &mut form).await?;
+
+    let html_body = render_preview_html(
+        &base_url.0,
+        &form.title,
+        &form.html_content,
+        form.collect_feedback,
+    )
+    .context("Failed to render html body.")?;
+    let plain_body = render_preview_text(
+        &base_url.0,
+        &form.title,
+        &form.text_content,
+        form.collect_feedback,
+    )
+    .context("Failed to render text body.")?;
+    Ok(NewsletterPreviewTemplate {
+        title: form.title,
+        html_body,
+        plain_body,
+    })
+}
+
+/// Links of previews do not refer to a subscriber.
+fn preview_links(base_url: &str, collect_feedback: bool) -> (String, String, Option<String>) {
+    let unsubscribe_link = format!(
+        "{}/subscriptions/unsubscribe?subscription_token=preview",
+        base_url
+    );
+    let preferences_link = format!("{}/preferences?t=preview", base_url);
+    let feedback_link = collect_feedback.then(|| format!("{}/feedback/preview", base_url));
+    (unsubscribe_link, preferences_link, feedback_link)
+}
+
+/// Render html email of content, whose snippets are resolved, for a placeholder subscriber.
+pub(super) fn render_preview_html(
+    base_url: &str,
+    title: &str,
+    html_content: &str,
+    collect_feedback: bool,
+) -> Result<String, askama::Error> {
+    let (unsubscribe_link, preferences_link, feedback_link) =
+        preview_links(base_url, collect_feedback);
+    EmailHtmlTemplate {
+        title,
+        name: PREVIEW_SUBSCRIBER_NAME,
+        content: html_content,
+        unsubscribe_link: &unsubscribe_link,
+        preferences_link: &preferences_link,
+        feedback_link: feedback_link.as_deref(),
+        open_tracking_link: None,
+    }
+    .render()
+}
+
+/// Render text email of content, whose snippets are resolved, for a placeholder subscriber.
+pub(super) fn render_preview_text(
+    base_url: &str,
+    title: &str,
+    text_content: &str,
+    collect_feedback: bool,
+) -> Result<String, askama::Error> {
+    let (unsubscribe_link, preferences_link, feedback_link) =
+        preview_links(base_url, collect_feedback);
+    EmailTextTemplate {
+        title,
+        name: PREVIEW_SUBSCRIBER_NAME,
+        content: text_content,
+        unsubscribe_link: &unsubscribe_link,
+        preferences_link: &preferences_link,
+        feedback_link: feedback_link.as_deref(),
+    }
+    .render()
+}
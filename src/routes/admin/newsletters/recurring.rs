@@ -0,0 +1,196 @@
+//! src/routes/admin/newsletters/recurring.rs
+
+use actix_web::{web, HttpResponse, Responder};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use askama_actix::Template;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::post::parse_scheduled_at;
+use crate::error::{Error, Z2PResult};
+use crate::recurring_issues::{get_recurring_issues, RecurringIssue, DATE_PLACEHOLDER};
+use crate::utils::see_other;
+
+/// Maximum period of a recurring issue.
+pub const MAX_PERIOD_DAYS: i32 = 365;
+
+#[derive(Template)]
+#[template(path = "recurring_issues.html")]
+struct RecurringIssuesTemplate {
+    flash_messages: Vec<String>,
+    recurring_issues: Vec<RecurringIssue>,
+    date_placeholder: &'static str,
+    max_period_days: i32,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct RecurringIssueFormData {
+    pub name: String,
+    pub title: String,
+    pub text_content: String,
+    pub html_content: String,
+    #[serde(default)]
+    pub markdown_content: String,
+    #[serde(default)]
+    pub collect_feedback: bool,
+    pub period_days: String,
+    pub first_scheduled_at: String,
+}
+
+pub async fn recurring_issues(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<impl Responder> {
+    let flash_messages: Vec<String> = flash_messages
+        .iter()
+        .map(|m| m.content().to_string())
+        .collect();
+    let recurring_issues = get_recurring_issues(&pool)
+        .await
+        .context("Failed to read recurring issues.")?;
+    Ok(RecurringIssuesTemplate {
+        flash_messages,
+        recurring_issues,
+        date_placeholder: DATE_PLACEHOLDER,
+        max_period_days: MAX_PERIOD_DAYS,
+    })
+}
+
+/// Create a recurring issue. A draft of each issue is created one period before it
+/// is scheduled and can be edited and published like any other draft.
+#[tracing::instrument(name = "Create recurring issue", skip_all, fields(name=%form.name))]
+pub async fn create_recurring_issue(
+    form: web::Form<RecurringIssueFormData>,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<HttpResponse> {
+    let form = form.into_inner();
+    let name = form.name.trim();
+    if name.is_empty() {
+        FlashMessage::error("You must set a name for the recurring issue.").send();
+        return Ok(see_other("/admin/newsletters/recurring"));
+    }
+    if form.title.trim().is_empty() {
+        FlashMessage::error("You must set a title for the recurring issue.").send();
+        return Ok(see_other("/admin/newsletters/recurring"));
+    }
+    let Some(period_days) = form
+        .period_days
+        .trim()
+        .parse::<i32>()
+        .ok()
+        .filter(|days| (1..=MAX_PERIOD_DAYS).contains(days))
+    else {
+        FlashMessage::error(format!(
+            "The period must be a number of days between 1 and {}.",
+            MAX_PERIOD_DAYS
+        ))
+        .send();
+        return Ok(see_other("/admin/newsletters/recurring"));
+    };
+    let Ok(Some(first_scheduled_at)) = parse_scheduled_at(&form.first_scheduled_at) else {
+        FlashMessage::error("The first issue must be scheduled at a valid date and time.").send();
+        return Ok(see_other("/admin/newsletters/recurring"));
+    };
+    sqlx::query!(
+        r#"
+        INSERT INTO recurring_issues (
+            recurring_issue_id,
+            name,
+            title,
+            text_content,
+            html_content,
+            markdown_content,
+            collect_feedback,
+            period_days,
+            next_scheduled_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        "#,
+        Uuid::new_v4(),
+        name,
+        form.title,
+        form.text_content,
+        form.html_content,
+        form.markdown_content,
+        form.collect_feedback,
+        period_days,
+        first_scheduled_at,
+    )
+    .execute(pool.as_ref())
+    .await
+    .context("Failed to store recurring issue.")?;
+    FlashMessage::info(format!("The recurring issue `{}` has been created.", name)).send();
+    Ok(see_other("/admin/newsletters/recurring"))
+}
+
+/// Skip the next issue of a recurrence; its draft is not created.
+#[tracing::instrument(name = "Skip next recurring issue", skip(pool))]
+pub async fn skip_recurring_issue(
+    path: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<HttpResponse> {
+    let row = sqlx::query!(
+        r#"
+        UPDATE recurring_issues
+        SET next_scheduled_at = next_scheduled_at + make_interval(days => period_days)
+        WHERE recurring_issue_id = $1
+        RETURNING name, next_scheduled_at
+        "#,
+        path.into_inner(),
+    )
+    .fetch_optional(pool.as_ref())
+    .await
+    .context("Failed to skip recurring issue.")?
+    .ok_or(Error::NotFound)?;
+    FlashMessage::info(format!(
+        "The next issue of `{}` has been skipped; the following issue is scheduled at {}.",
+        row.name,
+        row.next_scheduled_at.format("%Y-%m-%d %H:%M UTC")
+    ))
+    .send();
+    Ok(see_other("/admin/newsletters/recurring"))
+}
+
+/// Paused recurrences create no drafts. Issues missed while paused are skipped on resume.
+#[tracing::instrument(name = "Pause recurring issue", skip(pool))]
+pub async fn pause_recurring_issue(
+    path: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<HttpResponse> {
+    let name = set_recurring_issue_paused(&pool, path.into_inner(), true).await?;
+    FlashMessage::info(format!("The recurring issue `{}` has been paused.", name)).send();
+    Ok(see_other("/admin/newsletters/recurring"))
+}
+
+#[tracing::instrument(name = "Resume recurring issue", skip(pool))]
+pub async fn resume_recurring_issue(
+    path: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<HttpResponse> {
+    let name = set_recurring_issue_paused(&pool, path.into_inner(), false).await?;
+    FlashMessage::info(format!("The recurring issue `{}` has been resumed.", name)).send();
+    Ok(see_other("/admin/newsletters/recurring"))
+}
+
+async fn set_recurring_issue_paused(
+    pool: &PgPool,
+    recurring_issue_id: Uuid,
+    paused: bool,
+) -> Z2PResult<String> {
+    let row = sqlx::query!(
+        r#"
+        UPDATE recurring_issues
+        SET paused = $2
+        WHERE recurring_issue_id = $1
+        RETURNING name
+        "#,
+        recurring_issue_id,
+        paused,
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to update recurring issue.")?
+    .ok_or(Error::NotFound)?;
+    Ok(row.name)
+}
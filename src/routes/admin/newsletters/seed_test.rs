@@ -0,0 +1,288 @@
+//! src/routes/admin/newsletters/seed_test.rs
+
+use actix_web::web::ReqData;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{Executor, PgPool};
+use uuid::Uuid;
+
+use super::checklist::content_hash;
+use super::post::{parse_attachment, prepare_content, resolve_form_snippets, scan_attachment};
+use super::preview::{render_preview_html, render_preview_text};
+use super::{NewsletterError, NewsletterFormData};
+use crate::attachment_scan::AttachmentScanner;
+use crate::authentication::UserId;
+use crate::domain::SubscriberEmail;
+use crate::email_client::{BatchEmail, EmailClient};
+use crate::error::{Error, Z2PResult};
+use crate::issue_delivery_worker::PgTransaction;
+use crate::startup::{ApplicationBaseUrl, SeedAddresses};
+use crate::utils::see_other;
+
+/// Number of seed tests shown on the publish page.
+const RECENT_SEED_TESTS: i64 = 5;
+
+/// Result of sending a seed test to one seed mailbox.
+pub struct SeedTestResult {
+    pub email: String,
+    /// Error of sending; `None` if the email server accepted the email
+    pub error: Option<String>,
+}
+
+/// Seed test of issue content with the results of all seed mailboxes.
+pub struct SeedTest {
+    pub seed_test_id: Uuid,
+    pub title: String,
+    pub sent_at: DateTime<Utc>,
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    pub results: Vec<SeedTestResult>,
+}
+
+/// Send the newsletter form to the configured seed mailboxes, e.g. accounts at different
+/// email providers, and record the results. After checking inbox placement of the seeds,
+/// the admin acknowledges the seed test. Like the test email, seeds are sent directly
+/// without delivery queue and idempotency store.
+#[tracing::instrument(
+    name = "Send seed test of newsletter",
+    skip_all,
+    fields(user_id=%&*user_id)
+)]
+pub async fn send_seed_test(
+    form: web::Form<NewsletterFormData>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    attachment_scanner: web::Data<AttachmentScanner>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    seed_addresses: web::Data<SeedAddresses>,
+    user_id: ReqData<UserId>,
+) -> Z2PResult<HttpResponse> {
+    if seed_addresses.0.is_empty() {
+        return Err(NewsletterError::NoSeedAddresses.into());
+    }
+    let mut form = form.into_inner();
+    prepare_content(&mut form)?;
+    let content_hash = content_hash(&form.title, &form.text_content, &form.html_content);
+    resolve_form_snippets(&pool, &mut form).await?;
+    let attachments: Vec<_> = parse_attachment(&form)?.into_iter().collect();
+    for attachment in attachments.iter() {
+        scan_attachment(&attachment_scanner, attachment).await?;
+    }
+    let html_body = render_preview_html(
+        &base_url.0,
+        &form.title,
+        &form.html_content,
+        form.collect_feedback,
+    )
+    .context("Failed to render html body.")?;
+    let plain_body = render_preview_text(
+        &base_url.0,
+        &form.title,
+        &form.text_content,
+        form.collect_feedback,
+    )
+    .context("Failed to render text body.")?;
+    let subject = format!("[SEED] {}", form.title);
+
+    let mut addresses = seed_addresses.0.clone();
+    addresses.sort();
+    addresses.dedup();
+    let mut results = Vec::new();
+    let mut recipients = Vec::new();
+    for address in addresses {
+        match SubscriberEmail::parse(address.clone()) {
+            Ok(recipient) => recipients.push(recipient),
+            Err(e) => results.push(SeedTestResult {
+                email: address,
+                error: Some(e.to_string()),
+            }),
+        }
+    }
+    let emails: Vec<BatchEmail> = recipients
+        .iter()
+        .map(|recipient| BatchEmail {
+            recipient,
+            subject: &subject,
+            html_content: &html_body,
+            text_content: &plain_body,
+            tag: None,
+            attachments: &attachments,
+        })
+        .collect();
+    let send_results = email_client.send_email_batch(&emails).await;
+    for (recipient, send_result) in recipients.iter().zip(send_results) {
+        results.push(SeedTestResult {
+            email: recipient.as_ref().to_owned(),
+            error: send_result.err().map(|e| e.to_string()),
+        });
+    }
+    store_seed_test(&pool, &content_hash, &form.title, &results).await?;
+
+    let num_failed = results.iter().filter(|r| r.error.is_some()).count();
+    FlashMessage::info(format!(
+        "A seed test has been sent to {} of {} seed mailboxes. Acknowledge it after checking inbox placement.",
+        results.len() - num_failed,
+        results.len()
+    ))
+    .send();
+    match Uuid::parse_str(&form.draft_id) {
+        Ok(draft_id) => Ok(see_other(&format!(
+            "/admin/newsletters?draft_id={}",
+            draft_id
+        ))),
+        Err(_) => Ok(see_other("/admin/newsletters")),
+    }
+}
+
+/// Acknowledge a seed test, which satisfies the `seed_test_acknowledged` item of the
+/// checklist, when the same content is published.
+#[tracing::instrument(
+    name = "Acknowledge seed test of newsletter",
+    skip(pool, user_id),
+    fields(user_id=%&*user_id)
+)]
+pub async fn acknowledge_seed_test(
+    path: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user_id: ReqData<UserId>,
+) -> Z2PResult<HttpResponse> {
+    let seed_test_id = path.into_inner();
+    let acknowledged = sqlx::query!(
+        r#"
+        UPDATE seed_tests
+        SET acknowledged_at = now()
+        WHERE seed_test_id = $1 AND acknowledged_at IS NULL
+        RETURNING title
+        "#,
+        seed_test_id,
+    )
+    .fetch_optional(pool.as_ref())
+    .await
+    .context("Failed to acknowledge seed test.")?;
+    match acknowledged {
+        Some(seed_test) => FlashMessage::info(format!(
+            "The seed test of `{}` has been acknowledged.",
+            seed_test.title
+        ))
+        .send(),
+        None => {
+            let exists = sqlx::query!(
+                "SELECT seed_test_id FROM seed_tests WHERE seed_test_id = $1",
+                seed_test_id,
+            )
+            .fetch_optional(pool.as_ref())
+            .await
+            .context("Failed to read seed test.")?;
+            if exists.is_none() {
+                return Err(Error::NotFound);
+            }
+            FlashMessage::info("The seed test has already been acknowledged.").send();
+        }
+    }
+    Ok(see_other("/admin/newsletters"))
+}
+
+#[tracing::instrument(skip(pool, results))]
+async fn store_seed_test(
+    pool: &PgPool,
+    content_hash: &str,
+    title: &str,
+    results: &[SeedTestResult],
+) -> Z2PResult<()> {
+    let mut transaction: PgTransaction = pool
+        .begin()
+        .await
+        .context("Failed to create transaction.")?;
+    let seed_test_id = Uuid::new_v4();
+    let query = sqlx::query!(
+        r#"
+        INSERT INTO seed_tests (seed_test_id, content_hash, title, sent_at)
+        VALUES ($1, $2, $3, now())
+        "#,
+        seed_test_id,
+        content_hash,
+        title,
+    );
+    transaction
+        .execute(query)
+        .await
+        .context("Failed to store seed test.")?;
+    for result in results {
+        let query = sqlx::query!(
+            r#"
+            INSERT INTO seed_test_results (seed_test_id, email, error)
+            VALUES ($1, $2, $3)
+            "#,
+            seed_test_id,
+            result.email,
+            result.error,
+        );
+        transaction
+            .execute(query)
+            .await
+            .context("Failed to store result of seed test.")?;
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit seed test.")?;
+    Ok(())
+}
+
+/// Most recent seed tests with their results for the overview on the publish page.
+#[tracing::instrument(skip(pool))]
+pub(crate) async fn get_recent_seed_tests(pool: &PgPool) -> Result<Vec<SeedTest>, sqlx::Error> {
+    let seed_tests = sqlx::query!(
+        r#"
+        SELECT seed_test_id, title, sent_at, acknowledged_at
+        FROM seed_tests
+        ORDER BY sent_at DESC
+        LIMIT $1
+        "#,
+        RECENT_SEED_TESTS,
+    )
+    .fetch_all(pool)
+    .await?;
+    let mut recent = Vec::with_capacity(seed_tests.len());
+    for seed_test in seed_tests {
+        let results = sqlx::query_as!(
+            SeedTestResult,
+            r#"
+            SELECT email, error
+            FROM seed_test_results
+            WHERE seed_test_id = $1
+            ORDER BY email
+            "#,
+            seed_test.seed_test_id,
+        )
+        .fetch_all(pool)
+        .await?;
+        recent.push(SeedTest {
+            seed_test_id: seed_test.seed_test_id,
+            title: seed_test.title,
+            sent_at: seed_test.sent_at,
+            acknowledged_at: seed_test.acknowledged_at,
+            results,
+        });
+    }
+    Ok(recent)
+}
+
+pub(super) async fn is_seed_test_acknowledged(
+    pool: &PgPool,
+    content_hash: &str,
+) -> Result<bool, sqlx::Error> {
+    let seed_test = sqlx::query!(
+        r#"
+        SELECT seed_test_id
+        FROM seed_tests
+        WHERE content_hash = $1 AND acknowledged_at IS NOT NULL
+        LIMIT 1
+        "#,
+        content_hash,
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(seed_test.is_some())
+}
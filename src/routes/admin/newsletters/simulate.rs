@@ -0,0 +1,214 @@
+//! src/routes/admin/newsletters/simulate.rs
+
+use actix_web::{web, Responder};
+use anyhow::Context;
+use askama_actix::Template;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::post::{
+    parse_attachment, parse_delivery_weight, parse_issue_list_id, parse_scheduled_at,
+    prepare_content, resolve_form_snippets,
+};
+use super::NewsletterFormData;
+use crate::domain::{SubscriberEmail, SubscriberName};
+use crate::error::Z2PResult;
+use crate::issue_delivery_worker::{get_daily_send_volume, EmailHtmlTemplate, EmailTextTemplate};
+use crate::provider_usage::{get_provider_usage, CostEstimate};
+use crate::routes::SubscriptionsStatus;
+use crate::startup::{ApplicationBaseUrl, ProviderPlan, SendRateLimits};
+
+/// Maximum number of render errors listed in the report.
+const MAX_REPORTED_ERRORS: usize = 20;
+
+#[derive(Template)]
+#[template(path = "newsletter_simulation.html")]
+struct SimulationReport {
+    title: String,
+    num_recipients: usize,
+    num_pending_confirmation: i64,
+    num_queued_tasks: i64,
+    scheduled_at: Option<DateTime<Utc>>,
+    estimated_days: Option<u64>,
+    /// Cost and quota of the emails, if the plan of the provider is configured
+    cost_estimate: Option<CostEstimate>,
+    num_render_errors: usize,
+    render_errors: Vec<String>,
+}
+
+struct Recipient {
+    id: Uuid,
+    email: String,
+    name: String,
+    subscription_token: Option<String>,
+}
+
+/// Run the targeting and rendering of the delivery worker for the newsletter form without
+/// storing the issue or enqueueing delivery tasks, and report what publishing would do.
+#[tracing::instrument(name = "Simulate sending a newsletter", skip_all)]
+pub async fn simulate_newsletter(
+    form: web::Form<NewsletterFormData>,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    send_rate_limits: web::Data<SendRateLimits>,
+    provider_plan: web::Data<ProviderPlan>,
+) -> Z2PResult<impl Responder> {
+    let mut form = form.into_inner();
+    prepare_content(&mut form)?;
+    resolve_form_snippets(&pool, &mut form).await?;
+    parse_attachment(&form)?;
+    let scheduled_at = parse_scheduled_at(&form.scheduled_at)?;
+    parse_delivery_weight(&form.delivery_weight)?;
+    let list_id = parse_issue_list_id(&pool, &form.list_id).await?;
+
+    let recipients = get_recipients(&pool, list_id)
+        .await
+        .context("Failed to read recipients of newsletter")?;
+    let num_pending_confirmation =
+        count_subscribers(&pool, list_id, SubscriptionsStatus::PendingConfirmation)
+            .await
+            .context("Failed to count subscribers pending confirmation")?;
+    let num_queued_tasks = count_queued_tasks(&pool)
+        .await
+        .context("Failed to count queued delivery tasks")?;
+
+    let mut render_errors = Vec::new();
+    for recipient in recipients.iter() {
+        if let Err(e) = render_email(&form, recipient, &base_url.0) {
+            render_errors.push(format!("{}: {}", recipient.id, e));
+        }
+    }
+    let num_render_errors = render_errors.len();
+    render_errors.truncate(MAX_REPORTED_ERRORS);
+
+    // queued tasks of other issues are sent in parallel and share the send volume
+    let start_date = scheduled_at.unwrap_or_else(Utc::now).date_naive();
+    let num_emails = (recipients.len() - num_render_errors) as u64;
+    let estimated_days = match send_rate_limits.0 {
+        Some(ref warm_up) => {
+            let num_sent = get_daily_send_volume(&pool, start_date).await?;
+            Some(warm_up.days_to_send(start_date, num_sent, num_emails + num_queued_tasks as u64))
+        }
+        None => None,
+    };
+    let cost_estimate = match provider_plan.0 {
+        Some(ref plan) => {
+            let usage = get_provider_usage(&pool, plan, start_date)
+                .await
+                .context("Failed to read usage of provider plan")?;
+            Some(CostEstimate::new(plan, usage, num_emails))
+        }
+        None => None,
+    };
+
+    Ok(SimulationReport {
+        title: form.title,
+        num_recipients: recipients.len(),
+        num_pending_confirmation,
+        num_queued_tasks,
+        scheduled_at,
+        estimated_days,
+        cost_estimate,
+        num_render_errors,
+        render_errors,
+    })
+}
+
+/// Render html and plain text email of a recipient like the delivery worker does.
+fn render_email(
+    form: &NewsletterFormData,
+    recipient: &Recipient,
+    base_url: &str,
+) -> Result<(), anyhow::Error> {
+    let name = SubscriberName::parse(recipient.name.clone())?;
+    SubscriberEmail::parse(recipient.email.clone())?;
+    let token = recipient
+        .subscription_token
+        .as_deref()
+        .context("Subscriber has no subscription token.")?;
+    let unsubscribe_link = format!(
+        "{}/subscriptions/unsubscribe?subscription_token={}",
+        base_url, token
+    );
+    // issue id is not known before publishing
+    let preferences_link = format!("{}/preferences?t={}", base_url, token);
+    let feedback_link = form
+        .collect_feedback
+        .then(|| format!("{}/feedback/simulation?t={}", base_url, token));
+    EmailTextTemplate {
+        title: &form.title,
+        name: name.as_ref(),
+        content: &form.text_content,
+        unsubscribe_link: &unsubscribe_link,
+        preferences_link: &preferences_link,
+        feedback_link: feedback_link.as_deref(),
+    }
+    .render()
+    .context("Failed to render text body.")?;
+    EmailHtmlTemplate {
+        title: &form.title,
+        name: name.as_ref(),
+        content: &form.html_content,
+        unsubscribe_link: &unsubscribe_link,
+        preferences_link: &preferences_link,
+        feedback_link: feedback_link.as_deref(),
+        open_tracking_link: None,
+    }
+    .render()
+    .context("Failed to render html body.")?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_recipients(pool: &PgPool, list_id: Uuid) -> Result<Vec<Recipient>, sqlx::Error> {
+    sqlx::query_as!(
+        Recipient,
+        r#"
+        SELECT s.id, s.email, s.name, t.subscription_token AS "subscription_token?"
+        FROM subscriptions s
+        LEFT JOIN subscription_tokens t ON t.subscriber_id = s.id
+        WHERE s.status = $1 AND s.list_id = $2
+        "#,
+        SubscriptionsStatus::Confirmed as SubscriptionsStatus,
+        list_id,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+#[tracing::instrument(skip(pool))]
+async fn count_subscribers(
+    pool: &PgPool,
+    list_id: Uuid,
+    status: SubscriptionsStatus,
+) -> Result<i64, sqlx::Error> {
+    let count = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM subscriptions
+        WHERE status = $1 AND list_id = $2
+        "#,
+        status as SubscriptionsStatus,
+        list_id,
+    )
+    .fetch_one(pool)
+    .await?
+    .count;
+    Ok(count)
+}
+
+#[tracing::instrument(skip_all)]
+async fn count_queued_tasks(pool: &PgPool) -> Result<i64, sqlx::Error> {
+    let count = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM issue_delivery_queue
+        WHERE status = 'pending'
+        "#,
+    )
+    .fetch_one(pool)
+    .await?
+    .count;
+    Ok(count)
+}
@@ -0,0 +1,192 @@
+//! src/routes/admin/newsletters/size_budget.rs
+
+use anyhow::Context;
+use scraper::{Html, Node};
+use sqlx::PgPool;
+use std::cmp::Reverse;
+
+use super::preview::render_preview_html;
+use super::NewsletterError;
+use crate::error::Error;
+use crate::snippets::{get_current_snippets, referenced_snippets};
+
+/// Number of largest content blocks, which are reported for emails beyond the budget.
+const NUM_REPORTED_BLOCKS: usize = 3;
+/// Number of words of a block's text, which describe the block in reports.
+const NUM_DESCRIPTION_WORDS: usize = 5;
+
+/// Size budget of rendered html emails including inlined images. Gmail clips
+/// emails, whose html exceeds 102 KB.
+#[derive(serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct EmailSizeBudget {
+    /// Publishing warns about larger emails.
+    #[serde(default = "default_warn_kilobytes")]
+    pub warn_kilobytes: u32,
+    /// Optional limit, beyond which publishing is rejected.
+    #[serde(default)]
+    pub max_kilobytes: Option<u32>,
+}
+
+impl Default for EmailSizeBudget {
+    fn default() -> Self {
+        Self {
+            warn_kilobytes: default_warn_kilobytes(),
+            max_kilobytes: None,
+        }
+    }
+}
+
+fn default_warn_kilobytes() -> u32 {
+    102
+}
+
+/// Size of rendered html email and the largest top level blocks of its content.
+struct EmailSize {
+    bytes: usize,
+    largest_blocks: Vec<(String, usize)>,
+}
+
+impl EmailSize {
+    fn measure(html_email: &str, html_content: &str) -> Self {
+        let fragment = Html::parse_fragment(html_content);
+        let mut blocks: Vec<(String, usize)> = fragment
+            .root_element()
+            .children()
+            .filter_map(|node| match node.value() {
+                Node::Element(element) => {
+                    let text: String = node
+                        .descendants()
+                        .filter_map(|n| n.value().as_text().map(|t| t.to_string()))
+                        .collect();
+                    let html = scraper::ElementRef::wrap(node)?.html();
+                    Some((describe_block(element.name(), &text), html.len()))
+                }
+                Node::Text(text) if !text.trim().is_empty() => {
+                    Some((describe_block("text", text), text.len()))
+                }
+                _ => None,
+            })
+            .collect();
+        blocks.sort_by_key(|(_, bytes)| Reverse(*bytes));
+        blocks.truncate(NUM_REPORTED_BLOCKS);
+        Self {
+            bytes: html_email.len(),
+            largest_blocks: blocks,
+        }
+    }
+
+    fn report(&self) -> String {
+        let blocks = self
+            .largest_blocks
+            .iter()
+            .map(|(description, bytes)| format!("{} ({} KB)", description, kilobytes(*bytes)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "the rendered email has {} KB, largest blocks are {}",
+            kilobytes(self.bytes),
+            blocks
+        )
+    }
+}
+
+/// Describe block by its tag and the first words of its text, e.g. `<p> "Welcome to …"`.
+fn describe_block(tag: &str, text: &str) -> String {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let tag = match tag {
+        "text" => tag.to_string(),
+        _ => format!("<{}>", tag),
+    };
+    match words.len() {
+        0 => tag,
+        n if n <= NUM_DESCRIPTION_WORDS => format!("{} \"{}\"", tag, words.join(" ")),
+        _ => format!("{} \"{} …\"", tag, words[..NUM_DESCRIPTION_WORDS].join(" ")),
+    }
+}
+
+/// Size in KB, rounded up.
+fn kilobytes(bytes: usize) -> usize {
+    bytes.div_ceil(1024)
+}
+
+/// Render the html email of an issue like its preview and check its size
+/// against the budget. Emails beyond the maximum size are rejected; emails beyond
+/// the warning threshold return a warning, which reports the largest blocks.
+pub(crate) async fn check_email_size(
+    pool: &PgPool,
+    base_url: &str,
+    budget: &EmailSizeBudget,
+    title: &str,
+    html_content: &str,
+    collect_feedback: bool,
+) -> Result<Option<String>, Error> {
+    let names = referenced_snippets(&[html_content]);
+    let html_content = if names.is_empty() {
+        html_content.to_string()
+    } else {
+        get_current_snippets(pool, &names)
+            .await
+            .context("Failed to read snippets.")?
+            .resolve_html(html_content)
+    };
+    let html_email = render_preview_html(base_url, title, &html_content, collect_feedback)
+        .context("Failed to render html body.")?;
+    let size = EmailSize::measure(&html_email, &html_content);
+    if let Some(max_kilobytes) = budget.max_kilobytes {
+        if size.bytes > max_kilobytes as usize * 1024 {
+            return Err(NewsletterError::EmailTooLarge(max_kilobytes, size.report()).into());
+        }
+    }
+    if size.bytes > budget.warn_kilobytes as usize * 1024 {
+        return Ok(Some(format!(
+            "The issue exceeds the email size budget of {} KB, beyond which email \
+            clients like Gmail clip emails: {}.",
+            budget.warn_kilobytes,
+            size.report()
+        )));
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{describe_block, EmailSize};
+
+    #[test]
+    fn largest_blocks_are_reported_first() {
+        let image = format!(r#"<img src="data:image/png;base64,{}">"#, "A".repeat(4000));
+        let html = format!(
+            "<h1>Title</h1><p>{}</p>{}<p>short</p>",
+            "word ".repeat(500),
+            image
+        );
+        let size = EmailSize::measure(&html, &html);
+        let descriptions: Vec<&str> = size
+            .largest_blocks
+            .iter()
+            .map(|(description, _)| description.as_str())
+            .collect();
+        assert_eq!(
+            descriptions,
+            [
+                "<img>",
+                "<p> \"word word word word word …\"",
+                "<h1> \"Title\""
+            ]
+        );
+        assert_eq!(size.bytes, html.len());
+    }
+
+    #[test]
+    fn blocks_are_described_by_tag_and_first_words() {
+        assert_eq!(describe_block("img", ""), "<img>");
+        assert_eq!(
+            describe_block("p", " Hello\n world "),
+            "<p> \"Hello world\""
+        );
+        assert_eq!(
+            describe_block("text", "one two three four five six"),
+            "text \"one two three four five …\""
+        );
+    }
+}
@@ -0,0 +1,111 @@
+//! src/routes/admin/newsletters/test_send.rs
+
+use actix_web::web::ReqData;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use askama::Template;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::checklist::{content_hash, record_test_email};
+use super::post::{parse_attachment, prepare_content, resolve_form_snippets, scan_attachment};
+use super::{NewsletterError, NewsletterFormData};
+use crate::attachment_scan::AttachmentScanner;
+use crate::authentication::UserId;
+use crate::domain::SubscriberEmail;
+use crate::email_client::{BatchEmail, EmailClient};
+use crate::error::Z2PResult;
+use crate::issue_delivery_worker::{EmailHtmlTemplate, EmailTextTemplate};
+use crate::startup::ApplicationBaseUrl;
+use crate::utils::see_other;
+
+/// Render the newsletter form with the email templates of the delivery worker and send it
+/// to the logged-in admin only. Neither the delivery queue nor the idempotency store is used.
+#[tracing::instrument(
+    name = "Send test email of newsletter",
+    skip_all,
+    fields(user_id=%&*user_id)
+)]
+pub async fn send_test_newsletter(
+    form: web::Form<NewsletterFormData>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    attachment_scanner: web::Data<AttachmentScanner>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    user_id: ReqData<UserId>,
+) -> Z2PResult<HttpResponse> {
+    let mut form = form.into_inner();
+    prepare_content(&mut form)?;
+    let content_hash = content_hash(&form.title, &form.text_content, &form.html_content);
+    resolve_form_snippets(&pool, &mut form).await?;
+    let attachments: Vec<_> = parse_attachment(&form)?.into_iter().collect();
+    for attachment in attachments.iter() {
+        scan_attachment(&attachment_scanner, attachment).await?;
+    }
+    let username = user_id.get_username(&pool).await?;
+    let recipient = user_id
+        .get_email(&pool)
+        .await?
+        .and_then(|email| SubscriberEmail::parse(email).ok())
+        .ok_or(NewsletterError::NoAdminEmail)?;
+
+    // links of test email do not refer to a subscriber
+    let unsubscribe_link = format!("{}/subscriptions/unsubscribe", base_url.0);
+    let preferences_link = format!("{}/preferences", base_url.0);
+    let feedback_link = form
+        .collect_feedback
+        .then(|| format!("{}/feedback/test", base_url.0));
+    let html_body = EmailHtmlTemplate {
+        title: &form.title,
+        name: &username,
+        content: &form.html_content,
+        unsubscribe_link: &unsubscribe_link,
+        preferences_link: &preferences_link,
+        feedback_link: feedback_link.as_deref(),
+        open_tracking_link: None,
+    }
+    .render()
+    .context("Failed to render html body.")?;
+    let plain_body = EmailTextTemplate {
+        title: &form.title,
+        name: &username,
+        content: &form.text_content,
+        unsubscribe_link: &unsubscribe_link,
+        preferences_link: &preferences_link,
+        feedback_link: feedback_link.as_deref(),
+    }
+    .render()
+    .context("Failed to render text body.")?;
+    let subject = format!("[TEST] {}", form.title);
+    let email = BatchEmail {
+        recipient: &recipient,
+        subject: &subject,
+        html_content: &html_body,
+        text_content: &plain_body,
+        tag: None,
+        attachments: &attachments,
+    };
+    email_client
+        .send_email_batch(&[email])
+        .await
+        .pop()
+        .context("No result of sending test email.")?
+        .context("Failed to send test email.")?;
+    record_test_email(&pool, &content_hash)
+        .await
+        .context("Failed to record test email.")?;
+
+    FlashMessage::info(format!(
+        "A test email has been sent to {}.",
+        recipient.as_ref()
+    ))
+    .send();
+    match Uuid::parse_str(&form.draft_id) {
+        Ok(draft_id) => Ok(see_other(&format!(
+            "/admin/newsletters?draft_id={}",
+            draft_id
+        ))),
+        Err(_) => Ok(see_other("/admin/newsletters")),
+    }
+}
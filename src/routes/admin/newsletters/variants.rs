@@ -0,0 +1,200 @@
+//! src/routes/admin/newsletters/variants.rs
+
+use actix_web::{web, HttpResponse, Responder};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use askama_actix::Template;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::post::{check_content, check_snippets};
+use crate::domain::Locale;
+use crate::error::{Error, Z2PResult};
+use crate::utils::see_other;
+
+/// Delivery counts of a language variant. The default content of the issue is
+/// listed without locale.
+struct VariantStats {
+    locale: Option<String>,
+    title: String,
+    num_delivered_newsletters: i32,
+    num_failed_deliveries: i32,
+}
+
+#[derive(Template)]
+#[template(path = "newsletter_variants.html")]
+struct NewsletterVariantsTemplate {
+    flash_messages: Vec<String>,
+    newsletter_issue_id: Uuid,
+    variants: Vec<VariantStats>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+pub struct NewsletterVariantFormData {
+    pub locale: String,
+    pub title: String,
+    pub text_content: String,
+    pub html_content: String,
+}
+
+/// List language variants of an issue with their delivery counts.
+pub async fn newsletter_variants(
+    flash_messages: IncomingFlashMessages,
+    newsletter_issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<impl Responder> {
+    let flash_messages: Vec<String> = flash_messages
+        .iter()
+        .map(|m| m.content().to_string())
+        .collect();
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let issue = sqlx::query!(
+        r#"
+        SELECT title, num_delivered_newsletters, num_failed_deliveries
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_optional(pool.as_ref())
+    .await
+    .context("Failed to read newsletter issue")?
+    .ok_or(Error::NotFound)?;
+    let mut variants: Vec<VariantStats> = sqlx::query_as!(
+        VariantStats,
+        r#"
+        SELECT
+            locale AS "locale?",
+            title,
+            num_delivered_newsletters,
+            num_failed_deliveries
+        FROM newsletter_issue_variants
+        WHERE newsletter_issue_id = $1
+        ORDER BY locale
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_all(pool.as_ref())
+    .await
+    .context("Failed to read language variants of newsletter issue")?;
+    // counters of issue include deliveries of all variants
+    let default_variant = VariantStats {
+        locale: None,
+        title: issue.title,
+        num_delivered_newsletters: issue.num_delivered_newsletters.unwrap_or(0)
+            - variants
+                .iter()
+                .map(|v| v.num_delivered_newsletters)
+                .sum::<i32>(),
+        num_failed_deliveries: issue.num_failed_deliveries.unwrap_or(0)
+            - variants
+                .iter()
+                .map(|v| v.num_failed_deliveries)
+                .sum::<i32>(),
+    };
+    variants.insert(0, default_variant);
+    Ok(NewsletterVariantsTemplate {
+        flash_messages,
+        newsletter_issue_id,
+        variants,
+    })
+}
+
+/// Add or replace the language variant of an issue. Variants apply to all
+/// emails of the issue, which have not been sent yet.
+#[tracing::instrument(name = "Save language variant of newsletter issue", skip(form, pool))]
+pub async fn save_newsletter_variant(
+    newsletter_issue_id: web::Path<Uuid>,
+    form: web::Form<NewsletterVariantFormData>,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<HttpResponse> {
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let variants_url = format!("/admin/newsletters/{}/variants", newsletter_issue_id);
+    let form = form.into_inner();
+    let locale = match Locale::parse(form.locale) {
+        Ok(locale) => locale,
+        Err(e) => {
+            FlashMessage::error(e.to_string()).send();
+            return Ok(see_other(&variants_url));
+        }
+    };
+    if let Err(e) = check_content(&form.title, &form.text_content, &form.html_content) {
+        FlashMessage::error(e.to_string()).send();
+        return Ok(see_other(&variants_url));
+    }
+    match check_snippets(&pool, &[&form.text_content, &form.html_content]).await {
+        Err(Error::NewsletterError(e)) => {
+            FlashMessage::error(e.to_string()).send();
+            return Ok(see_other(&variants_url));
+        }
+        result => result?,
+    }
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO newsletter_issue_variants (
+            newsletter_issue_id,
+            locale,
+            title,
+            text_content,
+            html_content
+        )
+        SELECT newsletter_issue_id, $2, $3, $4, $5
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        ON CONFLICT (newsletter_issue_id, locale) DO UPDATE
+        SET
+            title = EXCLUDED.title,
+            text_content = EXCLUDED.text_content,
+            html_content = EXCLUDED.html_content
+        "#,
+        newsletter_issue_id,
+        locale.as_ref(),
+        form.title,
+        form.text_content,
+        form.html_content,
+    )
+    .execute(pool.as_ref())
+    .await
+    .context("Failed to save language variant of newsletter issue")?;
+    if result.rows_affected() == 0 {
+        return Err(Error::NotFound);
+    }
+    FlashMessage::info(format!(
+        "The language variant `{}` has been saved.",
+        locale.as_ref()
+    ))
+    .send();
+    Ok(see_other(&variants_url))
+}
+
+/// Remove a language variant; its subscribers receive the default content of the issue.
+#[tracing::instrument(name = "Delete language variant of newsletter issue", skip(pool))]
+pub async fn delete_newsletter_variant(
+    path: web::Path<(Uuid, String)>,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<HttpResponse> {
+    let (newsletter_issue_id, locale) = path.into_inner();
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM newsletter_issue_variants
+        WHERE newsletter_issue_id = $1 AND locale = $2
+        "#,
+        newsletter_issue_id,
+        locale
+    )
+    .execute(pool.as_ref())
+    .await
+    .context("Failed to delete language variant of newsletter issue")?;
+    if result.rows_affected() == 0 {
+        return Err(Error::NotFound);
+    }
+    FlashMessage::info(format!(
+        "The language variant `{}` has been deleted.",
+        locale
+    ))
+    .send();
+    Ok(see_other(&format!(
+        "/admin/newsletters/{}/variants",
+        newsletter_issue_id
+    )))
+}
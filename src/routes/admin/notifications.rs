@@ -0,0 +1,96 @@
+//! src/routes/admin/notifications.rs
+
+use actix_web::{web, HttpResponse, Responder};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use askama_actix::Template;
+use sqlx::PgPool;
+
+use crate::authentication::UserId;
+use crate::error::Z2PResult;
+use crate::notifications::{
+    get_notification_preferences, save_notification_preferences, NotificationChannel,
+    NotificationEvent, NotificationPreferences,
+};
+use crate::utils::see_other;
+
+#[derive(Template)]
+#[template(path = "notifications.html")]
+struct NotificationsTemplate {
+    flash_messages: Vec<String>,
+    preferences: NotificationPreferences,
+    events: [NotificationEvent; 2],
+}
+
+/// Checked events are sent with the name of the event.
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct NotificationsFormData {
+    pub channel: NotificationChannel,
+    pub slack_webhook_url: String,
+    pub subscriber_milestone: Option<String>,
+    pub delivery_quarantined: Option<String>,
+}
+
+pub async fn notification_preferences(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Z2PResult<impl Responder> {
+    let flash_messages: Vec<String> = flash_messages
+        .iter()
+        .map(|m| m.content().to_string())
+        .collect();
+    let preferences = get_notification_preferences(&pool, **user_id)
+        .await
+        .context("Failed to read notification preferences.")?;
+    Ok(NotificationsTemplate {
+        flash_messages,
+        preferences,
+        events: NotificationEvent::ALL,
+    })
+}
+
+/// Save on which channel the user is notified about which events.
+#[tracing::instrument(name = "Save notification preferences", skip_all, fields(user_id=%*user_id))]
+pub async fn save_notifications(
+    form: web::Form<NotificationsFormData>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Z2PResult<HttpResponse> {
+    let form = form.into_inner();
+    let slack_webhook_url =
+        Some(form.slack_webhook_url.trim().to_string()).filter(|url| !url.is_empty());
+    if let Some(url) = &slack_webhook_url {
+        if !reqwest::Url::parse(url).is_ok_and(|url| ["http", "https"].contains(&url.scheme())) {
+            FlashMessage::error("The Slack webhook URL is not a valid http(s) URL.").send();
+            return Ok(see_other("/admin/notifications"));
+        }
+    }
+    if form.channel == NotificationChannel::Slack && slack_webhook_url.is_none() {
+        FlashMessage::error("You must set a Slack webhook URL to be notified on Slack.").send();
+        return Ok(see_other("/admin/notifications"));
+    }
+    let events = [
+        (
+            NotificationEvent::SubscriberMilestone,
+            &form.subscriber_milestone,
+        ),
+        (
+            NotificationEvent::DeliveryQuarantined,
+            &form.delivery_quarantined,
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(event, checked)| checked.is_some().then_some(event))
+    .collect();
+    let preferences = NotificationPreferences {
+        channel: form.channel,
+        slack_webhook_url,
+        events,
+    };
+    save_notification_preferences(&pool, **user_id, &preferences)
+        .await
+        .context("Failed to save notification preferences.")?;
+    FlashMessage::info("Your notification preferences have been saved.").send();
+    Ok(see_other("/admin/notifications"))
+}
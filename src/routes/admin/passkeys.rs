@@ -0,0 +1,122 @@
+//! src/routes/admin/passkeys.rs
+
+use actix_web::{web, HttpResponse, Responder};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use askama_actix::Template;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::authentication::{
+    delete_passkey, get_passkey_credential_ids, get_passkeys, store_passkey, Passkey,
+    PasskeyRelyingParty, RegistrationCredential, UserId,
+};
+use crate::error::{Error, Z2PResult};
+use crate::session_state::TypedSession;
+use crate::utils::see_other;
+
+#[derive(Template)]
+#[template(path = "passkeys.html")]
+struct PasskeysTemplate {
+    flash_messages: Vec<String>,
+    passkeys: Vec<Passkey>,
+}
+
+/// Passkey, which has been created by the authenticator of the browser.
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct PasskeyRegistration {
+    pub name: String,
+    pub credential: RegistrationCredential,
+}
+
+pub async fn passkeys(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Z2PResult<impl Responder> {
+    let flash_messages: Vec<String> = flash_messages
+        .iter()
+        .map(|m| m.content().to_string())
+        .collect();
+    let passkeys = get_passkeys(&pool, **user_id)
+        .await
+        .context("Failed to read passkeys.")?;
+    Ok(PasskeysTemplate {
+        flash_messages,
+        passkeys,
+    })
+}
+
+/// Options to create a passkey in the browser; the challenge is kept in the session.
+#[tracing::instrument(name = "Start passkey registration", skip_all, fields(user_id=%*user_id))]
+pub async fn passkey_registration_options(
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    relying_party: web::Data<PasskeyRelyingParty>,
+    session: TypedSession,
+) -> Z2PResult<HttpResponse> {
+    let username = user_id.get_username(&pool).await?;
+    let registered = get_passkey_credential_ids(&pool, **user_id)
+        .await
+        .context("Failed to read passkeys.")?;
+    let (options, challenge) =
+        relying_party.registration_options(**user_id, &username, &registered);
+    session.insert_passkey_challenge(&challenge)?;
+    Ok(HttpResponse::Ok().json(options))
+}
+
+/// Store the passkey, which the browser created with the options of the session.
+#[tracing::instrument(name = "Register passkey", skip_all, fields(user_id=%*user_id))]
+pub async fn register_passkey(
+    registration: web::Json<PasskeyRegistration>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    relying_party: web::Data<PasskeyRelyingParty>,
+    session: TypedSession,
+) -> Z2PResult<HttpResponse> {
+    let name = registration.name.trim();
+    if name.is_empty() {
+        FlashMessage::error("You must set a name for the passkey.").send();
+        return Ok(see_other("/admin/security/passkeys"));
+    }
+    let passkey = session
+        .take_passkey_challenge()?
+        .context("No passkey registration is pending.")
+        .and_then(|challenge| {
+            relying_party.finish_registration(&challenge, &registration.credential)
+        });
+    let passkey = match passkey {
+        Ok(passkey) => passkey,
+        Err(e) => {
+            tracing::warn!(error.cause_chain = ?e, "Failed to verify passkey registration.");
+            FlashMessage::error("The passkey could not be registered.").send();
+            return Ok(see_other("/admin/security/passkeys"));
+        }
+    };
+    match store_passkey(&pool, **user_id, name, &passkey).await {
+        Ok(_) => FlashMessage::info(format!("The passkey `{}` has been registered.", name)).send(),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            FlashMessage::error("The passkey is already registered.").send()
+        }
+        Err(e) => {
+            return Err(anyhow::Error::from(e)
+                .context("Failed to store passkey.")
+                .into())
+        }
+    }
+    Ok(see_other("/admin/security/passkeys"))
+}
+
+#[tracing::instrument(name = "Delete passkey", skip(pool))]
+pub async fn remove_passkey(
+    path: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Z2PResult<HttpResponse> {
+    let name = delete_passkey(&pool, **user_id, path.into_inner())
+        .await
+        .context("Failed to delete passkey.")?
+        .ok_or(Error::NotFound)?;
+    FlashMessage::info(format!("The passkey `{}` has been deleted.", name)).send();
+    Ok(see_other("/admin/security/passkeys"))
+}
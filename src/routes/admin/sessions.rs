@@ -0,0 +1,86 @@
+//! src/routes/admin/sessions.rs
+
+use actix_web::{web, HttpResponse, Responder};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use askama_actix::Template;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::authentication::{
+    get_admin_sessions, revoke_admin_session, revoke_all_admin_sessions, AdminSession, UserId,
+};
+use crate::error::{Error, Z2PResult};
+use crate::session_state::TypedSession;
+use crate::utils::see_other;
+
+#[derive(Template)]
+#[template(path = "sessions.html")]
+struct SessionsTemplate {
+    flash_messages: Vec<String>,
+    sessions: Vec<AdminSession>,
+    current_session_id: Option<Uuid>,
+}
+
+pub async fn admin_sessions(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    session: TypedSession,
+) -> Z2PResult<impl Responder> {
+    let flash_messages: Vec<String> = flash_messages
+        .iter()
+        .map(|m| m.content().to_string())
+        .collect();
+    let sessions = get_admin_sessions(&pool, **user_id)
+        .await
+        .context("Failed to read admin sessions.")?;
+    Ok(SessionsTemplate {
+        flash_messages,
+        sessions,
+        current_session_id: session.get_session_id()?,
+    })
+}
+
+/// Revoke another session of the user, which is logged out with its next request.
+#[tracing::instrument(name = "Revoke session", skip(pool, session))]
+pub async fn revoke_session(
+    path: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    session: TypedSession,
+) -> Z2PResult<HttpResponse> {
+    let session_id = path.into_inner();
+    if !revoke_admin_session(&pool, **user_id, session_id)
+        .await
+        .context("Failed to revoke admin session.")?
+    {
+        return Err(Error::NotFound);
+    }
+    if session.get_session_id()? == Some(session_id) {
+        session.log_out();
+        FlashMessage::info("You have successfully logged out.").send();
+        return Ok(see_other("/login"));
+    }
+    FlashMessage::info("The session has been revoked.").send();
+    Ok(see_other("/admin/sessions"))
+}
+
+/// Revoke all sessions of the user including the current one.
+#[tracing::instrument(name = "Log out everywhere", skip(pool, session))]
+pub async fn revoke_all_sessions(
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    session: TypedSession,
+) -> Z2PResult<HttpResponse> {
+    let n_sessions = revoke_all_admin_sessions(&pool, **user_id)
+        .await
+        .context("Failed to revoke admin sessions.")?;
+    session.log_out();
+    FlashMessage::info(format!(
+        "You have been logged out of {} sessions.",
+        n_sessions
+    ))
+    .send();
+    Ok(see_other("/login"))
+}
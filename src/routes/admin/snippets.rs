@@ -0,0 +1,73 @@
+//! src/routes/admin/snippets.rs
+
+use actix_web::{web, HttpResponse, Responder};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use askama_actix::Template;
+use sqlx::PgPool;
+
+use crate::error::Z2PResult;
+use crate::snippets::{
+    get_snippet_overviews, is_valid_snippet_name, save_snippet, SnippetOverview,
+};
+use crate::utils::see_other;
+
+#[derive(Template)]
+#[template(path = "snippets.html")]
+struct SnippetsTemplate {
+    flash_messages: Vec<String>,
+    snippets: Vec<SnippetOverview>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct SnippetFormData {
+    pub name: String,
+    pub text_content: String,
+    pub html_content: String,
+}
+
+pub async fn content_snippets(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<impl Responder> {
+    let flash_messages: Vec<String> = flash_messages
+        .iter()
+        .map(|m| m.content().to_string())
+        .collect();
+    let snippets = get_snippet_overviews(&pool)
+        .await
+        .context("Failed to read snippets.")?;
+    Ok(SnippetsTemplate {
+        flash_messages,
+        snippets,
+    })
+}
+
+/// Create a snippet or store a new version of an existing snippet. Issues, whose
+/// emails are already rendered, keep the version they were rendered with.
+pub async fn save_content_snippet(
+    form: web::Form<SnippetFormData>,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<HttpResponse> {
+    let name = form.name.trim();
+    if !is_valid_snippet_name(name) {
+        FlashMessage::error(
+            "The name of a snippet must consist of up to 64 letters, digits, `-` and `_`.",
+        )
+        .send();
+        return Ok(see_other("/admin/snippets"));
+    }
+    if form.text_content.is_empty() || form.html_content.is_empty() {
+        FlashMessage::error("You must set text and html content of the snippet.").send();
+        return Ok(see_other("/admin/snippets"));
+    }
+    let version = save_snippet(&pool, name, &form.text_content, &form.html_content)
+        .await
+        .context("Failed to save snippet.")?;
+    FlashMessage::info(format!(
+        "Version {} of snippet `{}` has been saved.",
+        version, name
+    ))
+    .send();
+    Ok(see_other("/admin/snippets"))
+}
@@ -0,0 +1,111 @@
+//! src/routes/admin/subscriber_export.rs
+
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::web::Bytes;
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use futures_util::stream;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::error::Error;
+
+/// Number of subscribers read from the database per chunk of the export.
+const EXPORT_PAGE_SIZE: i64 = 1000;
+
+struct ExportedSubscriber {
+    id: Uuid,
+    email: String,
+    name: String,
+    status: String,
+    subscribed_at: DateTime<Utc>,
+    list: String,
+}
+
+/// Position of the last exported subscriber; `None` before the first page.
+type ExportCursor = Option<(DateTime<Utc>, Uuid)>;
+
+/// Stream all subscribers as CSV file in order of subscription. Subscribers are read
+/// page by page, therefore the export does not hold all subscribers in memory.
+/// Subscribers of several lists have one row per list.
+#[tracing::instrument(name = "Export subscribers", skip(pool))]
+pub async fn export_subscribers(pool: web::Data<PgPool>) -> HttpResponse {
+    let pool = pool.into_inner();
+    // state is the cursor of the next page and whether the header is written
+    let initial_state = Some((None, false));
+    let pages = stream::unfold(initial_state, move |state| {
+        let pool = pool.clone();
+        async move {
+            let (cursor, header_written) = state?;
+            match export_page(&pool, cursor, !header_written).await {
+                Ok((_, None)) if header_written => None,
+                Ok((chunk, next_cursor)) => Some((Ok(chunk), next_cursor.map(|c| (Some(c), true)))),
+                // stop streaming after an error; the client receives a truncated body
+                Err(e) => Some((Err(e), None)),
+            }
+        }
+    });
+    let file_name = format!("subscribers-{}.csv", Utc::now().format("%Y-%m-%d"));
+    HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(file_name)],
+        })
+        .insert_header(("Cache-Control", "no-store"))
+        .streaming(pages)
+}
+
+/// Render the page of subscribers after `cursor` as CSV and return it with the cursor
+/// of the last subscriber of the page, if the page is not empty.
+async fn export_page(
+    pool: &PgPool,
+    cursor: ExportCursor,
+    with_header: bool,
+) -> Result<(Bytes, ExportCursor), Error> {
+    let (after_subscribed_at, after_id) = cursor.unzip();
+    let subscribers = sqlx::query_as!(
+        ExportedSubscriber,
+        r#"
+        SELECT
+            s.id,
+            s.email,
+            s.name,
+            s.status::text AS "status!",
+            s.subscribed_at,
+            l.name AS list
+        FROM subscriptions s
+        JOIN lists l ON l.list_id = s.list_id
+        WHERE $1::timestamptz IS NULL OR (s.subscribed_at, s.id) > ($1, $2)
+        ORDER BY s.subscribed_at, s.id
+        LIMIT $3
+        "#,
+        after_subscribed_at,
+        after_id,
+        EXPORT_PAGE_SIZE,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to read subscribers to export.")?;
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    if with_header {
+        writer
+            .write_record(["email", "name", "status", "subscribed_at", "list"])
+            .context("Failed to write CSV header.")?;
+    }
+    for subscriber in subscribers.iter() {
+        writer
+            .write_record([
+                subscriber.email.as_str(),
+                subscriber.name.as_str(),
+                subscriber.status.as_str(),
+                &subscriber.subscribed_at.to_rfc3339(),
+                subscriber.list.as_str(),
+            ])
+            .context("Failed to write CSV row.")?;
+    }
+    let chunk = writer.into_inner().context("Failed to finish CSV chunk.")?;
+    let next_cursor = subscribers.last().map(|s| (s.subscribed_at, s.id));
+    Ok((Bytes::from(chunk), next_cursor))
+}
@@ -0,0 +1,324 @@
+//! src/routes/admin/subscriber_import.rs
+
+use std::time::Instant;
+
+use actix_multipart::form::{bytes::Bytes, text::Text, MultipartForm};
+use actix_web::{web, HttpResponse, Responder};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use askama_actix::Template;
+use chrono::Utc;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::bounces::is_suppressed;
+use crate::domain::{Locale, NewSubscriber, SubscriberEmail, SubscriberName, SubscriberToken};
+use crate::email_client::EmailClient;
+use crate::error::Z2PResult;
+use crate::mailing_lists::{get_mailing_lists, parse_list_id, MailingList};
+use crate::metrics::ConfirmationEmailMetrics;
+use crate::routes::{send_confirmation_email, store_token, SubscriptionsStatus};
+use crate::startup::ApplicationBaseUrl;
+use crate::subscriber_events::{record_subscriber_event, SubscriberEventKind};
+use crate::subscriber_milestones::record_reached_milestones;
+use crate::utils::see_other;
+
+/// Limit of uploaded CSV files.
+pub const MAX_IMPORT_FILE_BYTES: usize = 5 * 1024 * 1024;
+
+/// Status of imported subscribers.
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum ImportStatus {
+    /// Subscribers confirmed their subscription at the previous provider.
+    Confirmed,
+    /// Subscribers receive a confirmation email and must confirm again.
+    PendingReconfirmation,
+}
+
+#[derive(MultipartForm)]
+pub struct ImportForm {
+    /// CSV file with header row and columns `email`, `name` and optional `locale`
+    file: Bytes,
+    status: Text<ImportStatus>,
+    /// Mailing list of imported subscribers; empty for the default list
+    list_id: Option<Text<String>>,
+}
+
+#[derive(serde::Deserialize)]
+struct ImportRow {
+    email: String,
+    name: String,
+    #[serde(default)]
+    locale: String,
+}
+
+impl TryFrom<ImportRow> for NewSubscriber {
+    type Error = String;
+
+    fn try_from(value: ImportRow) -> Result<Self, Self::Error> {
+        let name = SubscriberName::parse(value.name).map_err(|e| e.to_string())?;
+        let email = SubscriberEmail::parse(value.email).map_err(|e| e.to_string())?;
+        let locale = if value.locale.trim().is_empty() {
+            None
+        } else {
+            Some(Locale::parse(value.locale).map_err(|e| e.to_string())?)
+        };
+        Ok(Self {
+            email,
+            name,
+            locale,
+        })
+    }
+}
+
+/// Row of the CSV file, which has not been imported.
+struct RowError {
+    /// line in CSV file, the header row is line 1
+    line: u64,
+    email: String,
+    message: String,
+}
+
+struct ImportReport {
+    num_rows: usize,
+    num_imported: usize,
+    errors: Vec<RowError>,
+}
+
+#[derive(Template)]
+#[template(path = "subscriber_import.html")]
+struct SubscriberImportTemplate {
+    flash_messages: Vec<String>,
+    lists: Vec<MailingList>,
+    report: Option<ImportReport>,
+}
+
+pub async fn subscriber_import_form(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<impl Responder> {
+    let flash_messages: Vec<String> = flash_messages
+        .iter()
+        .map(|m| m.content().to_string())
+        .collect();
+    let lists = get_mailing_lists(&pool)
+        .await
+        .context("Failed to read mailing lists.")?;
+    Ok(SubscriberImportTemplate {
+        flash_messages,
+        lists,
+        report: None,
+    })
+}
+
+/// Import subscribers of a CSV file, e.g. an export of another newsletter provider.
+/// Each row is validated and imported on its own; invalid rows and emails, which
+/// are already subscribed to the list, are listed in the returned report.
+#[tracing::instrument(
+    name = "Import subscribers",
+    skip(form, pool, email_client, base_url, confirmation_metrics),
+    fields(status = ?form.status.0)
+)]
+pub async fn import_subscribers(
+    MultipartForm(form): MultipartForm<ImportForm>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    confirmation_metrics: web::Data<ConfirmationEmailMetrics>,
+) -> Z2PResult<HttpResponse> {
+    let list_id = form.list_id.map(|l| l.0).unwrap_or_default();
+    let Some(list_id) = parse_list_id(pool.as_ref(), &list_id)
+        .await
+        .context("Failed to read mailing list.")?
+    else {
+        FlashMessage::error(format!("`{}` is not a known mailing list.", list_id)).send();
+        return Ok(see_other("/admin/subscribers/import"));
+    };
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(form.file.data.as_ref());
+    let headers = reader.headers().cloned().unwrap_or_default();
+    let email_column = headers.iter().position(|header| header == "email");
+    if email_column.is_none() || !headers.iter().any(|header| header == "name") {
+        FlashMessage::error("The CSV file must have a header row with columns `email` and `name`.")
+            .send();
+        return Ok(see_other("/admin/subscribers/import"));
+    }
+
+    let mut report = ImportReport {
+        num_rows: 0,
+        num_imported: 0,
+        errors: Vec::new(),
+    };
+    for record in reader.records() {
+        report.num_rows += 1;
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                report.errors.push(RowError {
+                    line: e.position().map(|p| p.line()).unwrap_or_default(),
+                    email: String::new(),
+                    message: format!("Invalid CSV row: {}", e),
+                });
+                continue;
+            }
+        };
+        let line = record.position().map(|p| p.line()).unwrap_or_default();
+        let email = email_column
+            .and_then(|column| record.get(column))
+            .unwrap_or_default()
+            .to_string();
+        let row: ImportRow = match record.deserialize(Some(&headers)) {
+            Ok(row) => row,
+            Err(e) => {
+                report.errors.push(RowError {
+                    line,
+                    email,
+                    message: format!("Invalid CSV row: {}", e),
+                });
+                continue;
+            }
+        };
+        let new_subscriber: NewSubscriber = match row.try_into() {
+            Ok(new_subscriber) => new_subscriber,
+            Err(message) => {
+                report.errors.push(RowError {
+                    line,
+                    email,
+                    message,
+                });
+                continue;
+            }
+        };
+        if is_suppressed(pool.as_ref(), new_subscriber.email.as_ref())
+            .await
+            .context("Failed to check suppression list.")?
+        {
+            report.errors.push(RowError {
+                line,
+                email,
+                message: "Email is on the suppression list.".to_string(),
+            });
+            continue;
+        }
+        let Some(token) = import_subscriber(&pool, &new_subscriber, list_id, form.status.0).await?
+        else {
+            report.errors.push(RowError {
+                line,
+                email,
+                message: "Email is already subscribed to the mailing list.".to_string(),
+            });
+            continue;
+        };
+        report.num_imported += 1;
+        if form.status.0 == ImportStatus::PendingReconfirmation {
+            let started_at = Instant::now();
+            let result =
+                send_confirmation_email(&email_client, new_subscriber, &base_url.0, &token).await;
+            confirmation_metrics.record(started_at.elapsed(), result.is_ok());
+            if let Err(e) = result {
+                tracing::warn!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Failed to send confirmation email to imported subscriber."
+                );
+                report.errors.push(RowError {
+                    line,
+                    email,
+                    message: "Imported, but failed to send confirmation email.".to_string(),
+                });
+            }
+        }
+    }
+
+    if form.status.0 == ImportStatus::Confirmed && report.num_imported > 0 {
+        // milestones are not essential for the import, therefore only log errors
+        if let Err(e) = record_reached_milestones(&pool, &email_client).await {
+            tracing::warn!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to record subscriber milestones."
+            );
+        }
+    }
+    let lists = get_mailing_lists(&pool)
+        .await
+        .context("Failed to read mailing lists.")?;
+    let body = SubscriberImportTemplate {
+        flash_messages: vec![],
+        lists,
+        report: Some(report),
+    }
+    .render()
+    .context("Failed to render import report.")?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(body))
+}
+
+/// Insert subscriber with token and return the token. Returns `None`, if the email
+/// is already subscribed to the list.
+#[tracing::instrument(name = "Import subscriber", skip(pool, new_subscriber))]
+async fn import_subscriber(
+    pool: &PgPool,
+    new_subscriber: &NewSubscriber,
+    list_id: Uuid,
+    status: ImportStatus,
+) -> Z2PResult<Option<SubscriberToken>> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    let subscriptions_status = match status {
+        ImportStatus::Confirmed => SubscriptionsStatus::Confirmed,
+        ImportStatus::PendingReconfirmation => SubscriptionsStatus::PendingConfirmation,
+    };
+    let Some(subscriber) = sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (id, email, name, subscribed_at, status, locale, list_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (list_id, email) DO NOTHING
+        RETURNING id
+        "#,
+        Uuid::new_v4(),
+        new_subscriber.email.as_ref(),
+        new_subscriber.name.as_ref(),
+        Utc::now(),
+        subscriptions_status as SubscriptionsStatus,
+        new_subscriber.locale.as_ref().map(|l| l.as_ref()),
+        list_id,
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .context("Failed to insert imported subscriber in the database.")?
+    else {
+        return Ok(None);
+    };
+    record_subscriber_event(
+        &mut *transaction,
+        subscriber.id,
+        SubscriberEventKind::Subscribed,
+        None,
+    )
+    .await
+    .context("Failed to record subscribed event.")?;
+    if status == ImportStatus::Confirmed {
+        record_subscriber_event(
+            &mut *transaction,
+            subscriber.id,
+            SubscriberEventKind::Confirmed,
+            None,
+        )
+        .await
+        .context("Failed to record confirmed event.")?;
+    }
+    // the token is also required for the unsubscribe link of confirmed subscribers
+    let subscription_token = SubscriberToken::generate_subscription_token();
+    store_token(&mut transaction, subscriber.id, &subscription_token).await?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to store an imported subscriber.")?;
+    Ok(Some(subscription_token))
+}
@@ -0,0 +1,87 @@
+//! src/routes/admin/subscribers.rs
+
+use actix_web::{web, Responder};
+use anyhow::Context;
+use askama_actix::Template;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::error::{Error, Z2PResult};
+use crate::routes::SubscriptionsStatus;
+use crate::subscriber_events::{get_subscriber_timeline, SubscriberEvent};
+
+struct Subscriber {
+    id: Uuid,
+    email: String,
+    name: String,
+    subscribed_at: DateTime<Utc>,
+    status: Option<SubscriptionsStatus>,
+}
+
+impl Subscriber {
+    fn is_confirmed(&self) -> bool {
+        self.status == Some(SubscriptionsStatus::Confirmed)
+    }
+}
+
+#[derive(Template)]
+#[template(path = "subscribers.html")]
+struct SubscribersTemplate {
+    subscribers: Vec<Subscriber>,
+}
+
+#[derive(Template)]
+#[template(path = "subscriber_details.html")]
+struct SubscriberDetailsTemplate {
+    subscriber_id: Uuid,
+    subscriber: Option<Subscriber>,
+    timeline: Vec<SubscriberEvent>,
+}
+
+pub async fn subscribers(pool: web::Data<PgPool>) -> Z2PResult<impl Responder> {
+    let subscribers = sqlx::query_as!(
+        Subscriber,
+        r#"
+        SELECT id, email, name, subscribed_at, status AS "status: SubscriptionsStatus"
+        FROM subscriptions
+        ORDER BY subscribed_at DESC
+        "#
+    )
+    .fetch_all(pool.as_ref())
+    .await
+    .context("Failed to read subscribers.")?;
+    Ok(SubscribersTemplate { subscribers })
+}
+
+/// Details of a subscriber with their timeline. The timeline of unsubscribed
+/// subscribers is still available.
+pub async fn subscriber_details(
+    subscriber_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<impl Responder> {
+    let subscriber_id = subscriber_id.into_inner();
+    let subscriber = sqlx::query_as!(
+        Subscriber,
+        r#"
+        SELECT id, email, name, subscribed_at, status AS "status: SubscriptionsStatus"
+        FROM subscriptions
+        WHERE id = $1
+        "#,
+        subscriber_id
+    )
+    .fetch_optional(pool.as_ref())
+    .await
+    .context("Failed to read subscriber.")?;
+    let timeline = get_subscriber_timeline(&pool, subscriber_id)
+        .await
+        .context("Failed to read timeline of subscriber.")?;
+    if subscriber.is_none() && timeline.is_empty() {
+        return Err(Error::NotFound);
+    }
+    Ok(SubscriberDetailsTemplate {
+        subscriber_id,
+        subscriber,
+        timeline,
+    })
+}
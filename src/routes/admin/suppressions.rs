@@ -0,0 +1,95 @@
+//! src/routes/admin/suppressions.rs
+
+use actix_web::{web, HttpResponse, Responder};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use askama_actix::Template;
+use sqlx::PgPool;
+
+use crate::bounces::{get_suppressions, remove_suppression, suppress_email, Suppression};
+use crate::domain::SubscriberEmail;
+use crate::error::Z2PResult;
+use crate::utils::see_other;
+
+/// Reason of suppressions, which were added on the admin page.
+const MANUAL_SUPPRESSION_REASON: &str = "manual";
+
+#[derive(Template)]
+#[template(path = "suppressions.html")]
+struct SuppressionsTemplate {
+    flash_messages: Vec<String>,
+    suppressions: Vec<Suppression>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct SuppressionFormData {
+    pub email: String,
+}
+
+pub async fn suppressions(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<impl Responder> {
+    let flash_messages: Vec<String> = flash_messages
+        .iter()
+        .map(|m| m.content().to_string())
+        .collect();
+    let suppressions = get_suppressions(&pool)
+        .await
+        .context("Failed to read suppression list.")?;
+    Ok(SuppressionsTemplate {
+        flash_messages,
+        suppressions,
+    })
+}
+
+#[tracing::instrument(name = "Add email to suppression list", skip_all)]
+pub async fn add_suppression(
+    form: web::Form<SuppressionFormData>,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<HttpResponse> {
+    let email = match SubscriberEmail::parse(form.0.email) {
+        Ok(email) => email,
+        Err(e) => {
+            FlashMessage::error(e.to_string()).send();
+            return Ok(see_other("/admin/suppressions"));
+        }
+    };
+    if suppress_email(pool.as_ref(), email.as_ref(), MANUAL_SUPPRESSION_REASON)
+        .await
+        .context("Failed to add email to suppression list.")?
+    {
+        FlashMessage::info(format!(
+            "`{}` has been added to the suppression list.",
+            email.as_ref()
+        ))
+        .send();
+    } else {
+        FlashMessage::error(format!(
+            "`{}` is on the suppression list already.",
+            email.as_ref()
+        ))
+        .send();
+    }
+    Ok(see_other("/admin/suppressions"))
+}
+
+#[tracing::instrument(name = "Remove email from suppression list", skip_all)]
+pub async fn delete_suppression(
+    form: web::Form<SuppressionFormData>,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<HttpResponse> {
+    if remove_suppression(pool.as_ref(), &form.email)
+        .await
+        .context("Failed to remove email from suppression list.")?
+    {
+        FlashMessage::info(format!(
+            "`{}` has been removed from the suppression list.",
+            form.email
+        ))
+        .send();
+    } else {
+        FlashMessage::error(format!("`{}` is not on the suppression list.", form.email)).send();
+    }
+    Ok(see_other("/admin/suppressions"))
+}
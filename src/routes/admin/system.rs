@@ -0,0 +1,55 @@
+//! src/routes/admin/system.rs
+
+use actix_web::{web, Responder};
+use anyhow::Context;
+use askama_actix::Template;
+use sqlx::PgPool;
+
+use crate::error::Z2PResult;
+use crate::idempotency::{get_stored_keys, StoredKeys, CLEANUP_WORKER_KIND};
+use crate::metrics::{IdempotencyMetrics, IdempotencyStats};
+use crate::startup::{ExternalIdempotencyStore, IdempotencyLifetime};
+use crate::worker_heartbeat::{get_worker_statuses, WorkerStatus};
+
+#[derive(Template)]
+#[template(path = "system.html")]
+struct SystemTemplate {
+    redis_store: bool,
+    lifetime_minutes: u32,
+    idempotency: IdempotencyStats,
+    /// Keys in Postgres; `None` with Redis store, whose keys expire by themselves
+    stored_keys: Option<StoredKeys>,
+    cleanup_workers: Vec<WorkerStatus>,
+}
+
+/// Status of subsystems, which operators tune via configuration.
+pub async fn system_status(
+    pool: web::Data<PgPool>,
+    idempotency_store: web::Data<ExternalIdempotencyStore>,
+    idempotency_lifetime: web::Data<IdempotencyLifetime>,
+    idempotency_metrics: web::Data<IdempotencyMetrics>,
+) -> Z2PResult<impl Responder> {
+    let redis_store = idempotency_store.0.is_some();
+    let stored_keys = if redis_store {
+        None
+    } else {
+        Some(
+            get_stored_keys(&pool)
+                .await
+                .context("Failed to count idempotency keys.")?,
+        )
+    };
+    let cleanup_workers = get_worker_statuses(&pool)
+        .await
+        .context("Failed to read worker heartbeats.")?
+        .into_iter()
+        .filter(|worker| worker.kind == CLEANUP_WORKER_KIND)
+        .collect();
+    Ok(SystemTemplate {
+        redis_store,
+        lifetime_minutes: idempotency_lifetime.0,
+        idempotency: idempotency_metrics.stats(),
+        stored_keys,
+        cleanup_workers,
+    })
+}
@@ -0,0 +1,234 @@
+//! src/routes/admin/users.rs
+
+use actix_web::web::ReqData;
+use actix_web::{web, HttpResponse, Responder};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use askama::Template;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::authentication::{
+    create_user_in_db, delete_password_reset_tokens, store_password_reset_token, UserId,
+};
+use crate::domain::SubscriberEmail;
+use crate::email_client::EmailClient;
+use crate::error::{Error, Z2PResult};
+use crate::policy::UserRole;
+use crate::startup::ApplicationBaseUrl;
+use crate::utils::see_other;
+
+/// Lifetime of the link of an invitation, with which a new user sets the password.
+pub const INVITATION_LIFETIME_HOURS: i64 = 72;
+
+#[derive(askama_actix::Template)]
+#[template(path = "users.html")]
+struct UsersTemplate {
+    flash_messages: Vec<String>,
+    current_user_id: Uuid,
+    users: Vec<AdminUser>,
+}
+
+struct AdminUser {
+    user_id: Uuid,
+    username: String,
+    email: Option<String>,
+    role: UserRole,
+    deactivated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct InviteUserFormData {
+    pub username: String,
+    pub email: String,
+    #[serde(default)]
+    pub role: UserRole,
+}
+
+#[derive(Template)]
+#[template(path = "email_invitation.html")]
+struct EmailHtmlTemplate<'a> {
+    username: &'a str,
+    invitation_link: &'a str,
+    lifetime_hours: i64,
+}
+
+#[derive(Template)]
+#[template(path = "email_invitation.txt")]
+struct EmailTextTemplate<'a> {
+    username: &'a str,
+    invitation_link: &'a str,
+    lifetime_hours: i64,
+}
+
+pub async fn admin_users(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+    user_id: ReqData<UserId>,
+) -> Z2PResult<impl Responder> {
+    let flash_messages: Vec<String> = flash_messages
+        .iter()
+        .map(|m| m.content().to_string())
+        .collect();
+    let users = get_admin_users(&pool)
+        .await
+        .context("Failed to read users.")?;
+    Ok(UsersTemplate {
+        flash_messages,
+        current_user_id: **user_id,
+        users,
+    })
+}
+
+/// Create a user and email an invitation link, with which the user sets the password.
+#[tracing::instrument(
+    name = "Invite admin user",
+    skip(form, pool, email_client, base_url),
+    fields(username=%form.username, role=form.role.as_str())
+)]
+pub async fn invite_user(
+    form: web::Form<InviteUserFormData>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Z2PResult<HttpResponse> {
+    let InviteUserFormData {
+        username,
+        email,
+        role,
+    } = form.into_inner();
+    let username = username.trim();
+    if username.is_empty() {
+        FlashMessage::error("You must set a username for the new user.").send();
+        return Ok(see_other("/admin/users"));
+    }
+    let email = match SubscriberEmail::parse(email) {
+        Ok(email) => email,
+        Err(e) => {
+            FlashMessage::error(e.to_string()).send();
+            return Ok(see_other("/admin/users"));
+        }
+    };
+    let Some(user_id) = create_user_in_db(username, email.as_ref(), role, &pool).await? else {
+        FlashMessage::error(format!("A user named `{}` exists already.", username)).send();
+        return Ok(see_other("/admin/users"));
+    };
+    let lifetime = Duration::hours(INVITATION_LIFETIME_HOURS);
+    let token = store_password_reset_token(&pool, user_id, lifetime)
+        .await
+        .context("Failed to store invitation token.")?;
+    let invitation_link = format!("{}/login/reset?token={}", base_url.0, token);
+    let html_body = EmailHtmlTemplate {
+        username,
+        invitation_link: &invitation_link,
+        lifetime_hours: INVITATION_LIFETIME_HOURS,
+    }
+    .render()
+    .context("Failed to render html body.")?;
+    let plain_body = EmailTextTemplate {
+        username,
+        invitation_link: &invitation_link,
+        lifetime_hours: INVITATION_LIFETIME_HOURS,
+    }
+    .render()
+    .context("Failed to render text body.")?;
+    match email_client
+        .send_email(
+            &email,
+            "You are invited to administrate the newsletter",
+            &html_body,
+            &plain_body,
+        )
+        .await
+    {
+        Ok(()) => FlashMessage::info(format!(
+            "`{}` has been invited via {}.",
+            username,
+            email.as_ref()
+        ))
+        .send(),
+        // the user exists and can request a link with "Forgot your password?"
+        Err(e) => {
+            tracing::error!(error.cause_chain = ?e, "Failed to send invitation email.");
+            FlashMessage::error(format!(
+                "`{}` has been created, but the invitation email could not be sent. \
+                The user can request a link to set the password on the login page.",
+                username
+            ))
+            .send();
+        }
+    }
+    Ok(see_other("/admin/users"))
+}
+
+/// Deactivated users can neither log in nor reset their password. Open sessions
+/// and links to set or reset the password are invalidated.
+#[tracing::instrument(name = "Deactivate admin user", skip(pool, user_id))]
+pub async fn deactivate_user(
+    path: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user_id: ReqData<UserId>,
+) -> Z2PResult<HttpResponse> {
+    let deactivated_user_id = path.into_inner();
+    if deactivated_user_id == **user_id {
+        FlashMessage::error("You can not deactivate your own user.").send();
+        return Ok(see_other("/admin/users"));
+    }
+    let username = sqlx::query!(
+        r#"
+        UPDATE users
+        SET deactivated_at = COALESCE(deactivated_at, now())
+        WHERE user_id = $1
+        RETURNING username
+        "#,
+        deactivated_user_id,
+    )
+    .fetch_optional(pool.as_ref())
+    .await
+    .context("Failed to deactivate user.")?
+    .ok_or(Error::NotFound)?
+    .username;
+    delete_password_reset_tokens(&pool, deactivated_user_id)
+        .await
+        .context("Failed to delete password reset tokens.")?;
+    FlashMessage::info(format!("`{}` has been deactivated.", username)).send();
+    Ok(see_other("/admin/users"))
+}
+
+#[tracing::instrument(name = "Reactivate admin user", skip(pool))]
+pub async fn reactivate_user(
+    path: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<HttpResponse> {
+    let username = sqlx::query!(
+        r#"
+        UPDATE users
+        SET deactivated_at = NULL
+        WHERE user_id = $1
+        RETURNING username
+        "#,
+        path.into_inner(),
+    )
+    .fetch_optional(pool.as_ref())
+    .await
+    .context("Failed to reactivate user.")?
+    .ok_or(Error::NotFound)?
+    .username;
+    FlashMessage::info(format!("`{}` has been reactivated.", username)).send();
+    Ok(see_other("/admin/users"))
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_admin_users(pool: &PgPool) -> Result<Vec<AdminUser>, sqlx::Error> {
+    sqlx::query_as!(
+        AdminUser,
+        r#"
+        SELECT user_id, username, email, role AS "role: UserRole", deactivated_at
+        FROM users
+        ORDER BY deactivated_at IS NOT NULL, username
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,44 @@
+//! src/routes/admin/workers.rs
+
+use actix_web::{web, Responder};
+use anyhow::Context;
+use askama_actix::Template;
+use sqlx::PgPool;
+
+use crate::delivery_attempts::{
+    get_attempts_per_version, get_dead_letters, DeadLetter, VersionAttempts,
+};
+use crate::delivery_queue::PgDeliveryQueue;
+use crate::error::Z2PResult;
+use crate::worker_heartbeat::{get_worker_statuses, WorkerStatus};
+
+#[derive(Template)]
+#[template(path = "workers.html")]
+struct WorkersTemplate {
+    workers: Vec<WorkerStatus>,
+    attempts_per_version: Vec<VersionAttempts>,
+    num_newer_tasks: i64,
+    dead_letters: Vec<DeadLetter>,
+}
+
+pub async fn workers(pool: web::Data<PgPool>) -> Z2PResult<impl Responder> {
+    let workers = get_worker_statuses(&pool)
+        .await
+        .context("Failed to read worker heartbeats.")?;
+    let attempts_per_version = get_attempts_per_version(&pool)
+        .await
+        .context("Failed to read delivery attempts per worker version.")?;
+    let num_newer_tasks = PgDeliveryQueue::new(pool.get_ref().clone())
+        .num_newer_tasks()
+        .await
+        .context("Failed to count delivery tasks of newer versions.")?;
+    let dead_letters = get_dead_letters(&pool)
+        .await
+        .context("Failed to read quarantined delivery tasks.")?;
+    Ok(WorkersTemplate {
+        workers,
+        attempts_per_version,
+        num_newer_tasks,
+        dead_letters,
+    })
+}
@@ -0,0 +1,92 @@
+//! src/routes/api/issues.rs
+
+use crate::error::{Error, Z2PResult};
+use actix_web::http::header::{CacheControl, CacheDirective, ETag, EntityTag, Header, IfNoneMatch};
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Newsletter issue as provided to integrations.
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct IssueDetails {
+    pub issue_id: Uuid,
+    pub title: String,
+    pub published_at: DateTime<Utc>,
+    pub html_content: String,
+    pub text_content: String,
+}
+
+/// Clients may cache issues for 5 minutes and revalidate them with their ETag afterwards.
+const MAX_AGE_SECONDS: u32 = 300;
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/issues/{issue_id}",
+    tag = "api",
+    params(("issue_id" = Uuid, Path, description = "Id of newsletter issue")),
+    responses(
+        (status = 200, description = "Issue found.", body = IssueDetails),
+        (status = 304, description = "Issue did not change since request with given ETag."),
+        (status = 401, description = "Missing or wrong API key."),
+        (status = 404, description = "Issue does not exist."),
+        (status = 429, description = "Rate limit of API key exceeded."),
+    ),
+    security(("api_key" = []))
+)]
+#[tracing::instrument(name = "Get issue details via API", skip(request, pool))]
+pub async fn issue_details(
+    request: HttpRequest,
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<HttpResponse> {
+    let issue = get_issue_details(&pool, issue_id.into_inner())
+        .await?
+        .ok_or(Error::NotFound)?;
+    // published issues are not changed, which makes published_at a sufficient version
+    let etag = EntityTag::new_strong(format!(
+        "{}-{}",
+        issue.issue_id,
+        issue.published_at.timestamp_micros()
+    ));
+    let cache_control = CacheControl(vec![
+        CacheDirective::Private,
+        CacheDirective::MaxAge(MAX_AGE_SECONDS),
+    ]);
+    let not_modified = match IfNoneMatch::parse(&request) {
+        Ok(IfNoneMatch::Any) => true,
+        Ok(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(&etag)),
+        Err(_) => false,
+    };
+    let mut response = if not_modified {
+        HttpResponse::NotModified()
+    } else {
+        HttpResponse::Ok()
+    };
+    response
+        .insert_header(ETag(etag))
+        .insert_header(cache_control);
+    if not_modified {
+        Ok(response.finish())
+    } else {
+        Ok(response.json(issue))
+    }
+}
+
+#[tracing::instrument(name = "Get issue details from database", skip(pool))]
+async fn get_issue_details(pool: &PgPool, issue_id: Uuid) -> Z2PResult<Option<IssueDetails>> {
+    let issue = sqlx::query_as!(
+        IssueDetails,
+        r#"
+        SELECT newsletter_issue_id AS issue_id, title, published_at, html_content, text_content
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        "#,
+        issue_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve issue details.")?;
+    Ok(issue)
+}
@@ -0,0 +1,24 @@
+//! src/routes/api/migrations.rs
+
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+
+use crate::error::Z2PResult;
+use crate::migration_check::check_migrations;
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/migrations",
+    tag = "api",
+    responses(
+        (status = 200, description = "Migrations of binary compared with database schema.", body = MigrationReport),
+        (status = 401, description = "Missing or wrong API key."),
+        (status = 429, description = "Rate limit of API key exceeded."),
+    ),
+    security(("api_key" = []))
+)]
+#[tracing::instrument(name = "Check database migrations via API", skip(pool))]
+pub async fn migration_status(pool: web::Data<PgPool>) -> Z2PResult<HttpResponse> {
+    let report = check_migrations(&pool).await?;
+    Ok(HttpResponse::Ok().json(report))
+}
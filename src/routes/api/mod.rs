@@ -0,0 +1,9 @@
+//! src/routes/api/mod.rs
+
+mod issues;
+mod migrations;
+mod publish;
+
+pub use issues::*;
+pub use migrations::*;
+pub use publish::*;
@@ -0,0 +1,125 @@
+//! src/routes/api/publish.rs
+
+use actix_web::web::ReqData;
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::authentication::ApiClientId;
+use crate::error::{Error, Z2PResult};
+use crate::idempotency::{
+    request_hash, save_response, try_processing, IdempotencyKeyHeader, NextAction,
+};
+use crate::metrics::IdempotencyMetrics;
+use crate::routes::admin::{
+    api_error_message, commit_published_issues, publish_issue_in_transaction,
+    publish_issue_via_api, ApiPublishData, EmailSizeBudget, PublishIssueInput,
+};
+use crate::startup::{
+    ApplicationBaseUrl, ExternalDeliveryQueue, ExternalIdempotencyStore, FrequencyCap,
+    PublishChecklist, UndoWindow,
+};
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct PublishedIssue {
+    pub issue_id: Uuid,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct ApiError {
+    pub error: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/issues",
+    tag = "api",
+    request_body = PublishIssueInput,
+    params(
+        ("Idempotency-Key" = Option<String>, Header, description = "UUID, with which retries of the request return the response of the first request instead of publishing the issue again."),
+    ),
+    responses(
+        (status = 200, description = "Issue published and queued for delivery.", body = PublishedIssue),
+        (status = 400, description = "Invalid issue, e.g. without content, or invalid idempotency key.", body = ApiError),
+        (status = 401, description = "Missing or wrong API key or token."),
+        (status = 422, description = "Idempotency key has been used for another issue."),
+        (status = 429, description = "Rate limit of API key exceeded."),
+    ),
+    security(("api_key" = []))
+)]
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(name = "Publish issue via API", skip_all, fields(title=%issue.title))]
+pub async fn publish_issue(
+    issue: web::Json<PublishIssueInput>,
+    idempotency_key_header: IdempotencyKeyHeader,
+    api_client_id: ReqData<ApiClientId>,
+    pool: web::Data<PgPool>,
+    external_queue: web::Data<ExternalDeliveryQueue>,
+    idempotency_store: web::Data<ExternalIdempotencyStore>,
+    idempotency_metrics: web::Data<IdempotencyMetrics>,
+    frequency_cap: web::Data<FrequencyCap>,
+    publish_checklist: web::Data<PublishChecklist>,
+    undo_window: web::Data<UndoWindow>,
+    email_size_budget: web::Data<EmailSizeBudget>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Z2PResult<HttpResponse> {
+    let data = ApiPublishData {
+        pool: &pool,
+        external_queue: &external_queue,
+        frequency_cap: &frequency_cap,
+        publish_checklist: &publish_checklist,
+        undo_window: &undo_window,
+        email_size_budget: &email_size_budget,
+        base_url: &base_url,
+    };
+    let issue = issue.into_inner();
+    let Some(idempotency_key) = idempotency_key_header.optional() else {
+        return api_response(publish_issue_via_api(&data, issue).await);
+    };
+    // a retried request gets the response of the first request instead of publishing
+    // the issue again
+    let user_id = api_client_id.idempotency_owner();
+    let next_action = try_processing(
+        &pool,
+        idempotency_store.0.as_ref(),
+        &idempotency_key,
+        user_id,
+        &request_hash(&issue)?,
+    )
+    .await?;
+    idempotency_metrics.record(&next_action);
+    let mut transaction = match next_action {
+        NextAction::StartProcessing(t) => t,
+        NextAction::ReturnSavedResponse(saved_response) => return Ok(saved_response),
+        NextAction::RejectReusedKey => return Err(Error::IdempotencyKeyReused),
+    };
+    let issue_id = match publish_issue_in_transaction(&data, issue, &mut transaction).await {
+        Ok(issue_id) => issue_id,
+        Err(e) => return api_response(Err(e)),
+    };
+    let response = HttpResponse::Ok().json(PublishedIssue { issue_id });
+    commit_published_issues(
+        &pool,
+        &external_queue,
+        &[issue_id],
+        save_response(
+            transaction,
+            idempotency_store.0.as_ref(),
+            &idempotency_key,
+            user_id,
+            response,
+        ),
+    )
+    .await
+}
+
+fn api_response(published: Z2PResult<Uuid>) -> Z2PResult<HttpResponse> {
+    match published {
+        Ok(issue_id) => Ok(HttpResponse::Ok().json(PublishedIssue { issue_id })),
+        // API clients get the reason of invalid input instead of a flash message
+        Err(e @ Error::NewsletterError(_)) => Ok(HttpResponse::BadRequest().json(ApiError {
+            error: api_error_message(&e),
+        })),
+        Err(e) => Err(e),
+    }
+}
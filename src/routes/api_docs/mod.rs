@@ -0,0 +1,136 @@
+//! src/routes/api_docs/mod.rs
+
+use actix_web::{HttpResponse, Responder};
+use askama_actix::Template;
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+use crate::migration_check::{MigrationReport, MigrationState, MigrationStatus};
+use crate::routes::{
+    ApiError, BounceNotification, ChecklistItem, DeepHealth, DependencyStatus, FeedbackFormData,
+    FormData, InboundEmail, IssueDetails, NewsletterFormData, PendingDelivery, PublishIssueInput,
+    PublishedIssue, Readiness, ResendFormData, ScimEmail, ScimError, ScimListResponse, ScimMeta,
+    ScimPatchOperation, ScimPatchRequest, ScimRole, ScimUser, ScimUserInput, SkippedDelivery,
+    SubscriberData, SubscriberDataEvent, SubscriberFeedback, SubscriberProfile, Suppression,
+    WorkersHealth,
+};
+use crate::worker_heartbeat::WorkerStatus;
+
+/// OpenAPI specification of all routes, which may be called by integrators.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "zero2prod newsletter"),
+    paths(
+        crate::routes::health_check,
+        crate::routes::worker_health_check,
+        crate::routes::deep_health_check,
+        crate::routes::liveness,
+        crate::routes::readiness,
+        crate::routes::subscribe,
+        crate::routes::confirm,
+        crate::routes::resend_confirmation,
+        crate::routes::unsubscribe,
+        crate::routes::subscriber_data,
+        crate::routes::submit_feedback,
+        crate::routes::publish_newsletter,
+        crate::routes::inbound_email,
+        crate::routes::bounce_notification,
+        crate::routes::publish_issue,
+        crate::routes::issue_details,
+        crate::routes::migration_status,
+        crate::routes::list_scim_users,
+        crate::routes::get_scim_user,
+        crate::routes::create_scim_user,
+        crate::routes::replace_scim_user,
+        crate::routes::patch_scim_user,
+        crate::routes::delete_scim_user,
+    ),
+    components(schemas(
+        FormData,
+        ResendFormData,
+        FeedbackFormData,
+        NewsletterFormData,
+        InboundEmail,
+        BounceNotification,
+        IssueDetails,
+        PublishIssueInput,
+        ChecklistItem,
+        PublishedIssue,
+        ApiError,
+        SubscriberData,
+        SubscriberProfile,
+        SubscriberDataEvent,
+        PendingDelivery,
+        SkippedDelivery,
+        SubscriberFeedback,
+        Suppression,
+        MigrationReport,
+        MigrationStatus,
+        MigrationState,
+        WorkersHealth,
+        WorkerStatus,
+        DeepHealth,
+        DependencyStatus,
+        Readiness,
+        ScimUser,
+        ScimEmail,
+        ScimRole,
+        ScimMeta,
+        ScimUserInput,
+        ScimListResponse,
+        ScimPatchRequest,
+        ScimPatchOperation,
+        ScimError
+    )),
+    modifiers(&SecuritySchemes),
+    tags(
+        (name = "health", description = "Availability of application"),
+        (name = "subscriptions", description = "Subscribe and unsubscribe to newsletter"),
+        (name = "feedback", description = "Reader feedback on newsletter issues"),
+        (name = "newsletters", description = "Publish newsletter issues (admin only)"),
+        (name = "webhooks", description = "Webhooks called by email provider"),
+        (name = "api", description = "Integration API, authenticated with the SCIM token"),
+        (name = "scim", description = "Provisioning of admin users by an identity provider, authenticated with the SCIM token"),
+    )
+)]
+pub struct ApiDoc;
+
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        // admin routes require a session cookie from POST /login
+        components.add_security_scheme(
+            "session_cookie",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new("id"))),
+        );
+        // webhooks require the webhook secret as basic auth password
+        components.add_security_scheme(
+            "webhook_basic_auth",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Basic).build()),
+        );
+        // integration API requires the API key as bearer token
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+        // SCIM requires the SCIM token of the identity provider as bearer token
+        components.add_security_scheme(
+            "scim_token",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+pub async fn openapi_json() -> HttpResponse {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
+
+#[derive(Template)]
+#[template(path = "api_docs.html")]
+struct ApiDocsTemplate {}
+
+pub async fn api_docs() -> impl Responder {
+    ApiDocsTemplate {}
+}
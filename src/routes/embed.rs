@@ -0,0 +1,189 @@
+//! src/routes/embed.rs
+
+use crate::domain::Locale;
+use crate::error::{Error, Z2PResult};
+use crate::locale::LocaleFallbacks;
+use crate::startup::ApplicationBaseUrl;
+use actix_web::http::header::{
+    AcceptLanguage, CacheControl, CacheDirective, Preference, ACCEPT_LANGUAGE, VARY,
+};
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use askama::Template;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Embeds may be cached for 5 minutes by browsers and proxies.
+const MAX_AGE_SECONDS: u32 = 300;
+/// Suggested size of the iframe in oEmbed responses.
+const EMBED_WIDTH: u32 = 600;
+const EMBED_HEIGHT: u32 = 800;
+
+#[derive(serde::Deserialize, Debug)]
+pub struct EmbedQuery {
+    /// `json` returns an oEmbed response instead of the html document.
+    pub format: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "embed_latest.html")]
+struct EmbedLatestTemplate<'a> {
+    title: &'a str,
+    published_at: DateTime<Utc>,
+    content: &'a str,
+    subscribe_link: &'a str,
+}
+
+/// oEmbed response of type `rich`, see https://oembed.com
+#[derive(serde::Serialize)]
+struct OEmbedResponse<'a> {
+    version: &'static str,
+    #[serde(rename = "type")]
+    oembed_type: &'static str,
+    title: &'a str,
+    provider_name: &'static str,
+    provider_url: &'a str,
+    html: String,
+    width: u32,
+    height: u32,
+}
+
+struct LatestIssue {
+    newsletter_issue_id: Uuid,
+    title: String,
+    published_at: DateTime<Utc>,
+    html_content: String,
+}
+
+struct IssueVariant {
+    title: String,
+    html_content: String,
+}
+
+#[tracing::instrument(
+    name = "Embed latest issue",
+    skip(accept_language, pool, base_url, locale_fallbacks)
+)]
+pub async fn embed_latest(
+    query: web::Query<EmbedQuery>,
+    accept_language: Option<web::Header<AcceptLanguage>>,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    locale_fallbacks: web::Data<LocaleFallbacks>,
+) -> Z2PResult<HttpResponse> {
+    let mut issue = get_latest_issue(&pool).await?.ok_or(Error::NotFound)?;
+    let preferred = preferred_locales(accept_language);
+    if !preferred.is_empty() {
+        let variants = get_issue_variants(&pool, issue.newsletter_issue_id).await?;
+        if let Some((_, variant)) = locale_fallbacks.resolve(&preferred, &variants) {
+            issue.title.clone_from(&variant.title);
+            issue.html_content.clone_from(&variant.html_content);
+        }
+    }
+    // caches must not serve a variant to readers of another language
+    let vary = (VARY, ACCEPT_LANGUAGE.as_str());
+    let cache_control = CacheControl(vec![
+        CacheDirective::Public,
+        CacheDirective::MaxAge(MAX_AGE_SECONDS),
+    ]);
+    if query.format.as_deref() == Some("json") {
+        let html = format!(
+            r#"<iframe src="{}/embed/latest" width="{}" height="{}" style="border:none;" title="{}"></iframe>"#,
+            base_url.0,
+            EMBED_WIDTH,
+            EMBED_HEIGHT,
+            htmlescape::encode_attribute(&issue.title)
+        );
+        let oembed = OEmbedResponse {
+            version: "1.0",
+            oembed_type: "rich",
+            title: &issue.title,
+            provider_name: "zero2prod newsletter",
+            provider_url: &base_url.0,
+            html,
+            width: EMBED_WIDTH,
+            height: EMBED_HEIGHT,
+        };
+        return Ok(HttpResponse::Ok()
+            .insert_header(cache_control)
+            .insert_header(vary)
+            .json(oembed));
+    }
+    let subscribe_link = format!("{}/subscriptions", base_url.0);
+    let body = EmbedLatestTemplate {
+        title: &issue.title,
+        published_at: issue.published_at,
+        content: &issue.html_content,
+        subscribe_link: &subscribe_link,
+    }
+    .render()
+    .context("Failed to render embed of latest issue.")?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .insert_header(cache_control)
+        .insert_header(vary)
+        .body(body))
+}
+
+/// Valid locales of the Accept-Language header in order of preference.
+fn preferred_locales(accept_language: Option<web::Header<AcceptLanguage>>) -> Vec<Locale> {
+    accept_language
+        .map(|header| header.into_inner().ranked())
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|preference| match preference {
+            Preference::Specific(tag) => Locale::parse(tag.to_string()).ok(),
+            Preference::Any => None,
+        })
+        .collect()
+}
+
+#[tracing::instrument(name = "Get latest issue from database", skip(pool))]
+async fn get_latest_issue(pool: &PgPool) -> Z2PResult<Option<LatestIssue>> {
+    let issue = sqlx::query_as!(
+        LatestIssue,
+        r#"
+        SELECT newsletter_issue_id, title, published_at, html_content
+        FROM newsletter_issues
+        WHERE COALESCE(scheduled_at, published_at) <= now()
+        ORDER BY published_at DESC
+        LIMIT 1
+        "#,
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve latest issue.")?;
+    Ok(issue)
+}
+
+#[tracing::instrument(name = "Get language variants of issue", skip(pool))]
+async fn get_issue_variants(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+) -> Z2PResult<HashMap<String, IssueVariant>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT locale, title, html_content
+        FROM newsletter_issue_variants
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve language variants of issue.")?;
+    Ok(rows
+        .into_iter()
+        .map(|r| {
+            (
+                r.locale,
+                IssueVariant {
+                    title: r.title,
+                    html_content: r.html_content,
+                },
+            )
+        })
+        .collect())
+}
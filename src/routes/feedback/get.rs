@@ -0,0 +1,43 @@
+//! src/routes/feedback/get.rs
+
+use crate::domain::SubscriberToken;
+use crate::error::{Error, Z2PResult};
+use crate::routes::{get_feedback_issue_title, FeedbackQuery};
+use crate::subscriber_repository::get_subscriber_id_of_known_token;
+use actix_web::{web, Responder};
+use actix_web_flash_messages::IncomingFlashMessages;
+use askama_actix::Template;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(Template)]
+#[template(path = "feedback.html")]
+struct FeedbackTemplate {
+    flash_messages: Vec<String>,
+    title: String,
+    feedback_link: String,
+}
+
+#[tracing::instrument(name = "Show feedback form of issue", skip_all, fields(newsletter_issue_id=%issue_id))]
+pub async fn feedback_form(
+    issue_id: web::Path<Uuid>,
+    query: web::Query<FeedbackQuery>,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Z2PResult<impl Responder> {
+    let issue_id = issue_id.into_inner();
+    let subscriber_token = SubscriberToken::parse(query.0.t)?;
+    get_subscriber_id_of_known_token(pool.as_ref(), &subscriber_token).await?;
+    let title = get_feedback_issue_title(&pool, issue_id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    let flash_messages: Vec<String> = flash_messages
+        .iter()
+        .map(|m| m.content().to_string())
+        .collect();
+    Ok(FeedbackTemplate {
+        flash_messages,
+        title,
+        feedback_link: format!("/feedback/{}?t={}", issue_id, subscriber_token.as_ref()),
+    })
+}
@@ -0,0 +1,7 @@
+//! src/routes/feedback/mod.rs
+
+mod get;
+mod post;
+
+pub use get::feedback_form;
+pub use post::*;
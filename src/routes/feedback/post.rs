@@ -0,0 +1,126 @@
+//! src/routes/feedback/post.rs
+
+use crate::domain::SubscriberToken;
+use crate::error::{Error, Z2PResult};
+use crate::subscriber_repository::get_subscriber_id_of_known_token;
+use crate::utils::see_other;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// The subscription token is passed as short query parameter `t`,
+/// since it is part of every feedback link in newsletter emails.
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FeedbackQuery {
+    pub t: String,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, utoipa::ToSchema)]
+pub struct FeedbackFormData {
+    pub useful: bool,
+    #[serde(default)]
+    pub comment: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/feedback/{issue_id}",
+    tag = "feedback",
+    params(("issue_id" = Uuid, Path, description = "Id of newsletter issue"), FeedbackQuery),
+    request_body(content = FeedbackFormData, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Feedback stored, redirect to feedback form."),
+        (status = 400, description = "Invalid or unknown subscription token."),
+        (status = 404, description = "Issue does not exist or does not collect feedback."),
+    )
+)]
+#[tracing::instrument(
+    name = "Submit feedback of subscriber for issue",
+    skip(query, form, pool),
+    fields(subscriber_id=tracing::field::Empty)
+)]
+pub async fn submit_feedback(
+    issue_id: web::Path<Uuid>,
+    query: web::Query<FeedbackQuery>,
+    form: web::Form<FeedbackFormData>,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<HttpResponse> {
+    let issue_id = issue_id.into_inner();
+    let subscriber_token = SubscriberToken::parse(query.0.t)?;
+    let subscriber_id = get_subscriber_id_of_known_token(pool.as_ref(), &subscriber_token).await?;
+    tracing::Span::current().record("subscriber_id", tracing::field::display(&subscriber_id));
+    get_feedback_issue_title(&pool, issue_id)
+        .await?
+        .ok_or(Error::NotFound)?;
+    let comment = form.0.comment.trim();
+    let comment = if comment.is_empty() {
+        None
+    } else {
+        Some(comment)
+    };
+    store_feedback(&pool, issue_id, subscriber_id, form.0.useful, comment).await?;
+    FlashMessage::info("Thank you for your feedback!").send();
+    Ok(see_other(&format!(
+        "/feedback/{}?t={}",
+        issue_id,
+        subscriber_token.as_ref()
+    )))
+}
+
+/// Returns the title of an issue, if the issue exists and collects feedback.
+#[tracing::instrument(name = "Get title of issue collecting feedback", skip(pool))]
+pub async fn get_feedback_issue_title(pool: &PgPool, issue_id: Uuid) -> Z2PResult<Option<String>> {
+    let result = sqlx::query!(
+        r#"
+        SELECT title
+        FROM newsletter_issues
+        WHERE
+            newsletter_issue_id = $1 AND
+            collect_feedback
+        "#,
+        issue_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to read issue collecting feedback from database.")?;
+    Ok(result.map(|r| r.title))
+}
+
+#[tracing::instrument(name = "Store feedback in the database", skip(pool, comment))]
+async fn store_feedback(
+    pool: &PgPool,
+    issue_id: Uuid,
+    subscriber_id: Uuid,
+    useful: bool,
+    comment: Option<&str>,
+) -> Z2PResult<()> {
+    // a subscriber may change their mind, therefore the latest feedback wins
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_feedback (
+            newsletter_issue_id,
+            subscriber_id,
+            useful,
+            comment,
+            submitted_at
+        )
+        VALUES ($1, $2, $3, $4, now())
+        ON CONFLICT (newsletter_issue_id, subscriber_id) DO UPDATE
+        SET
+            useful = EXCLUDED.useful,
+            comment = EXCLUDED.comment,
+            submitted_at = EXCLUDED.submitted_at
+        "#,
+        issue_id,
+        subscriber_id,
+        useful,
+        comment
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store feedback in the database.")?;
+    Ok(())
+}
@@ -1,7 +1,266 @@
 //! src/routes/health_check.rs
 
-use actix_web::HttpResponse;
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use chrono::Utc;
+use sqlx::PgPool;
+use std::future::Future;
+use std::time::{Duration, Instant};
 
+use crate::delivery_queue::{DeliveryQueue, PgDeliveryQueue};
+use crate::email_client::EmailClient;
+use crate::error::Z2PResult;
+use crate::migration_check::check_migrations;
+use crate::startup::{ExternalDeliveryQueue, SessionStoreClient};
+use crate::worker_heartbeat::{get_worker_statuses, workers_are_healthy, WorkerStatus};
+
+/// Due tasks waiting longer than this indicate, that no worker drains the delivery queue.
+const QUEUE_STALL_MINUTES: i64 = 15;
+/// Readiness fails, if the session store does not answer within this time.
+const SESSION_STORE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[utoipa::path(
+    get,
+    path = "/health_check",
+    tag = "health",
+    responses((status = 200, description = "Application is up."))
+)]
 pub async fn health_check() -> HttpResponse {
     HttpResponse::Ok().finish()
 }
+
+#[utoipa::path(
+    get,
+    path = "/live",
+    tag = "health",
+    responses((status = 200, description = "Process is up; a failing probe calls for a restart."))
+)]
+pub async fn liveness() -> HttpResponse {
+    HttpResponse::Ok().finish()
+}
+
+/// Dependencies, which the instance requires to serve requests.
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct Readiness {
+    pub ready: bool,
+    pub database: DependencyStatus,
+    /// All migrations of the binary are applied and none of a newer release.
+    pub migrations: DependencyStatus,
+    pub session_store: DependencyStatus,
+}
+
+#[utoipa::path(
+    get,
+    path = "/ready",
+    tag = "health",
+    responses(
+        (status = 200, description = "Instance is able to serve requests.", body = Readiness),
+        (status = 503, description = "Instance should not receive traffic yet.", body = Readiness),
+    )
+)]
+#[tracing::instrument(name = "Check readiness", skip(pool, session_store))]
+pub async fn readiness(
+    pool: web::Data<PgPool>,
+    session_store: web::Data<SessionStoreClient>,
+) -> HttpResponse {
+    let database = DependencyStatus::check(check_database(&pool)).await;
+    let migrations = DependencyStatus::check(async {
+        let report = check_migrations(&pool).await?;
+        if !report.compatible {
+            anyhow::bail!(
+                "{} pending migration(s), {} unknown version(s), binary is not compatible \
+                with database schema.",
+                report.num_pending(),
+                report.unknown_versions.len()
+            );
+        }
+        Ok(())
+    })
+    .await;
+    let session_store = DependencyStatus::check(async {
+        let ping = async {
+            let mut connection = session_store.0.get_multiplexed_tokio_connection().await?;
+            ::redis::cmd("PING")
+                .query_async::<_, String>(&mut connection)
+                .await
+        };
+        tokio::time::timeout(SESSION_STORE_TIMEOUT, ping)
+            .await
+            .context("Session store did not answer in time.")?
+            .context("Session store is unavailable.")?;
+        Ok(())
+    })
+    .await;
+    let ready = database.healthy && migrations.healthy && session_store.healthy;
+    let mut response = if ready {
+        HttpResponse::Ok()
+    } else {
+        HttpResponse::ServiceUnavailable()
+    };
+    response.json(Readiness {
+        ready,
+        database,
+        migrations,
+        session_store,
+    })
+}
+
+/// Health of background workers for monitoring.
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct WorkersHealth {
+    pub healthy: bool,
+    pub workers: Vec<WorkerStatus>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/health_check/workers",
+    tag = "health",
+    responses(
+        (status = 200, description = "Each kind of background worker has a live worker.", body = WorkersHealth),
+        (status = 503, description = "All workers of a kind stopped sending heartbeats.", body = WorkersHealth),
+    )
+)]
+#[tracing::instrument(name = "Check health of workers", skip(pool))]
+pub async fn worker_health_check(pool: web::Data<PgPool>) -> Z2PResult<HttpResponse> {
+    let workers = get_worker_statuses(&pool)
+        .await
+        .context("Failed to read worker heartbeats.")?;
+    let healthy = workers_are_healthy(&workers);
+    let mut response = if healthy {
+        HttpResponse::Ok()
+    } else {
+        HttpResponse::ServiceUnavailable()
+    };
+    Ok(response.json(WorkersHealth { healthy, workers }))
+}
+
+/// Health of a single dependency of the application.
+#[derive(Debug, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct DependencyStatus {
+    pub healthy: bool,
+    /// Duration of the check.
+    pub latency_milliseconds: u64,
+    /// Cause, if the dependency is unhealthy.
+    pub detail: Option<String>,
+}
+
+impl DependencyStatus {
+    async fn check<F>(check: F) -> Self
+    where
+        F: Future<Output = Result<(), anyhow::Error>>,
+    {
+        let started_at = Instant::now();
+        let outcome = check.await;
+        let latency_milliseconds = started_at.elapsed().as_millis() as u64;
+        match outcome {
+            Ok(()) => Self {
+                healthy: true,
+                latency_milliseconds,
+                detail: None,
+            },
+            Err(e) => {
+                tracing::warn!(error.cause_chain = ?e, "Health check of dependency failed.");
+                Self {
+                    healthy: false,
+                    latency_milliseconds,
+                    detail: Some(format!("{:#}", e)),
+                }
+            }
+        }
+    }
+}
+
+/// Health of the dependencies of the application for monitoring.
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct DeepHealth {
+    pub healthy: bool,
+    pub database: DependencyStatus,
+    pub delivery_queue: DependencyStatus,
+    /// Only checked on request, since it calls the email provider.
+    pub email_provider: Option<DependencyStatus>,
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeepHealthQuery {
+    /// Check, that the active email provider is reachable.
+    #[serde(default)]
+    ping_email_provider: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/health_check/deep",
+    tag = "health",
+    params(DeepHealthQuery),
+    responses(
+        (status = 200, description = "All checked dependencies are healthy.", body = DeepHealth),
+        (status = 503, description = "At least one dependency is unhealthy.", body = DeepHealth),
+    )
+)]
+#[tracing::instrument(
+    name = "Check health of dependencies",
+    skip(pool, external_queue, email_client, query)
+)]
+pub async fn deep_health_check(
+    pool: web::Data<PgPool>,
+    external_queue: web::Data<ExternalDeliveryQueue>,
+    email_client: web::Data<EmailClient>,
+    query: web::Query<DeepHealthQuery>,
+) -> HttpResponse {
+    let database = DependencyStatus::check(check_database(&pool)).await;
+    let delivery_queue = DependencyStatus::check(async {
+        match external_queue.0 {
+            Some(ref queue) => check_queue_is_drained(queue).await,
+            None => check_queue_is_drained(&PgDeliveryQueue::new(pool.get_ref().clone())).await,
+        }
+    })
+    .await;
+    let email_provider = if query.ping_email_provider {
+        Some(DependencyStatus::check(email_client.probe()).await)
+    } else {
+        None
+    };
+    let healthy = database.healthy
+        && delivery_queue.healthy
+        && email_provider.as_ref().is_none_or(|status| status.healthy);
+    let mut response = if healthy {
+        HttpResponse::Ok()
+    } else {
+        HttpResponse::ServiceUnavailable()
+    };
+    response.json(DeepHealth {
+        healthy,
+        database,
+        delivery_queue,
+        email_provider,
+    })
+}
+
+/// The queue is drained, if no due task waits longer than the stall threshold.
+async fn check_queue_is_drained(queue: &impl DeliveryQueue) -> Result<(), anyhow::Error> {
+    let Some(oldest_due_task) = queue
+        .oldest_due_task()
+        .await
+        .context("Delivery queue is unavailable.")?
+    else {
+        return Ok(());
+    };
+    let waiting = Utc::now() - oldest_due_task;
+    if waiting > chrono::TimeDelta::minutes(QUEUE_STALL_MINUTES) {
+        anyhow::bail!(
+            "Oldest due delivery task waits since {} minutes; workers do not drain the queue.",
+            waiting.num_minutes()
+        );
+    }
+    Ok(())
+}
+
+async fn check_database(pool: &PgPool) -> Result<(), anyhow::Error> {
+    sqlx::query!("SELECT 1 AS one")
+        .fetch_one(pool)
+        .await
+        .context("Database is unavailable.")?;
+    Ok(())
+}
@@ -0,0 +1,108 @@
+//! src/routes/login/forgot.rs
+
+use actix_web::{web, HttpResponse, Responder};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use askama::Template;
+use chrono::Duration;
+use sqlx::PgPool;
+
+use crate::authentication::{store_password_reset_token, PASSWORD_RESET_TOKEN_LIFETIME_MINUTES};
+use crate::domain::SubscriberEmail;
+use crate::email_client::EmailClient;
+use crate::error::Z2PResult;
+use crate::startup::ApplicationBaseUrl;
+use crate::utils::see_other;
+
+#[derive(askama_actix::Template)]
+#[template(path = "forgot_password.html")]
+struct ForgotPasswordTemplate {
+    flash_messages: Vec<String>,
+}
+
+pub async fn forgot_password_form(flash_messages: IncomingFlashMessages) -> impl Responder {
+    let flash_messages: Vec<String> = flash_messages
+        .iter()
+        .map(|m| m.content().to_string())
+        .collect();
+    ForgotPasswordTemplate { flash_messages }
+}
+
+#[derive(serde::Deserialize)]
+pub struct ForgotPasswordFormData {
+    username: String,
+}
+
+#[derive(Template)]
+#[template(path = "email_password_reset.html")]
+struct EmailHtmlTemplate<'a> {
+    username: &'a str,
+    reset_link: &'a str,
+    lifetime_minutes: i64,
+}
+
+#[derive(Template)]
+#[template(path = "email_password_reset.txt")]
+struct EmailTextTemplate<'a> {
+    username: &'a str,
+    reset_link: &'a str,
+    lifetime_minutes: i64,
+}
+
+/// Email a password reset link to the user, if the user has an email address. The
+/// response does not reveal, whether the username exists or has an email address.
+#[tracing::instrument(
+    name = "Request password reset",
+    skip(form, pool, email_client, base_url),
+    fields(username=%form.username)
+)]
+pub async fn forgot_password(
+    form: web::Form<ForgotPasswordFormData>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Z2PResult<HttpResponse> {
+    let user = sqlx::query!(
+        "SELECT user_id, email FROM users WHERE username = $1 AND deactivated_at IS NULL",
+        form.username
+    )
+    .fetch_optional(pool.as_ref())
+    .await
+    .context("Failed to read user of password reset.")?;
+    let recipient = user.and_then(|user| {
+        let email = SubscriberEmail::parse(user.email?).ok()?;
+        Some((user.user_id, email))
+    });
+    if let Some((user_id, email)) = recipient {
+        let lifetime = Duration::minutes(PASSWORD_RESET_TOKEN_LIFETIME_MINUTES);
+        let token = store_password_reset_token(&pool, user_id, lifetime)
+            .await
+            .context("Failed to store password reset token.")?;
+        let reset_link = format!("{}/login/reset?token={}", base_url.0, token);
+        let html_body = EmailHtmlTemplate {
+            username: &form.username,
+            reset_link: &reset_link,
+            lifetime_minutes: PASSWORD_RESET_TOKEN_LIFETIME_MINUTES,
+        }
+        .render()
+        .context("Failed to render html body.")?;
+        let plain_body = EmailTextTemplate {
+            username: &form.username,
+            reset_link: &reset_link,
+            lifetime_minutes: PASSWORD_RESET_TOKEN_LIFETIME_MINUTES,
+        }
+        .render()
+        .context("Failed to render text body.")?;
+        if let Err(e) = email_client
+            .send_email(&email, "Reset your password", &html_body, &plain_body)
+            .await
+        {
+            tracing::error!(error.cause_chain = ?e, "Failed to send password reset email.");
+        }
+    }
+    FlashMessage::info(
+        "If the user has an email address, a link to reset the password has been sent to it.",
+    )
+    .send();
+    Ok(see_other("/login"))
+}
@@ -1,6 +1,7 @@
 //! src/routes/login/get.rs
 
-use actix_web::Responder;
+use crate::authentication::OidcClient;
+use actix_web::{web, Responder};
 use actix_web_flash_messages::IncomingFlashMessages;
 use askama_actix::Template;
 
@@ -8,12 +9,19 @@ use askama_actix::Template;
 #[template(path = "login.html")]
 struct LoginTemplate {
     flash_messages: Vec<String>,
+    oidc_enabled: bool,
 }
 
-pub async fn login_form(flash_messages: IncomingFlashMessages) -> impl Responder {
+pub async fn login_form(
+    flash_messages: IncomingFlashMessages,
+    oidc_client: web::Data<OidcClient>,
+) -> impl Responder {
     let flash_messages: Vec<String> = flash_messages
         .iter()
         .map(|m| m.content().to_string())
         .collect();
-    LoginTemplate { flash_messages }
+    LoginTemplate {
+        flash_messages,
+        oidc_enabled: oidc_client.is_enabled(),
+    }
 }
@@ -1,6 +1,14 @@
 //! src/routes/login/mod.rs
 
+mod forgot;
 mod get;
+mod oidc;
+mod passkey;
 mod post;
+mod reset;
+pub use forgot::{forgot_password, forgot_password_form};
 pub use get::login_form;
+pub use oidc::{oidc_callback, oidc_login};
+pub use passkey::{passkey_login, passkey_login_options};
 pub use post::login;
+pub use reset::{reset_password, reset_password_form};
@@ -0,0 +1,72 @@
+//! src/routes/login/oidc.rs
+
+use crate::authentication::{get_user_of_oidc_identity, start_admin_session, OidcClient};
+use crate::error::{Error, Z2PResult};
+use crate::session_state::TypedSession;
+use crate::utils::see_other;
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use sqlx::PgPool;
+
+#[derive(serde::Deserialize)]
+pub struct CallbackParams {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+/// Redirect to the login of the OpenID Connect provider; 404, if OIDC is disabled.
+#[tracing::instrument(skip_all)]
+pub async fn oidc_login(
+    oidc_client: web::Data<OidcClient>,
+    session: TypedSession,
+) -> Z2PResult<HttpResponse> {
+    if !oidc_client.is_enabled() {
+        return Err(Error::NotFound);
+    }
+    let (url, login) = oidc_client.authorization_request().await.map_err(|e| {
+        tracing::error!(error.cause_chain = ?e, "Failed to start OIDC login.");
+        Error::LoginError
+    })?;
+    session.insert_oidc_login(&login)?;
+    Ok(see_other(url.as_str()))
+}
+
+/// Log in the user of the identity, which the provider authenticated. Identities
+/// without an active local user are rejected like wrong credentials.
+#[tracing::instrument(
+    skip_all,
+    fields(subject=tracing::field::Empty, user_id=tracing::field::Empty)
+)]
+pub async fn oidc_callback(
+    request: HttpRequest,
+    params: web::Query<CallbackParams>,
+    oidc_client: web::Data<OidcClient>,
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+) -> Z2PResult<HttpResponse> {
+    if !oidc_client.is_enabled() {
+        return Err(Error::NotFound);
+    }
+    let login = session.take_oidc_login()?;
+    let (Some(login), Some(code), Some(state)) = (login, &params.code, &params.state) else {
+        tracing::warn!(error = ?params.error, "OIDC callback without pending login or code.");
+        return Err(Error::LoginError);
+    };
+    if !login.matches(state) {
+        tracing::warn!("State of OIDC callback does not match login.");
+        return Err(Error::LoginError);
+    }
+    let identity = oidc_client.exchange_code(code, &login).await.map_err(|e| {
+        tracing::warn!(error.cause_chain = ?e, "Failed to authenticate OIDC identity.");
+        Error::LoginError
+    })?;
+    tracing::Span::current().record("subject", tracing::field::display(&identity.subject));
+    let user_id = get_user_of_oidc_identity(&pool, &identity)
+        .await
+        .context("Failed to get user of OIDC identity.")?
+        .ok_or(Error::LoginError)?;
+    tracing::Span::current().record("user_id", tracing::field::display(&user_id));
+    start_admin_session(&pool, &session, user_id, &request).await?;
+    Ok(see_other("/admin/dashboard"))
+}
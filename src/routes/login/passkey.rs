@@ -0,0 +1,58 @@
+//! src/routes/login/passkey.rs
+
+use crate::authentication::{
+    get_passkey_of_credential, record_passkey_use, start_admin_session, AuthenticationCredential,
+    PasskeyRelyingParty,
+};
+use crate::error::{Error, Z2PResult};
+use crate::session_state::TypedSession;
+use crate::utils::see_other;
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use sqlx::PgPool;
+
+/// Options to log in with a passkey in the browser; the challenge is kept in the session.
+#[tracing::instrument(skip_all)]
+pub async fn passkey_login_options(
+    relying_party: web::Data<PasskeyRelyingParty>,
+    session: TypedSession,
+) -> Z2PResult<HttpResponse> {
+    let (options, challenge) = relying_party.authentication_options();
+    session.insert_passkey_challenge(&challenge)?;
+    Ok(HttpResponse::Ok().json(options))
+}
+
+/// Log in the user of the passkey, which signed the challenge of the session. Unknown
+/// passkeys and invalid signatures are rejected like wrong credentials.
+#[tracing::instrument(skip_all, fields(user_id=tracing::field::Empty))]
+pub async fn passkey_login(
+    request: HttpRequest,
+    credential: web::Json<AuthenticationCredential>,
+    pool: web::Data<PgPool>,
+    relying_party: web::Data<PasskeyRelyingParty>,
+    session: TypedSession,
+) -> Z2PResult<HttpResponse> {
+    let challenge = session.take_passkey_challenge()?.ok_or(Error::LoginError)?;
+    let credential_id = credential.credential_id().map_err(|_| Error::LoginError)?;
+    let passkey = get_passkey_of_credential(&pool, &credential_id)
+        .await
+        .context("Failed to get passkey of credential.")?
+        .ok_or(Error::LoginError)?;
+    let sign_count = relying_party
+        .finish_authentication(
+            &challenge,
+            &credential,
+            &passkey.public_key,
+            passkey.sign_count,
+        )
+        .map_err(|e| {
+            tracing::warn!(error.cause_chain = ?e, "Failed to verify passkey login.");
+            Error::LoginError
+        })?;
+    record_passkey_use(&pool, passkey.passkey_id, sign_count)
+        .await
+        .context("Failed to record use of passkey.")?;
+    tracing::Span::current().record("user_id", tracing::field::display(&passkey.user_id));
+    start_admin_session(&pool, &session, passkey.user_id, &request).await?;
+    Ok(see_other("/admin/dashboard"))
+}
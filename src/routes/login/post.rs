@@ -1,10 +1,15 @@
 //! src/routes/login/post.rs
 
-use crate::authentication::{validate_credentials, Credentials};
+use crate::authentication::{
+    get_login_lockout, record_failed_login, reset_failed_logins, start_admin_session,
+    validate_credentials, Credentials, LoginSubjects, LoginThrottleSettings,
+};
 use crate::error::{Error, Z2PResult};
 use crate::session_state::TypedSession;
 use crate::utils::see_other;
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use chrono::Utc;
 use secrecy::Secret;
 use sqlx::PgPool;
 
@@ -14,27 +19,47 @@ pub struct FormData {
     password: Secret<String>,
 }
 
+/// Usernames and IP addresses are locked out temporarily after too many failed logins.
 #[tracing::instrument(
-    skip(form, pool, session),
+    skip(request, form, pool, throttle, session),
     fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
 )]
 pub async fn login(
+    request: HttpRequest,
     form: web::Form<FormData>,
     pool: web::Data<PgPool>,
+    throttle: web::Data<LoginThrottleSettings>,
     session: TypedSession,
 ) -> Z2PResult<HttpResponse> {
     let credentials = Credentials {
         username: form.0.username,
         password: form.0.password,
     };
-    tracing::Span::current().record("username", &tracing::field::display(&credentials.username));
+    tracing::Span::current().record("username", tracing::field::display(&credentials.username));
+    let ip = throttle.client_ip.client_ip(&request);
+    let subjects = LoginSubjects::new(&credentials.username, ip.as_deref());
+    if let Some(locked_until) = get_login_lockout(&pool, &subjects)
+        .await
+        .context("Failed to check login lockout.")?
+    {
+        let seconds = (locked_until - Utc::now()).num_seconds().max(1);
+        return Err(Error::LoginLockedOut(seconds));
+    }
     // mask CredentialsError with anonymous LoginError to prevent leakage of
     // information about a failed user login.
-    let user_id = validate_credentials(credentials, &pool)
+    let user_id = match validate_credentials(credentials, &pool).await {
+        Ok(user_id) => user_id,
+        Err(_) => {
+            record_failed_login(&pool, &throttle, &subjects)
+                .await
+                .context("Failed to record failed login.")?;
+            return Err(Error::LoginError);
+        }
+    };
+    tracing::Span::current().record("user_id", tracing::field::display(&user_id));
+    reset_failed_logins(&pool, &subjects)
         .await
-        .map_err(|_| Error::LoginError)?;
-    tracing::Span::current().record("user_id", &tracing::field::display(&user_id));
-    session.renew();
-    session.insert_user_id(user_id)?;
+        .context("Failed to reset failed logins.")?;
+    start_admin_session(&pool, &session, user_id, &request).await?;
     Ok(see_other("/admin/dashboard"))
 }
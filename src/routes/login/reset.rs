@@ -0,0 +1,97 @@
+//! src/routes/login/reset.rs
+
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use askama_actix::Template;
+use secrecy::Secret;
+use sqlx::PgPool;
+
+use crate::authentication::{
+    change_password_in_db, check_new_password_properties, delete_password_reset_tokens,
+    get_user_id_of_reset_token, use_password_reset_token, CredentialsError,
+};
+use crate::error::Z2PResult;
+use crate::utils::see_other;
+
+const INVALID_RESET_LINK: &str = "The password reset link is invalid or has expired.";
+
+#[derive(serde::Deserialize)]
+pub struct ResetPasswordQuery {
+    token: String,
+}
+
+#[derive(Template)]
+#[template(path = "reset_password.html")]
+struct ResetPasswordTemplate {
+    flash_messages: Vec<String>,
+    token: String,
+}
+
+pub async fn reset_password_form(
+    query: web::Query<ResetPasswordQuery>,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Z2PResult<HttpResponse> {
+    let user_id = get_user_id_of_reset_token(&pool, &query.token)
+        .await
+        .context("Failed to read password reset token.")?;
+    if user_id.is_none() {
+        FlashMessage::error(INVALID_RESET_LINK).send();
+        return Ok(see_other("/login/forgot"));
+    }
+    let flash_messages: Vec<String> = flash_messages
+        .iter()
+        .map(|m| m.content().to_string())
+        .collect();
+    let body = ResetPasswordTemplate {
+        flash_messages,
+        token: query.0.token,
+    }
+    .render()
+    .context("Failed to render reset password form.")?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(body))
+}
+
+#[derive(serde::Deserialize)]
+pub struct ResetPasswordFormData {
+    token: String,
+    new_password: Secret<String>,
+    new_password_check: Secret<String>,
+}
+
+/// Set a new password with a single-use token of a password reset link.
+#[tracing::instrument(name = "Reset password", skip_all, fields(user_id=tracing::field::Empty))]
+pub async fn reset_password(
+    form: web::Form<ResetPasswordFormData>,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<HttpResponse> {
+    let form = form.into_inner();
+    match check_new_password_properties(&form.new_password, &form.new_password_check) {
+        Ok(()) => {}
+        // invalid input is shown in the reset form, the token stays valid
+        Err(e @ CredentialsError::DifferentNewPasswords)
+        | Err(e @ CredentialsError::InvalidNewPassword) => {
+            FlashMessage::error(e.to_string()).send();
+            return Ok(see_other(&format!("/login/reset?token={}", form.token)));
+        }
+        Err(e) => return Err(e.into()),
+    }
+    let Some(user_id) = use_password_reset_token(&pool, &form.token)
+        .await
+        .context("Failed to use password reset token.")?
+    else {
+        FlashMessage::error(INVALID_RESET_LINK).send();
+        return Ok(see_other("/login/forgot"));
+    };
+    tracing::Span::current().record("user_id", tracing::field::display(&user_id));
+    change_password_in_db(user_id, form.new_password, &pool).await?;
+    delete_password_reset_tokens(&pool, user_id)
+        .await
+        .context("Failed to delete password reset tokens.")?;
+    FlashMessage::info("Your password has been reset. Please log in with your new password.")
+        .send();
+    Ok(see_other("/login"))
+}
@@ -1,12 +1,30 @@
 //! src/routes/mod.rs
 mod admin;
+mod api;
+mod api_docs;
+mod embed;
+mod feedback;
 mod health_check;
 mod home;
 mod login;
+mod open_tracking;
+mod preferences;
+mod scim;
+mod setup;
 mod subscriptions;
+mod webhooks;
 
 pub use admin::*;
+pub use api::*;
+pub use api_docs::*;
+pub use embed::*;
+pub use feedback::*;
 pub use health_check::*;
 pub use home::*;
 pub use login::*;
+pub use open_tracking::*;
+pub use preferences::*;
+pub use scim::*;
+pub use setup::*;
 pub use subscriptions::*;
+pub use webhooks::*;
@@ -0,0 +1,43 @@
+//! src/routes/open_tracking.rs
+
+use crate::domain::SubscriberToken;
+use crate::error::Z2PResult;
+use crate::subscriber_events::record_open;
+use crate::subscriber_repository::get_subscriber_id_of_known_token;
+use actix_web::http::header::{CacheControl, CacheDirective};
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Transparent GIF image of 1x1 pixel.
+const TRACKING_PIXEL: &[u8] = &[
+    0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xff, 0xff, 0xff, 0x21, 0xf9, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x01, 0x00, 0x00, 0x02, 0x01, 0x44, 0x00, 0x3b,
+];
+
+/// The subscription token is passed as short query parameter `t` like in feedback links.
+#[derive(serde::Deserialize)]
+pub struct OpenTrackingQuery {
+    pub t: String,
+}
+
+/// Image of newsletter emails, which records when a subscriber opened the issue.
+/// Opens are used to learn the best send time of subscribers.
+#[tracing::instrument(name = "Track open of issue", skip_all, fields(newsletter_issue_id=%issue_id))]
+pub async fn track_open(
+    issue_id: web::Path<Uuid>,
+    query: web::Query<OpenTrackingQuery>,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<HttpResponse> {
+    let subscriber_token = SubscriberToken::parse(query.into_inner().t)?;
+    let subscriber_id = get_subscriber_id_of_known_token(pool.as_ref(), &subscriber_token).await?;
+    record_open(&pool, subscriber_id, issue_id.into_inner())
+        .await
+        .context("Failed to record open of issue.")?;
+    Ok(HttpResponse::Ok()
+        .content_type("image/gif")
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .body(TRACKING_PIXEL))
+}
@@ -0,0 +1,117 @@
+//! src/routes/preferences/get.rs
+
+use actix_web::{web, Responder};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use askama_actix::Template;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::SubscriberToken;
+use crate::error::Z2PResult;
+use crate::mailing_lists::{get_mailing_lists, MailingList};
+use crate::routes::{PreferencesQuery, SubscriptionsStatus};
+use crate::subscriber_repository::get_subscriber_id_of_known_token;
+
+/// Choices of the maximum number of newsletter emails per week.
+const FREQUENCY_CHOICES: [i32; 4] = [1, 2, 3, 5];
+
+/// Profile and preferences of a subscription, which are managed in the preference center.
+pub(crate) struct SubscriberPreferences {
+    pub(crate) email: String,
+    pub(crate) name: String,
+    pub(crate) locale: Option<String>,
+    pub(crate) status: SubscriptionsStatus,
+    pub(crate) list_id: Uuid,
+    pub(crate) max_emails_per_week: Option<i32>,
+}
+
+#[derive(Template)]
+#[template(path = "preferences.html")]
+struct PreferencesTemplate {
+    flash_messages: Vec<String>,
+    token: String,
+    preferences: SubscriberPreferences,
+    locale: String,
+    is_pending: bool,
+    /// All mailing lists and whether the email is subscribed to them
+    lists: Vec<(MailingList, bool)>,
+    /// Choices of emails per week and whether they are selected
+    frequency_choices: Vec<(i32, bool)>,
+}
+
+#[tracing::instrument(name = "Show preference center", skip_all)]
+pub async fn preferences_form(
+    query: web::Query<PreferencesQuery>,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Z2PResult<impl Responder> {
+    let subscriber_token = SubscriberToken::parse(query.0.t)?;
+    let subscriber_id = get_subscriber_id_of_known_token(pool.as_ref(), &subscriber_token).await?;
+    let preferences = get_subscriber_preferences(&pool, subscriber_id)
+        .await
+        .context("Failed to read preferences of subscriber.")?;
+    let subscribed_lists = get_subscribed_list_ids(&pool, &preferences.email)
+        .await
+        .context("Failed to read mailing lists of subscriber.")?;
+    let lists = get_mailing_lists(&pool)
+        .await
+        .context("Failed to read mailing lists.")?
+        .into_iter()
+        .map(|list| {
+            let is_subscribed = subscribed_lists.contains(&list.list_id);
+            (list, is_subscribed)
+        })
+        .collect();
+    let flash_messages: Vec<String> = flash_messages
+        .iter()
+        .map(|m| m.content().to_string())
+        .collect();
+    let frequency_choices = FREQUENCY_CHOICES
+        .into_iter()
+        .map(|n| (n, preferences.max_emails_per_week == Some(n)))
+        .collect();
+    Ok(PreferencesTemplate {
+        flash_messages,
+        token: subscriber_token.as_ref().to_owned(),
+        locale: preferences.locale.clone().unwrap_or_default(),
+        is_pending: preferences.status == SubscriptionsStatus::PendingConfirmation,
+        preferences,
+        lists,
+        frequency_choices,
+    })
+}
+
+#[tracing::instrument(skip(pool))]
+pub(crate) async fn get_subscriber_preferences(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<SubscriberPreferences, sqlx::Error> {
+    sqlx::query_as!(
+        SubscriberPreferences,
+        r#"
+        SELECT email, name, locale, status AS "status: SubscriptionsStatus", list_id,
+            max_emails_per_week
+        FROM subscriptions
+        WHERE id = $1
+        "#,
+        subscriber_id
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Lists, to which the email is subscribed, confirmed or pending.
+#[tracing::instrument(skip(pool))]
+pub(crate) async fn get_subscribed_list_ids(
+    pool: &PgPool,
+    email: &str,
+) -> Result<Vec<Uuid>, sqlx::Error> {
+    let list_ids = sqlx::query!("SELECT list_id FROM subscriptions WHERE email = $1", email)
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|r| r.list_id)
+        .collect();
+    Ok(list_ids)
+}
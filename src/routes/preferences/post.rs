@@ -0,0 +1,193 @@
+//! src/routes/preferences/post.rs
+
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::{Executor, PgPool};
+use uuid::Uuid;
+
+use crate::domain::{Locale, SubscriberName, SubscriberToken};
+use crate::error::{Error, Z2PResult};
+use crate::issue_delivery_worker::PgTransaction;
+use crate::mailing_lists::get_mailing_lists;
+use crate::routes::{
+    get_subscribed_list_ids, get_subscriber_preferences, remove_subscriber_from_database,
+    store_token, SubscriptionsStatus,
+};
+use crate::subscriber_events::{record_subscriber_event, SubscriberEventKind};
+use crate::subscriber_repository::get_subscriber_id_of_known_token;
+use crate::utils::see_other;
+
+/// The subscription token is passed as short query parameter `t`,
+/// since it is part of every newsletter email.
+#[derive(serde::Deserialize)]
+pub struct PreferencesQuery {
+    pub t: String,
+}
+
+/// Preferences form; `list_id` is repeated for every checked mailing list.
+#[derive(Default)]
+struct PreferencesFormData {
+    name: String,
+    locale: String,
+    max_emails_per_week: String,
+    list_ids: Vec<String>,
+}
+
+impl From<Vec<(String, String)>> for PreferencesFormData {
+    fn from(pairs: Vec<(String, String)>) -> Self {
+        let mut form = Self::default();
+        for (key, value) in pairs {
+            match key.as_str() {
+                "name" => form.name = value,
+                "locale" => form.locale = value,
+                "max_emails_per_week" => form.max_emails_per_week = value,
+                "list_id" => form.list_ids.push(value),
+                _ => {}
+            }
+        }
+        form
+    }
+}
+
+#[tracing::instrument(
+    name = "Save preferences of subscriber",
+    skip_all,
+    fields(subscriber_id = tracing::field::Empty)
+)]
+pub async fn save_preferences(
+    query: web::Query<PreferencesQuery>,
+    form: web::Form<Vec<(String, String)>>,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<HttpResponse> {
+    let subscriber_token = SubscriberToken::parse(query.0.t)?;
+    let subscriber_id = get_subscriber_id_of_known_token(pool.as_ref(), &subscriber_token).await?;
+    tracing::Span::current().record("subscriber_id", tracing::field::display(&subscriber_id));
+    let form = PreferencesFormData::from(form.0);
+    match update_preferences(&pool, subscriber_id, form).await {
+        Ok(()) => FlashMessage::info("Your preferences have been saved.").send(),
+        // invalid input is shown in the preference center
+        Err(Error::SubscriptionError(e)) => FlashMessage::error(e.to_string()).send(),
+        Err(e) => return Err(e),
+    }
+    Ok(see_other(&format!(
+        "/preferences?t={}",
+        subscriber_token.as_ref()
+    )))
+}
+
+/// Update profile and frequency of all subscriptions of the email. Confirmed subscribers
+/// join and leave mailing lists; the list of the token itself is left by unsubscribing.
+async fn update_preferences(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+    form: PreferencesFormData,
+) -> Z2PResult<()> {
+    let name = SubscriberName::parse(form.name)?;
+    let locale = if form.locale.trim().is_empty() {
+        None
+    } else {
+        Some(Locale::parse(form.locale)?)
+    };
+    let max_emails_per_week = form
+        .max_emails_per_week
+        .parse::<i32>()
+        .ok()
+        .filter(|n| *n > 0);
+    let preferences = get_subscriber_preferences(pool, subscriber_id)
+        .await
+        .context("Failed to read preferences of subscriber.")?;
+    let subscribed_lists = get_subscribed_list_ids(pool, &preferences.email)
+        .await
+        .context("Failed to read mailing lists of subscriber.")?;
+    let mut transaction: PgTransaction = pool
+        .begin()
+        .await
+        .context("Failed to create transaction.")?;
+    let query = sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET name = $2, locale = $3, max_emails_per_week = $4
+        WHERE email = $1
+        "#,
+        preferences.email,
+        name.as_ref(),
+        locale.as_ref().map(|l| l.as_ref()),
+        max_emails_per_week,
+    );
+    transaction
+        .execute(query)
+        .await
+        .context("Failed to update preferences of subscriber.")?;
+    let mut left_subscriptions = Vec::new();
+    if preferences.status == SubscriptionsStatus::Confirmed {
+        let selected_lists: Vec<Uuid> = get_mailing_lists(pool)
+            .await
+            .context("Failed to read mailing lists.")?
+            .into_iter()
+            .map(|l| l.list_id)
+            .filter(|list_id| form.list_ids.contains(&list_id.to_string()))
+            .collect();
+        for list_id in selected_lists
+            .iter()
+            .filter(|list_id| !subscribed_lists.contains(list_id))
+        {
+            let new_subscriber_id = Uuid::new_v4();
+            let query = sqlx::query!(
+                r#"
+                INSERT INTO subscriptions
+                    (id, email, name, subscribed_at, status, locale, list_id, max_emails_per_week)
+                SELECT $2, email, name, now(), $3, locale, $4, max_emails_per_week
+                FROM subscriptions
+                WHERE id = $1
+                "#,
+                subscriber_id,
+                new_subscriber_id,
+                SubscriptionsStatus::Confirmed as SubscriptionsStatus,
+                list_id,
+            );
+            transaction
+                .execute(query)
+                .await
+                .context("Failed to join mailing list.")?;
+            store_token(
+                &mut transaction,
+                new_subscriber_id,
+                &SubscriberToken::generate_subscription_token(),
+            )
+            .await?;
+            for kind in [
+                SubscriberEventKind::Subscribed,
+                SubscriberEventKind::Confirmed,
+            ] {
+                record_subscriber_event(&mut *transaction, new_subscriber_id, kind, None)
+                    .await
+                    .context("Failed to record event of joining mailing list.")?;
+            }
+        }
+        left_subscriptions = sqlx::query!(
+            r#"
+            SELECT id
+            FROM subscriptions
+            WHERE email = $1 AND list_id <> $2 AND NOT list_id = ANY($3)
+            "#,
+            preferences.email,
+            preferences.list_id,
+            &selected_lists,
+        )
+        .fetch_all(&mut *transaction)
+        .await
+        .context("Failed to read subscriptions of left mailing lists.")?
+        .into_iter()
+        .map(|r| r.id)
+        .collect();
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit preferences of subscriber.")?;
+    for left_subscriber_id in left_subscriptions {
+        remove_subscriber_from_database(pool, left_subscriber_id).await?;
+    }
+    Ok(())
+}
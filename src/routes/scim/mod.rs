@@ -0,0 +1,5 @@
+//! src/routes/scim/mod.rs
+
+mod users;
+
+pub use users::*;
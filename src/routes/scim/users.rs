@@ -0,0 +1,848 @@
+//! src/routes/scim/users.rs
+
+use actix_web::http::header::LOCATION;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::authentication::{
+    create_user_in_db, delete_password_reset_tokens, revoke_all_admin_sessions, ApiClientId,
+};
+use crate::domain::SubscriberEmail;
+use crate::error::Z2PResult;
+use crate::policy::UserRole;
+use crate::startup::ApplicationBaseUrl;
+use crate::user_audit_log::{record_user_audit_event, UserAuditAction};
+
+const SCIM_CONTENT_TYPE: &str = "application/scim+json";
+const USER_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:User";
+const LIST_RESPONSE_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:ListResponse";
+const ERROR_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:Error";
+
+/// Admin user as SCIM resource.
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimUser {
+    pub schemas: Vec<String>,
+    pub id: Uuid,
+    pub user_name: String,
+    pub active: bool,
+    pub emails: Vec<ScimEmail>,
+    pub roles: Vec<ScimRole>,
+    pub meta: ScimMeta,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct ScimEmail {
+    pub value: String,
+    #[serde(default)]
+    pub primary: bool,
+}
+
+/// Role of the user, either `admin` or `editor`.
+#[derive(Clone, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct ScimRole {
+    pub value: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimMeta {
+    pub resource_type: String,
+    pub location: String,
+}
+
+/// User, which is created or replaced by the identity provider. Attributes, which are
+/// not mapped onto admin users, are ignored. Users without role are editors.
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimUserInput {
+    pub user_name: String,
+    #[serde(default)]
+    pub emails: Vec<ScimEmail>,
+    #[serde(default = "default_active")]
+    pub active: bool,
+    #[serde(default)]
+    pub roles: Vec<ScimRole>,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimListResponse {
+    pub schemas: Vec<String>,
+    pub total_results: usize,
+    pub start_index: usize,
+    pub items_per_page: usize,
+    #[serde(rename = "Resources")]
+    pub resources: Vec<ScimUser>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ScimListQuery {
+    filter: Option<String>,
+}
+
+/// Partial update of a user; only `replace` and `add` operations are supported.
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct ScimPatchRequest {
+    #[serde(rename = "Operations")]
+    pub operations: Vec<ScimPatchOperation>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct ScimPatchOperation {
+    pub op: String,
+    /// Attribute to change; without path, the value is an object of attributes.
+    pub path: Option<String>,
+    #[schema(value_type = Object)]
+    pub value: serde_json::Value,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimError {
+    pub schemas: Vec<String>,
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scim_type: Option<String>,
+    pub detail: String,
+}
+
+fn scim_error(status: StatusCode, scim_type: Option<&str>, detail: &str) -> HttpResponse {
+    HttpResponse::build(status)
+        .content_type(SCIM_CONTENT_TYPE)
+        .json(ScimError {
+            schemas: vec![ERROR_SCHEMA.to_string()],
+            status: status.as_u16().to_string(),
+            scim_type: scim_type.map(String::from),
+            detail: detail.to_string(),
+        })
+}
+
+fn user_not_found() -> HttpResponse {
+    scim_error(StatusCode::NOT_FOUND, None, "The user does not exist.")
+}
+
+fn username_taken(username: &str) -> HttpResponse {
+    scim_error(
+        StatusCode::CONFLICT,
+        Some("uniqueness"),
+        &format!("A user named `{}` exists already.", username),
+    )
+}
+
+struct UserRecord {
+    user_id: Uuid,
+    username: String,
+    email: Option<String>,
+    role: UserRole,
+    deactivated_at: Option<DateTime<Utc>>,
+}
+
+impl UserRecord {
+    fn into_scim_user(self, base_url: &ApplicationBaseUrl) -> ScimUser {
+        ScimUser {
+            schemas: vec![USER_SCHEMA.to_string()],
+            id: self.user_id,
+            user_name: self.username,
+            active: self.deactivated_at.is_none(),
+            emails: self
+                .email
+                .map(|value| {
+                    vec![ScimEmail {
+                        value,
+                        primary: true,
+                    }]
+                })
+                .unwrap_or_default(),
+            roles: vec![ScimRole {
+                value: self.role.as_str().to_string(),
+            }],
+            meta: ScimMeta {
+                resource_type: "User".to_string(),
+                location: format!("{}/scim/v2/Users/{}", base_url.0, self.user_id),
+            },
+        }
+    }
+
+    fn into_input(self) -> ScimUserInput {
+        ScimUserInput {
+            user_name: self.username,
+            emails: self
+                .email
+                .map(|value| {
+                    vec![ScimEmail {
+                        value,
+                        primary: true,
+                    }]
+                })
+                .unwrap_or_default(),
+            active: self.deactivated_at.is_none(),
+            roles: vec![ScimRole {
+                value: self.role.as_str().to_string(),
+            }],
+        }
+    }
+}
+
+/// User input mapped onto an admin user.
+struct ValidUser {
+    username: String,
+    email: Option<SubscriberEmail>,
+    role: UserRole,
+    active: bool,
+}
+
+impl TryFrom<ScimUserInput> for ValidUser {
+    type Error = String;
+
+    fn try_from(input: ScimUserInput) -> Result<Self, Self::Error> {
+        let username = input.user_name.trim().to_string();
+        if username.is_empty() {
+            return Err("The userName must not be empty.".to_string());
+        }
+        // the primary email address is used to link logins via OpenID Connect
+        let email = input
+            .emails
+            .iter()
+            .find(|e| e.primary)
+            .or(input.emails.first())
+            .map(|e| SubscriberEmail::parse(e.value.clone()))
+            .transpose()
+            .map_err(|e| e.to_string())?;
+        let role = match input.roles.first() {
+            Some(role) => [UserRole::Admin, UserRole::Editor]
+                .into_iter()
+                .find(|r| r.as_str() == role.value)
+                .ok_or_else(|| {
+                    format!(
+                        "Unknown role `{}`; roles are `admin` and `editor`.",
+                        role.value
+                    )
+                })?,
+            None => UserRole::Editor,
+        };
+        Ok(Self {
+            username,
+            email,
+            role,
+            active: input.active,
+        })
+    }
+}
+
+enum UpdateOutcome {
+    Updated(UserRecord),
+    NotFound,
+    UsernameTaken,
+}
+
+fn actor(api_client: &ApiClientId) -> String {
+    format!("scim:{}", api_client.0)
+}
+
+#[utoipa::path(
+    get,
+    path = "/scim/v2/Users",
+    tag = "scim",
+    params(("filter" = Option<String>, Query, description = r#"Only `userName eq "<name>"` is supported"#)),
+    responses(
+        (status = 200, description = "All admin users matching the filter.", body = ScimListResponse),
+        (status = 400, description = "Unsupported filter.", body = ScimError),
+        (status = 401, description = "Missing or wrong SCIM token."),
+    ),
+    security(("scim_token" = []))
+)]
+#[tracing::instrument(name = "List users via SCIM", skip(pool, base_url))]
+pub async fn list_scim_users(
+    query: web::Query<ScimListQuery>,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Z2PResult<HttpResponse> {
+    let username = match query.filter.as_deref() {
+        Some(filter) => match parse_username_filter(filter) {
+            Some(username) => Some(username),
+            None => {
+                return Ok(scim_error(
+                    StatusCode::BAD_REQUEST,
+                    Some("invalidFilter"),
+                    r#"Only filters `userName eq "<name>"` are supported."#,
+                ))
+            }
+        },
+        None => None,
+    };
+    let users: Vec<ScimUser> = get_users(&pool, username.as_deref())
+        .await
+        .context("Failed to read users.")?
+        .into_iter()
+        .map(|user| user.into_scim_user(&base_url))
+        .collect();
+    // the few admin users are returned on one page
+    Ok(HttpResponse::Ok()
+        .content_type(SCIM_CONTENT_TYPE)
+        .json(ScimListResponse {
+            schemas: vec![LIST_RESPONSE_SCHEMA.to_string()],
+            total_results: users.len(),
+            start_index: 1,
+            items_per_page: users.len(),
+            resources: users,
+        }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/scim/v2/Users/{user_id}",
+    tag = "scim",
+    params(("user_id" = Uuid, Path, description = "Id of admin user")),
+    responses(
+        (status = 200, description = "User found.", body = ScimUser),
+        (status = 401, description = "Missing or wrong SCIM token."),
+        (status = 404, description = "User does not exist.", body = ScimError),
+    ),
+    security(("scim_token" = []))
+)]
+#[tracing::instrument(name = "Get user via SCIM", skip(pool, base_url))]
+pub async fn get_scim_user(
+    path: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Z2PResult<HttpResponse> {
+    let Some(user) = get_user(&pool, path.into_inner())
+        .await
+        .context("Failed to read user.")?
+    else {
+        return Ok(user_not_found());
+    };
+    Ok(HttpResponse::Ok()
+        .content_type(SCIM_CONTENT_TYPE)
+        .json(user.into_scim_user(&base_url)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/scim/v2/Users",
+    tag = "scim",
+    request_body = ScimUserInput,
+    responses(
+        (status = 201, description = "User provisioned.", body = ScimUser),
+        (status = 400, description = "Invalid user, e.g. without email address.", body = ScimError),
+        (status = 401, description = "Missing or wrong SCIM token."),
+        (status = 409, description = "A user with this userName exists already.", body = ScimError),
+    ),
+    security(("scim_token" = []))
+)]
+#[tracing::instrument(
+    name = "Provision user via SCIM",
+    skip_all,
+    fields(username = %input.user_name)
+)]
+pub async fn create_scim_user(
+    input: web::Json<ScimUserInput>,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    api_client: web::ReqData<ApiClientId>,
+) -> Z2PResult<HttpResponse> {
+    let user = match ValidUser::try_from(input.into_inner()) {
+        Ok(user) => user,
+        Err(e) => {
+            return Ok(scim_error(
+                StatusCode::BAD_REQUEST,
+                Some("invalidValue"),
+                &e,
+            ))
+        }
+    };
+    // provisioned users log in via OpenID Connect, which links them by email address
+    let Some(email) = &user.email else {
+        return Ok(scim_error(
+            StatusCode::BAD_REQUEST,
+            Some("invalidValue"),
+            "The user needs an email address.",
+        ));
+    };
+    let Some(user_id) = create_user_in_db(&user.username, email.as_ref(), user.role, &pool).await?
+    else {
+        return Ok(username_taken(&user.username));
+    };
+    let actor = actor(&api_client);
+    record_user_audit_event(
+        pool.as_ref(),
+        user_id,
+        UserAuditAction::Provisioned,
+        &actor,
+        Some(&format!(
+            "`{}` <{}> as {}",
+            user.username,
+            email.as_ref(),
+            user.role.as_str()
+        )),
+    )
+    .await
+    .context("Failed to record provisioning of user.")?;
+    if !user.active {
+        // users may be provisioned before they are assigned in the identity provider
+        update_user(&pool, user_id, &user, &actor)
+            .await
+            .context("Failed to deactivate provisioned user.")?;
+    }
+    let user = get_user(&pool, user_id)
+        .await
+        .context("Failed to read provisioned user.")?
+        .context("Provisioned user does not exist.")?;
+    let user = user.into_scim_user(&base_url);
+    Ok(HttpResponse::Created()
+        .content_type(SCIM_CONTENT_TYPE)
+        .insert_header((LOCATION, user.meta.location.clone()))
+        .json(user))
+}
+
+#[utoipa::path(
+    put,
+    path = "/scim/v2/Users/{user_id}",
+    tag = "scim",
+    params(("user_id" = Uuid, Path, description = "Id of admin user")),
+    request_body = ScimUserInput,
+    responses(
+        (status = 200, description = "User replaced.", body = ScimUser),
+        (status = 400, description = "Invalid user, e.g. without email address.", body = ScimError),
+        (status = 401, description = "Missing or wrong SCIM token."),
+        (status = 404, description = "User does not exist.", body = ScimError),
+        (status = 409, description = "Another user with this userName exists.", body = ScimError),
+    ),
+    security(("scim_token" = []))
+)]
+#[tracing::instrument(
+    name = "Replace user via SCIM",
+    skip(input, pool, base_url, api_client)
+)]
+pub async fn replace_scim_user(
+    path: web::Path<Uuid>,
+    input: web::Json<ScimUserInput>,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    api_client: web::ReqData<ApiClientId>,
+) -> Z2PResult<HttpResponse> {
+    let user = match ValidUser::try_from(input.into_inner()) {
+        Ok(user) => user,
+        Err(e) => {
+            return Ok(scim_error(
+                StatusCode::BAD_REQUEST,
+                Some("invalidValue"),
+                &e,
+            ))
+        }
+    };
+    updated_user_response(&pool, path.into_inner(), &user, &api_client, &base_url).await
+}
+
+#[utoipa::path(
+    patch,
+    path = "/scim/v2/Users/{user_id}",
+    tag = "scim",
+    params(("user_id" = Uuid, Path, description = "Id of admin user")),
+    request_body = ScimPatchRequest,
+    responses(
+        (status = 200, description = "User updated.", body = ScimUser),
+        (status = 400, description = "Unsupported operation or invalid value.", body = ScimError),
+        (status = 401, description = "Missing or wrong SCIM token."),
+        (status = 404, description = "User does not exist.", body = ScimError),
+        (status = 409, description = "Another user with this userName exists.", body = ScimError),
+    ),
+    security(("scim_token" = []))
+)]
+#[tracing::instrument(name = "Patch user via SCIM", skip(patch, pool, base_url, api_client))]
+pub async fn patch_scim_user(
+    path: web::Path<Uuid>,
+    patch: web::Json<ScimPatchRequest>,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    api_client: web::ReqData<ApiClientId>,
+) -> Z2PResult<HttpResponse> {
+    let user_id = path.into_inner();
+    let Some(user) = get_user(&pool, user_id)
+        .await
+        .context("Failed to read user.")?
+    else {
+        return Ok(user_not_found());
+    };
+    let mut input = user.into_input();
+    let user = match apply_patch(&mut input, &patch.operations)
+        .and_then(|()| ValidUser::try_from(input))
+    {
+        Ok(user) => user,
+        Err(e) => {
+            return Ok(scim_error(
+                StatusCode::BAD_REQUEST,
+                Some("invalidValue"),
+                &e,
+            ))
+        }
+    };
+    updated_user_response(&pool, user_id, &user, &api_client, &base_url).await
+}
+
+#[utoipa::path(
+    delete,
+    path = "/scim/v2/Users/{user_id}",
+    tag = "scim",
+    params(("user_id" = Uuid, Path, description = "Id of admin user")),
+    responses(
+        (status = 204, description = "User deprovisioned, i.e. deactivated."),
+        (status = 401, description = "Missing or wrong SCIM token."),
+        (status = 404, description = "User does not exist.", body = ScimError),
+    ),
+    security(("scim_token" = []))
+)]
+#[tracing::instrument(name = "Deprovision user via SCIM", skip(pool, api_client))]
+pub async fn delete_scim_user(
+    path: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    api_client: web::ReqData<ApiClientId>,
+) -> Z2PResult<HttpResponse> {
+    let user_id = path.into_inner();
+    let Some(user) = get_user(&pool, user_id)
+        .await
+        .context("Failed to read user.")?
+    else {
+        return Ok(user_not_found());
+    };
+    // deprovisioned users are deactivated instead of deleted, so that their audit log
+    // and the content they published keep their author
+    let mut input = user.into_input();
+    input.active = false;
+    let user = ValidUser::try_from(input).map_err(anyhow::Error::msg)?;
+    match update_user(&pool, user_id, &user, &actor(&api_client))
+        .await
+        .context("Failed to deactivate user.")?
+    {
+        UpdateOutcome::Updated(_) => Ok(HttpResponse::NoContent().finish()),
+        UpdateOutcome::NotFound => Ok(user_not_found()),
+        UpdateOutcome::UsernameTaken => Ok(username_taken(&user.username)),
+    }
+}
+
+async fn updated_user_response(
+    pool: &PgPool,
+    user_id: Uuid,
+    user: &ValidUser,
+    api_client: &ApiClientId,
+    base_url: &ApplicationBaseUrl,
+) -> Z2PResult<HttpResponse> {
+    match update_user(pool, user_id, user, &actor(api_client))
+        .await
+        .context("Failed to update user.")?
+    {
+        UpdateOutcome::Updated(user) => Ok(HttpResponse::Ok()
+            .content_type(SCIM_CONTENT_TYPE)
+            .json(user.into_scim_user(base_url))),
+        UpdateOutcome::NotFound => Ok(user_not_found()),
+        UpdateOutcome::UsernameTaken => Ok(username_taken(&user.username)),
+    }
+}
+
+/// Parse filters `userName eq "<name>"`, which identity providers use to look up
+/// existing users before they provision them.
+fn parse_username_filter(filter: &str) -> Option<String> {
+    let mut parts = filter.trim().splitn(3, ' ');
+    let attribute = parts.next()?;
+    let operator = parts.next()?;
+    let value = parts.next()?.trim();
+    if !attribute.eq_ignore_ascii_case("userName") || !operator.eq_ignore_ascii_case("eq") {
+        return None;
+    }
+    value
+        .strip_prefix('"')?
+        .strip_suffix('"')
+        .map(|username| username.to_string())
+}
+
+/// Apply `replace` and `add` operations on the attributes of the user.
+fn apply_patch(input: &mut ScimUserInput, operations: &[ScimPatchOperation]) -> Result<(), String> {
+    for operation in operations {
+        if !["replace", "add"]
+            .iter()
+            .any(|op| operation.op.eq_ignore_ascii_case(op))
+        {
+            return Err(format!(
+                "The operation `{}` is not supported; use `replace`.",
+                operation.op
+            ));
+        }
+        match (&operation.path, &operation.value) {
+            (Some(path), value) => set_attribute(input, path, value)?,
+            (None, serde_json::Value::Object(attributes)) => {
+                for (attribute, value) in attributes {
+                    set_attribute(input, attribute, value)?;
+                }
+            }
+            (None, _) => return Err("Operations without path need an object as value.".to_string()),
+        }
+    }
+    Ok(())
+}
+
+fn set_attribute(
+    input: &mut ScimUserInput,
+    attribute: &str,
+    value: &serde_json::Value,
+) -> Result<(), String> {
+    let invalid_value = |e: serde_json::Error| format!("Invalid value of `{}`: {}", attribute, e);
+    match attribute.to_ascii_lowercase().as_str() {
+        "active" => {
+            input.active = match value {
+                serde_json::Value::Bool(active) => *active,
+                // some identity providers send booleans as strings
+                serde_json::Value::String(active) if active.eq_ignore_ascii_case("true") => true,
+                serde_json::Value::String(active) if active.eq_ignore_ascii_case("false") => false,
+                _ => {
+                    return Err(format!(
+                        "Invalid value of `{}`: expected boolean",
+                        attribute
+                    ))
+                }
+            }
+        }
+        "username" => {
+            input.user_name = serde_json::from_value(value.clone()).map_err(invalid_value)?
+        }
+        "emails" => input.emails = serde_json::from_value(value.clone()).map_err(invalid_value)?,
+        "roles" => input.roles = serde_json::from_value(value.clone()).map_err(invalid_value)?,
+        // like with PUT, attributes, which are not mapped onto admin users, are ignored
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Update the user and log the changes in the audit log. Deactivated users are logged
+/// out and their links to reset the password are invalidated.
+#[tracing::instrument(name = "Update user from SCIM", skip(pool, user))]
+async fn update_user(
+    pool: &PgPool,
+    user_id: Uuid,
+    user: &ValidUser,
+    actor: &str,
+) -> Result<UpdateOutcome, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let Some(current) = sqlx::query_as!(
+        UserRecord,
+        r#"
+        SELECT user_id, username, email, role AS "role: UserRole", deactivated_at
+        FROM users
+        WHERE user_id = $1
+        FOR UPDATE
+        "#,
+        user_id,
+    )
+    .fetch_optional(&mut *transaction)
+    .await?
+    else {
+        return Ok(UpdateOutcome::NotFound);
+    };
+    let username_taken = sqlx::query!(
+        "SELECT user_id FROM users WHERE username = $1 AND user_id <> $2",
+        user.username,
+        user_id,
+    )
+    .fetch_optional(&mut *transaction)
+    .await?
+    .is_some();
+    if username_taken {
+        return Ok(UpdateOutcome::UsernameTaken);
+    }
+    let updated = sqlx::query_as!(
+        UserRecord,
+        r#"
+        UPDATE users
+        SET
+            username = $2,
+            email = $3,
+            role = $4,
+            deactivated_at = CASE WHEN $5 THEN NULL ELSE COALESCE(deactivated_at, now()) END
+        WHERE user_id = $1
+        RETURNING user_id, username, email, role AS "role: UserRole", deactivated_at
+        "#,
+        user_id,
+        user.username,
+        user.email.as_ref().map(|email| email.as_ref()),
+        user.role as UserRole,
+        user.active,
+    )
+    .fetch_one(&mut *transaction)
+    .await?;
+    let mut changes = Vec::new();
+    if current.username != updated.username {
+        changes.push(format!(
+            "userName `{}` -> `{}`",
+            current.username, updated.username
+        ));
+    }
+    if current.email != updated.email {
+        changes.push(format!(
+            "email {} -> {}",
+            current.email.as_deref().unwrap_or("none"),
+            updated.email.as_deref().unwrap_or("none")
+        ));
+    }
+    if current.role != updated.role {
+        changes.push(format!(
+            "role {} -> {}",
+            current.role.as_str(),
+            updated.role.as_str()
+        ));
+    }
+    if !changes.is_empty() {
+        record_user_audit_event(
+            &mut *transaction,
+            user_id,
+            UserAuditAction::Updated,
+            actor,
+            Some(&changes.join(", ")),
+        )
+        .await?;
+    }
+    let was_active = current.deactivated_at.is_none();
+    if was_active != user.active {
+        let action = if user.active {
+            UserAuditAction::Reactivated
+        } else {
+            UserAuditAction::Deactivated
+        };
+        record_user_audit_event(&mut *transaction, user_id, action, actor, None).await?;
+    }
+    transaction.commit().await?;
+    if was_active && !user.active {
+        delete_password_reset_tokens(pool, user_id).await?;
+        revoke_all_admin_sessions(pool, user_id).await?;
+    }
+    Ok(UpdateOutcome::Updated(updated))
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_user(pool: &PgPool, user_id: Uuid) -> Result<Option<UserRecord>, sqlx::Error> {
+    sqlx::query_as!(
+        UserRecord,
+        r#"
+        SELECT user_id, username, email, role AS "role: UserRole", deactivated_at
+        FROM users
+        WHERE user_id = $1
+        "#,
+        user_id,
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_users(pool: &PgPool, username: Option<&str>) -> Result<Vec<UserRecord>, sqlx::Error> {
+    sqlx::query_as!(
+        UserRecord,
+        r#"
+        SELECT user_id, username, email, role AS "role: UserRole", deactivated_at
+        FROM users
+        WHERE $1::TEXT IS NULL OR username = $1
+        ORDER BY username
+        "#,
+        username,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn input() -> ScimUserInput {
+        ScimUserInput {
+            user_name: "ursula".to_string(),
+            emails: vec![ScimEmail {
+                value: "ursula@example.com".to_string(),
+                primary: true,
+            }],
+            active: true,
+            roles: vec![],
+        }
+    }
+
+    fn operation(op: &str, path: Option<&str>, value: serde_json::Value) -> ScimPatchOperation {
+        ScimPatchOperation {
+            op: op.to_string(),
+            path: path.map(String::from),
+            value,
+        }
+    }
+
+    #[test]
+    fn only_username_equality_filters_are_supported() {
+        assert_eq!(
+            parse_username_filter(r#"userName eq "ursula""#).as_deref(),
+            Some("ursula")
+        );
+        assert_eq!(
+            parse_username_filter(r#"username EQ "le guin""#).as_deref(),
+            Some("le guin")
+        );
+        assert_eq!(parse_username_filter(r#"userName sw "u""#), None);
+        assert_eq!(parse_username_filter(r#"emails eq "u@example.com""#), None);
+        assert_eq!(parse_username_filter("userName eq ursula"), None);
+    }
+
+    #[test]
+    fn patch_replaces_attributes_with_and_without_path() {
+        let mut user = input();
+        let operations = [
+            operation("replace", Some("active"), json!(false)),
+            operation(
+                "Replace",
+                None,
+                json!({"roles": [{"value": "admin"}], "name": {}}),
+            ),
+        ];
+
+        apply_patch(&mut user, &operations).unwrap();
+
+        assert!(!user.active);
+        let user = ValidUser::try_from(user).unwrap();
+        assert_eq!(user.role, UserRole::Admin);
+    }
+
+    #[test]
+    fn patch_accepts_booleans_as_strings() {
+        let mut user = input();
+        apply_patch(
+            &mut user,
+            &[operation("replace", Some("active"), json!("False"))],
+        )
+        .unwrap();
+        assert!(!user.active);
+    }
+
+    #[test]
+    fn patch_rejects_remove_operations() {
+        let mut user = input();
+        assert!(apply_patch(
+            &mut user,
+            &[operation("remove", Some("emails"), json!(null))]
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn users_need_a_known_role() {
+        let mut user = input();
+        user.roles = vec![ScimRole {
+            value: "owner".to_string(),
+        }];
+        assert!(ValidUser::try_from(user).is_err());
+        assert_eq!(ValidUser::try_from(input()).unwrap().role, UserRole::Editor);
+    }
+}
@@ -0,0 +1,227 @@
+//! src/routes/setup.rs
+
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use askama::Template;
+use reqwest::Url;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+
+use crate::authentication::{check_new_password_properties, secrets_match};
+use crate::domain::SubscriberEmail;
+use crate::email_client::EmailClient;
+use crate::error::{Error, Z2PResult};
+use crate::instance_setup::{complete_setup, needs_setup, InitialAdmin, InstanceSettings};
+use crate::startup::{ApplicationBaseUrl, SetupToken};
+use crate::utils::see_other;
+
+const SETUP_COMPLETED: &str = "The initial setup has been completed. Please log in.";
+
+#[derive(askama_actix::Template)]
+#[template(path = "setup.html")]
+struct SetupTemplate {
+    flash_messages: Vec<String>,
+    base_url: String,
+    token: String,
+}
+
+#[derive(Template)]
+#[template(path = "email_setup_test.html")]
+struct EmailHtmlTemplate<'a> {
+    username: &'a str,
+    base_url: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "email_setup_test.txt")]
+struct EmailTextTemplate<'a> {
+    username: &'a str,
+    base_url: &'a str,
+}
+
+#[derive(serde::Deserialize)]
+pub struct SetupTokenQuery {
+    #[serde(default)]
+    token: Option<Secret<String>>,
+}
+
+/// The setup token from the startup log or the configuration proves, that the visitor
+/// operates the instance.
+fn check_setup_token(token: Option<&Secret<String>>, setup_token: &SetupToken) -> Z2PResult<()> {
+    match token {
+        Some(token) if secrets_match(token, &setup_token.0) => Ok(()),
+        _ => {
+            tracing::warn!("Rejected initial setup without valid setup token.");
+            Err(Error::Forbidden)
+        }
+    }
+}
+
+/// One-time setup of a new instance, which is served as long as no user exists.
+pub async fn setup_form(
+    query: web::Query<SetupTokenQuery>,
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    setup_token: web::Data<SetupToken>,
+) -> Z2PResult<HttpResponse> {
+    if !needs_setup(&pool)
+        .await
+        .context("Failed to check for initial setup.")?
+    {
+        return Ok(see_other("/login"));
+    }
+    let token = query.into_inner().token;
+    check_setup_token(token.as_ref(), &setup_token)?;
+    let flash_messages: Vec<String> = flash_messages
+        .iter()
+        .map(|m| m.content().to_string())
+        .collect();
+    let body = SetupTemplate {
+        flash_messages,
+        base_url: base_url.0.clone(),
+        token: token.map(|t| t.expose_secret().clone()).unwrap_or_default(),
+    }
+    .render()
+    .context("Failed to render setup form.")?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(body))
+}
+
+#[derive(serde::Deserialize)]
+pub struct SetupFormData {
+    username: String,
+    email: String,
+    password: Secret<String>,
+    password_check: Secret<String>,
+    base_url: String,
+    sender_email: String,
+    #[serde(default)]
+    token: Option<Secret<String>>,
+}
+
+/// Create the initial admin, store base url and sender of the instance and send a
+/// test email from the new sender to the admin. Base url and sender replace the
+/// configured ones with the next start of the application.
+#[tracing::instrument(
+    name = "Initial setup",
+    skip(form, pool, email_client, setup_token),
+    fields(username=%form.username)
+)]
+pub async fn setup(
+    form: web::Form<SetupFormData>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    setup_token: web::Data<SetupToken>,
+) -> Z2PResult<HttpResponse> {
+    if !needs_setup(&pool)
+        .await
+        .context("Failed to check for initial setup.")?
+    {
+        FlashMessage::info(SETUP_COMPLETED).send();
+        return Ok(see_other("/login"));
+    }
+    let form = form.into_inner();
+    check_setup_token(form.token.as_ref(), &setup_token)?;
+    let token = form.token.clone();
+    let (admin, admin_email, settings, sender) = match parse_setup_form(form) {
+        Ok(parsed) => parsed,
+        Err(message) => {
+            FlashMessage::error(message).send();
+            return Ok(see_other(&setup_location(token.as_ref())));
+        }
+    };
+    let username = admin.username.clone();
+    if complete_setup(&pool, admin, &settings).await?.is_none() {
+        FlashMessage::info(SETUP_COMPLETED).send();
+        return Ok(see_other("/login"));
+    }
+    let html_body = EmailHtmlTemplate {
+        username: &username,
+        base_url: &settings.base_url,
+    }
+    .render()
+    .context("Failed to render html body.")?;
+    let plain_body = EmailTextTemplate {
+        username: &username,
+        base_url: &settings.base_url,
+    }
+    .render()
+    .context("Failed to render text body.")?;
+    match email_client
+        .send_email_from(
+            &sender,
+            &admin_email,
+            "Your newsletter is set up",
+            &html_body,
+            &plain_body,
+        )
+        .await
+    {
+        Ok(()) => FlashMessage::info(format!(
+            "The initial setup has been completed and a test email has been sent to {}. \
+            Base URL and sender apply after the next restart. Please log in.",
+            admin_email.as_ref()
+        ))
+        .send(),
+        Err(e) => {
+            tracing::error!(error.cause_chain = ?e, "Failed to send test email of setup.");
+            FlashMessage::error(format!(
+                "The initial setup has been completed, but the test email from {} could \
+                not be sent. Please check the configuration of the email provider. \
+                Base URL and sender apply after the next restart.",
+                sender.as_ref()
+            ))
+            .send();
+        }
+    }
+    Ok(see_other("/login"))
+}
+
+/// Setup form, which keeps the setup token of the visitor.
+fn setup_location(token: Option<&Secret<String>>) -> String {
+    let token = token
+        .map(|t| t.expose_secret().as_str())
+        .unwrap_or_default();
+    format!("/setup?token={}", urlencoding::encode(token))
+}
+
+/// Validate form; invalid input is reported with a message for the setup form.
+fn parse_setup_form(
+    form: SetupFormData,
+) -> Result<
+    (
+        InitialAdmin,
+        SubscriberEmail,
+        InstanceSettings,
+        SubscriberEmail,
+    ),
+    String,
+> {
+    let username = form.username.trim();
+    if username.is_empty() {
+        return Err("You must set a username for the admin.".to_string());
+    }
+    let admin_email = SubscriberEmail::parse(form.email.trim().to_string())
+        .map_err(|_| format!("`{}` is not a valid email of the admin.", form.email))?;
+    check_new_password_properties(&form.password, &form.password_check)
+        .map_err(|e| e.to_string())?;
+    let base_url = form.base_url.trim().trim_end_matches('/');
+    if !Url::parse(base_url).is_ok_and(|url| ["http", "https"].contains(&url.scheme())) {
+        return Err("The base URL must be an http or https URL.".to_string());
+    }
+    let sender = SubscriberEmail::parse(form.sender_email.trim().to_string())
+        .map_err(|_| format!("`{}` is not a valid sender email.", form.sender_email))?;
+    let settings = InstanceSettings {
+        base_url: base_url.to_string(),
+        sender_email: sender.as_ref().to_string(),
+    };
+    let admin = InitialAdmin {
+        username: username.to_string(),
+        email: admin_email.as_ref().to_string(),
+        password: form.password,
+    };
+    Ok((admin, admin_email, settings, sender))
+}
@@ -1,8 +1,13 @@
 //! src/routes/subscriptions_confirm.rs
 
-use crate::domain::{SubscriberEmail, SubscriberName, SubscriberToken, ValidationError};
+use crate::domain::{SubscriberToken, ValidationError};
+use crate::email_client::EmailClient;
 use crate::error::Z2PResult;
-use crate::routes::get_status_from_subscriber_id;
+use crate::startup::{ExternalDeliveryQueue, FrequencyCap};
+use crate::subscriber_events::{record_subscriber_event, SubscriberEventKind};
+use crate::subscriber_milestones::record_reached_milestones;
+use crate::subscriber_repository::{get_subscriber_id_of_known_token, SubscriberRepository};
+use crate::welcome_issue::{enqueue_welcome_issue, WelcomeIssue};
 use actix_web::{web, Responder};
 use anyhow::Context;
 use askama_actix::Template;
@@ -10,7 +15,17 @@ use chrono::{DateTime, Utc};
 use sqlx::PgPool;
 use uuid::Uuid;
 
-#[derive(serde::Serialize, serde::Deserialize, Debug, sqlx::Type, PartialEq, Eq)]
+#[derive(
+    serde::Serialize,
+    serde::Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    sqlx::Type,
+    async_graphql::Enum,
+    PartialEq,
+    Eq,
+)]
 #[sqlx(type_name = "subscriptions_status", rename_all = "snake_case")]
 pub enum SubscriptionsStatus {
     PendingConfirmation,
@@ -24,38 +39,106 @@ struct SubscriptionsTokenTemplate {
     name: String,
     email: String,
     subscribed_at: DateTime<Utc>,
+    token: String,
 }
 
-#[tracing::instrument(name = "Confirm a pending subscriber", skip(subscriber_token, pool))]
+#[utoipa::path(
+    get,
+    path = "/subscriptions/confirm",
+    tag = "subscriptions",
+    params(SubscriberToken),
+    responses(
+        (status = 200, description = "Subscription confirmed.", content_type = "text/html"),
+        (status = 400, description = "Invalid or unknown subscription token."),
+        (status = 303, description = "Expired confirmation link, redirect to /subscriptions/token."),
+    )
+)]
+#[tracing::instrument(
+    name = "Confirm a pending subscriber",
+    skip(
+        subscriber_token,
+        pool,
+        email_client,
+        external_queue,
+        welcome_issue,
+        frequency_cap
+    )
+)]
 pub async fn confirm(
     subscriber_token: web::Query<SubscriberToken>,
     pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    external_queue: web::Data<ExternalDeliveryQueue>,
+    welcome_issue: web::Data<WelcomeIssue>,
+    frequency_cap: web::Data<FrequencyCap>,
 ) -> Z2PResult<impl Responder> {
     subscriber_token.is_valid()?;
-    let id = get_subscriber_id_from_token(&pool, &subscriber_token).await?;
-    match id {
-        // Non-existing token!
-        None => Err(ValidationError::InvalidToken(
+    let subscriber_id = get_subscriber_id_of_known_token(pool.as_ref(), &subscriber_token).await?;
+    if pool.status_from_subscriber_id(subscriber_id).await?
+        == SubscriptionsStatus::PendingConfirmation
+        && is_token_expired(&pool, &subscriber_token).await?
+    {
+        Err(ValidationError::ExpiredToken(
             subscriber_token.as_ref().to_owned(),
-        ))?,
-        Some(subscriber_id) => {
-            let new_subscription = confirm_subscriber(&pool, subscriber_id).await?;
-            let (name, email, _, subscribed_at) =
-                get_subscriber_from_subscriber_id(&pool, subscriber_id).await?;
-            Ok(SubscriptionsTokenTemplate {
-                new_subscription,
-                name: name.as_ref().to_owned(),
-                email: email.as_ref().to_owned(),
-                subscribed_at,
-            })
+        ))?;
+    }
+    let new_subscription = confirm_subscriber(&pool, subscriber_id).await?;
+    if new_subscription {
+        // milestones are not essential for confirmation, therefore only log errors
+        if let Err(e) = record_reached_milestones(&pool, &email_client).await {
+            tracing::warn!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to record subscriber milestones."
+            );
+        }
+        // the welcome issue is a courtesy, therefore only log errors as well
+        if let Err(e) = enqueue_welcome_issue(
+            &pool,
+            &external_queue,
+            &frequency_cap,
+            &welcome_issue,
+            subscriber_id,
+        )
+        .await
+        {
+            tracing::warn!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to enqueue welcome issue."
+            );
         }
     }
+    let subscriber = pool.subscriber_from_subscriber_id(subscriber_id).await?;
+    Ok(SubscriptionsTokenTemplate {
+        new_subscription,
+        name: subscriber.name.as_ref().to_owned(),
+        email: subscriber.email.as_ref().to_owned(),
+        subscribed_at: subscriber.subscribed_at,
+        token: subscriber.token.as_ref().to_owned(),
+    })
+}
+
+#[tracing::instrument(name = "Check expiry of subscription token", skip_all)]
+async fn is_token_expired(pool: &PgPool, subscriber_token: &SubscriberToken) -> Z2PResult<bool> {
+    let result = sqlx::query!(
+        r#"
+        SELECT expires_at < now() AS "expired!"
+        FROM subscription_tokens
+        WHERE subscription_token = $1
+        "#,
+        subscriber_token.as_ref(),
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to read expiry of subscription token.")?;
+    Ok(result.expired)
 }
 
 #[tracing::instrument(name = "Mark subscriber as confirmed", skip(subscriber_id, pool))]
 async fn confirm_subscriber(pool: &PgPool, subscriber_id: Uuid) -> Z2PResult<bool> {
     // check status of entry with subscriber_id
-    match get_status_from_subscriber_id(pool, subscriber_id).await? {
+    match pool.status_from_subscriber_id(subscriber_id).await? {
         SubscriptionsStatus::PendingConfirmation => {
             // Update status to confirmed
             sqlx::query!(
@@ -68,62 +151,12 @@ async fn confirm_subscriber(pool: &PgPool, subscriber_id: Uuid) -> Z2PResult<boo
             .context(
                 "Failed to update status of subscriber_id for confirmation of subscription.",
             )?;
+            record_subscriber_event(pool, subscriber_id, SubscriberEventKind::Confirmed, None)
+                .await
+                .context("Failed to record confirmed event.")?;
             Ok(true)
         }
         // subscription is already confirmed
         SubscriptionsStatus::Confirmed => Ok(false),
     }
 }
-
-#[tracing::instrument(name = "Get subscriber_id from token", skip(subscription_token, pool))]
-pub async fn get_subscriber_id_from_token(
-    pool: &PgPool,
-    subscription_token: &SubscriberToken,
-) -> Z2PResult<Option<Uuid>> {
-    let result = sqlx::query!(
-        "SELECT subscriber_id FROM subscription_tokens
-        WHERE subscription_token = $1",
-        subscription_token.as_ref(),
-    )
-    .fetch_optional(pool)
-    .await
-    .context("Failed to read subscriber_id of subscription_token from database.")?;
-    Ok(result.map(|r| r.subscriber_id))
-}
-
-#[tracing::instrument(
-    name = "Get name, eamil and subscribed_at from subscriber_id",
-    skip_all
-)]
-pub async fn get_subscriber_from_subscriber_id(
-    pool: &PgPool,
-    subscriber_id: Uuid,
-) -> Z2PResult<(
-    SubscriberName,
-    SubscriberEmail,
-    SubscriberToken,
-    DateTime<Utc>,
-)> {
-    let result = sqlx::query!(
-        "SELECT email, name, subscribed_at FROM subscriptions
-        WHERE id = $1",
-        subscriber_id,
-    )
-    .fetch_one(pool)
-    .await
-    .context("Failed to read subscriber data of subscription_id from database.")?;
-    let token = sqlx::query!(
-        "SELECT subscription_token FROM subscription_tokens
-        WHERE subscriber_id = $1",
-        subscriber_id,
-    )
-    .fetch_one(pool)
-    .await
-    .context("Failed to read subscriber token of subscription_id from database.")?;
-    Ok((
-        SubscriberName::parse(result.name)?,
-        SubscriberEmail::parse(result.email)?,
-        SubscriberToken::parse(token.subscription_token)?,
-        result.subscribed_at,
-    ))
-}
@@ -0,0 +1,200 @@
+//! src/routes/subscriptions/data.rs
+
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::SubscriberToken;
+use crate::error::Z2PResult;
+use crate::subscriber_repository::get_subscriber_id_of_known_token;
+
+/// Everything stored about a subscriber.
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct SubscriberData {
+    pub profile: SubscriberProfile,
+    /// Subscribed, confirmed and unsubscribed events in order of occurrence
+    pub subscription_history: Vec<SubscriberDataEvent>,
+    /// Received, failed and opened issues in order of occurrence
+    pub deliveries: Vec<SubscriberDataEvent>,
+    /// Issues, which are still queued for delivery to the subscriber
+    pub pending_deliveries: Vec<PendingDelivery>,
+    /// Issues, which were skipped because of the frequency cap
+    pub skipped_deliveries: Vec<SkippedDelivery>,
+    pub feedback: Vec<SubscriberFeedback>,
+    /// Set, if emails to the address are suppressed, e.g. after a hard bounce
+    pub suppression: Option<Suppression>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct SubscriberProfile {
+    pub id: Uuid,
+    pub email: String,
+    pub name: String,
+    pub status: String,
+    pub locale: Option<String>,
+    pub list: String,
+    pub subscribed_at: DateTime<Utc>,
+    /// Chosen in the preference center
+    pub max_emails_per_week: Option<i32>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct SubscriberDataEvent {
+    pub kind: String,
+    pub issue_id: Option<Uuid>,
+    pub issue_title: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct PendingDelivery {
+    pub issue_id: Uuid,
+    pub issue_title: String,
+    pub status: String,
+    pub n_retries: i16,
+    pub execute_after: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct SkippedDelivery {
+    pub issue_id: Uuid,
+    pub issue_title: String,
+    pub skipped_at: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct SubscriberFeedback {
+    pub issue_id: Uuid,
+    pub issue_title: String,
+    pub useful: bool,
+    pub comment: Option<String>,
+    pub submitted_at: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct Suppression {
+    pub reason: String,
+    pub suppressed_at: DateTime<Utc>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/subscriptions/data",
+    tag = "subscriptions",
+    params(SubscriberToken),
+    responses(
+        (status = 200, description = "Data stored about the subscriber.", body = SubscriberData),
+        (status = 400, description = "Invalid or unknown subscription token."),
+    )
+)]
+#[tracing::instrument(name = "Export data of subscriber", skip(subscriber_token, pool))]
+pub async fn subscriber_data(
+    subscriber_token: web::Query<SubscriberToken>,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<HttpResponse> {
+    subscriber_token.is_valid()?;
+    let subscriber_id = get_subscriber_id_of_known_token(pool.as_ref(), &subscriber_token).await?;
+    let data = get_subscriber_data(&pool, subscriber_id).await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(("Cache-Control", "no-store"))
+        .json(data))
+}
+
+#[tracing::instrument(name = "Get data of subscriber from database", skip(pool))]
+async fn get_subscriber_data(pool: &PgPool, subscriber_id: Uuid) -> Z2PResult<SubscriberData> {
+    let profile = sqlx::query_as!(
+        SubscriberProfile,
+        r#"
+        SELECT s.id, s.email, s.name, s.status::text AS "status!", s.locale,
+            l.name AS list, s.subscribed_at, s.max_emails_per_week
+        FROM subscriptions s
+        JOIN lists l ON l.list_id = s.list_id
+        WHERE s.id = $1
+        "#,
+        subscriber_id
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to read profile of subscriber.")?;
+    let events = sqlx::query_as!(
+        SubscriberDataEvent,
+        r#"
+        SELECT e.kind::text AS "kind!", e.newsletter_issue_id AS issue_id,
+            i.title AS "issue_title?", e.occurred_at
+        FROM subscriber_events e
+        LEFT JOIN newsletter_issues i ON i.newsletter_issue_id = e.newsletter_issue_id
+        WHERE e.subscriber_id = $1
+        ORDER BY e.occurred_at, e.event_id
+        "#,
+        subscriber_id
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to read events of subscriber.")?;
+    let (subscription_history, deliveries) = events
+        .into_iter()
+        .partition(|e| ["subscribed", "confirmed", "unsubscribed"].contains(&e.kind.as_str()));
+    let pending_deliveries = sqlx::query_as!(
+        PendingDelivery,
+        r#"
+        SELECT q.newsletter_issue_id AS issue_id, i.title AS issue_title,
+            q.status::text AS "status!", q.n_retries, q.execute_after
+        FROM issue_delivery_queue q
+        JOIN newsletter_issues i ON i.newsletter_issue_id = q.newsletter_issue_id
+        WHERE q.user_id = $1
+        ORDER BY q.execute_after
+        "#,
+        subscriber_id
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to read pending deliveries of subscriber.")?;
+    let skipped_deliveries = sqlx::query_as!(
+        SkippedDelivery,
+        r#"
+        SELECT c.newsletter_issue_id AS issue_id, i.title AS issue_title, c.skipped_at
+        FROM frequency_capped_sends c
+        JOIN newsletter_issues i ON i.newsletter_issue_id = c.newsletter_issue_id
+        WHERE c.subscriber_id = $1
+        ORDER BY c.skipped_at
+        "#,
+        subscriber_id
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to read skipped deliveries of subscriber.")?;
+    let feedback = sqlx::query_as!(
+        SubscriberFeedback,
+        r#"
+        SELECT f.newsletter_issue_id AS issue_id, i.title AS issue_title, f.useful,
+            f.comment, f.submitted_at
+        FROM issue_feedback f
+        JOIN newsletter_issues i ON i.newsletter_issue_id = f.newsletter_issue_id
+        WHERE f.subscriber_id = $1
+        ORDER BY f.submitted_at
+        "#,
+        subscriber_id
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to read feedback of subscriber.")?;
+    let suppression = sqlx::query_as!(
+        Suppression,
+        "SELECT reason, suppressed_at FROM suppressions WHERE email = $1",
+        profile.email
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to read suppression of subscriber.")?;
+    Ok(SubscriberData {
+        profile,
+        subscription_history,
+        deliveries,
+        pending_deliveries,
+        skipped_deliveries,
+        feedback,
+        suppression,
+    })
+}
@@ -1,19 +1,37 @@
 //! src/routes/subscriptions/get.rs
 
-use actix_web::Responder;
+use actix_web::{web, Responder};
 use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
 use askama_actix::Template;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::error::Z2PResult;
+use crate::mailing_lists::{get_mailing_lists, MailingList};
 
 #[derive(Template)]
 #[template(path = "subscriptions.html")]
 struct SubscriptionsTemplate {
     flash_messages: Vec<String>,
+    lists: Vec<MailingList>,
+    idempotency_key: Uuid,
 }
 
-pub async fn subscription_form(flash_messages: IncomingFlashMessages) -> impl Responder {
+pub async fn subscription_form(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<impl Responder> {
     let flash_messages: Vec<String> = flash_messages
         .iter()
         .map(|m| m.content().to_string())
         .collect();
-    SubscriptionsTemplate { flash_messages }
+    let lists = get_mailing_lists(&pool)
+        .await
+        .context("Failed to read mailing lists.")?;
+    Ok(SubscriptionsTemplate {
+        flash_messages,
+        lists,
+        idempotency_key: Uuid::new_v4(),
+    })
 }
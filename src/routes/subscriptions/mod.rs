@@ -1,13 +1,17 @@
 //! src/routes/subscriptions/mod.rs
 
 mod confirm;
+mod data;
 mod get;
 mod post;
+mod resend;
 mod token;
 mod unsubscribe;
 
 pub use confirm::*;
+pub use data::*;
 pub use get::subscription_form;
 pub use post::*;
+pub use resend::*;
 pub use token::*;
 pub use unsubscribe::*;
@@ -2,24 +2,38 @@
 
 // required for source()
 use std::error::Error as StdError;
+use std::time::Instant;
 
 use actix_web::{web, HttpResponse};
 use anyhow::Context;
 use askama::Template;
-use chrono::Utc;
+use chrono::{Duration, Utc};
 use sqlx::postgres::PgDatabaseError;
 use sqlx::{Executor, PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
+use crate::bounces::is_suppressed;
 use crate::domain::{
-    NewSubscriber, SubscriberEmail, SubscriberName, SubscriberToken, ValidationError,
+    Locale, NewSubscriber, SubscriberEmail, SubscriberName, SubscriberToken, ValidationError,
 };
 use crate::email_client::EmailClient;
 use crate::error::{Error, Z2PResult};
+use crate::idempotency::{
+    request_hash, save_response, try_processing, IdempotencyKeyHeader, NextAction,
+    ANONYMOUS_USER_ID,
+};
+use crate::mailing_lists::parse_list_id;
+use crate::metrics::{ConfirmationEmailMetrics, IdempotencyMetrics};
 use crate::routes::SubscriptionsStatus;
-use crate::startup::ApplicationBaseUrl;
+use crate::startup::{ApplicationBaseUrl, ExternalIdempotencyStore};
+use crate::subscriber_events::{record_subscriber_event, SubscriberEventKind};
+use crate::subscriber_repository::SubscriberRepository;
 use crate::utils::see_other;
 
+/// Confirmation links of pending subscribers expire after this time. Tokens of
+/// confirmed subscribers are still used for unsubscribe links and never expire.
+pub const CONFIRMATION_TOKEN_LIFETIME_HOURS: i64 = 48;
+
 /// Checks if err results from trying to subscribe the same email twice
 fn is_email_subscribed_twice_err(err: &Error) -> bool {
     if let Some(source_error) = err.source() {
@@ -29,7 +43,7 @@ fn is_email_subscribed_twice_err(err: &Error) -> bool {
                     if let Some(table) = pg_err.table() {
                         if table == "subscriptions" {
                             if let Some(constraint) = pg_err.constraint() {
-                                if constraint == "subscriptions_email_key" {
+                                if constraint == "subscriptions_list_id_email_key" {
                                     return true;
                                 }
                             }
@@ -42,10 +56,21 @@ fn is_email_subscribed_twice_err(err: &Error) -> bool {
     false
 }
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, serde::Serialize, utoipa::ToSchema)]
+#[schema(as = SubscriptionFormData)]
 pub struct FormData {
     email: String,
     name: String,
+    /// Optional preferred language like `de` or `pt-br`
+    #[serde(default)]
+    locale: String,
+    /// Mailing list to subscribe to; empty for the default list
+    #[serde(default)]
+    list_id: String,
+    /// Optional idempotency key of the subscription form; the `Idempotency-Key`
+    /// header takes precedence, if present.
+    #[serde(default)]
+    idempotency_key: String,
 }
 
 impl TryFrom<FormData> for NewSubscriber {
@@ -54,13 +79,37 @@ impl TryFrom<FormData> for NewSubscriber {
     fn try_from(value: FormData) -> Result<Self, Self::Error> {
         let name = SubscriberName::parse(value.name)?;
         let email = SubscriberEmail::parse(value.email)?;
-        Ok(Self { email, name })
+        let locale = if value.locale.trim().is_empty() {
+            None
+        } else {
+            Some(Locale::parse(value.locale)?)
+        };
+        Ok(Self {
+            email,
+            name,
+            locale,
+        })
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/subscriptions",
+    tag = "subscriptions",
+    request_body(content = SubscriptionFormData, content_type = "application/x-www-form-urlencoded"),
+    params(
+        ("Idempotency-Key" = Option<String>, Header, description = "UUID, which replaces the idempotency key of the form."),
+    ),
+    responses(
+        (status = 303, description = "Confirmation email sent, redirect to /subscriptions/token."),
+        (status = 400, description = "Invalid name, email or idempotency key."),
+        (status = 422, description = "Idempotency key has been used for other input."),
+    )
+)]
+#[allow(clippy::too_many_arguments)]
 #[tracing::instrument(
     name = "Adding a new subscriber.",
-    skip(form, pool, email_client, base_url),
+    skip_all,
     fields(
         subscriber_email = %form.email,
         subscriber_name = %form.name
@@ -68,34 +117,95 @@ impl TryFrom<FormData> for NewSubscriber {
 )]
 pub async fn subscribe(
     form: web::Form<FormData>,
+    idempotency_key_header: IdempotencyKeyHeader,
+    idempotency_store: web::Data<ExternalIdempotencyStore>,
+    idempotency_metrics: web::Data<IdempotencyMetrics>,
     pool: web::Data<PgPool>,
     email_client: web::Data<EmailClient>,
     base_url: web::Data<ApplicationBaseUrl>,
+    confirmation_metrics: web::Data<ConfirmationEmailMetrics>,
 ) -> Z2PResult<HttpResponse> {
-    let new_subscriber = form.0.try_into();
-    let new_subscriber = new_subscriber?;
-    let subscription_token = match subscribe_transaction(&new_subscriber, pool.as_ref()).await {
+    let mut form = form.into_inner();
+    let idempotency_key =
+        idempotency_key_header.or_optional_form_field(std::mem::take(&mut form.idempotency_key))?;
+    // requests without key, e.g. of forms embedded in other sites, are not deduplicated
+    let Some(idempotency_key) = idempotency_key else {
+        return add_subscriber(form, &pool, &email_client, &base_url, &confirmation_metrics).await;
+    };
+    // a double-submitted form gets the response of the first submit and does not
+    // send a second confirmation email
+    let next_action = try_processing(
+        &pool,
+        idempotency_store.0.as_ref(),
+        &idempotency_key,
+        ANONYMOUS_USER_ID,
+        &request_hash(&form)?,
+    )
+    .await?;
+    idempotency_metrics.record(&next_action);
+    let transaction = match next_action {
+        NextAction::StartProcessing(t) => t,
+        NextAction::ReturnSavedResponse(saved_response) => return Ok(saved_response),
+        NextAction::RejectReusedKey => return Err(Error::IdempotencyKeyReused),
+    };
+    let response =
+        add_subscriber(form, &pool, &email_client, &base_url, &confirmation_metrics).await?;
+    let response = save_response(
+        transaction,
+        idempotency_store.0.as_ref(),
+        &idempotency_key,
+        ANONYMOUS_USER_ID,
+        response,
+    )
+    .await?;
+    Ok(response)
+}
+
+/// Store new subscriber and send confirmation email.
+async fn add_subscriber(
+    form: FormData,
+    pool: &PgPool,
+    email_client: &EmailClient,
+    base_url: &ApplicationBaseUrl,
+    confirmation_metrics: &ConfirmationEmailMetrics,
+) -> Z2PResult<HttpResponse> {
+    let list_id = parse_list_id(pool, &form.list_id)
+        .await
+        .context("Failed to read mailing list.")?
+        .ok_or_else(|| ValidationError::InvalidList(form.list_id.clone()))?;
+    let new_subscriber = form.try_into();
+    let new_subscriber: NewSubscriber = new_subscriber?;
+    if is_suppressed(pool, new_subscriber.email.as_ref())
+        .await
+        .context("Failed to check suppression list.")?
+    {
+        return Err(
+            ValidationError::SuppressedEmail(new_subscriber.email.as_ref().to_owned()).into(),
+        );
+    }
+    let subscription_token = match subscribe_transaction(&new_subscriber, list_id, pool).await {
         Ok(new_subscription_token) => new_subscription_token,
         Err(err) => {
             if is_email_subscribed_twice_err(&err) {
                 // get id from new_subscriber
-                let subscriber_id =
-                    get_subscriber_id_from_email(pool.as_ref(), &new_subscriber).await?;
+                let subscriber_id = pool
+                    .subscriber_id_from_email(list_id, &new_subscriber.email)
+                    .await?;
                 // existing subscriber, check if status is confirmed
-                match get_status_from_subscriber_id(pool.as_ref(), subscriber_id).await? {
+                match pool.status_from_subscriber_id(subscriber_id).await? {
                     SubscriptionsStatus::Confirmed => {
                         // new subscriber is already confirmed
                         // grab token of existing subscriber with id
-                        let token =
-                            get_token_from_subscriber_id(pool.as_ref(), subscriber_id).await?;
+                        let token = pool.token_from_subscriber_id(subscriber_id).await?;
                         return Ok(see_other(&format!(
                             "/subscriptions/confirm?subscription_token={}",
                             token.as_ref()
                         )));
                     }
                     SubscriptionsStatus::PendingConfirmation => {
-                        // grab token of existing subscriber with id
-                        get_token_from_subscriber_id(pool.as_ref(), subscriber_id).await?
+                        // send the same link again with a fresh lifetime
+                        extend_token_lifetime(pool, subscriber_id).await?;
+                        pool.token_from_subscriber_id(subscriber_id).await?
                     }
                 }
             } else {
@@ -103,13 +213,16 @@ pub async fn subscribe(
             }
         }
     };
-    send_confirmation_email(
-        &email_client,
+    let started_at = Instant::now();
+    let result = send_confirmation_email(
+        email_client,
         new_subscriber,
         &base_url.0,
         &subscription_token,
     )
-    .await?;
+    .await;
+    confirmation_metrics.record(started_at.elapsed(), result.is_ok());
+    result?;
     Ok(see_other("/subscriptions/token"))
 }
 
@@ -119,6 +232,7 @@ pub async fn subscribe(
 )]
 pub async fn subscribe_transaction(
     new_subscriber: &NewSubscriber,
+    list_id: Uuid,
     pool: &PgPool,
 ) -> Z2PResult<SubscriberToken> {
     // init transaction
@@ -127,7 +241,15 @@ pub async fn subscribe_transaction(
         .await
         .context("Failed to acquire a Postgres connection from the pool")?;
     // insert subscriber in transaction
-    let subscriber_id = insert_subscriber(&mut transaction, new_subscriber).await?;
+    let subscriber_id = insert_subscriber(&mut transaction, new_subscriber, list_id).await?;
+    record_subscriber_event(
+        &mut *transaction,
+        subscriber_id,
+        SubscriberEventKind::Subscribed,
+        None,
+    )
+    .await
+    .context("Failed to record subscribed event.")?;
     // insert token in transaction
     let subscription_token = SubscriberToken::generate_subscription_token();
     store_token(&mut transaction, subscriber_id, &subscription_token).await?;
@@ -147,16 +269,19 @@ pub async fn subscribe_transaction(
 pub async fn insert_subscriber(
     transaction: &mut Transaction<'_, Postgres>,
     new_subscriber: &NewSubscriber,
+    list_id: Uuid,
 ) -> Z2PResult<Uuid> {
     let subscriber_id = Uuid::new_v4();
     let query = sqlx::query!(
-        r#"INSERT INTO subscriptions (id, email, name, subscribed_at, status)
-        VALUES ($1, $2, $3, $4, $5)"#,
+        r#"INSERT INTO subscriptions (id, email, name, subscribed_at, status, locale, list_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)"#,
         subscriber_id,
         new_subscriber.email.as_ref(),
         new_subscriber.name.as_ref(),
         Utc::now(),
         SubscriptionsStatus::PendingConfirmation as SubscriptionsStatus,
+        new_subscriber.locale.as_ref().map(|l| l.as_ref()),
+        list_id,
     );
     transaction
         .execute(query)
@@ -175,10 +300,11 @@ pub async fn store_token(
     subscription_token: &SubscriberToken,
 ) -> Z2PResult<()> {
     let query = sqlx::query!(
-        r#"INSERT INTO subscription_tokens (subscription_token, subscriber_id)
-        VALUES ($1, $2)"#,
+        r#"INSERT INTO subscription_tokens (subscription_token, subscriber_id, expires_at)
+        VALUES ($1, $2, $3)"#,
         subscription_token.as_ref(),
-        subscriber_id
+        subscriber_id,
+        Utc::now() + Duration::hours(CONFIRMATION_TOKEN_LIFETIME_HOURS),
     );
     transaction
         .execute(query)
@@ -187,6 +313,43 @@ pub async fn store_token(
     Ok(())
 }
 
+#[tracing::instrument(name = "Extend lifetime of subscription token", skip(pool))]
+async fn extend_token_lifetime(pool: &PgPool, subscriber_id: Uuid) -> Z2PResult<()> {
+    sqlx::query!(
+        "UPDATE subscription_tokens SET expires_at = $2 WHERE subscriber_id = $1",
+        subscriber_id,
+        Utc::now() + Duration::hours(CONFIRMATION_TOKEN_LIFETIME_HOURS),
+    )
+    .execute(pool)
+    .await
+    .context("Failed to extend lifetime of subscription token.")?;
+    Ok(())
+}
+
+/// Replace all tokens of a subscriber with a freshly generated token.
+#[tracing::instrument(name = "Renew subscription token", skip(pool))]
+pub async fn renew_token(pool: &PgPool, subscriber_id: Uuid) -> Z2PResult<SubscriberToken> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    let query = sqlx::query!(
+        "DELETE FROM subscription_tokens WHERE subscriber_id = $1",
+        subscriber_id
+    );
+    transaction
+        .execute(query)
+        .await
+        .context("Failed to delete old subscription tokens.")?;
+    let subscription_token = SubscriberToken::generate_subscription_token();
+    store_token(&mut transaction, subscriber_id, &subscription_token).await?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to renew a subscription token.")?;
+    Ok(subscription_token)
+}
+
 #[derive(Template)]
 #[template(path = "email_subscription_link.html")]
 struct EmailHtmlTemplate<'a> {
@@ -194,6 +357,7 @@ struct EmailHtmlTemplate<'a> {
     token: &'a str,
     confirmation_link: &'a str,
     unsubscribe_link: &'a str,
+    preferences_link: &'a str,
 }
 
 #[derive(Template)]
@@ -203,6 +367,7 @@ struct EmailTextTemplate<'a> {
     token: &'a str,
     confirmation_link: &'a str,
     unsubscribe_link: &'a str,
+    preferences_link: &'a str,
 }
 
 #[tracing::instrument(
@@ -227,11 +392,13 @@ pub async fn send_confirmation_email(
         base_url,
         subscription_token.as_ref()
     );
+    let preferences_link = format!("{}/preferences?t={}", base_url, subscription_token.as_ref());
     let plain_body = EmailTextTemplate {
         name: new_subscriber.name.as_ref(),
         token: subscription_token.as_ref(),
         confirmation_link: &confirmation_link,
         unsubscribe_link: &unsubscribe_link,
+        preferences_link: &preferences_link,
     }
     .render()
     .context("Failed to render html body.")?;
@@ -240,6 +407,7 @@ pub async fn send_confirmation_email(
         token: subscription_token.as_ref(),
         confirmation_link: &confirmation_link,
         unsubscribe_link: &unsubscribe_link,
+        preferences_link: &preferences_link,
     }
     .render()
     .context("Failed to render html body.")?;
@@ -247,61 +415,3 @@ pub async fn send_confirmation_email(
         .send_email(&new_subscriber.email, "Welcome!", &html_body, &plain_body)
         .await
 }
-
-#[tracing::instrument(name = "Get subscriber id from email", skip(new_subscriber, pool))]
-pub async fn get_subscriber_id_from_email(
-    pool: &PgPool,
-    new_subscriber: &NewSubscriber,
-) -> Z2PResult<Uuid> {
-    let result = sqlx::query!(
-        "SELECT id FROM subscriptions \
-        WHERE email = $1",
-        new_subscriber.email.as_ref(),
-    )
-    .fetch_one(pool)
-    .await
-    .context("Failed to read subscriber_id of email from database")?;
-    Ok(result.id)
-}
-
-#[tracing::instrument(name = "Get token from subscriber_id", skip(subscriber_id, pool))]
-pub async fn get_token_from_subscriber_id(
-    pool: &PgPool,
-    subscriber_id: Uuid,
-) -> Z2PResult<SubscriberToken> {
-    let result = sqlx::query!(
-        "SELECT subscription_token FROM subscription_tokens \
-        WHERE subscriber_id = $1",
-        subscriber_id,
-    )
-    .fetch_one(pool)
-    .await
-    .context("Failed to read subscription_token of subscriber_id from database")?;
-    // use with_context instead of automatic validation error transformation, since
-    // invalid token has been read from database, which is an unexpected error.
-    let subscription_token = SubscriberToken::parse(result.subscription_token.clone())
-        .with_context(|| {
-            format!(
-                "Read invalid subscription token `{}` from database.",
-                result.subscription_token
-            )
-        })?;
-    Ok(subscription_token)
-}
-
-#[tracing::instrument(name = "Get status from subscriber_id", skip(subscriber_id, pool))]
-pub async fn get_status_from_subscriber_id(
-    pool: &PgPool,
-    subscriber_id: Uuid,
-) -> Z2PResult<SubscriptionsStatus> {
-    // get status of entry with subscriber_id
-    let result = sqlx::query!(
-        "SELECT status AS \"status: SubscriptionsStatus\" FROM subscriptions \
-        WHERE id = $1",
-        subscriber_id,
-    )
-    .fetch_one(pool)
-    .await
-    .context("Failed to read status of subscriber_id from database")?;
-    Ok(result.status)
-}
@@ -0,0 +1,96 @@
+//! src/routes/subscriptions/resend.rs
+
+use std::time::Instant;
+
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+
+use crate::bounces::is_suppressed;
+use crate::domain::{Locale, NewSubscriber, SubscriberEmail, SubscriberName, ValidationError};
+use crate::email_client::EmailClient;
+use crate::error::Z2PResult;
+use crate::mailing_lists::parse_list_id;
+use crate::metrics::ConfirmationEmailMetrics;
+use crate::routes::{renew_token, send_confirmation_email, SubscriptionsStatus};
+use crate::startup::ApplicationBaseUrl;
+use crate::utils::see_other;
+
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+pub struct ResendFormData {
+    email: String,
+    /// Mailing list of the pending subscription; empty for the default list
+    #[serde(default)]
+    list_id: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/subscriptions/resend",
+    tag = "subscriptions",
+    request_body(content = ResendFormData, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Confirmation email sent to pending subscriber, redirect to /subscriptions/token."),
+    )
+)]
+#[tracing::instrument(
+    name = "Resend confirmation email",
+    skip(form, pool, email_client, base_url, confirmation_metrics),
+    fields(subscriber_email = %form.email)
+)]
+pub async fn resend_confirmation(
+    form: web::Form<ResendFormData>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    confirmation_metrics: web::Data<ConfirmationEmailMetrics>,
+) -> Z2PResult<HttpResponse> {
+    let list_id = parse_list_id(pool.as_ref(), &form.list_id)
+        .await
+        .context("Failed to read mailing list.")?
+        .ok_or_else(|| ValidationError::InvalidList(form.list_id.clone()))?;
+    let email = SubscriberEmail::parse(form.0.email)?;
+    let pending_subscriber = sqlx::query!(
+        r#"
+        SELECT id, name, locale, status AS "status: SubscriptionsStatus"
+        FROM subscriptions
+        WHERE list_id = $1 AND email = $2
+        "#,
+        list_id,
+        email.as_ref(),
+    )
+    .fetch_optional(pool.as_ref())
+    .await
+    .context("Failed to read subscriber of email from database.")?
+    .filter(|r| r.status == SubscriptionsStatus::PendingConfirmation);
+    let is_suppressed = is_suppressed(pool.as_ref(), email.as_ref())
+        .await
+        .context("Failed to check suppression list.")?;
+    // unknown, confirmed and suppressed emails get the same answer, so the form does not
+    // reveal who is subscribed
+    if let Some(subscriber) = pending_subscriber.filter(|_| !is_suppressed) {
+        let subscription_token = renew_token(&pool, subscriber.id).await?;
+        let new_subscriber = NewSubscriber {
+            email,
+            name: SubscriberName::parse(subscriber.name)
+                .context("Read invalid subscriber name from database.")?,
+            locale: subscriber.locale.and_then(|l| Locale::parse(l).ok()),
+        };
+        let started_at = Instant::now();
+        let result = send_confirmation_email(
+            &email_client,
+            new_subscriber,
+            &base_url.0,
+            &subscription_token,
+        )
+        .await;
+        confirmation_metrics.record(started_at.elapsed(), result.is_ok());
+        result?;
+    }
+    FlashMessage::info(
+        "If your subscription is waiting for confirmation, a new confirmation email is on its way.",
+    )
+    .send();
+    Ok(see_other("/subscriptions/token"))
+}
@@ -1,9 +1,10 @@
 //! src/routes/subscriptions_confirm.rs
 
-use crate::domain::{SubscriberToken, ValidationError};
+use crate::domain::SubscriberToken;
 use crate::error::Z2PResult;
 use crate::issue_delivery_worker::PgTransaction;
-use crate::routes::{get_subscriber_from_subscriber_id, get_subscriber_id_from_token};
+use crate::subscriber_events::{record_subscriber_event, SubscriberEventKind};
+use crate::subscriber_repository::{get_subscriber_id_of_known_token, SubscriberRepository};
 use actix_web::{web, Responder};
 use anyhow::Context;
 use askama_actix::Template;
@@ -17,31 +18,33 @@ struct UnsubscribeTemplate {
     email: String,
 }
 
+#[utoipa::path(
+    get,
+    path = "/subscriptions/unsubscribe",
+    tag = "subscriptions",
+    params(SubscriberToken),
+    responses(
+        (status = 200, description = "Subscriber removed.", content_type = "text/html"),
+        (status = 400, description = "Invalid or unknown subscription token."),
+    )
+)]
 #[tracing::instrument(name = "Confirm unsubscribe subscriber", skip(subscriber_token, pool))]
 pub async fn unsubscribe(
     subscriber_token: web::Query<SubscriberToken>,
     pool: web::Data<PgPool>,
 ) -> Z2PResult<impl Responder> {
     subscriber_token.is_valid()?;
-    let id = get_subscriber_id_from_token(&pool, &subscriber_token).await?;
-    match id {
-        // Non-existing token!
-        None => Err(ValidationError::InvalidToken(
-            subscriber_token.as_ref().to_owned(),
-        ))?,
-        Some(subscriber_id) => {
-            let (name, email, ..) = get_subscriber_from_subscriber_id(&pool, subscriber_id).await?;
-            remove_subscriber_from_database(&pool, subscriber_id).await?;
-            Ok(UnsubscribeTemplate {
-                name: name.as_ref().to_owned(),
-                email: email.as_ref().to_owned(),
-            })
-        }
-    }
+    let subscriber_id = get_subscriber_id_of_known_token(pool.as_ref(), &subscriber_token).await?;
+    let subscriber = pool.subscriber_from_subscriber_id(subscriber_id).await?;
+    remove_subscriber_from_database(&pool, subscriber_id).await?;
+    Ok(UnsubscribeTemplate {
+        name: subscriber.name.as_ref().to_owned(),
+        email: subscriber.email.as_ref().to_owned(),
+    })
 }
 
 #[tracing::instrument(name = "Remove subscriber and token from database", skip_all)]
-async fn remove_subscriber_from_database(pool: &PgPool, subscriber_id: Uuid) -> Z2PResult<()> {
+pub async fn remove_subscriber_from_database(pool: &PgPool, subscriber_id: Uuid) -> Z2PResult<()> {
     // start transaction
     let mut transaction: PgTransaction = pool
         .begin()
@@ -73,6 +76,14 @@ async fn remove_subscriber_from_database(pool: &PgPool, subscriber_id: Uuid) ->
         .execute(query)
         .await
         .context("Failed to execute query to remove subscriber")?;
+    record_subscriber_event(
+        &mut *transaction,
+        subscriber_id,
+        SubscriberEventKind::Unsubscribed,
+        None,
+    )
+    .await
+    .context("Failed to record unsubscribed event.")?;
     // commit transaction
     transaction
         .commit()
@@ -0,0 +1,134 @@
+//! src/routes/webhooks/bounce.rs
+
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use sqlx::{Executor, PgPool};
+use uuid::Uuid;
+
+use crate::bounces::{apply_bounce_policy, suppress_email, BounceKind};
+use crate::error::Z2PResult;
+use crate::issue_delivery_worker::PgTransaction;
+use crate::subscriber_events::{record_subscriber_event, SubscriberEventKind};
+
+/// Bounce or spam complaint as posted by Postmark's bounce and spam complaint webhooks.
+#[derive(serde::Deserialize, serde::Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "PascalCase")]
+pub struct BounceNotification {
+    /// Bounce type, e.g. `HardBounce`, `SoftBounce` or `SpamComplaint`
+    #[serde(rename = "Type")]
+    pub bounce_type: String,
+    pub email: String,
+    /// Tag of the bounced email; newsletter emails are tagged with the id of their issue.
+    #[serde(default)]
+    pub tag: String,
+}
+
+impl BounceNotification {
+    fn is_spam_complaint(&self) -> bool {
+        self.bounce_type == "SpamComplaint"
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/webhooks/email/bounce",
+    tag = "webhooks",
+    request_body = BounceNotification,
+    responses(
+        (status = 200, description = "Bounce processed or ignored."),
+        (status = 401, description = "Missing or wrong webhook secret."),
+    ),
+    security(("webhook_basic_auth" = []))
+)]
+#[tracing::instrument(
+    name = "Process bounce notification",
+    skip(bounce, pool),
+    fields(recipient_email = %bounce.email, bounce_type = %bounce.bounce_type)
+)]
+pub async fn bounce_notification(
+    bounce: web::Json<BounceNotification>,
+    pool: web::Data<PgPool>,
+) -> Z2PResult<HttpResponse> {
+    let mut transaction: PgTransaction = pool
+        .begin()
+        .await
+        .context("Failed to create transaction.")?;
+    if bounce.is_spam_complaint() {
+        suppress_email(&mut *transaction, &bounce.email, "spam_complaint")
+            .await
+            .context("Failed to suppress email of spam complaint.")?;
+    } else {
+        // Answer with 200 to unknown bounce types and auto-replies, otherwise
+        // the email provider would retry to post them.
+        let Some(kind) = BounceKind::from_postmark_type(&bounce.bounce_type) else {
+            tracing::info!("Ignored bounce of unknown type.");
+            return Ok(HttpResponse::Ok().finish());
+        };
+        if kind == BounceKind::AutoReply {
+            return Ok(HttpResponse::Ok().finish());
+        }
+        apply_bounce_policy(&mut *transaction, &bounce.email, kind)
+            .await
+            .context("Failed to apply bounce policy.")?;
+        // emails without issue tag are e.g. confirmation emails
+        if let Ok(newsletter_issue_id) = Uuid::parse_str(&bounce.tag) {
+            record_bounced_delivery(&mut transaction, newsletter_issue_id, &bounce.email).await?;
+        }
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit bounce notification.")?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// The email provider accepted the email, therefore the delivery worker counted it as
+/// delivered. Count it as failed delivery instead and add it to the timeline of the
+/// subscriber, who received the issue.
+#[tracing::instrument(skip(transaction, email))]
+async fn record_bounced_delivery(
+    transaction: &mut PgTransaction,
+    newsletter_issue_id: Uuid,
+    email: &str,
+) -> Z2PResult<()> {
+    let Some(subscriber) = sqlx::query!(
+        r#"
+        SELECT s.id
+        FROM subscriptions s
+        JOIN newsletter_issues i ON i.list_id = s.list_id
+        WHERE i.newsletter_issue_id = $1 AND s.email = $2
+        "#,
+        newsletter_issue_id,
+        email,
+    )
+    .fetch_optional(&mut **transaction)
+    .await
+    .context("Failed to read subscriber of bounced email.")?
+    else {
+        tracing::info!("Bounced email does not belong to a subscriber of the issue.");
+        return Ok(());
+    };
+    let query = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET
+            num_delivered_newsletters = num_delivered_newsletters - 1,
+            num_failed_deliveries = num_failed_deliveries + 1
+        WHERE newsletter_issue_id = $1 AND num_delivered_newsletters > 0
+        "#,
+        newsletter_issue_id,
+    );
+    transaction
+        .execute(query)
+        .await
+        .context("Failed to count bounced delivery.")?;
+    record_subscriber_event(
+        &mut **transaction,
+        subscriber.id,
+        SubscriberEventKind::DeliveryFailed,
+        Some(newsletter_issue_id),
+    )
+    .await
+    .context("Failed to record bounced delivery.")?;
+    Ok(())
+}
@@ -0,0 +1,217 @@
+//! src/routes/webhooks/inbound.rs
+
+use crate::domain::{SubscriberEmail, SubscriberName, ValidationError};
+use crate::email_client::EmailClient;
+use crate::error::Z2PResult;
+use crate::routes::remove_subscriber_from_database;
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use askama::Template;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Inbound email as posted by Postmark's inbound webhook.
+#[derive(serde::Deserialize, serde::Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "PascalCase")]
+pub struct InboundEmail {
+    pub from: String,
+    #[serde(default)]
+    pub subject: String,
+    #[serde(default)]
+    pub text_body: String,
+    /// Text of reply without quoted history, if Postmark could extract it.
+    #[serde(default)]
+    pub stripped_text_reply: String,
+}
+
+/// Commands subscribers may send by replying to our emails.
+#[derive(Debug, PartialEq, Eq)]
+enum MailCommand {
+    Unsubscribe,
+}
+
+impl MailCommand {
+    /// Look for a command as first word of the reply or, if the reply
+    /// does not start with a command, in the subject.
+    fn parse(inbound_email: &InboundEmail) -> Option<Self> {
+        let reply = if inbound_email.stripped_text_reply.trim().is_empty() {
+            &inbound_email.text_body
+        } else {
+            &inbound_email.stripped_text_reply
+        };
+        Self::parse_first_word(reply).or_else(|| {
+            // strip reply prefixes like "Re: " from subject
+            let mut subject = inbound_email.subject.trim();
+            while let Some((prefix, rest)) = subject.split_once(':') {
+                if !["re", "aw", "fwd"].contains(&prefix.trim().to_lowercase().as_str()) {
+                    break;
+                }
+                subject = rest.trim();
+            }
+            Self::parse_first_word(subject)
+        })
+    }
+
+    fn parse_first_word(text: &str) -> Option<Self> {
+        let first_word = text
+            .split_whitespace()
+            .next()?
+            .trim_matches(|c: char| !c.is_alphanumeric())
+            .to_uppercase();
+        match first_word.as_str() {
+            "STOP" | "UNSUBSCRIBE" => Some(Self::Unsubscribe),
+            _ => None,
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/webhooks/email/inbound",
+    tag = "webhooks",
+    request_body = InboundEmail,
+    responses(
+        (status = 200, description = "Inbound email processed or ignored."),
+        (status = 401, description = "Missing or wrong webhook secret."),
+    ),
+    security(("webhook_basic_auth" = []))
+)]
+#[tracing::instrument(
+    name = "Process inbound email",
+    skip(inbound_email, pool, email_client),
+    fields(sender_email = %inbound_email.from)
+)]
+pub async fn inbound_email(
+    inbound_email: web::Json<InboundEmail>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+) -> Z2PResult<HttpResponse> {
+    // Answer always with 200 to inbound emails without command, unknown or invalid
+    // senders. Otherwise the email provider would retry to deliver them.
+    let Some(command) = MailCommand::parse(&inbound_email) else {
+        tracing::info!("Inbound email does not contain a command.");
+        return Ok(HttpResponse::Ok().finish());
+    };
+    let Ok(sender) = SubscriberEmail::parse(inbound_email.0.from) else {
+        tracing::warn!("Inbound email has an invalid sender address.");
+        return Ok(HttpResponse::Ok().finish());
+    };
+    match command {
+        MailCommand::Unsubscribe => {
+            // the command has no list context, therefore it ends all subscriptions
+            let subscriptions = get_subscriptions_of_email(&pool, &sender).await?;
+            let Some((_, name)) = subscriptions.first() else {
+                tracing::info!("Sender of unsubscribe command is not subscribed.");
+                return Ok(HttpResponse::Ok().finish());
+            };
+            for (subscriber_id, _) in subscriptions.iter() {
+                remove_subscriber_from_database(&pool, *subscriber_id).await?;
+            }
+            send_unsubscribe_confirmation_email(&email_client, &sender, name).await?;
+        }
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Subscriber id and name of all subscriptions of email to mailing lists.
+#[tracing::instrument(name = "Get subscriptions of email", skip_all)]
+async fn get_subscriptions_of_email(
+    pool: &PgPool,
+    email: &SubscriberEmail,
+) -> Z2PResult<Vec<(Uuid, SubscriberName)>> {
+    let result = sqlx::query!(
+        "SELECT id, name FROM subscriptions \
+        WHERE email = $1",
+        email.as_ref(),
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to read subscriptions of email from database")?;
+    let subscriptions = result
+        .into_iter()
+        .map(|r| Ok((r.id, SubscriberName::parse(r.name)?)))
+        .collect::<Result<_, ValidationError>>()?;
+    Ok(subscriptions)
+}
+
+#[derive(Template)]
+#[template(path = "email_unsubscribe_confirmation.html")]
+struct EmailHtmlTemplate<'a> {
+    name: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "email_unsubscribe_confirmation.txt")]
+struct EmailTextTemplate<'a> {
+    name: &'a str,
+}
+
+#[tracing::instrument(name = "Send unsubscribe confirmation email", skip_all)]
+async fn send_unsubscribe_confirmation_email(
+    email_client: &EmailClient,
+    recipient: &SubscriberEmail,
+    name: &SubscriberName,
+) -> Z2PResult<()> {
+    let plain_body = EmailTextTemplate {
+        name: name.as_ref(),
+    }
+    .render()
+    .context("Failed to render text body.")?;
+    let html_body = EmailHtmlTemplate {
+        name: name.as_ref(),
+    }
+    .render()
+    .context("Failed to render html body.")?;
+    email_client
+        .send_email(
+            recipient,
+            "You have been unsubscribed",
+            &html_body,
+            &plain_body,
+        )
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InboundEmail, MailCommand};
+
+    fn inbound_email(subject: &str, stripped_text_reply: &str) -> InboundEmail {
+        InboundEmail {
+            from: "ursula_le_guin@gmail.com".to_string(),
+            subject: subject.to_string(),
+            text_body: format!("{}\n\n> quoted newsletter", stripped_text_reply),
+            stripped_text_reply: stripped_text_reply.to_string(),
+        }
+    }
+
+    #[test]
+    fn stop_and_unsubscribe_replies_are_recognized() {
+        for reply in ["STOP", "stop", "Unsubscribe!", "  STOP please remove me"] {
+            assert_eq!(
+                MailCommand::parse(&inbound_email("Re: Newsletter", reply)),
+                Some(MailCommand::Unsubscribe),
+                "reply `{}` was not recognized",
+                reply
+            );
+        }
+    }
+
+    #[test]
+    fn command_in_subject_is_recognized() {
+        assert_eq!(
+            MailCommand::parse(&inbound_email("Re: AW: unsubscribe", "")),
+            Some(MailCommand::Unsubscribe)
+        );
+    }
+
+    #[test]
+    fn replies_without_command_are_ignored() {
+        for reply in ["Thanks for the newsletter!", "Don't stop writing", ""] {
+            assert_eq!(
+                MailCommand::parse(&inbound_email("Re: Newsletter", reply)),
+                None
+            );
+        }
+    }
+}
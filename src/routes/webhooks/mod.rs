@@ -0,0 +1,7 @@
+//! src/routes/webhooks/mod.rs
+
+mod bounce;
+mod inbound;
+
+pub use bounce::*;
+pub use inbound::*;
@@ -0,0 +1,376 @@
+//! src/secrets.rs
+
+//! Secret values of the configuration, which are resolved from an external secrets
+//! backend. A value `secret://<name>#<key>` is replaced by the field `<key>` of the
+//! secret `<name>`, e.g. `secret://zero2prod/database#password`.
+
+use anyhow::Context;
+use chrono::Utc;
+use config::{Config, ConfigError, Source, Value, ValueKind};
+use hmac::{Hmac, Mac};
+use secrecy::ExposeSecret;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::configuration::{SecretsBackendSettings, SecretsSettings};
+
+const SECRET_REFERENCE_PREFIX: &str = "secret://";
+
+/// Fields of fetched secrets by backend and name of secret. Cached fields are reused
+/// by configurations read within `refresh_seconds`; later reads fetch the secret again,
+/// which picks up rotated values.
+static SECRETS_CACHE: LazyLock<Mutex<HashMap<String, CachedSecret>>> =
+    LazyLock::new(Default::default);
+
+struct CachedSecret {
+    fetched_at: Instant,
+    fields: HashMap<String, String>,
+}
+
+/// Reference to field `key` of secret `name` in the secrets backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretReference {
+    pub name: String,
+    pub key: String,
+}
+
+impl SecretReference {
+    /// Parse reference from configuration value; `None` for other values.
+    pub fn parse(value: &str) -> Option<Result<Self, String>> {
+        let reference = value.strip_prefix(SECRET_REFERENCE_PREFIX)?;
+        Some(match reference.split_once('#') {
+            Some((name, key)) if !name.is_empty() && !key.is_empty() => Ok(Self {
+                name: name.to_string(),
+                key: key.to_string(),
+            }),
+            _ => Err(format!(
+                "Invalid secret reference `{}`, expected `{}<name>#<key>`.",
+                value, SECRET_REFERENCE_PREFIX
+            )),
+        })
+    }
+}
+
+/// Replace secret references in the values of `config` by the values of the secrets
+/// backend in section `secrets`. Configurations without references are returned as is.
+pub fn resolve_secrets(config: Config) -> Result<Config, ConfigError> {
+    let mut references = Vec::new();
+    collect_references(
+        String::new(),
+        &Value::from(config.collect()?),
+        &mut references,
+    );
+    if references.is_empty() {
+        return Ok(config);
+    }
+    let settings: SecretsSettings = match config.get("secrets") {
+        Ok(settings) => settings,
+        Err(ConfigError::NotFound(_)) => {
+            return Err(ConfigError::Message(format!(
+                "Secret reference at `{}` requires a `secrets` backend.",
+                references[0].0
+            )))
+        }
+        Err(e) => return Err(e),
+    };
+    let mut parsed = Vec::with_capacity(references.len());
+    for (key, value) in references {
+        let reference = SecretReference::parse(&value)
+            .expect("value is a secret reference")
+            .map_err(|e| ConfigError::Message(format!("{} at `{}`", e, key)))?;
+        parsed.push((key, reference));
+    }
+    let references: Vec<&SecretReference> = parsed.iter().map(|(_, r)| r).collect();
+    // configuration is read synchronously, also inside of the runtime of the application
+    let values = std::thread::scope(|s| {
+        s.spawn(|| {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .context("Failed to build runtime to fetch secrets.")?
+                .block_on(fetch_secret_values(&settings, &references))
+        })
+        .join()
+        .map_err(|_| anyhow::anyhow!("Fetching secrets panicked."))?
+    })
+    .map_err(|e| ConfigError::Message(format!("{:#}", e)))?;
+    let mut builder = Config::builder().add_source(config);
+    for ((key, _), value) in parsed.into_iter().zip(values) {
+        builder = builder.set_override(key, value)?;
+    }
+    builder.build()
+}
+
+/// Collect keys and values of all secret references in `value` below `key`.
+fn collect_references(key: String, value: &Value, references: &mut Vec<(String, String)>) {
+    match &value.kind {
+        ValueKind::String(s) if s.starts_with(SECRET_REFERENCE_PREFIX) => {
+            references.push((key, s.clone()))
+        }
+        ValueKind::Table(table) => {
+            for (name, value) in table {
+                let key = if key.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}.{}", key, name)
+                };
+                collect_references(key, value, references);
+            }
+        }
+        ValueKind::Array(array) => {
+            for (index, value) in array.iter().enumerate() {
+                collect_references(format!("{}[{}]", key, index), value, references);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Values of referenced fields in the order of `references`.
+async fn fetch_secret_values(
+    settings: &SecretsSettings,
+    references: &[&SecretReference],
+) -> Result<Vec<String>, anyhow::Error> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_millis(settings.timeout_milliseconds))
+        .build()
+        .context("Failed to build client of secrets backend.")?;
+    let refresh_after = Duration::from_secs(settings.refresh_seconds);
+    let mut values = Vec::with_capacity(references.len());
+    for reference in references {
+        let cache_key = format!("{}:{}", settings.backend.id(), reference.name);
+        let cached = SECRETS_CACHE
+            .lock()
+            .expect("secrets cache is not poisoned")
+            .get(&cache_key)
+            .filter(|cached| cached.fetched_at.elapsed() < refresh_after)
+            .map(|cached| cached.fields.clone());
+        let fields = match cached {
+            Some(fields) => fields,
+            None => {
+                let fields = fetch_secret(&client, &settings.backend, &reference.name)
+                    .await
+                    .with_context(|| format!("Failed to fetch secret `{}`.", reference.name))?;
+                SECRETS_CACHE
+                    .lock()
+                    .expect("secrets cache is not poisoned")
+                    .insert(
+                        cache_key,
+                        CachedSecret {
+                            fetched_at: Instant::now(),
+                            fields: fields.clone(),
+                        },
+                    );
+                fields
+            }
+        };
+        let value = fields.get(&reference.key).with_context(|| {
+            format!(
+                "Secret `{}` has no field `{}`.",
+                reference.name, reference.key
+            )
+        })?;
+        values.push(value.clone());
+    }
+    Ok(values)
+}
+
+#[tracing::instrument(name = "Fetch secret from backend", skip(client, backend))]
+async fn fetch_secret(
+    client: &reqwest::Client,
+    backend: &SecretsBackendSettings,
+    name: &str,
+) -> Result<HashMap<String, String>, anyhow::Error> {
+    let fields = match backend {
+        SecretsBackendSettings::Vault {
+            address,
+            token,
+            mount,
+        } => {
+            // key-value secrets engine version 2
+            let response: serde_json::Value = client
+                .get(format!(
+                    "{}/v1/{}/data/{}",
+                    address.trim_end_matches('/'),
+                    mount,
+                    name
+                ))
+                .header("X-Vault-Token", token.expose_secret())
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            response["data"]["data"].clone()
+        }
+        SecretsBackendSettings::AwsSecretsManager {
+            region,
+            access_key_id,
+            secret_access_key,
+            session_token,
+            endpoint,
+        } => {
+            let url = endpoint
+                .clone()
+                .unwrap_or_else(|| format!("https://secretsmanager.{}.amazonaws.com/", region));
+            let url = reqwest::Url::parse(&url).context("Invalid endpoint of secrets manager.")?;
+            let body = serde_json::json!({ "SecretId": name }).to_string();
+            let signer = AwsSigner {
+                access_key_id,
+                secret_access_key: secret_access_key.expose_secret(),
+                region,
+                service: "secretsmanager",
+            };
+            let mut headers = vec![
+                ("content-type", "application/x-amz-json-1.1".to_string()),
+                ("x-amz-target", "secretsmanager.GetSecretValue".to_string()),
+            ];
+            if let Some(session_token) = session_token {
+                headers.push((
+                    "x-amz-security-token",
+                    session_token.expose_secret().clone(),
+                ));
+            }
+            let headers = signer.sign(&url, &body, headers, Utc::now())?;
+            let mut request = client.post(url).body(body);
+            for (name, value) in headers {
+                // reqwest sets the host header itself
+                if name != "host" {
+                    request = request.header(name, value);
+                }
+            }
+            let response: serde_json::Value =
+                request.send().await?.error_for_status()?.json().await?;
+            let secret_string = response["SecretString"]
+                .as_str()
+                .context("Secret has no SecretString.")?;
+            serde_json::from_str(secret_string).context("SecretString is no JSON object.")?
+        }
+    };
+    let serde_json::Value::Object(fields) = fields else {
+        anyhow::bail!("Secret has no fields.");
+    };
+    Ok(fields
+        .into_iter()
+        .map(|(key, value)| match value {
+            serde_json::Value::String(s) => (key, s),
+            other => (key, other.to_string()),
+        })
+        .collect())
+}
+
+/// Signature version 4 of AWS requests with a JSON body.
+struct AwsSigner<'a> {
+    access_key_id: &'a str,
+    secret_access_key: &'a str,
+    region: &'a str,
+    service: &'a str,
+}
+
+impl AwsSigner<'_> {
+    /// Headers of request to `url` including date, host and authorization.
+    fn sign(
+        &self,
+        url: &reqwest::Url,
+        body: &str,
+        mut headers: Vec<(&'static str, String)>,
+        now: chrono::DateTime<Utc>,
+    ) -> Result<Vec<(&'static str, String)>, anyhow::Error> {
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().context("Url has no host.")?, port),
+            None => url.host_str().context("Url has no host.")?.to_string(),
+        };
+        headers.push(("host", host));
+        headers.push(("x-amz-date", amz_date.clone()));
+        headers.sort_by(|a, b| a.0.cmp(b.0));
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+            .collect();
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_request = format!(
+            "POST\n{}\n{}\n{}\n{}\n{}",
+            url.path(),
+            url.query().unwrap_or_default(),
+            canonical_headers,
+            signed_headers,
+            hex::encode(Sha256::digest(body))
+        );
+        let scope = format!("{}/{}/{}/aws4_request", date, self.region, self.service);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical_request))
+        );
+        let signature = hex::encode(hmac_sha256(
+            &self.signing_key(&date),
+            string_to_sign.as_bytes(),
+        ));
+        headers.push((
+            "authorization",
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                self.access_key_id, scope, signed_headers, signature
+            ),
+        ));
+        Ok(headers)
+    }
+
+    fn signing_key(&self, date: &str) -> Vec<u8> {
+        let key = format!("AWS4{}", self.secret_access_key);
+        let key = hmac_sha256(key.as_bytes(), date.as_bytes());
+        let key = hmac_sha256(&key, self.region.as_bytes());
+        let key = hmac_sha256(&key, self.service.as_bytes());
+        hmac_sha256(&key, b"aws4_request")
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secret_references_are_parsed() {
+        assert_eq!(
+            SecretReference::parse("secret://zero2prod/database#password"),
+            Some(Ok(SecretReference {
+                name: "zero2prod/database".to_string(),
+                key: "password".to_string(),
+            }))
+        );
+        assert!(SecretReference::parse("plain password").is_none());
+        assert!(SecretReference::parse("secret://zero2prod/database")
+            .unwrap()
+            .is_err());
+    }
+
+    #[test]
+    fn signing_key_matches_aws_example() {
+        // example of the documentation of signature version 4
+        let signer = AwsSigner {
+            access_key_id: "AKIDEXAMPLE",
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            region: "us-east-1",
+            service: "iam",
+        };
+        assert_eq!(
+            hex::encode(signer.signing_key("20120215")),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+}
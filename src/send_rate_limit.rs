@@ -0,0 +1,112 @@
+//! src/send_rate_limit.rs
+
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::watch;
+
+use crate::config_reload::TunableSettings;
+use crate::configuration::RateLimitSettings;
+use crate::token_bucket::TokenBucket;
+
+/// Send rate limit of emails, which all loops of the delivery worker share. Without
+/// settings no limit applies. A reloaded limit replaces the bucket, which starts full.
+pub struct SendRateLimiter {
+    state: Mutex<LimiterState>,
+}
+
+struct LimiterState {
+    tunables: watch::Receiver<TunableSettings>,
+    settings: Option<RateLimitSettings>,
+    bucket: Option<Arc<TokenBucket>>,
+}
+
+impl SendRateLimiter {
+    pub fn new(tunables: watch::Receiver<TunableSettings>) -> Self {
+        let settings = tunables.borrow().email_rate_limit.clone();
+        let bucket = settings
+            .as_ref()
+            .map(|settings| Arc::new(TokenBucket::from_settings(settings)));
+        Self {
+            state: Mutex::new(LimiterState {
+                tunables,
+                settings,
+                bucket,
+            }),
+        }
+    }
+
+    /// Bucket of the current limit; returns `None`, if no limit is configured.
+    pub fn bucket(&self) -> Option<Arc<TokenBucket>> {
+        let mut state = self.state.lock().unwrap();
+        let LimiterState {
+            tunables,
+            settings,
+            bucket,
+        } = &mut *state;
+        // a closed channel keeps the current settings; changes of other tunable
+        // settings keep the bucket with its tokens
+        if tunables.has_changed().unwrap_or(false) {
+            let reloaded = tunables.borrow_and_update().email_rate_limit.clone();
+            if reloaded != *settings {
+                *bucket = reloaded
+                    .as_ref()
+                    .map(|settings| Arc::new(TokenBucket::from_settings(settings)));
+                *settings = reloaded;
+            }
+        }
+        bucket.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tunables(email_rate_limit: Option<RateLimitSettings>) -> TunableSettings {
+        TunableSettings {
+            log_level: "info".to_string(),
+            api_rate_limit: None,
+            email_rate_limit,
+            n_retries: 3,
+            execute_retry_after_milliseconds: 1000,
+        }
+    }
+
+    fn rate_limit(max_emails: u32) -> Option<RateLimitSettings> {
+        Some(RateLimitSettings {
+            max_emails,
+            interval_seconds: 3600,
+        })
+    }
+
+    #[test]
+    fn without_settings_no_limit_applies() {
+        let limiter = SendRateLimiter::new(watch::channel(tunables(None)).1);
+        assert!(limiter.bucket().is_none());
+    }
+
+    #[test]
+    fn reloaded_limit_replaces_bucket() {
+        let (sender, receiver) = watch::channel(tunables(rate_limit(2)));
+        let limiter = SendRateLimiter::new(receiver);
+        assert_eq!(limiter.bucket().unwrap().take(10), 2);
+
+        sender.send_replace(tunables(rate_limit(5)));
+
+        assert_eq!(limiter.bucket().unwrap().take(10), 5);
+    }
+
+    #[test]
+    fn other_reloaded_settings_keep_bucket() {
+        let (sender, receiver) = watch::channel(tunables(rate_limit(2)));
+        let limiter = SendRateLimiter::new(receiver);
+        assert_eq!(limiter.bucket().unwrap().take(10), 2);
+
+        sender.send_replace(TunableSettings {
+            n_retries: 7,
+            ..tunables(rate_limit(2))
+        });
+
+        assert_eq!(limiter.bucket().unwrap().take(10), 0);
+    }
+}
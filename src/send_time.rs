@@ -0,0 +1,83 @@
+//! src/send_time.rs
+
+use chrono::{DateTime, DurationRound, TimeDelta, Timelike, Utc};
+use sqlx::{Postgres, Transaction};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Opens older than this are ignored, since reading habits change.
+const LEARNING_PERIOD_DAYS: i32 = 90;
+
+/// UTC hour of day, in which subscribers opened most of their newsletter emails.
+/// Subscribers without tracked opens in the learning period are not included.
+/// Ties are resolved to the earlier hour.
+#[tracing::instrument(skip_all)]
+pub async fn get_best_send_hours(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_ids: &[Uuid],
+) -> Result<HashMap<Uuid, u32>, sqlx::Error> {
+    let best_hours = sqlx::query!(
+        r#"
+        SELECT DISTINCT ON (subscriber_id)
+            subscriber_id,
+            EXTRACT(HOUR FROM occurred_at AT TIME ZONE 'UTC')::int AS "hour!"
+        FROM subscriber_events
+        WHERE kind = 'opened'
+            AND subscriber_id = ANY($1)
+            AND occurred_at > now() - make_interval(days => $2)
+        GROUP BY subscriber_id, 2
+        ORDER BY subscriber_id, COUNT(*) DESC, 2
+        "#,
+        subscriber_ids,
+        LEARNING_PERIOD_DAYS,
+    )
+    .fetch_all(&mut **transaction)
+    .await?
+    .into_iter()
+    .map(|r| (r.subscriber_id, r.hour as u32))
+    .collect();
+    Ok(best_hours)
+}
+
+/// First start of `best_hour` within 24 hours after `earliest`. If `earliest` is
+/// within `best_hour`, it is returned unchanged.
+pub fn optimized_send_time(earliest: DateTime<Utc>, best_hour: u32) -> DateTime<Utc> {
+    if earliest.hour() == best_hour {
+        return earliest;
+    }
+    let hours_ahead = (best_hour + 24 - earliest.hour()) % 24;
+    earliest
+        .duration_trunc(TimeDelta::hours(1))
+        .expect("Truncation to hours is in range.")
+        + TimeDelta::hours(hours_ahead.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::optimized_send_time;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn send_time_is_moved_to_start_of_best_hour_on_same_day() {
+        let earliest = Utc.with_ymd_and_hms(2024, 7, 30, 6, 25, 0).unwrap();
+        assert_eq!(
+            optimized_send_time(earliest, 18),
+            Utc.with_ymd_and_hms(2024, 7, 30, 18, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn past_best_hour_is_moved_to_next_day() {
+        let earliest = Utc.with_ymd_and_hms(2024, 7, 30, 20, 10, 0).unwrap();
+        assert_eq!(
+            optimized_send_time(earliest, 7),
+            Utc.with_ymd_and_hms(2024, 7, 31, 7, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn send_time_within_best_hour_is_unchanged() {
+        let earliest = Utc.with_ymd_and_hms(2024, 7, 30, 9, 45, 0).unwrap();
+        assert_eq!(optimized_send_time(earliest, 9), earliest);
+    }
+}
@@ -1,5 +1,6 @@
 //! src/sessionn_state.rs
 
+use crate::authentication::{OidcLoginState, PasskeyChallenge};
 use crate::error::{error_chain_fmt, Error, Z2PResult};
 use actix_session::{Session, SessionExt};
 use actix_web::{dev::Payload, FromRequest, HttpRequest};
@@ -28,6 +29,9 @@ pub struct TypedSession(Session);
 
 impl TypedSession {
     const USER_ID_KEY: &'static str = "user_id";
+    const OIDC_LOGIN_KEY: &'static str = "oidc_login";
+    const PASSKEY_CHALLENGE_KEY: &'static str = "passkey_challenge";
+    const SESSION_ID_KEY: &'static str = "session_id";
 
     pub fn renew(&self) {
         self.0.renew();
@@ -47,6 +51,55 @@ impl TypedSession {
             .map_err(Error::from)
     }
 
+    /// Id of the stored admin session, which can be revoked remotely.
+    pub fn insert_session_id(&self, session_id: Uuid) -> Z2PResult<()> {
+        self.0
+            .insert(Self::SESSION_ID_KEY, session_id)
+            .map_err(SessionError::from)
+            .map_err(Error::from)
+    }
+
+    pub fn get_session_id(&self) -> Z2PResult<Option<Uuid>> {
+        self.0
+            .get(Self::SESSION_ID_KEY)
+            .map_err(SessionError::from)
+            .map_err(Error::from)
+    }
+
+    pub fn insert_oidc_login(&self, login: &OidcLoginState) -> Z2PResult<()> {
+        self.0
+            .insert(Self::OIDC_LOGIN_KEY, login)
+            .map_err(SessionError::from)
+            .map_err(Error::from)
+    }
+
+    /// State of a pending OIDC login, which can only be used once.
+    pub fn take_oidc_login(&self) -> Z2PResult<Option<OidcLoginState>> {
+        let login = self
+            .0
+            .get(Self::OIDC_LOGIN_KEY)
+            .map_err(SessionError::from)?;
+        self.0.remove(Self::OIDC_LOGIN_KEY);
+        Ok(login)
+    }
+
+    pub fn insert_passkey_challenge(&self, challenge: &PasskeyChallenge) -> Z2PResult<()> {
+        self.0
+            .insert(Self::PASSKEY_CHALLENGE_KEY, challenge)
+            .map_err(SessionError::from)
+            .map_err(Error::from)
+    }
+
+    /// Challenge of a pending passkey ceremony, which can only be used once.
+    pub fn take_passkey_challenge(&self) -> Z2PResult<Option<PasskeyChallenge>> {
+        let challenge = self
+            .0
+            .get(Self::PASSKEY_CHALLENGE_KEY)
+            .map_err(SessionError::from)?;
+        self.0.remove(Self::PASSKEY_CHALLENGE_KEY);
+        Ok(challenge)
+    }
+
     pub fn log_out(self) {
         self.0.purge();
     }
@@ -0,0 +1,311 @@
+//! src/smoketest.rs
+
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use secrecy::{ExposeSecret, Secret};
+use uuid::Uuid;
+
+/// Settings of a smoke test run, read from `SMOKETEST_*` environment variables,
+/// e.g. `SMOKETEST_BASE_URL=https://staging.example.com`.
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct SmokeTestSettings {
+    /// Base URL of the deployed instance.
+    pub base_url: String,
+    /// API token minted on `/admin/api_tokens` of the deployed instance.
+    pub api_token: Secret<String>,
+    /// Mailing list, which only has smoke test subscribers, so that real subscribers
+    /// never receive smoke test issues.
+    pub list_id: Uuid,
+    /// Base URL of the test inbox provider, which speaks the Mailpit API.
+    pub inbox_url: String,
+    /// Domain of addresses, whose emails end up in the test inbox.
+    pub inbox_domain: String,
+    /// Time to wait for emails and deliveries.
+    #[serde(default = "default_timeout_seconds")]
+    pub timeout_seconds: u64,
+}
+
+fn default_timeout_seconds() -> u64 {
+    120
+}
+
+pub fn get_smoke_test_settings() -> Result<SmokeTestSettings, config::ConfigError> {
+    config::Config::builder()
+        .add_source(config::Environment::with_prefix("SMOKETEST"))
+        .build()?
+        .try_deserialize()
+}
+
+/// HTTP client of a deployed instance.
+pub struct SmokeTestClient {
+    client: reqwest::Client,
+    base_url: String,
+    api_token: Secret<String>,
+}
+
+impl SmokeTestClient {
+    pub fn new(base_url: &str, api_token: Secret<String>) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .redirect(reqwest::redirect::Policy::none())
+                .timeout(Duration::from_secs(30))
+                .build()
+                .expect("Failed to build HTTP client."),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_token,
+        }
+    }
+
+    pub async fn health_check(&self) -> Result<(), anyhow::Error> {
+        self.client
+            .get(format!("{}/health_check", self.base_url))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    pub async fn subscribe(&self, email: &str, list_id: Uuid) -> Result<(), anyhow::Error> {
+        let response = self
+            .client
+            .post(format!("{}/subscriptions", self.base_url))
+            .form(&[
+                ("name", "Smoke Test"),
+                ("email", email),
+                ("list_id", &list_id.to_string()),
+            ])
+            .send()
+            .await?;
+        if !response.status().is_success() && !response.status().is_redirection() {
+            bail!("Subscribing failed with status {}.", response.status());
+        }
+        Ok(())
+    }
+
+    pub async fn confirm(&self, confirmation_link: &str) -> Result<(), anyhow::Error> {
+        let response = self.client.get(confirmation_link).send().await?;
+        if !response.status().is_success() && !response.status().is_redirection() {
+            bail!("Confirming failed with status {}.", response.status());
+        }
+        Ok(())
+    }
+
+    /// Publish a text issue to the list via the JSON API and return its id.
+    pub async fn publish_issue(
+        &self,
+        title: &str,
+        text_content: &str,
+        list_id: Uuid,
+    ) -> Result<Uuid, anyhow::Error> {
+        let response = self
+            .client
+            .post(format!("{}/api/v1/issues", self.base_url))
+            .bearer_auth(self.api_token.expose_secret())
+            .json(&serde_json::json!({
+                "title": title,
+                "text_content": text_content,
+                "html_content": format!("<p>{}</p>", text_content),
+                "list_id": list_id,
+            }))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            bail!(
+                "Publishing failed with status {}: {}",
+                status,
+                response.text().await.unwrap_or_default()
+            );
+        }
+        let published: serde_json::Value = response.json().await?;
+        published["issue_id"]
+            .as_str()
+            .and_then(|id| Uuid::parse_str(id).ok())
+            .context("Response of publishing has no issue id.")
+    }
+
+    pub async fn delivery_stats(&self, issue_id: Uuid) -> Result<DeliveryStats, anyhow::Error> {
+        let query = format!(
+            r#"{{ issue(id: "{}") {{ deliveryStats {{ numCurrentSubscribers numDelivered numFailed numPending }} }} }}"#,
+            issue_id
+        );
+        let response: serde_json::Value = self
+            .client
+            .post(format!("{}/admin/api/graphql", self.base_url))
+            .bearer_auth(self.api_token.expose_secret())
+            .json(&serde_json::json!({ "query": query }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let stats = &response["data"]["issue"]["deliveryStats"];
+        if stats.is_null() {
+            bail!(
+                "Delivery stats of issue {} are missing: {}",
+                issue_id,
+                response
+            );
+        }
+        Ok(serde_json::from_value(stats.clone())?)
+    }
+}
+
+#[derive(serde::Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct DeliveryStats {
+    pub num_current_subscribers: i32,
+    pub num_delivered: i32,
+    pub num_failed: i32,
+    pub num_pending: i64,
+}
+
+/// Test inbox provider with the Mailpit API, which catches emails of its domain.
+pub struct TestInbox {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl TestInbox {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Text of the latest email to the address, whose subject contains `subject`.
+    pub async fn find_email(
+        &self,
+        to: &str,
+        subject: &str,
+    ) -> Result<Option<String>, anyhow::Error> {
+        let search: serde_json::Value = self
+            .client
+            .get(format!("{}/api/v1/search", self.base_url))
+            .query(&[("query", format!("to:\"{}\"", to))])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let message_id = search["messages"].as_array().and_then(|messages| {
+            messages
+                .iter()
+                .find(|m| m["Subject"].as_str().is_some_and(|s| s.contains(subject)))
+                .and_then(|m| m["ID"].as_str())
+        });
+        let Some(message_id) = message_id else {
+            return Ok(None);
+        };
+        let message: serde_json::Value = self
+            .client
+            .get(format!("{}/api/v1/message/{}", self.base_url, message_id))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(message["Text"].as_str().map(str::to_string))
+    }
+}
+
+/// Confirmation link in the text of a confirmation email.
+pub fn confirmation_link(text: &str) -> Option<&str> {
+    let path = text.find("/subscriptions/confirm?")?;
+    let start = text[..path].rfind("http")?;
+    let end = text[start..]
+        .find(|c: char| c.is_whitespace() || c == '"' || c == '<' || c == '>')
+        .map_or(text.len(), |end| start + end);
+    Some(&text[start..end])
+}
+
+/// Poll `check` every two seconds until it returns a value or the timeout elapses.
+async fn wait_for<T, F, Fut>(
+    timeout: Duration,
+    what: &str,
+    mut check: F,
+) -> Result<T, anyhow::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Option<T>, anyhow::Error>>,
+{
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        if let Some(value) = check().await? {
+            return Ok(value);
+        }
+        if tokio::time::Instant::now() >= deadline {
+            bail!("Timed out waiting for {}.", what);
+        }
+        tokio::time::sleep(Duration::from_secs(2)).await;
+    }
+}
+
+/// Subscribe a fresh test inbox address, confirm it, publish an issue to the smoke test
+/// list and verify, that it is delivered.
+pub async fn run_smoke_test(settings: &SmokeTestSettings) -> Result<(), anyhow::Error> {
+    let client = SmokeTestClient::new(&settings.base_url, settings.api_token.clone());
+    let inbox = TestInbox::new(&settings.inbox_url);
+    let timeout = Duration::from_secs(settings.timeout_seconds);
+    let run_id = Uuid::new_v4();
+    let email = format!("smoketest-{}@{}", run_id, settings.inbox_domain);
+
+    client
+        .health_check()
+        .await
+        .context("Instance is not healthy.")?;
+    tracing::info!("Instance is healthy.");
+
+    client.subscribe(&email, settings.list_id).await?;
+    let confirmation_email = wait_for(timeout, "confirmation email", || {
+        inbox.find_email(&email, "Welcome")
+    })
+    .await?;
+    let link = confirmation_link(&confirmation_email)
+        .context("Confirmation email has no confirmation link.")?;
+    client.confirm(link).await?;
+    tracing::info!(email, "Subscribed and confirmed test inbox address.");
+
+    let title = format!("Smoke test {}", run_id);
+    let issue_id = client
+        .publish_issue(&title, "Smoke test issue.", settings.list_id)
+        .await?;
+    tracing::info!(%issue_id, "Published smoke test issue.");
+
+    let stats = wait_for(timeout, "delivery of smoke test issue", || async {
+        let stats = client.delivery_stats(issue_id).await?;
+        Ok((stats.num_pending == 0).then_some(stats))
+    })
+    .await?;
+    if stats.num_current_subscribers < 1 || stats.num_delivered < 1 || stats.num_failed > 0 {
+        bail!("Unexpected delivery stats of smoke test issue: {:?}", stats);
+    }
+    wait_for(timeout, "smoke test issue in test inbox", || {
+        inbox.find_email(&email, &title)
+    })
+    .await?;
+    tracing::info!(?stats, "Smoke test issue has been delivered.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confirmation_link_is_found_in_text_and_html() {
+        let link = "https://staging.example.com/subscriptions/confirm?subscription_token=abc";
+        assert_eq!(
+            confirmation_link(&format!("Welcome!\nVisit {} to confirm.", link)),
+            Some(link)
+        );
+        assert_eq!(
+            confirmation_link(&format!(r#"<a href="{}">here</a>"#, link)),
+            Some(link)
+        );
+        assert_eq!(confirmation_link("Welcome!"), None);
+    }
+}
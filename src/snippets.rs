@@ -0,0 +1,321 @@
+//! src/snippets.rs
+
+use std::collections::{BTreeSet, HashMap};
+
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+const PLACEHOLDER_START: &str = "{{snippet:";
+const PLACEHOLDER_END: &str = "}}";
+pub const MAX_SNIPPET_NAME_LENGTH: usize = 64;
+
+/// Names of snippets consist of ASCII letters, digits, `-` and `_`.
+pub fn is_valid_snippet_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_SNIPPET_NAME_LENGTH
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Names of all snippets referenced by `{{snippet:name}}` placeholders of `content`.
+pub fn snippet_names(content: &str) -> BTreeSet<&str> {
+    let mut names = BTreeSet::new();
+    let mut rest = content;
+    while let Some(start) = rest.find(PLACEHOLDER_START) {
+        rest = &rest[start + PLACEHOLDER_START.len()..];
+        if let Some(end) = rest.find(PLACEHOLDER_END) {
+            let name = &rest[..end];
+            if is_valid_snippet_name(name) {
+                names.insert(name);
+            }
+        }
+    }
+    names
+}
+
+/// Names of snippets referenced by any of `contents`, in order of their name.
+pub fn referenced_snippets(contents: &[&str]) -> Vec<String> {
+    contents
+        .iter()
+        .flat_map(|content| snippet_names(content))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(str::to_string)
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct SnippetContent {
+    pub text_content: String,
+    pub html_content: String,
+}
+
+/// Snippets by name, which replace placeholders of issue content.
+#[derive(Debug, Default)]
+pub struct Snippets(HashMap<String, SnippetContent>);
+
+impl Snippets {
+    pub fn resolve_text(&self, content: &str) -> String {
+        self.resolve(content, |snippet| &snippet.text_content)
+    }
+
+    pub fn resolve_html(&self, content: &str) -> String {
+        self.resolve(content, |snippet| &snippet.html_content)
+    }
+
+    /// Replace placeholders of known snippets; other placeholders are kept as they are.
+    fn resolve(&self, content: &str, select: impl Fn(&SnippetContent) -> &str) -> String {
+        let mut resolved = String::with_capacity(content.len());
+        let mut rest = content;
+        while let Some(start) = rest.find(PLACEHOLDER_START) {
+            resolved.push_str(&rest[..start]);
+            let placeholder = &rest[start..];
+            let snippet = placeholder[PLACEHOLDER_START.len()..]
+                .find(PLACEHOLDER_END)
+                .map(|end| {
+                    let name = &placeholder[PLACEHOLDER_START.len()..][..end];
+                    (name, PLACEHOLDER_START.len() + end + PLACEHOLDER_END.len())
+                })
+                .and_then(|(name, len)| self.0.get(name).map(|snippet| (snippet, len)));
+            match snippet {
+                Some((snippet, len)) => {
+                    resolved.push_str(select(snippet));
+                    rest = &placeholder[len..];
+                }
+                None => {
+                    resolved.push_str(PLACEHOLDER_START);
+                    rest = &placeholder[PLACEHOLDER_START.len()..];
+                }
+            }
+        }
+        resolved.push_str(rest);
+        resolved
+    }
+}
+
+/// Referenced snippets, which do not exist, in order of their name.
+#[tracing::instrument(name = "Find unknown snippets", skip(executor))]
+pub async fn unknown_snippets<'e>(
+    executor: impl PgExecutor<'e>,
+    names: &[String],
+) -> Result<Vec<String>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT name AS "name!"
+        FROM UNNEST($1::text[]) AS referenced(name)
+        WHERE NOT EXISTS (SELECT 1 FROM snippets s WHERE s.name = referenced.name)
+        ORDER BY name
+        "#,
+        names,
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(rows.into_iter().map(|r| r.name).collect())
+}
+
+/// Latest version of referenced snippets, e.g. for previews.
+#[tracing::instrument(name = "Get current snippets", skip(executor))]
+pub async fn get_current_snippets<'e>(
+    executor: impl PgExecutor<'e>,
+    names: &[String],
+) -> Result<Snippets, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT DISTINCT ON (name) name, text_content, html_content
+        FROM snippet_versions
+        WHERE name = ANY($1)
+        ORDER BY name, version DESC
+        "#,
+        names,
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(Snippets(
+        rows.into_iter()
+            .map(|r| {
+                (
+                    r.name,
+                    SnippetContent {
+                        text_content: r.text_content,
+                        html_content: r.html_content,
+                    },
+                )
+            })
+            .collect(),
+    ))
+}
+
+/// Pin referenced snippets of an issue to their latest version, when they are
+/// rendered for the first time, and return the pinned versions. Later versions of
+/// a snippet do not change emails of issues, which are already being delivered.
+/// Pinned snippets are also the record of snippet usage.
+#[tracing::instrument(name = "Pin snippets of issue", skip(pool))]
+pub async fn pin_issue_snippets(
+    pool: &PgPool,
+    issue_id: Uuid,
+    names: &[String],
+) -> Result<Snippets, sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO newsletter_issue_snippets (newsletter_issue_id, name, version, pinned_at)
+        SELECT DISTINCT ON (name) $1, name, version, now()
+        FROM snippet_versions
+        WHERE name = ANY($2)
+        ORDER BY name, version DESC
+        ON CONFLICT (newsletter_issue_id, name) DO NOTHING
+        "#,
+        issue_id,
+        names,
+    )
+    .execute(pool)
+    .await?;
+    let rows = sqlx::query!(
+        r#"
+        SELECT v.name, v.text_content, v.html_content
+        FROM newsletter_issue_snippets p
+        JOIN snippet_versions v ON v.name = p.name AND v.version = p.version
+        WHERE p.newsletter_issue_id = $1 AND p.name = ANY($2)
+        "#,
+        issue_id,
+        names,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(Snippets(
+        rows.into_iter()
+            .map(|r| {
+                (
+                    r.name,
+                    SnippetContent {
+                        text_content: r.text_content,
+                        html_content: r.html_content,
+                    },
+                )
+            })
+            .collect(),
+    ))
+}
+
+/// Latest version of a snippet with the number of issues, which use any version of it.
+pub struct SnippetOverview {
+    pub name: String,
+    pub version: i32,
+    pub text_content: String,
+    pub html_content: String,
+    pub updated_at: DateTime<Utc>,
+    pub num_issues: i64,
+}
+
+#[tracing::instrument(name = "Get snippet overviews", skip(pool))]
+pub async fn get_snippet_overviews(pool: &PgPool) -> Result<Vec<SnippetOverview>, sqlx::Error> {
+    sqlx::query_as!(
+        SnippetOverview,
+        r#"
+        SELECT DISTINCT ON (v.name)
+            v.name,
+            v.version,
+            v.text_content,
+            v.html_content,
+            v.created_at AS updated_at,
+            (
+                SELECT COUNT(*)
+                FROM newsletter_issue_snippets p
+                WHERE p.name = v.name
+            ) AS "num_issues!"
+        FROM snippet_versions v
+        ORDER BY v.name, v.version DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Store content as new version of the snippet, which is created if it does not
+/// exist, and return the version.
+#[tracing::instrument(name = "Save snippet", skip(pool, text_content, html_content))]
+pub async fn save_snippet(
+    pool: &PgPool,
+    name: &str,
+    text_content: &str,
+    html_content: &str,
+) -> Result<i32, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO snippets (name, created_at)
+        VALUES ($1, now())
+        ON CONFLICT (name) DO NOTHING
+        "#,
+        name,
+    )
+    .execute(&mut *transaction)
+    .await?;
+    // lock snippet to number concurrent versions in order
+    sqlx::query!("SELECT name FROM snippets WHERE name = $1 FOR UPDATE", name)
+        .fetch_one(&mut *transaction)
+        .await?;
+    let version = sqlx::query!(
+        r#"
+        INSERT INTO snippet_versions (name, version, text_content, html_content, created_at)
+        SELECT $1, COALESCE(MAX(version), 0) + 1, $2, $3, now()
+        FROM snippet_versions
+        WHERE name = $1
+        RETURNING version
+        "#,
+        name,
+        text_content,
+        html_content,
+    )
+    .fetch_one(&mut *transaction)
+    .await?
+    .version;
+    transaction.commit().await?;
+    Ok(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snippets() -> Snippets {
+        Snippets(HashMap::from([(
+            "signature".to_string(),
+            SnippetContent {
+                text_content: "Cheers, Jane".to_string(),
+                html_content: "<p>Cheers, <b>Jane</b></p>".to_string(),
+            },
+        )]))
+    }
+
+    #[test]
+    fn snippet_names_finds_valid_names_once() {
+        let content = "{{snippet:signature}} {{snippet:legal_2024}} {{snippet:signature}} \
+            {{snippet:not valid}} {{snippet:unterminated";
+        let names: Vec<_> = snippet_names(content).into_iter().collect();
+        assert_eq!(names, vec!["legal_2024", "signature"]);
+    }
+
+    #[test]
+    fn known_snippets_are_resolved_for_text_and_html() {
+        let snippets = snippets();
+        assert_eq!(
+            snippets.resolve_text("Bye.\n{{snippet:signature}}"),
+            "Bye.\nCheers, Jane"
+        );
+        assert_eq!(
+            snippets.resolve_html("<p>Bye.</p>{{snippet:signature}}"),
+            "<p>Bye.</p><p>Cheers, <b>Jane</b></p>"
+        );
+    }
+
+    #[test]
+    fn unknown_placeholders_are_kept() {
+        let content = "{{snippet:promo}} {{snippet:{{snippet:signature}} {{snippet:";
+        assert_eq!(
+            snippets().resolve_text(content),
+            "{{snippet:promo}} {{snippet:Cheers, Jane {{snippet:"
+        );
+    }
+}
@@ -1,39 +1,115 @@
 //! src/startup.rs
 
-use crate::authentication::reject_anonymous_users;
-use crate::configuration::{DatabaseSettings, Settings};
+use crate::api_rate_limit::ApiRateLimiter;
+use crate::attachment_scan::AttachmentScanner;
+use crate::authentication::{
+    reject_invalid_api_keys, reject_invalid_scim_tokens, reject_unauthorized_admin_api_calls,
+    reject_unauthorized_users, reject_unauthorized_webhooks, OidcClient, PasskeyRelyingParty,
+};
+use crate::config_reload::TunableSettings;
+use crate::configuration::{
+    ApplicationSettings, CorsSettings, DatabaseSettings, DeliveryQueueSettings,
+    IdempotencyStoreSettings, ProviderPlanSettings, Settings, WarmUpSettings,
+};
+use crate::delivery_queue::RedisDeliveryQueue;
 use crate::email_client::EmailClient;
 use crate::error::{Error, Z2PResult};
+use crate::idempotency::RedisIdempotencyStore;
+use crate::instance_setup::{apply_instance_settings, needs_setup, setup_token};
+use crate::metrics::{ConfirmationEmailMetrics, IdempotencyMetrics};
+use crate::migration_check::{verify_schema, MIGRATOR};
 use crate::routes::{
-    admin_dashboard, change_password, change_password_form, confirm, delivery_overview,
-    health_check, home, log_out, login, login_form, publish_newsletter, publish_newsletter_form,
-    subscribe, subscription_form, subscription_token, unsubscribe,
+    acknowledge_seed_test, add_suppression, admin_dashboard, admin_graphql, admin_sessions,
+    admin_users, api_docs, api_tokens, bounce_notification, build_admin_schema, cancel_newsletter,
+    change_delivery, change_email, change_email_form, change_password, change_password_form,
+    confirm, content_snippets, create_api_token, create_list, create_recurring_issue,
+    create_scim_user, deactivate_user, deep_health_check, delete_newsletter,
+    delete_newsletter_variant, delete_scim_user, delete_suppression, delivery_comparison,
+    delivery_overview, edit_newsletter, edit_newsletter_form, embed_latest, export_subscribers,
+    feedback_form, forgot_password, forgot_password_form, get_scim_user, health_check, home,
+    import_subscribers, inbound_email, invite_user, issue_calendar, issue_details, issue_trace,
+    list_scim_users, liveness, log_out, login, login_form, mailing_lists, migration_status,
+    newsletter_drafts, newsletter_variants, notification_preferences, oidc_callback, oidc_login,
+    openapi_json, passkey_login, passkey_login_options, passkey_registration_options, passkeys,
+    patch_scim_user, pause_recurring_issue, preferences_form, preview_newsletter, publish_issue,
+    publish_newsletter, publish_newsletter_form, reactivate_user, readiness, recurring_issues,
+    register_passkey, remove_passkey, replace_scim_user, resend_confirmation, reset_password,
+    reset_password_form, resume_recurring_issue, revoke_all_sessions, revoke_session, revoke_token,
+    save_content_snippet, save_newsletter_draft, save_newsletter_variant, save_notifications,
+    save_preferences, send_seed_test, send_test_newsletter, setup, setup_form, simulate_newsletter,
+    skip_recurring_issue, submit_feedback, subscribe, subscriber_data, subscriber_details,
+    subscriber_import_form, subscribers, subscription_form, subscription_token, suppressions,
+    system_status, track_open, unsubscribe, worker_health_check, workers, ChecklistItem,
+    MAX_IMPORT_FILE_BYTES, MAX_NEWSLETTER_FORM_BYTES,
 };
+use actix_cors::Cors;
+use actix_multipart::form::MultipartFormConfig;
 use actix_session::{storage::RedisSessionStore, SessionMiddleware};
-use actix_web::{cookie::Key, dev::Server, web, web::Data, App, HttpServer};
+use actix_web::{
+    cookie::Key,
+    dev::{Server, ServerHandle},
+    middleware::Condition,
+    web,
+    web::Data,
+    App, HttpServer,
+};
 use actix_web_flash_messages::{storage::CookieMessageStore, FlashMessagesFramework};
 use actix_web_lab::middleware::from_fn;
 use anyhow::Context;
 use secrecy::{ExposeSecret, Secret};
 use sqlx::{postgres::PgPoolOptions, PgPool};
 use std::net::TcpListener;
+use std::time::Duration;
+use tokio::sync::watch;
 use tracing_actix_web::TracingLogger;
 
 pub struct Application {
     port: u16,
     server: Server,
+    tunables: watch::Sender<TunableSettings>,
 }
 
 impl Application {
-    pub async fn build(configuration: Settings) -> Z2PResult<Self> {
+    pub async fn build(mut configuration: Settings) -> Z2PResult<Self> {
         let connection_pool = get_connection_pool(&configuration.database);
         // migrate production database
-        sqlx::migrate!("./migrations")
+        MIGRATOR
             .run(&connection_pool)
             .await
             .context("Failed to migrate the database.")?;
+        // fail fast instead of failing later in request handlers
+        verify_schema(&connection_pool).await?;
+        // settings of the initial setup override the configuration
+        apply_instance_settings(&mut configuration, &connection_pool).await?;
+        let is_setup_token_configured = configuration.application.setup_token.is_some();
+        let setup_token = setup_token(configuration.application.setup_token.take());
+        if needs_setup(&connection_pool)
+            .await
+            .context("Failed to check for initial setup.")?
+        {
+            if is_setup_token_configured {
+                tracing::warn!(
+                    "No user exists yet, complete the initial setup at {}/setup?token=<configured setup token>.",
+                    configuration.application.base_url
+                );
+            } else {
+                tracing::warn!(
+                    "No user exists yet, complete the initial setup at {}/setup?token={}.",
+                    configuration.application.base_url,
+                    setup_token.expose_secret()
+                );
+            }
+        }
+        configuration.application.setup_token = Some(setup_token);
 
+        let (tunables, tunables_receiver) = watch::channel(TunableSettings::from(&configuration));
+        let external_queue = get_external_delivery_queue(&configuration, &connection_pool).await?;
+        let idempotency_store = get_external_idempotency_store(&configuration).await?;
+        let warm_up = configuration.emailclient.warm_up.clone();
+        let provider_plan = configuration.emailclient.plan.clone();
         let email_client = configuration.emailclient.client();
+        let attachment_scanner = AttachmentScanner::new(configuration.attachment_scan);
+        let oidc_client = OidcClient::new(configuration.oidc, &configuration.application.base_url);
         let address = format!(
             "{}:{}",
             configuration.application.host, configuration.application.port
@@ -44,19 +120,41 @@ impl Application {
             listener,
             connection_pool,
             email_client,
-            configuration.application.base_url,
-            configuration.application.hmac_secret,
+            attachment_scanner,
+            oidc_client,
+            external_queue,
+            idempotency_store,
+            warm_up,
+            provider_plan,
+            configuration.application,
             configuration.redis_uri,
+            tunables_receiver,
         )
         .await?;
 
-        Ok(Self { port, server })
+        Ok(Self {
+            port,
+            server,
+            tunables,
+        })
     }
 
     pub fn port(&self) -> u16 {
         self.port
     }
 
+    /// Channel of tunable settings, which publishes reloaded settings to the server
+    /// and, via its receivers, to the workers.
+    pub fn tunable_settings(&self) -> watch::Sender<TunableSettings> {
+        self.tunables.clone()
+    }
+
+    /// Handle to stop the server; a graceful stop drains in-flight requests.
+    pub fn shutdown_handle(&self) -> ServerHandle {
+        self.server.handle()
+    }
+
+    /// Run the server, until it is stopped via its shutdown handle.
     pub async fn run_until_stopped(self) -> Z2PResult<()> {
         self.server
             .await
@@ -65,32 +163,201 @@ impl Application {
     }
 }
 
+/// Wait for ctrl-c or SIGTERM, e.g. of a container runtime.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!(error.message = %e, "Failed to listen for ctrl-c.");
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!(error.message = %e, "Failed to listen for SIGTERM.");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+/// CORS of public routes, which other sites may call, if configured. Requests of
+/// other origins are not rejected, but their responses are not readable by the caller.
+pub fn public_cors(settings: Option<&CorsSettings>) -> Condition<Cors> {
+    Condition::new(
+        settings.is_some(),
+        settings.map(CorsSettings::cors).unwrap_or_default(),
+    )
+}
+
 pub fn get_connection_pool(configuration: &DatabaseSettings) -> PgPool {
     PgPoolOptions::new().connect_lazy_with(configuration.with_db())
 }
 
+pub async fn get_external_delivery_queue(
+    configuration: &Settings,
+    pool: &PgPool,
+) -> Z2PResult<ExternalDeliveryQueue> {
+    let queue = match configuration.delivery_queue {
+        DeliveryQueueSettings::Postgres => None,
+        DeliveryQueueSettings::Redis {
+            ref key_prefix,
+            lease_seconds,
+        } => Some(
+            RedisDeliveryQueue::connect(
+                configuration.redis_uri.expose_secret(),
+                key_prefix,
+                Duration::from_secs(lease_seconds),
+                pool.clone(),
+            )
+            .await?,
+        ),
+    };
+    Ok(ExternalDeliveryQueue(queue))
+}
+
+pub async fn get_external_idempotency_store(
+    configuration: &Settings,
+) -> Z2PResult<ExternalIdempotencyStore> {
+    let store = match configuration.idempotency_store {
+        IdempotencyStoreSettings::Postgres => None,
+        IdempotencyStoreSettings::Redis {
+            ref key_prefix,
+            lease_seconds,
+        } => Some(
+            RedisIdempotencyStore::connect(
+                configuration.redis_uri.expose_secret(),
+                key_prefix,
+                Duration::from_secs(
+                    u64::from(configuration.application.idempotency_lifetime_minutes) * 60,
+                ),
+                // requests, which are in flight at shutdown, must keep their reservation
+                Duration::from_secs(
+                    lease_seconds.max(configuration.application.shutdown_timeout_seconds),
+                ),
+            )
+            .await?,
+        ),
+    };
+    Ok(ExternalIdempotencyStore(store))
+}
+
 // We need to define a wrapper type in order to retrieve the URL
 // in the `subscribe` handler.
 // Retrieval from the context, in actix-web, is type-based: using
 // a raw `String` would expose us to conflicts.
 pub struct ApplicationBaseUrl(pub String);
 
+// Secret to authenticate calls of the email provider's webhooks
+pub struct WebhookSecret(pub Secret<String>);
+
+// Key to authenticate calls of the integration API
+pub struct ApiKey(pub Secret<String>);
+
+// Token, which the initial setup requires
+pub struct SetupToken(pub Secret<String>);
+
+// Token of the identity provider, which provisions admin users via SCIM, if configured
+pub struct ScimToken(pub Option<Secret<String>>);
+
+// Delivery queue outside of the database, if configured instead of the Postgres queue
+pub struct ExternalDeliveryQueue(pub Option<RedisDeliveryQueue>);
+
+// Store of idempotency keys outside of the database, if configured instead of Postgres
+pub struct ExternalIdempotencyStore(pub Option<RedisIdempotencyStore>);
+
+// Minutes, after which idempotency keys are outlived
+pub struct IdempotencyLifetime(pub u32);
+
+// Client of the Redis session store, which readiness probes ping
+pub struct SessionStoreClient(pub redis::Client);
+
+// Maximum number of newsletter emails per subscriber within 7 days, if configured
+pub struct FrequencyCap(pub Option<u32>);
+
+// Items of the pre-publish checklist; empty if publishing is not gated
+pub struct PublishChecklist(pub Vec<ChecklistItem>);
+
+// Addresses of seed mailboxes, which receive seed tests of issues before publishing
+pub struct SeedAddresses(pub Vec<String>);
+
+// Minutes after publishing, in which an issue can be cancelled; 0 if disabled
+pub struct UndoWindow(pub u32);
+
+// Warm-up schedule of the delivery worker to estimate delivery durations
+pub struct SendRateLimits(pub Option<WarmUpSettings>);
+
+// Plan of the email provider to track the monthly send volume against, if configured
+pub struct ProviderPlan(pub Option<ProviderPlanSettings>);
+
+#[allow(clippy::too_many_arguments)]
 async fn run(
     listener: TcpListener,
     db_pool: PgPool,
     email_client: EmailClient,
-    base_url: String,
-    hmac_secret: Secret<String>,
+    attachment_scanner: AttachmentScanner,
+    oidc_client: OidcClient,
+    external_queue: ExternalDeliveryQueue,
+    idempotency_store: ExternalIdempotencyStore,
+    warm_up: Option<WarmUpSettings>,
+    provider_plan: Option<ProviderPlanSettings>,
+    application: ApplicationSettings,
     redis_uri: Secret<String>,
+    tunables: watch::Receiver<TunableSettings>,
 ) -> Z2PResult<Server> {
     // Wrap the database pool and email client in a smart pointer
     let db_pool = Data::new(db_pool);
     let email_client = Data::new(email_client);
-    let base_url = Data::new(ApplicationBaseUrl(base_url));
-    let secret_key = Key::from(hmac_secret.expose_secret().as_bytes());
+    let attachment_scanner = Data::new(attachment_scanner);
+    let oidc_client = Data::new(oidc_client);
+    let passkey_relying_party = Data::new(PasskeyRelyingParty::new(&application.base_url));
+    let external_queue = Data::new(external_queue);
+    let idempotency_store = Data::new(idempotency_store);
+    let idempotency_lifetime = Data::new(IdempotencyLifetime(
+        application.idempotency_lifetime_minutes,
+    ));
+    let idempotency_metrics = Data::new(IdempotencyMetrics::default());
+    let base_url = Data::new(ApplicationBaseUrl(application.base_url));
+    let webhook_secret = Data::new(WebhookSecret(application.webhook_secret));
+    let api_key = Data::new(ApiKey(application.api_key));
+    let scim_token = Data::new(ScimToken(application.scim_token));
+    let setup_token = Data::new(SetupToken(
+        application.setup_token.context("Setup token is not set.")?,
+    ));
+    let api_rate_limiter = Data::new(ApiRateLimiter::new(tunables));
+    let send_rate_limits = Data::new(SendRateLimits(warm_up));
+    let provider_plan = Data::new(ProviderPlan(provider_plan));
+    let frequency_cap = Data::new(FrequencyCap(application.max_emails_per_subscriber_per_week));
+    let publish_checklist = Data::new(PublishChecklist(application.publish_checklist.clone()));
+    let seed_addresses = Data::new(SeedAddresses(application.seed_addresses.clone()));
+    let undo_window = Data::new(UndoWindow(application.undo_window_minutes));
+    let welcome_issue = Data::new(application.welcome_issue.clone());
+    let email_size_budget = Data::new(application.email_size_budget.clone());
+    let login_throttle = Data::new(application.login_throttle.clone());
+    let locale_fallbacks = Data::new(application.locale_fallbacks.clone());
+    let admin_schema = Data::new(build_admin_schema());
+    let confirmation_metrics = Data::new(ConfirmationEmailMetrics::new(Duration::from_millis(
+        application.confirmation_latency_slo_milliseconds,
+    )));
+    let secret_key = Key::from(application.hmac_secret.expose_secret().as_bytes());
     let message_store = CookieMessageStore::builder(secret_key.clone()).build();
     let message_framework = FlashMessagesFramework::builder(message_store).build();
     let redis_store = RedisSessionStore::new(redis_uri.expose_secret()).await?;
+    let session_store = Data::new(SessionStoreClient(
+        redis::Client::open(redis_uri.expose_secret().as_str()).context("Invalid redis uri.")?,
+    ));
+    let shutdown_timeout = application.shutdown_timeout_seconds;
+    let cors = application.cors.clone();
     let server = HttpServer::new(move || {
         App::new()
             .wrap(message_framework.clone())
@@ -102,27 +369,241 @@ async fn run(
             .route("/", web::get().to(home))
             .route("/login", web::get().to(login_form))
             .route("/login", web::post().to(login))
+            .route("/login/oidc", web::get().to(oidc_login))
+            .route("/login/oidc/callback", web::get().to(oidc_callback))
+            .route("/login/passkey", web::post().to(passkey_login))
+            .route(
+                "/login/passkey/options",
+                web::post().to(passkey_login_options),
+            )
+            .route("/login/forgot", web::get().to(forgot_password_form))
+            .route("/login/forgot", web::post().to(forgot_password))
+            .route("/login/reset", web::get().to(reset_password_form))
+            .route("/login/reset", web::post().to(reset_password))
             .route("/health_check", web::get().to(health_check))
-            .route("/subscriptions", web::get().to(subscription_form))
-            .route("/subscriptions", web::post().to(subscribe))
-            .route("/subscriptions/token", web::get().to(subscription_token))
-            .route("/subscriptions/confirm", web::get().to(confirm))
-            .route("/subscriptions/unsubscribe", web::get().to(unsubscribe))
+            .route("/health_check/workers", web::get().to(worker_health_check))
+            .route("/health_check/deep", web::get().to(deep_health_check))
+            .route("/live", web::get().to(liveness))
+            .route("/ready", web::get().to(readiness))
+            .route("/setup", web::get().to(setup_form))
+            .route("/setup", web::post().to(setup))
+            .service(
+                web::scope("/subscriptions")
+                    .wrap(public_cors(cors.as_ref()))
+                    .route("", web::get().to(subscription_form))
+                    .route("", web::post().to(subscribe))
+                    .route("/token", web::get().to(subscription_token))
+                    .route("/confirm", web::get().to(confirm))
+                    .route("/resend", web::post().to(resend_confirmation))
+                    .route("/unsubscribe", web::get().to(unsubscribe))
+                    .route("/data", web::get().to(subscriber_data)),
+            )
+            .route("/feedback/{issue_id}", web::get().to(feedback_form))
+            .route("/feedback/{issue_id}", web::post().to(submit_feedback))
+            .route("/open/{issue_id}", web::get().to(track_open))
+            .route("/preferences", web::get().to(preferences_form))
+            .route("/preferences", web::post().to(save_preferences))
+            .route("/embed/latest", web::get().to(embed_latest))
+            .route("/api/openapi.json", web::get().to(openapi_json))
+            .route("/api/docs", web::get().to(api_docs))
+            // registered before the admin scope, which would reject API key calls
+            .service(
+                web::resource("/admin/api/graphql")
+                    .wrap(from_fn(reject_unauthorized_admin_api_calls))
+                    .route(web::post().to(admin_graphql)),
+            )
             .service(
                 web::scope("/admin")
-                    .wrap(from_fn(reject_anonymous_users))
+                    .wrap(from_fn(reject_unauthorized_users))
+                    // newsletter form may contain an attachment
+                    .app_data(web::FormConfig::default().limit(MAX_NEWSLETTER_FORM_BYTES))
+                    // CSV files of subscriber imports are kept in memory
+                    .app_data(
+                        MultipartFormConfig::default()
+                            .total_limit(MAX_IMPORT_FILE_BYTES)
+                            .memory_limit(MAX_IMPORT_FILE_BYTES),
+                    )
                     .route("/dashboard", web::get().to(admin_dashboard))
+                    .route("/api_tokens", web::get().to(api_tokens))
+                    .route("/api_tokens", web::post().to(create_api_token))
+                    .route(
+                        "/api_tokens/{token_id}/revoke",
+                        web::post().to(revoke_token),
+                    )
+                    .route("/security/passkeys", web::get().to(passkeys))
+                    .route("/security/passkeys", web::post().to(register_passkey))
+                    .route(
+                        "/security/passkeys/options",
+                        web::post().to(passkey_registration_options),
+                    )
+                    .route(
+                        "/security/passkeys/{passkey_id}/delete",
+                        web::post().to(remove_passkey),
+                    )
+                    .route("/notifications", web::get().to(notification_preferences))
+                    .route("/notifications", web::post().to(save_notifications))
+                    .route("/sessions", web::get().to(admin_sessions))
+                    .route("/sessions/revoke_all", web::post().to(revoke_all_sessions))
+                    .route(
+                        "/sessions/{session_id}/revoke",
+                        web::post().to(revoke_session),
+                    )
                     .route("/delivery_overview", web::get().to(delivery_overview))
+                    .route(
+                        "/delivery_overview/compare",
+                        web::get().to(delivery_comparison),
+                    )
+                    .route("/calendar", web::get().to(issue_calendar))
+                    .route(
+                        "/delivery_overview/delivery",
+                        web::post().to(change_delivery),
+                    )
                     .route("/newsletters", web::get().to(publish_newsletter_form))
                     .route("/newsletters", web::post().to(publish_newsletter))
+                    .route("/newsletters/draft", web::post().to(save_newsletter_draft))
+                    .route("/newsletters/drafts", web::get().to(newsletter_drafts))
+                    .route("/newsletters/test", web::post().to(send_test_newsletter))
+                    .route("/newsletters/preview", web::post().to(preview_newsletter))
+                    .route("/newsletters/simulate", web::post().to(simulate_newsletter))
+                    .route("/newsletters/seed_test", web::post().to(send_seed_test))
+                    .route("/newsletters/recurring", web::get().to(recurring_issues))
+                    .route(
+                        "/newsletters/recurring",
+                        web::post().to(create_recurring_issue),
+                    )
+                    .route(
+                        "/newsletters/recurring/{recurring_issue_id}/skip",
+                        web::post().to(skip_recurring_issue),
+                    )
+                    .route(
+                        "/newsletters/recurring/{recurring_issue_id}/pause",
+                        web::post().to(pause_recurring_issue),
+                    )
+                    .route(
+                        "/newsletters/recurring/{recurring_issue_id}/resume",
+                        web::post().to(resume_recurring_issue),
+                    )
+                    .route(
+                        "/newsletters/seed_test/{seed_test_id}/acknowledge",
+                        web::post().to(acknowledge_seed_test),
+                    )
+                    .route(
+                        "/newsletters/{issue_id}/edit",
+                        web::get().to(edit_newsletter_form),
+                    )
+                    .route(
+                        "/newsletters/{issue_id}/edit",
+                        web::post().to(edit_newsletter),
+                    )
+                    .route(
+                        "/newsletters/{issue_id}/delete",
+                        web::post().to(delete_newsletter),
+                    )
+                    .route(
+                        "/newsletters/{issue_id}/cancel",
+                        web::post().to(cancel_newsletter),
+                    )
+                    .route("/newsletters/{issue_id}/trace", web::get().to(issue_trace))
+                    .route(
+                        "/newsletters/{issue_id}/variants",
+                        web::get().to(newsletter_variants),
+                    )
+                    .route(
+                        "/newsletters/{issue_id}/variants",
+                        web::post().to(save_newsletter_variant),
+                    )
+                    .route(
+                        "/newsletters/{issue_id}/variants/{locale}/delete",
+                        web::post().to(delete_newsletter_variant),
+                    )
+                    .route("/subscribers", web::get().to(subscribers))
+                    .route("/subscribers/export.csv", web::get().to(export_subscribers))
+                    .route("/subscribers/import", web::get().to(subscriber_import_form))
+                    .route("/subscribers/import", web::post().to(import_subscribers))
+                    .route(
+                        "/subscribers/{subscriber_id}",
+                        web::get().to(subscriber_details),
+                    )
+                    .route("/suppressions", web::get().to(suppressions))
+                    .route("/suppressions", web::post().to(add_suppression))
+                    .route("/suppressions/delete", web::post().to(delete_suppression))
+                    .route("/users", web::get().to(admin_users))
+                    .route("/users", web::post().to(invite_user))
+                    .route(
+                        "/users/{user_id}/deactivate",
+                        web::post().to(deactivate_user),
+                    )
+                    .route(
+                        "/users/{user_id}/reactivate",
+                        web::post().to(reactivate_user),
+                    )
+                    .route("/workers", web::get().to(workers))
+                    .route("/system", web::get().to(system_status))
+                    .route("/lists", web::get().to(mailing_lists))
+                    .route("/lists", web::post().to(create_list))
+                    .route("/snippets", web::get().to(content_snippets))
+                    .route("/snippets", web::post().to(save_content_snippet))
                     .route("/password", web::get().to(change_password_form))
                     .route("/password", web::post().to(change_password))
+                    .route("/email", web::get().to(change_email_form))
+                    .route("/email", web::post().to(change_email))
                     .route("/logout", web::post().to(log_out)),
             )
+            .service(
+                web::scope("/webhooks")
+                    .wrap(from_fn(reject_unauthorized_webhooks))
+                    .route("/email/inbound", web::post().to(inbound_email))
+                    .route("/email/bounce", web::post().to(bounce_notification)),
+            )
+            .service(
+                web::scope("/api/v1")
+                    .wrap(from_fn(reject_invalid_api_keys))
+                    .route("/issues", web::post().to(publish_issue))
+                    .route("/issues/{issue_id}", web::get().to(issue_details))
+                    .route("/migrations", web::get().to(migration_status)),
+            )
+            .service(
+                web::scope("/scim/v2")
+                    .wrap(from_fn(reject_invalid_scim_tokens))
+                    .route("/Users", web::get().to(list_scim_users))
+                    .route("/Users", web::post().to(create_scim_user))
+                    .route("/Users/{user_id}", web::get().to(get_scim_user))
+                    .route("/Users/{user_id}", web::put().to(replace_scim_user))
+                    .route("/Users/{user_id}", web::patch().to(patch_scim_user))
+                    .route("/Users/{user_id}", web::delete().to(delete_scim_user)),
+            )
             .app_data(db_pool.clone())
             .app_data(email_client.clone())
+            .app_data(attachment_scanner.clone())
+            .app_data(oidc_client.clone())
+            .app_data(passkey_relying_party.clone())
+            .app_data(external_queue.clone())
+            .app_data(idempotency_store.clone())
+            .app_data(idempotency_lifetime.clone())
+            .app_data(idempotency_metrics.clone())
+            .app_data(session_store.clone())
+            .app_data(publish_checklist.clone())
+            .app_data(seed_addresses.clone())
+            .app_data(undo_window.clone())
+            .app_data(welcome_issue.clone())
+            .app_data(email_size_budget.clone())
+            .app_data(login_throttle.clone())
+            .app_data(locale_fallbacks.clone())
             .app_data(base_url.clone())
+            .app_data(webhook_secret.clone())
+            .app_data(api_key.clone())
+            .app_data(scim_token.clone())
+            .app_data(setup_token.clone())
+            .app_data(api_rate_limiter.clone())
+            .app_data(send_rate_limits.clone())
+            .app_data(provider_plan.clone())
+            .app_data(frequency_cap.clone())
+            .app_data(confirmation_metrics.clone())
+            .app_data(admin_schema.clone())
     })
+    // shutdown signals are handled by the caller via the handle of the server
+    .disable_signals()
+    .shutdown_timeout(shutdown_timeout)
     .listen(listener)
     .context("Failed to start listening on HttpServer.")?
     .run();
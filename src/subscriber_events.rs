@@ -0,0 +1,111 @@
+//! src/subscriber_events.rs
+
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+/// Events of a subscriber, which are assembled to a timeline for support staff.
+#[derive(
+    Debug, Clone, Copy, sqlx::Type, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[sqlx(type_name = "subscriber_event_kind", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriberEventKind {
+    Subscribed,
+    Confirmed,
+    ReceivedIssue,
+    DeliveryFailed,
+    Unsubscribed,
+    Opened,
+}
+
+pub struct SubscriberEvent {
+    pub kind: SubscriberEventKind,
+    pub issue_title: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl SubscriberEvent {
+    pub fn description(&self) -> String {
+        let issue_title = self.issue_title.as_deref().unwrap_or("unknown issue");
+        match self.kind {
+            SubscriberEventKind::Subscribed => "subscribed".to_string(),
+            SubscriberEventKind::Confirmed => "confirmed subscription".to_string(),
+            SubscriberEventKind::ReceivedIssue => format!("received issue \"{}\"", issue_title),
+            SubscriberEventKind::DeliveryFailed => {
+                format!("delivery of issue \"{}\" failed", issue_title)
+            }
+            SubscriberEventKind::Unsubscribed => "unsubscribed".to_string(),
+            SubscriberEventKind::Opened => format!("opened issue \"{}\"", issue_title),
+        }
+    }
+}
+
+/// Append event to timeline of subscriber. Pass a transaction as executor to record the
+/// event together with the change it describes.
+#[tracing::instrument(name = "Record subscriber event", skip(executor))]
+pub async fn record_subscriber_event<'e>(
+    executor: impl PgExecutor<'e>,
+    subscriber_id: Uuid,
+    kind: SubscriberEventKind,
+    newsletter_issue_id: Option<Uuid>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriber_events (subscriber_id, kind, newsletter_issue_id, occurred_at)
+        VALUES ($1, $2, $3, now())
+        "#,
+        subscriber_id,
+        kind as SubscriberEventKind,
+        newsletter_issue_id,
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// Record first open of an issue by a subscriber. Later opens of the same issue are
+/// ignored, so that rereading does not skew the learned send time.
+#[tracing::instrument(name = "Record open of issue", skip(pool))]
+pub async fn record_open(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+    newsletter_issue_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriber_events (subscriber_id, kind, newsletter_issue_id, occurred_at)
+        SELECT $1, 'opened', $2, now()
+        WHERE EXISTS (SELECT 1 FROM newsletter_issues WHERE newsletter_issue_id = $2)
+            AND NOT EXISTS (
+                SELECT 1 FROM subscriber_events
+                WHERE subscriber_id = $1 AND kind = 'opened' AND newsletter_issue_id = $2
+            )
+        "#,
+        subscriber_id,
+        newsletter_issue_id,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(name = "Get timeline of subscriber", skip(pool))]
+pub async fn get_subscriber_timeline(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<Vec<SubscriberEvent>, sqlx::Error> {
+    sqlx::query_as!(
+        SubscriberEvent,
+        r#"
+        SELECT e.kind AS "kind: SubscriberEventKind", i.title AS "issue_title?", e.occurred_at
+        FROM subscriber_events e
+        LEFT JOIN newsletter_issues i ON i.newsletter_issue_id = e.newsletter_issue_id
+        WHERE e.subscriber_id = $1
+        ORDER BY e.occurred_at, e.event_id
+        "#,
+        subscriber_id
+    )
+    .fetch_all(pool)
+    .await
+}
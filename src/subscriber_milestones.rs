@@ -0,0 +1,88 @@
+//! src/subscriber_milestones.rs
+
+use crate::email_client::EmailClient;
+use crate::error::Z2PResult;
+use crate::notifications::{notify_admins, AdminNotification};
+use crate::routes::SubscriptionsStatus;
+use anyhow::Context;
+use chrono::{DateTime, TimeDelta, Utc};
+use sqlx::PgPool;
+
+/// Numbers of confirmed subscribers, which are celebrated as milestones.
+pub const MILESTONES: [i32; 3] = [100, 1_000, 10_000];
+
+/// Reached milestones are shown on the admin dashboard for one week.
+const SHOW_MILESTONE_DAYS: i64 = 7;
+
+pub struct ReachedMilestone {
+    pub milestone: i32,
+    pub reached_at: DateTime<Utc>,
+}
+
+/// Record all milestones reached by the current number of confirmed subscribers.
+/// Each milestone is recorded only once, even if the number of subscribers drops
+/// below it and rises again. Admin users are notified about newly reached milestones.
+#[tracing::instrument(name = "Record reached subscriber milestones", skip_all)]
+pub async fn record_reached_milestones(pool: &PgPool, email_client: &EmailClient) -> Z2PResult<()> {
+    let num_confirmed = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM subscriptions
+        WHERE status = $1
+        "#,
+        SubscriptionsStatus::Confirmed as SubscriptionsStatus,
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to count confirmed subscribers.")?
+    .count;
+    for milestone in MILESTONES
+        .into_iter()
+        .filter(|m| *m as i64 <= num_confirmed)
+    {
+        let newly_reached = sqlx::query!(
+            r#"
+            INSERT INTO subscriber_milestones (milestone, reached_at)
+            VALUES ($1, now())
+            ON CONFLICT DO NOTHING
+            "#,
+            milestone,
+        )
+        .execute(pool)
+        .await
+        .context("Failed to record subscriber milestone.")?
+        .rows_affected()
+            == 1;
+        if newly_reached {
+            tracing::info!(milestone, "Subscriber milestone reached.");
+            notify_admins(
+                pool,
+                email_client,
+                &AdminNotification::SubscriberMilestone { milestone },
+            )
+            .await;
+        }
+    }
+    Ok(())
+}
+
+/// Highest milestone reached within the last week, if any.
+#[tracing::instrument(name = "Get recent subscriber milestone", skip(pool))]
+pub async fn get_recent_milestone(pool: &PgPool) -> Z2PResult<Option<ReachedMilestone>> {
+    let since = Utc::now() - TimeDelta::days(SHOW_MILESTONE_DAYS);
+    let milestone = sqlx::query_as!(
+        ReachedMilestone,
+        r#"
+        SELECT milestone, reached_at
+        FROM subscriber_milestones
+        WHERE reached_at > $1
+        ORDER BY milestone DESC
+        LIMIT 1
+        "#,
+        since,
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to read recent subscriber milestone.")?;
+    Ok(milestone)
+}
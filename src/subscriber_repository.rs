@@ -0,0 +1,246 @@
+//! src/subscriber_repository.rs
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::{SubscriberEmail, SubscriberName, SubscriberToken, ValidationError};
+use crate::error::Z2PResult;
+use crate::routes::SubscriptionsStatus;
+
+/// Subscriber data as stored in database.
+#[derive(Debug, Clone)]
+pub struct SubscriberRecord {
+    pub name: SubscriberName,
+    pub email: SubscriberEmail,
+    pub token: SubscriberToken,
+    pub subscribed_at: DateTime<Utc>,
+}
+
+/// Lookups of subscribers and their tokens. `PgPool` implements them with queries
+/// against the database; tests may use an in-memory implementation instead.
+// Implementations are only used with concrete types, therefore Send bounds of
+// returned futures are checked at call sites.
+#[allow(async_fn_in_trait)]
+pub trait SubscriberRepository {
+    /// Id of the subscription of `email` to mailing list `list_id`.
+    async fn subscriber_id_from_email(
+        &self,
+        list_id: Uuid,
+        email: &SubscriberEmail,
+    ) -> Z2PResult<Uuid>;
+
+    async fn subscriber_id_from_token(&self, token: &SubscriberToken) -> Z2PResult<Option<Uuid>>;
+
+    async fn token_from_subscriber_id(&self, subscriber_id: Uuid) -> Z2PResult<SubscriberToken>;
+
+    async fn status_from_subscriber_id(
+        &self,
+        subscriber_id: Uuid,
+    ) -> Z2PResult<SubscriptionsStatus>;
+
+    async fn subscriber_from_subscriber_id(
+        &self,
+        subscriber_id: Uuid,
+    ) -> Z2PResult<SubscriberRecord>;
+}
+
+/// Get id of subscriber with token. Unknown tokens are a validation error.
+pub async fn get_subscriber_id_of_known_token(
+    repository: &impl SubscriberRepository,
+    token: &SubscriberToken,
+) -> Z2PResult<Uuid> {
+    let subscriber_id = repository
+        .subscriber_id_from_token(token)
+        .await?
+        .ok_or_else(|| ValidationError::InvalidToken(token.as_ref().to_owned()))?;
+    Ok(subscriber_id)
+}
+
+impl SubscriberRepository for PgPool {
+    #[tracing::instrument(name = "Get subscriber id from email", skip_all)]
+    async fn subscriber_id_from_email(
+        &self,
+        list_id: Uuid,
+        email: &SubscriberEmail,
+    ) -> Z2PResult<Uuid> {
+        let result = sqlx::query!(
+            "SELECT id FROM subscriptions \
+            WHERE list_id = $1 AND email = $2",
+            list_id,
+            email.as_ref(),
+        )
+        .fetch_one(self)
+        .await
+        .context("Failed to read subscriber_id of email from database")?;
+        Ok(result.id)
+    }
+
+    #[tracing::instrument(name = "Get subscriber_id from token", skip_all)]
+    async fn subscriber_id_from_token(&self, token: &SubscriberToken) -> Z2PResult<Option<Uuid>> {
+        let result = sqlx::query!(
+            "SELECT subscriber_id FROM subscription_tokens
+            WHERE subscription_token = $1",
+            token.as_ref(),
+        )
+        .fetch_optional(self)
+        .await
+        .context("Failed to read subscriber_id of subscription_token from database.")?;
+        Ok(result.map(|r| r.subscriber_id))
+    }
+
+    #[tracing::instrument(name = "Get token from subscriber_id", skip_all)]
+    async fn token_from_subscriber_id(&self, subscriber_id: Uuid) -> Z2PResult<SubscriberToken> {
+        let result = sqlx::query!(
+            "SELECT subscription_token FROM subscription_tokens \
+            WHERE subscriber_id = $1",
+            subscriber_id,
+        )
+        .fetch_one(self)
+        .await
+        .context("Failed to read subscription_token of subscriber_id from database")?;
+        // use with_context instead of automatic validation error transformation, since
+        // invalid token has been read from database, which is an unexpected error.
+        let subscription_token = SubscriberToken::parse(result.subscription_token.clone())
+            .with_context(|| {
+                format!(
+                    "Read invalid subscription token `{}` from database.",
+                    result.subscription_token
+                )
+            })?;
+        Ok(subscription_token)
+    }
+
+    #[tracing::instrument(name = "Get status from subscriber_id", skip_all)]
+    async fn status_from_subscriber_id(
+        &self,
+        subscriber_id: Uuid,
+    ) -> Z2PResult<SubscriptionsStatus> {
+        let result = sqlx::query!(
+            "SELECT status AS \"status: SubscriptionsStatus\" FROM subscriptions \
+            WHERE id = $1",
+            subscriber_id,
+        )
+        .fetch_one(self)
+        .await
+        .context("Failed to read status of subscriber_id from database")?;
+        Ok(result.status)
+    }
+
+    #[tracing::instrument(
+        name = "Get name, email, token and subscribed_at from subscriber_id",
+        skip_all
+    )]
+    async fn subscriber_from_subscriber_id(
+        &self,
+        subscriber_id: Uuid,
+    ) -> Z2PResult<SubscriberRecord> {
+        let result = sqlx::query!(
+            "SELECT email, name, subscribed_at FROM subscriptions
+            WHERE id = $1",
+            subscriber_id,
+        )
+        .fetch_one(self)
+        .await
+        .context("Failed to read subscriber data of subscription_id from database.")?;
+        let token = self.token_from_subscriber_id(subscriber_id).await?;
+        Ok(SubscriberRecord {
+            name: SubscriberName::parse(result.name)?,
+            email: SubscriberEmail::parse(result.email)?,
+            token,
+            subscribed_at: result.subscribed_at,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use claims::{assert_err, assert_ok};
+
+    /// In-memory repository with a single confirmed subscriber.
+    struct MockSubscriberRepository {
+        id: Uuid,
+        record: SubscriberRecord,
+    }
+
+    impl MockSubscriberRepository {
+        fn new() -> Self {
+            Self {
+                id: Uuid::new_v4(),
+                record: SubscriberRecord {
+                    name: SubscriberName::parse("Jane Doe".to_string()).unwrap(),
+                    email: SubscriberEmail::parse("jane@example.com".to_string()).unwrap(),
+                    token: SubscriberToken::generate_subscription_token(),
+                    subscribed_at: Utc::now(),
+                },
+            }
+        }
+    }
+
+    impl SubscriberRepository for MockSubscriberRepository {
+        async fn subscriber_id_from_email(
+            &self,
+            _list_id: Uuid,
+            email: &SubscriberEmail,
+        ) -> Z2PResult<Uuid> {
+            if email.as_ref() == self.record.email.as_ref() {
+                Ok(self.id)
+            } else {
+                Err(anyhow::anyhow!("Unknown email."))?
+            }
+        }
+
+        async fn subscriber_id_from_token(
+            &self,
+            token: &SubscriberToken,
+        ) -> Z2PResult<Option<Uuid>> {
+            Ok((token.as_ref() == self.record.token.as_ref()).then_some(self.id))
+        }
+
+        async fn token_from_subscriber_id(
+            &self,
+            _subscriber_id: Uuid,
+        ) -> Z2PResult<SubscriberToken> {
+            Ok(self.record.token.clone())
+        }
+
+        async fn status_from_subscriber_id(
+            &self,
+            _subscriber_id: Uuid,
+        ) -> Z2PResult<SubscriptionsStatus> {
+            Ok(SubscriptionsStatus::Confirmed)
+        }
+
+        async fn subscriber_from_subscriber_id(
+            &self,
+            _subscriber_id: Uuid,
+        ) -> Z2PResult<SubscriberRecord> {
+            Ok(self.record.clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn known_token_returns_subscriber_id() {
+        let repository = MockSubscriberRepository::new();
+        let subscriber_id =
+            get_subscriber_id_of_known_token(&repository, &repository.record.token).await;
+        assert_eq!(assert_ok!(subscriber_id), repository.id);
+    }
+
+    #[tokio::test]
+    async fn unknown_token_is_a_validation_error() {
+        let repository = MockSubscriberRepository::new();
+        let result = get_subscriber_id_of_known_token(
+            &repository,
+            &SubscriberToken::generate_subscription_token(),
+        )
+        .await;
+        assert!(matches!(
+            assert_err!(result),
+            Error::SubscriptionError(ValidationError::InvalidToken(_))
+        ));
+    }
+}
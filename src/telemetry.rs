@@ -1,11 +1,24 @@
 //!telemetry.rs
 
+use crate::configuration::OtlpSettings;
+use anyhow::Context;
+use opentelemetry::{global, trace::TracerProvider as _, KeyValue};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{
+    runtime,
+    trace::{Sampler, Tracer, TracerProvider},
+    Resource,
+};
+use std::sync::OnceLock;
 use tokio::task::JoinHandle;
 use tracing::subscriber::set_global_default;
 use tracing::Subscriber;
 use tracing_bunyan_formatter::{BunyanFormattingLayer, JsonStorageLayer};
 use tracing_log::LogTracer;
-use tracing_subscriber::{fmt::MakeWriter, layer::SubscriberExt, EnvFilter, Registry};
+use tracing_subscriber::{fmt::MakeWriter, layer::SubscriberExt, reload, EnvFilter, Registry};
+
+/// Handle to replace the filter of the first subscriber, e.g. after reloading the log level.
+static LOG_FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
 
 /// Compose multiple layers into a `tracing`'s subscriber.
 ///
@@ -17,10 +30,15 @@ use tracing_subscriber::{fmt::MakeWriter, layer::SubscriberExt, EnvFilter, Regis
 /// We need to explicitly call out that the returned subscriber is
 /// `Send` and `Sync` to make it possible to pass it to `init_subscriber`
 /// later on.
+///
+/// With `otlp` settings spans are additionally exported to an OpenTelemetry
+/// collector. The batch exporter runs on the tokio runtime, therefore the
+/// subscriber has to be created inside of it.
 pub fn get_subscriber<Sink>(
     name: String,
     env_filter: String,
     sink: Sink,
+    otlp: Option<&OtlpSettings>,
 ) -> impl Subscriber + Send + Sync
 where
     // This "weird" syntax is a higher-ranked trait bound (HRTB)
@@ -32,11 +50,55 @@ where
 {
     let env_filter =
         EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(env_filter));
+    let (env_filter, filter_handle) = reload::Layer::new(env_filter);
+    let _ = LOG_FILTER.set(filter_handle);
     let formatting_layer = BunyanFormattingLayer::new(name, sink);
+    let otlp_layer =
+        otlp.map(|settings| tracing_opentelemetry::layer().with_tracer(otlp_tracer(settings)));
     Registry::default()
         .with(env_filter)
         .with(JsonStorageLayer)
         .with(formatting_layer)
+        .with(otlp_layer)
+}
+
+/// Build tracer, which exports sampled spans in batches via OTLP/HTTP. Its provider is
+/// registered globally to be flushed by `shutdown_tracing`.
+fn otlp_tracer(settings: &OtlpSettings) -> Tracer {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(settings.endpoint.clone())
+        .build()
+        .expect("Failed to build OTLP span exporter");
+    let sampler = Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+        settings.sampling_ratio,
+    )));
+    let provider = TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_sampler(sampler)
+        .with_resource(Resource::new(vec![KeyValue::new(
+            "service.name",
+            settings.service_name.clone(),
+        )]))
+        .build();
+    let tracer = provider.tracer(settings.service_name.clone());
+    global::set_tracer_provider(provider);
+    tracer
+}
+
+/// Replace the filter of log output, e.g. with "info" or "zero2prod=debug,info".
+pub fn set_log_level(log_level: &str) -> Result<(), anyhow::Error> {
+    let filter = EnvFilter::try_new(log_level).context("Invalid log level.")?;
+    LOG_FILTER
+        .get()
+        .context("No subscriber has been created.")?
+        .reload(filter)
+        .context("Failed to replace filter of subscriber.")
+}
+
+/// Export remaining spans before exit. Does nothing without OTLP export.
+pub fn shutdown_tracing() {
+    global::shutdown_tracer_provider();
 }
 
 /// Register a subscriber as global default to process span data.
@@ -0,0 +1,158 @@
+//! src/token_bucket.rs
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::configuration::RateLimitSettings;
+
+/// Token bucket, which refills continuously up to its capacity. One token allows
+/// sending one email or one API request. The bucket starts full, which allows an
+/// initial burst.
+pub struct TokenBucket {
+    capacity: f64,
+    refill_per_second: f64,
+    state: Mutex<BucketState>,
+}
+
+struct BucketState {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// State of a bucket after taking a single token.
+#[derive(Debug, Clone, Copy)]
+pub struct BucketStatus {
+    pub taken: bool,
+    pub capacity: u32,
+    pub remaining: u32,
+    /// Time until the next token is available; zero, if tokens remain.
+    pub until_next_token: Duration,
+    /// Time until the bucket is full again.
+    pub until_full: Duration,
+}
+
+impl TokenBucket {
+    pub fn new(capacity: u32, refill_interval: Duration) -> Self {
+        let capacity = f64::from(capacity.max(1));
+        Self {
+            capacity,
+            refill_per_second: capacity / refill_interval.as_secs_f64().max(f64::EPSILON),
+            state: Mutex::new(BucketState {
+                tokens: capacity,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn from_settings(settings: &RateLimitSettings) -> Self {
+        Self::new(
+            settings.max_emails,
+            Duration::from_secs(settings.interval_seconds),
+        )
+    }
+
+    /// Take up to `n` tokens; returns number of taken tokens, which may be 0.
+    pub fn take(&self, n: u32) -> u32 {
+        self.take_at(n, Instant::now())
+    }
+
+    /// Return unused tokens of a previous `take`.
+    pub fn give_back(&self, n: u32) {
+        let mut state = self.state.lock().unwrap();
+        state.tokens = (state.tokens + f64::from(n)).min(self.capacity);
+    }
+
+    /// Take one token and report the state of the bucket afterwards.
+    pub fn take_one(&self) -> BucketStatus {
+        self.take_one_at(Instant::now())
+    }
+
+    fn take_at(&self, n: u32, now: Instant) -> u32 {
+        let mut state = self.state.lock().unwrap();
+        self.refill(&mut state, now);
+        let taken = state.tokens.floor().min(f64::from(n));
+        state.tokens -= taken;
+        taken as u32
+    }
+
+    fn take_one_at(&self, now: Instant) -> BucketStatus {
+        let mut state = self.state.lock().unwrap();
+        self.refill(&mut state, now);
+        let taken = state.tokens >= 1.0;
+        if taken {
+            state.tokens -= 1.0;
+        }
+        BucketStatus {
+            taken,
+            capacity: self.capacity as u32,
+            remaining: state.tokens.floor() as u32,
+            until_next_token: self.refill_duration((1.0 - state.tokens).max(0.0)),
+            until_full: self.refill_duration(self.capacity - state.tokens),
+        }
+    }
+
+    fn refill(&self, state: &mut BucketState, now: Instant) {
+        let elapsed = now.saturating_duration_since(state.refilled_at);
+        state.tokens =
+            (state.tokens + elapsed.as_secs_f64() * self.refill_per_second).min(self.capacity);
+        state.refilled_at = now;
+    }
+
+    fn refill_duration(&self, tokens: f64) -> Duration {
+        Duration::from_secs_f64(tokens / self.refill_per_second)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TokenBucket;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn full_bucket_allows_burst_up_to_capacity() {
+        let bucket = TokenBucket::new(10, Duration::from_secs(60));
+        let now = Instant::now();
+        assert_eq!(bucket.take_at(4, now), 4);
+        assert_eq!(bucket.take_at(50, now), 6);
+        assert_eq!(bucket.take_at(1, now), 0);
+    }
+
+    #[test]
+    fn bucket_refills_with_configured_rate() {
+        // 10 emails per minute refill one token each 6 seconds
+        let bucket = TokenBucket::new(10, Duration::from_secs(60));
+        let start = Instant::now();
+        assert_eq!(bucket.take_at(10, start), 10);
+        assert_eq!(bucket.take_at(10, start + Duration::from_secs(5)), 0);
+        assert_eq!(bucket.take_at(10, start + Duration::from_secs(13)), 2);
+        // refill never exceeds capacity
+        assert_eq!(bucket.take_at(20, start + Duration::from_secs(3600)), 10);
+    }
+
+    #[test]
+    fn unused_tokens_can_be_given_back() {
+        let bucket = TokenBucket::new(5, Duration::from_secs(60));
+        let now = Instant::now();
+        assert_eq!(bucket.take_at(5, now), 5);
+        bucket.give_back(3);
+        assert_eq!(bucket.take_at(5, now), 3);
+    }
+
+    #[test]
+    fn status_of_single_token_reports_remaining_tokens_and_refill_time() {
+        // 2 requests per minute refill one token each 30 seconds
+        let bucket = TokenBucket::new(2, Duration::from_secs(60));
+        let start = Instant::now();
+        let status = bucket.take_one_at(start);
+        assert!(status.taken);
+        assert_eq!(status.remaining, 1);
+        assert_eq!(status.until_next_token, Duration::ZERO);
+        assert_eq!(status.until_full.as_secs_f64().round(), 30.0);
+        assert!(bucket.take_one_at(start).taken);
+        let status = bucket.take_one_at(start + Duration::from_secs(10));
+        assert!(!status.taken);
+        assert_eq!(status.remaining, 0);
+        assert_eq!(status.until_next_token.as_secs_f64().round(), 20.0);
+        assert_eq!(status.until_full.as_secs_f64().round(), 50.0);
+    }
+}
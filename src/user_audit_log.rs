@@ -0,0 +1,39 @@
+//! src/user_audit_log.rs
+
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+/// Changes of admin users, which are logged for audits.
+#[derive(Debug, Clone, Copy, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "user_audit_action", rename_all = "snake_case")]
+pub enum UserAuditAction {
+    Provisioned,
+    Updated,
+    Deactivated,
+    Reactivated,
+}
+
+/// Append a change of a user to the audit log. Pass a transaction as executor to log
+/// the change together with the change itself.
+#[tracing::instrument(name = "Record user audit event", skip(executor))]
+pub async fn record_user_audit_event<'e>(
+    executor: impl PgExecutor<'e>,
+    user_id: Uuid,
+    action: UserAuditAction,
+    actor: &str,
+    details: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO user_audit_log (user_id, action, actor, details, occurred_at)
+        VALUES ($1, $2, $3, $4, now())
+        "#,
+        user_id,
+        action as UserAuditAction,
+        actor,
+        details,
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,152 @@
+//! src/welcome_issue.rs
+
+use anyhow::Context;
+use chrono::Utc;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::delivery_queue::{DeliveryQueue, PgDeliveryQueue};
+use crate::frequency_cap::apply_frequency_cap;
+use crate::issue_delivery_worker::{notify_delivery_worker, DeliveryTaskStatus};
+use crate::startup::{ExternalDeliveryQueue, FrequencyCap};
+
+/// Issue, which newly confirmed subscribers receive from the archive as part of
+/// the welcome flow.
+#[derive(serde::Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WelcomeIssue {
+    #[default]
+    Disabled,
+    /// Most recent issue of the subscriber's list, whose delivery has started
+    Latest,
+    /// Configured issue, e.g. a "best of" issue
+    Issue(Uuid),
+}
+
+/// Enqueue delivery of the welcome issue to a newly confirmed subscriber. The delivery
+/// worker sends it like every other issue and counts the subscriber as additional
+/// recipient of the issue. Returns the id of the enqueued issue, if there is one and
+/// the subscriber is below the frequency cap.
+#[tracing::instrument(
+    name = "Enqueue welcome issue",
+    skip(pool, external_queue, frequency_cap)
+)]
+pub async fn enqueue_welcome_issue(
+    pool: &PgPool,
+    external_queue: &ExternalDeliveryQueue,
+    frequency_cap: &FrequencyCap,
+    welcome_issue: &WelcomeIssue,
+    subscriber_id: Uuid,
+) -> Result<Option<Uuid>, anyhow::Error> {
+    let issue_id = match welcome_issue {
+        WelcomeIssue::Disabled => None,
+        WelcomeIssue::Latest => get_welcome_issue_id(pool, subscriber_id, None)
+            .await
+            .context("Failed to read latest issue.")?,
+        WelcomeIssue::Issue(issue_id) => {
+            let welcome_issue_id = get_welcome_issue_id(pool, subscriber_id, Some(*issue_id))
+                .await
+                .context("Failed to read welcome issue.")?;
+            if welcome_issue_id.is_none() {
+                tracing::warn!(
+                    %issue_id,
+                    "Configured welcome issue does not exist, is an issue of another list \
+                    or its delivery has not started or has been cancelled."
+                );
+            }
+            welcome_issue_id
+        }
+    };
+    let Some(issue_id) = issue_id else {
+        return Ok(None);
+    };
+    if let Some(ref queue) = external_queue.0 {
+        let num_cancelled = queue
+            .count_tasks(issue_id, DeliveryTaskStatus::Cancelled)
+            .await
+            .context("Failed to read cancelled deliveries of welcome issue.")?;
+        if num_cancelled > 0 {
+            tracing::warn!(%issue_id, "Delivery of welcome issue has been cancelled.");
+            return Ok(None);
+        }
+    }
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    let recipients = apply_frequency_cap(
+        &mut transaction,
+        external_queue,
+        frequency_cap.0,
+        issue_id,
+        vec![subscriber_id],
+    )
+    .await
+    .context("Failed to apply frequency cap to welcome issue.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit skipped send of welcome issue.")?;
+    if recipients.is_empty() {
+        return Ok(None);
+    }
+    let num_tasks = match external_queue.0 {
+        Some(ref queue) => queue.enqueue(issue_id, &[subscriber_id], Utc::now()).await,
+        None => {
+            PgDeliveryQueue::new(pool.clone())
+                .enqueue(issue_id, &[subscriber_id], Utc::now())
+                .await
+        }
+    }
+    .context("Failed to enqueue delivery of welcome issue.")?;
+    sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET num_current_subscribers = COALESCE(num_current_subscribers, 0) + $2
+        WHERE newsletter_issue_id = $1
+        "#,
+        issue_id,
+        num_tasks as i32,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to count recipient of welcome issue.")?;
+    notify_delivery_worker(pool)
+        .await
+        .context("Failed to notify delivery worker.")?;
+    Ok(Some(issue_id))
+}
+
+/// Issues are only sent from the archive to subscribers of their list, after their
+/// delivery has started, i.e. after their scheduled time and undo window, and if their
+/// delivery has not been cancelled. Cancelled tasks of an external queue are not
+/// visible here. Without `issue_id` the most recent issue is chosen.
+async fn get_welcome_issue_id(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+    issue_id: Option<Uuid>,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        SELECT i.newsletter_issue_id
+        FROM newsletter_issues i
+        JOIN subscriptions s ON s.list_id = i.list_id
+        WHERE
+            s.id = $1 AND
+            ($2::uuid IS NULL OR i.newsletter_issue_id = $2) AND
+            COALESCE(GREATEST(i.scheduled_at, i.cancellable_until), i.published_at) <= now() AND
+            NOT EXISTS (
+                SELECT 1
+                FROM issue_delivery_queue q
+                WHERE q.newsletter_issue_id = i.newsletter_issue_id AND q.status = 'cancelled'
+            )
+        ORDER BY i.published_at DESC
+        LIMIT 1
+        "#,
+        subscriber_id,
+        issue_id,
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(result.map(|r| r.newsletter_issue_id))
+}
@@ -0,0 +1,179 @@
+//! src/worker_heartbeat.rs
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Minimum time between two writes of a heartbeat, which limits writes of busy loops.
+const WRITE_INTERVAL: Duration = Duration::from_secs(5);
+/// Heartbeats of workers, which have not been seen for a day, are removed.
+const RETENTION_HOURS: i32 = 24;
+
+/// Heartbeat of a background worker loop. Each loop has its own heartbeat row, which
+/// keeps the time the loop was last seen and the number of processed tasks.
+pub struct WorkerHeartbeat {
+    worker_id: String,
+    kind: &'static str,
+    stale_after: Duration,
+    /// processed tasks, which have not been written yet
+    tasks_processed: u64,
+    last_written: Option<Instant>,
+}
+
+impl WorkerHeartbeat {
+    /// `stale_after` is the time without heartbeat, after which the worker is
+    /// considered dead. It must be longer than the longest sleep of the loop.
+    pub fn new(kind: &'static str, stale_after: Duration) -> Self {
+        Self {
+            worker_id: format!("{}-{}", kind, Uuid::new_v4()),
+            kind,
+            stale_after,
+            tasks_processed: 0,
+            last_written: None,
+        }
+    }
+
+    pub fn worker_id(&self) -> &str {
+        &self.worker_id
+    }
+
+    /// Count processed tasks and write heartbeat, if the last write is older than a few
+    /// seconds. Failed writes are logged and retried with the next beat, since a
+    /// missing heartbeat must not stop the worker.
+    pub async fn beat(&mut self, pool: &PgPool, tasks_processed: u64) {
+        self.tasks_processed += tasks_processed;
+        if self
+            .last_written
+            .is_some_and(|last_written| last_written.elapsed() < WRITE_INTERVAL)
+        {
+            return;
+        }
+        let first_beat = self.last_written.is_none();
+        match self.write(pool, first_beat).await {
+            Ok(()) => {
+                self.tasks_processed = 0;
+                self.last_written = Some(Instant::now());
+            }
+            Err(e) => {
+                tracing::warn!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    worker_id = %self.worker_id,
+                    "Failed to write worker heartbeat."
+                );
+            }
+        }
+    }
+
+    async fn write(&self, pool: &PgPool, first_beat: bool) -> Result<(), sqlx::Error> {
+        if first_beat {
+            sqlx::query!(
+                r#"
+                DELETE FROM worker_heartbeats
+                WHERE last_seen < now() - make_interval(hours => $1)
+                "#,
+                RETENTION_HOURS,
+            )
+            .execute(pool)
+            .await?;
+        }
+        sqlx::query!(
+            r#"
+            INSERT INTO worker_heartbeats (
+                worker_id,
+                kind,
+                started_at,
+                last_seen,
+                tasks_processed,
+                stale_after_seconds
+            )
+            VALUES ($1, $2, now(), now(), $3, $4)
+            ON CONFLICT (worker_id) DO UPDATE
+            SET
+                last_seen = now(),
+                tasks_processed = worker_heartbeats.tasks_processed + EXCLUDED.tasks_processed
+            "#,
+            self.worker_id,
+            self.kind,
+            self.tasks_processed as i64,
+            self.stale_after.as_secs() as i32,
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+}
+
+/// Status of a background worker loop from its last heartbeat.
+#[derive(Debug, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct WorkerStatus {
+    pub worker_id: String,
+    pub kind: String,
+    pub started_at: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    pub tasks_processed: i64,
+    pub alive: bool,
+}
+
+/// Status of workers seen within the retention time, ordered by kind and start.
+#[tracing::instrument(name = "Get status of workers", skip(pool))]
+pub async fn get_worker_statuses(pool: &PgPool) -> Result<Vec<WorkerStatus>, sqlx::Error> {
+    sqlx::query_as!(
+        WorkerStatus,
+        r#"
+        SELECT
+            worker_id,
+            kind,
+            started_at,
+            last_seen,
+            tasks_processed,
+            last_seen > now() - make_interval(secs => stale_after_seconds) AS "alive!"
+        FROM worker_heartbeats
+        WHERE last_seen > now() - make_interval(hours => $1)
+        ORDER BY kind, started_at
+        "#,
+        RETENTION_HOURS,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Workers are healthy, if each kind of worker has at least one live worker. Dead
+/// workers of a kind are expected after a restart, which starts new workers.
+pub fn workers_are_healthy(workers: &[WorkerStatus]) -> bool {
+    workers.iter().all(|worker| {
+        workers
+            .iter()
+            .any(|other| other.kind == worker.kind && other.alive)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{workers_are_healthy, WorkerStatus};
+    use chrono::Utc;
+
+    fn worker(kind: &str, alive: bool) -> WorkerStatus {
+        WorkerStatus {
+            worker_id: format!("{}-test", kind),
+            kind: kind.to_string(),
+            started_at: Utc::now(),
+            last_seen: Utc::now(),
+            tasks_processed: 0,
+            alive,
+        }
+    }
+
+    #[test]
+    fn dead_worker_replaced_by_live_worker_of_same_kind_is_healthy() {
+        let workers = [worker("delivery", false), worker("delivery", true)];
+        assert!(workers_are_healthy(&workers));
+    }
+
+    #[test]
+    fn kind_without_live_worker_is_unhealthy() {
+        let workers = [worker("delivery", true), worker("cleanup", false)];
+        assert!(!workers_are_healthy(&workers));
+    }
+}
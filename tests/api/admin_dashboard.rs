@@ -1,6 +1,11 @@
 //! tests/api/admin_dashboard.rs
 
-use crate::helpers::{assert_is_redirect_to, spawn_app};
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use crate::newsletter::{create_confirmed_subscriber, when_sending_an_email};
+use std::time::Duration;
+use uuid::Uuid;
+use wiremock::ResponseTemplate;
+use zero2prod::worker_heartbeat::WorkerHeartbeat;
 
 #[tokio::test]
 async fn you_must_be_logged_in_to_access_the_admin_dashboard() {
@@ -13,3 +18,112 @@ async fn you_must_be_logged_in_to_access_the_admin_dashboard() {
     // Assert
     assert_is_redirect_to(&response, "/login")
 }
+
+/// Insert confirmed subscribers directly into the database
+pub async fn insert_confirmed_subscribers(app: &TestApp, n: usize) {
+    for i in 0..n {
+        sqlx::query!(
+            r#"INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+            VALUES ($1, $2, $3, now(), 'confirmed')"#,
+            Uuid::new_v4(),
+            format!("subscriber{}@example.com", i),
+            format!("Subscriber {}", i),
+        )
+        .execute(&app.db_pool)
+        .await
+        .expect("Failed to insert confirmed subscriber.");
+    }
+}
+
+#[tokio::test]
+async fn dashboard_celebrates_reached_subscriber_milestone() {
+    // Arrange
+    let test_app = spawn_app().await;
+    insert_confirmed_subscribers(&test_app, 99).await;
+
+    // Act - confirmation of 100th subscriber
+    create_confirmed_subscriber(&test_app).await;
+    test_app.test_user.login(&test_app).await;
+    let html_page = test_app.get_admin_dashboard_html().await;
+
+    // Assert
+    assert!(html_page.contains("your newsletter reached 100 confirmed subscribers"));
+}
+
+#[tokio::test]
+async fn dashboard_shows_no_milestone_below_first_milestone() {
+    // Arrange
+    let test_app = spawn_app().await;
+    insert_confirmed_subscribers(&test_app, 98).await;
+
+    // Act - confirmation of 99th subscriber
+    create_confirmed_subscriber(&test_app).await;
+    test_app.test_user.login(&test_app).await;
+    let html_page = test_app.get_admin_dashboard_html().await;
+
+    // Assert
+    assert!(!html_page.contains("Congratulations"));
+}
+
+#[tokio::test]
+async fn dashboard_shows_confirmation_email_metrics() {
+    // Arrange
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&test_app.email_server)
+        .await;
+
+    // Act - confirmation email of second subscriber fails
+    let response = test_app
+        .post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+    assert_eq!(response.status().as_u16(), 500);
+    test_app.test_user.login(&test_app).await;
+    let html_page = test_app.get_admin_dashboard_html().await;
+
+    // Assert
+    assert!(html_page.contains("Confirmation emails since start: 1 sent, 1 failed"));
+    assert!(html_page.contains("1 above SLO of 2000 ms"));
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_status_of_workers() {
+    // Arrange
+    let test_app = spawn_app().await;
+
+    // Act
+    let response = test_app.get_response_from_url("/admin/workers").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login")
+}
+
+#[tokio::test]
+async fn workers_page_shows_dead_and_alive_workers() {
+    // Arrange
+    let test_app = spawn_app().await;
+    let mut dead = WorkerHeartbeat::new("delivery", Duration::from_secs(60));
+    dead.beat(&test_app.db_pool, 0).await;
+    sqlx::query!("UPDATE worker_heartbeats SET last_seen = now() - INTERVAL '2 minutes'")
+        .execute(&test_app.db_pool)
+        .await
+        .unwrap();
+    let mut alive = WorkerHeartbeat::new("delivery", Duration::from_secs(60));
+    alive.beat(&test_app.db_pool, 7).await;
+    test_app.test_user.login(&test_app).await;
+
+    // Act
+    let html_page = test_app
+        .get_response_from_url("/admin/workers")
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    // Assert
+    assert!(html_page.contains(&format!("<b>dead</b>: {}", dead.worker_id())));
+    assert!(html_page.contains(&format!("alive: {}", alive.worker_id())));
+    assert!(html_page.contains("7 tasks processed"));
+}
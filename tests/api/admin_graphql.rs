@@ -0,0 +1,297 @@
+//! tests/api/admin_graphql.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, spawn_app_with};
+use crate::newsletter::create_confirmed_subscriber;
+use secrecy::ExposeSecret;
+use uuid::Uuid;
+use zero2prod::configuration::DeliveryQueueSettings;
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_use_the_admin_graphql_api() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!("{}/admin/api/graphql", &app.address))
+        .json(&serde_json::json!({ "query": "{ issues { id } }" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn admin_graphql_api_accepts_api_key_and_rejects_wrong_keys() {
+    // Arrange
+    let app = spawn_app().await;
+    let url = format!("{}/admin/api/graphql", &app.address);
+    let query = serde_json::json!({ "query": "{ issues { title } }" });
+
+    // Act
+    let response = app
+        .api_client
+        .post(&url)
+        .bearer_auth(app.api_key.expose_secret())
+        .json(&query)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let rejected = app
+        .api_client
+        .post(&url)
+        .bearer_auth("wrong-api-key")
+        .json(&query)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["data"]["issues"], serde_json::json!([]));
+    assert_eq!(401, rejected.status().as_u16());
+}
+
+#[tokio::test]
+async fn api_key_may_neither_read_nor_unsubscribe_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+    let (email, ..) = create_confirmed_subscriber(&app).await;
+    let subscriber_id = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id;
+
+    for query in [
+        "{ subscribers { email } }".to_string(),
+        format!(r#"{{ subscriber(id: "{}") {{ email }} }}"#, subscriber_id),
+        format!(
+            r#"mutation {{ unsubscribe(subscriberId: "{}") }}"#,
+            subscriber_id
+        ),
+    ] {
+        // Act
+        let response: serde_json::Value = app
+            .api_client
+            .post(format!("{}/admin/api/graphql", &app.address))
+            .bearer_auth(app.api_key.expose_secret())
+            .json(&serde_json::json!({ "query": query }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .json()
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(
+            response["errors"][0]["message"],
+            "You are not allowed to access the requested resource",
+            "{query}"
+        );
+        assert!(!response.to_string().contains(email.as_ref()));
+    }
+    let num_subscribers = sqlx::query!("SELECT COUNT(*) AS count FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(num_subscribers, Some(1));
+}
+
+#[tokio::test]
+async fn issues_published_via_graphql_are_queued_for_delivery() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let published = app
+        .post_admin_graphql(
+            r#"mutation {
+                publishIssue(input: { title: "GraphQL issue", markdownContent: "**Hello**" })
+            }"#,
+        )
+        .await;
+    let issue_id = published["data"]["publishIssue"].as_str().unwrap();
+    let issue = app
+        .post_admin_graphql(&format!(
+            r#"{{ issue(id: "{}") {{ title deliveryStats {{ numCurrentSubscribers numPending }} }} }}"#,
+            issue_id
+        ))
+        .await;
+
+    // Assert
+    assert_eq!(issue["data"]["issue"]["title"], "GraphQL issue");
+    assert_eq!(
+        issue["data"]["issue"]["deliveryStats"],
+        serde_json::json!({ "numCurrentSubscribers": 1, "numPending": 1 })
+    );
+}
+
+#[tokio::test]
+#[ignore = "requires Redis with Lua scripting, run with --ignored"]
+async fn pending_tasks_of_redis_queue_are_counted_in_graphql_delivery_stats() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.delivery_queue = DeliveryQueueSettings::Redis {
+            key_prefix: format!("test_queue:{}", Uuid::new_v4()),
+            lease_seconds: 60,
+        };
+    })
+    .await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    let published = app
+        .post_admin_graphql(
+            r#"mutation {
+                publishIssue(input: { title: "GraphQL issue", markdownContent: "**Hello**" })
+            }"#,
+        )
+        .await;
+    let issue_id = published["data"]["publishIssue"].as_str().unwrap();
+
+    // Act
+    let issue = app
+        .post_admin_graphql(&format!(
+            r#"{{ issue(id: "{}") {{ deliveryStats {{ numPending }} }} }}"#,
+            issue_id
+        ))
+        .await;
+
+    // Assert
+    assert_eq!(
+        issue["data"]["issue"]["deliveryStats"],
+        serde_json::json!({ "numPending": 1 })
+    );
+    assert_eq!(app.num_rows_of_table("issue_delivery_queue").await, 0);
+}
+
+#[tokio::test]
+async fn retried_graphql_publish_with_idempotency_key_publishes_issue_once() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let idempotency_key = uuid::Uuid::new_v4().to_string();
+    let publish = || async {
+        app.api_client
+            .post(format!("{}/admin/api/graphql", &app.address))
+            .header("Idempotency-Key", &idempotency_key)
+            .json(&serde_json::json!({ "query": r#"mutation {
+                publishIssue(input: { title: "GraphQL issue", markdownContent: "**Hello**" })
+            }"# }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .json::<serde_json::Value>()
+            .await
+            .expect("Failed to parse GraphQL response.")
+    };
+
+    // Act
+    let first = publish().await;
+    let retried = publish().await;
+
+    // Assert
+    assert!(first["data"]["publishIssue"].is_string());
+    assert_eq!(first, retried);
+    let num_issues = sqlx::query!("SELECT COUNT(*) AS count FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(num_issues, Some(1));
+}
+
+#[tokio::test]
+async fn failed_graphql_request_with_idempotency_key_publishes_no_issue() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!("{}/admin/api/graphql", &app.address))
+        .header("Idempotency-Key", uuid::Uuid::new_v4().to_string())
+        .json(&serde_json::json!({ "query": r#"mutation {
+            first: publishIssue(input: { title: "GraphQL issue", markdownContent: "**Hello**" })
+            second: publishIssue(input: { title: "No content" })
+        }"# }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json::<serde_json::Value>()
+        .await
+        .expect("Failed to parse GraphQL response.");
+
+    // Assert
+    assert_eq!(
+        response["errors"][0]["message"],
+        "You must set text content for your newsletter."
+    );
+    assert!(response["data"].is_null());
+    let num_issues = sqlx::query!("SELECT COUNT(*) AS count FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(num_issues, Some(0));
+}
+
+#[tokio::test]
+async fn publishing_via_graphql_requires_content() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .post_admin_graphql(r#"mutation { publishIssue(input: { title: "No content" }) }"#)
+        .await;
+
+    // Assert
+    assert_eq!(
+        response["errors"][0]["message"],
+        "You must set text content for your newsletter."
+    );
+    let num_issues = sqlx::query!("SELECT COUNT(*) AS count FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(num_issues, Some(0));
+}
+
+#[tokio::test]
+async fn subscribers_can_be_unsubscribed_via_graphql() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let subscriber_id = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id;
+    app.test_user.login(&app).await;
+    let mutation = format!(
+        r#"mutation {{ unsubscribe(subscriberId: "{}") }}"#,
+        subscriber_id
+    );
+
+    // Act
+    let removed = app.post_admin_graphql(&mutation).await;
+    let removed_again = app.post_admin_graphql(&mutation).await;
+
+    // Assert
+    assert_eq!(removed["data"]["unsubscribe"], true);
+    assert_eq!(removed_again["data"]["unsubscribe"], false);
+    let subscribers = app.post_admin_graphql("{ subscribers { id } }").await;
+    assert_eq!(subscribers["data"]["subscribers"], serde_json::json!([]));
+}
@@ -0,0 +1,349 @@
+//! tests/api/admin_users.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use crate::newsletter::when_sending_an_email;
+use reqwest::Url;
+use uuid::Uuid;
+use wiremock::ResponseTemplate;
+use zero2prod::policy::UserRole;
+use zero2prod::routes::InviteUserFormData;
+
+async fn post_invite_user(
+    app: &TestApp,
+    username: &str,
+    email: &str,
+    role: UserRole,
+) -> reqwest::Response {
+    app.api_client
+        .post(format!("{}/admin/users", &app.address))
+        .form(&InviteUserFormData {
+            username: username.to_string(),
+            email: email.to_string(),
+            role,
+        })
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn get_users_html(app: &TestApp) -> String {
+    app.get_response_from_url("/admin/users")
+        .await
+        .text()
+        .await
+        .unwrap()
+}
+
+async fn user_id_of(app: &TestApp, username: &str) -> Uuid {
+    sqlx::query!("SELECT user_id FROM users WHERE username = $1", username)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .user_id
+}
+
+/// Invite a user as logged-in admin, follow the link of the invitation email with a
+/// new client and set the password. Returns the client and the password of the user.
+async fn invite_and_activate_user(
+    app: &TestApp,
+    username: &str,
+    role: UserRole,
+) -> (reqwest::Client, String) {
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let response = post_invite_user(app, username, "ursula@example.com", role).await;
+    assert_is_redirect_to(&response, "/admin/users");
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body["To"], "ursula@example.com");
+    let link = linkify::LinkFinder::new()
+        .links(body["TextBody"].as_str().unwrap())
+        .map(|l| Url::parse(l.as_str()).unwrap())
+        .find(|l| l.path() == "/login/reset")
+        .expect("Invitation email has no link to set the password.");
+    let token = link
+        .query_pairs()
+        .find(|(key, _)| key == "token")
+        .unwrap()
+        .1
+        .to_string();
+
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .cookie_store(true)
+        .build()
+        .unwrap();
+    let password = Uuid::new_v4().to_string();
+    let response = client
+        .post(format!("{}/login/reset", &app.address))
+        .form(&[
+            ("token", token.as_str()),
+            ("new_password", password.as_str()),
+            ("new_password_check", password.as_str()),
+        ])
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/login");
+    (client, password)
+}
+
+async fn login_with_client(
+    app: &TestApp,
+    client: &reqwest::Client,
+    username: &str,
+    password: &str,
+) -> reqwest::Response {
+    client
+        .post(format!("{}/login", &app.address))
+        .form(&[("username", username), ("password", password)])
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_manage_users() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_response_from_url("/admin/users").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn invited_user_sets_password_and_logs_in() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let (client, password) = invite_and_activate_user(&app, "ursula", UserRole::Admin).await;
+
+    // Assert
+    let html_page = get_users_html(&app).await;
+    assert!(html_page.contains("`ursula` has been invited via ursula@example.com."));
+    assert!(html_page.contains("<b>ursula</b> (ursula@example.com)"));
+    let response = login_with_client(&app, &client, "ursula", &password).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn deactivated_user_is_logged_out_and_can_not_log_in() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let (client, password) = invite_and_activate_user(&app, "ursula", UserRole::Admin).await;
+    login_with_client(&app, &client, "ursula", &password).await;
+    let user_id = user_id_of(&app, "ursula").await;
+
+    // Act - Part 1 - deactivate
+    let response = app
+        .api_client
+        .post(format!(
+            "{}/admin/users/{}/deactivate",
+            &app.address, user_id
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert - Part 1
+    assert_is_redirect_to(&response, "/admin/users");
+    let response = client
+        .get(format!("{}/admin/dashboard", &app.address))
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/login");
+    let response = login_with_client(&app, &client, "ursula", &password).await;
+    assert_is_redirect_to(&response, "/login");
+
+    // Act - Part 2 - reactivate
+    let response = app
+        .api_client
+        .post(format!(
+            "{}/admin/users/{}/reactivate",
+            &app.address, user_id
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert - Part 2
+    assert_is_redirect_to(&response, "/admin/users");
+    let response = login_with_client(&app, &client, "ursula", &password).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn admin_can_not_deactivate_own_user() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!(
+            "{}/admin/users/{}/deactivate",
+            &app.address, app.test_user.user_id
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/users");
+    let html_page = get_users_html(&app).await;
+    assert!(html_page.contains("You can not deactivate your own user."));
+}
+
+#[tokio::test]
+async fn existing_username_can_not_be_invited() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = post_invite_user(
+        &app,
+        &app.test_user.username,
+        "ursula@example.com",
+        UserRole::Admin,
+    )
+    .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/users");
+    let html_page = get_users_html(&app).await;
+    assert!(html_page.contains("exists already."));
+    assert_eq!(app.num_rows_of_table("password_reset_tokens").await, 0);
+}
+
+#[tokio::test]
+async fn editor_may_publish_but_not_manage_users() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let (client, password) = invite_and_activate_user(&app, "ursula", UserRole::Editor).await;
+    login_with_client(&app, &client, "ursula", &password).await;
+
+    // Act
+    let dashboard = client
+        .get(format!("{}/admin/dashboard", &app.address))
+        .send()
+        .await
+        .unwrap();
+    let newsletters = client
+        .get(format!("{}/admin/newsletters", &app.address))
+        .send()
+        .await
+        .unwrap();
+    let users = client
+        .get(format!("{}/admin/users", &app.address))
+        .send()
+        .await
+        .unwrap();
+    let invitation = client
+        .post(format!("{}/admin/users", &app.address))
+        .form(&[("username", "mallory"), ("email", "mallory@example.com")])
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(200, dashboard.status().as_u16());
+    assert!(!dashboard.text().await.unwrap().contains("/admin/users"));
+    assert_eq!(200, newsletters.status().as_u16());
+    assert_eq!(403, users.status().as_u16());
+    assert_eq!(403, invitation.status().as_u16());
+    let html_page = get_users_html(&app).await;
+    assert!(html_page.contains("role: editor"));
+    assert!(!html_page.contains("mallory"));
+}
+
+#[tokio::test]
+async fn editor_may_not_manage_users_via_percent_encoded_path() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let (client, password) = invite_and_activate_user(&app, "ursula", UserRole::Editor).await;
+    login_with_client(&app, &client, "ursula", &password).await;
+
+    for path in ["/admin/%75sers", "/admin/user%73"] {
+        // Act
+        let response = client
+            .get(format!("{}{}", &app.address, path))
+            .send()
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(403, response.status().as_u16(), "{path}");
+    }
+}
+
+#[tokio::test]
+async fn editor_may_neither_read_nor_unsubscribe_subscribers_via_graphql() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let (client, password) = invite_and_activate_user(&app, "ursula", UserRole::Editor).await;
+    login_with_client(&app, &client, "ursula", &password).await;
+    let subscriber_id = Uuid::new_v4();
+
+    for query in [
+        "{ subscribers { email } }".to_string(),
+        format!(r#"{{ subscriber(id: "{}") {{ email }} }}"#, subscriber_id),
+        format!(
+            r#"mutation {{ unsubscribe(subscriberId: "{}") }}"#,
+            subscriber_id
+        ),
+    ] {
+        // Act
+        let response: serde_json::Value = client
+            .post(format!("{}/admin/api/graphql", &app.address))
+            .json(&serde_json::json!({ "query": query }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(
+            response["errors"][0]["message"],
+            "You are not allowed to access the requested resource",
+            "{query}"
+        );
+    }
+    let issues: serde_json::Value = client
+        .post(format!("{}/admin/api/graphql", &app.address))
+        .json(&serde_json::json!({ "query": "{ issues { id } }" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(issues["data"]["issues"], serde_json::json!([]));
+}
@@ -0,0 +1,50 @@
+//! tests/api/api_docs.rs
+
+use crate::helpers::spawn_app;
+
+#[tokio::test]
+async fn openapi_spec_documents_public_routes() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_response_from_url("/api/openapi.json").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let spec: serde_json::Value = response.json().await.unwrap();
+    assert!(spec["openapi"].as_str().unwrap().starts_with("3."));
+    for path in [
+        "/health_check",
+        "/subscriptions",
+        "/subscriptions/confirm",
+        "/subscriptions/unsubscribe",
+        "/feedback/{issue_id}",
+        "/admin/newsletters",
+        "/webhooks/email/inbound",
+        "/webhooks/email/bounce",
+    ] {
+        assert!(
+            spec["paths"][path].is_object(),
+            "{} is not documented",
+            path
+        );
+    }
+    let schemes = &spec["components"]["securitySchemes"];
+    assert!(schemes["session_cookie"].is_object());
+    assert!(schemes["webhook_basic_auth"].is_object());
+}
+
+#[tokio::test]
+async fn api_docs_page_loads_openapi_spec() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_response_from_url("/api/docs").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains(r#"url: "/api/openapi.json""#));
+}
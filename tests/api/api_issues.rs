@@ -0,0 +1,97 @@
+//! tests/api/api_issues.rs
+
+use crate::helpers::{spawn_app, TestApp};
+use crate::newsletter::valid_newsletter_form_data;
+use secrecy::ExposeSecret;
+use zero2prod::routes::IssueDetails;
+
+/// Publish a newsletter issue and return its id
+async fn publish_issue(app: &TestApp) -> uuid::Uuid {
+    app.test_user.login(app).await;
+    let newsletter = valid_newsletter_form_data();
+    app.post_newsletters(&newsletter).await;
+    sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues",)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch published issue.")
+        .newsletter_issue_id
+}
+
+#[tokio::test]
+async fn api_rejects_requests_without_valid_api_key() {
+    // Arrange
+    let app = spawn_app().await;
+    let issue_id = publish_issue(&app).await;
+    let url = format!("{}/api/v1/issues/{}", app.address, issue_id);
+
+    for api_key in [None, Some("wrong-api-key")] {
+        // Act
+        let mut request = app.api_client.get(&url);
+        if let Some(api_key) = api_key {
+            request = request.bearer_auth(api_key);
+        }
+        let response = request.send().await.expect("Failed to execute request.");
+
+        // Assert
+        assert_eq!(401, response.status().as_u16());
+        assert_eq!(
+            r#"Bearer realm="api""#,
+            response.headers()["WWW-Authenticate"]
+        );
+    }
+}
+
+#[tokio::test]
+async fn api_returns_issue_details_with_caching_headers() {
+    // Arrange
+    let app = spawn_app().await;
+    let issue_id = publish_issue(&app).await;
+    let newsletter = valid_newsletter_form_data();
+
+    // Act
+    let response = app.get_api_issue(&issue_id.to_string()).await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!("private, max-age=300", response.headers()["Cache-Control"]);
+    assert!(response.headers().contains_key("ETag"));
+    let issue: IssueDetails = response.json().await.unwrap();
+    assert_eq!(issue.issue_id, issue_id);
+    assert_eq!(issue.title, newsletter.title);
+    assert_eq!(issue.html_content, newsletter.html_content);
+    assert_eq!(issue.text_content, newsletter.text_content);
+}
+
+#[tokio::test]
+async fn api_returns_not_modified_for_matching_etag() {
+    // Arrange
+    let app = spawn_app().await;
+    let issue_id = publish_issue(&app).await;
+    let response = app.get_api_issue(&issue_id.to_string()).await;
+    let etag = response.headers()["ETag"].clone();
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!("{}/api/v1/issues/{}", app.address, issue_id))
+        .bearer_auth(app.api_key.expose_secret())
+        .header("If-None-Match", etag)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(304, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn api_returns_404_for_unknown_issue() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_api_issue(&uuid::Uuid::new_v4().to_string()).await;
+
+    // Assert
+    assert_eq!(404, response.status().as_u16());
+}
@@ -0,0 +1,99 @@
+//! tests/api/api_migrations.rs
+
+use crate::helpers::spawn_app;
+
+#[tokio::test]
+async fn migrated_database_is_compatible() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_api_migrations().await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(report["compatible"], true);
+    assert_eq!(report["unknown_versions"], serde_json::json!([]));
+    let migrations = report["migrations"].as_array().unwrap();
+    assert!(!migrations.is_empty());
+    assert!(migrations.iter().all(|m| m["state"] == "applied"));
+}
+
+#[tokio::test]
+async fn pending_changed_and_unknown_migrations_are_reported() {
+    // Arrange
+    let app = spawn_app().await;
+    let versions: Vec<i64> =
+        sqlx::query!("SELECT version FROM _sqlx_migrations ORDER BY version DESC LIMIT 2")
+            .fetch_all(&app.db_pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|r| r.version)
+            .collect();
+    let (pending_version, changed_version) = (versions[0], versions[1]);
+    sqlx::query!(
+        "DELETE FROM _sqlx_migrations WHERE version = $1",
+        pending_version
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    sqlx::query!(
+        "UPDATE _sqlx_migrations SET checksum = '\\x00' WHERE version = $1",
+        changed_version
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    sqlx::query!(
+        r#"
+        INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time)
+        VALUES (99990101000000, 'from newer release', true, '\x00', 0)
+        "#
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    let response = app.get_api_migrations().await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(report["compatible"], false);
+    assert_eq!(
+        report["unknown_versions"],
+        serde_json::json!([99990101000000_i64])
+    );
+    let state_of = |version: i64| {
+        report["migrations"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|m| m["version"] == version)
+            .unwrap()["state"]
+            .clone()
+    };
+    assert_eq!(state_of(pending_version), "pending");
+    assert_eq!(state_of(changed_version), "checksum_mismatch");
+}
+
+#[tokio::test]
+async fn migration_report_requires_api_key() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!("{}/api/v1/migrations", app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(401, response.status().as_u16());
+}
@@ -0,0 +1,106 @@
+//! tests/api/api_rate_limit.rs
+
+use crate::helpers::{spawn_app, spawn_app_with, TestApp};
+use secrecy::ExposeSecret;
+use std::collections::HashMap;
+use zero2prod::configuration::{ApiKeyRateLimitSettings, ApiRateLimitSettings};
+
+async fn spawn_app_with_api_rate_limit(
+    max_requests: u32,
+    keys: HashMap<String, ApiKeyRateLimitSettings>,
+) -> TestApp {
+    spawn_app_with(|c| {
+        c.application.api_rate_limit = Some(ApiRateLimitSettings {
+            max_requests,
+            interval_seconds: 3600,
+            keys,
+        })
+    })
+    .await
+}
+
+async fn post_admin_graphql_with_api_key(app: &TestApp) -> reqwest::Response {
+    app.api_client
+        .post(format!("{}/admin/api/graphql", &app.address))
+        .bearer_auth(app.api_key.expose_secret())
+        .json(&serde_json::json!({ "query": "{ subscribers { email } }" }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+fn header<'a>(response: &'a reqwest::Response, name: &str) -> &'a str {
+    response.headers().get(name).unwrap().to_str().unwrap()
+}
+
+#[tokio::test]
+async fn api_calls_report_quota_and_are_rejected_beyond_limit() {
+    // Arrange
+    let app = spawn_app_with_api_rate_limit(2, HashMap::new()).await;
+
+    // Act
+    let first = app.get_api_migrations().await;
+    let second = post_admin_graphql_with_api_key(&app).await;
+    let rejected = app.get_api_migrations().await;
+
+    // Assert
+    assert_eq!(200, first.status().as_u16());
+    assert_eq!(header(&first, "X-RateLimit-Limit"), "2");
+    assert_eq!(header(&first, "X-RateLimit-Remaining"), "1");
+    assert_eq!(200, second.status().as_u16());
+    assert_eq!(header(&second, "X-RateLimit-Remaining"), "0");
+    assert_eq!(429, rejected.status().as_u16());
+    assert_eq!(header(&rejected, "X-RateLimit-Remaining"), "0");
+    assert!(header(&rejected, "Retry-After").parse::<u64>().unwrap() > 0);
+}
+
+#[tokio::test]
+async fn limit_of_api_key_overrides_default_limit() {
+    // Arrange
+    let keys = HashMap::from([(
+        "default".to_string(),
+        ApiKeyRateLimitSettings {
+            max_requests: 3,
+            interval_seconds: 3600,
+        },
+    )]);
+    let app = spawn_app_with_api_rate_limit(1, keys).await;
+
+    // Act
+    for _ in 0..3 {
+        assert_eq!(200, app.get_api_migrations().await.status().as_u16());
+    }
+    let rejected = app.get_api_migrations().await;
+
+    // Assert
+    assert_eq!(429, rejected.status().as_u16());
+    assert_eq!(header(&rejected, "X-RateLimit-Limit"), "3");
+}
+
+#[tokio::test]
+async fn web_ui_sessions_are_not_rate_limited() {
+    // Arrange
+    let app = spawn_app_with_api_rate_limit(1, HashMap::new()).await;
+    app.test_user.login(&app).await;
+    post_admin_graphql_with_api_key(&app).await;
+
+    // Act
+    let body = app.post_admin_graphql("{ subscribers { email } }").await;
+
+    // Assert
+    assert!(body["errors"].is_null());
+    assert!(body["data"]["subscribers"].is_array());
+}
+
+#[tokio::test]
+async fn api_calls_without_rate_limit_have_no_quota_headers() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_api_migrations().await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    assert!(response.headers().get("X-RateLimit-Limit").is_none());
+}
@@ -0,0 +1,244 @@
+//! tests/api/api_tokens.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+
+async fn mint_api_token(app: &TestApp, name: &str) -> String {
+    let html_page = app
+        .api_client
+        .post(format!("{}/admin/api_tokens", &app.address))
+        .form(&[("name", name)])
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .text()
+        .await
+        .unwrap();
+    let start = html_page
+        .find(r#"<pre id="minted_token">"#)
+        .expect("Minted token is missing.")
+        + r#"<pre id="minted_token">"#.len();
+    let end = start + html_page[start..].find("</pre>").unwrap();
+    html_page[start..end].to_string()
+}
+
+async fn post_api_issue(app: &TestApp, token: &str, body: serde_json::Value) -> reqwest::Response {
+    app.api_client
+        .post(format!("{}/api/v1/issues", &app.address))
+        .bearer_auth(token)
+        .json(&body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn num_issues(app: &TestApp) -> Option<i64> {
+    sqlx::query!("SELECT COUNT(*) AS count FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_manage_api_tokens() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_response_from_url("/admin/api_tokens").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn minted_token_is_shown_once_and_listed_by_name() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let token = mint_api_token(&app, "CI publish job").await;
+    let html_page = app
+        .get_response_from_url("/admin/api_tokens")
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    // Assert
+    assert!(token.starts_with("z2p_"));
+    assert!(html_page.contains("CI publish job"));
+    assert!(html_page.contains("never used"));
+    assert!(!html_page.contains(&token));
+}
+
+#[tokio::test]
+async fn api_token_must_have_a_name() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!("{}/admin/api_tokens", &app.address))
+        .form(&[("name", " ")])
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/api_tokens");
+    let html_page = app
+        .get_response_from_url("/admin/api_tokens")
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("You must set a name for the API token."));
+    assert_eq!(app.num_rows_of_table("api_tokens").await, 0);
+}
+
+#[tokio::test]
+async fn issue_is_published_with_api_token() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let token = mint_api_token(&app, "CI publish job").await;
+
+    // Act
+    let response = post_api_issue(
+        &app,
+        &token,
+        serde_json::json!({ "title": "API issue", "markdown_content": "**Hello**" }),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let published: serde_json::Value = response.json().await.unwrap();
+    let issue_id = published["issue_id"].as_str().unwrap();
+    let title = sqlx::query!(
+        "SELECT title FROM newsletter_issues WHERE newsletter_issue_id = $1",
+        uuid::Uuid::parse_str(issue_id).unwrap()
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .title;
+    assert_eq!(title, "API issue");
+    let html_page = app
+        .get_response_from_url("/admin/api_tokens")
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("last used"));
+}
+
+#[tokio::test]
+async fn invalid_issue_is_rejected_with_reason() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let token = mint_api_token(&app, "CI publish job").await;
+
+    // Act
+    let response = post_api_issue(&app, &token, serde_json::json!({ "title": "No content" })).await;
+
+    // Assert
+    assert_eq!(400, response.status().as_u16());
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        error["error"],
+        "You must set text content for your newsletter."
+    );
+    assert_eq!(num_issues(&app).await, Some(0));
+}
+
+#[tokio::test]
+async fn retried_publish_with_idempotency_key_publishes_issue_once() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let token = mint_api_token(&app, "CI publish job").await;
+    let idempotency_key = uuid::Uuid::new_v4().to_string();
+    let post_issue = |title: &'static str| {
+        app.api_client
+            .post(format!("{}/api/v1/issues", &app.address))
+            .bearer_auth(&token)
+            .header("Idempotency-Key", &idempotency_key)
+            .json(&serde_json::json!({ "title": title, "markdown_content": "**Hello**" }))
+            .send()
+    };
+
+    // Act
+    let first = post_issue("API issue").await.unwrap();
+    let retried = post_issue("API issue").await.unwrap();
+    let reused = post_issue("Other API issue").await.unwrap();
+
+    // Assert
+    assert_eq!(200, first.status().as_u16());
+    assert_eq!(200, retried.status().as_u16());
+    assert_eq!(422, reused.status().as_u16());
+    let first: serde_json::Value = first.json().await.unwrap();
+    let retried: serde_json::Value = retried.json().await.unwrap();
+    assert_eq!(first["issue_id"], retried["issue_id"]);
+    assert_eq!(num_issues(&app).await, Some(1));
+}
+
+#[tokio::test]
+async fn revoked_or_unknown_token_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let token = mint_api_token(&app, "CI publish job").await;
+    let token_id = sqlx::query!("SELECT token_id FROM api_tokens")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .token_id;
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!(
+            "{}/admin/api_tokens/{}/revoke",
+            &app.address, token_id
+        ))
+        .send()
+        .await
+        .unwrap();
+    let issue = serde_json::json!({ "title": "API issue", "text_content": "Hello" });
+    let revoked = post_api_issue(&app, &token, issue.clone()).await;
+    let unknown = post_api_issue(&app, "z2p_unknown", issue).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/api_tokens");
+    assert_eq!(401, revoked.status().as_u16());
+    assert_eq!(401, unknown.status().as_u16());
+    assert_eq!(num_issues(&app).await, Some(0));
+}
+
+#[tokio::test]
+async fn revoking_unknown_token_returns_404() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!(
+            "{}/admin/api_tokens/{}/revoke",
+            &app.address,
+            uuid::Uuid::new_v4()
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(404, response.status().as_u16());
+}
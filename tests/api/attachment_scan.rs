@@ -0,0 +1,165 @@
+//! tests/api/attachment_scan.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app_with, TestApp};
+use crate::newsletter::valid_newsletter_form_data;
+use base64::Engine;
+use wiremock::{
+    matchers::{header, method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+use zero2prod::configuration::{
+    AttachmentScanSettings, AttachmentScannerSettings, DetectionAction,
+};
+use zero2prod::routes::NewsletterFormData;
+
+/// Spawn app with a mock server as external scan API.
+async fn spawn_app_with_scanner(on_detection: DetectionAction) -> (TestApp, MockServer) {
+    let scan_server = MockServer::start().await;
+    let scan_url = format!("{}/scan", scan_server.uri());
+    let test_app = spawn_app_with(|c| {
+        c.attachment_scan = Some(AttachmentScanSettings {
+            scanner: AttachmentScannerSettings::Api {
+                url: scan_url,
+                token: None,
+            },
+            on_detection,
+            timeout_milliseconds: 1000,
+        });
+    })
+    .await;
+    (test_app, scan_server)
+}
+
+async fn mount_infected_scan_result(scan_server: &MockServer) {
+    Mock::given(path("/scan"))
+        .and(method("POST"))
+        .and(header("Content-Type", "application/octet-stream"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "infected": true,
+            "signature": "Eicar-Test-Signature"
+        })))
+        .expect(1)
+        .mount(scan_server)
+        .await;
+}
+
+fn newsletter_with_attachment() -> NewsletterFormData {
+    NewsletterFormData {
+        attachment_name: "issue.pdf".to_string(),
+        attachment_content_type: "application/pdf".to_string(),
+        attachment_content: base64::engine::general_purpose::STANDARD.encode(b"%PDF-1.4 issue"),
+        ..valid_newsletter_form_data()
+    }
+}
+
+async fn get_stored_scan_results(test_app: &TestApp) -> Vec<(String, Option<String>)> {
+    sqlx::query!(
+        r#"SELECT scan_status::TEXT AS "scan_status!", scan_signature FROM newsletter_issue_attachments"#
+    )
+    .fetch_all(&test_app.db_pool)
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|r| (r.scan_status, r.scan_signature))
+    .collect()
+}
+
+#[tokio::test]
+async fn infected_attachment_is_rejected() {
+    // Arrange
+    let (test_app, scan_server) = spawn_app_with_scanner(DetectionAction::Block).await;
+    mount_infected_scan_result(&scan_server).await;
+    test_app.test_user.login(&test_app).await;
+
+    // Act
+    let response = test_app
+        .post_newsletters(&newsletter_with_attachment())
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let html_page = test_app.get_publish_newsletter_html().await;
+    assert!(html_page.contains(
+        "<p><i>The attachment has been rejected, because malware was detected: \
+        Eicar-Test-Signature</i></p>"
+    ));
+    let num_issues = sqlx::query!(r#"SELECT COUNT(*) AS "count!" FROM newsletter_issues"#)
+        .fetch_one(&test_app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(num_issues, 0);
+}
+
+#[tokio::test]
+async fn infected_attachment_is_stored_flagged() {
+    // Arrange
+    let (test_app, scan_server) = spawn_app_with_scanner(DetectionAction::Flag).await;
+    mount_infected_scan_result(&scan_server).await;
+    test_app.test_user.login(&test_app).await;
+
+    // Act
+    let response = test_app
+        .post_newsletters(&newsletter_with_attachment())
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    assert_eq!(
+        get_stored_scan_results(&test_app).await,
+        vec![(
+            "infected".to_string(),
+            Some("Eicar-Test-Signature".to_string())
+        )]
+    );
+}
+
+#[tokio::test]
+async fn clean_attachment_is_stored_as_clean() {
+    // Arrange
+    let (test_app, scan_server) = spawn_app_with_scanner(DetectionAction::Block).await;
+    Mock::given(path("/scan"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "infected": false
+        })))
+        .expect(1)
+        .mount(&scan_server)
+        .await;
+    test_app.test_user.login(&test_app).await;
+
+    // Act
+    let response = test_app
+        .post_newsletters(&newsletter_with_attachment())
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    assert_eq!(
+        get_stored_scan_results(&test_app).await,
+        vec![("clean".to_string(), None)]
+    );
+}
+
+#[tokio::test]
+async fn attachment_is_rejected_if_scanner_fails() {
+    // Arrange
+    let (test_app, scan_server) = spawn_app_with_scanner(DetectionAction::Flag).await;
+    Mock::given(path("/scan"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&scan_server)
+        .await;
+    test_app.test_user.login(&test_app).await;
+
+    // Act
+    let response = test_app
+        .post_newsletters(&newsletter_with_attachment())
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let html_page = test_app.get_publish_newsletter_html().await;
+    assert!(html_page.contains(
+        "<p><i>The attachment could not be scanned for malware. Please try again later.</i></p>"
+    ));
+}
@@ -0,0 +1,130 @@
+//! tests/api/bounce_webhook.rs
+
+use crate::helpers::spawn_app;
+use crate::newsletter::{
+    create_confirmed_subscriber, valid_newsletter_form_data, when_sending_an_email,
+};
+use wiremock::ResponseTemplate;
+use zero2prod::routes::BounceNotification;
+
+fn bounce_of(email: &str, bounce_type: &str, tag: &str) -> BounceNotification {
+    BounceNotification {
+        bounce_type: bounce_type.to_string(),
+        email: email.to_string(),
+        tag: tag.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn bounce_notifications_without_credentials_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!("{}/webhooks/email/bounce", app.address))
+        .json(&bounce_of("ursula@example.com", "HardBounce", ""))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(app.num_rows_of_table("suppressions").await, 0);
+}
+
+#[tokio::test]
+async fn hard_bounce_suppresses_email() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .post_bounce_notification(&bounce_of("ursula@example.com", "HardBounce", ""))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let suppression = sqlx::query!("SELECT email, reason FROM suppressions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(suppression.email, "ursula@example.com");
+    assert_eq!(suppression.reason, "hard_bounce");
+}
+
+#[tokio::test]
+async fn spam_complaint_suppresses_email() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    app.post_bounce_notification(&bounce_of("ursula@example.com", "SpamComplaint", ""))
+        .await;
+
+    // Assert
+    let suppression = sqlx::query!("SELECT reason FROM suppressions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(suppression.reason, "spam_complaint");
+}
+
+#[tokio::test]
+async fn bounced_newsletter_counts_as_failed_delivery() {
+    // Arrange
+    let app = spawn_app().await;
+    let (email, _) = create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.post_newsletters(&valid_newsletter_form_data()).await;
+    app.dispatch_all_pending_emails().await;
+    let issue_id = app.get_newsletter_issue_id().await;
+
+    // Act
+    app.post_bounce_notification(&bounce_of(
+        email.as_ref(),
+        "HardBounce",
+        &issue_id.to_string(),
+    ))
+    .await;
+
+    // Assert
+    let issue = sqlx::query!(
+        "SELECT num_delivered_newsletters, num_failed_deliveries FROM newsletter_issues"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(issue.num_delivered_newsletters, Some(0));
+    assert_eq!(issue.num_failed_deliveries, Some(1));
+    let num_failed_events = sqlx::query!(
+        "SELECT COUNT(*) AS \"count!\" FROM subscriber_events WHERE kind = 'delivery_failed'"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .count;
+    assert_eq!(num_failed_events, 1);
+}
+
+#[tokio::test]
+async fn auto_replies_and_unknown_bounce_types_are_ignored() {
+    // Arrange
+    let app = spawn_app().await;
+
+    for bounce_type in ["AutoResponder", "Unknown"] {
+        // Act
+        let response = app
+            .post_bounce_notification(&bounce_of("ursula@example.com", bounce_type, ""))
+            .await;
+
+        // Assert
+        assert_eq!(response.status().as_u16(), 200);
+    }
+    assert_eq!(app.num_rows_of_table("suppressions").await, 0);
+}
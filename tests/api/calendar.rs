@@ -0,0 +1,76 @@
+//! tests/api/calendar.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+use crate::newsletter::{create_confirmed_subscriber, valid_newsletter_form_data};
+use chrono::{Months, Utc};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_calendar() {
+    // Arrange
+    let test_app = spawn_app().await;
+
+    // Act
+    let response = test_app.get_response_from_url("/admin/calendar").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login")
+}
+
+#[tokio::test]
+async fn calendar_shows_issues_and_drafts_in_their_month() {
+    // Arrange
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    test_app.test_user.login(&test_app).await;
+    let mut published = valid_newsletter_form_data();
+    published.title = "Published issue".to_string();
+    test_app.post_newsletters(&published).await;
+    let next_month = Utc::now().checked_add_months(Months::new(1)).unwrap();
+    let mut scheduled = valid_newsletter_form_data();
+    scheduled.title = "Scheduled issue".to_string();
+    scheduled.scheduled_at = next_month.to_rfc3339();
+    test_app.post_newsletters(&scheduled).await;
+    let mut draft = valid_newsletter_form_data();
+    draft.title = "Planned draft".to_string();
+    draft.scheduled_at = next_month.to_rfc3339();
+    test_app.post_newsletter_draft(&draft).await;
+
+    // Act
+    let current_page = test_app
+        .get_response_from_url("/admin/calendar")
+        .await
+        .text()
+        .await
+        .unwrap();
+    let next_page = test_app
+        .get_response_from_url(&format!(
+            "/admin/calendar?month={}",
+            next_month.format("%Y-%m")
+        ))
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    // Assert
+    assert!(current_page.contains("Published issue</a> <i>(sending"));
+    assert!(!current_page.contains("Scheduled issue"));
+    assert!(next_page.contains("Scheduled issue</a> <i>(scheduled"));
+    assert!(next_page.contains("Planned draft</a> <i>(draft"));
+    assert!(!next_page.contains("Published issue"));
+}
+
+#[tokio::test]
+async fn calendar_rejects_invalid_month() {
+    // Arrange
+    let test_app = spawn_app().await;
+    test_app.test_user.login(&test_app).await;
+
+    // Act
+    let response = test_app
+        .get_response_from_url("/admin/calendar?month=2024-13")
+        .await;
+
+    // Assert
+    assert_eq!(404, response.status().as_u16());
+}
@@ -0,0 +1,100 @@
+//! tests/api/config_reload.rs
+
+use crate::helpers::spawn_app;
+use std::collections::HashMap;
+use zero2prod::config_reload::publish_tunable_settings;
+use zero2prod::configuration::{ApiRateLimitSettings, RateLimitSettings};
+use zero2prod::send_rate_limit::SendRateLimiter;
+use zero2prod::telemetry::set_log_level;
+
+#[tokio::test]
+async fn reloaded_api_rate_limit_applies_without_restart() {
+    // Arrange
+    let app = spawn_app().await;
+    let response = app.get_api_migrations().await;
+    assert!(response.headers().get("X-RateLimit-Limit").is_none());
+    let mut reloaded = app.tunable_settings.borrow().clone();
+    reloaded.api_rate_limit = Some(ApiRateLimitSettings {
+        max_requests: 1,
+        interval_seconds: 3600,
+        keys: HashMap::new(),
+    });
+
+    // Act
+    let published = publish_tunable_settings(&app.tunable_settings, reloaded);
+
+    // Assert
+    assert!(published);
+    let first = app.get_api_migrations().await;
+    assert_eq!(200, first.status().as_u16());
+    assert_eq!(first.headers().get("X-RateLimit-Limit").unwrap(), "1");
+    let rejected = app.get_api_migrations().await;
+    assert_eq!(429, rejected.status().as_u16());
+}
+
+#[tokio::test]
+async fn reloaded_retry_settings_are_published_to_workers() {
+    // Arrange
+    let app = spawn_app().await;
+    let worker_settings = app.tunable_settings.subscribe();
+    let mut reloaded = app.tunable_settings.borrow().clone();
+    reloaded.n_retries = 7;
+    reloaded.execute_retry_after_milliseconds = 5000;
+
+    // Act
+    let published = publish_tunable_settings(&app.tunable_settings, reloaded);
+
+    // Assert
+    assert!(published);
+    assert!(worker_settings.has_changed().unwrap());
+    let worker_settings = worker_settings.borrow();
+    assert_eq!(worker_settings.n_retries, 7);
+    assert_eq!(worker_settings.retry_time_delta().num_seconds(), 5);
+}
+
+#[tokio::test]
+async fn reloaded_send_rate_limit_is_applied_by_workers() {
+    // Arrange
+    let app = spawn_app().await;
+    let rate_limit = SendRateLimiter::new(app.tunable_settings.subscribe());
+    assert!(rate_limit.bucket().is_none());
+    let mut reloaded = app.tunable_settings.borrow().clone();
+    reloaded.email_rate_limit = Some(RateLimitSettings {
+        max_emails: 2,
+        interval_seconds: 3600,
+    });
+
+    // Act
+    let published = publish_tunable_settings(&app.tunable_settings, reloaded);
+
+    // Assert
+    assert!(published);
+    assert_eq!(rate_limit.bucket().unwrap().take(10), 2);
+}
+
+#[tokio::test]
+async fn unchanged_settings_are_not_published() {
+    // Arrange
+    let app = spawn_app().await;
+    let worker_settings = app.tunable_settings.subscribe();
+    let reloaded = app.tunable_settings.borrow().clone();
+
+    // Act
+    let published = publish_tunable_settings(&app.tunable_settings, reloaded);
+
+    // Assert
+    assert!(!published);
+    assert!(!worker_settings.has_changed().unwrap());
+}
+
+#[tokio::test]
+async fn invalid_log_level_is_rejected() {
+    // Arrange
+    spawn_app().await;
+
+    // Act
+    let result = set_log_level("zero2prod=[");
+
+    // Assert
+    assert!(result.is_err());
+}
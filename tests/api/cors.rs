@@ -0,0 +1,103 @@
+//! tests/api/cors.rs
+
+use crate::helpers::{spawn_app, spawn_app_with, TestApp};
+use zero2prod::configuration::CorsSettings;
+
+const EMBEDDING_SITE: &str = "https://www.example.com";
+
+async fn spawn_app_with_cors() -> TestApp {
+    spawn_app_with(|c| {
+        c.application.cors = Some(CorsSettings {
+            allowed_origins: vec![EMBEDDING_SITE.to_string()],
+            allowed_methods: vec!["GET".to_string(), "POST".to_string()],
+            allowed_headers: vec!["Content-Type".to_string()],
+            max_age_seconds: 600,
+        })
+    })
+    .await
+}
+
+async fn preflight_subscribe(app: &TestApp, origin: &str) -> reqwest::Response {
+    app.api_client
+        .request(
+            reqwest::Method::OPTIONS,
+            format!("{}/subscriptions", &app.address),
+        )
+        .header("Origin", origin)
+        .header("Access-Control-Request-Method", "POST")
+        .header("Access-Control-Request-Headers", "content-type")
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn get_subscription_form(app: &TestApp, origin: &str) -> reqwest::Response {
+    app.api_client
+        .get(format!("{}/subscriptions", &app.address))
+        .header("Origin", origin)
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn preflight_of_subscribe_is_allowed_for_configured_origin() {
+    // Arrange
+    let app = spawn_app_with_cors().await;
+
+    // Act
+    let response = preflight_subscribe(&app, EMBEDDING_SITE).await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let headers = response.headers();
+    assert_eq!(
+        headers.get("Access-Control-Allow-Origin").unwrap(),
+        EMBEDDING_SITE
+    );
+    let allowed_methods = headers
+        .get("Access-Control-Allow-Methods")
+        .unwrap()
+        .to_str()
+        .unwrap();
+    assert!(allowed_methods.contains("POST"));
+    assert_eq!(headers.get("Access-Control-Max-Age").unwrap(), "600");
+}
+
+#[tokio::test]
+async fn responses_of_public_routes_are_readable_by_configured_origin_only() {
+    // Arrange
+    let app = spawn_app_with_cors().await;
+
+    // Act
+    let allowed = get_subscription_form(&app, EMBEDDING_SITE).await;
+    let other = get_subscription_form(&app, "https://other.example.org").await;
+
+    // Assert
+    assert_eq!(200, allowed.status().as_u16());
+    assert_eq!(
+        allowed
+            .headers()
+            .get("Access-Control-Allow-Origin")
+            .unwrap(),
+        EMBEDDING_SITE
+    );
+    assert_eq!(200, other.status().as_u16());
+    assert!(other.headers().get("Access-Control-Allow-Origin").is_none());
+}
+
+#[tokio::test]
+async fn cors_is_disabled_without_configuration() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = get_subscription_form(&app, EMBEDDING_SITE).await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    assert!(response
+        .headers()
+        .get("Access-Control-Allow-Origin")
+        .is_none());
+}
@@ -0,0 +1,123 @@
+//! tests/api/delivery_comparison.rs
+
+use crate::helpers::{spawn_app, TestApp};
+use crate::newsletter::{
+    create_confirmed_subscriber, valid_newsletter_form_data, when_sending_an_email,
+};
+use reqwest::Url;
+use uuid::Uuid;
+use wiremock::ResponseTemplate;
+use zero2prod::routes::NewsletterFormData;
+
+/// Publish an issue and deliver it to all confirmed subscribers.
+async fn publish_and_deliver(app: &TestApp, title: &str) -> Uuid {
+    let form = NewsletterFormData {
+        title: title.to_string(),
+        ..valid_newsletter_form_data()
+    };
+    app.post_newsletters(&form).await;
+    app.dispatch_all_pending_emails().await;
+    sqlx::query!(
+        "SELECT newsletter_issue_id FROM newsletter_issues WHERE title = $1",
+        title
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .newsletter_issue_id
+}
+
+/// Emails of the issue with the given title sent to subscribers.
+async fn sent_emails(app: &TestApp, title: &str) -> Vec<wiremock::Request> {
+    app.email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|request| {
+            let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            body["Subject"] == title
+        })
+        .collect()
+}
+
+fn open_tracking_link(app: &TestApp, email_request: &wiremock::Request) -> Url {
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    let mut link = linkify::LinkFinder::new()
+        .links(body["HtmlBody"].as_str().unwrap())
+        .map(|l| Url::parse(l.as_str()).unwrap())
+        .find(|l| l.path().starts_with("/open/"))
+        .unwrap();
+    link.set_port(Some(app.port)).unwrap();
+    link
+}
+
+#[tokio::test]
+async fn comparison_shows_rates_of_selected_issues() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.test_user.login(&app).await;
+    let first_issue_id = publish_and_deliver(&app, "First issue").await;
+    let second_issue_id = publish_and_deliver(&app, "Second issue").await;
+    // one reader opens the first issue, another reader unsubscribes after the second
+    let first_emails = sent_emails(&app, "First issue").await;
+    reqwest::get(open_tracking_link(&app, &first_emails[0]))
+        .await
+        .unwrap();
+    let second_emails = sent_emails(&app, "Second issue").await;
+    let unsubscribe_link = app.get_email_links(&second_emails[1]).html.unsubscribe;
+    reqwest::get(unsubscribe_link).await.unwrap();
+
+    // Act
+    let html_page = app
+        .get_response_from_url(&format!(
+            "/admin/delivery_overview/compare?issue_id={}&issue_id={}",
+            first_issue_id, second_issue_id
+        ))
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    // Assert
+    assert!(html_page.contains("id=\"comparison\""));
+    assert!(html_page.contains("<td>2 (100.0%)</td>"));
+    // first issue: 1 of 2 opened, no unsubscribes
+    assert!(html_page.contains("<td>1 (50.0%)</td>\n                <td>0 (0.0%)</td>"));
+    // second issue: no opens, 1 of 2 unsubscribed
+    assert!(html_page.contains("<td>0 (0.0%)</td>\n                <td>1 (50.0%)</td>"));
+    assert!(html_page.contains("<b>Open rate</b>"));
+}
+
+#[tokio::test]
+async fn comparison_requires_two_issues() {
+    // Arrange
+    let app = spawn_app().await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.test_user.login(&app).await;
+    let issue_id = publish_and_deliver(&app, "Only issue").await;
+
+    // Act
+    let html_page = app
+        .get_response_from_url(&format!(
+            "/admin/delivery_overview/compare?issue_id={}",
+            issue_id
+        ))
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    // Assert
+    assert!(html_page.contains("Select at least two issues to compare."));
+    assert!(!html_page.contains("id=\"comparison\""));
+}
@@ -0,0 +1,147 @@
+//! tests/api/delivery_dead_letters.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app_with, TestApp};
+use crate::newsletter::{
+    create_confirmed_subscriber, valid_newsletter_form_data, when_sending_an_email,
+};
+
+use std::time::Duration;
+use wiremock::matchers::method;
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::delivery_attempts::DeliveryAttempts;
+use zero2prod::delivery_queue::{DeliveryQueue, PgDeliveryQueue};
+use zero2prod::issue_delivery_worker::{try_execute_task, ExecutionOutcome};
+
+/// Publish an issue to all confirmed subscribers without delivering it.
+pub async fn publish_issue(test_app: &TestApp) {
+    test_app.test_user.login(test_app).await;
+    let response = test_app
+        .post_newsletters(&valid_newsletter_form_data())
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+}
+
+/// Simulate a crash of the worker by dropping the execution, while the email
+/// is sent; the attempt stays in flight.
+pub async fn crash_execution(test_app: &TestApp, attempts: &DeliveryAttempts) {
+    let execution = try_execute_task(
+        &test_app.db_pool,
+        &test_app.email_client,
+        test_app.n_retries,
+        test_app.time_delta,
+        test_app.batch_size,
+        &test_app.address,
+        test_app.warm_up.as_ref(),
+        None,
+        &test_app.locale_fallbacks,
+        attempts,
+    );
+    assert!(tokio::time::timeout(Duration::from_millis(500), execution)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn task_is_quarantined_after_max_task_crashes() {
+    // Arrange
+    let test_app = spawn_app_with(|c| c.emailclient.max_task_crashes = 2).await;
+    create_confirmed_subscriber(&test_app).await;
+    publish_issue(&test_app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+        .mount(&test_app.email_server)
+        .await;
+
+    // Act
+    for _ in 0..2 {
+        crash_execution(&test_app, &test_app.delivery_attempts).await;
+    }
+    let outcome = test_app.execute_task().await;
+
+    // Assert
+    assert!(matches!(outcome, ExecutionOutcome::TaskCompleted));
+    assert_eq!(test_app.num_rows_of_table("delivery_dead_letters").await, 1);
+    assert_eq!(test_app.num_rows_of_table("delivery_attempts").await, 0);
+    let queue = PgDeliveryQueue::new(test_app.db_pool.clone());
+    assert!(queue.is_empty().await.unwrap());
+    let overview = test_app.get_newsletter_delivery_overview().await;
+    assert_eq!(overview.num_failed_deliveries, Some(1));
+    assert_eq!(overview.num_delivered_newsletters, Some(0));
+    let html = test_app.get_response_from_url("/admin/workers").await;
+    let html = html.text().await.unwrap();
+    assert!(html.contains(r#"<p id="dead_letter">"#));
+    assert!(html.contains("no panic message, the process was stopped"));
+}
+
+#[tokio::test]
+async fn dead_letter_keeps_panic_message_of_crashed_loop() {
+    // Arrange
+    let test_app = spawn_app_with(|c| c.emailclient.max_task_crashes = 1).await;
+    create_confirmed_subscriber(&test_app).await;
+    publish_issue(&test_app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+        .mount(&test_app.email_server)
+        .await;
+    let crashing_loop = DeliveryAttempts::new(1);
+    crash_execution(&test_app, &crashing_loop).await;
+
+    // Act
+    crashing_loop
+        .record_panic(&test_app.db_pool, "poison subscriber")
+        .await
+        .unwrap();
+    test_app.execute_task().await;
+
+    // Assert
+    let dead_letter = sqlx::query!("SELECT n_crashes, panic_message FROM delivery_dead_letters")
+        .fetch_one(&test_app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(dead_letter.n_crashes, 1);
+    assert_eq!(
+        dead_letter.panic_message.as_deref(),
+        Some("poison subscriber")
+    );
+    let html = test_app.get_response_from_url("/admin/workers").await;
+    assert!(html.text().await.unwrap().contains("poison subscriber"));
+}
+
+#[tokio::test]
+async fn crashed_task_is_executed_alone() {
+    // Arrange
+    let test_app = spawn_app_with(|c| c.emailclient.batch_size = 10).await;
+    create_confirmed_subscriber(&test_app).await;
+    create_confirmed_subscriber(&test_app).await;
+    publish_issue(&test_app).await;
+    let crashing_loop = DeliveryAttempts::new(3);
+    {
+        // batches of emails are sent to another endpoint
+        let _guard = Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+            .mount_as_scoped(&test_app.email_server)
+            .await;
+        crash_execution(&test_app, &crashing_loop).await;
+    }
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&test_app.email_server)
+        .await;
+
+    // Act - Part 1 - execute crashed tasks
+    test_app.execute_task().await;
+
+    // Assert - Part 1
+    let overview = test_app.get_newsletter_delivery_overview().await;
+    assert_eq!(overview.num_delivered_newsletters, Some(1));
+    assert_eq!(test_app.num_rows_of_table("delivery_attempts").await, 1);
+
+    // Act - Part 2 - execute remaining task
+    test_app.execute_task().await;
+
+    // Assert - Part 2
+    let overview = test_app.get_newsletter_delivery_overview().await;
+    assert_eq!(overview.num_delivered_newsletters, Some(2));
+    assert_eq!(test_app.num_rows_of_table("delivery_attempts").await, 0);
+    assert_eq!(test_app.num_rows_of_table("delivery_dead_letters").await, 0);
+}
@@ -2,10 +2,15 @@
 
 use crate::helpers::{assert_is_redirect_to, spawn_app};
 use crate::newsletter::{
-    create_confirmed_subscriber, valid_newsletter_form_data, when_sending_an_email,
+    create_confirmed_subscriber, make_valid_subscriber_email_invalid, valid_newsletter_form_data,
+    when_sending_an_email,
 };
 
+use chrono::{TimeDelta, Utc};
+use scraper::{Html, Selector};
 use wiremock::ResponseTemplate;
+use zero2prod::issue_delivery_worker::{email_template_version, ExecutionOutcome};
+use zero2prod::routes::{DeliveryAction, NewsletterFormData};
 
 #[tokio::test]
 async fn overview_of_delivered_newsletters_contains_newsletter_title() {
@@ -91,3 +96,290 @@ async fn following_issue_id_link_html_contains_delivery_info() {
 
     // Mock verifies on Drop that we have sent one newsletter email
 }
+
+#[tokio::test]
+async fn scheduled_newsletter_is_not_delivered_before_scheduled_time() {
+    // Arrange
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&test_app.email_server)
+        .await;
+
+    // Act - Part 1 - Login
+    test_app.test_user.login(&test_app).await;
+
+    // Act - Part 2 - Publish newsletter scheduled in one hour
+    let newsletter = NewsletterFormData {
+        scheduled_at: (Utc::now() + TimeDelta::hours(1)).to_rfc3339(),
+        ..valid_newsletter_form_data()
+    };
+    let response = test_app.post_newsletters(&newsletter).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    // Act - Part 3 - Task of scheduled newsletter is postponed
+    let outcome = test_app.execute_task().await;
+    assert!(matches!(outcome, ExecutionOutcome::PostponedTasks));
+
+    // Assert
+    let issue_id_html = test_app.get_delivered_newsletter_issue_id_html().await;
+    assert!(issue_id_html.contains("<p><i>Delivery status: scheduled for "));
+
+    // Mock verifies on Drop that we have not sent any newsletter email
+}
+
+#[tokio::test]
+async fn urgent_issue_is_delivered_next_to_backlog_of_other_issue() {
+    // Arrange
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&test_app.email_server)
+        .await;
+    test_app.test_user.login(&test_app).await;
+
+    // Act - Part 1 - Publish regular and urgent issue
+    test_app
+        .post_newsletters(&valid_newsletter_form_data())
+        .await;
+    let urgent_newsletter = NewsletterFormData {
+        title: "Urgent announcement".to_string(),
+        delivery_weight: "80".to_string(),
+        ..valid_newsletter_form_data()
+    };
+    let response = test_app.post_newsletters(&urgent_newsletter).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    // Act - Part 2 - Deliver both issues
+    test_app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let html_page = test_app.get_delivery_overview_html().await;
+    let document = Html::parse_document(&html_page);
+    let selector = Selector::parse("a#issue").unwrap();
+    let urgent_issue_link = document
+        .select(&selector)
+        .find(|element| element.inner_html() == "Urgent announcement")
+        .and_then(|element| element.value().attr("href"))
+        .unwrap();
+    let issue_id_html = test_app
+        .get_response_from_url(urgent_issue_link)
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(issue_id_html.contains("<p><i>delivery_weight: 80</i></p>"));
+    assert!(issue_id_html.contains("<p><i>Delivery status: finished.</i></p>"));
+
+    // Mock verifies on Drop that we have sent both newsletter emails
+}
+
+#[tokio::test]
+async fn paused_delivery_is_resumed_later() {
+    // Arrange
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    test_app.test_user.login(&test_app).await;
+    test_app
+        .post_newsletters(&valid_newsletter_form_data())
+        .await;
+    let issue_id = test_app.get_newsletter_issue_id().await;
+
+    // Act - Part 1 - Pause delivery
+    let response = test_app
+        .post_delivery_action(issue_id, DeliveryAction::Pause)
+        .await;
+    let issue_page = format!("/admin/delivery_overview?newsletter_issue_id={}", issue_id);
+    assert_is_redirect_to(&response, &issue_page);
+    let mock_guard = when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount_as_scoped(&test_app.email_server)
+        .await;
+    test_app.dispatch_all_pending_emails().await;
+    drop(mock_guard);
+
+    // Assert - Part 1
+    let issue_id_html = test_app
+        .get_response_from_url(&issue_page)
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(issue_id_html.contains("<p><i>Delivery has been paused for 1 recipients.</i></p>"));
+    assert!(issue_id_html.contains("<p><i>Delivery status: paused.</i></p>"));
+
+    // Act - Part 2 - Resume delivery
+    let response = test_app
+        .post_delivery_action(issue_id, DeliveryAction::Resume)
+        .await;
+    assert_is_redirect_to(&response, &issue_page);
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+    test_app.dispatch_all_pending_emails().await;
+
+    // Assert - Part 2
+    let issue_id_html = test_app.get_delivered_newsletter_issue_id_html().await;
+    assert!(issue_id_html.contains("<p><i>Delivery status: finished.</i></p>"));
+}
+
+#[tokio::test]
+async fn cancelled_delivery_shows_undelivered_recipients() {
+    // Arrange
+    let test_app = spawn_app().await;
+    for _ in 0..2 {
+        create_confirmed_subscriber(&test_app).await;
+    }
+    test_app.test_user.login(&test_app).await;
+    test_app
+        .post_newsletters(&valid_newsletter_form_data())
+        .await;
+    let issue_id = test_app.get_newsletter_issue_id().await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&test_app.email_server)
+        .await;
+
+    // Act
+    let response = test_app
+        .post_delivery_action(issue_id, DeliveryAction::Cancel)
+        .await;
+    let issue_page = format!("/admin/delivery_overview?newsletter_issue_id={}", issue_id);
+    assert_is_redirect_to(&response, &issue_page);
+    test_app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let issue_id_html = test_app
+        .get_response_from_url(&issue_page)
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(issue_id_html.contains("<p><i>Delivery has been cancelled for 2 recipients.</i></p>"));
+    assert!(issue_id_html.contains("<p><i>num_undelivered_recipients: 2</i></p>"));
+    assert!(issue_id_html.contains("<p><i>Delivery status: cancelled.</i></p>"));
+
+    // Mock verifies on Drop that we have not sent any newsletter email
+}
+
+#[tokio::test]
+async fn failed_deliveries_are_retried() {
+    // Arrange
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    let (email, _) = create_confirmed_subscriber(&test_app).await;
+    let valid_email = email.as_ref().to_string();
+    make_valid_subscriber_email_invalid(&test_app, email).await;
+    test_app.test_user.login(&test_app).await;
+    test_app
+        .post_newsletters(&valid_newsletter_form_data())
+        .await;
+    let issue_id = test_app.get_newsletter_issue_id().await;
+    let _mock_guard = when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&test_app.email_server)
+        .await;
+    test_app.dispatch_all_pending_emails().await;
+    drop(_mock_guard);
+    assert_eq!(
+        test_app
+            .get_newsletter_delivery_overview()
+            .await
+            .num_failed_deliveries,
+        Some(1)
+    );
+
+    // Act - Part 1 - Fix email address and retry failed deliveries
+    sqlx::query!(
+        "UPDATE subscriptions SET email = $1 WHERE email = $2",
+        valid_email,
+        valid_email.replace("@", "_at_"),
+    )
+    .execute(&test_app.db_pool)
+    .await
+    .unwrap();
+    let response = test_app
+        .post_delivery_action(issue_id, DeliveryAction::RetryFailed)
+        .await;
+    let issue_page = format!("/admin/delivery_overview?newsletter_issue_id={}", issue_id);
+    assert_is_redirect_to(&response, &issue_page);
+
+    // Assert - Part 1
+    let issue_id_html = test_app
+        .get_response_from_url(&issue_page)
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(issue_id_html.contains("<p><i>Delivery has been retried for 1 recipients.</i></p>"));
+    assert!(issue_id_html.contains("<p><i>num_failed_deliveries: 0</i></p>"));
+    assert!(issue_id_html.contains("<p><i>Delivery status: in progress.</i></p>"));
+
+    // Act - Part 2 - Deliver to recipient of failed delivery only
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+    test_app.dispatch_all_pending_emails().await;
+
+    // Assert - Part 2
+    let issue_id_html = test_app.get_delivered_newsletter_issue_id_html().await;
+    assert!(issue_id_html.contains("<p><i>num_delivered_newsletters: 2</i></p>"));
+    assert!(issue_id_html.contains("<p><i>Delivery status: finished.</i></p>"));
+}
+
+#[tokio::test]
+async fn delivered_issue_is_pinned_to_template_version() {
+    // Arrange
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+    test_app.test_user.login(&test_app).await;
+    test_app
+        .post_newsletters(&valid_newsletter_form_data())
+        .await;
+
+    // Act
+    test_app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let template_version = sqlx::query!("SELECT template_version FROM newsletter_issues")
+        .fetch_one(&test_app.db_pool)
+        .await
+        .unwrap()
+        .template_version;
+    assert_eq!(template_version, Some(email_template_version()));
+    let issue_id_html = test_app.get_delivered_newsletter_issue_id_html().await;
+    assert!(issue_id_html.contains(&format!(
+        "<p><i>template_version: {}</i></p>",
+        email_template_version()
+    )));
+    assert!(!issue_id_html.contains("Email templates changed since delivery started."));
+
+    // Act - templates of binary differ from pinned version
+    sqlx::query!("UPDATE newsletter_issues SET template_version = '0000000000000000'")
+        .execute(&test_app.db_pool)
+        .await
+        .unwrap();
+
+    // Assert
+    let issue_id_html = test_app.get_delivered_newsletter_issue_id_html().await;
+    assert!(issue_id_html.contains("Email templates changed since delivery started."));
+}
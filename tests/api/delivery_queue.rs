@@ -0,0 +1,681 @@
+//! tests/api/delivery_queue.rs
+
+use crate::delivery_dead_letters::crash_execution;
+use crate::helpers::{assert_is_redirect_to, spawn_app, spawn_app_with, TestApp};
+use crate::newsletter::{
+    create_confirmed_subscriber, valid_newsletter_form_data, when_sending_an_email,
+};
+
+use chrono::{TimeDelta, Utc};
+use secrecy::ExposeSecret;
+use sqlx::postgres::PgListener;
+use std::time::Duration;
+use uuid::Uuid;
+use wiremock::ResponseTemplate;
+use zero2prod::configuration::{get_configuration, DeliveryQueueSettings};
+use zero2prod::delivery_queue::{DeliveryQueue, PgDeliveryQueue, RedisDeliveryQueue, TASK_VERSION};
+use zero2prod::issue_delivery_worker::{DeliveryTaskStatus, ExecutionOutcome, DELIVERY_CHANNEL};
+use zero2prod::routes::DeliveryAction;
+use zero2prod::startup::get_connection_pool;
+
+/// Publish an issue to one confirmed subscriber without delivering it.
+async fn publish_issue_to_one_subscriber(test_app: &TestApp) {
+    create_confirmed_subscriber(test_app).await;
+    test_app.test_user.login(test_app).await;
+    let response = test_app
+        .post_newsletters(&valid_newsletter_form_data())
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+}
+
+#[tokio::test]
+async fn claimed_tasks_are_dequeued_by_one_worker_only() {
+    // Arrange
+    let test_app = spawn_app().await;
+    publish_issue_to_one_subscriber(&test_app).await;
+    let queue = PgDeliveryQueue::new(test_app.db_pool.clone());
+
+    // Act
+    let (mut claim, tasks) = queue.dequeue(10).await.unwrap();
+    let (_, tasks_of_other_worker) = queue.dequeue(10).await.unwrap();
+
+    // Assert
+    assert_eq!(tasks.len(), 1);
+    assert!(tasks_of_other_worker.is_empty());
+    queue.complete(&mut claim, &tasks[0]).await.unwrap();
+    queue.release(claim).await.unwrap();
+    assert!(queue.is_empty().await.unwrap());
+}
+
+#[tokio::test]
+async fn postponed_retry_increments_retry_counter() {
+    // Arrange
+    let test_app = spawn_app().await;
+    publish_issue_to_one_subscriber(&test_app).await;
+    let queue = PgDeliveryQueue::new(test_app.db_pool.clone());
+    let (mut claim, tasks) = queue.dequeue(10).await.unwrap();
+
+    // Act
+    queue
+        .postpone(
+            &mut claim,
+            &tasks[0],
+            Utc::now() - TimeDelta::seconds(1),
+            true,
+        )
+        .await
+        .unwrap();
+    queue.release(claim).await.unwrap();
+
+    // Assert
+    let (_, tasks) = queue.dequeue(10).await.unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].n_retries, 1);
+}
+
+#[tokio::test]
+async fn tasks_of_newer_versions_are_left_to_newer_workers() {
+    // Arrange
+    let test_app = spawn_app().await;
+    publish_issue_to_one_subscriber(&test_app).await;
+    // task enqueued by a newer binary during a rolling deploy
+    sqlx::query!(
+        "UPDATE issue_delivery_queue SET task_version = $1",
+        TASK_VERSION + 1
+    )
+    .execute(&test_app.db_pool)
+    .await
+    .unwrap();
+    let queue = PgDeliveryQueue::new(test_app.db_pool.clone());
+
+    // Act
+    let (_, tasks) = queue.dequeue(10).await.unwrap();
+
+    // Assert
+    assert!(tasks.is_empty());
+    assert!(!queue.is_empty().await.unwrap());
+    assert_eq!(queue.num_newer_tasks().await.unwrap(), 1);
+    let html = test_app.get_response_from_url("/admin/workers").await;
+    let html = html.text().await.unwrap();
+    assert!(html.contains("1 delivery tasks wait for workers of a newer version."));
+}
+
+#[tokio::test]
+async fn claimed_tasks_record_version_of_worker() {
+    // Arrange
+    let test_app = spawn_app().await;
+    publish_issue_to_one_subscriber(&test_app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+        .mount(&test_app.email_server)
+        .await;
+
+    // Act
+    crash_execution(&test_app, &test_app.delivery_attempts).await;
+
+    // Assert
+    let attempt = sqlx::query!("SELECT worker_version FROM delivery_attempts")
+        .fetch_one(&test_app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(
+        attempt.worker_version.as_deref(),
+        Some(env!("CARGO_PKG_VERSION"))
+    );
+    let html = test_app.get_response_from_url("/admin/workers").await;
+    let html = html.text().await.unwrap();
+    assert!(html.contains(&format!(
+        "in flight at workers of version {}: 1",
+        env!("CARGO_PKG_VERSION")
+    )));
+}
+
+#[tokio::test]
+async fn enqueued_tasks_are_not_dequeued_before_execute_after() {
+    // Arrange
+    let test_app = spawn_app().await;
+    publish_issue_to_one_subscriber(&test_app).await;
+    let queue = PgDeliveryQueue::new(test_app.db_pool.clone());
+    let (mut claim, tasks) = queue.dequeue(10).await.unwrap();
+    let task = &tasks[0];
+    queue.complete(&mut claim, task).await.unwrap();
+    queue.release(claim).await.unwrap();
+
+    // Act
+    let num_tasks = queue
+        .enqueue(
+            task.issue_id,
+            &[task.user_id],
+            Utc::now() + TimeDelta::hours(1),
+        )
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(num_tasks, 1);
+    let (_, tasks) = queue.dequeue(10).await.unwrap();
+    assert!(tasks.is_empty());
+    assert!(!queue.is_empty().await.unwrap());
+}
+
+/// Redis queue with keys, which are unique to the test.
+async fn redis_queue(key_prefix: &str) -> RedisDeliveryQueue {
+    let configuration = get_configuration().expect("Failed to read configuration.");
+    RedisDeliveryQueue::connect(
+        configuration.redis_uri.expose_secret(),
+        key_prefix,
+        Duration::from_secs(60),
+        get_connection_pool(&configuration.database),
+    )
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+#[ignore = "requires Redis with Lua scripting, run with --ignored"]
+async fn released_tasks_of_redis_queue_are_due_again() {
+    // Arrange
+    let queue = redis_queue(&format!("test_queue:{}", Uuid::new_v4())).await;
+    let issue_id = Uuid::new_v4();
+    let user_ids = [Uuid::new_v4(), Uuid::new_v4()];
+    queue
+        .enqueue(issue_id, &user_ids, Utc::now() - TimeDelta::seconds(1))
+        .await
+        .unwrap();
+
+    // Act - Part 1 - claimed tasks are invisible to other workers
+    let (mut claim, tasks) = queue.dequeue(10).await.unwrap();
+    assert_eq!(tasks.len(), 2);
+    let (_, tasks_of_other_worker) = queue.dequeue(10).await.unwrap();
+    assert!(tasks_of_other_worker.is_empty());
+
+    // Act - Part 2 - complete one task and release the other one unchanged
+    queue.complete(&mut claim, &tasks[0]).await.unwrap();
+    queue.release(claim).await.unwrap();
+
+    // Assert
+    let (_, tasks_after_release) = queue.dequeue(10).await.unwrap();
+    assert_eq!(tasks_after_release.len(), 1);
+    assert_eq!(tasks_after_release[0].user_id, tasks[1].user_id);
+    assert_eq!(tasks_after_release[0].n_retries, 0);
+}
+
+#[tokio::test]
+#[ignore = "requires Redis with Lua scripting, run with --ignored"]
+async fn invalid_tasks_are_moved_out_of_redis_queue() {
+    // Arrange
+    let key_prefix = format!("test_queue:{}", Uuid::new_v4());
+    let queue = redis_queue(&key_prefix).await;
+    let due = Utc::now() - TimeDelta::seconds(1);
+    let configuration = get_configuration().expect("Failed to read configuration.");
+    let client = redis::Client::open(configuration.redis_uri.expose_secret().as_str()).unwrap();
+    let mut connection = client.get_multiplexed_async_connection().await.unwrap();
+    let issue_id = Uuid::new_v4();
+    queue
+        .enqueue(issue_id, &[Uuid::new_v4()], due)
+        .await
+        .unwrap();
+    redis::pipe()
+        .zadd(
+            format!("{}:tasks:{}", key_prefix, issue_id),
+            "not-a-task",
+            due.timestamp_millis(),
+        )
+        .hset(format!("{}:data", key_prefix), "not-a-task", "garbage")
+        .query_async::<_, ()>(&mut connection)
+        .await
+        .unwrap();
+
+    // Act
+    let (mut claim, tasks) = queue.dequeue(10).await.unwrap();
+
+    // Assert
+    assert_eq!(tasks.len(), 1);
+    queue.complete(&mut claim, &tasks[0]).await.unwrap();
+    queue.release(claim).await.unwrap();
+    assert!(queue.is_empty().await.unwrap());
+    let invalid_data: Option<String> = redis::cmd("HGET")
+        .arg(format!("{}:invalid", key_prefix))
+        .arg("not-a-task")
+        .query_async(&mut connection)
+        .await
+        .unwrap();
+    assert_eq!(invalid_data.as_deref(), Some("garbage"));
+}
+
+#[tokio::test]
+#[ignore = "requires Redis with Lua scripting, run with --ignored"]
+async fn redis_queue_dequeues_issues_according_to_their_delivery_weight() {
+    // Arrange
+    let test_app = spawn_app().await;
+    let configuration = get_configuration().expect("Failed to read configuration.");
+    let queue = RedisDeliveryQueue::connect(
+        configuration.redis_uri.expose_secret(),
+        &format!("test_queue:{}", Uuid::new_v4()),
+        Duration::from_secs(60),
+        test_app.db_pool.clone(),
+    )
+    .await
+    .unwrap();
+    let urgent_issue_id = Uuid::new_v4();
+    let regular_issue_id = Uuid::new_v4();
+    for (issue_id, delivery_weight) in [(urgent_issue_id, 100), (regular_issue_id, 1)] {
+        sqlx::query!(
+            r#"
+            INSERT INTO newsletter_issues (
+                newsletter_issue_id, title, text_content, html_content, published_at,
+                num_current_subscribers, num_delivered_newsletters, num_failed_deliveries,
+                delivery_weight
+            )
+            VALUES ($1, 'Title', 'Text', '<p>HTML</p>', now(), 1, 0, 0, $2)
+            "#,
+            issue_id,
+            delivery_weight,
+        )
+        .execute(&test_app.db_pool)
+        .await
+        .unwrap();
+        queue
+            .enqueue(
+                issue_id,
+                &[Uuid::new_v4()],
+                Utc::now() - TimeDelta::seconds(1),
+            )
+            .await
+            .unwrap();
+    }
+
+    // Act
+    let mut num_urgent_batches = 0;
+    for _ in 0..20 {
+        let (claim, tasks) = queue.dequeue(10).await.unwrap();
+        // a batch holds tasks of one issue only
+        assert_eq!(tasks.len(), 1);
+        if tasks[0].issue_id == urgent_issue_id {
+            num_urgent_batches += 1;
+        }
+        queue.release(claim).await.unwrap();
+    }
+
+    // Assert
+    assert!(num_urgent_batches >= 15);
+}
+
+#[tokio::test]
+#[ignore = "requires Redis with Lua scripting, run with --ignored"]
+async fn redis_queue_counts_pending_tasks_of_issues_and_subscribers() {
+    // Arrange
+    let queue = redis_queue(&format!("test_queue:{}", Uuid::new_v4())).await;
+    let issue_id = Uuid::new_v4();
+    let paused_issue_id = Uuid::new_v4();
+    let cancelled_issue_id = Uuid::new_v4();
+    let subscriber_id = Uuid::new_v4();
+    let other_subscriber_id = Uuid::new_v4();
+    let due = Utc::now() - TimeDelta::seconds(1);
+    for issue_id in [issue_id, paused_issue_id, cancelled_issue_id] {
+        queue
+            .enqueue(issue_id, &[subscriber_id, other_subscriber_id], due)
+            .await
+            .unwrap();
+    }
+    for (issue_id, status) in [
+        (paused_issue_id, DeliveryTaskStatus::Paused),
+        (cancelled_issue_id, DeliveryTaskStatus::Cancelled),
+    ] {
+        queue
+            .update_status(issue_id, &[DeliveryTaskStatus::Pending], status)
+            .await
+            .unwrap();
+    }
+
+    // Act
+    let num_pending = queue.pending_count(issue_id).await.unwrap();
+    let pending_for = queue
+        .pending_for(&[subscriber_id], cancelled_issue_id)
+        .await
+        .unwrap();
+    let pending_for_other_issues = queue.pending_for(&[subscriber_id], issue_id).await.unwrap();
+
+    // Assert
+    assert_eq!(num_pending, 2);
+    assert_eq!(pending_for.len(), 1);
+    assert_eq!(pending_for[&subscriber_id], 2);
+    assert_eq!(pending_for_other_issues[&subscriber_id], 1);
+}
+
+#[tokio::test]
+#[ignore = "requires Redis with sorted sets, run with --ignored"]
+async fn oldest_due_task_of_redis_queue_ignores_postponed_tasks() {
+    // Arrange
+    let queue = redis_queue(&format!("test_queue:{}", Uuid::new_v4())).await;
+    assert!(queue.oldest_due_task().await.unwrap().is_none());
+    let now = Utc::now();
+    let longest_waiting = now - TimeDelta::minutes(20);
+    for execute_after in [now + TimeDelta::minutes(5), longest_waiting, now] {
+        queue
+            .enqueue(Uuid::new_v4(), &[Uuid::new_v4()], execute_after)
+            .await
+            .unwrap();
+    }
+
+    // Act
+    let oldest_due_task = queue.oldest_due_task().await.unwrap();
+
+    // Assert
+    assert_eq!(
+        oldest_due_task.map(|due_at| due_at.timestamp_millis()),
+        Some(longest_waiting.timestamp_millis())
+    );
+}
+
+#[tokio::test]
+#[ignore = "requires Redis with Lua scripting, run with --ignored"]
+async fn newsletters_are_delivered_through_configured_redis_queue() {
+    // Arrange
+    let key_prefix = format!("test_queue:{}", Uuid::new_v4());
+    let test_app = spawn_app_with(|c| {
+        c.delivery_queue = DeliveryQueueSettings::Redis {
+            key_prefix: key_prefix.clone(),
+            lease_seconds: 60,
+        }
+    })
+    .await;
+    publish_issue_to_one_subscriber(&test_app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+    let queue = RedisDeliveryQueue::connect(
+        test_app.redis_uri.expose_secret(),
+        &key_prefix,
+        Duration::from_secs(60),
+        test_app.db_pool.clone(),
+    )
+    .await
+    .unwrap();
+
+    // Assert - Part 1 - tasks are enqueued in redis instead of Postgres
+    assert!(PgDeliveryQueue::new(test_app.db_pool.clone())
+        .is_empty()
+        .await
+        .unwrap());
+    assert!(!queue.is_empty().await.unwrap());
+
+    // Act
+    let outcome = test_app.execute_task_with_queue(&queue).await;
+
+    // Assert - Part 2
+    assert!(matches!(outcome, ExecutionOutcome::TaskCompleted));
+    assert!(queue.is_empty().await.unwrap());
+    // Mock verifies on Drop that we have sent one newsletter email
+}
+
+#[tokio::test]
+#[ignore = "requires Redis with Lua scripting, run with --ignored"]
+async fn paused_tasks_of_redis_queue_are_not_dequeued_until_resumed() {
+    // Arrange
+    let queue = redis_queue(&format!("test_queue:{}", Uuid::new_v4())).await;
+    let issue_id = Uuid::new_v4();
+    let other_issue_id = Uuid::new_v4();
+    let due = Utc::now() - TimeDelta::seconds(1);
+    queue
+        .enqueue(issue_id, &[Uuid::new_v4(), Uuid::new_v4()], due)
+        .await
+        .unwrap();
+    queue
+        .enqueue(other_issue_id, &[Uuid::new_v4()], due)
+        .await
+        .unwrap();
+
+    // Act - Part 1 - pause
+    let num_paused = queue
+        .update_status(
+            issue_id,
+            &[DeliveryTaskStatus::Pending],
+            DeliveryTaskStatus::Paused,
+        )
+        .await
+        .unwrap();
+
+    // Assert - Part 1
+    assert_eq!(num_paused, 2);
+    assert_eq!(
+        queue
+            .count_tasks(issue_id, DeliveryTaskStatus::Paused)
+            .await
+            .unwrap(),
+        2
+    );
+    let (claim, tasks) = queue.dequeue(10).await.unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].issue_id, other_issue_id);
+    queue.release(claim).await.unwrap();
+
+    // Act - Part 2 - resume
+    let num_resumed = queue
+        .update_status(
+            issue_id,
+            &[DeliveryTaskStatus::Paused],
+            DeliveryTaskStatus::Pending,
+        )
+        .await
+        .unwrap();
+
+    // Assert - Part 2
+    assert_eq!(num_resumed, 2);
+    // a batch holds tasks of one issue only
+    let (_, tasks) = queue.dequeue(10).await.unwrap();
+    let (_, other_tasks) = queue.dequeue(10).await.unwrap();
+    assert_eq!(tasks.len() + other_tasks.len(), 3);
+}
+
+#[tokio::test]
+#[ignore = "requires Redis with Lua scripting, run with --ignored"]
+async fn tasks_of_redis_queue_cancelled_while_claimed_are_not_released_as_pending() {
+    // Arrange
+    let queue = redis_queue(&format!("test_queue:{}", Uuid::new_v4())).await;
+    let issue_id = Uuid::new_v4();
+    queue
+        .enqueue(
+            issue_id,
+            &[Uuid::new_v4()],
+            Utc::now() - TimeDelta::seconds(1),
+        )
+        .await
+        .unwrap();
+    let (claim, tasks) = queue.dequeue(10).await.unwrap();
+    assert_eq!(tasks.len(), 1);
+
+    // Act
+    let num_cancelled = queue
+        .update_status(
+            issue_id,
+            &[DeliveryTaskStatus::Pending, DeliveryTaskStatus::Paused],
+            DeliveryTaskStatus::Cancelled,
+        )
+        .await
+        .unwrap();
+    queue.release(claim).await.unwrap();
+
+    // Assert
+    assert_eq!(num_cancelled, 1);
+    assert!(queue.is_empty().await.unwrap());
+    assert_eq!(
+        queue
+            .count_tasks(issue_id, DeliveryTaskStatus::Cancelled)
+            .await
+            .unwrap(),
+        1
+    );
+}
+
+#[tokio::test]
+#[ignore = "requires Redis with Lua scripting, run with --ignored"]
+async fn redis_queue_enqueues_missing_tasks_only() {
+    // Arrange
+    let queue = redis_queue(&format!("test_queue:{}", Uuid::new_v4())).await;
+    let issue_id = Uuid::new_v4();
+    let pending = Uuid::new_v4();
+    let cancelled = Uuid::new_v4();
+    let missing = Uuid::new_v4();
+    let due = Utc::now() - TimeDelta::seconds(1);
+    queue.enqueue(issue_id, &[cancelled], due).await.unwrap();
+    queue
+        .update_status(
+            issue_id,
+            &[DeliveryTaskStatus::Pending],
+            DeliveryTaskStatus::Cancelled,
+        )
+        .await
+        .unwrap();
+    queue.enqueue(issue_id, &[pending], due).await.unwrap();
+
+    // Act
+    let num_tasks = queue
+        .enqueue_missing(issue_id, &[pending, cancelled, missing], due)
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(num_tasks, 1);
+    let (_, tasks) = queue.dequeue(10).await.unwrap();
+    let mut user_ids: Vec<Uuid> = tasks.iter().map(|t| t.user_id).collect();
+    user_ids.sort();
+    let mut expected = vec![pending, missing];
+    expected.sort();
+    assert_eq!(user_ids, expected);
+}
+
+#[tokio::test]
+#[ignore = "requires Redis with Lua scripting, run with --ignored"]
+async fn issue_cancelled_within_undo_window_is_removed_from_redis_queue() {
+    // Arrange
+    let key_prefix = format!("test_queue:{}", Uuid::new_v4());
+    let test_app = spawn_app_with(|c| {
+        c.application.undo_window_minutes = 10;
+        c.delivery_queue = DeliveryQueueSettings::Redis {
+            key_prefix: key_prefix.clone(),
+            lease_seconds: 60,
+        }
+    })
+    .await;
+    create_confirmed_subscriber(&test_app).await;
+    test_app.test_user.login(&test_app).await;
+    test_app
+        .post_newsletters(&valid_newsletter_form_data())
+        .await;
+    let issue_id = test_app.get_newsletter_issue_id().await;
+    let queue = redis_queue(&key_prefix).await;
+    assert!(!queue.is_empty().await.unwrap());
+
+    // Act
+    let response = test_app.post_cancel_newsletter(issue_id).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    assert!(queue.is_empty().await.unwrap());
+    assert_eq!(test_app.num_rows_of_table("newsletter_issues").await, 0);
+}
+
+#[tokio::test]
+#[ignore = "requires Redis with Lua scripting, run with --ignored"]
+async fn delivery_actions_change_tasks_of_configured_redis_queue() {
+    // Arrange
+    let key_prefix = format!("test_queue:{}", Uuid::new_v4());
+    let test_app = spawn_app_with(|c| {
+        c.delivery_queue = DeliveryQueueSettings::Redis {
+            key_prefix: key_prefix.clone(),
+            lease_seconds: 60,
+        }
+    })
+    .await;
+    publish_issue_to_one_subscriber(&test_app).await;
+    let issue_id = test_app.get_newsletter_issue_id().await;
+    let queue = redis_queue(&key_prefix).await;
+
+    // Act
+    let response = test_app
+        .post_delivery_action(issue_id, DeliveryAction::Pause)
+        .await;
+
+    // Assert
+    assert_is_redirect_to(
+        &response,
+        &format!("/admin/delivery_overview?newsletter_issue_id={}", issue_id),
+    );
+    let html_page = test_app.get_delivery_overview_html().await;
+    assert!(html_page.contains("<p><i>Delivery has been paused for 1 recipients.</i></p>"));
+    assert!(queue.is_empty().await.unwrap());
+    assert_eq!(
+        queue
+            .count_tasks(issue_id, DeliveryTaskStatus::Paused)
+            .await
+            .unwrap(),
+        1
+    );
+}
+
+#[tokio::test]
+#[ignore = "requires Redis with sorted sets, run with --ignored"]
+async fn failed_enqueue_in_redis_queue_is_not_saved_as_idempotent_response() {
+    // Arrange
+    let key_prefix = format!("test_queue:{}", Uuid::new_v4());
+    let test_app = spawn_app_with(|c| {
+        c.delivery_queue = DeliveryQueueSettings::Redis {
+            key_prefix: key_prefix.clone(),
+            lease_seconds: 60,
+        }
+    })
+    .await;
+    create_confirmed_subscriber(&test_app).await;
+    test_app.test_user.login(&test_app).await;
+    let client = redis::Client::open(test_app.redis_uri.expose_secret().as_str()).unwrap();
+    let mut connection = client.get_multiplexed_async_connection().await.unwrap();
+    // a value of the wrong type makes enqueueing fail
+    redis::cmd("SET")
+        .arg(format!("{}:issues", key_prefix))
+        .arg("blocked")
+        .query_async::<_, ()>(&mut connection)
+        .await
+        .unwrap();
+    let newsletter_request_body = valid_newsletter_form_data();
+
+    // Act - Part 1 - enqueue fails
+    let response = test_app.post_newsletters(&newsletter_request_body).await;
+
+    // Assert - Part 1
+    assert_eq!(response.status().as_u16(), 500);
+    assert_eq!(test_app.num_rows_of_table("newsletter_issues").await, 0);
+
+    // Act - Part 2 - retry with the same idempotency key
+    redis::cmd("DEL")
+        .arg(format!("{}:issues", key_prefix))
+        .query_async::<_, ()>(&mut connection)
+        .await
+        .unwrap();
+    let response = test_app.post_newsletters(&newsletter_request_body).await;
+
+    // Assert - Part 2
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    assert_eq!(test_app.num_rows_of_table("newsletter_issues").await, 1);
+    assert!(!redis_queue(&key_prefix).await.is_empty().await.unwrap());
+}
+
+#[tokio::test]
+async fn publishing_a_newsletter_wakes_the_delivery_worker() {
+    // Arrange
+    let test_app = spawn_app().await;
+    let mut listener = PgListener::connect_with(&test_app.db_pool).await.unwrap();
+    listener.listen(DELIVERY_CHANNEL).await.unwrap();
+
+    // Act
+    publish_issue_to_one_subscriber(&test_app).await;
+
+    // Assert
+    let notification = tokio::time::timeout(Duration::from_secs(1), listener.recv())
+        .await
+        .expect("Delivery worker was not notified.")
+        .unwrap();
+    assert_eq!(notification.channel(), DELIVERY_CHANNEL);
+}
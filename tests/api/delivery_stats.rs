@@ -0,0 +1,161 @@
+//! tests/api/delivery_stats.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use crate::newsletter::{
+    create_confirmed_subscriber, make_valid_subscriber_email_invalid, valid_newsletter_form_data,
+    when_sending_an_email,
+};
+
+use uuid::Uuid;
+use wiremock::ResponseTemplate;
+use zero2prod::delivery_stats::backfill_delivery_stats;
+use zero2prod::routes::DeliveryAction;
+
+/// Deliver an issue to two confirmed subscribers and return its id.
+async fn deliver_issue_to_two_subscribers(app: &TestApp) -> Uuid {
+    for _ in 0..2 {
+        create_confirmed_subscriber(app).await;
+    }
+    app.test_user.login(app).await;
+    app.post_newsletters(&valid_newsletter_form_data()).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+    app.get_newsletter_issue_id().await
+}
+
+/// Count deliveries twice like a worker, which crashed after updating the counters.
+async fn let_counters_drift(app: &TestApp, issue_id: Uuid) {
+    sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET num_delivered_newsletters = 3, num_failed_deliveries = 1
+        WHERE newsletter_issue_id = $1
+        "#,
+        issue_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn drifted_counters_are_corrected_from_delivery_log() {
+    // Arrange
+    let app = spawn_app().await;
+    let issue_id = deliver_issue_to_two_subscribers(&app).await;
+    let_counters_drift(&app, issue_id).await;
+
+    // Act
+    let response = app
+        .post_delivery_action(issue_id, DeliveryAction::ReconcileStats)
+        .await;
+
+    // Assert
+    let issue_page = format!("/admin/delivery_overview?newsletter_issue_id={}", issue_id);
+    assert_is_redirect_to(&response, &issue_page);
+    let issue_html = app
+        .get_response_from_url(&issue_page)
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(issue_html
+        .contains("<p><i>Delivery statistics have been corrected from the delivery log.</i></p>"));
+    assert!(issue_html.contains("<p><i>num_delivered_newsletters: 2</i></p>"));
+    assert!(issue_html.contains("<p><i>num_failed_deliveries: 0</i></p>"));
+    assert!(issue_html.contains("<p><i>Delivery status: finished.</i></p>"));
+}
+
+#[tokio::test]
+async fn dry_run_reports_drift_without_correcting_it() {
+    // Arrange
+    let app = spawn_app().await;
+    let issue_id = deliver_issue_to_two_subscribers(&app).await;
+    let_counters_drift(&app, issue_id).await;
+
+    // Act
+    let report = backfill_delivery_stats(&app.db_pool, None, true)
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(report.num_checked, 1);
+    assert_eq!(report.corrections.len(), 1);
+    let correction = &report.corrections[0];
+    assert_eq!(correction.newsletter_issue_id, issue_id);
+    assert_eq!(
+        (correction.recorded_delivered, correction.recorded_failed),
+        (3, 1)
+    );
+    assert_eq!((correction.num_delivered, correction.num_failed), (2, 0));
+    let overview = app.get_newsletter_delivery_overview().await;
+    assert_eq!(overview.num_delivered_newsletters, Some(3));
+    assert_eq!(overview.num_failed_deliveries, Some(1));
+}
+
+#[tokio::test]
+async fn issues_with_deliveries_in_flight_are_skipped() {
+    // Arrange
+    let app = spawn_app().await;
+    let issue_id = deliver_issue_to_two_subscribers(&app).await;
+    let_counters_drift(&app, issue_id).await;
+    sqlx::query!(
+        r#"
+        INSERT INTO delivery_attempts
+            (newsletter_issue_id, user_id, worker_id, in_flight, n_crashes)
+        VALUES ($1, $2, $3, TRUE, 0)
+        "#,
+        issue_id,
+        Uuid::new_v4(),
+        Uuid::new_v4(),
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    let report = backfill_delivery_stats(&app.db_pool, None, false)
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(report.skipped_in_flight, vec![issue_id]);
+    assert!(report.corrections.is_empty());
+    let overview = app.get_newsletter_delivery_overview().await;
+    assert_eq!(overview.num_delivered_newsletters, Some(3));
+}
+
+#[tokio::test]
+async fn retried_failed_deliveries_are_not_counted_as_failed() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let (email, _) = create_confirmed_subscriber(&app).await;
+    make_valid_subscriber_email_invalid(&app, email).await;
+    app.test_user.login(&app).await;
+    app.post_newsletters(&valid_newsletter_form_data()).await;
+    let issue_id = app.get_newsletter_issue_id().await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+    app.post_delivery_action(issue_id, DeliveryAction::RetryFailed)
+        .await;
+
+    // Act
+    let report = backfill_delivery_stats(&app.db_pool, Some(issue_id), false)
+        .await
+        .unwrap();
+
+    // Assert
+    assert!(report.corrections.is_empty());
+    let overview = app.get_newsletter_delivery_overview().await;
+    assert_eq!(overview.num_delivered_newsletters, Some(1));
+    assert_eq!(overview.num_failed_deliveries, Some(0));
+}
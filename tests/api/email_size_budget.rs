@@ -0,0 +1,65 @@
+//! tests/api/email_size_budget.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, spawn_app_with};
+use crate::newsletter::valid_newsletter_form_data;
+use zero2prod::routes::NewsletterFormData;
+
+/// Newsletter with an inlined image of about `kilobytes` KB.
+fn newsletter_with_image(kilobytes: usize) -> NewsletterFormData {
+    NewsletterFormData {
+        html_content: format!(
+            r#"<h1>Holiday pictures</h1><img src="data:image/png;base64,{}"><p>See you soon</p>"#,
+            "A".repeat(kilobytes * 1024)
+        ),
+        ..valid_newsletter_form_data()
+    }
+}
+
+#[tokio::test]
+async fn publishing_warns_about_emails_beyond_size_budget() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.post_newsletters(&newsletter_with_image(110)).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    assert_eq!(app.num_rows_of_table("newsletter_issues").await, 1);
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("The newsletter issue has been accepted"));
+    assert!(html_page.contains("exceeds the email size budget of 102 KB"));
+    assert!(html_page.contains("largest blocks are &lt;img&gt; (111 KB)"));
+}
+
+#[tokio::test]
+async fn emails_within_size_budget_are_published_without_warning() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    app.post_newsletters(&newsletter_with_image(50)).await;
+
+    // Assert
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("The newsletter issue has been accepted"));
+    assert!(!html_page.contains("size budget"));
+}
+
+#[tokio::test]
+async fn emails_beyond_maximum_size_are_rejected() {
+    // Arrange
+    let app = spawn_app_with(|c| c.application.email_size_budget.max_kilobytes = Some(100)).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.post_newsletters(&newsletter_with_image(110)).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    assert_eq!(app.num_rows_of_table("newsletter_issues").await, 0);
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("The issue exceeds the maximum email size of 100 KB"));
+}
@@ -0,0 +1,111 @@
+//! tests/api/embed.rs
+
+use crate::helpers::{spawn_app, spawn_app_with};
+use crate::newsletter::valid_newsletter_form_data;
+use zero2prod::routes::NewsletterVariantFormData;
+
+#[tokio::test]
+async fn embed_returns_404_without_published_issue() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_response_from_url("/embed/latest").await;
+
+    // Assert
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn embed_shows_latest_published_issue() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let mut first_issue = valid_newsletter_form_data();
+    first_issue.title = "First issue".to_string();
+    app.post_newsletters(&first_issue).await;
+    let mut latest_issue = valid_newsletter_form_data();
+    latest_issue.title = "Latest issue".to_string();
+    latest_issue.html_content = "<p>Content of latest issue</p>".to_string();
+    app.post_newsletters(&latest_issue).await;
+    app.post_logout().await;
+
+    // Act
+    let response = app.get_response_from_url("/embed/latest").await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!("public, max-age=300", response.headers()["Cache-Control"]);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("<h1>Latest issue</h1>"));
+    assert!(html_page.contains("<p>Content of latest issue</p>"));
+    assert!(!html_page.contains("First issue"));
+}
+
+#[tokio::test]
+async fn embed_returns_oembed_json_on_request() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let newsletter = valid_newsletter_form_data();
+    app.post_newsletters(&newsletter).await;
+
+    // Act
+    let response = app.get_response_from_url("/embed/latest?format=json").await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let oembed: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(oembed["version"], "1.0");
+    assert_eq!(oembed["type"], "rich");
+    assert_eq!(oembed["title"], newsletter.title.as_str());
+    let html = oembed["html"].as_str().unwrap();
+    assert!(html.starts_with("<iframe"));
+    assert!(html.contains(r#"/embed/latest""#));
+}
+
+#[tokio::test]
+async fn embed_shows_variant_of_accepted_language_with_fallbacks() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.application.locale_fallbacks = serde_json::from_str(r#"{"fr": ["de"]}"#).unwrap();
+    })
+    .await;
+    app.test_user.login(&app).await;
+    app.post_newsletters(&valid_newsletter_form_data()).await;
+    let newsletter_issue_id = app.get_newsletter_issue_id().await;
+    let variant = NewsletterVariantFormData {
+        locale: "de".to_string(),
+        title: "Neueste Ausgabe".to_string(),
+        text_content: "Inhalt".to_string(),
+        html_content: "<p>Inhalt der neuesten Ausgabe</p>".to_string(),
+    };
+    app.post_newsletter_variant(newsletter_issue_id, &variant)
+        .await;
+    app.post_logout().await;
+
+    for (accept_language, expected_title) in [
+        ("de-AT,en;q=0.5", "Neueste Ausgabe"),
+        ("fr-CA", "Neueste Ausgabe"),
+        ("it, en;q=0.8", "Newsletter title"),
+    ] {
+        // Act
+        let response = app
+            .api_client
+            .get(format!("{}/embed/latest", app.address))
+            .header("Accept-Language", accept_language)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        // Assert
+        assert_eq!(200, response.status().as_u16());
+        assert_eq!("accept-language", response.headers()["Vary"]);
+        let html_page = response.text().await.unwrap();
+        assert!(
+            html_page.contains(&format!("<h1>{}</h1>", expected_title)),
+            "Unexpected title for Accept-Language `{}`.",
+            accept_language
+        );
+    }
+}
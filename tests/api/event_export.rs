@@ -0,0 +1,118 @@
+//! tests/api/event_export.rs
+
+use crate::helpers::spawn_app;
+use crate::newsletter::create_confirmed_subscriber;
+
+use claims::assert_err;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use zero2prod::configuration::{EventExportSettings, EventSinkSettings};
+use zero2prod::event_export::{export_pending_events, EventSink, ExportedEvent};
+use zero2prod::subscriber_events::SubscriberEventKind;
+
+/// Sink, which records published events and fails after `fail_after` events.
+#[derive(Default)]
+struct RecordingSink {
+    published: Mutex<Vec<(String, ExportedEvent)>>,
+    fail_after: Option<usize>,
+}
+
+impl EventSink for RecordingSink {
+    async fn publish(&self, topic: &str, _key: &str, payload: &[u8]) -> Result<(), anyhow::Error> {
+        let mut published = self.published.lock().unwrap();
+        if self.fail_after == Some(published.len()) {
+            anyhow::bail!("Broker is not available.");
+        }
+        published.push((topic.to_string(), serde_json::from_slice(payload)?));
+        Ok(())
+    }
+}
+
+fn export_settings(default_topic: Option<&str>) -> EventExportSettings {
+    EventExportSettings {
+        sink: EventSinkSettings::Nats {
+            url: "nats://localhost:4222".to_string(),
+        },
+        topics: HashMap::from([(
+            SubscriberEventKind::Confirmed,
+            "newsletter.confirmations".to_string(),
+        )]),
+        default_topic: default_topic.map(str::to_string),
+        batch_size: 10,
+        poll_interval_seconds: 1,
+    }
+}
+
+#[tokio::test]
+async fn events_are_exported_once_to_mapped_topics() {
+    // Arrange
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    let sink = RecordingSink::default();
+    let settings = export_settings(Some("newsletter.events"));
+
+    // Act
+    let first = export_pending_events(&test_app.db_pool, &sink, &settings)
+        .await
+        .unwrap();
+    let second = export_pending_events(&test_app.db_pool, &sink, &settings)
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(first, 2);
+    assert_eq!(second, 0);
+    let published = sink.published.lock().unwrap();
+    assert_eq!(published.len(), 2);
+    assert_eq!(published[0].0, "newsletter.events");
+    assert_eq!(published[0].1.kind, SubscriberEventKind::Subscribed);
+    assert_eq!(published[1].0, "newsletter.confirmations");
+    assert_eq!(published[1].1.kind, SubscriberEventKind::Confirmed);
+    assert_eq!(published[0].1.subscriber_id, published[1].1.subscriber_id);
+}
+
+#[tokio::test]
+async fn events_without_topic_are_skipped() {
+    // Arrange
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    let sink = RecordingSink::default();
+    let settings = export_settings(None);
+
+    // Act
+    let processed = export_pending_events(&test_app.db_pool, &sink, &settings)
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(processed, 2);
+    let published = sink.published.lock().unwrap();
+    assert_eq!(published.len(), 1);
+    assert_eq!(published[0].1.kind, SubscriberEventKind::Confirmed);
+}
+
+#[tokio::test]
+async fn events_are_exported_again_after_failed_publish() {
+    // Arrange
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    let failing_sink = RecordingSink {
+        fail_after: Some(1),
+        ..Default::default()
+    };
+    let sink = RecordingSink::default();
+    let settings = export_settings(Some("newsletter.events"));
+
+    // Act
+    let failed = export_pending_events(&test_app.db_pool, &failing_sink, &settings).await;
+    let retried = export_pending_events(&test_app.db_pool, &sink, &settings)
+        .await
+        .unwrap();
+
+    // Assert
+    assert_err!(failed);
+    assert_eq!(failing_sink.published.lock().unwrap().len(), 1);
+    assert_eq!(retried, 1);
+    let published = sink.published.lock().unwrap();
+    assert_eq!(published[0].1.kind, SubscriberEventKind::Confirmed);
+}
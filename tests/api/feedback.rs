@@ -0,0 +1,177 @@
+//! tests/api/feedback.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use crate::newsletter::{
+    create_confirmed_subscriber, valid_newsletter_form_data, when_sending_an_email,
+};
+use reqwest::Url;
+use wiremock::ResponseTemplate;
+use zero2prod::routes::{FeedbackFormData, NewsletterFormData};
+
+fn newsletter_form_data_collecting_feedback() -> NewsletterFormData {
+    NewsletterFormData {
+        collect_feedback: true,
+        ..valid_newsletter_form_data()
+    }
+}
+
+/// Extract the feedback link from the text body of the last email sent.
+async fn get_feedback_link(app: &TestApp) -> Option<Url> {
+    let email_request = app.email_server.received_requests().await.unwrap().pop()?;
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    linkify::LinkFinder::new()
+        .links(body["TextBody"].as_str().unwrap())
+        .filter(|l| *l.kind() == linkify::LinkKind::Url)
+        .map(|l| Url::parse(l.as_str()).unwrap())
+        .find(|l| l.path().starts_with("/feedback/"))
+        .map(|mut l| {
+            // Let's make sure we don't call random APIs on the web
+            assert_eq!(l.host_str().unwrap(), "127.0.0.1");
+            // Let's rewrite the URL to include the port
+            l.set_port(Some(app.port)).unwrap();
+            l
+        })
+}
+
+/// publish a newsletter to one confirmed subscriber and deliver it
+async fn publish_and_deliver_newsletter(app: &TestApp, newsletter: &NewsletterFormData) {
+    create_confirmed_subscriber(app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.test_user.login(app).await;
+    let response = app.post_newsletters(newsletter).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+}
+
+async fn post_feedback(app: &TestApp, link: Url, form: &FeedbackFormData) -> reqwest::Response {
+    app.api_client
+        .post(link)
+        .form(form)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn newsletter_without_feedback_request_contains_no_feedback_link() {
+    // Arrange
+    let test_app = spawn_app().await;
+
+    // Act
+    publish_and_deliver_newsletter(&test_app, &valid_newsletter_form_data()).await;
+
+    // Assert
+    assert!(get_feedback_link(&test_app).await.is_none());
+}
+
+#[tokio::test]
+async fn submitted_feedback_is_shown_in_delivery_overview() {
+    // Arrange
+    let test_app = spawn_app().await;
+    publish_and_deliver_newsletter(&test_app, &newsletter_form_data_collecting_feedback()).await;
+    let feedback_link = get_feedback_link(&test_app).await.unwrap();
+
+    // Act - Part 1 - Get feedback form
+    let html_page = test_app
+        .click_email_link(feedback_link.clone())
+        .await
+        .error_for_status()
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("<b>Newsletter title</b>"));
+
+    // Act - Part 2 - Submit feedback
+    let feedback = FeedbackFormData {
+        useful: true,
+        comment: "Great read!".to_string(),
+    };
+    let response = post_feedback(&test_app, feedback_link.clone(), &feedback).await;
+
+    // Assert
+    let location = format!(
+        "{}?{}",
+        feedback_link.path(),
+        feedback_link.query().unwrap()
+    );
+    assert_is_redirect_to(&response, &location);
+
+    // Act - Part 3 - Follow the redirect
+    let html_page = test_app
+        .click_email_link(feedback_link)
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("<p><i>Thank you for your feedback!</i></p>"));
+
+    // Act - Part 4 - Summary is shown to admins
+    let issue_id_html = test_app.get_delivered_newsletter_issue_id_html().await;
+    assert!(issue_id_html.contains("<p><i>useful: 1</i></p>"));
+    assert!(issue_id_html.contains("<p><i>not useful: 0</i></p>"));
+    assert!(issue_id_html.contains("<p><i>comment: Great read!</i></p>"));
+}
+
+#[tokio::test]
+async fn repeated_feedback_replaces_previous_feedback() {
+    // Arrange
+    let test_app = spawn_app().await;
+    publish_and_deliver_newsletter(&test_app, &newsletter_form_data_collecting_feedback()).await;
+    let feedback_link = get_feedback_link(&test_app).await.unwrap();
+
+    // Act
+    for useful in [true, false] {
+        let feedback = FeedbackFormData {
+            useful,
+            comment: "".to_string(),
+        };
+        post_feedback(&test_app, feedback_link.clone(), &feedback).await;
+    }
+
+    // Assert
+    assert_eq!(test_app.num_rows_of_table("issue_feedback").await, 1);
+    let issue_id_html = test_app.get_delivered_newsletter_issue_id_html().await;
+    assert!(issue_id_html.contains("<p><i>useful: 0</i></p>"));
+    assert!(issue_id_html.contains("<p><i>not useful: 1</i></p>"));
+}
+
+#[tokio::test]
+async fn feedback_with_not_existing_token_is_rejected() {
+    // Arrange
+    let test_app = spawn_app().await;
+    publish_and_deliver_newsletter(&test_app, &newsletter_form_data_collecting_feedback()).await;
+    let mut feedback_link = get_feedback_link(&test_app).await.unwrap();
+    let not_existing_token: String = std::iter::repeat_with(|| '1').take(25).collect();
+    feedback_link.set_query(Some(&format!("t={}", not_existing_token)));
+
+    // Act
+    let feedback = FeedbackFormData {
+        useful: true,
+        comment: "".to_string(),
+    };
+    let response = post_feedback(&test_app, feedback_link, &feedback).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/subscriptions/token");
+    assert_eq!(test_app.num_rows_of_table("issue_feedback").await, 0);
+}
+
+#[tokio::test]
+async fn feedback_for_unknown_issue_returns_404() {
+    // Arrange
+    let test_app = spawn_app().await;
+    publish_and_deliver_newsletter(&test_app, &newsletter_form_data_collecting_feedback()).await;
+    let mut feedback_link = get_feedback_link(&test_app).await.unwrap();
+    feedback_link.set_path(&format!("/feedback/{}", uuid::Uuid::new_v4()));
+
+    // Act
+    let response = test_app.click_email_link(feedback_link).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+}
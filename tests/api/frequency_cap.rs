@@ -0,0 +1,127 @@
+//! tests/api/frequency_cap.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app_with, TestApp};
+use crate::newsletter::{
+    create_confirmed_subscriber, valid_newsletter_form_data, when_sending_an_email,
+};
+use uuid::Uuid;
+use wiremock::ResponseTemplate;
+use zero2prod::configuration::DeliveryQueueSettings;
+use zero2prod::routes::NewsletterFormData;
+
+async fn spawn_app_with_frequency_cap(max_emails_per_week: u32) -> TestApp {
+    spawn_app_with(|c| {
+        c.application.max_emails_per_subscriber_per_week = Some(max_emails_per_week);
+    })
+    .await
+}
+
+async fn publish_newsletter(app: &TestApp, title: &str) -> Uuid {
+    let newsletter = NewsletterFormData {
+        title: title.to_string(),
+        ..valid_newsletter_form_data()
+    };
+    let response = app.post_newsletters(&newsletter).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    sqlx::query!(
+        "SELECT newsletter_issue_id FROM newsletter_issues WHERE title = $1",
+        title
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .newsletter_issue_id
+}
+
+async fn num_current_subscribers(app: &TestApp, newsletter_issue_id: Uuid) -> Option<i32> {
+    sqlx::query!(
+        "SELECT num_current_subscribers FROM newsletter_issues WHERE newsletter_issue_id = $1",
+        newsletter_issue_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .num_current_subscribers
+}
+
+#[tokio::test]
+async fn issue_beyond_frequency_cap_is_skipped_and_reported() {
+    // Arrange
+    let app = spawn_app_with_frequency_cap(1).await;
+    create_confirmed_subscriber(&app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.test_user.login(&app).await;
+    publish_newsletter(&app, "First issue").await;
+    app.dispatch_all_pending_emails().await;
+
+    // Act
+    let issue_id = publish_newsletter(&app, "Second issue").await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert - mock verifies on drop that only the first issue was sent
+    assert_eq!(num_current_subscribers(&app, issue_id).await, Some(0));
+    let html = app
+        .get_response_from_url(&format!(
+            "/admin/delivery_overview?newsletter_issue_id={}",
+            issue_id
+        ))
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(html.contains("num_skipped_by_frequency_cap: 1"));
+}
+
+#[tokio::test]
+async fn pending_deliveries_count_against_frequency_cap() {
+    // Arrange
+    let app = spawn_app_with_frequency_cap(2).await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    publish_newsletter(&app, "First issue").await;
+
+    // Act
+    let second_issue_id = publish_newsletter(&app, "Second issue").await;
+    let third_issue_id = publish_newsletter(&app, "Third issue").await;
+
+    // Assert
+    assert_eq!(
+        num_current_subscribers(&app, second_issue_id).await,
+        Some(1)
+    );
+    assert_eq!(num_current_subscribers(&app, third_issue_id).await, Some(0));
+    assert_eq!(app.num_rows_of_table("frequency_capped_sends").await, 1);
+}
+
+#[tokio::test]
+#[ignore = "requires Redis with Lua scripting, run with --ignored"]
+async fn pending_deliveries_of_redis_queue_count_against_frequency_cap() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.application.max_emails_per_subscriber_per_week = Some(2);
+        c.delivery_queue = DeliveryQueueSettings::Redis {
+            key_prefix: format!("test_queue:{}", Uuid::new_v4()),
+            lease_seconds: 60,
+        };
+    })
+    .await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    publish_newsletter(&app, "First issue").await;
+
+    // Act
+    let second_issue_id = publish_newsletter(&app, "Second issue").await;
+    let third_issue_id = publish_newsletter(&app, "Third issue").await;
+
+    // Assert
+    assert_eq!(
+        num_current_subscribers(&app, second_issue_id).await,
+        Some(1)
+    );
+    assert_eq!(num_current_subscribers(&app, third_issue_id).await, Some(0));
+    assert_eq!(app.num_rows_of_table("frequency_capped_sends").await, 1);
+}
@@ -1,6 +1,12 @@
 //! tests/api/health_check.rs
 
 use crate::helpers::spawn_app;
+use crate::newsletter::{create_confirmed_subscriber, valid_newsletter_form_data};
+use std::time::Duration;
+use wiremock::matchers::method;
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::routes::{DeepHealth, Readiness, WorkersHealth};
+use zero2prod::worker_heartbeat::WorkerHeartbeat;
 
 // `tokio::test` is the testing equivalent of `tokio::main`.
 // It also spares you from having to specify the `#[test]` attribute.
@@ -15,7 +21,7 @@ async fn health_check_works() {
 
     // Act
     let response = client
-        .get(&format!("{}/health_check", &test_app.address))
+        .get(format!("{}/health_check", &test_app.address))
         .send()
         .await
         .expect("Failed to execute request.");
@@ -24,3 +30,176 @@ async fn health_check_works() {
     assert!(response.status().is_success());
     assert_eq!(Some(0), response.content_length());
 }
+
+#[tokio::test]
+async fn worker_health_check_reports_live_workers() {
+    // Arrange
+    let test_app = spawn_app().await;
+    let mut heartbeat = WorkerHeartbeat::new("delivery", Duration::from_secs(60));
+    heartbeat.beat(&test_app.db_pool, 3).await;
+
+    // Act
+    let response = test_app
+        .get_response_from_url("/health_check/workers")
+        .await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let health: WorkersHealth = response.json().await.unwrap();
+    assert!(health.healthy);
+    assert_eq!(health.workers.len(), 1);
+    assert_eq!(health.workers[0].worker_id, heartbeat.worker_id());
+    assert_eq!(health.workers[0].tasks_processed, 3);
+}
+
+#[tokio::test]
+async fn worker_health_check_fails_if_all_workers_of_a_kind_are_dead() {
+    // Arrange
+    let test_app = spawn_app().await;
+    let mut heartbeat = WorkerHeartbeat::new("delivery", Duration::from_secs(60));
+    heartbeat.beat(&test_app.db_pool, 0).await;
+    sqlx::query!("UPDATE worker_heartbeats SET last_seen = now() - INTERVAL '2 minutes'")
+        .execute(&test_app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    let response = test_app
+        .get_response_from_url("/health_check/workers")
+        .await;
+
+    // Assert
+    assert_eq!(503, response.status().as_u16());
+    let health: WorkersHealth = response.json().await.unwrap();
+    assert!(!health.healthy);
+    assert!(!health.workers[0].alive);
+}
+
+#[tokio::test]
+async fn deep_health_check_reports_healthy_dependencies() {
+    // Arrange
+    let test_app = spawn_app().await;
+
+    // Act
+    let response = test_app.get_response_from_url("/health_check/deep").await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let health: DeepHealth = response.json().await.unwrap();
+    assert!(health.healthy);
+    assert!(health.database.healthy);
+    assert!(health.delivery_queue.healthy);
+    assert!(health.email_provider.is_none());
+}
+
+#[tokio::test]
+async fn deep_health_check_fails_if_delivery_queue_is_not_drained() {
+    // Arrange
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    test_app.test_user.login(&test_app).await;
+    test_app
+        .post_newsletters(&valid_newsletter_form_data())
+        .await;
+    sqlx::query!("UPDATE issue_delivery_queue SET execute_after = now() - INTERVAL '1 hour'")
+        .execute(&test_app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    let response = test_app.get_response_from_url("/health_check/deep").await;
+
+    // Assert
+    assert_eq!(503, response.status().as_u16());
+    let health: DeepHealth = response.json().await.unwrap();
+    assert!(!health.healthy);
+    assert!(health.database.healthy);
+    assert!(!health.delivery_queue.healthy);
+    assert!(health
+        .delivery_queue
+        .detail
+        .unwrap()
+        .contains("workers do not drain the queue"));
+}
+
+#[tokio::test]
+async fn deep_health_check_pings_email_provider_on_request() {
+    // Arrange
+    let test_app = spawn_app().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    // Act
+    let response = test_app
+        .get_response_from_url("/health_check/deep?ping_email_provider=true")
+        .await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let health: DeepHealth = response.json().await.unwrap();
+    assert!(health.email_provider.unwrap().healthy);
+}
+
+#[tokio::test]
+async fn liveness_probe_works() {
+    // Arrange
+    let test_app = spawn_app().await;
+
+    // Act
+    let response = test_app.get_response_from_url("/live").await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(Some(0), response.content_length());
+}
+
+#[tokio::test]
+async fn readiness_probe_reports_ready_dependencies() {
+    // Arrange
+    let test_app = spawn_app().await;
+
+    // Act
+    let response = test_app.get_response_from_url("/ready").await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let readiness: Readiness = response.json().await.unwrap();
+    assert!(readiness.ready);
+    assert!(readiness.database.healthy);
+    assert!(readiness.migrations.healthy);
+    assert!(readiness.session_store.healthy);
+}
+
+#[tokio::test]
+async fn readiness_probe_fails_if_database_has_migrations_of_a_newer_release() {
+    // Arrange
+    let test_app = spawn_app().await;
+    sqlx::query!(
+        r#"
+        INSERT INTO _sqlx_migrations
+            (version, description, success, checksum, execution_time)
+        VALUES (99990101000000, 'newer release', TRUE, '\x00', 0)
+        "#
+    )
+    .execute(&test_app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    let response = test_app.get_response_from_url("/ready").await;
+
+    // Assert
+    assert_eq!(503, response.status().as_u16());
+    let readiness: Readiness = response.json().await.unwrap();
+    assert!(!readiness.ready);
+    assert!(readiness.database.healthy);
+    assert!(!readiness.migrations.healthy);
+    assert!(readiness
+        .migrations
+        .detail
+        .unwrap()
+        .contains("1 unknown version(s)"));
+}
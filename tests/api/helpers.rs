@@ -1,5 +1,6 @@
 //! tests/api/helpers.rs
 
+use actix_web::dev::ServerHandle;
 use anyhow::Error;
 use argon2::password_hash::SaltString;
 use argon2::{Algorithm, Argon2, Params, PasswordHasher, Version};
@@ -8,18 +9,30 @@ use lazy_static::lazy_static;
 use once_cell::sync::Lazy;
 use reqwest::Url;
 use scraper::{Html, Selector};
+use secrecy::{ExposeSecret, Secret};
 use sqlx::{Connection, Executor, PgConnection, PgPool, Row};
 use std::str::FromStr;
 use std::time::Duration;
+use tokio::sync::watch;
 use uuid::Uuid;
 use wiremock::MockServer;
-use zero2prod::configuration::{get_configuration, DatabaseSettings};
+use zero2prod::config_reload::TunableSettings;
+use zero2prod::configuration::{get_configuration, DatabaseSettings, Settings, WarmUpSettings};
+use zero2prod::delivery_attempts::DeliveryAttempts;
+use zero2prod::delivery_queue::DeliveryQueue;
 use zero2prod::domain::{SubscriberEmail, SubscriberToken};
 use zero2prod::email_client::EmailClient;
-use zero2prod::issue_delivery_worker::{try_execute_task, ExecutionOutcome};
-use zero2prod::routes::NewsletterFormData;
+use zero2prod::issue_delivery_worker::{
+    try_execute_queued_task, try_execute_task, ExecutionOutcome,
+};
+use zero2prod::locale::LocaleFallbacks;
+use zero2prod::routes::{
+    BounceNotification, DeliveryAction, DeliveryActionFormData, EditNewsletterFormData,
+    EmailFormData, NewsletterFormData, NewsletterVariantFormData, NotificationsFormData,
+};
 use zero2prod::startup::{get_connection_pool, Application};
 use zero2prod::telemetry::{get_subscriber, init_subscriber};
+use zero2prod::token_bucket::TokenBucket;
 
 static TRACING: Lazy<()> = Lazy::new(|| {
     let default_filter_level = "info".to_string();
@@ -29,10 +42,11 @@ static TRACING: Lazy<()> = Lazy::new(|| {
     // `get_subscriber`, therefore they are not the same type. We could work around
     // it, but this is the most straight-forward way of moving forward.
     if std::env::var("TEST_LOG").is_ok() {
-        let subscriber = get_subscriber(subscriber_name, default_filter_level, std::io::stdout);
+        let subscriber =
+            get_subscriber(subscriber_name, default_filter_level, std::io::stdout, None);
         init_subscriber(subscriber);
     } else {
-        let subscriber = get_subscriber(subscriber_name, default_filter_level, std::io::sink);
+        let subscriber = get_subscriber(subscriber_name, default_filter_level, std::io::sink, None);
         init_subscriber(subscriber);
     }
 });
@@ -102,15 +116,27 @@ pub struct TestApp {
     pub test_user: TestUser,
     pub api_client: reqwest::Client,
     pub email_client: EmailClient,
+    #[allow(dead_code)]
     pub db_name: String,
     pub n_retries: u8,
     pub time_delta: chrono::TimeDelta,
+    pub batch_size: u16,
+    pub warm_up: Option<WarmUpSettings>,
+    pub locale_fallbacks: LocaleFallbacks,
+    pub delivery_attempts: DeliveryAttempts,
+    pub webhook_secret: Secret<String>,
+    pub api_key: Secret<String>,
+    pub scim_token: Secret<String>,
+    #[allow(dead_code)]
+    pub redis_uri: Secret<String>,
+    pub shutdown_handle: ServerHandle,
+    pub tunable_settings: watch::Sender<TunableSettings>,
 }
 
 impl TestApp {
     pub async fn post_subscriptions(&self, body: String) -> reqwest::Response {
         self.api_client
-            .post(&format!("{}/subscriptions", &self.address))
+            .post(format!("{}/subscriptions", &self.address))
             .header("Content-Type", "application/x-www-form-urlencoded")
             .body(body)
             .send()
@@ -157,8 +183,8 @@ impl TestApp {
             }
         };
 
-        let html = get_link(&body["HtmlBody"].as_str().unwrap());
-        let plain_text = get_link(&body["TextBody"].as_str().unwrap());
+        let html = get_link(body["HtmlBody"].as_str().unwrap());
+        let plain_text = get_link(body["TextBody"].as_str().unwrap());
         SubscriberLinks { html, plain_text }
     }
 
@@ -177,27 +203,193 @@ impl TestApp {
         let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
         // get reciever from body
         let reciever_email = body["To"].as_str().unwrap();
-        let reciever_email = SubscriberEmail::parse(reciever_email.to_owned()).unwrap();
-        reciever_email
+
+        SubscriberEmail::parse(reciever_email.to_owned()).unwrap()
     }
 
     /// Post newsletters
     pub async fn post_newsletters(&self, form: &NewsletterFormData) -> reqwest::Response {
         self.api_client
-            .post(&format!("{}/admin/newsletters", &self.address))
+            .post(format!("{}/admin/newsletters", &self.address))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Post newsletters with idempotency key in header
+    pub async fn post_newsletters_with_idempotency_header(
+        &self,
+        form: &NewsletterFormData,
+        idempotency_key: &str,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/newsletters", &self.address))
+            .header("Idempotency-Key", idempotency_key)
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Post newsletter draft
+    pub async fn post_newsletter_draft(&self, form: &NewsletterFormData) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/newsletters/draft", &self.address))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Post newsletter test email
+    pub async fn post_newsletter_test(&self, form: &NewsletterFormData) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/newsletters/test", &self.address))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Post newsletter seed test
+    pub async fn post_newsletter_seed_test(&self, form: &NewsletterFormData) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/newsletters/seed_test", &self.address))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Post newsletter preview
+    pub async fn post_newsletter_preview(&self, form: &NewsletterFormData) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/newsletters/preview", &self.address))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Post newsletter send simulation
+    pub async fn post_newsletter_simulate(&self, form: &NewsletterFormData) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/newsletters/simulate", &self.address))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// helper for sending a POST /admin/email request
+    pub async fn post_admin_email(&self, email: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/email", &self.address))
+            .form(&EmailFormData {
+                email: email.to_string(),
+            })
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// helper to edit title and content of a newsletter issue
+    pub async fn post_notifications(&self, form: &NotificationsFormData) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/notifications", &self.address))
             .form(form)
             .send()
             .await
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_newsletter_variant(
+        &self,
+        newsletter_issue_id: Uuid,
+        form: &NewsletterVariantFormData,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/newsletters/{}/variants",
+                &self.address, newsletter_issue_id
+            ))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_newsletter_variants_html(&self, newsletter_issue_id: Uuid) -> String {
+        self.api_client
+            .get(format!(
+                "{}/admin/newsletters/{}/variants",
+                &self.address, newsletter_issue_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_edit_newsletter(
+        &self,
+        newsletter_issue_id: Uuid,
+        form: &EditNewsletterFormData,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/newsletters/{}/edit",
+                &self.address, newsletter_issue_id
+            ))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// helper to delete a newsletter issue
+    pub async fn post_delete_newsletter(&self, newsletter_issue_id: Uuid) -> reqwest::Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/newsletters/{}/delete",
+                &self.address, newsletter_issue_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// helper to cancel a newsletter issue within its undo window
+    pub async fn post_cancel_newsletter(&self, newsletter_issue_id: Uuid) -> reqwest::Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/newsletters/{}/cancel",
+                &self.address, newsletter_issue_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// helper to get html of newsletter drafts
+    pub async fn get_newsletter_drafts_html(&self) -> String {
+        self.get_response_from_url("/admin/newsletters/drafts")
+            .await
+            .text()
+            .await
+            .unwrap()
+    }
+
     /// helper for sending a POST /login request
     pub async fn post_login<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
     {
         self.api_client
-            .post(&format!("{}/login", &self.address))
+            .post(format!("{}/login", &self.address))
             // This 'reqwest' method makes sure that the body is URL-encoded
             // and the 'Content-Type' header is set accordingly.
             .form(body)
@@ -209,7 +401,7 @@ impl TestApp {
     /// helper to get Response from url
     pub async fn get_response_from_url(&self, path: &str) -> reqwest::Response {
         self.api_client
-            .get(&format!("{}{}", self.address, path))
+            .get(format!("{}{}", self.address, path))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -300,7 +492,7 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.api_client
-            .post(&format!("{}/admin/password", self.address))
+            .post(format!("{}/admin/password", self.address))
             .form(body)
             .send()
             .await
@@ -310,26 +502,119 @@ impl TestApp {
     /// helper to log out
     pub async fn post_logout(&self) -> reqwest::Response {
         self.api_client
-            .post(&format!("{}/admin/logout", self.address))
+            .post(format!("{}/admin/logout", self.address))
             .send()
             .await
             .expect("Failed to execute request.")
     }
 
+    /// helper to post an inbound email to the email webhook
+    pub async fn post_inbound_email<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/webhooks/email/inbound", self.address))
+            .basic_auth("postmark", Some(self.webhook_secret.expose_secret()))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// helper to post a bounce notification to the bounce webhook
+    pub async fn post_bounce_notification(&self, body: &BounceNotification) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/webhooks/email/bounce", self.address))
+            .basic_auth("postmark", Some(self.webhook_secret.expose_secret()))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// helper to execute GraphQL query of admin API with session of logged in user
+    pub async fn post_admin_graphql(&self, query: &str) -> serde_json::Value {
+        self.api_client
+            .post(format!("{}/admin/api/graphql", &self.address))
+            .json(&serde_json::json!({ "query": query }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .json()
+            .await
+            .expect("Failed to parse GraphQL response.")
+    }
+
+    /// helper to get issue details from integration API
+    pub async fn get_api_migrations(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/api/v1/migrations", &self.address))
+            .bearer_auth(self.api_key.expose_secret())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_api_issue(&self, issue_id: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/api/v1/issues/{}", self.address, issue_id))
+            .bearer_auth(self.api_key.expose_secret())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// helper to execute one batch of tasks from task queue
+    pub async fn execute_task(&self) -> ExecutionOutcome {
+        self.execute_task_with_rate_limit(None).await
+    }
+
+    /// helper to execute one batch of tasks, which is limited by send rate
+    pub async fn execute_task_with_rate_limit(
+        &self,
+        rate_limit: Option<&TokenBucket>,
+    ) -> ExecutionOutcome {
+        try_execute_task(
+            &self.db_pool,
+            &self.email_client,
+            self.n_retries,
+            self.time_delta,
+            self.batch_size,
+            &self.address,
+            self.warm_up.as_ref(),
+            rate_limit,
+            &self.locale_fallbacks,
+            &self.delivery_attempts,
+        )
+        .await
+        .unwrap()
+    }
+
+    /// helper to execute one batch of tasks from given queue
+    pub async fn execute_task_with_queue(&self, queue: &impl DeliveryQueue) -> ExecutionOutcome {
+        try_execute_queued_task(
+            &self.db_pool,
+            queue,
+            &self.email_client,
+            self.n_retries,
+            self.time_delta,
+            self.batch_size,
+            &self.address,
+            self.warm_up.as_ref(),
+            None,
+            &self.locale_fallbacks,
+            &self.delivery_attempts,
+        )
+        .await
+        .unwrap()
+    }
+
     /// helper to send all newsletter emails from task queue
     pub async fn dispatch_all_pending_emails(&self) -> bool {
         let mut postponed_tasks = false;
         loop {
-            match try_execute_task(
-                &self.db_pool,
-                &self.email_client,
-                self.n_retries,
-                self.time_delta,
-                &self.address,
-            )
-            .await
-            .unwrap()
-            {
+            match self.execute_task().await {
                 ExecutionOutcome::EmptyQueue => break,
                 ExecutionOutcome::PostponedTasks => {
                     postponed_tasks = true;
@@ -355,11 +640,40 @@ impl TestApp {
         .unwrap()
     }
 
+    /// helper to read id of the only newsletter issue
+    pub async fn get_newsletter_issue_id(&self) -> Uuid {
+        sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+            .fetch_one(&self.db_pool)
+            .await
+            .unwrap()
+            .newsletter_issue_id
+    }
+
+    /// helper to pause, resume or cancel delivery of a newsletter issue
+    pub async fn post_delivery_action(
+        &self,
+        newsletter_issue_id: Uuid,
+        action: DeliveryAction,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/delivery_overview/delivery",
+                &self.address
+            ))
+            .form(&DeliveryActionFormData {
+                newsletter_issue_id,
+                action,
+            })
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     /// helper to get delivery overview html
     pub async fn get_delivery_overview_html(&self) -> String {
         //self.get_response_from_url("/admin/delivery_overview")
         self.api_client
-            .get(&format!("{}/admin/delivery_overview", self.address))
+            .get(format!("{}/admin/delivery_overview", self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -449,6 +763,11 @@ pub fn assert_is_redirect_to(response: &reqwest::Response, location: &str) {
 /// Spin up an instance of our application
 /// and returns its address (i.e. http://localhost:XXXX)
 pub async fn spawn_app() -> TestApp {
+    spawn_app_with(|_| {}).await
+}
+
+/// Spawn app with additional changes of its configuration.
+pub async fn spawn_app_with(configure: impl FnOnce(&mut Settings)) -> TestApp {
     // The first time `initialize` is invoked the code in `TRACING` is executed.
     // All other invocations will instead skip execution.
     Lazy::force(&TRACING);
@@ -472,6 +791,9 @@ pub async fn spawn_app() -> TestApp {
         c.emailclient.n_retries = 3;
         // reduce execute_retry_after_milliseconds to 1000ms to shorten test time
         c.emailclient.execute_retry_after_milliseconds = 1000;
+        // send one email per request, tests of batches set batch_size of TestApp
+        c.emailclient.batch_size = 1;
+        configure(&mut c);
         c
     };
 
@@ -482,7 +804,9 @@ pub async fn spawn_app() -> TestApp {
         .await
         .expect("Failed to build application");
     let application_port = application.port();
-    let _ = tokio::spawn(application.run_until_stopped());
+    let shutdown_handle = application.shutdown_handle();
+    let tunable_settings = application.tunable_settings();
+    tokio::spawn(application.run_until_stopped());
 
     let client = reqwest::Client::builder()
         .redirect(reqwest::redirect::Policy::none())
@@ -502,9 +826,22 @@ pub async fn spawn_app() -> TestApp {
         test_user: TestUser::generate(),
         api_client: client,
         n_retries: configuration.emailclient.n_retries,
+        batch_size: configuration.emailclient.batch_size,
+        warm_up: configuration.emailclient.warm_up.clone(),
+        locale_fallbacks: configuration.application.locale_fallbacks.clone(),
+        delivery_attempts: DeliveryAttempts::new(configuration.emailclient.max_task_crashes),
         email_client: configuration.emailclient.client(),
         db_name: configuration.database.database_name,
         time_delta,
+        webhook_secret: configuration.application.webhook_secret,
+        api_key: configuration.application.api_key,
+        scim_token: configuration
+            .application
+            .scim_token
+            .expect("SCIM token is not configured."),
+        redis_uri: configuration.redis_uri,
+        shutdown_handle,
+        tunable_settings,
     };
     test_app.test_user.store(&test_app.db_pool).await;
     test_app
@@ -0,0 +1,305 @@
+//! tests/api/idempotency.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, spawn_app_with};
+use crate::newsletter::{
+    create_confirmed_subscriber, valid_newsletter_form_data, when_sending_an_email,
+};
+use secrecy::ExposeSecret;
+use std::time::Duration;
+use uuid::Uuid;
+use wiremock::ResponseTemplate;
+use zero2prod::configuration::IdempotencyStoreSettings;
+use zero2prod::idempotency::{
+    delete_outlived_idempotency_key, request_hash, save_response, try_processing, IdempotencyKey,
+    NextAction, RedisIdempotencyStore, CLEANUP_WORKER_KIND,
+};
+use zero2prod::utils::see_other;
+use zero2prod::worker_heartbeat::WorkerHeartbeat;
+
+#[tokio::test]
+async fn newsletter_creation_is_idempotent_with_redis_store() {
+    // Arrange
+    let key_prefix = format!("test_idempotency:{}", Uuid::new_v4());
+    let test_app = spawn_app_with(|c| {
+        c.idempotency_store = IdempotencyStoreSettings::Redis {
+            key_prefix: key_prefix.clone(),
+            lease_seconds: 60,
+        }
+    })
+    .await;
+    create_confirmed_subscriber(&test_app).await;
+    test_app.test_user.login(&test_app).await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    // Act - submit newsletter form twice
+    let newsletter_request_body = valid_newsletter_form_data();
+    let response = test_app.post_newsletters(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let response = test_app.post_newsletters(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    test_app.dispatch_all_pending_emails().await;
+
+    // Assert - keys are stored in redis instead of Postgres
+    assert_eq!(test_app.num_rows_of_table("idempotency").await, 0);
+    assert_eq!(test_app.num_rows_of_table("newsletter_issues").await, 1);
+    // Mock verifies on Drop that we have sent the newsletter email **once**
+}
+
+#[tokio::test]
+async fn redis_store_returns_saved_response_of_key() {
+    // Arrange
+    let test_app = spawn_app().await;
+    let store = RedisIdempotencyStore::connect(
+        test_app.redis_uri.expose_secret(),
+        &format!("test_idempotency:{}", Uuid::new_v4()),
+        Duration::from_secs(60),
+        Duration::from_secs(60),
+    )
+    .await
+    .unwrap();
+    let idempotency_key = IdempotencyKey::try_from(Uuid::new_v4().to_string()).unwrap();
+    let user_id = Uuid::new_v4();
+    let payload_hash = request_hash(&"request payload").unwrap();
+
+    // Act - Part 1 - reserve key and save response
+    let NextAction::StartProcessing(transaction) = try_processing(
+        &test_app.db_pool,
+        Some(&store),
+        &idempotency_key,
+        user_id,
+        &payload_hash,
+    )
+    .await
+    .unwrap() else {
+        panic!("Expected to start processing of new key.");
+    };
+    save_response(
+        transaction,
+        Some(&store),
+        &idempotency_key,
+        user_id,
+        see_other("/saved"),
+    )
+    .await
+    .unwrap();
+
+    // Act - Part 2 - same key of other user is not reserved
+    let next_action = try_processing(
+        &test_app.db_pool,
+        Some(&store),
+        &idempotency_key,
+        Uuid::new_v4(),
+        &payload_hash,
+    )
+    .await
+    .unwrap();
+    assert!(matches!(next_action, NextAction::StartProcessing(_)));
+
+    // Act - Part 3 - key of user returns saved response
+    let next_action = try_processing(
+        &test_app.db_pool,
+        Some(&store),
+        &idempotency_key,
+        user_id,
+        &payload_hash,
+    )
+    .await
+    .unwrap();
+
+    // Assert - Part 1 - saved response of key
+    let NextAction::ReturnSavedResponse(response) = next_action else {
+        panic!("Expected saved response of key.");
+    };
+    assert_eq!(response.status().as_u16(), 303);
+    assert_eq!(response.headers().get("Location").unwrap(), "/saved");
+
+    // Assert - Part 2 - key of user with other payload is rejected
+    let next_action = try_processing(
+        &test_app.db_pool,
+        Some(&store),
+        &idempotency_key,
+        user_id,
+        &request_hash(&"other payload").unwrap(),
+    )
+    .await
+    .unwrap();
+    assert!(matches!(next_action, NextAction::RejectReusedKey));
+}
+
+#[tokio::test]
+async fn request_which_lost_its_reservation_in_redis_is_not_committed() {
+    // Arrange
+    let test_app = spawn_app().await;
+    let key_prefix = format!("test_idempotency:{}", Uuid::new_v4());
+    let store = RedisIdempotencyStore::connect(
+        test_app.redis_uri.expose_secret(),
+        &key_prefix,
+        Duration::from_secs(60),
+        Duration::from_secs(60),
+    )
+    .await
+    .unwrap();
+    let idempotency_key = IdempotencyKey::try_from(Uuid::new_v4().to_string()).unwrap();
+    let user_id = Uuid::new_v4();
+    let NextAction::StartProcessing(mut transaction) = try_processing(
+        &test_app.db_pool,
+        Some(&store),
+        &idempotency_key,
+        user_id,
+        &request_hash(&"request payload").unwrap(),
+    )
+    .await
+    .unwrap() else {
+        panic!("Expected to start processing of new key.");
+    };
+    sqlx::query!(
+        "INSERT INTO paused_domains (domain, reason, paused_until) VALUES ('example.com', 'test', now())"
+    )
+    .execute(&mut **transaction)
+    .await
+    .unwrap();
+    // the reservation expires, e.g. because the request took longer than its lease
+    let client = redis::Client::open(test_app.redis_uri.expose_secret().as_str()).unwrap();
+    let mut connection = client.get_multiplexed_async_connection().await.unwrap();
+    redis::cmd("DEL")
+        .arg(format!(
+            "{}:{}:{}",
+            key_prefix,
+            user_id,
+            idempotency_key.as_ref()
+        ))
+        .query_async::<_, ()>(&mut connection)
+        .await
+        .unwrap();
+
+    // Act
+    let result = save_response(
+        transaction,
+        Some(&store),
+        &idempotency_key,
+        user_id,
+        see_other("/saved"),
+    )
+    .await;
+
+    // Assert
+    assert!(result.is_err());
+    assert_eq!(test_app.num_rows_of_table("paused_domains").await, 0);
+}
+
+#[tokio::test]
+async fn reused_idempotency_key_with_other_content_is_rejected_with_422() {
+    // Arrange
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    test_app.test_user.login(&test_app).await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+    let mut newsletter_request_body = valid_newsletter_form_data();
+    let response = test_app.post_newsletters(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    // Act
+    newsletter_request_body.title = "Another newsletter title".to_string();
+    let response = test_app.post_newsletters(&newsletter_request_body).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 422);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("The idempotency key has already been used for a request with other content"));
+    test_app.dispatch_all_pending_emails().await;
+    assert_eq!(test_app.num_rows_of_table("newsletter_issues").await, 1);
+    // Mock verifies on Drop that we have sent the newsletter email **once**
+}
+
+#[tokio::test]
+async fn outlived_idempotency_keys_are_deleted_in_batches() {
+    // Arrange
+    let test_app = spawn_app().await;
+    for created_minutes_ago in [0, 90, 90, 90, 90, 90] {
+        sqlx::query!(
+            r#"
+            INSERT INTO idempotency (user_id, idempotency_key, created_at)
+            VALUES ($1, $2, NOW() - make_interval(mins => $3))
+            "#,
+            Uuid::new_v4(),
+            Uuid::new_v4().to_string(),
+            created_minutes_ago
+        )
+        .execute(&test_app.db_pool)
+        .await
+        .unwrap();
+    }
+
+    // Act
+    let num_deleted = delete_outlived_idempotency_key(&test_app.db_pool, 60, 2)
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(num_deleted, 5);
+    assert_eq!(test_app.num_rows_of_table("idempotency").await, 1);
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_system_status() {
+    // Arrange
+    let test_app = spawn_app().await;
+
+    // Act
+    let response = test_app.get_response_from_url("/admin/system").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login")
+}
+
+#[tokio::test]
+async fn system_status_shows_idempotency_metrics_and_cleanup_counts() {
+    // Arrange
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    test_app.test_user.login(&test_app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&test_app.email_server)
+        .await;
+    let mut newsletter_request_body = valid_newsletter_form_data();
+    test_app.post_newsletters(&newsletter_request_body).await;
+    test_app.post_newsletters(&newsletter_request_body).await;
+    newsletter_request_body.title = "Another newsletter title".to_string();
+    test_app.post_newsletters(&newsletter_request_body).await;
+    let mut cleanup_worker = WorkerHeartbeat::new(CLEANUP_WORKER_KIND, Duration::from_secs(1200));
+    cleanup_worker.beat(&test_app.db_pool, 3).await;
+
+    // Act
+    let html_page = test_app
+        .get_response_from_url("/admin/system")
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    // Assert
+    assert!(html_page.contains("stored in Postgres for 60 minutes"));
+    assert!(html_page.contains("1 new keys processed"));
+    assert!(html_page.contains("1 replayed keys answered with the saved response"));
+    assert!(html_page.contains("1 replayed keys with other content rejected"));
+    assert!(html_page.contains("Stored keys: 1"));
+    assert!(html_page.contains(&format!(
+        "Cleanup worker {}: alive",
+        cleanup_worker.worker_id()
+    )));
+    assert!(html_page.contains("3 outlived keys deleted"));
+}
@@ -0,0 +1,125 @@
+//! tests/api/inbound_email.rs
+
+use crate::helpers::spawn_app;
+use crate::newsletter::{create_confirmed_subscriber, when_sending_an_email};
+use wiremock::ResponseTemplate;
+use zero2prod::domain::SubscriberEmail;
+use zero2prod::routes::InboundEmail;
+
+fn reply_of(sender: &SubscriberEmail, reply: &str) -> InboundEmail {
+    InboundEmail {
+        from: sender.as_ref().to_owned(),
+        subject: "Re: Newsletter title".to_string(),
+        text_body: format!("{}\n\n> Newsletter body as plain text", reply),
+        stripped_text_reply: reply.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn inbound_emails_without_credentials_are_rejected() {
+    // Arrange
+    let test_app = spawn_app().await;
+    let (sender, _) = create_confirmed_subscriber(&test_app).await;
+
+    // Act
+    let response = test_app
+        .api_client
+        .post(format!("{}/webhooks/email/inbound", test_app.address))
+        .json(&reply_of(&sender, "STOP"))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        r#"Basic realm="webhooks""#,
+        response.headers()["WWW-Authenticate"]
+    );
+    assert_eq!(test_app.num_rows_of_table("subscriptions").await, 1);
+}
+
+#[tokio::test]
+async fn stop_reply_unsubscribes_the_sender_and_confirms_it() {
+    // Arrange
+    let test_app = spawn_app().await;
+    let (sender, _) = create_confirmed_subscriber(&test_app).await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    // Act
+    let response = test_app
+        .post_inbound_email(&reply_of(&sender, "STOP"))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(test_app.num_rows_of_table("subscriptions").await, 0);
+    assert_eq!(test_app.num_rows_of_table("subscription_tokens").await, 0);
+    let email_request = test_app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    assert_eq!(
+        test_app.get_reciever_email(&email_request).as_ref(),
+        sender.as_ref()
+    );
+
+    // Mock verifies on Drop that we have sent the confirmation email
+}
+
+#[tokio::test]
+async fn replies_without_command_are_accepted_and_ignored() {
+    // Arrange
+    let test_app = spawn_app().await;
+    let (sender, _) = create_confirmed_subscriber(&test_app).await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&test_app.email_server)
+        .await;
+
+    // Act
+    let response = test_app
+        .post_inbound_email(&reply_of(&sender, "Thank you for this great newsletter!"))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(test_app.num_rows_of_table("subscriptions").await, 1);
+
+    // Mock verifies on Drop that we have sent no email
+}
+
+#[tokio::test]
+async fn stop_reply_of_unknown_sender_is_accepted_and_ignored() {
+    // Arrange
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    let unknown_sender = SubscriberEmail::parse("unknown@example.com".to_string()).unwrap();
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&test_app.email_server)
+        .await;
+
+    // Act
+    let response = test_app
+        .post_inbound_email(&reply_of(&unknown_sender, "STOP"))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(test_app.num_rows_of_table("subscriptions").await, 1);
+
+    // Mock verifies on Drop that we have sent no email
+}
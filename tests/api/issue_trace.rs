@@ -0,0 +1,74 @@
+//! tests/api/issue_trace.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+use crate::newsletter::{
+    create_confirmed_subscriber, valid_newsletter_form_data, when_sending_an_email,
+};
+use uuid::Uuid;
+use wiremock::ResponseTemplate;
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_download_the_trace_of_an_issue() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .get_response_from_url(&format!("/admin/newsletters/{}/trace", Uuid::new_v4()))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn trace_of_unknown_issue_is_not_found() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .get_response_from_url(&format!("/admin/newsletters/{}/trace", Uuid::new_v4()))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn trace_bundles_delivery_events_of_issue() {
+    // Arrange
+    let app = spawn_app().await;
+    let (email, _) = create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.post_newsletters(&valid_newsletter_form_data()).await;
+    app.dispatch_all_pending_emails().await;
+    let issue_id = app.get_newsletter_issue_id().await;
+
+    // Act
+    let response = app
+        .get_response_from_url(&format!("/admin/newsletters/{}/trace", issue_id))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["Content-Disposition"],
+        format!("attachment; filename=\"issue-{}-trace.json\"", issue_id).as_str()
+    );
+    let body = response.text().await.unwrap();
+    // recipients are identified by id only
+    assert!(!body.contains(email.as_ref()));
+    let trace: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(trace["issue"]["newsletter_issue_id"], issue_id.to_string());
+    assert_eq!(trace["issue"]["num_delivered_newsletters"], 1);
+    let events = trace["events"].as_array().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["kind"], "received_issue");
+    assert!(trace["queued_deliveries"].as_array().unwrap().is_empty());
+}
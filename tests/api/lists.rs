@@ -0,0 +1,180 @@
+//! tests/api/lists.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use crate::newsletter::{
+    create_confirmed_subscriber, valid_newsletter_form_data, when_sending_an_email,
+};
+use uuid::Uuid;
+use wiremock::ResponseTemplate;
+use zero2prod::routes::{ListFormData, NewsletterFormData};
+
+async fn post_list(app: &TestApp, name: &str) -> reqwest::Response {
+    app.api_client
+        .post(format!("{}/admin/lists", &app.address))
+        .form(&ListFormData {
+            name: name.to_string(),
+        })
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn create_list(app: &TestApp, name: &str) -> Uuid {
+    let response = post_list(app, name).await;
+    assert_is_redirect_to(&response, "/admin/lists");
+    sqlx::query!("SELECT list_id FROM lists WHERE name = $1", name)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .list_id
+}
+
+async fn subscribe_to_list(app: &TestApp, list_id: &str, email: &str) -> reqwest::Response {
+    let body =
+        serde_urlencoded::to_string([("name", "le guin"), ("email", email), ("list_id", list_id)])
+            .unwrap();
+    app.post_subscriptions(body).await
+}
+
+async fn create_confirmed_list_subscriber(app: &TestApp, list_id: Uuid, email: &str) {
+    let _mock_guard = when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    let response = subscribe_to_list(app, &list_id.to_string(), email).await;
+    assert_is_redirect_to(&response, "/subscriptions/token");
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let links = app.get_email_links(&email_request);
+    reqwest::get(links.html.confirmation.unwrap())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn admin_can_create_mailing_lists_with_unique_names() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    create_list(&app, "Release notes").await;
+    let response = post_list(&app, "Release notes").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/lists");
+    let html = app
+        .get_response_from_url("/admin/lists")
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(html.contains("A mailing list named `Release notes` exists already."));
+    assert!(html.contains("Newsletter: 0 confirmed"));
+    assert!(html.contains("Release notes: 0 confirmed"));
+}
+
+#[tokio::test]
+async fn subscribe_form_offers_lists_once_there_are_several() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    assert!(!app.get_subscriptions_html().await.contains("list_id"));
+
+    // Act
+    create_list(&app, "Release notes").await;
+
+    // Assert
+    let html = app.get_subscriptions_html().await;
+    assert!(html.contains("name=\"list_id\""));
+    assert!(html.contains("Release notes"));
+}
+
+#[tokio::test]
+async fn same_email_can_subscribe_to_several_lists() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let list_id = create_list(&app, "Release notes").await;
+
+    // Act
+    create_confirmed_list_subscriber(&app, Uuid::nil(), "ursula@example.com").await;
+    create_confirmed_list_subscriber(&app, list_id, "ursula@example.com").await;
+
+    // Assert
+    let lists: Vec<Uuid> = sqlx::query!(
+        "SELECT list_id FROM subscriptions WHERE email = $1 AND status = 'confirmed' ORDER BY list_id",
+        "ursula@example.com"
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|r| r.list_id)
+    .collect();
+    assert_eq!(lists, vec![Uuid::nil(), list_id]);
+}
+
+#[tokio::test]
+async fn subscribing_to_unknown_list_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = subscribe_to_list(&app, &Uuid::new_v4().to_string(), "ursula@example.com").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/subscriptions");
+    assert_eq!(app.num_rows_of_table("subscriptions").await, 0);
+}
+
+#[tokio::test]
+async fn issues_are_delivered_to_subscribers_of_their_list_only() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let list_id = create_list(&app, "Release notes").await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_list_subscriber(&app, list_id, "ursula@example.com").await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let newsletter = NewsletterFormData {
+        list_id: list_id.to_string(),
+        ..valid_newsletter_form_data()
+    };
+    let response = app.post_newsletters(&newsletter).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+
+    // Assert - mock verifies on drop that only one email was sent
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    assert_eq!(
+        app.get_reciever_email(&email_request).as_ref(),
+        "ursula@example.com"
+    );
+    assert_eq!(
+        app.get_newsletter_delivery_overview()
+            .await
+            .num_current_subscribers,
+        Some(1)
+    );
+}
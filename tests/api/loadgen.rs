@@ -0,0 +1,45 @@
+//! tests/api/loadgen.rs
+
+use crate::helpers::spawn_app;
+use wiremock::matchers::any;
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::configuration::get_configuration;
+use zero2prod::loadgen::run_load_test;
+
+#[tokio::test]
+async fn load_test_delivers_synthetic_issue_without_sending_emails() {
+    // Arrange
+    let test_app = spawn_app().await;
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.database.database_name = test_app.db_name.clone();
+    configuration.emailclient.base_url = test_app.email_server.uri();
+    configuration.emailclient.worker_concurrency = 2;
+    configuration.emailclient.batch_size = 5;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&test_app.email_server)
+        .await;
+
+    // Act
+    let report = run_load_test(configuration, 25)
+        .await
+        .expect("Load test failed.");
+
+    // Assert
+    assert_eq!(report.num_emails, 25);
+    assert_eq!(report.num_failed, 0);
+    assert_eq!(report.worker_concurrency, 2);
+    assert!(report.throughput() > 0.0);
+    assert!(report.latency_percentile(50.0) <= report.latency_percentile(99.0));
+    let num_synthetic_subscribers = sqlx::query!(
+        "SELECT COUNT(*) AS count FROM subscriptions WHERE list_id = $1",
+        report.list_id
+    )
+    .fetch_one(&test_app.db_pool)
+    .await
+    .unwrap()
+    .count;
+    assert_eq!(num_synthetic_subscribers, Some(25));
+    assert!(report.to_string().contains("emails/s"));
+}
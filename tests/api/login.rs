@@ -1,6 +1,7 @@
 //! tests/api/login.rs
 
-use crate::helpers::{assert_is_redirect_to, spawn_app};
+use crate::helpers::{assert_is_redirect_to, spawn_app, spawn_app_with, TestApp};
+use zero2prod::authentication::ClientIpSource;
 
 #[tokio::test]
 async fn an_error_flash_message_is_set_on_failure() {
@@ -39,3 +40,158 @@ async fn redirect_to_admin_dashboard_after_login_success() {
     let html_page = test_app.get_admin_dashboard_html().await;
     assert!(html_page.contains(&format!("Welcome {}", test_app.test_user.username)));
 }
+
+async fn post_login_of(app: &TestApp, username: &str, password: &str) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "username": username,
+        "password": password
+    }))
+    .await
+}
+
+#[tokio::test]
+async fn username_is_locked_out_after_too_many_failed_logins() {
+    // Arrange
+    let app = spawn_app_with(|c| c.application.login_throttle.max_failures_per_username = 2).await;
+    let username = app.test_user.username.clone();
+    for _ in 0..2 {
+        post_login_of(&app, &username, "wrong-password").await;
+    }
+
+    // Act - correct password during lockout
+    let response = post_login_of(&app, &username, &app.test_user.password).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("Too many failed login attempts. Please try again in"));
+    let locked_until = sqlx::query!(
+        "SELECT locked_until FROM failed_logins WHERE subject = $1",
+        format!("username:{}", username)
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .locked_until
+    .unwrap();
+    let lockout = locked_until - chrono::Utc::now();
+    assert!(lockout > chrono::Duration::seconds(20) && lockout <= chrono::Duration::seconds(30));
+}
+
+#[tokio::test]
+async fn lockout_increases_with_further_failures() {
+    // Arrange
+    let app = spawn_app_with(|c| c.application.login_throttle.max_failures_per_username = 1).await;
+    let username = app.test_user.username.clone();
+    post_login_of(&app, &username, "wrong-password").await;
+    // the lockout ends immediately
+    sqlx::query!("UPDATE failed_logins SET locked_until = now()")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    post_login_of(&app, &username, "wrong-password").await;
+
+    // Assert
+    let row = sqlx::query!(
+        "SELECT failures, locked_until FROM failed_logins WHERE subject = $1",
+        format!("username:{}", username)
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(row.failures, 2);
+    let lockout = row.locked_until.unwrap() - chrono::Utc::now();
+    assert!(lockout > chrono::Duration::seconds(50) && lockout <= chrono::Duration::seconds(60));
+}
+
+#[tokio::test]
+async fn successful_login_resets_failed_logins_of_username() {
+    // Arrange
+    let app = spawn_app().await;
+    let username = app.test_user.username.clone();
+    post_login_of(&app, &username, "wrong-password").await;
+
+    // Act
+    let response = app.test_user.login(&app).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    let num_failures =
+        sqlx::query!("SELECT count(*) AS count FROM failed_logins WHERE subject LIKE 'username:%'")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap()
+            .count;
+    assert_eq!(num_failures, Some(0));
+}
+
+async fn post_login_from(
+    app: &TestApp,
+    forwarded_for: &str,
+    username: &str,
+    password: &str,
+) -> reqwest::Response {
+    app.api_client
+        .post(format!("{}/login", &app.address))
+        .header("X-Forwarded-For", forwarded_for)
+        .form(&serde_json::json!({
+            "username": username,
+            "password": password
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn failed_logins_are_not_counted_per_ip_without_client_ip_source() {
+    // Arrange
+    let app = spawn_app_with(|c| c.application.login_throttle.max_failures_per_ip = 1).await;
+    post_login_of(&app, "mallory", "wrong-password").await;
+
+    // Act - all clients of a load balancer share its address
+    let response = app.test_user.login(&app).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    let num_failures =
+        sqlx::query!("SELECT count(*) AS count FROM failed_logins WHERE subject LIKE 'ip:%'")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap()
+            .count;
+    assert_eq!(num_failures, Some(0));
+}
+
+#[tokio::test]
+async fn forwarded_client_ip_is_locked_out_after_too_many_failed_logins() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.application.login_throttle.max_failures_per_ip = 1;
+        c.application.login_throttle.client_ip = ClientIpSource::ForwardedHeader;
+    })
+    .await;
+    post_login_from(&app, "203.0.113.7", "mallory", "wrong-password").await;
+
+    // Act
+    let locked_out = post_login_from(
+        &app,
+        "203.0.113.7",
+        &app.test_user.username,
+        &app.test_user.password,
+    )
+    .await;
+    let other_client = post_login_from(
+        &app,
+        "198.51.100.23",
+        &app.test_user.username,
+        &app.test_user.password,
+    )
+    .await;
+
+    // Assert
+    assert_is_redirect_to(&locked_out, "/login");
+    assert_is_redirect_to(&other_client, "/admin/dashboard");
+}
@@ -0,0 +1,252 @@
+//! tests/api/login_oidc.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, spawn_app_with, TestApp};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use reqwest::Url;
+use secrecy::Secret;
+use wiremock::matchers::{body_string_contains, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+use zero2prod::configuration::OidcSettings;
+
+const CLIENT_ID: &str = "zero2prod";
+
+/// Test app with a mock OIDC provider, which serves its metadata.
+async fn spawn_app_with_oidc() -> (TestApp, MockServer) {
+    let provider = MockServer::start().await;
+    let issuer_url = provider.uri();
+    let app = spawn_app_with(|c| {
+        c.oidc = Some(OidcSettings {
+            issuer_url: issuer_url.clone(),
+            client_id: CLIENT_ID.to_string(),
+            client_secret: Secret::new("client-secret".to_string()),
+            timeout_milliseconds: 2000,
+        })
+    })
+    .await;
+    Mock::given(method("GET"))
+        .and(path("/.well-known/openid-configuration"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "issuer": issuer_url,
+            "authorization_endpoint": format!("{}/authorize", issuer_url),
+            "token_endpoint": format!("{}/token", issuer_url),
+        })))
+        .mount(&provider)
+        .await;
+    (app, provider)
+}
+
+/// Start the login and return the query parameters of the redirect to the provider.
+async fn start_oidc_login(app: &TestApp) -> Url {
+    let response = app.get_response_from_url("/login/oidc").await;
+    assert_eq!(response.status().as_u16(), 303);
+    let location = response
+        .headers()
+        .get("Location")
+        .unwrap()
+        .to_str()
+        .unwrap();
+    Url::parse(location).unwrap()
+}
+
+fn query_param(url: &Url, name: &str) -> String {
+    url.query_pairs()
+        .find(|(k, _)| k == name)
+        .map(|(_, v)| v.to_string())
+        .unwrap()
+}
+
+/// Let the provider issue an ID token with the claims for the code.
+async fn mock_token_endpoint(provider: &MockServer, code: &str, claims: serde_json::Value) {
+    let id_token = format!(
+        "{}.{}.signature",
+        URL_SAFE_NO_PAD.encode(r#"{"alg":"RS256"}"#),
+        URL_SAFE_NO_PAD.encode(claims.to_string())
+    );
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .and(body_string_contains(format!("code={}", code)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "access_token": "access-token",
+            "token_type": "Bearer",
+            "id_token": id_token,
+        })))
+        .mount(provider)
+        .await;
+}
+
+fn claims(provider: &MockServer, nonce: &str, subject: &str, email: &str) -> serde_json::Value {
+    serde_json::json!({
+        "iss": provider.uri(),
+        "sub": subject,
+        "aud": CLIENT_ID,
+        "exp": chrono::Utc::now().timestamp() + 300,
+        "nonce": nonce,
+        "email": email,
+        "email_verified": true,
+    })
+}
+
+async fn set_email_of_test_user(app: &TestApp, email: &str) {
+    sqlx::query!(
+        "UPDATE users SET email = $2 WHERE user_id = $1",
+        app.test_user.user_id,
+        email
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+}
+
+async fn log_in_via_provider(
+    app: &TestApp,
+    provider: &MockServer,
+    code: &str,
+    subject: &str,
+    email: &str,
+) -> reqwest::Response {
+    let authorization = start_oidc_login(app).await;
+    let nonce = query_param(&authorization, "nonce");
+    mock_token_endpoint(provider, code, claims(provider, &nonce, subject, email)).await;
+    app.get_response_from_url(&format!(
+        "/login/oidc/callback?code={}&state={}",
+        code,
+        query_param(&authorization, "state")
+    ))
+    .await
+}
+
+#[tokio::test]
+async fn oidc_login_is_disabled_without_settings() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let login_page = app.get_login_html().await;
+    let response = app.get_response_from_url("/login/oidc").await;
+
+    // Assert
+    assert!(!login_page.contains("/login/oidc"));
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn oidc_login_redirects_to_provider_with_pkce() {
+    // Arrange
+    let (app, provider) = spawn_app_with_oidc().await;
+
+    // Act
+    let login_page = app.get_login_html().await;
+    let authorization = start_oidc_login(&app).await;
+
+    // Assert
+    assert!(login_page.contains("Login with single sign-on"));
+    assert!(authorization
+        .as_str()
+        .starts_with(&format!("{}/authorize?", provider.uri())));
+    assert_eq!(query_param(&authorization, "client_id"), CLIENT_ID);
+    assert_eq!(query_param(&authorization, "response_type"), "code");
+    assert_eq!(query_param(&authorization, "code_challenge_method"), "S256");
+    assert!(query_param(&authorization, "redirect_uri").ends_with("/login/oidc/callback"));
+}
+
+#[tokio::test]
+async fn first_oidc_login_links_user_by_verified_email() {
+    // Arrange
+    let (app, provider) = spawn_app_with_oidc().await;
+    set_email_of_test_user(&app, "admin@example.com").await;
+
+    // Act - Part 1 - Login via provider
+    let response =
+        log_in_via_provider(&app, &provider, "code1", "sso-42", "admin@example.com").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    let html_page = app.get_admin_dashboard_html().await;
+    assert!(html_page.contains(&format!("Welcome {}", app.test_user.username)));
+
+    // Act - Part 2 - Later logins map the subject, even if the email address changed
+    app.post_logout().await;
+    set_email_of_test_user(&app, "changed@example.com").await;
+    let response =
+        log_in_via_provider(&app, &provider, "code2", "sso-42", "other@example.com").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn oidc_login_of_unknown_or_unverified_identity_is_rejected() {
+    // Arrange
+    let (app, provider) = spawn_app_with_oidc().await;
+    set_email_of_test_user(&app, "admin@example.com").await;
+
+    // Act - unknown email address
+    let unknown = log_in_via_provider(&app, &provider, "code1", "sso-1", "eve@example.com").await;
+    // Act - email address of provider is not verified
+    let authorization = start_oidc_login(&app).await;
+    let mut unverified = claims(
+        &provider,
+        &query_param(&authorization, "nonce"),
+        "sso-2",
+        "admin@example.com",
+    );
+    unverified["email_verified"] = serde_json::json!(false);
+    mock_token_endpoint(&provider, "code2", unverified).await;
+    let unverified = app
+        .get_response_from_url(&format!(
+            "/login/oidc/callback?code=code2&state={}",
+            query_param(&authorization, "state")
+        ))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&unknown, "/login");
+    assert_is_redirect_to(&unverified, "/login");
+    assert!(app
+        .get_login_html()
+        .await
+        .contains("Failed Login Authentication"));
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn oidc_callback_with_wrong_state_or_nonce_is_rejected() {
+    // Arrange
+    let (app, provider) = spawn_app_with_oidc().await;
+    set_email_of_test_user(&app, "admin@example.com").await;
+
+    // Act - wrong state
+    start_oidc_login(&app).await;
+    let wrong_state = app
+        .get_response_from_url("/login/oidc/callback?code=code1&state=forged")
+        .await;
+    // Act - ID token with nonce of another login
+    let authorization = start_oidc_login(&app).await;
+    mock_token_endpoint(
+        &provider,
+        "code2",
+        claims(&provider, "replayed", "sso-42", "admin@example.com"),
+    )
+    .await;
+    let wrong_nonce = app
+        .get_response_from_url(&format!(
+            "/login/oidc/callback?code=code2&state={}",
+            query_param(&authorization, "state")
+        ))
+        .await;
+    // Act - the state of a login can not be used twice
+    let reused_state = app
+        .get_response_from_url(&format!(
+            "/login/oidc/callback?code=code2&state={}",
+            query_param(&authorization, "state")
+        ))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&wrong_state, "/login");
+    assert_is_redirect_to(&wrong_nonce, "/login");
+    assert_is_redirect_to(&reused_state, "/login");
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+}
@@ -1,12 +1,69 @@
 //! tests/api/main.rs
 
 mod admin_dashboard;
+mod admin_graphql;
+mod admin_users;
+mod api_docs;
+mod api_issues;
+mod api_migrations;
+mod api_rate_limit;
+mod api_tokens;
+mod attachment_scan;
+mod bounce_webhook;
+mod calendar;
 mod change_password;
+mod config_reload;
+mod cors;
+mod delivery_comparison;
+mod delivery_dead_letters;
 mod delivery_overview;
+mod delivery_queue;
+mod delivery_stats;
+mod email_size_budget;
+mod embed;
+mod event_export;
+mod feedback;
+mod frequency_cap;
 mod health_check;
 mod helpers;
+mod idempotency;
+mod inbound_email;
+mod issue_trace;
+mod lists;
+mod loadgen;
 mod login;
+mod login_oidc;
 mod newsletter;
+mod newsletter_drafts;
+mod newsletter_edit;
+mod newsletter_preview;
+mod newsletter_simulation;
+mod newsletter_test_send;
+mod newsletter_variants;
+mod notifications;
+mod passkeys;
+mod password_reset;
+mod preferences;
+mod provider_usage;
+mod publish_checklist;
+mod recurring_issues;
+mod schema_check;
+mod scim;
+mod secrets;
+mod seed_test;
+mod send_time;
+mod sessions;
+mod setup;
+mod shutdown;
+mod snippets;
+mod subscriber_data;
+mod subscriber_export;
+mod subscriber_import;
+mod subscribers;
 mod subscriptions;
 mod subscriptions_confirm;
+mod subscriptions_resend;
 mod subscriptions_unsubscribe;
+mod suppressions;
+mod undo_window;
+mod welcome_issue;
@@ -1,6 +1,8 @@
 //! tests/api/newsletter.rs
 
 use crate::helpers::{assert_is_redirect_to, spawn_app, SubscriberLinks, TestApp};
+use base64::Engine;
+use chrono::Utc;
 use fake::{
     faker::{internet::en::SafeEmail, name::en::Name},
     Fake,
@@ -10,9 +12,12 @@ use wiremock::{
     matchers::{any, method, path},
     Mock, MockBuilder, ResponseTemplate,
 };
+use zero2prod::configuration::WarmUpSettings;
 use zero2prod::domain::{SubscriberEmail, SubscriberName};
 use zero2prod::idempotency::delete_outlived_idempotency_key;
-use zero2prod::routes::NewsletterFormData;
+use zero2prod::issue_delivery_worker::ExecutionOutcome;
+use zero2prod::routes::{NewsletterFormData, MAX_ATTACHMENT_BYTES};
+use zero2prod::token_bucket::TokenBucket;
 
 /// have some helpers for Newsletters
 pub fn valid_newsletter_form_data() -> NewsletterFormData {
@@ -20,7 +25,22 @@ pub fn valid_newsletter_form_data() -> NewsletterFormData {
         title: "Newsletter title".to_string(),
         html_content: "<p>Newsletter body as HTML</p>".to_string(),
         text_content: "Newsletter body as plain text".to_string(),
+        markdown_content: String::new(),
         idempotency_key: uuid::Uuid::new_v4().to_string(),
+        collect_feedback: false,
+        scheduled_at: String::new(),
+        delivery_weight: String::new(),
+        optimize_send_time: false,
+        list_id: String::new(),
+        attachment_name: String::new(),
+        attachment_content_type: String::new(),
+        attachment_content: String::new(),
+        draft_id: String::new(),
+        checked_subject_set: false,
+        checked_preview_sent: false,
+        checked_links_validated: false,
+        checked_segment_chosen: false,
+        checked_seed_test_acknowledged: false,
     }
 }
 
@@ -29,7 +49,22 @@ fn invalid_title_newsletter_form_data() -> NewsletterFormData {
         title: "".to_string(),
         html_content: "<p>Newsletter body as HTML</p>".to_string(),
         text_content: "Newsletter body as plain text".to_string(),
+        markdown_content: String::new(),
         idempotency_key: uuid::Uuid::new_v4().to_string(),
+        collect_feedback: false,
+        scheduled_at: String::new(),
+        delivery_weight: String::new(),
+        optimize_send_time: false,
+        list_id: String::new(),
+        attachment_name: String::new(),
+        attachment_content_type: String::new(),
+        attachment_content: String::new(),
+        draft_id: String::new(),
+        checked_subject_set: false,
+        checked_preview_sent: false,
+        checked_links_validated: false,
+        checked_segment_chosen: false,
+        checked_seed_test_acknowledged: false,
     }
 }
 
@@ -38,7 +73,22 @@ fn invalid_text_content_newsletter_form_data() -> NewsletterFormData {
         title: "Newsletter title".to_string(),
         html_content: "<p>Newsletter body as HTML</p>".to_string(),
         text_content: "".to_string(),
+        markdown_content: String::new(),
         idempotency_key: uuid::Uuid::new_v4().to_string(),
+        collect_feedback: false,
+        scheduled_at: String::new(),
+        delivery_weight: String::new(),
+        optimize_send_time: false,
+        list_id: String::new(),
+        attachment_name: String::new(),
+        attachment_content_type: String::new(),
+        attachment_content: String::new(),
+        draft_id: String::new(),
+        checked_subject_set: false,
+        checked_preview_sent: false,
+        checked_links_validated: false,
+        checked_segment_chosen: false,
+        checked_seed_test_acknowledged: false,
     }
 }
 
@@ -47,7 +97,22 @@ fn invalid_html_content_newsletter_form_data() -> NewsletterFormData {
         title: "Newsletter title".to_string(),
         html_content: "".to_string(),
         text_content: "Newsletter body as plain text".to_string(),
+        markdown_content: String::new(),
         idempotency_key: uuid::Uuid::new_v4().to_string(),
+        collect_feedback: false,
+        scheduled_at: String::new(),
+        delivery_weight: String::new(),
+        optimize_send_time: false,
+        list_id: String::new(),
+        attachment_name: String::new(),
+        attachment_content_type: String::new(),
+        attachment_content: String::new(),
+        draft_id: String::new(),
+        checked_subject_set: false,
+        checked_preview_sent: false,
+        checked_links_validated: false,
+        checked_segment_chosen: false,
+        checked_seed_test_acknowledged: false,
     }
 }
 
@@ -57,14 +122,14 @@ pub fn when_sending_an_email() -> MockBuilder {
 }
 
 /// Use the public API of the application under test to create an unconfirmed subscriber
-async fn create_unconfirmed_subscriber(
+pub async fn create_unconfirmed_subscriber(
     app: &TestApp,
 ) -> (SubscriberEmail, SubscriberName, SubscriberLinks) {
     // We support working with multiple subscribers,
     // thier details must be randomized to avoid conflicts.
     let name: String = Name().fake();
     let email: String = SafeEmail().fake();
-    let body = serde_urlencoded::to_string(&serde_json::json!({
+    let body = serde_urlencoded::to_string(serde_json::json!({
         "name": name,
         "email": email
     }))
@@ -79,7 +144,7 @@ async fn create_unconfirmed_subscriber(
         .expect(1)
         .mount_as_scoped(&app.email_server)
         .await;
-    app.post_subscriptions(body.into())
+    app.post_subscriptions(body)
         .await
         .error_for_status()
         .unwrap();
@@ -108,7 +173,7 @@ pub async fn create_confirmed_subscriber(app: &TestApp) -> (SubscriberEmail, Sub
     (email, name)
 }
 
-async fn make_valid_subscriber_email_invalid(app: &TestApp, email: SubscriberEmail) {
+pub async fn make_valid_subscriber_email_invalid(app: &TestApp, email: SubscriberEmail) {
     // get user_id from email
     let subscriber_id = sqlx::query!(
         "SELECT id FROM subscriptions \
@@ -308,7 +373,7 @@ async fn newsletters_are_delivered_to_confirmed_subscribers_only() {
     // first email (index 0) is confirmation link email
     // secondemail (index 1) is newsltter email
     let email_request = &test_app.email_server.received_requests().await.unwrap()[1];
-    let email_links = test_app.get_email_links(&email_request);
+    let email_links = test_app.get_email_links(email_request);
     assert_eq!(email_links.html.confirmation, None);
     assert_eq!(email_links.plain_text.confirmation, None);
     assert_eq!(
@@ -519,6 +584,421 @@ async fn newsletter_is_send_after_a_transient_failure() {
     // and sent one newsletter email
 }
 
+#[tokio::test]
+async fn newsletter_attachment_is_delivered_with_email() {
+    // Arrange
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    let attachment_content = b"%PDF-1.4 newsletter attachment".to_vec();
+    let newsletter = NewsletterFormData {
+        attachment_name: "issue.pdf".to_string(),
+        attachment_content_type: "application/pdf".to_string(),
+        attachment_content: base64::engine::general_purpose::STANDARD.encode(&attachment_content),
+        ..valid_newsletter_form_data()
+    };
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    // Act
+    test_app.test_user.login(&test_app).await;
+    let response = test_app.post_newsletters(&newsletter).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    test_app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let email_request = test_app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    let attachment = &body["Attachments"][0];
+    assert_eq!(attachment["Name"], "issue.pdf");
+    assert_eq!(attachment["ContentType"], "application/pdf");
+    assert_eq!(
+        base64::engine::general_purpose::STANDARD
+            .decode(attachment["Content"].as_str().unwrap())
+            .unwrap(),
+        attachment_content
+    );
+}
+
+#[tokio::test]
+async fn markdown_newsletter_is_delivered_as_html_and_plain_text() {
+    // Arrange
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    let newsletter = NewsletterFormData {
+        html_content: String::new(),
+        text_content: String::new(),
+        markdown_content: "Some **bold** news with a [link](https://example.com).".to_string(),
+        ..valid_newsletter_form_data()
+    };
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    // Act
+    test_app.test_user.login(&test_app).await;
+    let response = test_app.post_newsletters(&newsletter).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    test_app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let email_request = test_app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert!(body["HtmlBody"].as_str().unwrap().contains(
+        r#"<p>Some <strong>bold</strong> news with a <a href="https://example.com">link</a>.</p>"#
+    ));
+    assert!(body["TextBody"]
+        .as_str()
+        .unwrap()
+        .contains("Some bold news with a link [1].\n\nLinks:\n[1] https://example.com"));
+}
+
+#[tokio::test]
+async fn invalid_newsletter_attachments_are_rejected() {
+    // Arrange
+    let test_app = spawn_app().await;
+    test_app.test_user.login(&test_app).await;
+    let too_large_content = vec![0u8; MAX_ATTACHMENT_BYTES + 1];
+    let test_cases = [
+        (
+            "no-base64!",
+            "issue.pdf",
+            "The attachment must have a file name and base64 encoded content.",
+        ),
+        (
+            "YXR0YWNobWVudA==",
+            "",
+            "The attachment must have a file name and base64 encoded content.",
+        ),
+        (
+            &base64::engine::general_purpose::STANDARD.encode(too_large_content),
+            "issue.pdf",
+            "The attachment exceeds the maximum size of 5 MB.",
+        ),
+    ];
+
+    for (attachment_content, attachment_name, error_message) in test_cases {
+        let newsletter = NewsletterFormData {
+            attachment_name: attachment_name.to_string(),
+            attachment_content: attachment_content.to_string(),
+            ..valid_newsletter_form_data()
+        };
+
+        // Act
+        let response = test_app.post_newsletters(&newsletter).await;
+
+        // Assert
+        assert_is_redirect_to(&response, "/admin/newsletters");
+        let html_page = test_app.get_publish_newsletter_html().await;
+        assert!(
+            html_page.contains(&format!("<p><i>{}</i></p>", error_message)),
+            "No error message for attachment {}",
+            attachment_name
+        );
+    }
+}
+
+#[tokio::test]
+async fn invalid_scheduled_time_is_rejected() {
+    // Arrange
+    let test_app = spawn_app().await;
+    test_app.test_user.login(&test_app).await;
+    let newsletter = NewsletterFormData {
+        scheduled_at: "next tuesday".to_string(),
+        ..valid_newsletter_form_data()
+    };
+
+    // Act
+    let response = test_app.post_newsletters(&newsletter).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let html_page = test_app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("<p><i>The scheduled time must be a valid date and time.</i></p>"));
+}
+
+#[tokio::test]
+async fn invalid_delivery_weights_are_rejected() {
+    // Arrange
+    let test_app = spawn_app().await;
+    test_app.test_user.login(&test_app).await;
+
+    for delivery_weight in ["0", "101", "urgent"] {
+        let newsletter = NewsletterFormData {
+            delivery_weight: delivery_weight.to_string(),
+            ..valid_newsletter_form_data()
+        };
+
+        // Act
+        let response = test_app.post_newsletters(&newsletter).await;
+
+        // Assert
+        assert_is_redirect_to(&response, "/admin/newsletters");
+        let html_page = test_app.get_publish_newsletter_html().await;
+        assert!(
+            html_page
+                .contains("<p><i>The delivery weight must be a number between 1 and 100.</i></p>"),
+            "No error message for delivery weight {}",
+            delivery_weight
+        );
+    }
+}
+
+#[tokio::test]
+async fn newsletters_are_delivered_in_batches() {
+    // Arrange
+    let mut test_app = spawn_app().await;
+    test_app.batch_size = 10;
+    for _ in 0..3 {
+        create_confirmed_subscriber(&test_app).await;
+    }
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+            { "ErrorCode": 0, "Message": "OK" },
+            { "ErrorCode": 0, "Message": "OK" },
+            { "ErrorCode": 0, "Message": "OK" }
+        ])))
+        // all three emails are sent in one request
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    // Act
+    test_app.test_user.login(&test_app).await;
+    test_app
+        .post_newsletters(&valid_newsletter_form_data())
+        .await;
+    test_app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let newsletter_delivery_overview = test_app.get_newsletter_delivery_overview().await;
+    assert_eq!(
+        newsletter_delivery_overview.num_delivered_newsletters,
+        Some(3)
+    );
+    assert_eq!(newsletter_delivery_overview.num_failed_deliveries, Some(0));
+}
+
+#[tokio::test]
+async fn warm_up_limits_daily_send_volume() {
+    // Arrange
+    let mut test_app = spawn_app().await;
+    test_app.warm_up = Some(WarmUpSettings {
+        start_date: Utc::now().date_naive(),
+        daily_limits: vec![2, 100],
+    });
+    for _ in 0..3 {
+        create_confirmed_subscriber(&test_app).await;
+    }
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        // only two of three emails are sent at first day of warm-up
+        .expect(2)
+        .mount(&test_app.email_server)
+        .await;
+
+    // Act
+    test_app.test_user.login(&test_app).await;
+    test_app
+        .post_newsletters(&valid_newsletter_form_data())
+        .await;
+    for _ in 0..2 {
+        let outcome = test_app.execute_task().await;
+        assert!(matches!(outcome, ExecutionOutcome::TaskCompleted));
+    }
+    let outcome = test_app.execute_task().await;
+
+    // Assert
+    assert!(matches!(outcome, ExecutionOutcome::PostponedTasks));
+    let newsletter_delivery_overview = test_app.get_newsletter_delivery_overview().await;
+    assert_eq!(
+        newsletter_delivery_overview.num_delivered_newsletters,
+        Some(2)
+    );
+}
+
+#[tokio::test]
+async fn concurrent_workers_do_not_exceed_daily_send_volume_of_warm_up() {
+    // Arrange
+    let mut test_app = spawn_app().await;
+    test_app.warm_up = Some(WarmUpSettings {
+        start_date: Utc::now().date_naive(),
+        daily_limits: vec![1, 100],
+    });
+    for _ in 0..2 {
+        create_confirmed_subscriber(&test_app).await;
+    }
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200).set_delay(std::time::Duration::from_millis(500)))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+    test_app.test_user.login(&test_app).await;
+    test_app
+        .post_newsletters(&valid_newsletter_form_data())
+        .await;
+
+    // Act
+    let (first, second) = tokio::join!(test_app.execute_task(), test_app.execute_task());
+
+    // Assert
+    let outcomes = [first, second];
+    assert_eq!(
+        outcomes
+            .iter()
+            .filter(|o| matches!(o, ExecutionOutcome::TaskCompleted))
+            .count(),
+        1
+    );
+    let num_sent = sqlx::query!("SELECT num_sent FROM daily_send_volume")
+        .fetch_one(&test_app.db_pool)
+        .await
+        .unwrap()
+        .num_sent;
+    assert_eq!(num_sent, 1);
+}
+
+#[tokio::test]
+async fn rejected_emails_of_a_batch_are_counted_as_failed_deliveries() {
+    // Arrange
+    let mut test_app = spawn_app().await;
+    test_app.batch_size = 10;
+    // no retries, rejected emails fail at first try
+    test_app.n_retries = 0;
+    for _ in 0..2 {
+        create_confirmed_subscriber(&test_app).await;
+    }
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+            { "ErrorCode": 0, "Message": "OK" },
+            { "ErrorCode": 406, "Message": "You tried to send to a recipient that has been marked as inactive." }
+        ])))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    // Act
+    test_app.test_user.login(&test_app).await;
+    test_app
+        .post_newsletters(&valid_newsletter_form_data())
+        .await;
+    test_app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let newsletter_delivery_overview = test_app.get_newsletter_delivery_overview().await;
+    assert_eq!(
+        newsletter_delivery_overview.num_delivered_newsletters,
+        Some(1)
+    );
+    assert_eq!(newsletter_delivery_overview.num_failed_deliveries, Some(1));
+}
+
+#[tokio::test]
+async fn hard_bounced_recipient_is_suppressed_without_retries() {
+    // Arrange
+    let test_app = spawn_app().await;
+    let (email, _) = create_confirmed_subscriber(&test_app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(422).set_body_json(serde_json::json!({
+            "ErrorCode": 406,
+            "Message": "You tried to send to a recipient that has been marked as inactive."
+        })))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+    test_app.test_user.login(&test_app).await;
+
+    // Act - Part 1 - Hard bounce fails at first try
+    test_app
+        .post_newsletters(&valid_newsletter_form_data())
+        .await;
+    test_app.dispatch_all_pending_emails().await;
+
+    // Assert - Part 1
+    let newsletter_delivery_overview = test_app.get_newsletter_delivery_overview().await;
+    assert_eq!(newsletter_delivery_overview.num_failed_deliveries, Some(1));
+    let reason = sqlx::query!(
+        "SELECT reason FROM suppressions WHERE email = $1",
+        email.as_ref()
+    )
+    .fetch_one(&test_app.db_pool)
+    .await
+    .unwrap()
+    .reason;
+    assert_eq!(reason, "hard_bounce");
+
+    // Act - Part 2 - Suppressed recipient is skipped without sending an email
+    test_app
+        .post_newsletters(&NewsletterFormData {
+            title: "Second issue".to_string(),
+            ..valid_newsletter_form_data()
+        })
+        .await;
+    test_app.dispatch_all_pending_emails().await;
+
+    // Mock verifies on Drop that we have sent only one newsletter email
+}
+
+#[tokio::test]
+async fn delivery_to_paused_domain_is_postponed() {
+    // Arrange
+    let test_app = spawn_app().await;
+    let (email, _) = create_confirmed_subscriber(&test_app).await;
+    let domain = email.as_ref().rsplit_once('@').unwrap().1.to_lowercase();
+    sqlx::query!(
+        "INSERT INTO paused_domains (domain, reason, paused_until) \
+        VALUES ($1, 'block', now() + interval '1 hour')",
+        domain
+    )
+    .execute(&test_app.db_pool)
+    .await
+    .unwrap();
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&test_app.email_server)
+        .await;
+    test_app.test_user.login(&test_app).await;
+
+    // Act
+    test_app
+        .post_newsletters(&valid_newsletter_form_data())
+        .await;
+    test_app.execute_task().await;
+
+    // Assert
+    let task = sqlx::query!("SELECT n_retries, execute_after FROM issue_delivery_queue")
+        .fetch_one(&test_app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(task.n_retries, 0);
+    assert!(task.execute_after > Utc::now() + chrono::TimeDelta::minutes(59));
+}
+
 #[tokio::test]
 async fn newsletter_creation_is_idempotent() {
     // Arrange
@@ -558,7 +1038,7 @@ async fn newsletter_creation_is_idempotent() {
 
     // Act - Part 5 - delete 1 idempotency key
     assert_eq!(
-        delete_outlived_idempotency_key(&test_app.db_pool, 0)
+        delete_outlived_idempotency_key(&test_app.db_pool, 0, 1000)
             .await
             .unwrap(),
         1
@@ -567,6 +1047,68 @@ async fn newsletter_creation_is_idempotent() {
     // Mock verifies on Drop that we have sent the newsletter email **once**
 }
 
+#[tokio::test]
+async fn idempotency_key_header_takes_precedence_over_form_field() {
+    // Arrange
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    test_app.test_user.login(&test_app).await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+    let idempotency_key = uuid::Uuid::new_v4().to_string();
+
+    // Act - Part 1 - Submit newsletter form without key in form field
+    let mut newsletter_request_body = valid_newsletter_form_data();
+    newsletter_request_body.idempotency_key = String::new();
+    let response = test_app
+        .post_newsletters_with_idempotency_header(&newsletter_request_body, &idempotency_key)
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    // Act - Part 2 - Submit again with same header, but another key in form field
+    newsletter_request_body.idempotency_key = uuid::Uuid::new_v4().to_string();
+    let response = test_app
+        .post_newsletters_with_idempotency_header(&newsletter_request_body, &idempotency_key)
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    test_app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let saved_key = sqlx::query!("SELECT idempotency_key FROM idempotency")
+        .fetch_one(&test_app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved_key.idempotency_key, idempotency_key);
+    // Mock verifies on Drop that we have sent the newsletter email **once**
+}
+
+#[tokio::test]
+async fn invalid_idempotency_key_header_is_rejected_with_400() {
+    // Arrange
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    test_app.test_user.login(&test_app).await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&test_app.email_server)
+        .await;
+
+    // Act
+    let response = test_app
+        .post_newsletters_with_idempotency_header(&valid_newsletter_form_data(), "not-a-uuid")
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(test_app.num_rows_of_table("newsletter_issues").await, 0);
+}
+
 #[tokio::test]
 async fn concurrent_form_submission_is_handled_gracefully() {
     // Arrange
@@ -597,3 +1139,80 @@ async fn concurrent_form_submission_is_handled_gracefully() {
     test_app.dispatch_all_pending_emails().await;
     // Mock verifies on Drop that we have sent the newsletter email **once**
 }
+
+#[tokio::test]
+async fn concurrent_worker_loops_deliver_each_email_once() {
+    // Arrange
+    let test_app = spawn_app().await;
+    for _ in 0..4 {
+        create_confirmed_subscriber(&test_app).await;
+    }
+    test_app.test_user.login(&test_app).await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(100)))
+        .expect(4)
+        .mount(&test_app.email_server)
+        .await;
+    let response = test_app
+        .post_newsletters(&valid_newsletter_form_data())
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    // Act - dispatch with three loops, which dequeue tasks concurrently
+    tokio::join!(
+        test_app.dispatch_all_pending_emails(),
+        test_app.dispatch_all_pending_emails(),
+        test_app.dispatch_all_pending_emails(),
+    );
+
+    // Assert
+    let newsletter_delivery_overview = test_app.get_newsletter_delivery_overview().await;
+    assert_eq!(
+        newsletter_delivery_overview.num_delivered_newsletters,
+        Some(4)
+    );
+    // Mock verifies on Drop that each subscriber got the newsletter email **once**
+}
+
+#[tokio::test]
+async fn delivery_is_postponed_when_send_rate_limit_is_reached() {
+    // Arrange
+    let test_app = spawn_app().await;
+    for _ in 0..3 {
+        create_confirmed_subscriber(&test_app).await;
+    }
+    test_app.test_user.login(&test_app).await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&test_app.email_server)
+        .await;
+    let response = test_app
+        .post_newsletters(&valid_newsletter_form_data())
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    // two emails per hour
+    let rate_limit = TokenBucket::new(2, Duration::from_secs(3600));
+
+    // Act
+    let outcomes = [
+        test_app
+            .execute_task_with_rate_limit(Some(&rate_limit))
+            .await,
+        test_app
+            .execute_task_with_rate_limit(Some(&rate_limit))
+            .await,
+        test_app
+            .execute_task_with_rate_limit(Some(&rate_limit))
+            .await,
+    ];
+
+    // Assert
+    assert!(matches!(outcomes[0], ExecutionOutcome::TaskCompleted));
+    assert!(matches!(outcomes[1], ExecutionOutcome::TaskCompleted));
+    assert!(matches!(outcomes[2], ExecutionOutcome::PostponedTasks));
+    assert_eq!(test_app.num_rows_of_table("issue_delivery_queue").await, 1);
+    // Mock verifies on Drop that only two emails have been sent
+}
@@ -0,0 +1,117 @@
+//! tests/api/newsletter_drafts.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+use crate::newsletter::{
+    create_confirmed_subscriber, valid_newsletter_form_data, when_sending_an_email,
+};
+use wiremock::ResponseTemplate;
+use zero2prod::routes::NewsletterFormData;
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_newsletter_drafts() {
+    // Arrange
+    let test_app = spawn_app().await;
+
+    // Act
+    let response = test_app
+        .get_response_from_url("/admin/newsletters/drafts")
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn saved_draft_can_be_loaded_into_publish_form() {
+    // Arrange
+    let test_app = spawn_app().await;
+    test_app.test_user.login(&test_app).await;
+    let draft = NewsletterFormData {
+        title: "Draft title".to_string(),
+        text_content: String::new(),
+        ..valid_newsletter_form_data()
+    };
+
+    // Act - Part 1 - Save draft
+    let response = test_app.post_newsletter_draft(&draft).await;
+
+    // Assert
+    let location = response
+        .headers()
+        .get("Location")
+        .unwrap()
+        .to_str()
+        .unwrap();
+    assert!(location.starts_with("/admin/newsletters?draft_id="));
+
+    // Act - Part 2 - Load draft into publish form
+    let html_page = test_app
+        .get_response_from_url(location)
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("<p><i>The draft has been saved.</i></p>"));
+    assert!(html_page.contains(r#"value="Draft title""#));
+    // html content is escaped in value attribute
+    assert!(html_page.contains("Newsletter body as HTML"));
+
+    // Act - Part 3 - Draft is listed
+    let html_page = test_app.get_newsletter_drafts_html().await;
+    assert!(html_page.contains("Draft title"));
+
+    // Act - Part 4 - Save draft again updates draft
+    let draft_id = location.trim_start_matches("/admin/newsletters?draft_id=");
+    let response = test_app
+        .post_newsletter_draft(&NewsletterFormData {
+            title: "Updated draft title".to_string(),
+            draft_id: draft_id.to_string(),
+            ..valid_newsletter_form_data()
+        })
+        .await;
+    assert_is_redirect_to(&response, location);
+    let html_page = test_app.get_newsletter_drafts_html().await;
+    assert!(html_page.contains("Updated draft title"));
+    assert!(!html_page.contains(">Draft title<"));
+}
+
+#[tokio::test]
+async fn published_draft_is_removed_from_drafts() {
+    // Arrange
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    test_app.test_user.login(&test_app).await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    let response = test_app
+        .post_newsletter_draft(&valid_newsletter_form_data())
+        .await;
+    let draft_id = response
+        .headers()
+        .get("Location")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .trim_start_matches("/admin/newsletters?draft_id=")
+        .to_string();
+
+    // Act
+    let newsletter = NewsletterFormData {
+        draft_id,
+        ..valid_newsletter_form_data()
+    };
+    let response = test_app.post_newsletters(&newsletter).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    test_app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let html_page = test_app.get_newsletter_drafts_html().await;
+    assert!(html_page.contains("<p><i>No drafts saved.</i></p>"));
+
+    // Mock verifies on Drop that we have sent one newsletter email
+}
@@ -0,0 +1,146 @@
+//! tests/api/newsletter_edit.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+use crate::newsletter::{
+    create_confirmed_subscriber, valid_newsletter_form_data, when_sending_an_email,
+};
+
+use wiremock::ResponseTemplate;
+use zero2prod::routes::{DeliveryAction, EditNewsletterFormData};
+
+fn edited_newsletter_form_data() -> EditNewsletterFormData {
+    EditNewsletterFormData {
+        title: "Corrected title".to_string(),
+        text_content: "Corrected text".to_string(),
+        html_content: "<p>Corrected html</p>".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn title_of_delivered_issue_can_be_corrected() {
+    // Arrange
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+    test_app.test_user.login(&test_app).await;
+    test_app
+        .post_newsletters(&valid_newsletter_form_data())
+        .await;
+    test_app.dispatch_all_pending_emails().await;
+    let issue_id = test_app.get_newsletter_issue_id().await;
+
+    // Act - Part 1 - Edit form shows current title
+    let html_page = test_app
+        .get_response_from_url(&format!("/admin/newsletters/{}/edit", issue_id))
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains(&valid_newsletter_form_data().title));
+
+    // Act - Part 2 - Post corrected issue
+    let response = test_app
+        .post_edit_newsletter(issue_id, &edited_newsletter_form_data())
+        .await;
+
+    // Assert
+    let overview_url = format!("/admin/delivery_overview?newsletter_issue_id={}", issue_id);
+    assert_is_redirect_to(&response, &overview_url);
+    let html_page = test_app
+        .get_response_from_url(&overview_url)
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("<p><i>The newsletter issue has been updated.</i></p>"));
+    assert!(html_page.contains("<p><b>Newsletter title: Corrected title</b></p>"));
+    assert!(html_page.contains("<p><i>Delivery status: finished.</i></p>"));
+}
+
+#[tokio::test]
+async fn issue_cannot_be_edited_or_deleted_while_delivery_is_in_progress() {
+    // Arrange
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    test_app.test_user.login(&test_app).await;
+    let newsletter = valid_newsletter_form_data();
+    test_app.post_newsletters(&newsletter).await;
+    let issue_id = test_app.get_newsletter_issue_id().await;
+    let edit_url = format!("/admin/newsletters/{}/edit", issue_id);
+
+    // Act - Part 1 - Edit issue
+    let response = test_app
+        .post_edit_newsletter(issue_id, &edited_newsletter_form_data())
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, &edit_url);
+    let html_page = test_app
+        .get_response_from_url(&edit_url)
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains(
+        "<p><i>The newsletter issue cannot be changed while its delivery is in progress. \
+        Wait until delivery has finished or cancel it.</i></p>"
+    ));
+    assert!(html_page.contains(&newsletter.title));
+
+    // Act - Part 2 - Delete issue
+    let response = test_app.post_delete_newsletter(issue_id).await;
+
+    // Assert
+    assert_is_redirect_to(&response, &edit_url);
+    assert_eq!(test_app.num_rows_of_table("newsletter_issues").await, 1);
+}
+
+#[tokio::test]
+async fn issue_with_cancelled_delivery_can_be_deleted() {
+    // Arrange
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&test_app.email_server)
+        .await;
+    test_app.test_user.login(&test_app).await;
+    test_app
+        .post_newsletters(&valid_newsletter_form_data())
+        .await;
+    let issue_id = test_app.get_newsletter_issue_id().await;
+    test_app
+        .post_delivery_action(issue_id, DeliveryAction::Cancel)
+        .await;
+
+    // Act
+    let response = test_app.post_delete_newsletter(issue_id).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/delivery_overview");
+    let html_page = test_app.get_delivery_overview_html().await;
+    assert!(html_page.contains("<p><i>The newsletter issue has been deleted.</i></p>"));
+    assert_eq!(test_app.num_rows_of_table("newsletter_issues").await, 0);
+    assert_eq!(test_app.num_rows_of_table("issue_delivery_queue").await, 0);
+    assert!(!test_app.dispatch_all_pending_emails().await);
+}
+
+#[tokio::test]
+async fn editing_unknown_issue_returns_404() {
+    // Arrange
+    let test_app = spawn_app().await;
+    test_app.test_user.login(&test_app).await;
+
+    // Act
+    let response = test_app
+        .get_response_from_url(&format!("/admin/newsletters/{}/edit", uuid::Uuid::new_v4()))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+}
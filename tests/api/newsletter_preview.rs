@@ -0,0 +1,70 @@
+//! tests/api/newsletter_preview.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+use crate::newsletter::{valid_newsletter_form_data, when_sending_an_email};
+use wiremock::ResponseTemplate;
+use zero2prod::routes::NewsletterFormData;
+
+#[tokio::test]
+async fn preview_renders_email_templates_without_sending() {
+    // Arrange
+    let test_app = spawn_app().await;
+    test_app.test_user.login(&test_app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&test_app.email_server)
+        .await;
+    let newsletter = NewsletterFormData {
+        collect_feedback: true,
+        ..valid_newsletter_form_data()
+    };
+
+    // Act
+    let response = test_app.post_newsletter_preview(&newsletter).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    // html body is escaped into srcdoc of iframe
+    assert!(html_page.contains("&lt;p&gt;Newsletter body as HTML&lt;/p&gt;"));
+    assert!(html_page.contains("Newsletter body as plain text"));
+    assert!(html_page.contains("Jane Doe"));
+    assert!(html_page.contains("subscription_token=preview"));
+    assert!(html_page.contains("/feedback/preview"));
+    assert_eq!(test_app.num_rows_of_table("newsletter_issues").await, 0);
+    assert_eq!(test_app.num_rows_of_table("issue_delivery_queue").await, 0);
+}
+
+#[tokio::test]
+async fn preview_requires_content() {
+    // Arrange
+    let test_app = spawn_app().await;
+    test_app.test_user.login(&test_app).await;
+    let newsletter = NewsletterFormData {
+        title: String::new(),
+        ..valid_newsletter_form_data()
+    };
+
+    // Act
+    let response = test_app.post_newsletter_preview(&newsletter).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let html_page = test_app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("<p><i>You must set a title for your newsletter.</i></p>"));
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_preview_newsletters() {
+    // Arrange
+    let test_app = spawn_app().await;
+
+    // Act
+    let response = test_app
+        .post_newsletter_preview(&valid_newsletter_form_data())
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
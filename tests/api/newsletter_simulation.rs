@@ -0,0 +1,59 @@
+//! tests/api/newsletter_simulation.rs
+
+use crate::helpers::spawn_app;
+use crate::newsletter::{
+    create_confirmed_subscriber, create_unconfirmed_subscriber,
+    make_valid_subscriber_email_invalid, valid_newsletter_form_data, when_sending_an_email,
+};
+use wiremock::ResponseTemplate;
+
+#[tokio::test]
+async fn simulation_reports_recipients_without_enqueueing_delivery() {
+    // Arrange
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    create_confirmed_subscriber(&test_app).await;
+    create_unconfirmed_subscriber(&test_app).await;
+    test_app.test_user.login(&test_app).await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&test_app.email_server)
+        .await;
+
+    // Act
+    let response = test_app
+        .post_newsletter_simulate(&valid_newsletter_form_data())
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("<p><i>recipients (confirmed subscribers): 2</i></p>"));
+    assert!(html_page.contains("<p><i>excluded (pending confirmation): 1</i></p>"));
+    assert!(html_page.contains("<p><i>render errors: 0</i></p>"));
+    assert_eq!(test_app.num_rows_of_table("newsletter_issues").await, 0);
+    assert_eq!(test_app.num_rows_of_table("issue_delivery_queue").await, 0);
+}
+
+#[tokio::test]
+async fn simulation_reports_recipients_with_invalid_contact_details() {
+    // Arrange
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    let (email, _) = create_confirmed_subscriber(&test_app).await;
+    make_valid_subscriber_email_invalid(&test_app, email).await;
+    test_app.test_user.login(&test_app).await;
+
+    // Act
+    let response = test_app
+        .post_newsletter_simulate(&valid_newsletter_form_data())
+        .await;
+
+    // Assert
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("<p><i>recipients (confirmed subscribers): 2</i></p>"));
+    assert!(html_page.contains("<p><i>render errors: 1</i></p>"));
+    assert!(html_page.contains("is not a valid subscriber email."));
+}
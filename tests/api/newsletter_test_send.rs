@@ -0,0 +1,92 @@
+//! tests/api/newsletter_test_send.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+use crate::newsletter::{
+    create_confirmed_subscriber, valid_newsletter_form_data, when_sending_an_email,
+};
+use wiremock::ResponseTemplate;
+
+#[tokio::test]
+async fn test_email_is_sent_to_admin_only() {
+    // Arrange
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    test_app.test_user.login(&test_app).await;
+    let response = test_app.post_admin_email("admin@example.com").await;
+    assert_is_redirect_to(&response, "/admin/email");
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    // Act
+    let response = test_app
+        .post_newsletter_test(&valid_newsletter_form_data())
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let html_page = test_app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("<p><i>A test email has been sent to admin@example.com.</i></p>"));
+    let email_request = test_app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body["To"], "admin@example.com");
+    assert_eq!(body["Subject"], "[TEST] Newsletter title");
+    // neither an issue is stored nor a delivery task is queued
+    assert!(!test_app.dispatch_all_pending_emails().await);
+    let html_page = test_app.get_delivery_overview_html().await;
+    assert!(!html_page.contains("id=\"issue\""));
+
+    // Mock verifies on Drop that we have sent one test email
+}
+
+#[tokio::test]
+async fn test_email_requires_email_address_of_admin() {
+    // Arrange
+    let test_app = spawn_app().await;
+    test_app.test_user.login(&test_app).await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&test_app.email_server)
+        .await;
+
+    // Act
+    let response = test_app
+        .post_newsletter_test(&valid_newsletter_form_data())
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let html_page = test_app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("<p><i>Set your email address to receive test emails.</i></p>"));
+}
+
+#[tokio::test]
+async fn invalid_admin_email_address_is_rejected() {
+    // Arrange
+    let test_app = spawn_app().await;
+    test_app.test_user.login(&test_app).await;
+
+    // Act
+    let response = test_app.post_admin_email("not-an-email").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/email");
+    let html_page = test_app
+        .get_response_from_url("/admin/email")
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("is not a valid subscriber email."));
+}
@@ -0,0 +1,174 @@
+//! tests/api/newsletter_variants.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, spawn_app_with, TestApp};
+use crate::newsletter::{
+    create_confirmed_subscriber, valid_newsletter_form_data, when_sending_an_email,
+};
+use wiremock::ResponseTemplate;
+use zero2prod::routes::NewsletterVariantFormData;
+
+fn german_variant() -> NewsletterVariantFormData {
+    NewsletterVariantFormData {
+        locale: "de".to_string(),
+        title: "Newsletter Titel".to_string(),
+        text_content: "Newsletter Inhalt als Text".to_string(),
+        html_content: "<p>Newsletter Inhalt als HTML</p>".to_string(),
+    }
+}
+
+/// Subscribe and confirm a subscriber with preferred locale; returns email address.
+async fn create_confirmed_subscriber_with_locale(app: &TestApp, locale: &str) -> String {
+    let email = "anna@example.com".to_string();
+    let body = serde_urlencoded::to_string(serde_json::json!({
+        "name": "Anna",
+        "email": email,
+        "locale": locale,
+    }))
+    .unwrap();
+    let _mock_guard = when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.post_subscriptions(body)
+        .await
+        .error_for_status()
+        .unwrap();
+    let email_request = &app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let links = app.get_email_links(email_request);
+    reqwest::get(links.html.confirmation.unwrap())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    email
+}
+
+#[tokio::test]
+async fn subscribers_receive_variant_of_their_locale() {
+    // Arrange
+    let test_app = spawn_app().await;
+    let german_email = create_confirmed_subscriber_with_locale(&test_app, "de-AT").await;
+    let (default_email, _) = create_confirmed_subscriber(&test_app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&test_app.email_server)
+        .await;
+    test_app.test_user.login(&test_app).await;
+    let response = test_app
+        .post_newsletters(&valid_newsletter_form_data())
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let newsletter_issue_id = test_app.get_newsletter_issue_id().await;
+
+    // Act
+    let response = test_app
+        .post_newsletter_variant(newsletter_issue_id, &german_variant())
+        .await;
+    assert_is_redirect_to(
+        &response,
+        &format!("/admin/newsletters/{}/variants", newsletter_issue_id),
+    );
+    test_app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let requests = test_app.email_server.received_requests().await.unwrap();
+    let subjects: Vec<(String, String)> = requests
+        .iter()
+        .map(|r| serde_json::from_slice::<serde_json::Value>(&r.body).unwrap())
+        .filter(|body| body["TextBody"].as_str().unwrap().contains("Newsletter"))
+        .map(|body| {
+            (
+                body["To"].as_str().unwrap().to_string(),
+                body["Subject"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect();
+    assert!(subjects.contains(&(german_email, "Newsletter Titel".to_string())));
+    assert!(subjects.contains(&(
+        default_email.as_ref().to_string(),
+        "Newsletter title".to_string()
+    )));
+    let html_page = test_app
+        .get_newsletter_variants_html(newsletter_issue_id)
+        .await;
+    assert!(html_page.contains("The language variant `de` has been saved."));
+    let html_page: String = html_page.split_whitespace().collect();
+    assert!(html_page.contains("<td>default</td><td>Newslettertitle</td><td>1</td><td>0</td>"));
+    assert!(html_page.contains("<td>de</td><td>NewsletterTitel</td><td>1</td><td>0</td>"));
+}
+
+#[tokio::test]
+async fn subscribers_receive_variant_of_configured_fallback_locale() {
+    // Arrange
+    let test_app = spawn_app_with(|c| {
+        c.application.locale_fallbacks = serde_json::from_str(r#"{"fr": ["de"]}"#).unwrap();
+    })
+    .await;
+    let french_email = create_confirmed_subscriber_with_locale(&test_app, "fr-CA").await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+    test_app.test_user.login(&test_app).await;
+    test_app
+        .post_newsletters(&valid_newsletter_form_data())
+        .await;
+    let newsletter_issue_id = test_app.get_newsletter_issue_id().await;
+    test_app
+        .post_newsletter_variant(newsletter_issue_id, &german_variant())
+        .await;
+
+    // Act
+    test_app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let email_request = test_app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body["To"].as_str().unwrap(), french_email);
+    assert_eq!(body["Subject"].as_str().unwrap(), "Newsletter Titel");
+}
+
+#[tokio::test]
+async fn invalid_locale_of_variant_is_rejected() {
+    // Arrange
+    let test_app = spawn_app().await;
+    test_app.test_user.login(&test_app).await;
+    test_app
+        .post_newsletters(&valid_newsletter_form_data())
+        .await;
+    let newsletter_issue_id = test_app.get_newsletter_issue_id().await;
+    let variant = NewsletterVariantFormData {
+        locale: "german".to_string(),
+        ..german_variant()
+    };
+
+    // Act
+    let response = test_app
+        .post_newsletter_variant(newsletter_issue_id, &variant)
+        .await;
+
+    // Assert
+    assert_is_redirect_to(
+        &response,
+        &format!("/admin/newsletters/{}/variants", newsletter_issue_id),
+    );
+    let html_page = test_app
+        .get_newsletter_variants_html(newsletter_issue_id)
+        .await;
+    assert!(html_page.contains("`german` is not a valid locale."));
+}
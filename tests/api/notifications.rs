@@ -0,0 +1,180 @@
+//! tests/api/notifications.rs
+
+use crate::admin_dashboard::insert_confirmed_subscribers;
+use crate::delivery_dead_letters::{crash_execution, publish_issue};
+use crate::helpers::{assert_is_redirect_to, spawn_app, spawn_app_with, TestApp};
+use crate::newsletter::{create_confirmed_subscriber, when_sending_an_email};
+use std::time::Duration;
+use wiremock::matchers::{body_string_contains, method, path};
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::notifications::NotificationChannel;
+use zero2prod::routes::NotificationsFormData;
+
+fn notifications_form(
+    channel: NotificationChannel,
+    slack_webhook_url: &str,
+    subscriber_milestone: bool,
+    delivery_quarantined: bool,
+) -> NotificationsFormData {
+    NotificationsFormData {
+        channel,
+        slack_webhook_url: slack_webhook_url.to_string(),
+        subscriber_milestone: subscriber_milestone.then(|| "on".to_string()),
+        delivery_quarantined: delivery_quarantined.then(|| "on".to_string()),
+    }
+}
+
+async fn get_notifications_html(app: &TestApp) -> String {
+    app.get_response_from_url("/admin/notifications")
+        .await
+        .text()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_change_notification_preferences() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let form = notifications_form(NotificationChannel::Email, "", true, true);
+    let response = app.post_notifications(&form).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    assert_eq!(app.num_rows_of_table("user_preferences").await, 0);
+}
+
+#[tokio::test]
+async fn notification_preferences_are_saved_per_user() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let form = notifications_form(
+        NotificationChannel::Slack,
+        "https://hooks.slack.com/services/T000/B000/XXXX",
+        false,
+        true,
+    );
+    let response = app.post_notifications(&form).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/notifications");
+    let html_page = get_notifications_html(&app).await;
+    assert!(html_page.contains("Your notification preferences have been saved."));
+    assert!(html_page.contains(r#"value="slack" checked"#));
+    assert!(html_page.contains("https://hooks.slack.com/services/T000/B000/XXXX"));
+    assert!(html_page.contains(r#"name="delivery_quarantined" value="on" checked"#));
+    assert!(!html_page.contains(r#"name="subscriber_milestone" value="on" checked"#));
+}
+
+#[tokio::test]
+async fn slack_channel_requires_a_valid_webhook_url() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    for (url, error_message) in [
+        (
+            "",
+            "You must set a Slack webhook URL to be notified on Slack.",
+        ),
+        (
+            "not a url",
+            "The Slack webhook URL is not a valid http(s) URL.",
+        ),
+    ] {
+        // Act
+        let form = notifications_form(NotificationChannel::Slack, url, true, true);
+        let response = app.post_notifications(&form).await;
+
+        // Assert
+        assert_is_redirect_to(&response, "/admin/notifications");
+        assert!(get_notifications_html(&app).await.contains(error_message));
+        assert_eq!(app.num_rows_of_table("user_preferences").await, 0);
+    }
+}
+
+#[tokio::test]
+async fn subscribed_admins_are_notified_about_milestones_by_email() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.post_admin_email("admin@example.com").await;
+    let form = notifications_form(NotificationChannel::Email, "", true, false);
+    app.post_notifications(&form).await;
+    insert_confirmed_subscribers(&app, 99).await;
+    when_sending_an_email()
+        .and(body_string_contains("admin@example.com"))
+        .and(body_string_contains("reached 100 confirmed subscribers"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - confirmation of 100th subscriber
+    create_confirmed_subscriber(&app).await;
+
+    // Assert
+    // Mock verifies on Drop that we have sent the notification
+}
+
+#[tokio::test]
+async fn admins_are_not_notified_about_unsubscribed_events() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.post_admin_email("admin@example.com").await;
+    let form = notifications_form(NotificationChannel::Email, "", false, true);
+    app.post_notifications(&form).await;
+    insert_confirmed_subscribers(&app, 99).await;
+    when_sending_an_email()
+        .and(body_string_contains("admin@example.com"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - confirmation of 100th subscriber
+    create_confirmed_subscriber(&app).await;
+
+    // Assert
+    // Mock verifies on Drop that we have not sent a notification
+}
+
+#[tokio::test]
+async fn subscribed_admins_are_notified_about_quarantined_tasks_on_slack() {
+    // Arrange
+    let app = spawn_app_with(|c| c.emailclient.max_task_crashes = 1).await;
+    create_confirmed_subscriber(&app).await;
+    publish_issue(&app).await;
+    let form = notifications_form(
+        NotificationChannel::Slack,
+        &format!("{}/slack", app.email_server.uri()),
+        false,
+        true,
+    );
+    app.post_notifications(&form).await;
+    Mock::given(path("/slack"))
+        .and(method("POST"))
+        .and(body_string_contains("has been quarantined"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+        .mount(&app.email_server)
+        .await;
+    crash_execution(&app, &app.delivery_attempts).await;
+
+    // Act
+    app.execute_task().await;
+
+    // Assert
+    assert_eq!(app.num_rows_of_table("delivery_dead_letters").await, 1);
+    // Mock verifies on Drop that we have sent the notification
+}
@@ -0,0 +1,283 @@
+//! tests/api/passkeys.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ciborium::Value;
+use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+use reqwest::Url;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+use zero2prod::configuration::get_configuration;
+
+/// Authenticator with an ES256 key, which answers WebAuthn options like a browser.
+struct SoftwareAuthenticator {
+    signing_key: SigningKey,
+    credential_id: Vec<u8>,
+    sign_count: u32,
+    rp_id: String,
+    origin: String,
+}
+
+impl SoftwareAuthenticator {
+    fn new() -> Self {
+        let base_url = get_configuration().unwrap().application.base_url;
+        let base_url = Url::parse(&base_url).unwrap();
+        Self {
+            signing_key: SigningKey::from_slice(&rand::random::<[u8; 32]>()).unwrap(),
+            credential_id: Uuid::new_v4().as_bytes().to_vec(),
+            sign_count: 0,
+            rp_id: base_url.host_str().unwrap().to_string(),
+            origin: base_url.origin().ascii_serialization(),
+        }
+    }
+
+    fn client_data(&self, ceremony: &str, options: &serde_json::Value) -> String {
+        let client_data = serde_json::json!({
+            "type": ceremony,
+            "challenge": options["challenge"],
+            "origin": self.origin,
+        });
+        URL_SAFE_NO_PAD.encode(client_data.to_string())
+    }
+
+    fn authenticator_data(&self, flags: u8) -> Vec<u8> {
+        let mut data = Sha256::digest(self.rp_id.as_bytes()).to_vec();
+        data.push(flags);
+        data.extend_from_slice(&self.sign_count.to_be_bytes());
+        data
+    }
+
+    /// Credential of `navigator.credentials.create()` with attestation `none`.
+    fn create(&self, options: &serde_json::Value) -> serde_json::Value {
+        let point = self.signing_key.verifying_key().to_encoded_point(false);
+        let cose_key = Value::Map(vec![
+            (1.into(), 2.into()),
+            (3.into(), (-7).into()),
+            ((-1).into(), 1.into()),
+            ((-2).into(), Value::Bytes(point.x().unwrap().to_vec())),
+            ((-3).into(), Value::Bytes(point.y().unwrap().to_vec())),
+        ]);
+        // user present, user verified and attested credential data included
+        let mut auth_data = self.authenticator_data(0x45);
+        auth_data.extend_from_slice(&[0; 16]);
+        auth_data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+        auth_data.extend_from_slice(&self.credential_id);
+        ciborium::into_writer(&cose_key, &mut auth_data).unwrap();
+        let attestation = Value::Map(vec![
+            ("fmt".into(), "none".into()),
+            ("attStmt".into(), Value::Map(vec![])),
+            ("authData".into(), Value::Bytes(auth_data)),
+        ]);
+        let mut attestation_object = vec![];
+        ciborium::into_writer(&attestation, &mut attestation_object).unwrap();
+        serde_json::json!({
+            "client_data_json": self.client_data("webauthn.create", options),
+            "attestation_object": URL_SAFE_NO_PAD.encode(attestation_object),
+        })
+    }
+
+    /// Assertion of `navigator.credentials.get()`, which increases the signature counter.
+    fn get(&mut self, options: &serde_json::Value) -> serde_json::Value {
+        self.sign_count += 1;
+        let client_data_json = self.client_data("webauthn.get", options);
+        let auth_data = self.authenticator_data(0x05);
+        let mut signed = auth_data.clone();
+        signed.extend_from_slice(&Sha256::digest(
+            URL_SAFE_NO_PAD.decode(&client_data_json).unwrap(),
+        ));
+        let signature: Signature = self.signing_key.sign(&signed);
+        serde_json::json!({
+            "credential_id": URL_SAFE_NO_PAD.encode(&self.credential_id),
+            "client_data_json": client_data_json,
+            "authenticator_data": URL_SAFE_NO_PAD.encode(auth_data),
+            "signature": URL_SAFE_NO_PAD.encode(signature.to_der().as_bytes()),
+        })
+    }
+}
+
+impl TestApp {
+    async fn post_passkey_options(&self, path: &str) -> serde_json::Value {
+        self.api_client
+            .post(format!("{}{}", self.address, path))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .error_for_status()
+            .unwrap()
+            .json()
+            .await
+            .unwrap()
+    }
+
+    async fn post_passkey_json(&self, path: &str, body: &serde_json::Value) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}{}", self.address, path))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    async fn get_passkeys_html(&self) -> String {
+        self.get_response_from_url("/admin/security/passkeys")
+            .await
+            .text()
+            .await
+            .unwrap()
+    }
+
+    /// Register a passkey of the authenticator for the logged in user.
+    async fn register_passkey(
+        &self,
+        authenticator: &SoftwareAuthenticator,
+        name: &str,
+    ) -> reqwest::Response {
+        let options = self
+            .post_passkey_options("/admin/security/passkeys/options")
+            .await;
+        let credential = authenticator.create(&options);
+        self.post_passkey_json(
+            "/admin/security/passkeys",
+            &serde_json::json!({"name": name, "credential": credential}),
+        )
+        .await
+    }
+
+    async fn login_with_passkey(
+        &self,
+        authenticator: &mut SoftwareAuthenticator,
+    ) -> reqwest::Response {
+        let options = self.post_passkey_options("/login/passkey/options").await;
+        self.post_passkey_json("/login/passkey", &authenticator.get(&options))
+            .await
+    }
+}
+
+#[tokio::test]
+async fn registered_passkey_logs_in_its_user() {
+    // Arrange
+    let app = spawn_app().await;
+    let mut authenticator = SoftwareAuthenticator::new();
+    app.test_user.login(&app).await;
+    let response = app.register_passkey(&authenticator, "Laptop").await;
+    assert_is_redirect_to(&response, "/admin/security/passkeys");
+    let html_page = app.get_passkeys_html().await;
+    assert!(html_page.contains("The passkey `Laptop` has been registered."));
+    assert!(html_page.contains("never used"));
+    app.post_logout().await;
+
+    // Act
+    let response = app.login_with_passkey(&mut authenticator).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    let html_page = app.get_admin_dashboard_html().await;
+    assert!(html_page.contains(&format!("Welcome {}", app.test_user.username)));
+    assert!(app.get_passkeys_html().await.contains("last used"));
+}
+
+#[tokio::test]
+async fn passkey_login_with_unknown_passkey_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    let mut authenticator = SoftwareAuthenticator::new();
+
+    // Act
+    let response = app.login_with_passkey(&mut authenticator).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    assert!(app
+        .get_login_html()
+        .await
+        .contains("Failed Login Authentication"));
+    assert_is_redirect_to(&app.get_admin_dashboard().await, "/login");
+}
+
+#[tokio::test]
+async fn passkey_login_rejects_replayed_and_cloned_assertions() {
+    // Arrange
+    let app = spawn_app().await;
+    let mut authenticator = SoftwareAuthenticator::new();
+    app.test_user.login(&app).await;
+    app.register_passkey(&authenticator, "Laptop").await;
+    app.post_logout().await;
+    let options = app.post_passkey_options("/login/passkey/options").await;
+    let assertion = authenticator.get(&options);
+    let response = app.post_passkey_json("/login/passkey", &assertion).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    app.post_logout().await;
+
+    // Act - replay of used challenge
+    let response = app.post_passkey_json("/login/passkey", &assertion).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+
+    // Act - clone of authenticator with stale signature counter
+    authenticator.sign_count -= 1;
+    let response = app.login_with_passkey(&mut authenticator).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    assert_is_redirect_to(&app.get_admin_dashboard().await, "/login");
+}
+
+#[tokio::test]
+async fn registration_without_matching_challenge_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    let authenticator = SoftwareAuthenticator::new();
+    app.test_user.login(&app).await;
+    app.post_passkey_options("/admin/security/passkeys/options")
+        .await;
+    let credential = authenticator.create(&serde_json::json!({"challenge": "forged"}));
+
+    // Act
+    let response = app
+        .post_passkey_json(
+            "/admin/security/passkeys",
+            &serde_json::json!({"name": "Laptop", "credential": credential}),
+        )
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/security/passkeys");
+    let html_page = app.get_passkeys_html().await;
+    assert!(html_page.contains("The passkey could not be registered."));
+    assert_eq!(app.num_rows_of_table("passkeys").await, 0);
+}
+
+#[tokio::test]
+async fn deleted_passkey_can_not_log_in() {
+    // Arrange
+    let app = spawn_app().await;
+    let mut authenticator = SoftwareAuthenticator::new();
+    app.test_user.login(&app).await;
+    app.register_passkey(&authenticator, "Laptop").await;
+    let passkey_id = sqlx::query!("SELECT passkey_id FROM passkeys")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .passkey_id;
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!(
+            "{}/admin/security/passkeys/{}/delete",
+            app.address, passkey_id
+        ))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/security/passkeys");
+    let html_page = app.get_passkeys_html().await;
+    assert!(html_page.contains("The passkey `Laptop` has been deleted."));
+    assert!(html_page.contains("No passkeys."));
+    app.post_logout().await;
+    let response = app.login_with_passkey(&mut authenticator).await;
+    assert_is_redirect_to(&response, "/login");
+}
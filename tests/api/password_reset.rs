@@ -0,0 +1,183 @@
+//! tests/api/password_reset.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use crate::newsletter::when_sending_an_email;
+use reqwest::Url;
+use uuid::Uuid;
+use wiremock::ResponseTemplate;
+
+async fn post_forgot_password(app: &TestApp, username: &str) -> reqwest::Response {
+    app.api_client
+        .post(format!("{}/login/forgot", &app.address))
+        .form(&[("username", username)])
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn post_reset_password(
+    app: &TestApp,
+    token: &str,
+    new_password: &str,
+    new_password_check: &str,
+) -> reqwest::Response {
+    app.api_client
+        .post(format!("{}/login/reset", &app.address))
+        .form(&[
+            ("token", token),
+            ("new_password", new_password),
+            ("new_password_check", new_password_check),
+        ])
+        .send()
+        .await
+        .unwrap()
+}
+
+/// Request a password reset of the test user, who has an email address, and return
+/// the reset link of the email.
+async fn request_reset_link(app: &TestApp) -> Url {
+    sqlx::query!(
+        "UPDATE users SET email = 'admin@example.com' WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let response = post_forgot_password(app, &app.test_user.username).await;
+    assert_is_redirect_to(&response, "/login");
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body["To"], "admin@example.com");
+    let mut link = linkify::LinkFinder::new()
+        .links(body["TextBody"].as_str().unwrap())
+        .map(|l| Url::parse(l.as_str()).unwrap())
+        .find(|l| l.path() == "/login/reset")
+        .expect("Password reset email has no reset link.");
+    link.set_port(Some(app.port)).unwrap();
+    link
+}
+
+fn token_of_link(link: &Url) -> String {
+    link.query_pairs()
+        .find(|(key, _)| key == "token")
+        .unwrap()
+        .1
+        .to_string()
+}
+
+#[tokio::test]
+async fn reset_link_sets_new_password_once() {
+    // Arrange
+    let app = spawn_app().await;
+    let link = request_reset_link(&app).await;
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("a link to reset the password has been sent to it."));
+    let response = app.api_client.get(link.clone()).send().await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let new_password = Uuid::new_v4().to_string();
+
+    // Act - Part 1 - reset password
+    let response =
+        post_reset_password(&app, &token_of_link(&link), &new_password, &new_password).await;
+
+    // Assert - Part 1
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("Your password has been reset."));
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &new_password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    // Act - Part 2 - reuse reset link
+    let response =
+        post_reset_password(&app, &token_of_link(&link), &new_password, &new_password).await;
+
+    // Assert - Part 2
+    assert_is_redirect_to(&response, "/login/forgot");
+    assert_eq!(app.num_rows_of_table("password_reset_tokens").await, 0);
+}
+
+#[tokio::test]
+async fn unknown_username_gets_the_same_response_without_email() {
+    // Arrange
+    let app = spawn_app().await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = post_forgot_password(&app, "unknown-user").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("a link to reset the password has been sent to it."));
+    assert_eq!(app.num_rows_of_table("password_reset_tokens").await, 0);
+}
+
+#[tokio::test]
+async fn expired_reset_link_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    let link = request_reset_link(&app).await;
+    sqlx::query!("UPDATE password_reset_tokens SET expires_at = now() - interval '1 minute'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    let response = app.api_client.get(link).send().await.unwrap();
+
+    // Assert
+    assert_is_redirect_to(&response, "/login/forgot");
+    let html_page = app
+        .get_response_from_url("/login/forgot")
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("The password reset link is invalid or has expired."));
+}
+
+#[tokio::test]
+async fn invalid_new_password_keeps_reset_link_valid() {
+    // Arrange
+    let app = spawn_app().await;
+    let link = request_reset_link(&app).await;
+
+    // Act
+    let token = token_of_link(&link);
+    let response = post_reset_password(&app, &token, "short", "short").await;
+
+    // Assert
+    assert_is_redirect_to(&response, &format!("/login/reset?token={}", token));
+    let html_page = app
+        .api_client
+        .get(link)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("The new password is invalid."));
+    let response = app.test_user.login(&app).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
@@ -0,0 +1,274 @@
+//! tests/api/preferences.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use crate::newsletter::{
+    create_unconfirmed_subscriber, valid_newsletter_form_data, when_sending_an_email,
+};
+use reqwest::Url;
+use wiremock::ResponseTemplate;
+use zero2prod::routes::{ListFormData, NewsletterFormData};
+
+/// Preference center link in the confirmation email of a new subscriber; the
+/// subscription is confirmed, if requested.
+async fn subscriber_preferences_link(app: &TestApp, confirm: bool) -> Url {
+    let (_, _, links) = create_unconfirmed_subscriber(app).await;
+    let confirmation_link = links.html.confirmation.unwrap();
+    if confirm {
+        reqwest::get(confirmation_link.clone())
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap();
+    }
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    let mut link = linkify::LinkFinder::new()
+        .links(body["HtmlBody"].as_str().unwrap())
+        .map(|l| Url::parse(l.as_str()).unwrap())
+        .find(|l| l.path() == "/preferences")
+        .expect("Confirmation email has no link to the preference center.");
+    link.set_port(Some(app.port)).unwrap();
+    link
+}
+
+async fn post_preferences(app: &TestApp, link: &Url, form: &[(&str, &str)]) -> reqwest::Response {
+    app.api_client
+        .post(link.clone())
+        .form(form)
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn preference_center_shows_profile_lists_and_unsubscribe_link() {
+    // Arrange
+    let app = spawn_app().await;
+    let link = subscriber_preferences_link(&app, true).await;
+
+    // Act
+    let response = app.api_client.get(link).send().await.unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("id=\"profile\""));
+    assert!(html_page.contains("id=\"lists\""));
+    assert!(html_page.contains("id=\"frequency\""));
+    assert!(html_page.contains("/subscriptions/unsubscribe?subscription_token="));
+    assert!(!html_page.contains("waiting for confirmation"));
+}
+
+#[tokio::test]
+async fn saving_preferences_updates_profile_and_frequency() {
+    // Arrange
+    let app = spawn_app().await;
+    let link = subscriber_preferences_link(&app, true).await;
+
+    // Act
+    let response = post_preferences(
+        &app,
+        &link,
+        &[
+            ("name", "Ursula"),
+            ("locale", "de"),
+            ("max_emails_per_week", "1"),
+        ],
+    )
+    .await;
+
+    // Assert
+    assert_is_redirect_to(
+        &response,
+        &format!("{}?{}", link.path(), link.query().unwrap()),
+    );
+    let saved = sqlx::query!("SELECT name, locale, max_emails_per_week FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.name, "Ursula");
+    assert_eq!(saved.locale.as_deref(), Some("de"));
+    assert_eq!(saved.max_emails_per_week, Some(1));
+    let html_page = app
+        .api_client
+        .get(link)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("Your preferences have been saved."));
+    assert!(html_page.contains("<option value=\"1\" selected>"));
+}
+
+#[tokio::test]
+async fn invalid_preferences_are_rejected_in_the_preference_center() {
+    // Arrange
+    let app = spawn_app().await;
+    let link = subscriber_preferences_link(&app, true).await;
+
+    // Act
+    post_preferences(&app, &link, &[("name", ""), ("locale", "")]).await;
+
+    // Assert
+    let html_page = app
+        .api_client
+        .get(link)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("`` is not a valid subscriber name."));
+}
+
+#[tokio::test]
+async fn confirmed_subscribers_join_and_leave_mailing_lists() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.api_client
+        .post(format!("{}/admin/lists", &app.address))
+        .form(&ListFormData {
+            name: "Release notes".to_string(),
+        })
+        .send()
+        .await
+        .unwrap();
+    let list_id = sqlx::query!("SELECT list_id FROM lists WHERE name = 'Release notes'")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .list_id
+        .to_string();
+    let link = subscriber_preferences_link(&app, true).await;
+
+    // Act - Part 1 - join list
+    post_preferences(
+        &app,
+        &link,
+        &[("name", "le guin"), ("list_id", list_id.as_str())],
+    )
+    .await;
+
+    // Assert - Part 1
+    let statuses = sqlx::query!("SELECT status::text AS \"status!\" FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(statuses.len(), 2);
+    assert!(statuses.iter().all(|s| s.status == "confirmed"));
+    assert_eq!(app.num_rows_of_table("subscription_tokens").await, 2);
+
+    // Act - Part 2 - leave list
+    post_preferences(&app, &link, &[("name", "le guin")]).await;
+
+    // Assert - Part 2
+    assert_eq!(app.num_rows_of_table("subscriptions").await, 1);
+    assert_eq!(app.num_rows_of_table("subscription_tokens").await, 1);
+}
+
+#[tokio::test]
+async fn pending_subscribers_can_not_join_mailing_lists() {
+    // Arrange
+    let app = spawn_app().await;
+    let link = subscriber_preferences_link(&app, false).await;
+    let html_page = app
+        .api_client
+        .get(link.clone())
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("waiting for confirmation"));
+
+    // Act
+    post_preferences(
+        &app,
+        &link,
+        &[
+            ("name", "le guin"),
+            ("list_id", "00000000-0000-0000-0000-000000000000"),
+        ],
+    )
+    .await;
+
+    // Assert
+    assert_eq!(app.num_rows_of_table("subscriptions").await, 1);
+}
+
+#[tokio::test]
+async fn chosen_frequency_caps_deliveries_to_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    let link = subscriber_preferences_link(&app, true).await;
+    post_preferences(
+        &app,
+        &link,
+        &[("name", "le guin"), ("max_emails_per_week", "1")],
+    )
+    .await;
+    app.test_user.login(&app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    for title in ["First issue", "Second issue"] {
+        app.post_newsletters(&NewsletterFormData {
+            title: title.to_string(),
+            ..valid_newsletter_form_data()
+        })
+        .await;
+        app.dispatch_all_pending_emails().await;
+    }
+
+    // Assert - mock verifies on drop that only the first issue was sent
+    assert_eq!(app.num_rows_of_table("frequency_capped_sends").await, 1);
+}
+
+#[tokio::test]
+async fn newsletter_emails_link_to_preference_center() {
+    // Arrange
+    let app = spawn_app().await;
+    subscriber_preferences_link(&app, true).await;
+    app.test_user.login(&app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_newsletters(&valid_newsletter_form_data()).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert!(body["HtmlBody"]
+        .as_str()
+        .unwrap()
+        .contains("/preferences?t="));
+    assert!(body["TextBody"]
+        .as_str()
+        .unwrap()
+        .contains("/preferences?t="));
+}
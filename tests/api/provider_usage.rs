@@ -0,0 +1,98 @@
+//! tests/api/provider_usage.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, spawn_app_with, TestApp};
+use crate::newsletter::{
+    create_confirmed_subscriber, valid_newsletter_form_data, when_sending_an_email,
+};
+use wiremock::ResponseTemplate;
+use zero2prod::configuration::ProviderPlanSettings;
+
+/// Spawn app with a provider plan, which allows a single email per month.
+async fn spawn_app_with_tiny_plan() -> TestApp {
+    spawn_app_with(|c| {
+        c.emailclient.plan = Some(ProviderPlanSettings {
+            monthly_email_limit: 1,
+            cost_per_thousand_emails: 500.0,
+            currency: "EUR".to_string(),
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn simulation_estimates_cost_and_warns_about_quota() {
+    // Arrange
+    let app = spawn_app_with_tiny_plan().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .post_newsletter_simulate(&valid_newsletter_form_data())
+        .await;
+
+    // Assert
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("estimated cost: 1.00 EUR for 2 emails"));
+    assert!(html_page.contains("0 of 1 emails sent, 1 remaining"));
+    assert!(html_page.contains(r#"<p id="quota_warning">"#));
+}
+
+#[tokio::test]
+async fn simulation_without_provider_plan_shows_no_cost() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .post_newsletter_simulate(&valid_newsletter_form_data())
+        .await;
+
+    // Assert
+    let html_page = response.text().await.unwrap();
+    assert!(!html_page.contains("estimated cost"));
+}
+
+#[tokio::test]
+async fn publishing_above_quota_is_accepted_with_warning() {
+    // Arrange
+    let app = spawn_app_with_tiny_plan().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.post_newsletters(&valid_newsletter_form_data()).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    assert_eq!(app.num_rows_of_table("issue_delivery_queue").await, 2);
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("The newsletter issue has been accepted"));
+    assert!(html_page.contains("Sending 2 emails exceeds the remaining quota of 1 emails"));
+}
+
+#[tokio::test]
+async fn sent_emails_are_tracked_against_provider_plan() {
+    // Arrange
+    let app = spawn_app_with_tiny_plan().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    app.post_newsletters(&valid_newsletter_form_data()).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let html_page = app.get_admin_dashboard_html().await;
+    assert!(html_page.contains("Provider plan this month: 1 of 1 newsletter emails sent (100%)"));
+    assert!(html_page.contains(r#"<p id="quota_warning">"#));
+}
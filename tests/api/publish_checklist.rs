@@ -0,0 +1,154 @@
+//! tests/api/publish_checklist.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app_with, TestApp};
+use crate::newsletter::{
+    create_confirmed_subscriber, valid_newsletter_form_data, when_sending_an_email,
+};
+use wiremock::ResponseTemplate;
+use zero2prod::mailing_lists::DEFAULT_LIST_ID;
+use zero2prod::routes::{ChecklistItem, NewsletterFormData};
+
+async fn spawn_app_with_checklist(checklist: Vec<ChecklistItem>) -> TestApp {
+    let app = spawn_app_with(|c| c.application.publish_checklist = checklist).await;
+    app.test_user.login(&app).await;
+    app
+}
+
+async fn assert_issue_is_accepted(app: &TestApp, form: &NewsletterFormData) {
+    let response = app.post_newsletters(form).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains(
+        "<p><i>The newsletter issue has been accepted - \
+        emails will go out shortly.</i></p>"
+    ));
+}
+
+#[tokio::test]
+async fn incomplete_checklist_rejects_publishing() {
+    // Arrange
+    let app = spawn_app_with_checklist(vec![
+        ChecklistItem::SubjectSet,
+        ChecklistItem::PreviewSent,
+        ChecklistItem::SegmentChosen,
+    ])
+    .await;
+    create_confirmed_subscriber(&app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_newsletters(&valid_newsletter_form_data()).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains(
+        "<p><i>The pre-publish checklist is incomplete: preview sent, segment chosen.</i></p>"
+    ));
+    assert!(!app.dispatch_all_pending_emails().await);
+    assert_eq!(app.num_rows_of_table("newsletter_issues").await, 0);
+}
+
+#[tokio::test]
+async fn publish_form_shows_checklist() {
+    // Arrange
+    let app = spawn_app_with_checklist(vec![
+        ChecklistItem::SubjectSet,
+        ChecklistItem::LinksValidated,
+    ])
+    .await;
+
+    // Act
+    let html_page = app.get_publish_newsletter_html().await;
+
+    // Assert
+    assert!(html_page.contains("Pre-publish checklist"));
+    assert!(html_page.contains("name=\"checked_links_validated\""));
+    assert!(!html_page.contains("name=\"checked_preview_sent\""));
+}
+
+#[tokio::test]
+async fn manually_checked_items_allow_publishing() {
+    // Arrange
+    let app = spawn_app_with_checklist(vec![
+        ChecklistItem::PreviewSent,
+        ChecklistItem::SegmentChosen,
+    ])
+    .await;
+
+    // Act & Assert
+    let form = NewsletterFormData {
+        checked_preview_sent: true,
+        checked_segment_chosen: true,
+        ..valid_newsletter_form_data()
+    };
+    assert_issue_is_accepted(&app, &form).await;
+}
+
+#[tokio::test]
+async fn test_email_and_chosen_list_satisfy_checklist() {
+    // Arrange
+    let app = spawn_app_with_checklist(vec![
+        ChecklistItem::SubjectSet,
+        ChecklistItem::PreviewSent,
+        ChecklistItem::SegmentChosen,
+    ])
+    .await;
+    app.post_admin_email("admin@example.com").await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    let form = || NewsletterFormData {
+        list_id: DEFAULT_LIST_ID.to_string(),
+        ..valid_newsletter_form_data()
+    };
+
+    // Act - Part 1 - changed content requires a new test email
+    app.post_newsletter_test(&form()).await;
+    let changed_form = NewsletterFormData {
+        text_content: "Changed body".to_string(),
+        ..form()
+    };
+    let response = app.post_newsletters(&changed_form).await;
+
+    // Assert - Part 1
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("The pre-publish checklist is incomplete: preview sent."));
+
+    // Act & Assert - Part 2 - content of the test email
+    assert_issue_is_accepted(&app, &form()).await;
+}
+
+#[tokio::test]
+async fn invalid_links_are_reported() {
+    // Arrange
+    let app = spawn_app_with_checklist(vec![ChecklistItem::LinksValidated]).await;
+    let form = NewsletterFormData {
+        html_content: r#"<p>See <a href="/archive">archive</a></p>"#.to_string(),
+        ..valid_newsletter_form_data()
+    };
+
+    // Act - Part 1
+    let response = app.post_newsletters(&form).await;
+
+    // Assert - Part 1
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains(
+        "The pre-publish checklist is incomplete: links validated \
+        (`/archive` is not an absolute URL)."
+    ));
+
+    // Act & Assert - Part 2 - absolute links are valid
+    let form = NewsletterFormData {
+        html_content: r#"<p>See <a href="https://example.com/archive">archive</a></p>"#.to_string(),
+        ..form
+    };
+    assert_issue_is_accepted(&app, &form).await;
+}
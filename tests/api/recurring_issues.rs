@@ -0,0 +1,212 @@
+//! tests/api/recurring_issues.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+use zero2prod::recurring_issues::create_due_drafts;
+use zero2prod::routes::RecurringIssueFormData;
+
+async fn post_recurring_issue(
+    app: &TestApp,
+    period_days: &str,
+    first_scheduled_at: &str,
+) -> reqwest::Response {
+    app.api_client
+        .post(format!("{}/admin/newsletters/recurring", &app.address))
+        .form(&RecurringIssueFormData {
+            name: "Monday roundup".to_string(),
+            title: "Roundup {date}".to_string(),
+            text_content: "Newsletter body as plain text".to_string(),
+            html_content: "<p>Newsletter body as HTML</p>".to_string(),
+            markdown_content: String::new(),
+            collect_feedback: false,
+            period_days: period_days.to_string(),
+            first_scheduled_at: first_scheduled_at.to_string(),
+        })
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn get_recurring_issues_html(app: &TestApp) -> String {
+    app.get_response_from_url("/admin/newsletters/recurring")
+        .await
+        .text()
+        .await
+        .unwrap()
+}
+
+async fn recurring_issue_id(app: &TestApp) -> Uuid {
+    sqlx::query!("SELECT recurring_issue_id FROM recurring_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .recurring_issue_id
+}
+
+async fn post_recurring_issue_action(app: &TestApp, action: &str) -> reqwest::Response {
+    let recurring_issue_id = recurring_issue_id(app).await;
+    app.api_client
+        .post(format!(
+            "{}/admin/newsletters/recurring/{}/{}",
+            &app.address, recurring_issue_id, action
+        ))
+        .send()
+        .await
+        .unwrap()
+}
+
+/// Scheduled time of the first issue in `days` from now, formatted as `datetime-local`.
+fn in_days(days: i64) -> String {
+    (Utc::now() + Duration::days(days))
+        .format("%Y-%m-%dT%H:%M")
+        .to_string()
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_manage_recurring_issues() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .get_response_from_url("/admin/newsletters/recurring")
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn due_recurring_issue_creates_one_scheduled_draft() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let first_scheduled_at = in_days(3);
+    let response = post_recurring_issue(&app, "7", &first_scheduled_at).await;
+    assert_is_redirect_to(&response, "/admin/newsletters/recurring");
+
+    // Act
+    let num_created_first = create_due_drafts(&app.db_pool).await.unwrap();
+    let num_created_second = create_due_drafts(&app.db_pool).await.unwrap();
+
+    // Assert
+    assert_eq!(num_created_first, 1);
+    assert_eq!(num_created_second, 0);
+    let draft = sqlx::query!("SELECT title, text_content, scheduled_at FROM newsletter_drafts")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(
+        draft.title,
+        format!("Roundup {}", &first_scheduled_at[..10])
+    );
+    assert_eq!(draft.text_content, "Newsletter body as plain text");
+    assert_eq!(
+        draft
+            .scheduled_at
+            .unwrap()
+            .format("%Y-%m-%dT%H:%M")
+            .to_string(),
+        first_scheduled_at
+    );
+    let html_page = get_recurring_issues_html(&app).await;
+    assert!(html_page.contains("The recurring issue `Monday roundup` has been created."));
+    let next_scheduled_at = draft.scheduled_at.unwrap() + Duration::days(7);
+    assert!(html_page.contains(&format!(
+        "next issue at {}",
+        next_scheduled_at.format("%Y-%m-%d %H:%M UTC")
+    )));
+}
+
+#[tokio::test]
+async fn recurring_issue_beyond_one_period_creates_no_draft() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    post_recurring_issue(&app, "7", &in_days(10)).await;
+
+    // Act
+    let num_created = create_due_drafts(&app.db_pool).await.unwrap();
+
+    // Assert
+    assert_eq!(num_created, 0);
+    assert_eq!(app.num_rows_of_table("newsletter_drafts").await, 0);
+}
+
+#[tokio::test]
+async fn skipped_issue_creates_no_draft() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    post_recurring_issue(&app, "7", &in_days(3)).await;
+
+    // Act
+    let response = post_recurring_issue_action(&app, "skip").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters/recurring");
+    let html_page = get_recurring_issues_html(&app).await;
+    assert!(html_page.contains("The next issue of `Monday roundup` has been skipped"));
+    assert_eq!(create_due_drafts(&app.db_pool).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn paused_recurring_issue_creates_no_draft_until_resumed() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    post_recurring_issue(&app, "7", &in_days(3)).await;
+
+    // Act - Part 1 - pause
+    let response = post_recurring_issue_action(&app, "pause").await;
+
+    // Assert - Part 1
+    assert_is_redirect_to(&response, "/admin/newsletters/recurring");
+    assert_eq!(create_due_drafts(&app.db_pool).await.unwrap(), 0);
+
+    // Act - Part 2 - resume
+    let response = post_recurring_issue_action(&app, "resume").await;
+
+    // Assert - Part 2
+    assert_is_redirect_to(&response, "/admin/newsletters/recurring");
+    assert_eq!(create_due_drafts(&app.db_pool).await.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn invalid_period_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = post_recurring_issue(&app, "0", &in_days(3)).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters/recurring");
+    let html_page = get_recurring_issues_html(&app).await;
+    assert!(html_page.contains("The period must be a number of days between 1 and 365."));
+    assert_eq!(app.num_rows_of_table("recurring_issues").await, 0);
+}
+
+#[tokio::test]
+async fn unknown_recurring_issue_returns_404() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!(
+            "{}/admin/newsletters/recurring/{}/pause",
+            &app.address,
+            Uuid::new_v4()
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+}
@@ -0,0 +1,50 @@
+//! tests/api/schema_check.rs
+
+use crate::helpers::spawn_app;
+use zero2prod::configuration::get_configuration;
+use zero2prod::startup::Application;
+
+#[tokio::test]
+async fn startup_fails_with_name_of_missing_column() {
+    // Arrange
+    let test_app = spawn_app().await;
+    // sabotage the database
+    sqlx::query!("ALTER TABLE subscription_tokens DROP COLUMN subscription_token;",)
+        .execute(&test_app.db_pool)
+        .await
+        .unwrap();
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.database.database_name = test_app.db_name.clone();
+    configuration.application.port = 0;
+
+    // Act
+    let result = Application::build(configuration).await;
+
+    // Assert
+    let Err(e) = result else {
+        panic!("Application started with drifted database schema.");
+    };
+    assert!(format!("{:?}", e).contains("column `subscription_tokens.subscription_token`"));
+}
+
+#[tokio::test]
+async fn startup_fails_with_name_of_missing_table() {
+    // Arrange
+    let test_app = spawn_app().await;
+    sqlx::query!("ALTER TABLE idempotency RENAME TO idempotency_old;",)
+        .execute(&test_app.db_pool)
+        .await
+        .unwrap();
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.database.database_name = test_app.db_name.clone();
+    configuration.application.port = 0;
+
+    // Act
+    let result = Application::build(configuration).await;
+
+    // Assert
+    let Err(e) = result else {
+        panic!("Application started with drifted database schema.");
+    };
+    assert!(format!("{:?}", e).contains("table `idempotency`"));
+}
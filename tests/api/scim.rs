@@ -0,0 +1,266 @@
+//! tests/api/scim.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use reqwest::Method;
+use secrecy::ExposeSecret;
+use serde_json::json;
+use uuid::Uuid;
+use zero2prod::authentication::mint_api_token;
+use zero2prod::routes::{ScimListResponse, ScimUser};
+
+/// Request to the SCIM API with the SCIM token as bearer token.
+fn scim_request(app: &TestApp, method: Method, path: &str) -> reqwest::RequestBuilder {
+    app.api_client
+        .request(method, format!("{}/scim/v2/{}", app.address, path))
+        .bearer_auth(app.scim_token.expose_secret())
+}
+
+/// Send JSON with the content type, which identity providers use.
+async fn send_scim_json(
+    request: reqwest::RequestBuilder,
+    body: serde_json::Value,
+) -> reqwest::Response {
+    request
+        .header("Content-Type", "application/scim+json")
+        .body(body.to_string())
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+fn scim_user_body(username: &str, role: &str) -> serde_json::Value {
+    json!({
+        "schemas": ["urn:ietf:params:scim:schemas:core:2.0:User"],
+        "userName": username,
+        "name": { "givenName": "Ursula", "familyName": "Le Guin" },
+        "emails": [{ "value": "ursula_le_guin@gmail.com", "primary": true, "type": "work" }],
+        "active": true,
+        "roles": [{ "value": role }]
+    })
+}
+
+async fn audit_actions(app: &TestApp, user_id: Uuid) -> Vec<String> {
+    sqlx::query!(
+        r#"
+        SELECT action::TEXT AS "action!"
+        FROM user_audit_log
+        WHERE user_id = $1
+        ORDER BY audit_id
+        "#,
+        user_id
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|r| r.action)
+    .collect()
+}
+
+#[tokio::test]
+async fn scim_rejects_requests_without_valid_scim_token() {
+    // Arrange
+    let app = spawn_app().await;
+    let num_users = app.num_rows_of_table("users").await;
+    let (_, api_token) = mint_api_token(&app.db_pool, "CI publish job")
+        .await
+        .unwrap();
+
+    for token in [
+        "wrong-scim-token",
+        app.api_key.expose_secret(),
+        api_token.expose_secret(),
+    ] {
+        // Act
+        let response = app
+            .api_client
+            .post(format!("{}/scim/v2/Users", app.address))
+            .bearer_auth(token)
+            .header("Content-Type", "application/scim+json")
+            .body(scim_user_body("mallory", "admin").to_string())
+            .send()
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(response.status().as_u16(), 401);
+    }
+    assert_eq!(app.num_rows_of_table("users").await, num_users);
+}
+
+#[tokio::test]
+async fn provisioned_user_is_created_with_role_and_logged() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = send_scim_json(
+        scim_request(&app, Method::POST, "Users"),
+        scim_user_body("ursula", "admin"),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 201);
+    assert_eq!(response.headers()["Content-Type"], "application/scim+json");
+    let location = response.headers()["Location"].to_str().unwrap().to_string();
+    let user: ScimUser = response.json().await.unwrap();
+    assert_eq!(location, user.meta.location);
+    assert_eq!(user.user_name, "ursula");
+    assert!(user.active);
+    assert_eq!(user.roles[0].value, "admin");
+    let saved = sqlx::query!(
+        r#"SELECT email, role::TEXT AS "role!" FROM users WHERE user_id = $1"#,
+        user.id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(saved.email.as_deref(), Some("ursula_le_guin@gmail.com"));
+    assert_eq!(saved.role, "admin");
+    assert_eq!(audit_actions(&app, user.id).await, ["provisioned"]);
+}
+
+#[tokio::test]
+async fn provisioning_needs_unique_username_and_email_address() {
+    // Arrange
+    let app = spawn_app().await;
+    let mut without_email = scim_user_body("ursula", "editor");
+    without_email["emails"] = json!([]);
+
+    // Act
+    let taken = send_scim_json(
+        scim_request(&app, Method::POST, "Users"),
+        scim_user_body(&app.test_user.username, "editor"),
+    )
+    .await;
+    let invalid = send_scim_json(scim_request(&app, Method::POST, "Users"), without_email).await;
+
+    // Assert
+    assert_eq!(taken.status().as_u16(), 409);
+    let error: serde_json::Value = taken.json().await.unwrap();
+    assert_eq!(error["scimType"], "uniqueness");
+    assert_eq!(invalid.status().as_u16(), 400);
+    let ursula = sqlx::query!("SELECT user_id FROM users WHERE username = 'ursula'")
+        .fetch_optional(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(ursula.is_none());
+    assert_eq!(app.num_rows_of_table("user_audit_log").await, 0);
+}
+
+#[tokio::test]
+async fn users_are_found_by_username_filter() {
+    // Arrange
+    let app = spawn_app().await;
+    let filter = format!(r#"userName eq "{}""#, app.test_user.username);
+
+    // Act
+    let found = scim_request(&app, Method::GET, "Users")
+        .query(&[("filter", filter.as_str())])
+        .send()
+        .await
+        .unwrap();
+    let unsupported = scim_request(&app, Method::GET, "Users")
+        .query(&[("filter", r#"displayName co "u""#)])
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(found.status().as_u16(), 200);
+    let list: ScimListResponse = found.json().await.unwrap();
+    assert_eq!(list.total_results, 1);
+    assert_eq!(list.resources[0].id, app.test_user.user_id);
+    assert_eq!(unsupported.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn patch_deactivates_user_and_ends_sessions() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let path = format!("Users/{}", app.test_user.user_id);
+
+    // Act
+    let response = send_scim_json(
+        scim_request(&app, Method::PATCH, &path),
+        json!({
+            "schemas": ["urn:ietf:params:scim:api:messages:2.0:PatchOp"],
+            "Operations": [{ "op": "Replace", "path": "active", "value": "False" }]
+        }),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let user: ScimUser = response.json().await.unwrap();
+    assert!(!user.active);
+    assert_eq!(app.num_rows_of_table("admin_sessions").await, 0);
+    assert_is_redirect_to(&app.get_admin_dashboard().await, "/login");
+    assert_eq!(
+        audit_actions(&app, app.test_user.user_id).await,
+        ["deactivated"]
+    );
+}
+
+#[tokio::test]
+async fn put_replaces_user_and_logs_changes() {
+    // Arrange
+    let app = spawn_app().await;
+    let path = format!("Users/{}", app.test_user.user_id);
+
+    // Act
+    let response = send_scim_json(
+        scim_request(&app, Method::PUT, &path),
+        scim_user_body("ursula", "editor"),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let user: ScimUser = response.json().await.unwrap();
+    assert_eq!(user.user_name, "ursula");
+    assert_eq!(user.roles[0].value, "editor");
+    let details = sqlx::query!("SELECT details FROM user_audit_log WHERE action = 'updated'")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .details
+        .unwrap();
+    assert!(details.contains("role admin -> editor"));
+    assert!(details.contains("userName"));
+}
+
+#[tokio::test]
+async fn delete_deprovisions_user_by_deactivation() {
+    // Arrange
+    let app = spawn_app().await;
+    let path = format!("Users/{}", app.test_user.user_id);
+
+    // Act
+    let response = scim_request(&app, Method::DELETE, &path)
+        .send()
+        .await
+        .unwrap();
+    let unknown = scim_request(&app, Method::DELETE, &format!("Users/{}", Uuid::new_v4()))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 204);
+    assert_eq!(unknown.status().as_u16(), 404);
+    let user: ScimUser = scim_request(&app, Method::GET, &path)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(!user.active);
+    assert_eq!(
+        audit_actions(&app, app.test_user.user_id).await,
+        ["deactivated"]
+    );
+}
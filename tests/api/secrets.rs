@@ -0,0 +1,158 @@
+//! tests/api/secrets.rs
+
+use config::Config;
+use serde_json::json;
+use uuid::Uuid;
+use wiremock::matchers::{header, header_exists, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+use zero2prod::secrets::resolve_secrets;
+
+/// Configuration with a secret reference to the password of the database.
+fn config_with_reference(secret_name: &str, backend: serde_json::Value) -> Config {
+    Config::builder()
+        .set_override(
+            "database.password",
+            format!("secret://{}#password", secret_name),
+        )
+        .unwrap()
+        .set_override("database.username", "postgres")
+        .unwrap()
+        .add_source(config::File::from_str(
+            &json!({ "secrets": { "backend": backend, "refresh_seconds": 300 } }).to_string(),
+            config::FileFormat::Json,
+        ))
+        .build()
+        .unwrap()
+}
+
+fn vault_backend(vault: &MockServer) -> serde_json::Value {
+    json!({ "type": "vault", "address": vault.uri(), "token": "vault-token" })
+}
+
+#[tokio::test]
+async fn secret_references_are_resolved_from_vault_and_cached() {
+    // Arrange
+    let vault = MockServer::start().await;
+    // unique name, since the cache of secrets is shared by all tests
+    let secret_name = format!("zero2prod/{}", Uuid::new_v4());
+    Mock::given(method("GET"))
+        .and(path(format!("/v1/secret/data/{}", secret_name)))
+        .and(header("X-Vault-Token", "vault-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": { "data": { "password": "s3cret" }, "metadata": { "version": 1 } }
+        })))
+        .expect(1)
+        .mount(&vault)
+        .await;
+
+    // Act
+    let resolved = resolve_secrets(config_with_reference(&secret_name, vault_backend(&vault)));
+    let resolved_again =
+        resolve_secrets(config_with_reference(&secret_name, vault_backend(&vault)));
+
+    // Assert
+    for config in [resolved.unwrap(), resolved_again.unwrap()] {
+        assert_eq!(config.get_string("database.password").unwrap(), "s3cret");
+        assert_eq!(config.get_string("database.username").unwrap(), "postgres");
+    }
+    // Mock verifies on Drop that the secret has been fetched once
+}
+
+#[tokio::test]
+async fn cached_secrets_are_refreshed_after_refresh_seconds() {
+    // Arrange
+    let vault = MockServer::start().await;
+    let secret_name = format!("zero2prod/{}", Uuid::new_v4());
+    Mock::given(method("GET"))
+        .and(path(format!("/v1/secret/data/{}", secret_name)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": { "data": { "password": "rotated" } }
+        })))
+        .expect(2)
+        .mount(&vault)
+        .await;
+    let config = || {
+        Config::builder()
+            .add_source(config_with_reference(&secret_name, vault_backend(&vault)))
+            .set_override("secrets.refresh_seconds", 0)
+            .unwrap()
+            .build()
+            .unwrap()
+    };
+
+    // Act
+    resolve_secrets(config()).unwrap();
+    let resolved = resolve_secrets(config()).unwrap();
+
+    // Assert
+    assert_eq!(resolved.get_string("database.password").unwrap(), "rotated");
+}
+
+#[tokio::test]
+async fn secret_references_are_resolved_from_aws_secrets_manager() {
+    // Arrange
+    let secrets_manager = MockServer::start().await;
+    let secret_name = format!("zero2prod/{}", Uuid::new_v4());
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .and(header("x-amz-target", "secretsmanager.GetSecretValue"))
+        .and(header("content-type", "application/x-amz-json-1.1"))
+        .and(header_exists("authorization"))
+        .and(header_exists("x-amz-date"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "Name": secret_name,
+            "SecretString": json!({ "password": "from-aws" }).to_string()
+        })))
+        .expect(1)
+        .mount(&secrets_manager)
+        .await;
+    let backend = json!({
+        "type": "aws_secrets_manager",
+        "region": "eu-central-1",
+        "access_key_id": "AKIDEXAMPLE",
+        "secret_access_key": "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+        "endpoint": secrets_manager.uri()
+    });
+
+    // Act
+    let resolved = resolve_secrets(config_with_reference(&secret_name, backend)).unwrap();
+
+    // Assert
+    assert_eq!(
+        resolved.get_string("database.password").unwrap(),
+        "from-aws"
+    );
+    let requests = secrets_manager.received_requests().await.unwrap();
+    let body: serde_json::Value = requests[0].body_json().unwrap();
+    assert_eq!(body["SecretId"], secret_name);
+    let authorization = requests[0].headers["authorization"].to_str().unwrap();
+    assert!(authorization.contains("/eu-central-1/secretsmanager/aws4_request"));
+}
+
+#[tokio::test]
+async fn secret_references_without_backend_or_field_are_rejected() {
+    // Arrange
+    let vault = MockServer::start().await;
+    let secret_name = format!("zero2prod/{}", Uuid::new_v4());
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": { "data": { "username": "app" } }
+        })))
+        .mount(&vault)
+        .await;
+    let without_backend = Config::builder()
+        .set_override("database.password", "secret://zero2prod/database#password")
+        .unwrap()
+        .build()
+        .unwrap();
+
+    // Act
+    let without_backend = resolve_secrets(without_backend);
+    let without_field = resolve_secrets(config_with_reference(&secret_name, vault_backend(&vault)));
+
+    // Assert
+    let error = without_backend.unwrap_err().to_string();
+    assert!(error.contains("requires a `secrets` backend"));
+    let error = without_field.unwrap_err().to_string();
+    assert!(error.contains("has no field `password`"));
+}
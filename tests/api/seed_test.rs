@@ -0,0 +1,150 @@
+//! tests/api/seed_test.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, spawn_app_with, TestApp};
+use crate::newsletter::{
+    create_confirmed_subscriber, valid_newsletter_form_data, when_sending_an_email,
+};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::routes::ChecklistItem;
+
+async fn spawn_app_with_seeds(seed_addresses: &[&str]) -> TestApp {
+    let seed_addresses = seed_addresses.iter().map(|a| a.to_string()).collect();
+    let app = spawn_app_with(|c| {
+        c.application.seed_addresses = seed_addresses;
+        c.application.publish_checklist = vec![ChecklistItem::SeedTestAcknowledged];
+    })
+    .await;
+    app.test_user.login(&app).await;
+    app
+}
+
+#[tokio::test]
+async fn seed_test_is_sent_to_seed_mailboxes_only() {
+    // Arrange
+    let app = spawn_app_with_seeds(&["seed@gmail.com", "seed@outlook.com", "no-email"]).await;
+    create_confirmed_subscriber(&app).await;
+    // both valid seed addresses are sent in one batch request
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+            { "ErrorCode": 0, "Message": "OK" },
+            { "ErrorCode": 0, "Message": "OK" }
+        ])))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_newsletter_seed_test(&valid_newsletter_form_data())
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("A seed test has been sent to 2 of 3 seed mailboxes."));
+    assert!(html_page.contains("id=\"seed_tests\""));
+    assert!(html_page.contains("<td>seed@gmail.com</td>"));
+    assert!(html_page.contains("is not a valid subscriber email."));
+    assert!(html_page.contains("Acknowledge seed test"));
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body[0]["Subject"], "[SEED] Newsletter title");
+    assert_eq!(app.num_rows_of_table("seed_test_results").await, 3);
+    // neither an issue is stored nor a delivery task is queued
+    assert!(!app.dispatch_all_pending_emails().await);
+}
+
+#[tokio::test]
+async fn acknowledged_seed_test_satisfies_checklist() {
+    // Arrange
+    let app = spawn_app_with_seeds(&["seed@gmail.com"]).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.post_newsletter_seed_test(&valid_newsletter_form_data())
+        .await;
+
+    // Act - Part 1 - publish before acknowledgment
+    app.post_newsletters(&valid_newsletter_form_data()).await;
+
+    // Assert - Part 1
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("The pre-publish checklist is incomplete: seed test acknowledged."));
+
+    // Act - Part 2 - acknowledge and publish
+    let seed_test_id = sqlx::query!("SELECT seed_test_id FROM seed_tests")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .seed_test_id;
+    let response = app
+        .api_client
+        .post(format!(
+            "{}/admin/newsletters/seed_test/{}/acknowledge",
+            &app.address, seed_test_id
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.post_newsletters(&valid_newsletter_form_data()).await;
+
+    // Assert - Part 2
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("The newsletter issue has been accepted"));
+    assert!(html_page.contains("Acknowledged at"));
+}
+
+#[tokio::test]
+async fn seed_test_requires_seed_addresses() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_newsletter_seed_test(&valid_newsletter_form_data())
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("<p><i>Configure seed addresses to send seed tests.</i></p>"));
+    assert!(!html_page.contains("Send seed test"));
+}
+
+#[tokio::test]
+async fn acknowledging_unknown_seed_test_returns_404() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!(
+            "{}/admin/newsletters/seed_test/{}/acknowledge",
+            &app.address,
+            uuid::Uuid::new_v4()
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+}
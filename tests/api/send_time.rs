@@ -0,0 +1,137 @@
+//! tests/api/send_time.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use crate::newsletter::{
+    create_confirmed_subscriber, valid_newsletter_form_data, when_sending_an_email,
+};
+use chrono::{TimeDelta, Timelike, Utc};
+use reqwest::Url;
+use uuid::Uuid;
+use wiremock::ResponseTemplate;
+use zero2prod::domain::SubscriberEmail;
+use zero2prod::routes::NewsletterFormData;
+
+/// Extract the open tracking link from the html body of the last email sent.
+async fn get_open_tracking_link(app: &TestApp) -> Option<Url> {
+    let email_request = app.email_server.received_requests().await.unwrap().pop()?;
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    linkify::LinkFinder::new()
+        .links(body["HtmlBody"].as_str().unwrap())
+        .filter(|l| *l.kind() == linkify::LinkKind::Url)
+        .map(|l| Url::parse(l.as_str()).unwrap())
+        .find(|l| l.path().starts_with("/open/"))
+        .map(|mut l| {
+            // Let's make sure we don't call random APIs on the web
+            assert_eq!(l.host_str().unwrap(), "127.0.0.1");
+            // Let's rewrite the URL to include the port
+            l.set_port(Some(app.port)).unwrap();
+            l
+        })
+}
+
+async fn get_subscriber_id(app: &TestApp, email: &SubscriberEmail) -> Uuid {
+    sqlx::query!(
+        "SELECT id FROM subscriptions WHERE email = $1",
+        email.as_ref()
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .id
+}
+
+async fn get_execute_after(app: &TestApp, subscriber_id: Uuid) -> chrono::DateTime<Utc> {
+    sqlx::query!(
+        "SELECT execute_after FROM issue_delivery_queue WHERE user_id = $1",
+        subscriber_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .execute_after
+}
+
+#[tokio::test]
+async fn first_open_of_delivered_issue_is_recorded_once() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.test_user.login(&app).await;
+    let response = app.post_newsletters(&valid_newsletter_form_data()).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+    let open_tracking_link = get_open_tracking_link(&app).await.unwrap();
+
+    // Act - open email twice
+    for _ in 0..2 {
+        let response = reqwest::get(open_tracking_link.clone()).await.unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(response.headers()["Content-Type"], "image/gif");
+    }
+
+    // Assert
+    let num_opens =
+        sqlx::query!(r#"SELECT COUNT(*) AS "n!" FROM subscriber_events WHERE kind = 'opened'"#)
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap()
+            .n;
+    assert_eq!(num_opens, 1);
+}
+
+#[tokio::test]
+async fn optimized_send_time_delays_delivery_to_best_hour_of_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    let (reader_email, _) = create_confirmed_subscriber(&app).await;
+    let (new_email, _) = create_confirmed_subscriber(&app).await;
+    let reader_id = get_subscriber_id(&app, &reader_email).await;
+    let new_id = get_subscriber_id(&app, &new_email).await;
+    // reader usually opens newsletters a few hours later in the day
+    let best_hour = (Utc::now() + TimeDelta::hours(5)).hour();
+    for days_ago in 1..=3 {
+        let occurred_at = (Utc::now() - TimeDelta::days(days_ago))
+            .with_hour(best_hour)
+            .unwrap();
+        sqlx::query!(
+            r#"
+            INSERT INTO subscriber_events (subscriber_id, kind, newsletter_issue_id, occurred_at)
+            VALUES ($1, 'opened', $2, $3)
+            "#,
+            reader_id,
+            Uuid::new_v4(),
+            occurred_at,
+        )
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    }
+    app.test_user.login(&app).await;
+
+    // Act
+    let newsletter = NewsletterFormData {
+        optimize_send_time: true,
+        ..valid_newsletter_form_data()
+    };
+    let response = app.post_newsletters(&newsletter).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    // Assert
+    let reader_execute_after = get_execute_after(&app, reader_id).await;
+    assert_eq!(reader_execute_after.hour(), best_hour);
+    assert_eq!(reader_execute_after.minute(), 0);
+    assert!(reader_execute_after > Utc::now() + TimeDelta::hours(4));
+    // subscribers without tracked opens are delivered immediately
+    assert!(get_execute_after(&app, new_id).await <= Utc::now());
+    assert_eq!(
+        app.get_newsletter_delivery_overview()
+            .await
+            .num_current_subscribers,
+        Some(2)
+    );
+}
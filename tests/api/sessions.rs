@@ -0,0 +1,169 @@
+//! tests/api/sessions.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use uuid::Uuid;
+
+/// Client of another browser, which logs in the test user.
+async fn log_in_other_browser(app: &TestApp) -> reqwest::Client {
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .cookie_store(true)
+        .user_agent("Other browser")
+        .build()
+        .unwrap();
+    let response = client
+        .post(format!("{}/login", &app.address))
+        .form(&[
+            ("username", app.test_user.username.as_str()),
+            ("password", app.test_user.password.as_str()),
+        ])
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    client
+}
+
+async fn get_dashboard(app: &TestApp, client: &reqwest::Client) -> reqwest::Response {
+    client
+        .get(format!("{}/admin/dashboard", &app.address))
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn post_revoke(app: &TestApp, path: &str) -> reqwest::Response {
+    app.api_client
+        .post(format!("{}/admin/sessions/{}", &app.address, path))
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn session_id_of_other_browser(app: &TestApp) -> Uuid {
+    sqlx::query!("SELECT session_id FROM admin_sessions WHERE user_agent = 'Other browser'")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .session_id
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_your_sessions() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_response_from_url("/admin/sessions").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn sessions_are_listed_with_their_metadata() {
+    // Arrange
+    let app = spawn_app().await;
+    log_in_other_browser(&app).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.get_response_from_url("/admin/sessions").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert_eq!(html_page.matches(r#"<p id="admin_session">"#).count(), 2);
+    assert!(html_page.contains("Other browser"));
+    assert!(html_page.contains("127.0.0.1"));
+    assert_eq!(html_page.matches("<b>This session</b>").count(), 1);
+}
+
+#[tokio::test]
+async fn revoked_session_is_logged_out_with_its_next_request() {
+    // Arrange
+    let app = spawn_app().await;
+    let other_browser = log_in_other_browser(&app).await;
+    app.test_user.login(&app).await;
+    let session_id = session_id_of_other_browser(&app).await;
+
+    // Act
+    let response = post_revoke(&app, &format!("{}/revoke", session_id)).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/sessions");
+    let html_page = app.get_response_from_url("/admin/sessions").await;
+    let html_page = html_page.text().await.unwrap();
+    assert!(html_page.contains("The session has been revoked."));
+    assert!(!html_page.contains("Other browser"));
+    assert_is_redirect_to(&get_dashboard(&app, &other_browser).await, "/login");
+    // the current session is still logged in
+    assert_eq!(app.get_admin_dashboard().await.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn sessions_of_other_users_can_not_be_revoked() {
+    // Arrange
+    let app = spawn_app().await;
+    log_in_other_browser(&app).await;
+    let session_id = session_id_of_other_browser(&app).await;
+    app.test_user.login(&app).await;
+    let other_user_id = Uuid::new_v4();
+    sqlx::query!(
+        "INSERT INTO users (user_id, username, password_hash) VALUES ($1, $2, 'unused')",
+        other_user_id,
+        other_user_id.to_string(),
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    sqlx::query!(
+        "UPDATE admin_sessions SET user_id = $1 WHERE session_id = $2",
+        other_user_id,
+        session_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    let response = post_revoke(&app, &format!("{}/revoke", session_id)).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+    assert_eq!(app.num_rows_of_table("admin_sessions").await, 2);
+}
+
+#[tokio::test]
+async fn log_out_everywhere_revokes_all_sessions() {
+    // Arrange
+    let app = spawn_app().await;
+    let other_browser = log_in_other_browser(&app).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = post_revoke(&app, "revoke_all").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("You have been logged out of 2 sessions."));
+    assert_eq!(app.num_rows_of_table("admin_sessions").await, 0);
+    assert_is_redirect_to(&app.get_admin_dashboard().await, "/login");
+    assert_is_redirect_to(&get_dashboard(&app, &other_browser).await, "/login");
+}
+
+#[tokio::test]
+async fn logout_removes_the_session() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    assert_eq!(app.num_rows_of_table("admin_sessions").await, 1);
+
+    // Act
+    let response = app.post_logout().await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    assert_eq!(app.num_rows_of_table("admin_sessions").await, 0);
+}
@@ -0,0 +1,188 @@
+//! tests/api/setup.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, spawn_app_with, TestApp};
+use crate::newsletter::when_sending_an_email;
+use secrecy::Secret;
+use wiremock::ResponseTemplate;
+use zero2prod::configuration::get_configuration;
+use zero2prod::instance_setup::apply_instance_settings;
+
+const SETUP_TOKEN: &str = "setup-token-of-new-instance";
+
+async fn spawn_app_with_setup_token() -> TestApp {
+    spawn_app_with(|c| c.application.setup_token = Some(Secret::new(SETUP_TOKEN.to_string()))).await
+}
+
+/// Remove the test user, so that the application needs the initial setup.
+async fn remove_all_users(app: &TestApp) {
+    sqlx::query!("DELETE FROM users")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+}
+
+fn valid_setup_form() -> Vec<(&'static str, &'static str)> {
+    vec![
+        ("username", "first-admin"),
+        ("email", "first-admin@example.com"),
+        ("password", "a-long-enough-password"),
+        ("password_check", "a-long-enough-password"),
+        ("base_url", "https://newsletter.example.com/"),
+        ("sender_email", "news@example.com"),
+        ("token", SETUP_TOKEN),
+    ]
+}
+
+async fn post_setup(app: &TestApp, form: &[(&str, &str)]) -> reqwest::Response {
+    app.api_client
+        .post(format!("{}/setup", &app.address))
+        .form(form)
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn setup_is_locked_while_users_exist() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let form_response = app.get_response_from_url("/setup").await;
+    let setup_response = post_setup(&app, &valid_setup_form()).await;
+
+    // Assert
+    assert_is_redirect_to(&form_response, "/login");
+    assert_is_redirect_to(&setup_response, "/login");
+    assert_eq!(app.num_rows_of_table("instance_settings").await, 0);
+}
+
+#[tokio::test]
+async fn initial_setup_creates_admin_and_sends_test_email_from_new_sender() {
+    // Arrange
+    let app = spawn_app_with_setup_token().await;
+    remove_all_users(&app).await;
+    let form_html = app
+        .get_response_from_url(&format!("/setup?token={}", SETUP_TOKEN))
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(form_html.contains(r#"<form action="/setup" method="post">"#));
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = post_setup(&app, &valid_setup_form()).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    let login_html = app.get_login_html().await;
+    assert!(login_html.contains("The initial setup has been completed and a test email"));
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body["From"], "news@example.com");
+    assert_eq!(body["To"], "first-admin@example.com");
+    let login = app
+        .post_login(&serde_json::json!({
+            "username": "first-admin",
+            "password": "a-long-enough-password"
+        }))
+        .await;
+    assert_is_redirect_to(&login, "/admin/dashboard");
+    let mut configuration = get_configuration().unwrap();
+    apply_instance_settings(&mut configuration, &app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(
+        configuration.application.base_url,
+        "https://newsletter.example.com"
+    );
+    assert_eq!(configuration.emailclient.sender_email, "news@example.com");
+    assert_is_redirect_to(&app.get_response_from_url("/setup").await, "/login");
+}
+
+#[tokio::test]
+async fn invalid_setup_is_rejected_without_creating_an_admin() {
+    // Arrange
+    let app = spawn_app_with_setup_token().await;
+    remove_all_users(&app).await;
+    let test_cases = [
+        ("username", "  ", "You must set a username for the admin."),
+        (
+            "email",
+            "no-email",
+            "`no-email` is not a valid email of the admin.",
+        ),
+        (
+            "password_check",
+            "another-long-password",
+            "You entered two different new passwords",
+        ),
+        (
+            "base_url",
+            "newsletter.example.com",
+            "The base URL must be an http or https URL.",
+        ),
+        (
+            "sender_email",
+            "no-sender",
+            "`no-sender` is not a valid sender email.",
+        ),
+    ];
+
+    for (field, value, error_message) in test_cases {
+        let mut form = valid_setup_form();
+        form.iter_mut().find(|(name, _)| *name == field).unwrap().1 = value;
+
+        // Act
+        let response = post_setup(&app, &form).await;
+
+        // Assert
+        let setup_url = format!("/setup?token={}", SETUP_TOKEN);
+        assert_is_redirect_to(&response, &setup_url);
+        let setup_html = app
+            .get_response_from_url(&setup_url)
+            .await
+            .text()
+            .await
+            .unwrap();
+        assert!(
+            setup_html.contains(error_message),
+            "The setup did not reject invalid {}.",
+            field
+        );
+    }
+    assert_eq!(app.num_rows_of_table("users").await, 0);
+    assert_eq!(app.num_rows_of_table("instance_settings").await, 0);
+}
+
+#[tokio::test]
+async fn setup_requires_the_setup_token() {
+    // Arrange
+    let app = spawn_app_with_setup_token().await;
+    remove_all_users(&app).await;
+    let mut form = valid_setup_form();
+    form.retain(|(name, _)| *name != "token");
+    let mut form_with_wrong_token = form.clone();
+    form_with_wrong_token.push(("token", "guessed-token"));
+
+    // Act
+    let form_response = app.get_response_from_url("/setup").await;
+    let form_response_with_wrong_token = app
+        .get_response_from_url("/setup?token=guessed-token")
+        .await;
+    let setup_response = post_setup(&app, &form).await;
+    let setup_response_with_wrong_token = post_setup(&app, &form_with_wrong_token).await;
+
+    // Assert
+    assert_eq!(form_response.status().as_u16(), 403);
+    assert_eq!(form_response_with_wrong_token.status().as_u16(), 403);
+    assert_eq!(setup_response.status().as_u16(), 403);
+    assert_eq!(setup_response_with_wrong_token.status().as_u16(), 403);
+    assert_eq!(app.num_rows_of_table("users").await, 0);
+    assert_eq!(app.num_rows_of_table("instance_settings").await, 0);
+}
@@ -0,0 +1,59 @@
+//! tests/api/shutdown.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app_with};
+use crate::newsletter::when_sending_an_email;
+use std::time::Duration;
+use wiremock::ResponseTemplate;
+
+#[tokio::test]
+async fn graceful_shutdown_drains_in_flight_requests() {
+    // Arrange
+    // generous timeout, the in-flight request may be slow under load of parallel tests
+    let app = spawn_app_with(|c| c.application.shutdown_timeout_seconds = 120).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(1)))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com".to_string();
+    let in_flight = tokio::spawn({
+        let client = app.api_client.clone();
+        let address = app.address.clone();
+        async move {
+            client
+                .post(format!("{}/subscriptions", address))
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .body(body)
+                .send()
+                .await
+        }
+    });
+    // wait until the handler sends the confirmation email, i.e. the request is in flight
+    while app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .is_empty()
+    {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    // Act
+    app.shutdown_handle.stop(true).await;
+
+    // Assert
+    let response = in_flight
+        .await
+        .unwrap()
+        .expect("In-flight request was dropped.");
+    assert_is_redirect_to(&response, "/subscriptions/token");
+    assert_eq!(app.num_rows_of_table("subscriptions").await, 1);
+    // stopped server accepts no new connections
+    let new_request = app
+        .api_client
+        .get(format!("{}/health_check", app.address))
+        .send()
+        .await;
+    assert!(new_request.is_err());
+}
@@ -0,0 +1,177 @@
+//! tests/api/snippets.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use crate::newsletter::{
+    create_confirmed_subscriber, valid_newsletter_form_data, when_sending_an_email,
+};
+use wiremock::ResponseTemplate;
+use zero2prod::routes::{NewsletterFormData, SnippetFormData};
+
+async fn post_snippet(app: &TestApp, name: &str, text: &str, html: &str) -> reqwest::Response {
+    app.api_client
+        .post(format!("{}/admin/snippets", &app.address))
+        .form(&SnippetFormData {
+            name: name.to_string(),
+            text_content: text.to_string(),
+            html_content: html.to_string(),
+        })
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn get_snippets_html(app: &TestApp) -> String {
+    app.get_response_from_url("/admin/snippets")
+        .await
+        .text()
+        .await
+        .unwrap()
+}
+
+fn newsletter_with_signature() -> NewsletterFormData {
+    NewsletterFormData {
+        html_content: "<p>Newsletter body</p>{{snippet:signature}}".to_string(),
+        text_content: "Newsletter body\n{{snippet:signature}}".to_string(),
+        ..valid_newsletter_form_data()
+    }
+}
+
+/// Html and text body of all emails sent so far.
+async fn get_email_bodies(app: &TestApp) -> Vec<(String, String)> {
+    app.email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| {
+            let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            (
+                body["HtmlBody"].as_str().unwrap().to_string(),
+                body["TextBody"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_manage_snippets() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = post_snippet(&app, "signature", "Jane", "<p>Jane</p>").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    assert_eq!(app.num_rows_of_table("snippets").await, 0);
+}
+
+#[tokio::test]
+async fn saving_a_snippet_again_creates_a_new_version() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = post_snippet(&app, "signature", "Jane", "<p>Jane</p>").await;
+    assert_is_redirect_to(&response, "/admin/snippets");
+    let response = post_snippet(&app, "signature", "Jane Doe", "<p>Jane Doe</p>").await;
+    assert_is_redirect_to(&response, "/admin/snippets");
+
+    // Assert
+    let html = get_snippets_html(&app).await;
+    assert!(html.contains("Version 2 of snippet `signature` has been saved."));
+    assert!(html.contains("<b>signature</b> version 2"));
+    assert!(html.contains("&lt;p&gt;Jane Doe&lt;/p&gt;"));
+    assert_eq!(app.num_rows_of_table("snippet_versions").await, 2);
+}
+
+#[tokio::test]
+async fn snippets_with_invalid_name_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = post_snippet(&app, "my signature", "Jane", "<p>Jane</p>").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/snippets");
+    let html = get_snippets_html(&app).await;
+    assert!(html.contains("The name of a snippet must consist of"));
+    assert_eq!(app.num_rows_of_table("snippets").await, 0);
+}
+
+#[tokio::test]
+async fn issues_with_unknown_snippets_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.post_newsletters(&newsletter_with_signature()).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let html = app.get_publish_newsletter_html().await;
+    assert!(html.contains("The snippet `signature` does not exist."));
+    assert_eq!(app.num_rows_of_table("newsletter_issues").await, 0);
+}
+
+#[tokio::test]
+async fn snippets_are_resolved_with_the_version_pinned_at_first_delivery() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    post_snippet(&app, "signature", "Cheers, Jane", "<p>Cheers, Jane</p>").await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Publish and deliver to first subscriber
+    let response = app.post_newsletters(&newsletter_with_signature()).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.execute_task().await;
+
+    // Act - Part 2 - Change snippet and deliver to second subscriber
+    post_snippet(&app, "signature", "Bye, Jane", "<p>Bye, Jane</p>").await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let bodies = get_email_bodies(&app).await;
+    // first two requests confirmed the subscribers
+    assert_eq!(bodies.len(), 4);
+    for (html_body, text_body) in bodies[2..].iter() {
+        assert!(html_body.contains("<p>Newsletter body</p><p>Cheers, Jane</p>"));
+        assert!(text_body.contains("Newsletter body\nCheers, Jane"));
+        assert!(!html_body.contains("{{snippet:"));
+    }
+    let html = get_snippets_html(&app).await;
+    assert!(html.contains("<b>signature</b> version 2"));
+    assert!(html.contains("used by 1 issue(s)"));
+}
+
+#[tokio::test]
+async fn previews_use_the_latest_version_of_snippets() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    post_snippet(&app, "signature", "Cheers, Jane", "<p>Cheers, Jane</p>").await;
+    post_snippet(&app, "signature", "Bye, Jane", "<p>Bye, Jane</p>").await;
+
+    // Act
+    let response = app
+        .post_newsletter_preview(&newsletter_with_signature())
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let html = response.text().await.unwrap();
+    assert!(html.contains("&lt;p&gt;Bye, Jane&lt;/p&gt;"));
+    assert!(html.contains("Newsletter body\nBye, Jane"));
+    assert_eq!(app.num_rows_of_table("newsletter_issue_snippets").await, 0);
+}
@@ -0,0 +1,83 @@
+//! tests/api/subscriber_data.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+use crate::newsletter::{
+    create_unconfirmed_subscriber, valid_newsletter_form_data, when_sending_an_email,
+};
+use wiremock::ResponseTemplate;
+use zero2prod::routes::SubscriberData;
+
+#[tokio::test]
+async fn subscriber_data_contains_profile_history_and_deliveries() {
+    // Arrange
+    let app = spawn_app().await;
+    let (email, name, links) = create_unconfirmed_subscriber(&app).await;
+    let mut data_link = links.html.confirmation.unwrap();
+    reqwest::get(data_link.clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    data_link.set_path("/subscriptions/data");
+    app.test_user.login(&app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.post_newsletters(&valid_newsletter_form_data()).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Act
+    let response = reqwest::get(data_link).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["Cache-Control"], "no-store");
+    let data: SubscriberData = response.json().await.unwrap();
+    assert_eq!(data.profile.email, email.as_ref());
+    assert_eq!(data.profile.name, name.as_ref());
+    assert_eq!(data.profile.status, "confirmed");
+    let history: Vec<_> = data
+        .subscription_history
+        .iter()
+        .map(|e| e.kind.as_str())
+        .collect();
+    assert_eq!(history, vec!["subscribed", "confirmed"]);
+    assert_eq!(data.deliveries.len(), 1);
+    assert_eq!(data.deliveries[0].kind, "received_issue");
+    assert_eq!(
+        data.deliveries[0].issue_title.as_deref(),
+        Some("Newsletter title")
+    );
+    assert!(data.pending_deliveries.is_empty());
+    assert!(data.suppression.is_none());
+}
+
+#[tokio::test]
+async fn subscriber_data_requires_valid_token() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act - Part 1 - missing token
+    let response = reqwest::get(format!("{}/subscriptions/data", app.address))
+        .await
+        .unwrap();
+
+    // Assert - Part 1
+    assert_eq!(response.status().as_u16(), 400);
+
+    // Act - Part 2 - unknown token
+    let response = app
+        .api_client
+        .get(format!(
+            "{}/subscriptions/data?subscription_token={}",
+            app.address,
+            "1".repeat(25)
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert - Part 2
+    assert_is_redirect_to(&response, "/subscriptions/token");
+}
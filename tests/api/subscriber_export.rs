@@ -0,0 +1,76 @@
+//! tests/api/subscriber_export.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+use crate::newsletter::{create_confirmed_subscriber, create_unconfirmed_subscriber};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_export_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .get_response_from_url("/admin/subscribers/export.csv")
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn export_without_subscribers_contains_only_the_header() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .get_response_from_url("/admin/subscribers/export.csv")
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.text().await.unwrap(),
+        "email,name,status,subscribed_at,list\n"
+    );
+}
+
+#[tokio::test]
+async fn export_contains_all_subscribers_as_csv_attachment() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let (confirmed_email, confirmed_name) = create_confirmed_subscriber(&app).await;
+    let (pending_email, pending_name, _) = create_unconfirmed_subscriber(&app).await;
+
+    // Act
+    let response = app
+        .get_response_from_url("/admin/subscribers/export.csv")
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["Content-Type"],
+        "text/csv; charset=utf-8"
+    );
+    let content_disposition = response.headers()["Content-Disposition"].to_str().unwrap();
+    assert!(content_disposition.starts_with("attachment; filename=\"subscribers-"));
+    assert!(content_disposition.ends_with(".csv\""));
+    let csv = response.text().await.unwrap();
+    let mut reader = csv::Reader::from_reader(csv.as_bytes());
+    let rows: Vec<Vec<String>> = reader
+        .records()
+        .map(|r| r.unwrap().iter().map(str::to_string).collect())
+        .collect();
+    assert_eq!(rows.len(), 2);
+    // rows are ordered by time of subscription
+    assert_eq!(rows[0][0], confirmed_email.as_ref());
+    assert_eq!(rows[0][1], confirmed_name.as_ref());
+    assert_eq!(rows[0][2], "confirmed");
+    assert_eq!(rows[0][4], "Newsletter");
+    assert_eq!(rows[1][0], pending_email.as_ref());
+    assert_eq!(rows[1][1], pending_name.as_ref());
+    assert_eq!(rows[1][2], "pending_confirmation");
+}
@@ -0,0 +1,149 @@
+//! tests/api/subscriber_import.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use crate::newsletter::when_sending_an_email;
+use reqwest::multipart::{Form, Part};
+use wiremock::ResponseTemplate;
+
+async fn post_import(app: &TestApp, csv: &str, status: &str) -> reqwest::Response {
+    let file = Part::text(csv.to_string())
+        .file_name("subscribers.csv")
+        .mime_str("text/csv")
+        .unwrap();
+    let form = Form::new()
+        .part("file", file)
+        .text("status", status.to_string());
+    app.api_client
+        .post(format!("{}/admin/subscribers/import", &app.address))
+        .multipart(form)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_import_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = post_import(&app, "email,name\nursula@example.com,le guin", "confirmed").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    assert_eq!(app.num_rows_of_table("subscriptions").await, 0);
+}
+
+#[tokio::test]
+async fn valid_rows_are_imported_as_confirmed_and_invalid_rows_are_reported() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    let csv = "\
+email,name,locale
+ursula@example.com,le guin,
+octavia@example.com,Octavia Butler,de
+not-an-email,Invalid Email,
+ursula@example.com,le guin again,
+";
+
+    // Act
+    let response = post_import(&app, csv, "confirmed").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let html = response.text().await.unwrap();
+    assert!(html.contains("Imported 2 of 4 rows."));
+    assert!(html.contains("Line 4 &lt;not-an-email&gt;"));
+    assert!(html.contains("Line 5 &lt;ursula@example.com&gt;: Email is already subscribed"));
+    let subscribers = sqlx::query!(
+        r#"SELECT email, status::text AS "status!", locale FROM subscriptions ORDER BY email"#
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(subscribers.len(), 2);
+    assert!(subscribers.iter().all(|s| s.status == "confirmed"));
+    assert_eq!(subscribers[0].email, "octavia@example.com");
+    assert_eq!(subscribers[0].locale.as_deref(), Some("de"));
+    assert_eq!(app.num_rows_of_table("subscription_tokens").await, 2);
+}
+
+#[tokio::test]
+async fn subscribers_imported_for_reconfirmation_receive_a_confirmation_email() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Import
+    let response = post_import(
+        &app,
+        "name,email\nle guin,ursula@example.com\n",
+        "pending_reconfirmation",
+    )
+    .await;
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("Imported 1 of 1 rows."));
+    let status = sqlx::query!(r#"SELECT status::text AS "status!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .status;
+    assert_eq!(status, "pending_confirmation");
+
+    // Act - Part 2 - Confirm
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let links = app.get_email_links(email_request);
+    reqwest::get(links.html.confirmation.unwrap())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let status = sqlx::query!(r#"SELECT status::text AS "status!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .status;
+    assert_eq!(status, "confirmed");
+}
+
+#[tokio::test]
+async fn csv_without_required_columns_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = post_import(
+        &app,
+        "mail,full_name\nursula@example.com,le guin",
+        "confirmed",
+    )
+    .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/subscribers/import");
+    let html = app
+        .get_response_from_url("/admin/subscribers/import")
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(html.contains("The CSV file must have a header row with columns `email` and `name`."));
+    assert_eq!(app.num_rows_of_table("subscriptions").await, 0);
+}
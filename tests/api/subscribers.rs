@@ -0,0 +1,93 @@
+//! tests/api/subscribers.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+use crate::newsletter::{valid_newsletter_form_data, when_sending_an_email};
+use wiremock::ResponseTemplate;
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_subscribers() {
+    // Arrange
+    let test_app = spawn_app().await;
+
+    // Act
+    let response = test_app.get_response_from_url("/admin/subscribers").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn unknown_subscriber_is_not_found() {
+    // Arrange
+    let test_app = spawn_app().await;
+    test_app.test_user.login(&test_app).await;
+
+    // Act
+    let response = test_app
+        .get_response_from_url(&format!("/admin/subscribers/{}", uuid::Uuid::new_v4()))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn subscriber_timeline_is_kept_after_unsubscribe() {
+    // Arrange
+    let test_app = spawn_app().await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&test_app.email_server)
+        .await;
+    let unsubscribe_link = test_app.subscribe_and_confirm_a_user().await;
+    let subscriber_id = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&test_app.db_pool)
+        .await
+        .unwrap()
+        .id;
+    test_app.test_user.login(&test_app).await;
+    test_app
+        .post_newsletters(&valid_newsletter_form_data())
+        .await;
+    test_app.dispatch_all_pending_emails().await;
+    let details_page = format!("/admin/subscribers/{}", subscriber_id);
+
+    // Act - Part 1 - Subscriber is listed with timeline
+    let html_page = test_app
+        .get_response_from_url("/admin/subscribers")
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains(&details_page));
+    let html_page = test_app
+        .get_response_from_url(&details_page)
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    // Assert - Part 1
+    assert!(html_page.contains("ursula_le_guin@gmail.com"));
+    assert!(html_page.contains(": subscribed</li>"));
+    assert!(html_page.contains(": confirmed subscription</li>"));
+    assert!(html_page.contains("received issue &quot;Newsletter title&quot;"));
+
+    // Act - Part 2 - Unsubscribe
+    test_app
+        .click_email_link(unsubscribe_link)
+        .await
+        .error_for_status()
+        .unwrap();
+    let html_page = test_app
+        .get_response_from_url(&details_page)
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    // Assert - Part 2
+    assert!(html_page.contains("is no longer subscribed."));
+    assert!(html_page.contains("received issue &quot;Newsletter title&quot;"));
+    assert!(html_page.contains(": unsubscribed</li>"));
+}
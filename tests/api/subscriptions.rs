@@ -142,7 +142,7 @@ async fn subscribe_with_valid_form_data() {
     // Act - Part 3 - Get the first intercepted email request
     // Assert
     let email_request = &test_app.email_server.received_requests().await.unwrap()[0];
-    let email_links = test_app.get_email_links(&email_request);
+    let email_links = test_app.get_email_links(email_request);
     // The two links should be identical
     assert_eq!(
         email_links.html.confirmation.unwrap(),
@@ -187,6 +187,52 @@ async fn subscribing_twice_sends_two_confirmation_emails_with_same_email_links_a
     // Mock asserts on drop, that exactly two confirmation emails are send
 }
 
+#[tokio::test]
+async fn double_submitted_subscription_form_sends_one_confirmation_email() {
+    // Arrange
+    let test_app = spawn_app().await;
+    let html_page = test_app.get_subscriptions_html().await;
+    let idempotency_key = html_page
+        .split(r#"name="idempotency_key" value=""#)
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .expect("Subscription form has no idempotency key.");
+    let body = format!(
+        "name=le%20guin&email=ursula_le_guin%40gmail.com&idempotency_key={}",
+        idempotency_key
+    );
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    // Act
+    let response_first = test_app.post_subscriptions(body.clone()).await;
+    let response_second = test_app.post_subscriptions(body).await;
+
+    // Assert
+    assert_is_redirect_to(&response_first, "/subscriptions/token");
+    assert_is_redirect_to(&response_second, "/subscriptions/token");
+
+    // Mock asserts on drop, that exactly one confirmation email is send
+}
+
+#[tokio::test]
+async fn subscribe_with_invalid_idempotency_key_returns_a_400() {
+    // Arrange
+    let test_app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com&idempotency_key=twice";
+
+    // Act
+    let response = test_app.post_subscriptions(body.into()).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+}
+
 #[tokio::test]
 async fn subscribe_fails_if_there_is_a_fatal_database_error() {
     // Arrange
@@ -88,7 +88,7 @@ async fn the_confirmation_link_returns_a_confirmation_message_if_called_first()
     test_app.post_subscriptions(body.into()).await;
     let email_request = &test_app.email_server.received_requests().await.unwrap()[0];
     let confirmation_link = test_app
-        .get_email_links(&email_request)
+        .get_email_links(email_request)
         .html
         .confirmation
         .unwrap();
@@ -126,7 +126,7 @@ async fn clicking_on_the_confirmation_link_persists_a_subscriber() {
     test_app.post_subscriptions(body.into()).await;
     let email_request = &test_app.email_server.received_requests().await.unwrap()[0];
     let confirmation_link = test_app
-        .get_email_links(&email_request)
+        .get_email_links(email_request)
         .html
         .confirmation
         .unwrap();
@@ -166,7 +166,7 @@ async fn the_confirmation_link_returns_a_welcome_back_message_if_called_twice_or
     test_app.post_subscriptions(body.into()).await;
     let email_request = &test_app.email_server.received_requests().await.unwrap()[0];
     let confirmation_link = test_app
-        .get_email_links(&email_request)
+        .get_email_links(email_request)
         .html
         .confirmation
         .unwrap();
@@ -208,7 +208,7 @@ async fn subscribing_an_already_confirmed_email_redirects_directly_to_confirm_pa
     test_app.post_subscriptions(body.into()).await;
     let email_request = &test_app.email_server.received_requests().await.unwrap()[0];
     let confirmation_link = test_app
-        .get_email_links(&email_request)
+        .get_email_links(email_request)
         .html
         .confirmation
         .unwrap();
@@ -0,0 +1,188 @@
+//! tests/api/subscriptions_resend.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::routes::SubscriptionsStatus;
+
+async fn expire_subscription_tokens(test_app: &TestApp) {
+    sqlx::query!("UPDATE subscription_tokens SET expires_at = now() - interval '1 minute'")
+        .execute(&test_app.db_pool)
+        .await
+        .unwrap();
+}
+
+async fn post_resend(test_app: &TestApp, body: &str) -> reqwest::Response {
+    test_app
+        .api_client
+        .post(format!("{}/subscriptions/resend", test_app.address))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(body.to_owned())
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn expired_confirmation_link_is_rejected_with_a_dedicated_message() {
+    // Arrange
+    let test_app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&test_app.email_server)
+        .await;
+    test_app
+        .post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+    let email_request = &test_app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_link = test_app
+        .get_email_links(email_request)
+        .html
+        .confirmation
+        .unwrap();
+    expire_subscription_tokens(&test_app).await;
+
+    // Act - Part 1 - click expired link
+    let response = test_app.click_email_link(confirmation_link).await;
+
+    // Assert - Part 1
+    assert_is_redirect_to(&response, "/subscriptions/token");
+    let saved = sqlx::query!("SELECT status AS \"status: SubscriptionsStatus\" FROM subscriptions")
+        .fetch_one(&test_app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, SubscriptionsStatus::PendingConfirmation);
+
+    // Act - Part 2 - Follow the redirect
+    let html_page = test_app.get_subscriptions_token_html().await;
+
+    // Assert - Part 2
+    assert!(html_page.contains("has expired. Please request a new confirmation email."));
+    assert!(html_page.contains("action=\"/subscriptions/resend\""));
+}
+
+#[tokio::test]
+async fn expiry_does_not_apply_to_confirmed_subscribers() {
+    // Arrange
+    let test_app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&test_app.email_server)
+        .await;
+    test_app
+        .post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+    let email_request = &test_app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_link = test_app
+        .get_email_links(email_request)
+        .html
+        .confirmation
+        .unwrap();
+    test_app
+        .click_email_link(confirmation_link.clone())
+        .await
+        .error_for_status()
+        .unwrap();
+    expire_subscription_tokens(&test_app).await;
+
+    // Act
+    let response = test_app.click_email_link(confirmation_link).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn resend_issues_a_fresh_token_which_confirms_the_subscription() {
+    // Arrange
+    let test_app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&test_app.email_server)
+        .await;
+    test_app
+        .post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+    expire_subscription_tokens(&test_app).await;
+
+    // Act - Part 1 - request a new confirmation email
+    let response = post_resend(&test_app, "email=ursula_le_guin%40gmail.com").await;
+
+    // Assert - Part 1
+    assert_is_redirect_to(&response, "/subscriptions/token");
+    let email_requests = test_app.email_server.received_requests().await.unwrap();
+    let old_link = test_app
+        .get_email_links(&email_requests[0])
+        .html
+        .confirmation
+        .unwrap();
+    let new_link = test_app
+        .get_email_links(&email_requests[1])
+        .html
+        .confirmation
+        .unwrap();
+    assert_ne!(old_link, new_link);
+    assert_eq!(test_app.num_rows_of_table("subscription_tokens").await, 1);
+
+    // Act - Part 2 - old link is unknown, new link confirms
+    let old_response = test_app.click_email_link(old_link).await;
+    let new_response = test_app.click_email_link(new_link).await;
+
+    // Assert - Part 2
+    assert_is_redirect_to(&old_response, "/subscriptions/token");
+    assert_eq!(new_response.status().as_u16(), 200);
+    let saved = sqlx::query!("SELECT status AS \"status: SubscriptionsStatus\" FROM subscriptions")
+        .fetch_one(&test_app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, SubscriptionsStatus::Confirmed);
+
+    // Mock asserts on drop, that exactly two confirmation emails are send
+}
+
+#[tokio::test]
+async fn resend_does_not_reveal_unknown_or_confirmed_emails() {
+    // Arrange
+    let test_app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+    test_app
+        .post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+    let email_request = &test_app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_link = test_app
+        .get_email_links(email_request)
+        .html
+        .confirmation
+        .unwrap();
+    test_app
+        .click_email_link(confirmation_link)
+        .await
+        .error_for_status()
+        .unwrap();
+
+    for body in [
+        "email=ursula_le_guin%40gmail.com",
+        "email=unknown%40example.com",
+    ] {
+        // Act
+        let response = post_resend(&test_app, body).await;
+
+        // Assert
+        assert_is_redirect_to(&response, "/subscriptions/token");
+        let html_page = test_app.get_subscriptions_token_html().await;
+        assert!(html_page.contains(
+            "If your subscription is waiting for confirmation, a new confirmation email is on its way."
+        ));
+    }
+
+    // Mock asserts on drop, that no further confirmation email is send
+}
@@ -0,0 +1,129 @@
+//! tests/api/suppressions.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use crate::newsletter::{
+    create_confirmed_subscriber, valid_newsletter_form_data, when_sending_an_email,
+};
+use wiremock::ResponseTemplate;
+use zero2prod::routes::SuppressionFormData;
+
+async fn post_suppression(app: &TestApp, path: &str, email: &str) -> reqwest::Response {
+    app.api_client
+        .post(format!("{}{}", &app.address, path))
+        .form(&SuppressionFormData {
+            email: email.to_string(),
+        })
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_suppression_list() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_response_from_url("/admin/suppressions").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn suppressed_emails_can_not_subscribe() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    post_suppression(&app, "/admin/suppressions", "ursula_le_guin@gmail.com").await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    let response = app.post_subscriptions(body.into()).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/subscriptions");
+    assert_eq!(app.num_rows_of_table("subscriptions").await, 0);
+    let html_page = app.get_subscriptions_html().await;
+    assert!(html_page.contains("does not receive emails anymore"));
+}
+
+#[tokio::test]
+async fn newsletters_are_not_delivered_to_suppressed_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+    let (email, _) = create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    post_suppression(&app, "/admin/suppressions", email.as_ref()).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_newsletters(&valid_newsletter_form_data()).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(app.num_rows_of_table("issue_delivery_queue").await, 0);
+    let issue = sqlx::query!("SELECT num_current_subscribers FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(issue.num_current_subscribers, Some(0));
+}
+
+#[tokio::test]
+async fn admin_adds_and_removes_suppressed_emails() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act - Part 1 - add email
+    let response = post_suppression(&app, "/admin/suppressions", "ursula@example.com").await;
+
+    // Assert - Part 1
+    assert_is_redirect_to(&response, "/admin/suppressions");
+    let html_page = app.get_response_from_url("/admin/suppressions").await;
+    let html_page = html_page.text().await.unwrap();
+    assert!(html_page.contains("`ursula@example.com` has been added to the suppression list."));
+    assert!(html_page.contains("ursula@example.com: manual since"));
+
+    // Act - Part 2 - add email again
+    post_suppression(&app, "/admin/suppressions", "ursula@example.com").await;
+
+    // Assert - Part 2
+    let html_page = app.get_response_from_url("/admin/suppressions").await;
+    let html_page = html_page.text().await.unwrap();
+    assert!(html_page.contains("`ursula@example.com` is on the suppression list already."));
+    assert_eq!(app.num_rows_of_table("suppressions").await, 1);
+
+    // Act - Part 3 - remove email
+    let response = post_suppression(&app, "/admin/suppressions/delete", "ursula@example.com").await;
+
+    // Assert - Part 3
+    assert_is_redirect_to(&response, "/admin/suppressions");
+    assert_eq!(app.num_rows_of_table("suppressions").await, 0);
+}
+
+#[tokio::test]
+async fn invalid_emails_are_not_added_to_the_suppression_list() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    post_suppression(&app, "/admin/suppressions", "definitely-not-an-email").await;
+
+    // Assert
+    let html_page = app.get_response_from_url("/admin/suppressions").await;
+    let html_page = html_page.text().await.unwrap();
+    assert!(html_page.contains("`definitely-not-an-email` is not a valid subscriber email."));
+    assert_eq!(app.num_rows_of_table("suppressions").await, 0);
+}
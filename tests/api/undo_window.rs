@@ -0,0 +1,111 @@
+//! tests/api/undo_window.rs
+
+use crate::helpers::{assert_is_redirect_to, spawn_app_with, TestApp};
+use crate::newsletter::{
+    create_confirmed_subscriber, valid_newsletter_form_data, when_sending_an_email,
+};
+use wiremock::ResponseTemplate;
+use zero2prod::issue_delivery_worker::ExecutionOutcome;
+
+async fn spawn_app_with_undo_window() -> TestApp {
+    let app = spawn_app_with(|c| c.application.undo_window_minutes = 10).await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    app
+}
+
+/// Let the undo window of all issues pass.
+async fn pass_undo_window(app: &TestApp) {
+    sqlx::query!("UPDATE newsletter_issues SET cancellable_until = now() - interval '1 second'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    sqlx::query!("UPDATE issue_delivery_queue SET execute_after = now() - interval '1 second'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn published_issue_is_pending_and_can_be_cancelled_within_undo_window() {
+    // Arrange
+    let app = spawn_app_with_undo_window().await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - publish
+    let response = app.post_newsletters(&valid_newsletter_form_data()).await;
+
+    // Assert - Part 1
+    let issue_id = app.get_newsletter_issue_id().await;
+    assert_is_redirect_to(
+        &response,
+        &format!("/admin/delivery_overview?newsletter_issue_id={}", issue_id),
+    );
+    let html_page = app
+        .get_response_from_url(&format!(
+            "/admin/delivery_overview?newsletter_issue_id={}",
+            issue_id
+        ))
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains(
+        "<p><i>The newsletter issue has been accepted - emails will go out in 10 minutes, \
+        unless you cancel it.</i></p>"
+    ));
+    assert!(html_page.contains("Delivery status: pending until"));
+    assert!(html_page.contains("Undo publishing"));
+    // the worker does not start delivery within the undo window
+    assert!(matches!(
+        app.execute_task().await,
+        ExecutionOutcome::PostponedTasks
+    ));
+
+    // Act - Part 2 - cancel
+    let response = app.post_cancel_newsletter(issue_id).await;
+
+    // Assert - Part 2
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains(
+        "<p><i>The newsletter issue has been cancelled. No emails have been sent.</i></p>"
+    ));
+    assert_eq!(app.num_rows_of_table("newsletter_issues").await, 0);
+    assert_eq!(app.num_rows_of_table("issue_delivery_queue").await, 0);
+}
+
+#[tokio::test]
+async fn issue_cannot_be_cancelled_after_undo_window() {
+    // Arrange
+    let app = spawn_app_with_undo_window().await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.post_newsletters(&valid_newsletter_form_data()).await;
+    let issue_id = app.get_newsletter_issue_id().await;
+    pass_undo_window(&app).await;
+
+    // Act
+    let response = app.post_cancel_newsletter(issue_id).await;
+
+    // Assert
+    assert_is_redirect_to(
+        &response,
+        &format!("/admin/delivery_overview?newsletter_issue_id={}", issue_id),
+    );
+    let html_page = app.get_delivery_overview_html().await;
+    assert!(html_page.contains(
+        "<p><i>The undo window of the newsletter issue has passed. \
+        Cancel its delivery instead.</i></p>"
+    ));
+    assert_eq!(app.num_rows_of_table("newsletter_issues").await, 1);
+    // delivery starts after the undo window
+    app.dispatch_all_pending_emails().await;
+}
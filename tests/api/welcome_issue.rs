@@ -0,0 +1,228 @@
+//! tests/api/welcome_issue.rs
+
+use crate::helpers::{spawn_app, spawn_app_with, TestApp};
+use crate::newsletter::{
+    create_confirmed_subscriber, create_unconfirmed_subscriber, valid_newsletter_form_data,
+    when_sending_an_email,
+};
+use uuid::Uuid;
+use wiremock::ResponseTemplate;
+use zero2prod::welcome_issue::WelcomeIssue;
+
+/// Number of sent emails with the given subject.
+async fn num_sent_emails(app: &TestApp, subject: &str) -> usize {
+    app.email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|request| {
+            let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            body["Subject"] == subject
+        })
+        .count()
+}
+
+/// Insert an issue into the archive, whose delivery has started a year ago.
+async fn insert_archived_issue(app: &TestApp, issue_id: Uuid, list_id: Uuid) {
+    sqlx::query!(
+        r#"
+        INSERT INTO newsletter_issues (
+            newsletter_issue_id, title, text_content, html_content, published_at,
+            num_current_subscribers, num_delivered_newsletters, num_failed_deliveries, list_id
+        )
+        VALUES (
+            $1, 'Best of', 'Best of as plain text', '<p>Best of as HTML</p>',
+            now() - interval '1 year', 0, 0, 0, $2
+        )
+        "#,
+        issue_id,
+        list_id,
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn newly_confirmed_subscribers_receive_the_latest_issue() {
+    // Arrange
+    let app = spawn_app_with(|c| c.application.welcome_issue = WelcomeIssue::Latest).await;
+    create_confirmed_subscriber(&app).await;
+    let (_, _, links) = create_unconfirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.post_newsletters(&valid_newsletter_form_data()).await;
+    app.dispatch_all_pending_emails().await;
+    assert_eq!(num_sent_emails(&app, "Newsletter title").await, 1);
+
+    // Act
+    reqwest::get(links.html.confirmation.unwrap())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(num_sent_emails(&app, "Newsletter title").await, 2);
+    let issue = sqlx::query!(
+        "SELECT num_current_subscribers, num_delivered_newsletters FROM newsletter_issues"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(issue.num_current_subscribers, Some(2));
+    assert_eq!(issue.num_delivered_newsletters, Some(2));
+}
+
+#[tokio::test]
+async fn newly_confirmed_subscribers_receive_the_configured_issue() {
+    // Arrange
+    let best_of_id = Uuid::new_v4();
+    let app =
+        spawn_app_with(|c| c.application.welcome_issue = WelcomeIssue::Issue(best_of_id)).await;
+    sqlx::query!(
+        r#"
+        INSERT INTO newsletter_issues (
+            newsletter_issue_id, title, text_content, html_content, published_at,
+            num_current_subscribers, num_delivered_newsletters, num_failed_deliveries
+        )
+        VALUES (
+            $1, 'Best of', 'Best of as plain text', '<p>Best of as HTML</p>',
+            now() - interval '1 year', 0, 0, 0
+        )
+        "#,
+        best_of_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    create_confirmed_subscriber(&app).await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(num_sent_emails(&app, "Best of").await, 1);
+}
+
+#[tokio::test]
+async fn unknown_configured_issue_does_not_fail_confirmation() {
+    // Arrange
+    let app =
+        spawn_app_with(|c| c.application.welcome_issue = WelcomeIssue::Issue(Uuid::new_v4())).await;
+
+    // Act
+    create_confirmed_subscriber(&app).await;
+
+    // Assert
+    assert_eq!(app.num_rows_of_table("issue_delivery_queue").await, 0);
+}
+
+#[tokio::test]
+async fn no_issue_is_sent_to_new_subscribers_by_default() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.post_newsletters(&valid_newsletter_form_data()).await;
+
+    // Act
+    create_confirmed_subscriber(&app).await;
+
+    // Assert
+    assert_eq!(app.num_rows_of_table("issue_delivery_queue").await, 0);
+}
+
+#[tokio::test]
+async fn configured_issue_of_another_list_is_not_sent() {
+    // Arrange
+    let best_of_id = Uuid::new_v4();
+    let app =
+        spawn_app_with(|c| c.application.welcome_issue = WelcomeIssue::Issue(best_of_id)).await;
+    let list_id = Uuid::new_v4();
+    sqlx::query!(
+        "INSERT INTO lists (list_id, name, created_at) VALUES ($1, 'Other list', now())",
+        list_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    insert_archived_issue(&app, best_of_id, list_id).await;
+
+    // Act
+    create_confirmed_subscriber(&app).await;
+
+    // Assert
+    assert_eq!(app.num_rows_of_table("issue_delivery_queue").await, 0);
+}
+
+#[tokio::test]
+async fn configured_issue_with_cancelled_delivery_is_not_sent() {
+    // Arrange
+    let best_of_id = Uuid::new_v4();
+    let app =
+        spawn_app_with(|c| c.application.welcome_issue = WelcomeIssue::Issue(best_of_id)).await;
+    insert_archived_issue(&app, best_of_id, Uuid::nil()).await;
+    create_confirmed_subscriber(&app).await;
+    sqlx::query!("UPDATE issue_delivery_queue SET status = 'cancelled'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    create_confirmed_subscriber(&app).await;
+
+    // Assert
+    assert_eq!(app.num_rows_of_table("issue_delivery_queue").await, 1);
+}
+
+#[tokio::test]
+async fn welcome_issue_is_not_sent_to_subscribers_at_frequency_cap() {
+    // Arrange
+    let best_of_id = Uuid::new_v4();
+    let app = spawn_app_with(|c| {
+        c.application.welcome_issue = WelcomeIssue::Issue(best_of_id);
+        c.application.max_emails_per_subscriber_per_week = Some(1);
+    })
+    .await;
+    insert_archived_issue(&app, best_of_id, Uuid::nil()).await;
+    let (email, _, links) = create_unconfirmed_subscriber(&app).await;
+    let subscriber_id = sqlx::query!(
+        "SELECT id FROM subscriptions WHERE email = $1",
+        email.as_ref()
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .id;
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriber_events (subscriber_id, kind, newsletter_issue_id, occurred_at)
+        VALUES ($1, 'received_issue', $2, now() - interval '1 day')
+        "#,
+        subscriber_id,
+        Uuid::new_v4(),
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    reqwest::get(links.html.confirmation.unwrap())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    assert_eq!(app.num_rows_of_table("issue_delivery_queue").await, 0);
+    assert_eq!(app.num_rows_of_table("frequency_capped_sends").await, 1);
+}